@@ -0,0 +1,197 @@
+//! Commands sent to a running issuer node over its admin console socket.
+
+use clap::{Args, Subcommand, ValueEnum};
+use colored::*;
+use dialoguer::{theme::ColorfulTheme, Confirm};
+use hesha_types::{ControlRequest, ControlResponse, CONTROL_SOCKET_FILE};
+use crate::config::config_dir;
+use crate::{input, output};
+use std::path::{Path, PathBuf};
+
+/// Control a running issuer node through its local admin console.
+#[derive(Debug, Args)]
+pub struct AdminCmd {
+    /// Name of the issuer configuration.
+    /// Defaults to 'default'
+    #[arg(short, long, global = true, default_value = "default")]
+    name: String,
+
+    /// Console socket, instead of issuer.sock in the issuer directory
+    #[arg(long, global = true, value_name = "PATH")]
+    socket: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: AdminCommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum AdminCommands {
+    /// Show the node's version, uptime, signing key, drain and maintenance state
+    Status,
+
+    /// Re-read keys from issuer.toml, e.g. after 'hesha setup rotate'
+    Reload,
+
+    /// Generate a new signing key and switch to it without a restart
+    RotateKey {
+        /// Days to keep publishing the old key.
+        /// Defaults to the configured attestation validity
+        #[arg(long, value_name = "DAYS")]
+        overlap_days: Option<i64>,
+
+        /// Rotate without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Sign with another key the node holds, e.g. the previous one after a rotation
+    ActivateKey {
+        /// Identifier of the key, as listed in pubkey.json
+        key_id: String,
+    },
+
+    /// Report unhealthy, then finish in-flight requests and exit
+    Drain {
+        /// Seconds to keep serving while load balancers stop sending traffic
+        #[arg(long, value_name = "SECS", default_value_t = 0)]
+        grace: u64,
+    },
+
+    /// Refuse attestation requests with 503 while keys and .well-known stay up
+    Maintenance {
+        /// Turn maintenance mode on or off
+        #[arg(value_enum)]
+        switch: Switch,
+
+        /// Note on why, shown by 'hesha admin status'
+        #[arg(long)]
+        reason: Option<String>,
+
+        /// Seconds clients are told to wait before retrying.
+        /// Defaults to 300
+        #[arg(long, value_name = "SECS")]
+        retry_after: Option<u64>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Switch {
+    On,
+    Off,
+}
+
+impl AdminCmd {
+    pub fn execute(self) -> anyhow::Result<()> {
+        let socket = self.socket.unwrap_or_else(|| {
+            config_dir().join("issuer").join(&self.name).join(CONTROL_SOCKET_FILE)
+        });
+        let request = match self.command {
+            AdminCommands::Status => ControlRequest::Status,
+            AdminCommands::Reload => ControlRequest::Reload,
+            AdminCommands::RotateKey { overlap_days, yes } => {
+                if !yes {
+                    anyhow::ensure!(
+                        !output::is_json() && input::is_interactive(),
+                        "Pass --yes to rotate without a confirmation prompt"
+                    );
+                    let confirm = Confirm::with_theme(&ColorfulTheme::default())
+                        .with_prompt(format!("Generate a new signing key for issuer '{}'?", self.name))
+                        .interact()?;
+                    if !confirm {
+                        println!("Rotation cancelled.");
+                        return Ok(());
+                    }
+                }
+                ControlRequest::RotateKey { overlap_days }
+            }
+            AdminCommands::ActivateKey { key_id } => ControlRequest::ActivateKey { key_id },
+            AdminCommands::Drain { grace } => ControlRequest::Drain { grace_secs: grace },
+            AdminCommands::Maintenance { switch, reason, retry_after } => ControlRequest::Maintenance {
+                enabled: matches!(switch, Switch::On),
+                reason,
+                retry_after_secs: retry_after,
+            },
+        };
+
+        let response = send(&socket, &request)?;
+        if let ControlResponse::Error { error, error_description } = &response {
+            anyhow::bail!("{}: {}", error, error_description);
+        }
+        if output::is_json() {
+            return output::json(&response);
+        }
+
+        match response {
+            ControlResponse::Status(status) => {
+                let state = if status.draining {
+                    "draining".yellow().bold()
+                } else if status.maintenance {
+                    "in maintenance".yellow().bold()
+                } else {
+                    "running".green().bold()
+                };
+                println!("Issuer '{}': {} (PID: {})", self.name, state, status.pid);
+                println!("  Version:        {}", status.version);
+                println!("  Started:        {}", status.started_at.to_rfc3339());
+                println!("  Listening on:   {}", status.bind_address);
+                println!("  Signing key:    {}", status.key_id);
+                println!("  Published keys: {}", status.published_keys);
+                println!("  Issued:         {}", status.attestations_issued);
+            }
+            ControlResponse::Keys(keys) => {
+                output::success(&format!(
+                    "Signing with key {} ({} key(s) published)",
+                    keys.key_id, keys.published_keys
+                ));
+            }
+            ControlResponse::Draining { grace_secs } => {
+                output::info(&format!(
+                    "Issuer node is draining; it exits {}s from now once in-flight requests finish",
+                    grace_secs
+                ));
+            }
+            ControlResponse::Maintenance(status) if status.enabled => {
+                output::warning(&format!(
+                    "Maintenance mode is on: attestation requests get 503 with Retry-After: {}",
+                    status.retry_after_secs
+                ));
+            }
+            ControlResponse::Maintenance(_) => {
+                output::success("Maintenance mode is off; attestation requests are accepted");
+            }
+            ControlResponse::Error { .. } => unreachable!("errors are returned above"),
+        }
+        Ok(())
+    }
+}
+
+/// Send one command and read the node's answer.
+#[cfg(unix)]
+fn send(socket: &Path, request: &ControlRequest) -> anyhow::Result<ControlResponse> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    let mut stream = UnixStream::connect(socket).map_err(|e| {
+        anyhow::anyhow!(
+            "Cannot reach the issuer node at {} ({}); is it running?",
+            socket.display(),
+            e
+        )
+    })?;
+    // Rotation writes key files; everything else answers at once
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    let mut line = serde_json::to_vec(request)?;
+    line.push(b'\n');
+    stream.write_all(&line)?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    serde_json::from_str(&response)
+        .map_err(|e| anyhow::anyhow!("Invalid response from the issuer node: {}", e))
+}
+
+#[cfg(not(unix))]
+fn send(_socket: &Path, _request: &ControlRequest) -> anyhow::Result<ControlResponse> {
+    anyhow::bail!("The admin console needs Unix domain sockets, which this platform lacks")
+}
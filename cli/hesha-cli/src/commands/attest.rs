@@ -1,24 +1,113 @@
 //! Attestation request command.
+//!
+//! Plain requests carry an idempotency key and are retried with backoff on
+//! transient network failures. The request is recorded under
+//! `~/.hesha/pending/<id>.json` until it completes, so if every retry fails
+//! it can be resumed later with `--resume-id <id>`; the issuer then returns
+//! the attestation it already issued instead of a duplicate.
 
-use crate::{config, output};
+use crate::{config, input, output};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
 use colored::*;
-use hesha_client::IssuerClient;
-use hesha_types::PhoneNumber;
+use dialoguer::Input;
+use hesha_client::{AttestationResponse, ClientError, IssuerClient};
+use hesha_crypto::generate_hex_nonce;
+use hesha_types::{KeyPair, PhoneNumber, PublicKey};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Number of times the user may enter the verification code.
+const OTP_ATTEMPTS: usize = 3;
+
+/// Delay before the first retry; doubled after every attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest delay between retries.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How an attestation is requested and where the result goes.
+pub struct AttestOptions<'a> {
+    /// Private key file, stored key name or keyring reference.
+    pub key: Option<&'a str>,
+    /// File to save the attestation to.
+    pub out: Option<&'a str>,
+    /// Verify the phone number with an SMS code first.
+    pub otp: bool,
+    /// Retries after a transient network failure.
+    pub retries: u32,
+}
+
+/// An attestation request that hasn't completed yet.
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingAttestation {
+    /// Reference printed to the user; also the idempotency key.
+    id: String,
+    issuer: String,
+    phone: String,
+    scope: String,
+    validity_days: Option<i64>,
+    /// Key argument, unless the key was read from stdin.
+    key: Option<String>,
+    /// Public key the request was made for.
+    user_pubkey: String,
+    created_at: DateTime<Utc>,
+}
+
+impl PendingAttestation {
+    fn path(id: &str) -> PathBuf {
+        config::config_dir().join("pending").join(format!("{}.json", id))
+    }
+    
+    fn load(id: &str) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric()),
+            "Invalid resume reference: {}",
+            id
+        );
+        let data = fs::read_to_string(Self::path(id))
+            .with_context(|| format!("No pending attestation request '{}'", id))?;
+        Ok(serde_json::from_str(&data)?)
+    }
+    
+    fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path(&self.id);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        
+        // The request holds the phone number
+        config::write_private(&path, &serde_json::to_string_pretty(self)?)
+    }
+    
+    fn remove(&self) {
+        let _ = fs::remove_file(Self::path(&self.id));
+    }
+}
 
 /// Execute attestation request.
+/// 
+/// With `otp` set, the issuer verifies the phone number itself: it sends
+/// an SMS code that the user is prompted for before the attestation is
+/// issued.
 pub async fn execute(
     issuer: &str,
     phone: &str,
     scope: &str,
-    key_path: Option<&str>,
-    output: Option<&str>,
     validity_days: Option<i64>,
+    options: AttestOptions<'_>,
 ) -> anyhow::Result<()> {
-    output::info("Requesting attestation...");
+    anyhow::ensure!(
+        !options.otp || input::is_interactive(),
+        "--otp prompts for the SMS code and needs an interactive terminal"
+    );
     
+
     // Load private key
-    let keypair = config::load_keypair(key_path)?;
+    let keypair = config::load_keypair(options.key)?;
     
     // Parse phone number
     let phone_number = PhoneNumber::new(phone)?;
@@ -26,17 +115,128 @@ pub async fn execute(
     // Create client
     let client = IssuerClient::new(issuer)?;
     
-    // Request attestation with specified scope and optional validity
-    let response = client
-        .request_attestation(&phone_number, &keypair.public, scope, validity_days)
-        .await?;
+    let response = if options.otp {
+        request_with_otp(&client, &phone_number, &keypair.public, scope, validity_days).await?
+    } else {
+        let pending = PendingAttestation {
+            id: generate_hex_nonce(),
+            issuer: issuer.to_string(),
+            phone: phone_number.to_string(),
+            scope: scope.to_string(),
+            validity_days,
+            key: options.key.filter(|key| !input::is_stdin(key)).map(str::to_string),
+            user_pubkey: keypair.public.to_base64(),
+            created_at: Utc::now(),
+        };
+        pending.save()?;
+        request_with_retry(&client, &pending, &keypair, options.retries).await?
+    };
     
-    output::success("Attestation received!");
-    println!("Proxy number: {}", response.proxy_number.yellow());
+    print_response(&response, options.out)
+}
+
+/// Resume a request that failed with a transient error.
+/// 
+/// The original idempotency key is reused, so an attestation that was
+/// issued but never received is returned rather than issued again.
+pub async fn resume(id: &str, options: AttestOptions<'_>) -> anyhow::Result<()> {
+    let pending = PendingAttestation::load(id)?;
+    
+    let keypair = config::load_keypair(options.key.or(pending.key.as_deref()))?;
+    anyhow::ensure!(
+        keypair.public.to_base64() == pending.user_pubkey,
+        "The key does not match the one used for request '{}'; pass the original key with --key",
+        id
+    );
+    
+    output::info(&format!("Resuming request {} to {}", pending.id, pending.issuer));
+    let client = IssuerClient::new(&pending.issuer)?;
+    let response = request_with_retry(&client, &pending, &keypair, options.retries).await?;
+    
+    print_response(&response, options.out)
+}
+
+/// Request the attestation, retrying transient failures with exponential
+/// backoff.
+/// 
+/// The pending request is removed once the issuer answers; if it can't be
+/// reached it is kept and the error says how to resume.
+async fn request_with_retry(
+    client: &IssuerClient,
+    pending: &PendingAttestation,
+    keypair: &KeyPair,
+    retries: u32,
+) -> anyhow::Result<AttestationResponse> {
+    let phone_number = PhoneNumber::new(&pending.phone)?;
+    let mut delay = INITIAL_BACKOFF;
+    let mut attempt = 0;
     
+    output::info("Requesting attestation...");
+    loop {
+        let result = client
+            .request_attestation_idempotent(
+                &phone_number,
+                &keypair.public,
+                &pending.scope,
+                pending.validity_days,
+                &pending.id,
+            )
+            .await;
+        
+        match result {
+            Ok(response) => {
+                pending.remove();
+                return Ok(response);
+            }
+            Err(e) if e.is_transient() && attempt < retries => {
+                attempt += 1;
+                output::warning(&format!(
+                    "{}; retrying in {}s ({}/{})",
+                    e,
+                    delay.as_secs(),
+                    attempt,
+                    retries
+                ));
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_BACKOFF);
+            }
+            Err(e) if e.is_transient() => return Err(resumable(e, &pending.id)),
+            Err(e) => {
+                // The issuer rejected the request; retrying won't help
+                pending.remove();
+                return Err(e.into());
+            }
+        }
+    }
+}
+
+/// Attach the resume reference to a transient failure.
+fn resumable(error: ClientError, id: &str) -> anyhow::Error {
+    anyhow::Error::from(error).context(format!(
+        "Attestation request failed; resume it with 'hesha attest --resume-id {}'",
+        id
+    ))
+}
+
+/// Save and print a received attestation.
+fn print_response(response: &AttestationResponse, output: Option<&str>) -> anyhow::Result<()> {
     // Save attestation
     if let Some(output_path) = output {
         fs::write(output_path, &response.attestation)?;
+    }
+    
+    if output::is_json() {
+        return output::json(&json!({
+            "proxy_number": response.proxy_number,
+            "attestation": response.attestation,
+            "saved_to": output,
+        }));
+    }
+    
+    output::success("Attestation received!");
+    println!("Proxy number: {}", response.proxy_number.yellow());
+    
+    if let Some(output_path) = output {
         println!("Saved to: {}", output_path.cyan());
     } else {
         println!("\nAttestation JWT:");
@@ -44,4 +244,72 @@ pub async fn execute(
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Drive the issuer's phone verification flow, by SMS code or carrier
+/// redirect, then request the attestation.
+async fn request_with_otp(
+    client: &IssuerClient,
+    phone: &PhoneNumber,
+    user_pubkey: &PublicKey,
+    scope: &str,
+    validity_days: Option<i64>,
+) -> anyhow::Result<AttestationResponse> {
+    output::info(&format!("Starting verification of {}...", phone));
+    let session = client.start_verification(phone).await?;
+    if let Some(seconds) = session.expires_in {
+        output::info(&format!("Verification expires in {} minutes", seconds.div_ceil(60)));
+    }
+    
+    match &session.verification_url {
+        Some(url) => confirm_carrier_redirect(client, &session.session_id, url).await?,
+        None => enter_sms_code(client, &session.session_id).await?,
+    }
+    
+    output::success("Phone number verified");
+    output::info("Requesting attestation...");
+    Ok(client
+        .request_session_attestation(&session.session_id, user_pubkey, scope, validity_days)
+        .await?)
+}
+
+/// Prompt for the SMS code until the issuer accepts it.
+async fn enter_sms_code(client: &IssuerClient, session_id: &str) -> anyhow::Result<()> {
+    output::info("A verification code was sent by SMS");
+    let mut attempts = 0;
+    loop {
+        let code: String = Input::new()
+            .with_prompt("Verification code")
+            .interact_text()?;
+        
+        if client.check_verification(session_id, code.trim()).await? {
+            return Ok(());
+        }
+        
+        attempts += 1;
+        anyhow::ensure!(attempts < OTP_ATTEMPTS, "Phone verification failed: too many incorrect codes");
+        output::warning(&format!("Incorrect code, {} attempt(s) left", OTP_ATTEMPTS - attempts));
+    }
+}
+
+/// Have the user open the carrier's link on the phone, then wait until the
+/// issuer has heard back from the carrier.
+async fn confirm_carrier_redirect(client: &IssuerClient, session_id: &str, url: &str) -> anyhow::Result<()> {
+    output::info("Open this link on the phone, using mobile data rather than Wi-Fi:");
+    println!("  {}", url);
+    let mut attempts = 0;
+    loop {
+        Input::<String>::new()
+            .with_prompt("Press Enter once the page says the number is verified")
+            .allow_empty(true)
+            .interact_text()?;
+        
+        if client.check_verification(session_id, "").await? {
+            return Ok(());
+        }
+        
+        attempts += 1;
+        anyhow::ensure!(attempts < OTP_ATTEMPTS, "Phone verification failed: the carrier did not confirm the number");
+        output::warning("The carrier has not confirmed the number yet");
+    }
+}
@@ -0,0 +1,180 @@
+//! Export and verification of an issuer node's signed audit log.
+
+use anyhow::Context;
+use clap::{Args, Subcommand};
+use hesha_client::IssuerClient;
+use hesha_types::{AuditEntry, AuditEvent, IssuerConfig, PublicKey};
+use crate::config::config_dir;
+use crate::output;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Export or verify the audit log.
+#[derive(Debug, Args)]
+pub struct AuditCmd {
+    #[command(subcommand)]
+    command: AuditCommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum AuditCommands {
+    /// Download audit entries from a running node as JSON lines
+    Export {
+        /// Name of the issuer configuration.
+        /// Defaults to 'default'
+        #[arg(short, long, default_value = "default")]
+        name: String,
+
+        /// Node URL. Defaults to http://localhost:<port> from the issuer config
+        #[arg(long, value_name = "URL")]
+        url: Option<String>,
+
+        /// Only export entries after this sequence number
+        #[arg(long, value_name = "SEQ", default_value_t = 0)]
+        after: u64,
+
+        /// Write to a file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+
+    /// Check the hash chain and signatures of an exported log
+    Verify {
+        /// Exported log (JSON lines)
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Name of the issuer configuration whose current and previous
+        /// keys are trusted. Defaults to 'default'
+        #[arg(short, long, default_value = "default")]
+        name: String,
+
+        /// Trust this issuer public key (base64url) instead; repeatable
+        #[arg(long = "public-key", value_name = "KEY")]
+        public_keys: Vec<String>,
+    },
+}
+
+impl AuditCmd {
+    pub async fn execute(self) -> anyhow::Result<()> {
+        match self.command {
+            AuditCommands::Export { name, url, after, out } => export(&name, url, after, out).await,
+            AuditCommands::Verify { file, name, public_keys } => verify(&file, &name, &public_keys),
+        }
+    }
+}
+
+/// Environment variable holding the operator credential for `/admin` routes.
+const ADMIN_TOKEN_ENV: &str = "HESHA_ADMIN_TOKEN";
+
+/// Client for an issuer node's `/admin` routes, authenticated with the
+/// credential in `HESHA_ADMIN_TOKEN` when it is set.
+pub(crate) fn admin_client(url: &str) -> anyhow::Result<IssuerClient> {
+    let client = IssuerClient::new(url)?;
+    Ok(match std::env::var(ADMIN_TOKEN_ENV) {
+        Ok(token) => client.with_admin_token(token),
+        Err(_) => client,
+    })
+}
+
+pub(crate) fn issuer_config(name: &str) -> anyhow::Result<IssuerConfig> {
+    let path = config_dir().join("issuer").join(name).join("config").join("issuer.toml");
+    IssuerConfig::from_file(&path)
+        .map_err(|e| anyhow::anyhow!("Cannot read issuer config {}: {}", path.display(), e))
+}
+
+async fn export(name: &str, url: Option<String>, after: u64, out: Option<PathBuf>) -> anyhow::Result<()> {
+    let url = match url {
+        Some(url) => url,
+        None => format!("http://localhost:{}", issuer_config(name)?.port),
+    };
+    let client = admin_client(&url)?;
+
+    let mut writer: Box<dyn Write> = match &out {
+        Some(path) => Box::new(fs::File::create(path).with_context(|| format!("Cannot create {}", path.display()))?),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    let entries = fetch_entries(&client, after).await
+        .with_context(|| format!("Failed to fetch audit log from {}", url))?;
+    for entry in &entries {
+        writeln!(writer, "{}", serde_json::to_string(entry)?)?;
+    }
+    let count = entries.len();
+    writer.flush()?;
+
+    if let Some(path) = out {
+        output::success(&format!("Exported {} audit entries to {}", count, path.display()));
+    }
+    Ok(())
+}
+
+/// Fetch every entry after `after`, page by page.
+/// 
+/// The node records each export in the log itself, so there is always one
+/// more entry; paging stops once a page holds nothing but those records.
+pub(crate) async fn fetch_entries(client: &IssuerClient, after: u64) -> anyhow::Result<Vec<AuditEntry>> {
+    let mut after = after;
+    let mut all = Vec::new();
+    loop {
+        let entries = client.audit_entries(after).await?;
+        let only_exports = entries.iter().all(|entry| {
+            matches!(&entry.event, AuditEvent::AdminAction { action, .. } if action == "audit.export")
+        });
+        let Some(last) = entries.last() else { break };
+        if only_exports && !all.is_empty() {
+            break;
+        }
+        after = last.seq;
+        all.extend(entries);
+    }
+    Ok(all)
+}
+
+fn verify(file: &PathBuf, name: &str, public_keys: &[String]) -> anyhow::Result<()> {
+    let trusted: Vec<PublicKey> = if public_keys.is_empty() {
+        let config = issuer_config(name)?;
+        std::iter::once(&config.identity.public_key_base64url)
+            .chain(config.previous_keys.iter().map(|key| &key.public_key_base64url))
+            .map(|key| PublicKey::from_base64(key))
+            .collect::<Result<_, _>>()?
+    } else {
+        public_keys.iter().map(|key| PublicKey::from_base64(key)).collect::<Result<_, _>>()?
+    };
+
+    let contents = fs::read_to_string(file).with_context(|| format!("Cannot read {}", file.display()))?;
+    let entries: Vec<AuditEntry> = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str(line).with_context(|| format!("Line {} is not an audit entry", number + 1))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    hesha_core::verify_audit_log(&entries, &trusted)?;
+
+    let range = match (entries.first(), entries.last()) {
+        (Some(first), Some(last)) => Some((first.seq, last.seq)),
+        _ => None,
+    };
+    if output::is_json() {
+        return output::json(&serde_json::json!({
+            "valid": true,
+            "entries": entries.len(),
+            "first_seq": range.map(|(first, _)| first),
+            "last_seq": range.map(|(_, last)| last),
+        }));
+    }
+    match range {
+        Some((1, last)) => output::success(&format!("Audit log intact: entries 1-{} verified", last)),
+        Some((first, last)) => {
+            output::success(&format!("Entries {}-{} verified", first, last));
+            output::warning(&format!(
+                "Log starts at entry {}; entries before it were not checked",
+                first
+            ));
+        }
+        None => output::info("No audit entries to verify"),
+    }
+    Ok(())
+}
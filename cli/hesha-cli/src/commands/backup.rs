@@ -0,0 +1,94 @@
+//! Encrypted backup of an issuer's configuration, keys and records.
+
+use anyhow::Context;
+use chrono::Utc;
+use clap::Args;
+use hesha_core::backup::{AUDIT_FILE, RECORDS_FILE};
+use hesha_core::Backup;
+use crate::commands::audit::{admin_client, fetch_entries, issuer_config};
+use crate::config::{config_dir, read_backup_passphrase};
+use crate::output;
+use std::fs;
+use std::path::PathBuf;
+
+/// Back up an issuer to an encrypted archive.
+#[derive(Debug, Args)]
+pub struct BackupCmd {
+    /// Name of the issuer configuration.
+    /// Defaults to 'default'
+    #[arg(short, long, default_value = "default")]
+    name: String,
+
+    /// Archive to write. Defaults to hesha-<name>-<time>.backup
+    #[arg(short, long, value_name = "FILE")]
+    out: Option<PathBuf>,
+
+    /// Node URL. Defaults to http://localhost:<port> from the issuer config
+    #[arg(long, value_name = "URL")]
+    url: Option<String>,
+
+    /// Only back up config and keys, without asking the node for records
+    #[arg(long)]
+    files_only: bool,
+}
+
+impl BackupCmd {
+    pub async fn execute(self) -> anyhow::Result<()> {
+        let issuer_dir = config_dir().join("issuer").join(&self.name);
+        let mut backup = Backup::from_issuer_dir(&issuer_dir)
+            .with_context(|| format!("Cannot back up issuer '{}'", self.name))?;
+
+        let mut audit_entries = 0;
+        if !self.files_only {
+            let url = match self.url {
+                Some(url) => url,
+                None => format!("http://localhost:{}", issuer_config(&self.name)?.port),
+            };
+            let client = admin_client(&url)?;
+            let unreachable = || format!(
+                "Cannot fetch records from the issuer node at {}; start it, or pass --files-only",
+                url
+            );
+
+            let records = client.backup_records().await.with_context(unreachable)?;
+            backup.add_file(RECORDS_FILE, &records);
+
+            let entries = fetch_entries(&client, 0).await.with_context(unreachable)?;
+            let mut audit = Vec::new();
+            for entry in &entries {
+                audit.extend(serde_json::to_vec(entry)?);
+                audit.push(b'\n');
+            }
+            audit_entries = entries.len();
+            backup.add_file(AUDIT_FILE, &audit);
+        }
+
+        let passphrase = read_backup_passphrase(true)?;
+        let sealed = backup.seal(&passphrase)?;
+        let out = self.out.unwrap_or_else(|| {
+            PathBuf::from(format!("hesha-{}-{}.backup", self.name, Utc::now().format("%Y%m%dT%H%M%SZ")))
+        });
+        fs::write(&out, sealed.to_json()?).with_context(|| format!("Cannot write {}", out.display()))?;
+
+        if output::is_json() {
+            return output::json(&serde_json::json!({
+                "file": out,
+                "created_at": backup.created_at,
+                "files": backup.files.iter().map(|file| &file.path).collect::<Vec<_>>(),
+                "records": !self.files_only,
+                "audit_entries": audit_entries,
+            }));
+        }
+        output::success(&format!("Backed up issuer '{}' to {}", self.name, out.display()));
+        for file in &backup.files {
+            println!("  {}", file.path);
+        }
+        if self.files_only {
+            output::warning("Records were not included; attestations and revocations can't be restored from this backup");
+        } else {
+            output::info(&format!("Included {} audit entries", audit_entries));
+        }
+        output::info("Keep the archive and its passphrase apart; together they hold the signing key");
+        Ok(())
+    }
+}
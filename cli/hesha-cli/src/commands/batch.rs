@@ -0,0 +1,276 @@
+//! Batch attestation requests from CSV or JSON input.
+//!
+//! Input rows need a `phone` column and may override `scope` and
+//! `validity_days`. Results are appended to a CSV file as each request
+//! completes and each JWT is saved to `<input>-attestations/<row>.jwt`,
+//! so an interrupted batch can be resumed with `--resume`: rows
+//! already marked `ok` are skipped and failed rows are retried.
+//!
+//! With `-` as the input, rows are read from stdin (JSON if it starts
+//! with `[`, CSV otherwise) and results default to `batch.results.csv`.
+
+use crate::{config, input, output, parallel};
+use anyhow::Context;
+use hesha_client::IssuerClient;
+use hesha_types::{PhoneNumber, MAX_VALIDITY_DAYS};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    sync::Arc,
+};
+
+/// Options for a batch run.
+pub struct BatchOptions<'a> {
+    /// Issuer URL.
+    pub issuer: &'a str,
+    /// Default scope for rows without one.
+    pub scope: Option<&'a str>,
+    /// Key file or stored key name.
+    pub key: Option<&'a str>,
+    /// Default validity for rows without one.
+    pub validity_days: Option<i64>,
+    /// Maximum number of requests in flight.
+    pub jobs: usize,
+    /// Results file (defaults to `<input>.results.csv`).
+    pub results: Option<&'a Path>,
+    /// Skip rows already completed in the results file.
+    pub resume: bool,
+}
+
+/// A single input row.
+#[derive(Debug, Clone, Deserialize)]
+struct BatchRow {
+    phone: String,
+    #[serde(default)]
+    scope: Option<String>,
+    #[serde(default)]
+    validity_days: Option<i64>,
+}
+
+/// Outcome of a single row, as written to the results file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BatchResult {
+    row: usize,
+    phone: String,
+    status: String,
+    proxy_number: String,
+    jwt_path: String,
+    error: String,
+}
+
+impl BatchResult {
+    fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
+}
+
+/// Execute a batch attestation run.
+pub async fn execute(input: &Path, options: BatchOptions<'_>) -> anyhow::Result<()> {
+    check_validity_days(options.validity_days).context("Invalid --validity-days")?;
+    let rows = read_rows(input)?;
+    // Name outputs after the input file, or "batch" for stdin
+    let input = if input == Path::new(input::STDIN) { Path::new("batch") } else { input };
+    let results_path = options
+        .results
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| input.with_extension("results.csv"));
+    let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("batch");
+    let jwt_dir = results_path.with_file_name(format!("{}-attestations", stem));
+
+    // Keep completed rows when resuming, start over otherwise
+    let mut results: BTreeMap<usize, BatchResult> = BTreeMap::new();
+    if options.resume && results_path.exists() {
+        for result in read_results(&results_path)? {
+            if result.is_ok() {
+                results.insert(result.row, result);
+            }
+        }
+    }
+
+    let pending: Vec<(usize, BatchRow)> = rows
+        .into_iter()
+        .enumerate()
+        .map(|(index, row)| (index + 1, row))
+        .filter(|(row, _)| !results.contains_key(row))
+        .collect();
+
+    output::info(&format!(
+        "Requesting {} attestations ({} already done)...",
+        pending.len(),
+        results.len()
+    ));
+
+    let keypair = Arc::new(config::load_keypair(options.key)?);
+    let client = IssuerClient::new(options.issuer)?;
+    fs::create_dir_all(&jwt_dir)
+        .with_context(|| format!("Failed to create {}", jwt_dir.display()))?;
+
+    // Rewrite the results file with the rows we're keeping, then append as we go
+    let mut writer = csv::Writer::from_path(&results_path)
+        .with_context(|| format!("Failed to write {}", results_path.display()))?;
+    for result in results.values() {
+        writer.serialize(result)?;
+    }
+    writer.flush()?;
+
+    let default_scope = options.scope.map(str::to_string);
+    let default_validity = options.validity_days;
+    let task = |(row, input): (usize, BatchRow)| {
+        let scope = input.scope.clone().or(default_scope.clone());
+        let validity_days = input.validity_days.or(default_validity);
+        let client = client.clone();
+        let keypair = keypair.clone();
+        let jwt_path = jwt_dir.join(format!("{}.jwt", row));
+
+        async move {
+            let outcome = async {
+                let scope = scope.context("No scope for row; pass --scope or add a scope column")?;
+                check_validity_days(validity_days)?;
+                let phone = PhoneNumber::new(&input.phone)?;
+                let response = client
+                    .request_attestation(&phone, &keypair.public, &scope, validity_days)
+                    .await?;
+                fs::write(&jwt_path, &response.attestation)?;
+                anyhow::Ok(response.proxy_number)
+            }
+            .await;
+
+            match outcome {
+                Ok(proxy_number) => BatchResult {
+                    row,
+                    phone: input.phone,
+                    status: "ok".to_string(),
+                    proxy_number,
+                    jwt_path: jwt_path.display().to_string(),
+                    error: String::new(),
+                },
+                Err(e) => BatchResult {
+                    row,
+                    phone: input.phone,
+                    status: "error".to_string(),
+                    proxy_number: String::new(),
+                    jwt_path: String::new(),
+                    error: e.to_string(),
+                },
+            }
+        }
+    };
+
+    let (mut succeeded, mut failed) = (0, 0);
+    parallel::run(pending, options.jobs, "Attesting", task, |result: BatchResult| {
+        if result.is_ok() {
+            succeeded += 1;
+        } else {
+            failed += 1;
+            output::warning(&format!("Row {} ({}): {}", result.row, result.phone, result.error));
+        }
+        writer.serialize(&result)?;
+        writer.flush()?;
+        results.insert(result.row, result);
+        Ok(())
+    })
+    .await?;
+
+    if output::is_json() {
+        return output::json(&serde_json::json!({
+            "results_file": results_path,
+            "jwt_dir": jwt_dir,
+            "succeeded": succeeded,
+            "failed": failed,
+            "skipped": results.len() - succeeded - failed,
+        }));
+    }
+
+    output::success(&format!(
+        "Batch complete: {} succeeded, {} failed. Results in {}",
+        succeeded,
+        failed,
+        results_path.display()
+    ));
+    if failed > 0 {
+        output::info("Re-run with --resume to retry failed rows");
+    }
+
+    Ok(())
+}
+
+/// Check a requested validity period is within 1..=[`MAX_VALIDITY_DAYS`] days.
+fn check_validity_days(days: Option<i64>) -> anyhow::Result<()> {
+    if let Some(days) = days {
+        anyhow::ensure!(
+            (1..=MAX_VALIDITY_DAYS).contains(&days),
+            "validity_days must be between 1 and {}, not {}",
+            MAX_VALIDITY_DAYS,
+            days
+        );
+    }
+    Ok(())
+}
+
+/// Read input rows from a CSV file or a JSON array.
+fn read_rows(input: &Path) -> anyhow::Result<Vec<BatchRow>> {
+    let data = input::read_input(&input.to_string_lossy())?;
+    let is_json = match input.extension().and_then(|e| e.to_str()) {
+        Some(extension) => extension == "json",
+        None => data.trim_start().starts_with('['),
+    };
+    if is_json {
+        return serde_json::from_str(&data)
+            .with_context(|| format!("{} must be a JSON array of {{\"phone\": ...}} objects", input.display()));
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(data.as_bytes());
+    reader
+        .deserialize()
+        .collect::<Result<Vec<BatchRow>, _>>()
+        .with_context(|| format!("{} must have a 'phone' column", input.display()))
+}
+
+fn read_results(path: &Path) -> anyhow::Result<Vec<BatchResult>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    reader
+        .deserialize()
+        .collect::<Result<Vec<BatchResult>, _>>()
+        .with_context(|| format!("Invalid results file {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_validity_days() {
+        for days in [None, Some(1), Some(90), Some(MAX_VALIDITY_DAYS)] {
+            assert!(check_validity_days(days).is_ok(), "{:?}", days);
+        }
+        for days in [Some(0), Some(-1), Some(MAX_VALIDITY_DAYS + 1)] {
+            assert!(check_validity_days(days).is_err(), "{:?}", days);
+        }
+    }
+
+    #[test]
+    fn test_read_rows() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let csv = dir.path().join("rows.csv");
+        fs::write(&csv, "phone, scope, validity_days\n+14155552671, 1, 30\n+14155552672,,\n").unwrap();
+        let rows = read_rows(&csv).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].scope.as_deref(), Some("1"));
+        assert_eq!(rows[0].validity_days, Some(30));
+        assert_eq!((rows[1].scope.as_deref(), rows[1].validity_days), (None, None));
+
+        let json = dir.path().join("rows.json");
+        fs::write(&json, r#"[{"phone": "+14155552671", "validity_days": 800}]"#).unwrap();
+        let rows = read_rows(&json).unwrap();
+        assert_eq!(rows[0].validity_days, Some(800));
+        assert!(check_validity_days(rows[0].validity_days).is_err());
+
+        fs::write(&csv, "number\n+14155552671\n").unwrap();
+        assert!(read_rows(&csv).is_err());
+    }
+}
@@ -0,0 +1,285 @@
+//! Key ceremonies sharing an issuer key among custodians.
+
+use anyhow::Context;
+use clap::{Args, Subcommand};
+use dialoguer::{theme::ColorfulTheme, Confirm, Password};
+use hesha_core::{recover_key, CeremonyTranscript, CustodianShare, KeyCeremony};
+use hesha_crypto::{decrypt_data, encrypt_data, EncryptedData, KdfParams};
+use crate::config;
+use crate::input;
+use crate::output;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Transcript file written by a ceremony.
+const TRANSCRIPT_FILE: &str = "transcript.json";
+
+/// Run or check a key ceremony.
+#[derive(Debug, Args)]
+pub struct CeremonyCmd {
+    #[command(subcommand)]
+    command: CeremonyCommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum CeremonyCommands {
+    /// Generate a key and split it among custodians
+    Run {
+        /// Custodian receiving a share; repeat for each
+        #[arg(long = "custodian", value_name = "NAME", required = true, num_args = 1)]
+        custodians: Vec<String>,
+
+        /// Shares needed to recover the key
+        #[arg(short, long, value_name = "K")]
+        threshold: u8,
+
+        /// Witness present who receives no share; repeatable
+        #[arg(long = "witness", value_name = "NAME")]
+        witnesses: Vec<String>,
+
+        /// Key ID the issuer will publish the key under
+        #[arg(long, value_name = "ID", default_value = "default")]
+        key_id: String,
+
+        /// Directory for the shares and transcript. Defaults to ceremony-<time>
+        #[arg(short, long, value_name = "DIR")]
+        out: Option<PathBuf>,
+
+        /// Encrypt each share with a passphrase its custodian chooses
+        #[arg(long)]
+        encrypt_shares: bool,
+
+        /// Don't ask for confirmation before starting
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Check a transcript's signature and, optionally, shares against it
+    Verify {
+        /// Ceremony transcript
+        #[arg(value_name = "TRANSCRIPT")]
+        transcript: PathBuf,
+
+        /// Share files to check
+        #[arg(value_name = "SHARE")]
+        shares: Vec<PathBuf>,
+    },
+
+    /// Recover the key from custodians' shares
+    Recover {
+        /// Ceremony transcript
+        #[arg(value_name = "TRANSCRIPT")]
+        transcript: PathBuf,
+
+        /// At least threshold share files
+        #[arg(value_name = "SHARE", required = true)]
+        shares: Vec<PathBuf>,
+
+        /// Private key file to write (e.g. keys/private.key of an issuer)
+        #[arg(short, long, value_name = "FILE")]
+        out: PathBuf,
+
+        /// Replace an existing key file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+impl CeremonyCmd {
+    pub fn execute(self) -> anyhow::Result<()> {
+        match self.command {
+            CeremonyCommands::Run { custodians, threshold, witnesses, key_id, out, encrypt_shares, yes } => {
+                run(custodians, threshold, witnesses, key_id, out, encrypt_shares, yes)
+            }
+            CeremonyCommands::Verify { transcript, shares } => verify(&transcript, &shares),
+            CeremonyCommands::Recover { transcript, shares, out, force } => recover(&transcript, &shares, &out, force),
+        }
+    }
+}
+
+fn run(
+    custodians: Vec<String>,
+    threshold: u8,
+    witnesses: Vec<String>,
+    key_id: String,
+    out: Option<PathBuf>,
+    encrypt_shares: bool,
+    yes: bool,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !encrypt_shares || input::is_interactive(),
+        "--encrypt-shares prompts each custodian for a passphrase and needs a terminal"
+    );
+    let out = out.unwrap_or_else(|| PathBuf::from(format!("ceremony-{}", chrono::Utc::now().format("%Y%m%dT%H%M%SZ"))));
+    if out.exists() && fs::read_dir(&out)?.next().is_some() {
+        anyhow::bail!("{} is not empty; choose a new directory for the ceremony", out.display());
+    }
+
+    if !output::is_json() {
+        output::text(&format!(
+            "Key ceremony: {} custodians, any {} of whom can recover the key",
+            custodians.len(),
+            threshold
+        ));
+        output::text(&format!("  Custodians: {}", custodians.join(", ")));
+        if !witnesses.is_empty() {
+            output::text(&format!("  Witnesses:  {}", witnesses.join(", ")));
+        }
+        output::text("\nThe key is generated in this process and only ever written out as shares.");
+        output::text("Run this on an offline machine, with every custodian and witness present.\n");
+    }
+    if !yes {
+        anyhow::ensure!(
+            !output::is_json() && input::is_interactive(),
+            "Pass --yes to run the ceremony without a confirmation prompt"
+        );
+        let proceed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Are all custodians and witnesses present?")
+            .default(false)
+            .interact()?;
+        anyhow::ensure!(proceed, "Ceremony cancelled");
+    }
+
+    let outcome = KeyCeremony::new(custodians, threshold)
+        .with_key_id(key_id)
+        .with_witnesses(witnesses)
+        .run()?;
+    let transcript = &outcome.transcript;
+    output::success(&format!(
+        "Generated key {} and checked {} recoveries from its shares",
+        transcript.public_key.to_base64(),
+        transcript.recoveries_checked.len()
+    ));
+
+    fs::create_dir_all(&out).with_context(|| format!("Cannot create {}", out.display()))?;
+    let mut share_files = Vec::new();
+    for share in &outcome.shares {
+        let path = out.join(share_file_name(share));
+        let mut contents = serde_json::to_string_pretty(share)?;
+        if encrypt_shares {
+            let passphrase = Password::new()
+                .with_prompt(format!("{}, choose a passphrase for your share", share.custodian))
+                .with_confirmation("Confirm passphrase", "Passphrases do not match")
+                .interact()?;
+            contents = serde_json::to_string_pretty(&encrypt_data(contents.as_bytes(), &passphrase, KdfParams::default())?)?;
+        }
+        config::write_private(&path, &contents)?;
+        if !output::is_json() {
+            output::text(&format!("  Share {} for {}: {}", share.share.index, share.custodian, path.display()));
+        }
+        share_files.push(path);
+    }
+    let transcript_path = out.join(TRANSCRIPT_FILE);
+    fs::write(&transcript_path, serde_json::to_string_pretty(transcript)?)
+        .with_context(|| format!("Cannot write {}", transcript_path.display()))?;
+
+    if output::is_json() {
+        return output::json(&serde_json::json!({
+            "ceremony_id": transcript.ceremony_id,
+            "key_id": transcript.key_id,
+            "public_key": transcript.public_key.to_base64(),
+            "threshold": transcript.threshold,
+            "transcript_file": transcript_path,
+            "share_files": share_files,
+        }));
+    }
+    output::success(&format!("Signed transcript written to {}", transcript_path.display()));
+    output::text("\nNext steps:");
+    output::text("  □ Each custodian takes their share file onto their own offline media");
+    output::text("  □ Delete the share files from this machine once they are copied");
+    output::text(&format!("  □ Each custodian checks their share: hesha ceremony verify {} <SHARE>", transcript_path.display()));
+    output::text("  □ Publish or archive the transcript; it holds no secret");
+    output::text(&format!(
+        "  □ Recover the key on the issuer host: hesha ceremony recover {} <SHARES>... -o keys/private.key",
+        transcript_path.display()
+    ));
+    Ok(())
+}
+
+fn verify(transcript_path: &Path, shares: &[PathBuf]) -> anyhow::Result<()> {
+    let transcript = read_transcript(transcript_path)?;
+    transcript.verify().context("Transcript signature does not check out")?;
+    for path in shares {
+        let share = read_share(path)?;
+        transcript.verify_share(&share).with_context(|| format!("{} does not belong to this ceremony", path.display()))?;
+    }
+
+    if output::is_json() {
+        return output::json(&serde_json::json!({
+            "ceremony_id": transcript.ceremony_id,
+            "key_id": transcript.key_id,
+            "public_key": transcript.public_key.to_base64(),
+            "threshold": transcript.threshold,
+            "custodians": transcript.custodians.iter().map(|custodian| &custodian.name).collect::<Vec<_>>(),
+            "shares_checked": shares.len(),
+        }));
+    }
+    output::success(&format!(
+        "Transcript of ceremony {} is signed by key {}",
+        transcript.ceremony_id,
+        transcript.public_key.to_base64()
+    ));
+    output::text(&format!("  Completed:  {}", transcript.completed_at.to_rfc3339()));
+    output::text(&format!(
+        "  Custodians: {} (any {} recover the key)",
+        transcript.custodians.iter().map(|custodian| custodian.name.as_str()).collect::<Vec<_>>().join(", "),
+        transcript.threshold
+    ));
+    if !transcript.witnesses.is_empty() {
+        output::text(&format!("  Witnesses:  {}", transcript.witnesses.join(", ")));
+    }
+    if !shares.is_empty() {
+        output::success(&format!("{} share(s) match the transcript", shares.len()));
+    }
+    Ok(())
+}
+
+fn recover(transcript_path: &Path, share_paths: &[PathBuf], out: &Path, force: bool) -> anyhow::Result<()> {
+    anyhow::ensure!(force || !out.exists(), "{} already exists; pass --force to replace it", out.display());
+    let transcript = read_transcript(transcript_path)?;
+    let shares = share_paths.iter().map(|path| read_share(path)).collect::<anyhow::Result<Vec<_>>>()?;
+    let keypair = recover_key(&transcript, &shares)?;
+    config::write_private(out, &keypair.private.to_base64())?;
+
+    if output::is_json() {
+        return output::json(&serde_json::json!({
+            "key_id": transcript.key_id,
+            "public_key": keypair.public.to_base64(),
+            "private_key_file": out,
+        }));
+    }
+    output::success(&format!("Recovered key {} into {}", keypair.public.to_base64(), out.display()));
+    output::info(&format!("Publish it under key ID '{}'", transcript.key_id));
+    Ok(())
+}
+
+fn read_transcript(path: &Path) -> anyhow::Result<CeremonyTranscript> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    serde_json::from_str(&contents).with_context(|| format!("{} is not a ceremony transcript", path.display()))
+}
+
+/// Read a share file, asking for its passphrase if it is encrypted.
+fn read_share(path: &Path) -> anyhow::Result<CustodianShare> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    if let Ok(share) = serde_json::from_str::<CustodianShare>(&contents) {
+        return Ok(share);
+    }
+    let encrypted: EncryptedData = serde_json::from_str(&contents)
+        .with_context(|| format!("{} is not a ceremony share", path.display()))?;
+    anyhow::ensure!(input::is_interactive(), "{} is encrypted; its custodian must enter the passphrase", path.display());
+    let passphrase = Password::new()
+        .with_prompt(format!("Passphrase for {}", path.display()))
+        .interact()?;
+    let decrypted = decrypt_data(&encrypted, &passphrase)
+        .with_context(|| format!("Cannot decrypt {}", path.display()))?;
+    Ok(serde_json::from_slice(&decrypted)?)
+}
+
+fn share_file_name(share: &CustodianShare) -> String {
+    let name: String = share
+        .custodian
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("share-{}-{}.json", share.share.index, name)
+}
@@ -0,0 +1,72 @@
+//! Commands for checking issuer configurations.
+
+use clap::{Args, Subcommand};
+use hesha_core::validate_issuer_config;
+use crate::output;
+use std::path::PathBuf;
+
+/// Inspect and check issuer configurations.
+#[derive(Debug, Args)]
+pub struct ConfigCmd {
+    #[command(subcommand)]
+    command: ConfigCommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommands {
+    /// Check an issuer.toml and its key files, reporting every problem
+    Validate {
+        /// issuer.toml or issuer directory to check.
+        /// Defaults to ~/.hesha/issuer/<name>/config/issuer.toml
+        #[arg(value_name = "PATH")]
+        path: Option<PathBuf>,
+        
+        /// Name of the issuer configuration. Defaults to 'default'
+        #[arg(short, long, default_value = "default", conflicts_with = "path")]
+        name: String,
+    },
+}
+
+impl ConfigCmd {
+    pub fn execute(self) -> anyhow::Result<()> {
+        match self.command {
+            ConfigCommands::Validate { path, name } => validate(path, &name),
+        }
+    }
+}
+
+fn validate(path: Option<PathBuf>, name: &str) -> anyhow::Result<()> {
+    let config_path = match path {
+        Some(path) if path.is_dir() => path.join("config").join("issuer.toml"),
+        Some(path) => path,
+        None => dirs::home_dir()
+            .expect("Could not find home directory")
+            .join(".hesha")
+            .join("issuer")
+            .join(name)
+            .join("config")
+            .join("issuer.toml"),
+    };
+    
+    let problems = validate_issuer_config(&config_path);
+    
+    if output::is_json() {
+        output::json(&serde_json::json!({
+            "config_file": config_path,
+            "valid": problems.is_empty(),
+            "problems": problems,
+        }))?;
+    } else if problems.is_empty() {
+        output::success(&format!("{} is valid", config_path.display()));
+    } else {
+        output::error(&format!("{} has {} problem(s):", config_path.display(), problems.len()));
+        for problem in &problems {
+            output::text(&format!("  - {}", problem));
+        }
+    }
+    
+    if !problems.is_empty() {
+        anyhow::bail!("Invalid issuer configuration");
+    }
+    Ok(())
+}
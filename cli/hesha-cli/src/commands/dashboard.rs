@@ -0,0 +1,388 @@
+//! Live terminal dashboard for issuer operators.
+
+use anyhow::Context;
+use chrono::{DateTime, Local, Utc};
+use clap::Args;
+use hesha_client::IssuerClient;
+use hesha_types::{IssuerConfig, NodeMetrics};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Sparkline, Table};
+use ratatui::Frame;
+use crate::commands::audit::admin_client;
+use crate::config::config_dir;
+use crate::output;
+use std::collections::VecDeque;
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+/// Number of polls kept for the issuance sparkline.
+const HISTORY: usize = 120;
+
+/// Watch a running issuer node: issuance rate, recent attestations, errors,
+/// and key and certificate expiry.
+#[derive(Debug, Args)]
+pub struct DashboardCmd {
+    /// Name of the issuer configuration.
+    /// Defaults to 'default'
+    #[arg(short, long, default_value = "default")]
+    name: String,
+    
+    /// Node URL. Defaults to http://localhost:<port> from the issuer config
+    #[arg(long, value_name = "URL")]
+    url: Option<String>,
+    
+    /// Seconds between polls
+    #[arg(long, value_name = "SECS", default_value_t = 2)]
+    interval: u64,
+}
+
+impl DashboardCmd {
+    pub async fn execute(self) -> anyhow::Result<()> {
+        let config_path = config_dir()
+            .join("issuer")
+            .join(&self.name)
+            .join("config")
+            .join("issuer.toml");
+        let config = IssuerConfig::from_file(&config_path).ok();
+        
+        let url = match (&self.url, &config) {
+            (Some(url), _) => url.clone(),
+            (None, Some(config)) => format!("http://localhost:{}", config.port),
+            (None, None) => anyhow::bail!(
+                "No configuration found for issuer '{}'; pass --url to watch a node directly",
+                self.name
+            ),
+        };
+        let client = admin_client(&url)?;
+        
+        // Scripts get a single snapshot
+        if output::is_json() || !std::io::stdout().is_terminal() {
+            let metrics = client.metrics().await
+                .with_context(|| format!("Failed to fetch metrics from {}", url))?;
+            return output::json(&metrics);
+        }
+        
+        let interval = Duration::from_secs(self.interval.max(1));
+        let mut dashboard = Dashboard {
+            name: self.name,
+            url,
+            config,
+            metrics: None,
+            error: None,
+            updated_at: None,
+            issued_per_poll: VecDeque::with_capacity(HISTORY),
+            last_issued: None,
+            interval,
+        };
+        
+        let mut terminal = ratatui::init();
+        let result: anyhow::Result<()> = async {
+            let mut next_poll = Instant::now();
+            loop {
+                if Instant::now() >= next_poll {
+                    dashboard.refresh(&client).await;
+                    next_poll = Instant::now() + interval;
+                }
+                terminal.draw(|frame| dashboard.draw(frame))?;
+                
+                if event::poll(Duration::from_millis(200))? {
+                    if let Event::Key(key) = event::read()? {
+                        if key.kind != KeyEventKind::Press {
+                            continue;
+                        }
+                        match key.code {
+                            KeyCode::Char('q') | KeyCode::Esc => break,
+                            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+                            KeyCode::Char('r') => next_poll = Instant::now(),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+        .await;
+        ratatui::restore();
+        
+        result
+    }
+}
+
+/// What the dashboard knows about the node.
+struct Dashboard {
+    name: String,
+    url: String,
+    config: Option<IssuerConfig>,
+    metrics: Option<NodeMetrics>,
+    error: Option<String>,
+    updated_at: Option<DateTime<Utc>>,
+    /// Attestations issued between consecutive polls, oldest first.
+    issued_per_poll: VecDeque<u64>,
+    last_issued: Option<u64>,
+    interval: Duration,
+}
+
+impl Dashboard {
+    async fn refresh(&mut self, client: &IssuerClient) {
+        match client.metrics().await {
+            Ok(metrics) => {
+                // A restarted node starts counting from zero again
+                let delta = match self.last_issued {
+                    Some(last) if metrics.attestations_issued >= last => metrics.attestations_issued - last,
+                    _ => 0,
+                };
+                if self.last_issued.is_some() {
+                    if self.issued_per_poll.len() == HISTORY {
+                        self.issued_per_poll.pop_front();
+                    }
+                    self.issued_per_poll.push_back(delta);
+                }
+                self.last_issued = Some(metrics.attestations_issued);
+                self.metrics = Some(metrics);
+                self.error = None;
+                self.updated_at = Some(Utc::now());
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+    
+    fn draw(&self, frame: &mut Frame) {
+        let [header, top, recent, footer] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(8),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [issuance, errors, keys] = Layout::horizontal([
+            Constraint::Percentage(40),
+            Constraint::Percentage(25),
+            Constraint::Percentage(35),
+        ])
+        .areas(top);
+        
+        frame.render_widget(self.header(), header);
+        self.draw_issuance(frame, issuance);
+        frame.render_widget(self.errors(), errors);
+        frame.render_widget(self.keys(), keys);
+        frame.render_widget(self.recent(), recent);
+        frame.render_widget(
+            Line::from(" q quit · r refresh").style(Style::new().fg(Color::DarkGray)),
+            footer,
+        );
+    }
+    
+    fn header(&self) -> Paragraph<'_> {
+        let status = match (&self.error, &self.metrics) {
+            (Some(error), _) => format!("unreachable: {}", error).red(),
+            (None, Some(metrics)) => format!(
+                "up {}",
+                format_duration(Utc::now() - metrics.started_at)
+            ).green(),
+            (None, None) => "connecting...".yellow(),
+        };
+        let updated = self.updated_at
+            .map(|at| format!("updated {}", at.with_timezone(&Local).format("%H:%M:%S")))
+            .unwrap_or_default();
+        
+        Paragraph::new(Line::from(vec![
+            self.name.clone().bold(),
+            "  ".into(),
+            self.url.clone().into(),
+            "  ".into(),
+            status,
+            "  ".into(),
+            updated.dark_gray(),
+        ]))
+        .block(Block::new().borders(Borders::ALL).title(" hesha dashboard "))
+    }
+    
+    fn draw_issuance(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let block = Block::new().borders(Borders::ALL).title(" Issuance ");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        
+        let [summary, chart] = Layout::vertical([Constraint::Length(2), Constraint::Min(1)]).areas(inner);
+        
+        let total = self.metrics.as_ref().map(|m| m.attestations_issued).unwrap_or(0);
+        let recent: Vec<u64> = self.issued_per_poll.iter().rev().take(30).copied().collect();
+        let per_poll = if recent.is_empty() { 0.0 } else { recent.iter().sum::<u64>() as f64 / recent.len() as f64 };
+        let per_minute = per_poll * 60.0 / self.interval.as_secs_f64();
+        
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::from(vec!["Total issued: ".into(), total.to_string().bold()]),
+                Line::from(vec!["Rate:         ".into(), format!("{:.1}/min", per_minute).bold()]),
+            ]),
+            summary,
+        );
+        
+        let data: Vec<u64> = self.issued_per_poll.iter().copied().collect();
+        let width = chart.width as usize;
+        let start = data.len().saturating_sub(width);
+        frame.render_widget(
+            Sparkline::default().data(&data[start..]).style(Style::new().fg(Color::Cyan)),
+            chart,
+        );
+    }
+    
+    fn errors(&self) -> Table<'_> {
+        let rows: Vec<Row> = self.metrics
+            .iter()
+            .flat_map(|metrics| metrics.errors.iter())
+            .map(|(code, count)| Row::new(vec![code.clone(), count.to_string()]))
+            .collect();
+        let title = if rows.is_empty() { " Errors (none) " } else { " Errors " };
+        
+        Table::new(rows, [Constraint::Min(10), Constraint::Length(8)])
+            .block(Block::new().borders(Borders::ALL).title(title))
+            .style(Style::new().fg(Color::Red))
+    }
+    
+    fn keys(&self) -> Table<'_> {
+        let now = Utc::now();
+        let mut rows = Vec::new();
+        if let Some(config) = &self.config {
+            rows.push(Row::new(vec![
+                config.identity.key_id.clone(),
+                "current".to_string(),
+            ]).style(Style::new().fg(Color::Green)));
+            for key in &config.previous_keys {
+                let until = DateTime::parse_from_rfc3339(&key.valid_until)
+                    .map(|until| until.with_timezone(&Utc));
+                let (label, color) = match until {
+                    Ok(until) if until > now => (format!("retires in {}", format_duration(until - now)), Color::Yellow),
+                    Ok(_) => ("past retirement".to_string(), Color::DarkGray),
+                    Err(_) => (format!("invalid valid_until '{}'", key.valid_until), Color::Red),
+                };
+                rows.push(Row::new(vec![key.key_id.clone(), label]).style(Style::new().fg(color)));
+            }
+        }
+        // Only reported by nodes terminating TLS themselves ([tls] or [acme])
+        if let Some(expires_at) = self.metrics.as_ref().and_then(|m| m.certificate_expires_at) {
+            let remaining = expires_at - now;
+            let label = if remaining > chrono::Duration::zero() {
+                format!("expires in {}", format_duration(remaining))
+            } else {
+                "expired".to_string()
+            };
+            let color = match remaining.num_days() {
+                ..=6 => Color::Red,
+                7..=29 => Color::Yellow,
+                _ => Color::Green,
+            };
+            rows.push(Row::new(vec!["TLS certificate".to_string(), label]).style(Style::new().fg(color)));
+        }
+        let title = if self.config.is_some() { " Keys " } else { " Keys (no local config) " };
+        
+        Table::new(rows, [Constraint::Percentage(50), Constraint::Percentage(50)])
+            .block(Block::new().borders(Borders::ALL).title(title))
+    }
+    
+    fn recent(&self) -> Table<'_> {
+        let now = Utc::now();
+        let rows: Vec<Row> = self.metrics
+            .iter()
+            .flat_map(|metrics| metrics.recent_attestations.iter())
+            .map(|attestation| Row::new(vec![
+                attestation.issued_at.with_timezone(&Local).format("%H:%M:%S").to_string(),
+                attestation.proxy_number.clone(),
+                attestation.jti.clone(),
+                if attestation.expires_at > now {
+                    format_duration(attestation.expires_at - now)
+                } else {
+                    "expired".to_string()
+                },
+            ]))
+            .collect();
+        
+        Table::new(rows, [
+            Constraint::Length(10),
+            Constraint::Length(18),
+            Constraint::Min(36),
+            Constraint::Length(12),
+        ])
+        .header(
+            Row::new(vec!["Issued", "Proxy number", "JTI", "Expires in"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::new().borders(Borders::ALL).title(" Recent attestations "))
+    }
+}
+
+/// Compact rendering of a duration, e.g. "12d 4h" or "5m 30s".
+fn format_duration(duration: chrono::Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    let (days, hours, minutes) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60);
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{}s", seconds),
+        (0, 0, m) => format!("{}m {}s", m, seconds % 60),
+        (0, h, m) => format!("{}h {}m", h, m),
+        (d, h, _) => format!("{}d {}h", d, h),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hesha_types::RecentAttestation;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[test]
+    fn test_draw_metrics() {
+        let now = Utc::now();
+        let metrics = NodeMetrics {
+            started_at: now - chrono::Duration::hours(3),
+            attestations_issued: 42,
+            proxy_collisions: 0,
+            errors: [("invalid_phone_number".to_string(), 2)].into(),
+            verification_checks: 0,
+            verification_failures: 0,
+            recent_attestations: vec![RecentAttestation {
+                jti: "6f1c2a9e-0d4b-4c1e-9f3a-2b7d8e5c4a10".to_string(),
+                proxy_number: "+99012345678".to_string(),
+                issued_at: now,
+                expires_at: now + chrono::Duration::days(30),
+            }],
+            attestation_expiry: None,
+            certificate_expires_at: Some(now + chrono::Duration::days(5) + chrono::Duration::minutes(1)),
+            jobs: Default::default(),
+        };
+        let dashboard = Dashboard {
+            name: "default".to_string(),
+            url: "http://localhost:3000".to_string(),
+            config: None,
+            metrics: Some(metrics),
+            error: None,
+            updated_at: Some(now),
+            issued_per_poll: [1, 0, 3].into(),
+            last_issued: Some(42),
+            interval: Duration::from_secs(2),
+        };
+
+        let mut terminal = Terminal::new(TestBackend::new(140, 24)).unwrap();
+        terminal.draw(|frame| dashboard.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let screen: String = buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>() + "\n")
+            .collect();
+
+        assert!(screen.contains("up 3h 0m"));
+        assert!(screen.contains("Total issued: 42"));
+        assert!(screen.contains("invalid_phone_number"));
+        assert!(screen.contains("+99012345678"));
+        assert!(screen.contains("TLS certificate"));
+        assert!(screen.contains("expires in 5d 0h"));
+
+        // Less than a week left is shown in red
+        let (row, line) = screen.lines().enumerate().find(|(_, line)| line.contains("TLS certificate")).unwrap();
+        let column = line[..line.find("TLS certificate").unwrap()].chars().count();
+        assert_eq!(buffer[(column as u16, row as u16)].fg, Color::Red);
+    }
+}
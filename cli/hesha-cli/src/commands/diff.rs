@@ -0,0 +1,201 @@
+//! Attestation comparison command.
+
+use crate::{input, output};
+use clap::Args;
+use colored::*;
+use hesha_core::attestation::jwt::{decode_header_unverified, decode_jwt_unverified};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+
+/// Compare the claims of two attestations.
+#[derive(Debug, Args)]
+pub struct DiffCmd {
+    /// Old attestation file, JWT string, or '-' for stdin
+    #[arg(value_name = "OLD")]
+    old: String,
+
+    /// New attestation file, JWT string, or '-' for stdin
+    #[arg(value_name = "NEW")]
+    new: String,
+}
+
+/// A single differing field.
+#[derive(Debug, Serialize)]
+struct Change {
+    field: String,
+    kind: ChangeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl DiffCmd {
+    pub fn execute(self) -> anyhow::Result<()> {
+        let old = input::read_attestation(&self.old)?;
+        let new = input::read_attestation(&self.new)?;
+
+        let mut changes = diff_fields("header.", &header(&old)?, &header(&new)?);
+        changes.extend(diff_fields("", &claims(&old)?, &claims(&new)?));
+
+        if output::is_json() {
+            return output::json(&serde_json::json!({
+                "identical": changes.is_empty(),
+                "changes": changes,
+            }));
+        }
+
+        if changes.is_empty() {
+            output::success("Attestations have identical claims");
+            return Ok(());
+        }
+
+        println!("{}", "Attestation Diff".cyan().bold());
+        println!("{}", "================".cyan());
+        for change in &changes {
+            println!();
+            match change.kind {
+                ChangeKind::Added => println!("{} {}", "+".green(), label(&change.field).bold()),
+                ChangeKind::Removed => println!("{} {}", "-".red(), label(&change.field).bold()),
+                ChangeKind::Changed => println!("{} {}", "~".yellow(), label(&change.field).bold()),
+            }
+            if let Some(old) = &change.old {
+                println!("    {} {}", "-".red(), format_value(&change.field, old).red());
+            }
+            if let Some(new) = &change.new {
+                println!("    {} {}", "+".green(), format_value(&change.field, new).green());
+            }
+        }
+        println!();
+        output::info(&format!("{} field(s) differ", changes.len()));
+
+        Ok(())
+    }
+}
+
+fn header(jwt: &str) -> anyhow::Result<Map<String, Value>> {
+    as_object(decode_header_unverified(jwt)?)
+}
+
+fn claims(jwt: &str) -> anyhow::Result<Map<String, Value>> {
+    as_object(decode_jwt_unverified(jwt)?)
+}
+
+fn as_object(value: Value) -> anyhow::Result<Map<String, Value>> {
+    match value {
+        Value::Object(map) => Ok(map),
+        _ => anyhow::bail!("Attestation JSON is not an object"),
+    }
+}
+
+/// Compare two JSON objects field by field.
+fn diff_fields(prefix: &str, old: &Map<String, Value>, new: &Map<String, Value>) -> Vec<Change> {
+    let fields: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let (old, new) = (old.get(field), new.get(field));
+            let kind = match (old, new) {
+                (Some(a), Some(b)) if a == b => return None,
+                (Some(_), Some(_)) => ChangeKind::Changed,
+                (None, Some(_)) => ChangeKind::Added,
+                (Some(_), None) => ChangeKind::Removed,
+                (None, None) => return None,
+            };
+            Some(Change {
+                field: format!("{}{}", prefix, field),
+                kind,
+                old: old.cloned(),
+                new: new.cloned(),
+            })
+        })
+        .collect()
+}
+
+/// Human-readable name for well-known claims.
+fn label(field: &str) -> String {
+    let name = match field {
+        "sub" => "Proxy number",
+        "iss" => "Issuer",
+        "trust_domain" => "Trust domain",
+        "user_pubkey" => "User public key",
+        "phone_hash" => "Phone hash",
+        "iat" => "Issued at",
+        "exp" => "Expires",
+        "jti" => "JWT ID",
+        "binding_proof" => "Binding proof",
+        "header.alg" => "Algorithm",
+        "header.kid" => "Issuer key ID",
+        _ => return field.to_string(),
+    };
+    format!("{} ({})", name, field)
+}
+
+/// Format a value, adding readable times for timestamps.
+fn format_value(field: &str, value: &Value) -> String {
+    match (field, value.as_i64()) {
+        ("iat" | "exp" | "nbf", Some(timestamp)) => match chrono::DateTime::from_timestamp(timestamp, 0) {
+            Some(time) => format!("{} ({})", timestamp, time),
+            None => timestamp.to_string(),
+        },
+        _ => match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn object(value: Value) -> Map<String, Value> {
+        as_object(value).unwrap()
+    }
+
+    #[test]
+    fn test_diff_fields() {
+        let old = object(json!({ "iss": "issuer.example.com", "exp": 1700000000, "aud": "app", "jti": "a" }));
+        let new = object(json!({ "iss": "issuer.example.com", "exp": 1800000000, "jti": "a", "trust_domain": "example.com" }));
+
+        let changes = diff_fields("", &old, &new);
+        let summary: Vec<(&str, ChangeKind)> = changes.iter().map(|c| (c.field.as_str(), c.kind)).collect();
+        assert_eq!(
+            summary,
+            [("aud", ChangeKind::Removed), ("exp", ChangeKind::Changed), ("trust_domain", ChangeKind::Added)]
+        );
+
+        assert_eq!((changes[0].old.clone(), changes[0].new.clone()), (Some(json!("app")), None));
+        assert_eq!((changes[1].old.clone(), changes[1].new.clone()), (Some(json!(1700000000)), Some(json!(1800000000))));
+        assert_eq!((changes[2].old.clone(), changes[2].new.clone()), (None, Some(json!("example.com"))));
+    }
+
+    #[test]
+    fn test_diff_fields_identical_and_prefixed() {
+        let header = object(json!({ "alg": "EdDSA", "kid": "key-1" }));
+        assert!(diff_fields("header.", &header, &header).is_empty());
+        assert!(diff_fields("", &Map::new(), &Map::new()).is_empty());
+
+        let rotated = object(json!({ "alg": "EdDSA", "kid": "key-2" }));
+        let changes = diff_fields("header.", &header, &rotated);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "header.kid");
+        assert_eq!(label(&changes[0].field), "Issuer key ID (header.kid)");
+
+        // Nested values compare as a whole
+        let old = object(json!({ "ext": { "verification_method": "sms" } }));
+        let new = object(json!({ "ext": { "verification_method": "email" } }));
+        assert_eq!(diff_fields("", &old, &new)[0].kind, ChangeKind::Changed);
+    }
+}
@@ -0,0 +1,75 @@
+//! Command for regenerating an issuer's well-known documents.
+
+use clap::Args;
+use hesha_core::export_issuer_info;
+use hesha_types::attestation::ServiceDiscovery;
+use crate::output;
+use std::path::PathBuf;
+
+/// Regenerate pubkey.json and jwks.json from the local issuer config and keys.
+#[derive(Debug, Args)]
+pub struct ExportIssuerInfoCmd {
+    /// Issuer directory containing config/ and keys/.
+    /// If not specified, defaults to ~/.hesha/issuer/<name>
+    #[arg(short, long)]
+    output_dir: Option<PathBuf>,
+    
+    /// Name of the issuer configuration. Defaults to 'default'
+    #[arg(short, long, default_value = "default")]
+    name: String,
+    
+    /// Directory to write the documents to.
+    /// Defaults to <issuer dir>/public/.well-known/hesha
+    #[arg(long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+    
+    /// Service URL to delegate to from the trust domain (adds service_info)
+    #[arg(long, value_name = "URL")]
+    service_url: Option<String>,
+    
+    /// Trust relationship between the trust domain and the service
+    #[arg(long, value_name = "TYPE", default_value = "subdomain", requires = "service_url")]
+    relationship: String,
+}
+
+impl ExportIssuerInfoCmd {
+    pub fn execute(self) -> anyhow::Result<()> {
+        let issuer_dir = self.output_dir.unwrap_or_else(|| {
+            let home = dirs::home_dir().expect("Could not find home directory");
+            home.join(".hesha").join("issuer").join(&self.name)
+        });
+        let out_dir = self.out_dir
+            .unwrap_or_else(|| issuer_dir.join("public").join(".well-known").join("hesha"));
+        
+        let service_info = self.service_url.map(|service_url| ServiceDiscovery {
+            service_url,
+            relationship: self.relationship,
+            metadata: None,
+        });
+        
+        let export = export_issuer_info(&issuer_dir, &out_dir, service_info.as_ref())?;
+        let trust_domain = &export.config.identity.trust_domain;
+        
+        if output::is_json() {
+            return output::json(&serde_json::json!({
+                "key_id": export.config.identity.key_id,
+                "trust_domain": trust_domain,
+                "pubkey_file": export.pubkey_path,
+                "jwks_file": export.jwks_path,
+                "service_info": service_info,
+            }));
+        }
+        
+        output::success(&format!("Wrote {}", export.pubkey_path.display()));
+        output::success(&format!("Wrote {}", export.jwks_path.display()));
+        output::text(&format!("\nCurrent key: {}", export.config.identity.key_id));
+        if !export.config.previous_keys.is_empty() {
+            output::text(&format!("Previous keys: {}", export.config.previous_keys.len()));
+        }
+        output::text("\nDeploy them at:");
+        output::text(&format!("  https://{}/.well-known/hesha/pubkey.json", trust_domain));
+        output::text(&format!("  https://{}/.well-known/hesha/jwks.json", trust_domain));
+        
+        Ok(())
+    }
+}
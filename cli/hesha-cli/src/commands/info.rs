@@ -46,6 +46,7 @@ pub fn execute() -> anyhow::Result<()> {
     
     println!("{}", "Environment Variables:".bold());
     println!("• {} - Default private key for attestations", "HESHA_PRIVATE_KEY".yellow());
+    println!("• {} - Passphrase for encrypted key files", "HESHA_KEY_PASSPHRASE".yellow());
     println!("• {} - Enable debug logging", "RUST_LOG=debug".yellow());
     println!();
     
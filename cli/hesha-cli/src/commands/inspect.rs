@@ -1,41 +1,209 @@
 //! Attestation inspection command.
 
+use crate::{input, output};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
 use colored::*;
+use hesha_core::attestation::jwt::{decode_header_unverified, decode_jwt_unverified};
 use hesha_core::parse_attestation_jwt;
-use std::fs;
+use hesha_types::HeshaError;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Validity periods longer than this are flagged.
+const LONG_VALIDITY_DAYS: i64 = 365;
+
+/// Result of a structural sanity check.
+#[derive(Debug, Serialize)]
+struct Check {
+    name: &'static str,
+    ok: bool,
+    detail: String,
+}
+
+/// Decoded claims of a JWT, refusing anything that isn't three base64url
+/// segments.
+fn raw_claims(jwt: &str) -> Result<String, HeshaError> {
+    let invalid = |reason: &str| HeshaError::InvalidAttestation(reason.to_string());
+    
+    let segments: Vec<&str> = jwt.split('.').collect();
+    let [header, claims, signature] = segments[..] else {
+        return Err(invalid("Not a JWT: expected three dot-separated segments"));
+    };
+    let decode = |segment: &str, name: &str| {
+        general_purpose::URL_SAFE_NO_PAD
+            .decode(segment)
+            .map_err(|_| invalid(&format!("Invalid {} encoding", name)))
+    };
+    decode(header, "header")?;
+    decode(signature, "signature")?;
+    
+    String::from_utf8(decode(claims, "claims")?).map_err(|_| invalid("Claims are not UTF-8"))
+}
 
 /// Execute inspection.
-pub fn execute(attestation: &str) -> anyhow::Result<()> {
+/// 
+/// With `raw` set only the decoded claim JSON is printed, exactly as
+/// it appears in the token.
+pub fn execute(attestation: &str, raw: bool) -> anyhow::Result<()> {
     // Load attestation (from file or direct JWT)
-    let jwt = if attestation.starts_with("eyJ") {
-        attestation.to_string()
-    } else {
-        fs::read_to_string(attestation)?
-    };
+    let jwt = input::read_attestation(attestation)?;
+    
+    if raw {
+        println!("{}", raw_claims(&jwt)?);
+        return Ok(());
+    }
+    
+    let claims: Value = decode_jwt_unverified(&jwt)?;
+    
+    let header = decode_header_unverified(&jwt)?;
     
     // Parse attestation
     let attestation = parse_attestation_jwt(&jwt)?;
     
+    let now = Utc::now();
+    let checks = checks(&header, &claims);
+    let warnings = warnings(&claims, attestation.iat, attestation.exp, now);
+    
+    if output::is_json() {
+        return output::json(&serde_json::json!({
+            "header": header,
+            "claims": claims,
+            "attestation": attestation,
+            "expires_in_seconds": (attestation.exp - now).num_seconds(),
+            "checks": checks,
+            "warnings": warnings,
+            "jwt_length": jwt.len(),
+        }));
+    }
+    
     println!("{}", "Attestation Details".cyan().bold());
     println!("{}", "===================".cyan());
     
-    println!("\n{}", "Basic Information:".yellow());
-    println!("  Issuer:         {}", attestation.iss);
-    println!("  Proxy Number:   {}", attestation.proxy_number);
+    println!("\n{}", "Header:".yellow());
+    println!("  Algorithm:      {}", header["alg"].as_str().unwrap_or("(missing)"));
+    println!("  Type:           {}", header["typ"].as_str().unwrap_or("(missing)"));
+    println!("  Key ID:         {}", header["kid"].as_str().unwrap_or("(none)"));
+    
+    println!("\n{}", "Claims:".yellow());
+    if let Some(claims) = claims.as_object() {
+        let width = claims.keys().map(String::len).max().unwrap_or(0);
+        for (name, value) in claims {
+            println!("  {:width$}  {}", name, format_claim(name, value), width = width);
+        }
+    }
+    
+    println!("\n{}", "Validity:".yellow());
     println!("  Issued At:      {}", attestation.iat);
     println!("  Expires:        {}", attestation.exp);
-    println!("  JWT ID:         {}", attestation.jti);
+    let remaining = attestation.exp - now;
+    if remaining > Duration::zero() {
+        println!("  Time Left:      {}", format_duration(remaining).green());
+    } else {
+        println!("  Time Left:      {}", format!("expired {} ago", format_duration(-remaining)).red());
+    }
+    
+    println!("\n{}", "Checks:".yellow());
+    for check in &checks {
+        let mark = if check.ok { "✓".green() } else { "✗".red() };
+        println!("  {} {:15} {}", mark, check.name, check.detail);
+    }
     
-    println!("\n{}", "Cryptographic Data:".yellow());
-    println!("  Phone Hash:     {}", attestation.phone_hash.to_hex());
-    println!("  User Public Key: {}", attestation.user_pubkey.to_base64());
-    println!("  Binding Proof:  {}", hex::encode(attestation.binding_proof.as_bytes()));
-    println!("  Salt:           {}", hex::encode(&attestation.salt));
-    println!("  Nonce:          {}", attestation.nonce);
+    if !warnings.is_empty() {
+        println!("\n{}", "Warnings:".yellow());
+        for warning in &warnings {
+            println!("  {} {}", "!".yellow(), warning);
+        }
+    }
     
     println!("\n{}", "JWT Token:".yellow());
     println!("  Length:         {} bytes", jwt.len());
     println!("  First 50 chars: {}...", &jwt[..50.min(jwt.len())]);
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Structural checks on the header and claim formats.
+fn checks(header: &Value, claims: &Value) -> Vec<Check> {
+    let alg = header["alg"].as_str().unwrap_or_default();
+    
+    let binding_proof = claims["binding_proof"].as_str().unwrap_or_default();
+    let proof_len = binding_proof
+        .strip_prefix("sig:")
+        .and_then(|sig| general_purpose::URL_SAFE_NO_PAD.decode(sig).ok())
+        .map(|bytes| bytes.len());
+    
+    let phone_hash = claims["phone_hash"].as_str().unwrap_or_default();
+    let hash_ok = phone_hash
+        .strip_prefix("sha256:")
+        .is_some_and(|hex| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()));
+    
+    vec![
+        Check {
+            name: "algorithm",
+            ok: alg == "EdDSA",
+            detail: format!("{} (expected EdDSA)", alg),
+        },
+        Check {
+            name: "binding_proof",
+            ok: proof_len == Some(64),
+            detail: match proof_len {
+                Some(64) => "sig:<64-byte Ed25519 signature>".to_string(),
+                Some(n) => format!("signature is {} bytes, expected 64", n),
+                None => "expected sig:<base64url>".to_string(),
+            },
+        },
+        Check {
+            name: "phone_hash",
+            ok: hash_ok,
+            detail: if hash_ok { "sha256:<hex>".to_string() } else { "expected sha256:<64 hex chars>".to_string() },
+        },
+    ]
+}
+
+/// Non-fatal issues worth pointing out.
+fn warnings(claims: &Value, iat: DateTime<Utc>, exp: DateTime<Utc>, now: DateTime<Utc>) -> Vec<String> {
+    let mut warnings = Vec::new();
+    
+    if exp <= now {
+        warnings.push("Attestation has expired".to_string());
+    }
+    if iat > now {
+        warnings.push(format!("Issued in the future ({})", iat));
+    }
+    let validity = exp - iat;
+    if validity > Duration::days(LONG_VALIDITY_DAYS) {
+        warnings.push(format!("Very long validity period ({} days)", validity.num_days()));
+    }
+    if claims.get("trust_domain").is_none() {
+        warnings.push("No trust_domain claim; the key is discovered from the issuer domain".to_string());
+    }
+    
+    warnings
+}
+
+/// Format a claim value, adding readable times for timestamps.
+fn format_claim(name: &str, value: &Value) -> String {
+    match (name, value.as_i64()) {
+        ("iat" | "exp" | "nbf", Some(timestamp)) => match DateTime::from_timestamp(timestamp, 0) {
+            Some(time) => format!("{} ({})", timestamp, time),
+            None => timestamp.to_string(),
+        },
+        _ => match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        },
+    }
+}
+
+/// Format a duration as days/hours/minutes.
+fn format_duration(duration: Duration) -> String {
+    let (days, hours, minutes) = (duration.num_days(), duration.num_hours() % 24, duration.num_minutes() % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
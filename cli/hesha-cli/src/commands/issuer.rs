@@ -0,0 +1,206 @@
+//! Commands for the issuer instances configured on this machine.
+
+use clap::{Args, Subcommand};
+use colored::*;
+use hesha_crypto::public_key_fingerprint;
+use hesha_types::{IssuerConfig, PublicKey};
+use crate::commands::start::{check_pid_file, PidFileState};
+use crate::config::config_dir;
+use crate::output;
+use serde::Serialize;
+use std::{fs, path::{Path, PathBuf}};
+
+/// Manage the local issuer instances under ~/.hesha/issuer.
+#[derive(Debug, Args)]
+pub struct IssuerCmd {
+    #[command(subcommand)]
+    command: IssuerCommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum IssuerCommands {
+    /// List configured issuers with their trust domain, port, key and state
+    List,
+}
+
+impl IssuerCmd {
+    pub fn execute(self) -> anyhow::Result<()> {
+        match self.command {
+            IssuerCommands::List => list(),
+        }
+    }
+}
+
+/// Summary of one issuer directory.
+#[derive(Debug, Serialize)]
+struct IssuerSummary {
+    name: String,
+    trust_domain: Option<String>,
+    port: Option<u16>,
+    key_id: Option<String>,
+    fingerprint: Option<String>,
+    running: bool,
+    pid: Option<u32>,
+    config_dir: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn list() -> anyhow::Result<()> {
+    let issuers = list_issuers(&config_dir().join("issuer"))?;
+    
+    if output::is_json() {
+        return output::json(&issuers);
+    }
+    
+    if issuers.is_empty() {
+        output::info("No issuers configured. Run 'hesha setup' to create one");
+        return Ok(());
+    }
+    
+    println!("{}", "Issuers".bold());
+    println!("{}", "═══════".bold());
+    for issuer in issuers {
+        let state = match (issuer.running, issuer.pid) {
+            (true, Some(pid)) => format!("{} (PID {})", "running".green().bold(), pid),
+            _ => "stopped".red().bold().to_string(),
+        };
+        let unknown = || "unknown".to_string();
+        
+        println!();
+        println!("{}  {}", issuer.name.cyan().bold(), state);
+        if let Some(error) = &issuer.error {
+            println!("  Error:        {}", error.red());
+        }
+        println!("  Trust domain: {}", issuer.trust_domain.unwrap_or_else(unknown));
+        println!("  Port:         {}", issuer.port.map(|port| port.to_string()).unwrap_or_else(unknown));
+        println!("  Key ID:       {}", issuer.key_id.unwrap_or_else(unknown));
+        println!("  Fingerprint:  {}", issuer.fingerprint.unwrap_or_else(unknown));
+        println!("  Directory:    {}", issuer.config_dir.display().to_string().dimmed());
+    }
+    
+    Ok(())
+}
+
+/// Summaries of the issuer directories under `issuers_dir`, by name.
+fn list_issuers(issuers_dir: &Path) -> anyhow::Result<Vec<IssuerSummary>> {
+    let mut dirs = Vec::new();
+    if issuers_dir.is_dir() {
+        for entry in fs::read_dir(issuers_dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            }
+        }
+    }
+    dirs.sort();
+    
+    Ok(dirs.into_iter().map(summarize).collect())
+}
+
+/// Read what can be read from an issuer directory; a broken config is
+/// reported rather than hiding the issuer.
+fn summarize(dir: PathBuf) -> IssuerSummary {
+    let name = dir.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let (running, pid) = match check_pid_file(&dir.join("issuer.pid")) {
+        PidFileState::Running(pid) => (true, Some(pid)),
+        PidFileState::Stale(_) | PidFileState::Missing => (false, None),
+    };
+    
+    let mut summary = IssuerSummary {
+        name,
+        trust_domain: None,
+        port: None,
+        key_id: None,
+        fingerprint: None,
+        running,
+        pid,
+        config_dir: dir.clone(),
+        error: None,
+    };
+    
+    match IssuerConfig::from_file(dir.join("config").join("issuer.toml")) {
+        Ok(config) => {
+            summary.fingerprint = PublicKey::from_base64(&config.identity.public_key_base64url)
+                .ok()
+                .map(|key| public_key_fingerprint(&key));
+            summary.trust_domain = Some(config.identity.trust_domain);
+            summary.port = Some(config.port);
+            summary.key_id = Some(config.identity.key_id);
+        }
+        Err(e) => {
+            summary.error = Some(format!(
+                "Failed to read config: {} (try 'hesha config validate -n {}' or 'hesha migrate')",
+                e, summary.name
+            ));
+        }
+    }
+    
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hesha_core::IssuerSetupBuilder;
+    
+    fn save_issuer(dir: &Path, domain: &str, port: u16) -> IssuerConfig {
+        let setup = IssuerSetupBuilder::new()
+            .name("Test Issuer")
+            .trust_domain(domain)
+            .contact_email("admin@example.com")
+            .port(port)
+            .build()
+            .unwrap();
+        setup.save(dir).unwrap();
+        setup.config
+    }
+    
+    #[test]
+    fn test_list_issuers() {
+        let temp = tempfile::tempdir().unwrap();
+        let config = save_issuer(&temp.path().join("beta"), "beta.example.com", 3001);
+        save_issuer(&temp.path().join("alpha"), "alpha.example.com", 3000);
+        fs::write(temp.path().join("notes.txt"), "not an issuer").unwrap();
+        
+        let issuers = list_issuers(temp.path()).unwrap();
+        let names: Vec<&str> = issuers.iter().map(|issuer| issuer.name.as_str()).collect();
+        assert_eq!(names, ["alpha", "beta"]);
+        
+        let beta = &issuers[1];
+        assert_eq!(beta.trust_domain.as_deref(), Some("beta.example.com"));
+        assert_eq!(beta.port, Some(3001));
+        assert_eq!(beta.key_id.as_deref(), Some("default"));
+        let key = PublicKey::from_base64(&config.identity.public_key_base64url).unwrap();
+        assert_eq!(beta.fingerprint, Some(public_key_fingerprint(&key)));
+        assert!(!beta.running);
+        assert_eq!(beta.pid, None);
+        assert_eq!(beta.config_dir, temp.path().join("beta"));
+        assert!(beta.error.is_none());
+    }
+    
+    #[test]
+    fn test_list_reports_broken_issuers() {
+        let temp = tempfile::tempdir().unwrap();
+        let broken = temp.path().join("broken");
+        fs::create_dir_all(broken.join("config")).unwrap();
+        fs::write(broken.join("config").join("issuer.toml"), "port = ").unwrap();
+        fs::write(broken.join("issuer.pid"), "not a pid").unwrap();
+        fs::create_dir(temp.path().join("empty")).unwrap();
+        
+        let issuers = list_issuers(temp.path()).unwrap();
+        assert_eq!(issuers.len(), 2);
+        for issuer in &issuers {
+            assert!(issuer.error.as_deref().unwrap().contains(&format!("-n {}", issuer.name)));
+            assert!(issuer.trust_domain.is_none());
+            assert!(!issuer.running);
+        }
+    }
+    
+    #[test]
+    fn test_list_without_issuers() {
+        let temp = tempfile::tempdir().unwrap();
+        assert!(list_issuers(temp.path()).unwrap().is_empty());
+        assert!(list_issuers(&temp.path().join("missing")).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+//! Key management commands for the ~/.hesha key store.
+
+use crate::config::{load_keypair_from_string, read_passphrase};
+use crate::keystore::{KeyStore, DEFAULT_KEY_NAME};
+use crate::{input, output};
+use clap::{Args, Subcommand};
+use colored::*;
+use hesha_crypto::{generate_keypair, public_key_fingerprint};
+use hesha_types::PublicKey;
+
+/// Manage named keys in the local key store.
+#[derive(Debug, Args)]
+pub struct KeyCmd {
+    #[command(subcommand)]
+    command: KeyCommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum KeyCommands {
+    /// List stored keys
+    List,
+
+    /// Import a key file into the store
+    Import {
+        /// Name to store the key under
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Key file (JSON, encrypted JSON or base64), or '-' for stdin
+        #[arg(value_name = "FILE")]
+        file: String,
+
+        /// Encrypt the stored key with a passphrase
+        #[arg(long)]
+        encrypt: bool,
+
+        /// Overwrite an existing key with the same name
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Print a stored key file
+    Export {
+        /// Key name
+        #[arg(value_name = "NAME", default_value = DEFAULT_KEY_NAME)]
+        name: String,
+
+        /// Print only the public key
+        #[arg(long)]
+        public: bool,
+    },
+
+    /// Replace a key with a newly generated one, archiving the old key
+    Rotate {
+        /// Key name
+        #[arg(value_name = "NAME", default_value = DEFAULT_KEY_NAME)]
+        name: String,
+
+        /// Encrypt the new key with a passphrase
+        #[arg(long)]
+        encrypt: bool,
+    },
+
+    /// Show the fingerprint of a stored key or key file
+    Fingerprint {
+        /// Key name or key file
+        #[arg(value_name = "NAME_OR_FILE", default_value = DEFAULT_KEY_NAME)]
+        key: String,
+    },
+}
+
+impl KeyCmd {
+    pub fn execute(self) -> anyhow::Result<()> {
+        let store = KeyStore::open_default();
+
+        match self.command {
+            KeyCommands::List => list(&store),
+            KeyCommands::Import { name, file, encrypt, force } => {
+                import(&store, &name, &file, encrypt, force)
+            }
+            KeyCommands::Export { name, public } => export(&store, &name, public),
+            KeyCommands::Rotate { name, encrypt } => rotate(&store, &name, encrypt),
+            KeyCommands::Fingerprint { key } => fingerprint(&store, &key),
+        }
+    }
+}
+
+fn list(store: &KeyStore) -> anyhow::Result<()> {
+    let keys = store.list()?;
+    if keys.is_empty() {
+        output::info("No keys stored. Run 'hesha key rotate' to create one");
+        return Ok(());
+    }
+
+    println!("{}", "Stored Keys".bold());
+    println!("{}", "═══════════".bold());
+    for key in keys {
+        let created = key
+            .created_at
+            .map(|dt| dt.format("%Y-%m-%d %H:%M UTC").to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let encrypted = if key.encrypted { " (encrypted)".dimmed().to_string() } else { String::new() };
+
+        println!();
+        println!("{}{}", key.name.cyan().bold(), encrypted);
+        println!("  Created:     {}", created);
+        println!("  Fingerprint: {}", public_key_fingerprint(&key.public_key));
+        println!("  File:        {}", key.path.display().to_string().dimmed());
+    }
+
+    Ok(())
+}
+
+fn import(store: &KeyStore, name: &str, file: &str, encrypt: bool, force: bool) -> anyhow::Result<()> {
+    if store.exists(name) && !force {
+        anyhow::bail!("Key '{}' already exists. Use --force to overwrite or 'hesha key rotate'", name);
+    }
+
+    let data = input::read_input(file)?;
+    let keypair = load_keypair_from_string(&data)?;
+    let passphrase = if encrypt { Some(read_passphrase(true)?) } else { None };
+
+    let path = store.save(name, &keypair, passphrase.as_deref())?;
+    output::success(&format!("Imported key '{}' to {}", name, path.display()));
+    println!("  Fingerprint: {}", public_key_fingerprint(&keypair.public));
+
+    Ok(())
+}
+
+fn export(store: &KeyStore, name: &str, public: bool) -> anyhow::Result<()> {
+    if public {
+        println!("{}", store.get(name)?.public_key.to_base64());
+    } else {
+        print!("{}", store.read(name)?);
+    }
+    Ok(())
+}
+
+fn rotate(store: &KeyStore, name: &str, encrypt: bool) -> anyhow::Result<()> {
+    let passphrase = if encrypt { Some(read_passphrase(true)?) } else { None };
+    let keypair = generate_keypair()?;
+
+    if store.exists(name) {
+        let archived = store.archive(name)?;
+        output::info(&format!("Archived previous key to {}", archived.display()));
+    }
+
+    let path = store.save(name, &keypair, passphrase.as_deref())?;
+    output::success(&format!("Generated key '{}' at {}", name, path.display()));
+    println!("  Public key:  {}", keypair.public.to_base64());
+    println!("  Fingerprint: {}", public_key_fingerprint(&keypair.public));
+
+    Ok(())
+}
+
+fn fingerprint(store: &KeyStore, key: &str) -> anyhow::Result<()> {
+    let public_key = if store.exists(key) {
+        store.get(key)?.public_key
+    } else {
+        let data = input::read_input(key)
+            .map_err(|_| anyhow::anyhow!("'{}' is neither a stored key nor a readable file", key))?;
+        public_key_from_file(&data)?
+    };
+
+    println!("{}", public_key_fingerprint(&public_key));
+    Ok(())
+}
+
+/// Get the public key from key file contents without decrypting if possible.
+fn public_key_from_file(data: &str) -> anyhow::Result<PublicKey> {
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(data) {
+        if let Some(public_key) = json["public_key"].as_str() {
+            return Ok(PublicKey::from_base64(public_key)?);
+        }
+    }
+
+    Ok(load_keypair_from_string(data)?.public)
+}
@@ -1,12 +1,100 @@
 //! Key generation command.
 
-use crate::output;
-use hesha_crypto::generate_keypair;
+use crate::{config, input, os_keyring, output};
+use colored::*;
+use dialoguer::Password;
+use hesha_crypto::{encrypt_keypair, generate_keypair, keypair_from_mnemonic};
 use serde_json::json;
 
+/// Environment variable holding the backup phrase for `--from-mnemonic`.
+const MNEMONIC_ENV: &str = "HESHA_MNEMONIC";
+
+/// Environment variable holding the optional BIP-39 passphrase.
+const MNEMONIC_PASSPHRASE_ENV: &str = "HESHA_MNEMONIC_PASSPHRASE";
+
+/// Where a generated key is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum KeyDestination {
+    /// Print the key.
+    Stdout,
+    /// Save the private key in the OS keyring.
+    Keyring,
+}
+
 /// Execute keygen command.
-pub fn execute(format: &str) -> anyhow::Result<()> {
-    let keypair = generate_keypair()?;
+/// 
+/// With `derive_path` the key is restored from a backup phrase along that
+/// path instead of being generated. A key already in the OS keyring under
+/// `name` is only replaced with `force`.
+pub fn execute(
+    format: &str,
+    encrypt: bool,
+    store: KeyDestination,
+    name: &str,
+    force: bool,
+    derive_path: Option<&str>,
+) -> anyhow::Result<()> {
+    if store == KeyDestination::Keyring && !force && os_keyring::exists(name)? {
+        anyhow::bail!("Key '{}' already exists in the OS keyring. Use --force to overwrite", name);
+    }
+    
+    let keypair = match derive_path {
+        Some(path) => {
+            let phrase = read_mnemonic()?;
+            let passphrase = std::env::var(MNEMONIC_PASSPHRASE_ENV).unwrap_or_default();
+            keypair_from_mnemonic(&phrase, &passphrase, path)?
+        }
+        None => generate_keypair()?,
+    };
+    
+    if store == KeyDestination::Keyring {
+        os_keyring::save(name, &keypair)?;
+        
+        if output::is_json() {
+            return output::json(&json!({
+                "key": format!("{}{}", os_keyring::KEYRING_PREFIX, name),
+                "public_key": keypair.public.to_base64(),
+            }));
+        }
+        
+        output::success(&format!("Saved private key '{}' to the OS keyring", name));
+        println!("Public key: {}", keypair.public.to_base64());
+        println!("\nUse it with: --key {}{}", os_keyring::KEYRING_PREFIX, name);
+        return Ok(());
+    }
+    
+    if encrypt {
+        if format != "json" {
+            anyhow::bail!("Encrypted keys are only available in json format");
+        }
+        
+        let passphrase = config::read_passphrase(true)?;
+        let encrypted = encrypt_keypair(&keypair, &passphrase)?;
+        println!("{}", encrypted.to_json()?);
+        if output::is_json() {
+            return Ok(());
+        }
+        
+        // Status goes to stderr so stdout can be redirected to a key file
+        eprintln!();
+        eprintln!("{} Encrypted keys generated successfully!", "✓".green());
+        output::warning("The passphrase cannot be recovered. Keep it safe.");
+        return Ok(());
+    }
+    
+    if output::is_json() {
+        // Same structure for every encoding so scripts don't need to parse text
+        let (private_key, public_key) = match format {
+            "json" | "base64" => (keypair.private.to_base64(), keypair.public.to_base64()),
+            "hex" => (hex::encode(keypair.private.as_bytes()), hex::encode(keypair.public.as_bytes())),
+            _ => anyhow::bail!("Unknown format: {}. Use json, hex, or base64", format),
+        };
+        return output::json(&json!({
+            "encoding": if format == "hex" { "hex" } else { "base64url" },
+            "private_key": private_key,
+            "public_key": public_key,
+        }));
+    }
     
     match format {
         "json" => {
@@ -30,8 +118,23 @@ pub fn execute(format: &str) -> anyhow::Result<()> {
     }
     
     eprintln!();
-    output::success("Keys generated successfully!");
+    eprintln!("{} Keys generated successfully!", "✓".green());
     output::warning("Store your private key securely.");
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Get the backup phrase from the environment, stdin or a prompt.
+/// 
+/// Never taken as an argument, so it doesn't end up in shell history.
+fn read_mnemonic() -> anyhow::Result<String> {
+    if let Ok(phrase) = std::env::var(MNEMONIC_ENV) {
+        return Ok(phrase);
+    }
+    
+    if input::is_interactive() {
+        return Ok(Password::new().with_prompt("Backup phrase").interact()?);
+    }
+    
+    input::read_input("-")
+}
@@ -0,0 +1,55 @@
+//! Man page generation command.
+
+use clap::Args;
+use crate::output;
+use std::{fs, io, path::{Path, PathBuf}};
+
+/// Generate roff man pages for the CLI.
+#[derive(Debug, Args)]
+pub struct ManCmd {
+    /// Directory to write one page per command into.
+    /// If not specified, the top-level page is written to stdout
+    #[arg(short = 'd', long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+}
+
+impl ManCmd {
+    pub fn execute(self, command: clap::Command) -> anyhow::Result<()> {
+        let Some(out_dir) = self.out_dir else {
+            clap_mangen::Man::new(command).render(&mut io::stdout())?;
+            return Ok(());
+        };
+
+        fs::create_dir_all(&out_dir)?;
+        let mut written = Vec::new();
+        write_pages(command, &out_dir, &mut written)?;
+
+        if output::is_json() {
+            return output::json(&serde_json::json!({ "pages": written }));
+        }
+
+        output::success(&format!("Wrote {} man pages to {}", written.len(), out_dir.display()));
+        Ok(())
+    }
+}
+
+/// Write the page for a command and, recursively, its subcommands.
+///
+/// Subcommand pages are named after their full path, e.g. `hesha-key-list.1`.
+fn write_pages(command: clap::Command, dir: &Path, written: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let name = command.get_display_name().unwrap_or_else(|| command.get_name()).to_string();
+
+    for sub in command.get_subcommands().filter(|sub| !sub.is_hide_set() && sub.get_name() != "help") {
+        let sub_name = format!("{}-{}", name, sub.get_name());
+        let sub = sub.clone().display_name(sub_name).version(env!("CARGO_PKG_VERSION"));
+        write_pages(sub, dir, written)?;
+    }
+
+    let man = clap_mangen::Man::new(command);
+    let path = dir.join(man.get_filename());
+    let mut file = fs::File::create(&path)?;
+    man.render(&mut file)?;
+    written.push(path);
+
+    Ok(())
+}
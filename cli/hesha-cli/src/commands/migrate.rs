@@ -0,0 +1,109 @@
+//! Upgrade ~/.hesha from layouts written by older releases.
+
+use anyhow::Context;
+use chrono::Utc;
+use clap::Args;
+use crate::config::{config_dir, load_keypair_from_string};
+use crate::keystore::{KeyStore, DEFAULT_KEY_NAME};
+use crate::output;
+use serde_json::json;
+use std::{fs, path::Path};
+
+/// Upgrade keys to the current format.
+#[derive(Debug, Args)]
+pub struct MigrateCmd {
+    /// Show what would change without writing anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl MigrateCmd {
+    pub fn execute(self) -> anyhow::Result<()> {
+        let root = config_dir();
+        let backup_dir = root
+            .join("backups")
+            .join(format!("migrate-{}", Utc::now().format("%Y%m%dT%H%M%SZ")));
+        
+        let mut changes = Vec::new();
+        
+        for change in migrate_legacy_key(&root, &backup_dir, self.dry_run)? {
+            changes.push(json!({ "path": root.join("key.json"), "change": change }));
+        }
+        
+        let backup = (!self.dry_run && !changes.is_empty()).then_some(&backup_dir);
+        if output::is_json() {
+            output::json(&json!({
+                "dry_run": self.dry_run,
+                "changes": changes,
+                "backup_dir": backup,
+            }))?;
+            return Ok(());
+        }
+        
+        if changes.is_empty() {
+            output::success(&format!("{} is already up to date", root.display()));
+            return Ok(());
+        }
+        
+        let mut current = None;
+        for change in &changes {
+            let path = change["path"].as_str().unwrap_or_default();
+            if current != Some(path) {
+                output::text(&format!("{}:", path));
+                current = Some(path);
+            }
+            output::text(&format!("  - {}", change["change"].as_str().unwrap_or_default()));
+        }
+        
+        match backup {
+            Some(backup_dir) => {
+                output::success(&format!("Applied {} change(s)", changes.len()));
+                output::info(&format!("Originals backed up to {}", backup_dir.display()));
+            }
+            None => output::info("Dry run: nothing was changed"),
+        }
+        
+        Ok(())
+    }
+}
+
+/// Move the single ~/.hesha/key.json used by early releases into the key
+/// store as the default key.
+fn migrate_legacy_key(root: &Path, backup_dir: &Path, dry_run: bool) -> anyhow::Result<Vec<String>> {
+    let legacy_path = root.join("key.json");
+    if !legacy_path.is_file() {
+        return Ok(Vec::new());
+    }
+    
+    let store = KeyStore::new(root.join("keys"));
+    if store.exists(DEFAULT_KEY_NAME) {
+        output::warning(&format!(
+            "Both {} and a '{}' key exist; leaving key.json in place",
+            legacy_path.display(),
+            DEFAULT_KEY_NAME
+        ));
+        return Ok(Vec::new());
+    }
+    
+    let change = format!("Moved key.json to keys/{}.json", DEFAULT_KEY_NAME);
+    if dry_run {
+        return Ok(vec![change]);
+    }
+    
+    let data = fs::read_to_string(&legacy_path)
+        .with_context(|| format!("Failed to read {}", legacy_path.display()))?;
+    
+    fs::create_dir_all(backup_dir)?;
+    fs::copy(&legacy_path, backup_dir.join("key.json"))
+        .with_context(|| format!("Failed to back up {}", legacy_path.display()))?;
+    
+    // JSON key files (plain or encrypted) move as is; bare keys are wrapped
+    if data.trim().starts_with('{') {
+        store.save_document(DEFAULT_KEY_NAME, &data)?;
+    } else {
+        store.save(DEFAULT_KEY_NAME, &load_keypair_from_string(&data)?, None)?;
+    }
+    fs::remove_file(&legacy_path)?;
+    
+    Ok(vec![change])
+}
@@ -1,10 +1,29 @@
 //! CLI commands.
 
+pub mod admin;
 pub mod attest;
+pub mod audit;
+pub mod backup;
+pub mod batch;
+pub mod ceremony;
+pub mod config;
+pub mod dashboard;
+pub mod diff;
+pub mod export_issuer_info;
 pub mod info;
 pub mod inspect;
+pub mod issuer;
+pub mod key;
 pub mod keygen;
+pub mod man;
+pub mod migrate;
+#[cfg(feature = "qr")]
+pub mod qr;
+pub mod restart;
+pub mod restore;
 pub mod setup_issuer;
 pub mod start;
+pub mod status;
 pub mod stop;
-pub mod verify;
\ No newline at end of file
+pub mod verify;
+pub mod wallet;
\ No newline at end of file
@@ -0,0 +1,326 @@
+//! QR code command for presenting attestations in person.
+//!
+//! The payload is the attestation in compact form: the decoded JWT header,
+//! claims and signature, deflated and Base45-encoded (RFC 9285) after a
+//! `HESHA1:` prefix. Base45 keeps to the QR alphanumeric character set, so
+//! the code is noticeably smaller than one holding the JWT text, and any
+//! scanner app returns the payload as plain text.
+
+use anyhow::Context;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use clap::Args;
+use crate::{commands, input, output};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use qrcode::{render::unicode, EcLevel, QrCode};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+mod scan;
+
+/// Prefix marking a compact QR payload, and its format version.
+const PAYLOAD_PREFIX: &str = "HESHA1:";
+
+/// Prefix of the JWT-text payloads written by earlier versions.
+const LEGACY_PREFIX: &str = "hesha:";
+
+/// Base45 alphabet (RFC 9285), a subset of the QR alphanumeric set.
+const BASE45: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Encode an attestation as a QR code, or decode a scanned payload.
+#[derive(Debug, Args)]
+pub struct QrCmd {
+    /// Attestation file, JWT string, or '-' for stdin to encode
+    #[arg(short, long, value_name = "FILE_OR_JWT", required_unless_present = "decode", conflicts_with = "decode")]
+    attestation: Option<String>,
+
+    /// Write the QR code to a PNG file instead of the terminal
+    #[arg(short = 'o', long = "out", value_name = "FILE")]
+    out: Option<PathBuf>,
+
+    /// Decode a QR code image (PNG or JPEG), or a scanned payload (text,
+    /// file, or '-' for stdin), and inspect the attestation it contains
+    #[arg(long, value_name = "IMAGE_OR_PAYLOAD")]
+    decode: Option<String>,
+}
+
+impl QrCmd {
+    pub fn execute(self) -> anyhow::Result<()> {
+        if let Some(arg) = self.decode {
+            let payload = if is_payload(&arg) {
+                arg
+            } else {
+                match read_image(Path::new(&arg))? {
+                    Some(payload) => payload,
+                    None => input::read_input(&arg)?,
+                }
+            };
+            let jwt = decode_payload(payload.trim())?;
+            return commands::inspect::execute(&jwt, false);
+        }
+
+        let attestation = self.attestation.expect("clap requires --attestation without --decode");
+        let jwt = input::read_attestation(&attestation)?;
+
+        // Make sure we only ever encode something that parses as an attestation
+        hesha_core::parse_attestation_jwt(&jwt)?;
+        let code = QrCode::with_error_correction_level(encode_payload(&jwt)?, EcLevel::L)?;
+
+        match self.out {
+            Some(path) => {
+                code.render::<image::Luma<u8>>()
+                    .min_dimensions(512, 512)
+                    .build()
+                    .save(&path)?;
+
+                if output::is_json() {
+                    return output::json(&serde_json::json!({ "saved_to": path }));
+                }
+                output::success(&format!("QR code saved to {}", path.display()));
+            }
+            None => {
+                let rendered = code
+                    .render::<unicode::Dense1x2>()
+                    .dark_color(unicode::Dense1x2::Light)
+                    .light_color(unicode::Dense1x2::Dark)
+                    .build();
+                println!("{}", rendered);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Check if an argument is a payload itself rather than a file holding one.
+fn is_payload(arg: &str) -> bool {
+    arg.starts_with(PAYLOAD_PREFIX) || arg.starts_with(LEGACY_PREFIX) || arg.starts_with("eyJ")
+}
+
+/// Payload of the attestation QR code in an image file.
+///
+/// Returns `None` if the file is not an image, so it can be read as a
+/// text payload instead.
+fn read_image(path: &Path) -> anyhow::Result<Option<String>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let reader = image::ImageReader::open(path)?.with_guessed_format()?;
+    if reader.format().is_none() {
+        return Ok(None);
+    }
+    let image = reader
+        .decode()
+        .with_context(|| format!("Failed to read image {}", path.display()))?
+        .to_luma8();
+
+    let content = scan::scan(&image)
+        .with_context(|| format!("No readable QR code found in {}", path.display()))?;
+    decode_payload(&content)
+        .with_context(|| format!("The QR code in {} does not hold a Hesha attestation", path.display()))?;
+    Ok(Some(content))
+}
+
+/// Compact QR payload for an attestation JWT.
+fn encode_payload(jwt: &str) -> anyhow::Result<String> {
+    let segments: Vec<&str> = jwt.split('.').collect();
+    let [header, claims, signature] = segments[..] else {
+        anyhow::bail!("Attestation is not a JWT with three segments");
+    };
+
+    let mut raw = Vec::new();
+    for segment in [header, claims] {
+        let bytes = decode_segment(segment)?;
+        let len = u16::try_from(bytes.len()).context("JWT segment too large for a QR code")?;
+        raw.extend_from_slice(&len.to_be_bytes());
+        raw.extend_from_slice(&bytes);
+    }
+    raw.extend_from_slice(&decode_segment(signature)?);
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&raw)?;
+    Ok(format!("{}{}", PAYLOAD_PREFIX, base45_encode(&encoder.finish()?)))
+}
+
+/// Recover the attestation JWT from a scanned QR payload.
+///
+/// Accepts compact payloads, the `hesha:` JWT payloads of earlier
+/// versions, and bare JWTs encoded by other tools.
+fn decode_payload(payload: &str) -> anyhow::Result<String> {
+    if let Some(jwt) = payload.strip_prefix(LEGACY_PREFIX) {
+        return Ok(jwt.to_string());
+    }
+    if payload.starts_with("eyJ") {
+        return Ok(payload.to_string());
+    }
+    let Some(encoded) = payload.strip_prefix(PAYLOAD_PREFIX) else {
+        anyhow::bail!("Not a Hesha attestation QR payload");
+    };
+
+    let compressed = base45_decode(encoded)?;
+    let mut raw = Vec::new();
+    DeflateDecoder::new(compressed.as_slice())
+        .read_to_end(&mut raw)
+        .context("Corrupt QR payload")?;
+
+    let mut rest = raw.as_slice();
+    let mut segments = Vec::with_capacity(3);
+    for _ in 0..2 {
+        anyhow::ensure!(rest.len() >= 2, "Truncated QR payload");
+        let len = usize::from(u16::from_be_bytes([rest[0], rest[1]]));
+        anyhow::ensure!(rest.len() - 2 >= len, "Truncated QR payload");
+        let (segment, tail) = rest[2..].split_at(len);
+        segments.push(URL_SAFE_NO_PAD.encode(segment));
+        rest = tail;
+    }
+    segments.push(URL_SAFE_NO_PAD.encode(rest));
+    Ok(segments.join("."))
+}
+
+/// Decode a base64url JWT segment, refusing forms that wouldn't
+/// re-encode to the same text (and so wouldn't verify after a round trip).
+fn decode_segment(segment: &str) -> anyhow::Result<Vec<u8>> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(segment)
+        .context("Attestation segment is not base64url")?;
+    anyhow::ensure!(URL_SAFE_NO_PAD.encode(&bytes) == segment, "Attestation segment is not canonical base64url");
+    Ok(bytes)
+}
+
+/// Base45-encode bytes (RFC 9285).
+fn base45_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len() / 2 * 3 + 2);
+    for chunk in data.chunks(2) {
+        let (mut n, digits) = match *chunk {
+            [a, b] => (usize::from(a) * 256 + usize::from(b), 3),
+            [a] => (usize::from(a), 2),
+            _ => unreachable!("chunks of at most two"),
+        };
+        for _ in 0..digits {
+            out.push(char::from(BASE45[n % 45]));
+            n /= 45;
+        }
+    }
+    out
+}
+
+/// Decode Base45 text (RFC 9285).
+fn base45_decode(text: &str) -> anyhow::Result<Vec<u8>> {
+    let values = text
+        .bytes()
+        .map(|c| BASE45.iter().position(|&b| b == c))
+        .collect::<Option<Vec<usize>>>()
+        .context("Invalid character in QR payload")?;
+
+    let mut out = Vec::with_capacity(values.len() / 3 * 2 + 1);
+    for chunk in values.chunks(3) {
+        match *chunk {
+            [c, d, e] => {
+                let n = c + d * 45 + e * 45 * 45;
+                anyhow::ensure!(n <= 0xffff, "Invalid Base45 in QR payload");
+                out.extend_from_slice(&(n as u16).to_be_bytes());
+            }
+            [c, d] => {
+                let n = c + d * 45;
+                anyhow::ensure!(n <= 0xff, "Invalid Base45 in QR payload");
+                out.push(n as u8);
+            }
+            _ => anyhow::bail!("Truncated QR payload"),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hesha_crypto::generate_keypair;
+    use hesha_types::{PhoneNumber, ProxyNumber};
+
+    fn attestation() -> String {
+        let issuer = generate_keypair().unwrap();
+        let user = generate_keypair().unwrap();
+        hesha_core::create_attestation(
+            "issuer.example.com",
+            &issuer.private,
+            &PhoneNumber::new("+14155552671").unwrap(),
+            &ProxyNumber::new("+10015551234567").unwrap(),
+            &user.public,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_base45_rfc_vectors() {
+        for (bytes, text) in [(&b"AB"[..], "BB8"), (b"Hello!!", "%69 VD92EX0"), (b"ietf!", "QED8WEX0")] {
+            assert_eq!(base45_encode(bytes), text);
+            assert_eq!(base45_decode(text).unwrap(), bytes);
+        }
+        assert!(base45_decode("GGW").is_err());
+        assert!(base45_decode("abc").is_err());
+        assert!(base45_decode("A").is_err());
+    }
+
+    #[test]
+    fn test_payload_round_trip() {
+        let jwt = attestation();
+        let payload = encode_payload(&jwt).unwrap();
+        assert!(payload.starts_with(PAYLOAD_PREFIX));
+        assert!(payload.bytes().all(|c| BASE45.contains(&c) || PAYLOAD_PREFIX.as_bytes().contains(&c)));
+        assert_eq!(decode_payload(&payload).unwrap(), jwt);
+        hesha_core::parse_attestation_jwt(&decode_payload(&payload).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_compact_code_is_smaller() {
+        let jwt = attestation();
+        let compact = QrCode::with_error_correction_level(encode_payload(&jwt).unwrap(), EcLevel::L).unwrap();
+        let legacy = QrCode::with_error_correction_level(format!("{}{}", LEGACY_PREFIX, jwt), EcLevel::L).unwrap();
+        assert!(compact.width() < legacy.width());
+    }
+
+    #[test]
+    fn test_decode_legacy_and_bare_payloads() {
+        let jwt = attestation();
+        assert_eq!(decode_payload(&format!("{}{}", LEGACY_PREFIX, jwt)).unwrap(), jwt);
+        assert_eq!(decode_payload(&jwt).unwrap(), jwt);
+        assert!(decode_payload("https://example.com").is_err());
+    }
+
+    #[test]
+    fn test_image_round_trip() {
+        let jwt = attestation();
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("qr.png");
+        QrCode::with_error_correction_level(encode_payload(&jwt).unwrap(), EcLevel::L)
+            .unwrap()
+            .render::<image::Luma<u8>>()
+            .min_dimensions(512, 512)
+            .build()
+            .save(&path)
+            .unwrap();
+
+        let payload = read_image(&path).unwrap().unwrap();
+        assert_eq!(decode_payload(&payload).unwrap(), jwt);
+
+        // A lossy copy, as from a camera
+        let photo = temp.path().join("qr.jpg");
+        image::open(&path).unwrap().to_rgb8().save(&photo).unwrap();
+        assert_eq!(read_image(&photo).unwrap().unwrap(), payload);
+    }
+
+    #[test]
+    fn test_text_files_are_not_images() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("payload.txt");
+        std::fs::write(&path, encode_payload(&attestation()).unwrap()).unwrap();
+        assert!(read_image(&path).unwrap().is_none());
+        assert!(read_image(&temp.path().join("missing.png")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rejects_malformed_input() {
+        assert!(encode_payload("only.two").is_err());
+        assert!(encode_payload("eyJ!.eyJ.sig").is_err());
+        assert!(decode_payload("HESHA1:BB8").is_err());
+    }
+}
@@ -0,0 +1,708 @@
+//! Reading QR codes from images for `hesha qr --decode`.
+//!
+//! The three finder patterns are found by their 1:1:3:1:1 run lengths,
+//! the module grid is sampled through the transform they span, and the
+//! grid is decoded with Reed-Solomon error correction. Module layout and
+//! block sizes come from the `qrcode` encoder, so both sides agree.
+//!
+//! This reads saved codes, screenshots and photos taken square-on, turned
+//! by any quarter turn. Perspective is not corrected, so a code
+//! photographed at a steep angle may not be found.
+
+use anyhow::Context;
+use image::GrayImage;
+use qrcode::bits::Bits;
+use qrcode::canvas::{Canvas, Module};
+use qrcode::ec::construct_codewords;
+use qrcode::{EcLevel, Version};
+
+/// Format information positions around the top-left finder, most
+/// significant bit first.
+const FORMAT_MAIN: [(usize, usize); 15] = [
+    (0, 8), (1, 8), (2, 8), (3, 8), (4, 8), (5, 8), (7, 8), (8, 8),
+    (8, 7), (8, 5), (8, 4), (8, 3), (8, 2), (8, 1), (8, 0),
+];
+
+/// Mask applied to format information so it is never all light.
+const FORMAT_MASK: u16 = 0x5412;
+
+/// Finder patterns tried when looking for the three corners of a code.
+const MAX_FINDERS: usize = 6;
+
+/// Decode the QR code in an image.
+pub fn scan(image: &GrayImage) -> anyhow::Result<String> {
+    let bitmap = Bitmap::new(image);
+    let finders = find_finders(&bitmap);
+
+    let mut error = None;
+    for corners in corner_triples(&finders) {
+        for version in corners.versions() {
+            match decode_grid(&corners.sample(&bitmap, version), version) {
+                Ok(text) => return Ok(text),
+                Err(e) => error = Some(e),
+            }
+        }
+    }
+    Err(error.unwrap_or_else(|| anyhow::anyhow!("No QR code found")))
+}
+
+/// An image reduced to dark and light pixels.
+struct Bitmap {
+    width: i64,
+    height: i64,
+    dark: Vec<bool>,
+}
+
+impl Bitmap {
+    /// Split pixels at the threshold that best separates the two tones
+    /// (Otsu's method).
+    fn new(image: &GrayImage) -> Self {
+        let mut histogram = [0u64; 256];
+        for pixel in image.pixels() {
+            histogram[usize::from(pixel.0[0])] += 1;
+        }
+        let total = histogram.iter().sum::<u64>() as f64;
+        let sum: f64 = histogram.iter().enumerate().map(|(i, &n)| i as f64 * n as f64).sum();
+
+        let (mut weight, mut weighted_sum, mut threshold, mut best) = (0.0, 0.0, 0u8, -1.0);
+        for (level, &n) in histogram.iter().enumerate() {
+            weight += n as f64;
+            weighted_sum += level as f64 * n as f64;
+            if weight == 0.0 || weight == total {
+                continue;
+            }
+            let between = weight * (total - weight) * (weighted_sum / weight - (sum - weighted_sum) / (total - weight)).powi(2);
+            if between > best {
+                best = between;
+                threshold = level as u8;
+            }
+        }
+
+        Self {
+            width: i64::from(image.width()),
+            height: i64::from(image.height()),
+            dark: image.pixels().map(|pixel| pixel.0[0] <= threshold).collect(),
+        }
+    }
+
+    /// Whether a pixel is dark; everything outside the image is light.
+    fn is_dark(&self, x: i64, y: i64) -> bool {
+        (0..self.width).contains(&x) && (0..self.height).contains(&y) && self.dark[(y * self.width + x) as usize]
+    }
+
+    /// Length of the run of pixels of one color starting at `(x, y)`
+    /// and going in steps of `step` rows.
+    fn vertical_run(&self, x: i64, mut y: i64, step: i64, dark: bool) -> i64 {
+        let mut len = 0;
+        while (0..self.height).contains(&y) && self.is_dark(x, y) == dark {
+            len += 1;
+            y += step;
+        }
+        len
+    }
+}
+
+/// A finder pattern's center and module size, in pixels.
+#[derive(Debug, Clone, Copy)]
+struct Finder {
+    x: f64,
+    y: f64,
+    module: f64,
+    hits: u32,
+}
+
+impl Finder {
+    fn distance(&self, other: &Finder) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
+
+/// Module size if run lengths have the 1:1:3:1:1 ratio of a finder.
+fn finder_module(runs: [i64; 5]) -> Option<f64> {
+    let total: i64 = runs.iter().sum();
+    if total < 7 {
+        return None;
+    }
+    let module = total as f64 / 7.0;
+    let tolerance = module / 2.0;
+    let fits = runs
+        .iter()
+        .zip([1.0, 1.0, 3.0, 1.0, 1.0])
+        .all(|(&run, units)| (run as f64 - units * module).abs() < units * tolerance);
+    fits.then_some(module)
+}
+
+/// Finder patterns in the image, most often seen first.
+fn find_finders(bitmap: &Bitmap) -> Vec<Finder> {
+    let mut finders: Vec<Finder> = Vec::new();
+    for y in 0..bitmap.height {
+        // Runs of one color along the row: (start, length, dark)
+        let mut runs: Vec<(i64, i64, bool)> = Vec::new();
+        for x in 0..bitmap.width {
+            let dark = bitmap.is_dark(x, y);
+            match runs.last_mut() {
+                Some(run) if run.2 == dark => run.1 += 1,
+                _ => runs.push((x, 1, dark)),
+            }
+        }
+
+        for window in runs.windows(5).filter(|window| window[0].2) {
+            let Some(horizontal) = finder_module([0, 1, 2, 3, 4].map(|i| window[i].1)) else {
+                continue;
+            };
+            let x = window[2].0 as f64 + window[2].1 as f64 / 2.0;
+            let Some((cy, vertical)) = vertical_finder(bitmap, x as i64, y) else {
+                continue;
+            };
+            add_finder(&mut finders, Finder { x, y: cy, module: (horizontal + vertical) / 2.0, hits: 1 });
+        }
+    }
+
+    // A true finder is crossed by several rows
+    finders.retain(|finder| finder.hits >= 2);
+    finders.sort_by_key(|finder| std::cmp::Reverse(finder.hits));
+    finders.truncate(MAX_FINDERS);
+    finders
+}
+
+/// Check a finder candidate along its column, returning its center row
+/// and module size.
+fn vertical_finder(bitmap: &Bitmap, x: i64, y: i64) -> Option<(f64, f64)> {
+    let up = bitmap.vertical_run(x, y, -1, true);
+    let down = bitmap.vertical_run(x, y + 1, 1, true);
+    let top = y - up + 1;
+    let bottom = y + down;
+
+    let light_up = bitmap.vertical_run(x, top - 1, -1, false);
+    let dark_up = bitmap.vertical_run(x, top - 1 - light_up, -1, true);
+    let light_down = bitmap.vertical_run(x, bottom + 1, 1, false);
+    let dark_down = bitmap.vertical_run(x, bottom + 1 + light_down, 1, true);
+
+    let module = finder_module([dark_up, light_up, up + down, light_down, dark_down])?;
+    Some((top as f64 + (up + down) as f64 / 2.0, module))
+}
+
+/// Merge a sighting into a finder seen nearby, or record a new one.
+fn add_finder(finders: &mut Vec<Finder>, found: Finder) {
+    match finders.iter_mut().find(|finder| finder.distance(&found) < finder.module * 2.0) {
+        Some(finder) => {
+            let hits = f64::from(finder.hits);
+            finder.x = (finder.x * hits + found.x) / (hits + 1.0);
+            finder.y = (finder.y * hits + found.y) / (hits + 1.0);
+            finder.module = (finder.module * hits + found.module) / (hits + 1.0);
+            finder.hits += 1;
+        }
+        None => finders.push(found),
+    }
+}
+
+/// The finder patterns at three corners of a code.
+struct Corners {
+    top_left: Finder,
+    top_right: Finder,
+    bottom_left: Finder,
+}
+
+/// Triples of finders that could be the corners of one code.
+fn corner_triples(finders: &[Finder]) -> Vec<Corners> {
+    let mut triples = Vec::new();
+    for i in 0..finders.len() {
+        for j in i + 1..finders.len() {
+            for k in j + 1..finders.len() {
+                if let Some(corners) = Corners::new([finders[i], finders[j], finders[k]]) {
+                    triples.push(corners);
+                }
+            }
+        }
+    }
+    triples
+}
+
+impl Corners {
+    /// Arrange three finders as corners, if they form the right isosceles
+    /// triangle of a code.
+    fn new(finders: [Finder; 3]) -> Option<Self> {
+        let modules = finders.map(|finder| finder.module);
+        let (smallest, largest) = modules.iter().fold((f64::MAX, 0.0f64), |(lo, hi), &m| (lo.min(m), hi.max(m)));
+        if largest > smallest * 1.5 {
+            return None;
+        }
+
+        // The top-left finder is opposite the longest side
+        let [a, b, c] = finders;
+        let (corner, p, q) = [(a, b, c), (b, a, c), (c, a, b)]
+            .into_iter()
+            .max_by(|x, y| x.1.distance(&x.2).total_cmp(&y.1.distance(&y.2)))?;
+        let (leg_p, leg_q, hypotenuse) = (corner.distance(&p), corner.distance(&q), p.distance(&q));
+        let leg = (leg_p + leg_q) / 2.0;
+        if (leg_p - leg_q).abs() > leg * 0.2 || (hypotenuse / (leg * std::f64::consts::SQRT_2) - 1.0).abs() > 0.15 {
+            return None;
+        }
+
+        // Going clockwise (with y pointing down) from top-right to bottom-left
+        let cross = (p.x - corner.x) * (q.y - corner.y) - (p.y - corner.y) * (q.x - corner.x);
+        let (top_right, bottom_left) = if cross > 0.0 { (p, q) } else { (q, p) };
+        Some(Self { top_left: corner, top_right, bottom_left })
+    }
+
+    /// Versions whose size fits the distance between the finders, best
+    /// guess first.
+    fn versions(&self) -> Vec<i16> {
+        let module = (self.top_left.module + self.top_right.module + self.bottom_left.module) / 3.0;
+        let leg = (self.top_left.distance(&self.top_right) + self.top_left.distance(&self.bottom_left)) / 2.0;
+        let estimate = ((leg / module + 7.0 - 17.0) / 4.0).round() as i16;
+        [estimate, estimate - 1, estimate + 1]
+            .into_iter()
+            .filter(|version| (1..=40).contains(version))
+            .collect()
+    }
+
+    /// Read the modules of a code of the given version, row by row.
+    fn sample(&self, bitmap: &Bitmap, version: i16) -> Vec<bool> {
+        let size = usize::from(version as u16) * 4 + 17;
+        // Finder centers sit 3.5 modules in from the edges
+        let span = (size - 7) as f64;
+        let (origin, right, down) = (self.top_left, self.top_right, self.bottom_left);
+
+        let mut grid = Vec::with_capacity(size * size);
+        for row in 0..size {
+            for col in 0..size {
+                let u = (col as f64 - 3.0) / span;
+                let v = (row as f64 - 3.0) / span;
+                let x = origin.x + u * (right.x - origin.x) + v * (down.x - origin.x);
+                let y = origin.y + u * (right.y - origin.y) + v * (down.y - origin.y);
+                grid.push(bitmap.is_dark(x.floor() as i64, y.floor() as i64));
+            }
+        }
+        grid
+    }
+}
+
+/// Decode the text of a sampled module grid.
+fn decode_grid(grid: &[bool], version: i16) -> anyhow::Result<String> {
+    let size = usize::from(version as u16) * 4 + 17;
+    let module = |x: usize, y: usize| grid[y * size + x];
+
+    let (ec_level, mask) = read_format(&module, size)?;
+
+    // Data modules are the ones the encoder leaves empty after drawing
+    // the finder, timing, alignment, format and version patterns
+    let qr_version = Version::Normal(version);
+    let mut layout = Canvas::new(qr_version, ec_level);
+    layout.draw_all_functional_patterns();
+    let is_data = |x: usize, y: usize| layout.get(x as i16, y as i16) == Module::Empty;
+
+    // Codewords run in two-module columns from the bottom right,
+    // alternately upwards and downwards, skipping the timing column
+    let mut codewords = Vec::new();
+    let (mut byte, mut bits) = (0u8, 0);
+    let mut upward = true;
+    let mut right = size - 1;
+    while right > 0 {
+        if right == 6 {
+            right -= 1;
+        }
+        for i in 0..size {
+            let y = if upward { size - 1 - i } else { i };
+            for x in [right, right - 1] {
+                if is_data(x, y) {
+                    byte = byte << 1 | u8::from(module(x, y) ^ mask_bit(mask, x, y));
+                    bits += 1;
+                    if bits == 8 {
+                        codewords.push(byte);
+                        (byte, bits) = (0, 0);
+                    }
+                }
+            }
+        }
+        upward = !upward;
+        right = right.saturating_sub(2);
+    }
+
+    let data = correct_blocks(&codewords, qr_version, ec_level)?;
+    parse_segments(&data, version)
+}
+
+/// Error correction level and mask from either copy of the format
+/// information, allowing up to three wrong bits.
+fn read_format(module: &impl Fn(usize, usize) -> bool, size: usize) -> anyhow::Result<(EcLevel, u8)> {
+    let side: Vec<(usize, usize)> = (0..7)
+        .map(|i| (8, size - 1 - i))
+        .chain((0..8).map(|i| (size - 8 + i, 8)))
+        .collect();
+    let read = |coords: &[(usize, usize)]| coords.iter().fold(0u16, |bits, &(x, y)| bits << 1 | u16::from(module(x, y)));
+    let copies = [read(&FORMAT_MAIN), read(&side)];
+
+    let (distance, format) = (0..32u16)
+        .flat_map(|format| copies.map(|bits| ((format_code(format) ^ bits).count_ones(), format)))
+        .min()
+        .expect("32 candidates");
+    anyhow::ensure!(distance <= 3, "Unreadable QR format information");
+
+    let ec_level = match (format >> 3) ^ 1 {
+        0 => EcLevel::L,
+        1 => EcLevel::M,
+        2 => EcLevel::Q,
+        _ => EcLevel::H,
+    };
+    Ok((ec_level, (format & 7) as u8))
+}
+
+/// Masked BCH(15,5) code word for five bits of format information.
+fn format_code(format: u16) -> u16 {
+    let mut remainder = format << 10;
+    for bit in (10..15).rev() {
+        if remainder & (1 << bit) != 0 {
+            remainder ^= 0x537 << (bit - 10);
+        }
+    }
+    (format << 10 | remainder) ^ FORMAT_MASK
+}
+
+/// Whether a mask pattern inverts the module at `(x, y)`.
+fn mask_bit(mask: u8, x: usize, y: usize) -> bool {
+    let value = match mask {
+        0 => (x + y) % 2,
+        1 => y % 2,
+        2 => x % 3,
+        3 => (x + y) % 3,
+        4 => (y / 2 + x / 3) % 2,
+        5 => (x * y) % 2 + (x * y) % 3,
+        6 => ((x * y) % 2 + (x * y) % 3) % 2,
+        _ => ((x + y) % 2 + (x * y) % 3) % 2,
+    };
+    value == 0
+}
+
+/// Split interleaved codewords into blocks, correct each, and join
+/// their data.
+fn correct_blocks(codewords: &[u8], version: Version, ec_level: EcLevel) -> anyhow::Result<Vec<u8>> {
+    let data_len = Bits::new(version).max_len(ec_level)? / 8;
+    anyhow::ensure!(codewords.len() > data_len, "QR code is too small for its version");
+
+    // Interleaving puts the second byte of the first block right after
+    // the first byte of every block, which gives the block count
+    let mut probe = vec![0; data_len];
+    probe[1] = 1;
+    let (interleaved, _) = construct_codewords(&probe, version, ec_level)?;
+    let blocks = interleaved.iter().position(|&byte| byte == 1).expect("probe byte is kept");
+
+    let ec_len = (codewords.len() - data_len) / blocks;
+    let short_len = data_len / blocks;
+    let short_blocks = blocks - data_len % blocks;
+
+    let mut codewords = codewords.iter().copied();
+    let mut next = || codewords.next().context("Truncated QR code");
+    let mut split: Vec<Vec<u8>> = vec![Vec::with_capacity(short_len + 1 + ec_len); blocks];
+    for i in 0..=short_len {
+        for (b, block) in split.iter_mut().enumerate() {
+            if i < short_len || b >= short_blocks {
+                block.push(next()?);
+            }
+        }
+    }
+    for _ in 0..ec_len {
+        for block in &mut split {
+            block.push(next()?);
+        }
+    }
+
+    let gf = Galois::new();
+    let mut data = Vec::with_capacity(data_len);
+    for mut block in split {
+        gf.correct(&mut block, ec_len)?;
+        data.extend_from_slice(&block[..block.len() - ec_len]);
+    }
+    Ok(data)
+}
+
+/// Arithmetic in GF(256) with the QR code polynomial x^8+x^4+x^3+x^2+1.
+struct Galois {
+    exp: [u8; 255],
+    log: [u8; 256],
+}
+
+impl Galois {
+    fn new() -> Self {
+        let (mut exp, mut log) = ([0u8; 255], [0u8; 256]);
+        let mut value = 1u16;
+        for (power, entry) in exp.iter_mut().enumerate() {
+            *entry = value as u8;
+            log[usize::from(value)] = power as u8;
+            value <<= 1;
+            if value & 0x100 != 0 {
+                value ^= 0x11d;
+            }
+        }
+        Self { exp, log }
+    }
+
+    /// α raised to `power`.
+    fn alpha(&self, power: usize) -> u8 {
+        self.exp[power % 255]
+    }
+
+    fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        self.alpha(usize::from(self.log[usize::from(a)]) + usize::from(self.log[usize::from(b)]))
+    }
+
+    fn div(&self, a: u8, b: u8) -> u8 {
+        if a == 0 {
+            return 0;
+        }
+        self.alpha(usize::from(self.log[usize::from(a)]) + 255 - usize::from(self.log[usize::from(b)]))
+    }
+
+    /// Evaluate a polynomial given lowest coefficient first.
+    fn eval(&self, poly: &[u8], x: u8) -> u8 {
+        poly.iter().rev().fold(0, |acc, &coefficient| self.mul(acc, x) ^ coefficient)
+    }
+
+    /// Correct a block of data followed by `ec_len` Reed-Solomon bytes
+    /// in place (Berlekamp-Massey, Chien search and Forney).
+    fn correct(&self, block: &mut [u8], ec_len: usize) -> anyhow::Result<()> {
+        // The block is a polynomial with its first byte as the highest term
+        let syndromes: Vec<u8> = (0..ec_len)
+            .map(|j| block.iter().fold(0, |acc, &byte| self.mul(acc, self.alpha(j)) ^ byte))
+            .collect();
+        if syndromes.iter().all(|&s| s == 0) {
+            return Ok(());
+        }
+
+        // Error locator polynomial, lowest coefficient first
+        let (mut locator, mut previous) = (vec![1u8], vec![1u8]);
+        let (mut errors, mut shift, mut previous_discrepancy) = (0, 1, 1u8);
+        for n in 0..ec_len {
+            let discrepancy = (1..=errors.min(locator.len() - 1))
+                .fold(syndromes[n], |d, i| d ^ self.mul(locator[i], syndromes[n - i]));
+            if discrepancy == 0 {
+                shift += 1;
+                continue;
+            }
+            let scale = self.div(discrepancy, previous_discrepancy);
+            let saved = locator.clone();
+            locator.resize(locator.len().max(previous.len() + shift), 0);
+            for (i, &coefficient) in previous.iter().enumerate() {
+                locator[i + shift] ^= self.mul(scale, coefficient);
+            }
+            if 2 * errors <= n {
+                errors = n + 1 - errors;
+                previous = saved;
+                previous_discrepancy = discrepancy;
+                shift = 1;
+            } else {
+                shift += 1;
+            }
+        }
+        anyhow::ensure!(2 * errors <= ec_len, "Too many errors in QR code");
+        locator.truncate(errors + 1);
+
+        // Error evaluator: syndromes times locator, modulo x^ec_len
+        let mut evaluator = vec![0u8; ec_len];
+        for (i, &syndrome) in syndromes.iter().enumerate() {
+            for (j, &coefficient) in locator.iter().enumerate().take(ec_len - i) {
+                evaluator[i + j] ^= self.mul(syndrome, coefficient);
+            }
+        }
+
+        let len = block.len();
+        let mut found = 0;
+        for (position, byte) in block.iter_mut().enumerate() {
+            let x = self.alpha(len - 1 - position);
+            let x_inverse = self.div(1, x);
+            if self.eval(&locator, x_inverse) != 0 {
+                continue;
+            }
+            // Formal derivative: only odd powers survive in GF(2^8)
+            let derivative = locator
+                .iter()
+                .enumerate()
+                .skip(1)
+                .step_by(2)
+                .fold(0, |acc, (power, &coefficient)| {
+                    let term = (1..power).fold(coefficient, |term, _| self.mul(term, x_inverse));
+                    acc ^ term
+                });
+            anyhow::ensure!(derivative != 0, "Uncorrectable QR code");
+            *byte ^= self.div(self.mul(x, self.eval(&evaluator, x_inverse)), derivative);
+            found += 1;
+        }
+        anyhow::ensure!(found == errors, "Uncorrectable QR code");
+        Ok(())
+    }
+}
+
+/// Reads big-endian bit fields from a byte string.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.position
+    }
+
+    fn read(&mut self, bits: usize) -> anyhow::Result<usize> {
+        anyhow::ensure!(bits <= self.remaining(), "Truncated QR code data");
+        let mut value = 0;
+        for _ in 0..bits {
+            let bit = self.data[self.position / 8] >> (7 - self.position % 8) & 1;
+            value = value << 1 | usize::from(bit);
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+/// Decode the numeric, alphanumeric and byte segments of a code's data.
+fn parse_segments(data: &[u8], version: i16) -> anyhow::Result<String> {
+    // Character count fields grow for versions 10 and 27
+    let size_class = match version {
+        1..=9 => 0,
+        10..=26 => 1,
+        _ => 2,
+    };
+    let mut reader = BitReader { data, position: 0 };
+    let mut text = Vec::new();
+
+    while reader.remaining() >= 4 {
+        match reader.read(4)? {
+            0b0000 => break,
+            0b0001 => {
+                let mut count = reader.read([10, 12, 14][size_class])?;
+                while count > 0 {
+                    let (digits, bits) = match count {
+                        1 => (1, 4),
+                        2 => (2, 7),
+                        _ => (3, 10),
+                    };
+                    let value = reader.read(bits)?;
+                    anyhow::ensure!(value < 10usize.pow(digits), "Invalid numeric data in QR code");
+                    text.extend(format!("{:0width$}", value, width = digits as usize).bytes());
+                    count -= digits as usize;
+                }
+            }
+            0b0010 => {
+                // The alphanumeric table is the Base45 alphabet, in order
+                let mut count = reader.read([9, 11, 13][size_class])?;
+                while count >= 2 {
+                    let value = reader.read(11)?;
+                    anyhow::ensure!(value < 45 * 45, "Invalid alphanumeric data in QR code");
+                    text.extend([super::BASE45[value / 45], super::BASE45[value % 45]]);
+                    count -= 2;
+                }
+                if count == 1 {
+                    let value = reader.read(6)?;
+                    anyhow::ensure!(value < 45, "Invalid alphanumeric data in QR code");
+                    text.push(super::BASE45[value]);
+                }
+            }
+            0b0100 => {
+                let count = reader.read([8, 16, 16][size_class])?;
+                for _ in 0..count {
+                    text.push(reader.read(8)? as u8);
+                }
+            }
+            0b0111 => {
+                // Extended channel designator; the text is kept as bytes
+                let first = reader.read(8)?;
+                if first & 0x80 != 0 {
+                    reader.read(if first & 0x40 == 0 { 8 } else { 16 })?;
+                }
+            }
+            mode => anyhow::bail!("Unsupported QR data mode {:04b}", mode),
+        }
+    }
+
+    String::from_utf8(text).context("QR code text is not UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{imageops, Luma};
+    use qrcode::ec::create_error_correction_code;
+    use qrcode::QrCode;
+
+    fn render(text: &str, ec_level: EcLevel) -> GrayImage {
+        QrCode::with_error_correction_level(text, ec_level)
+            .unwrap()
+            .render::<Luma<u8>>()
+            .build()
+    }
+
+    #[test]
+    fn test_scan_segments_and_versions() {
+        let long = "Hesha attestations, presented in person. ".repeat(12);
+        for (text, ec_level) in [
+            ("HESHA1:BB8%69 VD92EX0", EcLevel::L),
+            ("0123456789012345", EcLevel::M),
+            ("hello, world", EcLevel::Q),
+            (long.as_str(), EcLevel::M),
+            ("ID 42: ünïcode", EcLevel::H),
+        ] {
+            assert_eq!(scan(&render(text, ec_level)).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn test_scan_turned_and_scaled() {
+        let code = QrCode::with_error_correction_level("HESHA1:QED8WEX0", EcLevel::L).unwrap();
+        let image = code.render::<Luma<u8>>().module_dimensions(3, 3).build();
+        assert_eq!(scan(&image).unwrap(), "HESHA1:QED8WEX0");
+        assert_eq!(scan(&imageops::rotate90(&image)).unwrap(), "HESHA1:QED8WEX0");
+        assert_eq!(scan(&imageops::rotate180(&image)).unwrap(), "HESHA1:QED8WEX0");
+        assert_eq!(scan(&imageops::rotate270(&image)).unwrap(), "HESHA1:QED8WEX0");
+    }
+
+    #[test]
+    fn test_scan_damaged() {
+        // An 8-pixel module size and a 4-module quiet zone; smudge a
+        // 3x3-module patch of data in the middle
+        let mut image = render("hello, world", EcLevel::H);
+        let center = image.width() / 2;
+        for y in center - 12..center + 12 {
+            for x in center - 12..center + 12 {
+                image.put_pixel(x, y, Luma([255]));
+            }
+        }
+        assert_eq!(scan(&image).unwrap(), "hello, world");
+    }
+
+    #[test]
+    fn test_scan_without_code() {
+        assert!(scan(&GrayImage::from_pixel(200, 200, Luma([255]))).is_err());
+    }
+
+    #[test]
+    fn test_corrects_errors() {
+        let gf = Galois::new();
+        let data: Vec<u8> = (0..40).map(|i| i * 5 + 3).collect();
+        let ec = create_error_correction_code(&data, 20);
+        let mut block = [data.clone(), ec].concat();
+        block[0] ^= 0xff;
+        block[17] ^= 0x01;
+        block[39] ^= 0x80;
+        block[55] ^= 0x42;
+        gf.correct(&mut block, 20).unwrap();
+        assert_eq!(&block[..40], &data[..]);
+
+        // More errors than half the correction bytes
+        for position in (0..60).step_by(5) {
+            block[position] ^= 0x11;
+        }
+        assert!(gf.correct(&mut block, 20).is_err());
+    }
+
+    #[test]
+    fn test_format_codes() {
+        // Values from the QR code specification's format information table
+        assert_eq!(format_code(0b01_000), 0x77c4);
+        assert_eq!(format_code(0b00_000), 0x5412);
+        assert_eq!(format_code(0b11_111), 0x2bed);
+    }
+}
@@ -0,0 +1,102 @@
+//! Restart command for cycling a background issuer node.
+
+use clap::Args;
+use std::path::Path;
+use std::time::Duration;
+use crate::commands::start::{check_pid_file, print_daemon_endpoints, spawn_daemon, PidFileState};
+use crate::{output, process};
+
+/// Restart the Hesha issuer node in the background.
+#[derive(Debug, Args)]
+pub struct RestartCmd {
+    /// Name of the issuer configuration.
+    /// Defaults to 'default'
+    #[arg(short, long, default_value = "default")]
+    name: String,
+    
+    /// Seconds to wait for the running node to exit
+    #[arg(long, value_name = "SECS", default_value_t = 10)]
+    timeout: u64,
+}
+
+impl RestartCmd {
+    pub fn execute(self) -> anyhow::Result<()> {
+        // Determine config directory
+        let config_dir = dirs::home_dir()
+            .expect("Could not find home directory")
+            .join(".hesha")
+            .join("issuer")
+            .join(&self.name);
+        
+        if !config_dir.join("config").join("issuer.toml").exists() {
+            output::error(&format!("No configuration found for issuer '{}'", self.name));
+            println!("\nRun 'hesha setup' first to create an issuer configuration");
+            return Ok(());
+        }
+        
+        stop_running(&config_dir.join("issuer.pid"), Duration::from_secs(self.timeout))?;
+        
+        let pid = spawn_daemon(&config_dir)?;
+        
+        output::success(&format!("Issuer node restarted in background (PID: {})", pid));
+        print_daemon_endpoints();
+        
+        Ok(())
+    }
+}
+
+/// Stop the node recorded in the PID file, if any, and remove the file.
+fn stop_running(pid_file: &Path, timeout: Duration) -> anyhow::Result<()> {
+    match check_pid_file(pid_file) {
+        PidFileState::Running(pid) => {
+            output::info(&format!("Stopping issuer node (PID: {})", pid));
+            process::terminate(pid)?;
+            if !process::wait_for_exit(pid, timeout) {
+                anyhow::bail!(
+                    "Issuer node (PID: {}) did not exit within {}s; not starting a second instance",
+                    pid,
+                    timeout.as_secs()
+                );
+            }
+            std::fs::remove_file(pid_file)?;
+        }
+        PidFileState::Stale(_) => {
+            output::warning("Issuer node was not running (cleaned up PID file)");
+            std::fs::remove_file(pid_file)?;
+        }
+        PidFileState::Missing => output::info("Issuer node was not running"),
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_stop_without_running_node() {
+        let temp = tempfile::tempdir().unwrap();
+        let pid_file = temp.path().join("issuer.pid");
+        stop_running(&pid_file, Duration::from_secs(1)).unwrap();
+        
+        std::fs::write(&pid_file, std::process::id().to_string()).unwrap();
+        stop_running(&pid_file, Duration::from_secs(1)).unwrap();
+        assert!(!pid_file.exists());
+        // The PID belonged to another program, which must be left alone
+        assert!(process::is_running(std::process::id()));
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_stop_running_node() {
+        let temp = tempfile::tempdir().unwrap();
+        let pid_file = temp.path().join("issuer.pid");
+        let pid = process::spawn_fake_issuer(temp.path());
+        std::fs::write(&pid_file, pid.to_string()).unwrap();
+        
+        stop_running(&pid_file, Duration::from_secs(5)).unwrap();
+        assert!(!pid_file.exists());
+        assert!(!process::is_issuer(pid));
+    }
+}
@@ -0,0 +1,127 @@
+//! Restore of an issuer from an encrypted backup.
+
+use anyhow::Context;
+use clap::Args;
+use hesha_core::backup::{AUDIT_FILE, RECORDS_FILE};
+use hesha_core::EncryptedBackup;
+use crate::commands::audit::{admin_client, issuer_config};
+use crate::config::{config_dir, read_backup_passphrase};
+use crate::output;
+use std::fs;
+use std::path::PathBuf;
+
+/// Restore an issuer from an archive made by 'hesha backup'.
+#[derive(Debug, Args)]
+pub struct RestoreCmd {
+    /// Archive made by 'hesha backup'
+    #[arg(value_name = "FILE")]
+    file: PathBuf,
+
+    /// Name of the issuer configuration to restore into.
+    /// Defaults to 'default'
+    #[arg(short, long, default_value = "default")]
+    name: String,
+
+    /// Node URL. Defaults to http://localhost:<port> from the issuer config
+    #[arg(long, value_name = "URL")]
+    url: Option<String>,
+
+    /// Replace existing config and key files
+    #[arg(long)]
+    force: bool,
+
+    /// Only restore config and keys
+    #[arg(long, conflicts_with = "records_only")]
+    files_only: bool,
+
+    /// Only load the records into the running node
+    #[arg(long)]
+    records_only: bool,
+}
+
+impl RestoreCmd {
+    pub async fn execute(self) -> anyhow::Result<()> {
+        let issuer_dir = config_dir().join("issuer").join(&self.name);
+        let contents = fs::read_to_string(&self.file)
+            .with_context(|| format!("Cannot read {}", self.file.display()))?;
+        let sealed = EncryptedBackup::from_json(&contents)?;
+        let passphrase = read_backup_passphrase(false)?;
+        // Decrypting checks the archive; each file is checked against its digest
+        let backup = sealed.open(&passphrase)?;
+
+        let mut written = Vec::new();
+        if !self.records_only {
+            written = backup.restore_files(&issuer_dir, self.force).map_err(|e| {
+                anyhow::anyhow!("{}; pass --force to replace it, or --records-only to skip files", e)
+            })?;
+        }
+
+        let records = match backup.file(RECORDS_FILE)? {
+            Some(records) if !self.files_only => Some(records),
+            _ => None,
+        };
+        let mut summary = None;
+        // Node that was unreachable, leaving the records to load later
+        let mut pending = None;
+        if let Some(records) = records {
+            let url = match self.url {
+                Some(url) => url,
+                None => format!("http://localhost:{}", issuer_config(&self.name)?.port),
+            };
+            match admin_client(&url)?.restore_records(records).await {
+                Ok(restored) => summary = Some(restored),
+                // On a new host the node can only start once its files are back
+                Err(e) if !written.is_empty() && e.is_transient() => pending = Some(url),
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Cannot load records into the issuer node at {}", url));
+                }
+            }
+        }
+
+        if output::is_json() {
+            return output::json(&serde_json::json!({
+                "created_at": backup.created_at,
+                "files": written,
+                "records": summary,
+                "records_pending": pending.is_some(),
+            }));
+        }
+        output::success(&format!(
+            "Restored backup from {} into issuer '{}'",
+            backup.created_at.format("%Y-%m-%d %H:%M UTC"),
+            self.name
+        ));
+        for path in &written {
+            println!("  {}", path.display());
+        }
+        if let Some(summary) = &summary {
+            output::info(&format!(
+                "Loaded {} attestation(s), {} proxy number(s) and {} revocation(s) into the node",
+                summary.attestations, summary.proxy_numbers, summary.revocations
+            ));
+            if summary.proxy_conflicts > 0 {
+                output::warning(&format!(
+                    "{} proxy number(s) already belonged to other phones on the node and were kept",
+                    summary.proxy_conflicts
+                ));
+            }
+        } else if let Some(url) = pending {
+            output::warning(&format!(
+                "No issuer node answered at {}; start it with 'hesha start -n {}', then load the records with 'hesha restore {} -n {} --records-only'",
+                url, self.name, self.file.display(), self.name
+            ));
+        } else if !self.files_only {
+            output::warning("The backup holds no records");
+        }
+        if written.iter().any(|path| path.ends_with(AUDIT_FILE)) {
+            output::info(&format!(
+                "The archived audit log is in {}; check it with 'hesha audit verify'",
+                issuer_dir.join(AUDIT_FILE).display()
+            ));
+        }
+        if summary.is_some() && !written.is_empty() {
+            output::info("Restart the issuer node to use the restored keys");
+        }
+        Ok(())
+    }
+}
@@ -1,14 +1,19 @@
 //! Setup command for initializing a new issuer.
 
-use clap::Args;
-use hesha_core::{IssuerSetupBuilder};
+use clap::{Args, Subcommand};
+use hesha_core::{rotate_issuer_key, IssuerSetupBuilder};
+use hesha_types::IssuerConfig;
 use dialoguer::{theme::ColorfulTheme, Input, Confirm};
-use crate::output;
+use crate::{input, output};
 use std::path::PathBuf;
 
 /// Setup a new Hesha issuer with interactive configuration.
 #[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct SetupIssuerCmd {
+    #[command(subcommand)]
+    command: Option<SetupCommands>,
+    
     /// Output directory for configuration and keys.
     /// If not specified, defaults to ~/.hesha/issuer/<name>
     #[arg(short, long)]
@@ -19,26 +24,68 @@ pub struct SetupIssuerCmd {
     #[arg(short, long, default_value = "default")]
     name: String,
     
-    /// Skip interactive prompts and use defaults.
+    /// Skip interactive prompts (implied when stdin is not a terminal).
+    /// Requires --issuer-name, --trust-domain and --contact-email (or their
+    /// environment variables)
     #[arg(long)]
     non_interactive: bool,
+    
+    /// Issuer name
+    #[arg(long = "issuer-name", env = "HESHA_ISSUER_NAME", value_name = "NAME")]
+    issuer_name: Option<String>,
+    
+    /// Trust domain where verifiers find the public key
+    #[arg(long, env = "HESHA_TRUST_DOMAIN", value_name = "DOMAIN")]
+    trust_domain: Option<String>,
+    
+    /// Contact email for this issuer
+    #[arg(long, env = "HESHA_CONTACT_EMAIL", value_name = "EMAIL")]
+    contact_email: Option<String>,
+    
+    /// Port the issuer node listens on
+    #[arg(long, env = "HESHA_PORT", value_name = "PORT", default_value = "3000")]
+    port: u16,
+}
+
+#[derive(Debug, Subcommand)]
+enum SetupCommands {
+    /// Rotate the issuer signing key
+    Rotate(RotateCmd),
+}
+
+/// Rotate an issuer's signing key, keeping the old key published.
+#[derive(Debug, Args)]
+pub struct RotateCmd {
+    /// Issuer directory containing config/ and keys/.
+    /// If not specified, defaults to ~/.hesha/issuer/<name>
+    #[arg(short, long)]
+    output_dir: Option<PathBuf>,
+    
+    /// Name of the issuer configuration. Defaults to 'default'
+    #[arg(short, long, default_value = "default")]
+    name: String,
+    
+    /// Days to keep publishing the old key.
+    /// Defaults to the configured attestation validity
+    #[arg(long, value_name = "DAYS")]
+    overlap_days: Option<i64>,
+    
+    /// Rotate without asking for confirmation
+    #[arg(short, long)]
+    yes: bool,
 }
 
 impl SetupIssuerCmd {
     pub fn execute(self) -> anyhow::Result<()> {
-        // Fun ASCII art banner
-        println!("\n{}", "═".repeat(60));
-        println!(r#"
-    ╦ ╦┌─┐┌─┐┬ ┬┌─┐  ╔╗╔┌─┐┌┬┐┌─┐
-    ╠═╣├┤ └─┐├─┤├─┤  ║║║│ │ ││├┤ 
-    ╩ ╩└─┘└─┘┴ ┴┴ ┴  ╝╚╝└─┘─┴┘└─┘
-    
-    🚀 Let's set up your node! 🚀
-        "#);
-        println!("{}\n", "═".repeat(60));
+        if let Some(SetupCommands::Rotate(cmd)) = self.command {
+            return cmd.execute();
+        }
+        
+        let json = output::is_json();
         
-        output::info("Welcome to Hesha Protocol Setup");
-        println!("Setting up your issuer node for the first time.\n");
+        if !json {
+            print_banner();
+        }
         
         // Determine output directory
         let output_dir = self.output_dir.unwrap_or_else(|| {
@@ -47,13 +94,35 @@ impl SetupIssuerCmd {
         });
         
         // Show where we're saving
-        println!("Configuration will be saved to: {}\n", output_dir.display());
+        output::text(&format!("Configuration will be saved to: {}\n", output_dir.display()));
         
         let theme = ColorfulTheme::default();
         
-        // Collect configuration interactively
-        let name = if self.non_interactive {
-            "Test Issuer".to_string()
+        // Never prompt when stdin isn't a terminal (scripts, CI, pipes)
+        let non_interactive = self.non_interactive || !input::is_interactive();
+        
+        // Values passed as flags or environment variables skip their prompt
+        if non_interactive {
+            let missing: Vec<&str> = [
+                ("--issuer-name", self.issuer_name.is_none()),
+                ("--trust-domain", self.trust_domain.is_none()),
+                ("--contact-email", self.contact_email.is_none()),
+            ]
+            .into_iter()
+            .filter_map(|(flag, missing)| missing.then_some(flag))
+            .collect();
+            
+            if !missing.is_empty() {
+                anyhow::bail!(
+                    "Non-interactive setup requires {} (or HESHA_ISSUER_NAME, HESHA_TRUST_DOMAIN, HESHA_CONTACT_EMAIL)",
+                    missing.join(", ")
+                );
+            }
+        }
+        
+        // Collect remaining configuration interactively
+        let name = if let Some(name) = self.issuer_name {
+            name
         } else {
             Input::with_theme(&theme)
                 .with_prompt("Enter your issuer name (e.g., 'Acme Verification Services')")
@@ -67,11 +136,11 @@ impl SetupIssuerCmd {
                 .interact_text()?
         };
         
-        let trust_domain = if self.non_interactive {
-            "issuer.example.com".to_string()
+        let trust_domain = if let Some(trust_domain) = self.trust_domain {
+            trust_domain
         } else {
-            println!("\nYour trust domain is where verifiers will find your public key.");
-            println!("This MUST be a domain you control and can serve HTTPS content from.");
+            output::text("\nYour trust domain is where verifiers will find your public key.");
+            output::text("This MUST be a domain you control and can serve HTTPS content from.");
             Input::with_theme(&theme)
                 .with_prompt("Enter your trust domain (e.g., 'issuer.example.com')")
                 .validate_with(|input: &String| {
@@ -88,8 +157,8 @@ impl SetupIssuerCmd {
                 .interact_text()?
         };
         
-        let contact_email = if self.non_interactive {
-            "admin@example.com".to_string()
+        let contact_email = if let Some(contact_email) = self.contact_email {
+            contact_email
         } else {
             Input::with_theme(&theme)
                 .with_prompt("Enter contact email for this issuer")
@@ -103,34 +172,34 @@ impl SetupIssuerCmd {
                 .interact_text()?
         };
         
-        
-        
         // Build the configuration
-        println!("\n{}", "─".repeat(60));
-        println!("🔑 Generating Ed25519 keypair for signing attestations...");
-        println!("{}", "─".repeat(60));
+        output::text(&format!("\n{}", "─".repeat(60)));
+        output::text("🔑 Generating Ed25519 keypair for signing attestations...");
+        output::text(&"─".repeat(60));
         
         let setup = IssuerSetupBuilder::new()
             .name(&name)
             .trust_domain(&trust_domain)
             .contact_email(&contact_email)
+            .port(self.port)
             .build()?;
         
         // Review configuration
-        if !self.non_interactive {
-            println!("\n📋 Review Configuration");
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            println!("Issuer Name: {}", setup.config.identity.name);
-            println!("Trust Domain: {}", setup.config.identity.trust_domain);
-            println!("Public Key URL: {}", setup.public_key_url());
-            println!("Contact Email: {}", setup.config.identity.contact_email);
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        if !non_interactive {
+            output::text("\n📋 Review Configuration");
+            output::text("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            output::text(&format!("Issuer Name: {}", setup.config.identity.name));
+            output::text(&format!("Trust Domain: {}", setup.config.identity.trust_domain));
+            output::text(&format!("Public Key URL: {}", setup.public_key_url()));
+            output::text(&format!("Contact Email: {}", setup.config.identity.contact_email));
+            output::text("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
             
             let confirm = Confirm::with_theme(&theme)
                 .with_prompt("Is this configuration correct?")
                 .interact()?;
             
             if !confirm {
+                anyhow::ensure!(!json, "Setup cancelled");
                 println!("Setup cancelled.");
                 return Ok(());
             }
@@ -143,6 +212,19 @@ impl SetupIssuerCmd {
         output::success(&format!("✅ Configuration saved to {}/config/issuer.toml", output_dir.display()));
         output::success(&format!("✅ Keys saved to {}/keys/", output_dir.display()));
         
+        if json {
+            return output::json(&serde_json::json!({
+                "name": setup.config.identity.name,
+                "trust_domain": setup.config.identity.trust_domain,
+                "contact_email": setup.config.identity.contact_email,
+                "public_key": setup.config.identity.public_key_base64url,
+                "public_key_url": setup.public_key_url(),
+                "config_file": output_dir.join("config").join("issuer.toml"),
+                "private_key_file": output_dir.join("keys").join("private.key"),
+                "public_key_endpoint_file": output_dir.join("config").join("public-key-endpoint.json"),
+            }));
+        }
+        
         // Display public key for easy copying
         println!("\n{}", "─".repeat(60));
         println!("📋 Your Public Key (copy this!):");
@@ -189,7 +271,7 @@ impl SetupIssuerCmd {
         
         println!("3️⃣  Start your issuer node:");
         println!("    💻 HESHA_CONFIG_DIR={} cargo run --bin hesha-issuer-node", output_dir.display());
-        println!("    Or: CONFIG_PATH={}/config/issuer.toml cargo run --bin hesha-issuer-node\n", output_dir.display());
+        println!("    Or: HESHA_CONFIG={}/config/issuer.toml cargo run --bin hesha-issuer-node\n", output_dir.display());
         
         println!("4️⃣  Test everything:");
         println!("    🧪 hesha verify --help\n");
@@ -200,4 +282,102 @@ impl SetupIssuerCmd {
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+impl RotateCmd {
+    pub fn execute(self) -> anyhow::Result<()> {
+        let issuer_dir = self.output_dir.unwrap_or_else(|| {
+            let home = dirs::home_dir().expect("Could not find home directory");
+            home.join(".hesha").join("issuer").join(&self.name)
+        });
+        let config_path = issuer_dir.join("config").join("issuer.toml");
+        
+        let config = IssuerConfig::from_file(&config_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", config_path.display(), e))?;
+        
+        // Old attestations must keep verifying until they expire
+        let overlap_days = self.overlap_days.unwrap_or(config.attestation_validity_days as i64);
+        anyhow::ensure!(overlap_days >= 0, "--overlap-days cannot be negative");
+        
+        if !self.yes {
+            anyhow::ensure!(
+                !output::is_json() && input::is_interactive(),
+                "Pass --yes to rotate without a confirmation prompt"
+            );
+            
+            output::text("\n🔄 Key Rotation");
+            output::text("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            output::text(&format!("Issuer: {} ({})", config.identity.name, config.identity.trust_domain));
+            output::text(&format!("Current key: {}", config.identity.key_id));
+            output::text(&format!("Old key stays published for: {} days", overlap_days));
+            output::text("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            
+            let confirm = Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Generate a new signing key?")
+                .interact()?;
+            
+            if !confirm {
+                println!("Rotation cancelled.");
+                return Ok(());
+            }
+        }
+        
+        let rotation = rotate_issuer_key(&issuer_dir, chrono::Duration::days(overlap_days))?;
+        let endpoint_path = issuer_dir.join("config").join("public-key-endpoint.json");
+        
+        if output::is_json() {
+            return output::json(&serde_json::json!({
+                "key_id": rotation.config.identity.key_id,
+                "public_key": rotation.config.identity.public_key_base64url,
+                "retired_key_id": rotation.retired.key_id,
+                "retired_key_valid_until": rotation.retired.valid_until,
+                "archived_private_key_file": rotation.archived_private_key,
+                "config_file": config_path,
+                "public_key_endpoint_file": endpoint_path,
+            }));
+        }
+        
+        output::success(&format!("✅ New signing key {} generated", rotation.config.identity.key_id));
+        output::success(&format!(
+            "✅ Old key {} archived to {}",
+            rotation.retired.key_id,
+            rotation.archived_private_key.display()
+        ));
+        
+        println!("\n📋 Your New Public Key:");
+        println!("\n{}\n", rotation.config.identity.public_key_base64url);
+        
+        println!("{}", "═".repeat(60));
+        println!("🚀 Rollout Checklist:\n");
+        println!("  □ Publish {}", endpoint_path.display());
+        println!("    at https://{}/.well-known/hesha/pubkey.json", rotation.config.identity.trust_domain);
+        println!("    (it lists both keys until {})", rotation.retired.valid_until);
+        println!("  □ Update verifiers that pin keys (trust bundles, --issuer-key)");
+        println!("  □ Restart the issuer node so new attestations use the new key");
+        println!("    (it keeps serving the old key for attestations that name it)");
+        println!("  □ Back up the new private key: {}/keys/private.key", issuer_dir.display());
+        println!("  □ Move the archived private key to offline storage");
+        println!("  □ Remove the old key from the endpoint after {}", rotation.retired.valid_until);
+        println!("    (the next rotation drops it automatically)");
+        println!("{}", "═".repeat(60));
+        
+        Ok(())
+    }
+}
+
+/// Print the welcome banner.
+fn print_banner() {
+    // Fun ASCII art banner
+    println!("\n{}", "═".repeat(60));
+    println!(r#"
+    ╦ ╦┌─┐┌─┐┬ ┬┌─┐  ╔╗╔┌─┐┌┬┐┌─┐
+    ╠═╣├┤ └─┐├─┤├─┤  ║║║│ │ ││├┤ 
+    ╩ ╩└─┘└─┘┴ ┴┴ ┴  ╝╚╝└─┘─┴┘└─┘
+    
+    🚀 Let's set up your node! 🚀
+    "#);
+    println!("{}\n", "═".repeat(60));
+    
+    output::info("Welcome to Hesha Protocol Setup");
+    println!("Setting up your issuer node for the first time.\n");
+}
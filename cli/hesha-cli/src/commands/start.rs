@@ -1,9 +1,13 @@
 //! Start command for running the issuer node.
 
 use clap::Args;
-use std::process::{Command, Stdio};
-use std::path::PathBuf;
-use crate::output;
+use hesha_types::{IssuerConfig, CONTROL_SOCKET_FILE};
+use std::process::Command;
+use std::path::{Path, PathBuf};
+use crate::{output, process};
+
+/// Default directory for system unit files.
+const SYSTEMD_UNIT_DIR: &str = "/etc/systemd/system";
 
 /// Start the Hesha issuer node.
 #[derive(Debug, Args)]
@@ -14,8 +18,21 @@ pub struct StartCmd {
     name: String,
     
     /// Run in background (daemon mode).
-    #[arg(short, long)]
+    /// On Linux servers prefer --install-systemd
+    #[arg(short, long, conflicts_with = "install_systemd")]
     daemon: bool,
+    
+    /// Write a systemd unit file for the issuer node instead of starting it
+    #[arg(long)]
+    install_systemd: bool,
+    
+    /// Directory to write the unit file to
+    #[arg(long, value_name = "DIR", default_value = SYSTEMD_UNIT_DIR, requires = "install_systemd")]
+    unit_dir: PathBuf,
+    
+    /// User the service runs as. Defaults to the current user
+    #[arg(long, value_name = "USER", requires = "install_systemd")]
+    service_user: Option<String>,
 }
 
 impl StartCmd {
@@ -34,6 +51,10 @@ impl StartCmd {
             return Ok(());
         }
         
+        if self.install_systemd {
+            return install_systemd_unit(&self.name, &config_dir, &self.unit_dir, self.service_user);
+        }
+        
         output::info(&format!("Starting Hesha issuer node ({})", self.name));
         println!("Config: {}", config_dir.display());
         
@@ -42,31 +63,21 @@ impl StartCmd {
             let pid_file = config_dir.join("issuer.pid");
             
             // Check if already running
-            if pid_file.exists() {
-                let pid = std::fs::read_to_string(&pid_file)?;
-                output::warning(&format!("Issuer may already be running (PID: {})", pid.trim()));
-                println!("Run 'hesha stop' to stop the existing instance");
-                return Ok(());
+            match check_pid_file(&pid_file) {
+                PidFileState::Running(pid) => {
+                    output::warning(&format!("Issuer is already running (PID: {})", pid));
+                    println!("Run 'hesha stop' to stop the existing instance");
+                    return Ok(());
+                }
+                // Left behind by an instance that exited without 'hesha stop'
+                PidFileState::Stale(_) => std::fs::remove_file(&pid_file)?,
+                PidFileState::Missing => {}
             }
             
-            // Find the issuer-node binary
-            let issuer_bin = find_issuer_binary()?;
-            
-            // Start in background
-            let child = Command::new(&issuer_bin)
-                .env("HESHA_CONFIG_DIR", &config_dir)
-                .stdout(Stdio::null())
-                .stderr(Stdio::null())
-                .spawn()?;
-            
-            // Save PID
-            std::fs::write(&pid_file, child.id().to_string())?;
+            let pid = spawn_daemon(&config_dir)?;
             
-            output::success(&format!("Issuer node started in background (PID: {})", child.id()));
-            println!("\nEndpoints:");
-            println!("  http://localhost:3000/attest");
-            println!("  http://localhost:3000/.well-known/hesha/pubkey.json");
-            println!("\nRun 'hesha stop' to stop the issuer");
+            output::success(&format!("Issuer node started in background (PID: {})", pid));
+            print_daemon_endpoints();
         } else {
             // Run in foreground using cargo
             let status = Command::new("cargo")
@@ -84,15 +95,65 @@ impl StartCmd {
     }
 }
 
+/// What a PID file says about the issuer node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PidFileState {
+    /// There is no PID file.
+    Missing,
+    /// The recorded process is a running issuer node.
+    Running(u32),
+    /// The recorded process has exited or its PID now belongs to another
+    /// program. `None` if the file doesn't hold a PID at all.
+    Stale(Option<u32>),
+}
+
+/// Check the PID file against the running processes.
+pub fn check_pid_file(pid_file: &Path) -> PidFileState {
+    let Ok(contents) = std::fs::read_to_string(pid_file) else {
+        return if pid_file.exists() { PidFileState::Stale(None) } else { PidFileState::Missing };
+    };
+    match contents.trim().parse() {
+        Ok(pid) if process::is_issuer(pid) => PidFileState::Running(pid),
+        Ok(pid) => PidFileState::Stale(Some(pid)),
+        Err(_) => PidFileState::Stale(None),
+    }
+}
+
+/// Start the issuer node in the background and record its PID.
+pub fn spawn_daemon(config_dir: &Path) -> anyhow::Result<u32> {
+    // Find the issuer-node binary
+    let issuer_bin = find_issuer_binary()?;
+    
+    // Start in background
+    let child = process::spawn_detached(
+        Command::new(&issuer_bin).env("HESHA_CONFIG_DIR", config_dir),
+    )?;
+    
+    // Save PID
+    std::fs::write(config_dir.join("issuer.pid"), child.id().to_string())?;
+    
+    Ok(child.id())
+}
+
+/// Print where a background issuer node can be reached.
+pub fn print_daemon_endpoints() {
+    println!("\nEndpoints:");
+    println!("  http://localhost:3000/attest");
+    println!("  http://localhost:3000/.well-known/hesha/pubkey.json");
+    println!("\nRun 'hesha stop' to stop the issuer");
+}
+
 fn find_issuer_binary() -> anyhow::Result<PathBuf> {
+    let bin_name = format!("issuer-node{}", std::env::consts::EXE_SUFFIX);
+    
     // Try release build first
-    let release_path = PathBuf::from("target/release/issuer-node");
+    let release_path = PathBuf::from("target/release").join(&bin_name);
     if release_path.exists() {
         return Ok(release_path);
     }
     
     // Try debug build
-    let debug_path = PathBuf::from("target/debug/issuer-node");
+    let debug_path = PathBuf::from("target/debug").join(&bin_name);
     if debug_path.exists() {
         return Ok(debug_path);
     }
@@ -105,6 +166,243 @@ fn find_issuer_binary() -> anyhow::Result<PathBuf> {
     anyhow::bail!("Could not find issuer-node binary. Run 'cargo build --release' first.");
 }
 
+/// Write a systemd unit for the issuer node and print how to enable it.
+fn install_systemd_unit(
+    name: &str,
+    config_dir: &Path,
+    unit_dir: &Path,
+    service_user: Option<String>,
+) -> anyhow::Result<()> {
+    // systemd needs absolute paths
+    let issuer_bin = find_issuer_binary()?.canonicalize()?;
+    let config_dir = config_dir.canonicalize()?;
+    let user = match service_user {
+        Some(user) => user,
+        None => std::env::var("USER")
+            .map_err(|_| anyhow::anyhow!("Could not determine the current user; pass --service-user"))?,
+    };
+    
+    let unit_name = format!("hesha-issuer-{}.service", name);
+    let unit_path = unit_dir.join(&unit_name);
+    let config_path = config_dir.join("config").join("issuer.toml");
+    let config = IssuerConfig::from_file(&config_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", config_path.display(), e))?;
+    let unit = systemd_unit(name, &issuer_bin, &config_dir, &user, &config);
+    let socket = match &config.node.control_socket {
+        Some(socket) => socket.clone(),
+        None => Path::new("/run").join(systemd_dir_name(name)).join(CONTROL_SOCKET_FILE),
+    };
+    
+    std::fs::write(&unit_path, unit).map_err(|e| {
+        anyhow::anyhow!("Failed to write {}: {} (try running with sudo or --unit-dir)", unit_path.display(), e)
+    })?;
+    
+    if output::is_json() {
+        return output::json(&serde_json::json!({
+            "unit": unit_name,
+            "unit_file": unit_path,
+            "binary": issuer_bin,
+            "config_dir": config_dir,
+            "user": user,
+            "control_socket": socket,
+        }));
+    }
+    
+    output::success(&format!("Wrote systemd unit to {}", unit_path.display()));
+    println!("\nEnable and start the issuer node with:");
+    println!("  sudo systemctl daemon-reload");
+    println!("  sudo systemctl enable --now {}", unit_name);
+    println!("\nCheck its status and logs with:");
+    println!("  systemctl status {}", unit_name);
+    println!("  journalctl -u {} -f", unit_name);
+    println!("\nReach its admin console with:");
+    println!("  sudo hesha admin -n {} --socket {} status", name, socket.display());
+    
+    Ok(())
+}
+
+/// Directory under /run and /var/lib that systemd gives an issuer's unit.
+fn systemd_dir_name(name: &str) -> String {
+    format!("hesha/{}", name)
+}
+
+/// Render the systemd unit for an issuer configuration.
+///
+/// The node gets a runtime directory for its console socket and a state
+/// directory, also its working directory, for relative SQLite and ACME
+/// cache paths. Absolute paths outside them are made writable explicitly.
+fn systemd_unit(name: &str, issuer_bin: &Path, config_dir: &Path, user: &str, config: &IssuerConfig) -> String {
+    let dir_name = systemd_dir_name(name);
+    
+    let mut writable: Vec<PathBuf> = [&config.node.storage_url, &config.node.state_url]
+        .into_iter()
+        .flatten()
+        .filter_map(|url| sqlite_path(url))
+        .filter_map(|path| path.parent().map(Path::to_path_buf))
+        .collect();
+    writable.extend(config.node.control_socket.as_deref().and_then(Path::parent).map(Path::to_path_buf));
+    if let Some(acme) = &config.acme {
+        writable.push(PathBuf::from(&acme.cache_dir));
+    }
+    writable.retain(|path| path.is_absolute());
+    writable.sort();
+    writable.dedup();
+    let read_write_paths: String = writable
+        .iter()
+        .map(|path| format!("ReadWritePaths=-{}\n", path.display()))
+        .collect();
+    
+    // HTTPS and ACME challenges are normally served on 443 and 80
+    let port = config.node.bind_address.map_or(config.port, |address| address.port());
+    let capabilities = if config.tls.is_some() || config.acme.is_some() || port < 1024 {
+        "AmbientCapabilities=CAP_NET_BIND_SERVICE\nCapabilityBoundingSet=CAP_NET_BIND_SERVICE"
+    } else {
+        "CapabilityBoundingSet="
+    };
+    
+    format!(
+        r#"[Unit]
+Description=Hesha issuer node ({name})
+Documentation=https://github.com/heshaorg/hesha
+After=network-online.target
+Wants=network-online.target
+
+[Service]
+Type=simple
+User={user}
+Environment="HESHA_CONFIG_DIR={config_dir}"
+ExecStart="{issuer_bin}"
+Restart=on-failure
+RestartSec=5
+RuntimeDirectory={dir_name}
+StateDirectory={dir_name}
+WorkingDirectory=%S/{dir_name}
 
+# Sandboxing: the node reads its configuration and keys, and only writes
+# its runtime and state directories
+NoNewPrivileges=true
+ProtectSystem=strict
+ProtectHome=read-only
+ReadOnlyPaths={config_dir}
+{read_write_paths}PrivateTmp=true
+PrivateDevices=true
+ProtectKernelTunables=true
+ProtectKernelModules=true
+ProtectControlGroups=true
+RestrictAddressFamilies=AF_INET AF_INET6 AF_UNIX
+RestrictNamespaces=true
+RestrictRealtime=true
+LockPersonality=true
+MemoryDenyWriteExecute=true
+{capabilities}
+UMask=0077
 
+[Install]
+WantedBy=multi-user.target
+"#,
+        name = name,
+        user = user,
+        config_dir = config_dir.display(),
+        issuer_bin = issuer_bin.display(),
+        dir_name = dir_name,
+        read_write_paths = read_write_paths,
+        capabilities = capabilities,
+    )
+}
 
+/// File behind a `sqlite:` storage URL.
+fn sqlite_path(url: &str) -> Option<PathBuf> {
+    let path = url.strip_prefix("sqlite:")?;
+    let path = path.strip_prefix("//").unwrap_or(path);
+    let path = path.split('?').next().unwrap_or_default();
+    (!path.is_empty()).then(|| PathBuf::from(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_check_pid_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let pid_file = temp.path().join("issuer.pid");
+        assert_eq!(check_pid_file(&pid_file), PidFileState::Missing);
+        
+        std::fs::write(&pid_file, "not a pid").unwrap();
+        assert_eq!(check_pid_file(&pid_file), PidFileState::Stale(None));
+        
+        // A live process that isn't an issuer node
+        let pid = std::process::id();
+        std::fs::write(&pid_file, format!("{}\n", pid)).unwrap();
+        assert_eq!(check_pid_file(&pid_file), PidFileState::Stale(Some(pid)));
+        
+        // A process that has exited
+        #[cfg(unix)]
+        {
+            let mut child = Command::new("true").spawn().unwrap();
+            let pid = child.id();
+            child.wait().unwrap();
+            std::fs::write(&pid_file, pid.to_string()).unwrap();
+            assert_eq!(check_pid_file(&pid_file), PidFileState::Stale(Some(pid)));
+        }
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_check_pid_file_running() {
+        let temp = tempfile::tempdir().unwrap();
+        let pid_file = temp.path().join("issuer.pid");
+        let pid = process::spawn_fake_issuer(temp.path());
+        std::fs::write(&pid_file, pid.to_string()).unwrap();
+        assert_eq!(check_pid_file(&pid_file), PidFileState::Running(pid));
+        process::kill(pid).unwrap();
+    }
+    
+    #[test]
+    fn test_systemd_unit() {
+        let mut config: IssuerConfig = toml::from_str(
+            r#"
+            port = 8443
+            
+            [identity]
+            name = "Test Issuer"
+            trust_domain = "issuer.example.com"
+            contact_email = "admin@example.com"
+            public_key_base64url = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
+            key_id = "default"
+            created_at = "2024-01-01T00:00:00Z"
+            
+            [node]
+            storage_url = "sqlite:/var/lib/hesha-data/issuer.db?mode=rwc"
+            control_socket = "/srv/hesha/console/issuer.sock"
+            
+            [acme]
+            contact = "ops@example.com"
+            "#,
+        )
+        .unwrap();
+        let unit = systemd_unit("prod", Path::new("/usr/bin/issuer-node"), Path::new("/etc/hesha"), "hesha", &config);
+        let lines: Vec<&str> = unit.lines().collect();
+        for line in [
+            "RuntimeDirectory=hesha/prod",
+            "StateDirectory=hesha/prod",
+            "WorkingDirectory=%S/hesha/prod",
+            "ReadOnlyPaths=/etc/hesha",
+            "ReadWritePaths=-/srv/hesha/console",
+            "ReadWritePaths=-/var/lib/hesha-data",
+            "AmbientCapabilities=CAP_NET_BIND_SERVICE",
+            "CapabilityBoundingSet=CAP_NET_BIND_SERVICE",
+        ] {
+            assert!(lines.contains(&line), "missing {:?} in:\n{}", line, unit);
+        }
+        // The relative ACME cache lands in the state directory
+        assert!(!unit.contains("ReadWritePaths=-acme"));
+        
+        config.acme = None;
+        config.node = Default::default();
+        let unit = systemd_unit("dev", Path::new("/usr/bin/issuer-node"), Path::new("/etc/hesha"), "hesha", &config);
+        assert!(unit.lines().any(|line| line == "CapabilityBoundingSet="));
+        assert!(!unit.contains("AmbientCapabilities"));
+        assert!(!unit.contains("ReadWritePaths"));
+    }
+}
@@ -0,0 +1,63 @@
+//! Status command for checking on the issuer node.
+
+use clap::Args;
+use colored::*;
+use crate::commands::start::{check_pid_file, PidFileState};
+use crate::{output, process};
+
+/// Show whether the Hesha issuer node is running.
+#[derive(Debug, Args)]
+pub struct StatusCmd {
+    /// Name of the issuer configuration.
+    /// Defaults to 'default'
+    #[arg(short, long, default_value = "default")]
+    name: String,
+}
+
+impl StatusCmd {
+    pub fn execute(self) -> anyhow::Result<()> {
+        // Determine config directory
+        let config_dir = dirs::home_dir()
+            .expect("Could not find home directory")
+            .join(".hesha")
+            .join("issuer")
+            .join(&self.name);
+        
+        let pid_file = config_dir.join("issuer.pid");
+        let state = check_pid_file(&pid_file);
+        let (pid, running) = match state {
+            PidFileState::Running(pid) => (Some(pid), true),
+            PidFileState::Stale(pid) => (pid, false),
+            PidFileState::Missing => (None, false),
+        };
+        
+        if output::is_json() {
+            return output::json(&serde_json::json!({
+                "name": self.name,
+                "running": running,
+                "pid": pid,
+                "config_dir": config_dir,
+            }));
+        }
+        
+        match state {
+            PidFileState::Running(pid) => {
+                println!("Issuer '{}': {} (PID: {})", self.name, "running".green().bold(), pid);
+            }
+            PidFileState::Stale(Some(pid)) if process::is_running(pid) => {
+                println!("Issuer '{}': {}", self.name, "stopped".red().bold());
+                output::warning(&format!(
+                    "Stale PID file: process {} is not an issuer node; 'hesha stop' will clean it up",
+                    pid
+                ));
+            }
+            PidFileState::Stale(_) => {
+                println!("Issuer '{}': {}", self.name, "stopped".red().bold());
+                output::warning("Stale PID file; 'hesha stop' will clean it up");
+            }
+            PidFileState::Missing => println!("Issuer '{}': {}", self.name, "stopped".red().bold()),
+        }
+        
+        Ok(())
+    }
+}
@@ -1,7 +1,12 @@
 //! Stop command for terminating the issuer node.
 
 use clap::Args;
-use crate::output;
+use std::time::Duration;
+use crate::commands::start::{check_pid_file, PidFileState};
+use crate::{output, process};
+
+/// How long to wait for a killed process to disappear.
+const KILL_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Stop the Hesha issuer node.
 #[derive(Debug, Args)]
@@ -10,6 +15,14 @@ pub struct StopCmd {
     /// Defaults to 'default'
     #[arg(short, long, default_value = "default")]
     name: String,
+    
+    /// Seconds to let in-flight requests drain before killing the node
+    #[arg(long, value_name = "SECS", default_value_t = 30)]
+    timeout: u64,
+    
+    /// Kill the node immediately without draining requests
+    #[arg(long, conflicts_with = "timeout")]
+    force: bool,
 }
 
 impl StopCmd {
@@ -23,47 +36,56 @@ impl StopCmd {
         
         let pid_file = config_dir.join("issuer.pid");
         
-        if !pid_file.exists() {
-            output::info("No running issuer node found");
-            return Ok(());
-        }
-        
-        // Read PID
-        let pid_str = std::fs::read_to_string(&pid_file)?;
-        let pid: u32 = pid_str.trim().parse()?;
-        
-        output::info(&format!("Stopping issuer node (PID: {})", pid));
-        
-        // Send terminate signal
-        #[cfg(unix)]
-        {
-            use nix::sys::signal::{self, Signal};
-            use nix::unistd::Pid;
-            
-            match signal::kill(Pid::from_raw(pid as i32), Signal::SIGTERM) {
-                Ok(_) => {
-                    // Remove PID file
-                    std::fs::remove_file(&pid_file)?;
-                    output::success("Issuer node stopped");
-                }
-                Err(e) => {
-                    // Process might have already exited
-                    if e == nix::errno::Errno::ESRCH {
-                        std::fs::remove_file(&pid_file)?;
-                        output::warning("Issuer node was not running (cleaned up PID file)");
-                    } else {
-                        output::error(&format!("Failed to stop issuer: {}", e));
-                    }
+        let pid = match check_pid_file(&pid_file) {
+            PidFileState::Running(pid) => pid,
+            PidFileState::Missing => {
+                output::info("No running issuer node found");
+                return Ok(());
+            }
+            PidFileState::Stale(pid) => {
+                std::fs::remove_file(&pid_file)?;
+                match pid {
+                    Some(pid) if process::is_running(pid) => output::warning(&format!(
+                        "PID {} now belongs to another process; not signaling it (cleaned up PID file)",
+                        pid
+                    )),
+                    _ => output::warning("Issuer node was not running (cleaned up PID file)"),
                 }
+                return Ok(());
             }
+        };
+        
+        let signaled = if self.force {
+            output::info(&format!("Killing issuer node (PID: {})", pid));
+            process::kill(pid)?
+        } else {
+            output::info(&format!("Stopping issuer node (PID: {}), waiting up to {}s for requests to drain", pid, self.timeout));
+            process::terminate(pid)?
+        };
+        
+        if !signaled {
+            // Process exited since the check
+            std::fs::remove_file(&pid_file)?;
+            output::warning("Issuer node was not running (cleaned up PID file)");
+            return Ok(());
         }
         
-        #[cfg(not(unix))]
-        {
-            output::error("Stop command is not supported on this platform yet");
-            println!("Please stop the issuer node manually");
+        let timeout = if self.force { KILL_TIMEOUT } else { Duration::from_secs(self.timeout) };
+        if !process::wait_for_exit(pid, timeout) {
+            anyhow::ensure!(!self.force, "Issuer node (PID: {}) is still running after being killed", pid);
+            output::warning(&format!("Issuer node did not exit within {}s; killing it", self.timeout));
+            process::kill(pid)?;
+            anyhow::ensure!(
+                process::wait_for_exit(pid, KILL_TIMEOUT),
+                "Issuer node (PID: {}) is still running after being killed",
+                pid
+            );
         }
         
+        // Only forget the PID once the process is really gone
+        std::fs::remove_file(&pid_file)?;
+        output::success("Issuer node stopped");
+        
         Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,207 @@
+//! Attestation wallet commands.
+
+use crate::keystore::KeyStore;
+use crate::{input, output};
+use crate::wallet::{Wallet, WalletEntry};
+use chrono::Duration;
+use clap::{Args, Subcommand};
+use colored::*;
+use hesha_core::parse_attestation_jwt;
+use std::{fs, path::PathBuf};
+
+/// Attestations expiring within this many days are highlighted.
+const EXPIRY_WARNING_DAYS: i64 = 7;
+
+/// Manage attestations in the local wallet.
+#[derive(Debug, Args)]
+pub struct WalletCmd {
+    #[command(subcommand)]
+    command: WalletCommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum WalletCommands {
+    /// Add an attestation to the wallet
+    Add {
+        /// Attestation file, JWT string, or '-' for stdin
+        #[arg(value_name = "FILE_OR_JWT")]
+        attestation: String,
+
+        /// Label for the attestation
+        #[arg(short, long)]
+        label: Option<String>,
+
+        /// Stored key the attestation was issued to (detected if omitted)
+        #[arg(short, long, value_name = "NAME")]
+        key: Option<String>,
+    },
+
+    /// List attestations in the wallet
+    List {
+        /// Only show attestations expiring within this many days
+        #[arg(long, value_name = "DAYS")]
+        expiring_within: Option<i64>,
+    },
+
+    /// Show an attestation's details
+    Show {
+        /// Attestation ID (or unique prefix), label or proxy number
+        #[arg(value_name = "ID")]
+        id: String,
+    },
+
+    /// Remove an attestation from the wallet
+    Remove {
+        /// Attestation ID (or unique prefix), label or proxy number
+        #[arg(value_name = "ID")]
+        id: String,
+    },
+
+    /// Print or save an attestation's JWT
+    Export {
+        /// Attestation ID (or unique prefix), label or proxy number
+        #[arg(value_name = "ID")]
+        id: String,
+
+        /// Write the JWT to a file instead of stdout
+        #[arg(short = 'o', long = "out", value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+}
+
+impl WalletCmd {
+    pub fn execute(self) -> anyhow::Result<()> {
+        let wallet = Wallet::open_default();
+
+        match self.command {
+            WalletCommands::Add { attestation, label, key } => add(&wallet, &attestation, label, key),
+            WalletCommands::List { expiring_within } => list(&wallet, expiring_within),
+            WalletCommands::Show { id } => show(&wallet, &id),
+            WalletCommands::Remove { id } => {
+                let entry = wallet.find(&id)?;
+                wallet.remove(&entry.id)?;
+                output::success(&format!("Removed attestation {} ({})", entry.id, entry.proxy_number));
+                Ok(())
+            }
+            WalletCommands::Export { id, out } => {
+                let entry = wallet.find(&id)?;
+                match out {
+                    Some(path) => {
+                        fs::write(&path, &entry.jwt)?;
+                        output::success(&format!("Saved attestation to {}", path.display()));
+                    }
+                    None => println!("{}", entry.jwt),
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn add(wallet: &Wallet, attestation: &str, label: Option<String>, key: Option<String>) -> anyhow::Result<()> {
+    let jwt = input::read_attestation(attestation)?;
+
+    let user_pubkey = parse_attestation_jwt(jwt.trim())?.user_pubkey;
+    let store = KeyStore::open_default();
+
+    let key = match key {
+        Some(name) => {
+            if store.get(&name)?.public_key != user_pubkey {
+                anyhow::bail!("Attestation was not issued to key '{}'", name);
+            }
+            Some(name)
+        }
+        // Find the stored key the attestation is bound to, if any
+        None => store
+            .list()?
+            .into_iter()
+            .find(|stored| stored.public_key == user_pubkey)
+            .map(|stored| stored.name),
+    };
+
+    let entry = WalletEntry::from_jwt(&jwt, label, key)?;
+    wallet.add(&entry)?;
+
+    if output::is_json() {
+        return output::json(&entry);
+    }
+
+    output::success(&format!("Added attestation {} to wallet", entry.id));
+    println!("  Proxy number: {}", entry.proxy_number.yellow());
+    match &entry.key {
+        Some(key) => println!("  Key:          {}", key.cyan()),
+        None => output::warning("No stored key matches this attestation's public key"),
+    }
+    Ok(())
+}
+
+fn list(wallet: &Wallet, expiring_within: Option<i64>) -> anyhow::Result<()> {
+    let mut entries = wallet.list()?;
+    if let Some(days) = expiring_within {
+        entries.retain(|entry| entry.expires_within(Duration::days(days)));
+    }
+
+    if output::is_json() {
+        return output::json(&entries);
+    }
+
+    if entries.is_empty() {
+        match expiring_within {
+            Some(days) => output::info(&format!("No attestations expiring within {} days", days)),
+            None => output::info("No attestations in wallet"),
+        }
+        return Ok(());
+    }
+
+    println!("{}", "Wallet".bold());
+    println!("{}", "══════".bold());
+    for entry in &entries {
+        println!();
+        let title = entry.label.as_deref().unwrap_or(&entry.proxy_number);
+        println!("{}  {}", title.cyan().bold(), entry.id.dimmed());
+        println!("  Issuer:       {}", entry.issuer);
+        println!("  Proxy number: {}", entry.proxy_number.yellow());
+        println!("  Key:          {}", entry.key.as_deref().unwrap_or("unknown"));
+        println!("  Expires:      {}", format_expiry(entry));
+    }
+
+    Ok(())
+}
+
+fn show(wallet: &Wallet, id: &str) -> anyhow::Result<()> {
+    let entry = wallet.find(id)?;
+
+    if output::is_json() {
+        return output::json(&entry);
+    }
+
+    println!("{}", "Attestation".cyan().bold());
+    println!("{}", "═══════════".cyan());
+    println!("  ID:           {}", entry.id);
+    if let Some(label) = &entry.label {
+        println!("  Label:        {}", label);
+    }
+    println!("  Issuer:       {}", entry.issuer);
+    println!("  Proxy number: {}", entry.proxy_number.yellow());
+    println!("  Key:          {}", entry.key.as_deref().unwrap_or("unknown"));
+    println!("  Issued:       {}", entry.issued_at);
+    println!("  Expires:      {}", format_expiry(&entry));
+    println!("  Added:        {}", entry.added_at);
+    println!("\n{}", "JWT:".yellow());
+    println!("{}", entry.jwt);
+
+    Ok(())
+}
+
+/// Format the expiry time, highlighting expired and soon-to-expire entries.
+fn format_expiry(entry: &WalletEntry) -> String {
+    let expires = entry.expires_at.format("%Y-%m-%d %H:%M UTC").to_string();
+    if entry.is_expired() {
+        format!("{} {}", expires, "(expired)".red().bold())
+    } else if entry.expires_within(Duration::days(EXPIRY_WARNING_DAYS)) {
+        let days = (entry.expires_at - chrono::Utc::now()).num_days();
+        format!("{} {}", expires, format!("(expires in {} days)", days).yellow())
+    } else {
+        expires
+    }
+}
@@ -1,10 +1,20 @@
 //! Configuration and key management.
 
 use anyhow::Context;
+use dialoguer::Password;
 use dirs::home_dir;
 use hesha_types::{KeyPair, PrivateKey};
-use hesha_crypto::keypair_from_private;
-use std::{fs, path::PathBuf};
+use hesha_crypto::{decrypt_keypair, keypair_from_private, EncryptedKey};
+use crate::input;
+use crate::keystore::{KeyStore, DEFAULT_KEY_NAME};
+use crate::os_keyring;
+use std::{fs, path::{Path, PathBuf}};
+
+/// Environment variable holding the passphrase for encrypted keys.
+pub const PASSPHRASE_ENV: &str = "HESHA_KEY_PASSPHRASE";
+
+/// Environment variable holding the passphrase for issuer backups.
+pub const BACKUP_PASSPHRASE_ENV: &str = "HESHA_BACKUP_PASSPHRASE";
 
 /// Get default config directory.
 pub fn config_dir() -> PathBuf {
@@ -13,12 +23,66 @@ pub fn config_dir() -> PathBuf {
         .join(".hesha")
 }
 
-/// Load keypair from file or environment.
-pub fn load_keypair(key_path: Option<&str>) -> anyhow::Result<KeyPair> {
-    // Try explicit path first
-    if let Some(path) = key_path {
-        let key_data = fs::read_to_string(path)
-            .with_context(|| format!("Failed to read key file: {}", path))?;
+/// Write a file only its owner can read, replacing any existing one.
+/// 
+/// The contents go to a temporary file created with mode 0600 (Unix only)
+/// which is then renamed into place, so the data is never readable by
+/// others and a crash never leaves a half-written file behind.
+pub fn write_private(path: &Path, contents: &str) -> anyhow::Result<()> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("Not a file path: {}", path.display()))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(file_name);
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    
+    // A leftover from an interrupted write may have other permissions
+    match fs::remove_file(&tmp_path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    
+    let written = options.open(&tmp_path).and_then(|mut file| {
+        use std::io::Write;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&tmp_path, path)) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(anyhow::Error::new(e).context(format!("Failed to write {}", path.display())));
+    }
+    
+    Ok(())
+}
+
+/// Load keypair from file, key store or environment.
+/// 
+/// `key` may be a path to a key file, the name of a stored key, or
+/// `keyring:<name>` for a key in the OS keyring.
+pub fn load_keypair(key: Option<&str>) -> anyhow::Result<KeyPair> {
+    let store = KeyStore::open_default();
+    
+    // Try explicit path or key name first
+    if let Some(key) = key {
+        if let Some(name) = os_keyring::parse_reference(key) {
+            return os_keyring::load(name);
+        }
+        
+        if !input::is_stdin(key) && !Path::new(key).exists() && store.exists(key) {
+            return load_keypair_from_string(&store.read(key)?);
+        }
+        
+        let key_data = input::read_input(key)
+            .with_context(|| format!("Failed to read key file: {}", key))?;
         
         return load_keypair_from_string(&key_data);
     }
@@ -28,22 +92,76 @@ pub fn load_keypair(key_path: Option<&str>) -> anyhow::Result<KeyPair> {
         return load_keypair_from_string(&key_data);
     }
     
-    // Try default location
-    let default_path = config_dir().join("key.json");
-    if default_path.exists() {
-        let key_data = fs::read_to_string(&default_path)
-            .with_context(|| format!("Failed to read default key file: {:?}", default_path))?;
+    // Try the default key in the key store
+    if store.exists(DEFAULT_KEY_NAME) {
+        return load_keypair_from_string(&store.read(DEFAULT_KEY_NAME)?);
+    }
+    
+    // Fall back to the legacy single key file
+    let legacy_path = config_dir().join("key.json");
+    if legacy_path.exists() {
+        let key_data = fs::read_to_string(&legacy_path)
+            .with_context(|| format!("Failed to read key file: {:?}", legacy_path))?;
         
         return load_keypair_from_string(&key_data);
     }
     
     anyhow::bail!(
-        "No private key found. Provide --key, set HESHA_PRIVATE_KEY, or run 'hesha keygen'"
+        "No private key found. Provide --key, set HESHA_PRIVATE_KEY, or run 'hesha key rotate' / 'hesha key import'"
     )
 }
 
-/// Load keypair from string (JSON or base64).
-fn load_keypair_from_string(data: &str) -> anyhow::Result<KeyPair> {
+/// Get the key passphrase from the environment or by prompting.
+/// 
+/// When `confirm` is set the user must enter the passphrase twice.
+pub fn read_passphrase(confirm: bool) -> anyhow::Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    
+    anyhow::ensure!(
+        input::is_interactive(),
+        "Key is encrypted; set {} to provide the passphrase non-interactively",
+        PASSPHRASE_ENV
+    );
+    
+    let mut prompt = Password::new().with_prompt("Key passphrase");
+    if confirm {
+        prompt = prompt.with_confirmation("Confirm passphrase", "Passphrases do not match");
+    }
+    
+    Ok(prompt.interact()?)
+}
+
+/// Get the backup passphrase from the environment or by prompting.
+pub fn read_backup_passphrase(confirm: bool) -> anyhow::Result<String> {
+    if let Ok(passphrase) = std::env::var(BACKUP_PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    
+    anyhow::ensure!(
+        input::is_interactive(),
+        "Set {} to provide the backup passphrase non-interactively",
+        BACKUP_PASSPHRASE_ENV
+    );
+    
+    let mut prompt = Password::new().with_prompt("Backup passphrase");
+    if confirm {
+        prompt = prompt.with_confirmation("Confirm passphrase", "Passphrases do not match");
+    }
+    
+    Ok(prompt.interact()?)
+}
+
+/// Load keypair from string (encrypted JSON, JSON or base64).
+pub fn load_keypair_from_string(data: &str) -> anyhow::Result<KeyPair> {
+    // Encrypted key files need the passphrase
+    if EncryptedKey::is_encrypted(data) {
+        let encrypted = EncryptedKey::from_json(data)?;
+        let passphrase = read_passphrase(false)?;
+        return Ok(decrypt_keypair(&encrypted, &passphrase)?);
+    }
+    
     // Try JSON format first
     if data.trim().starts_with('{') {
         let json: serde_json::Value = serde_json::from_str(data)?;
@@ -0,0 +1,125 @@
+//! Process exit codes.
+//!
+//! Codes are stable so scripts can branch on the failure class:
+//!
+//! | Code | Meaning                                      |
+//! |------|----------------------------------------------|
+//! | 0    | Success                                      |
+//! | 1    | Other error                                  |
+//! | 2    | Invalid attestation (format or signature)    |
+//! | 3    | Attestation expired                          |
+//! | 4    | Network error                                |
+//! | 5    | Attestation revoked                          |
+//! | 6    | Phone number does not match the attestation  |
+//! | 64   | Invalid command-line usage                   |
+
+use hesha_client::ClientError;
+use hesha_types::HeshaError;
+
+/// Other error.
+pub const FAILURE: i32 = 1;
+/// Invalid attestation (format or signature).
+pub const INVALID_ATTESTATION: i32 = 2;
+/// Attestation expired.
+pub const EXPIRED: i32 = 3;
+/// Network error.
+pub const NETWORK: i32 = 4;
+/// Attestation revoked.
+pub const REVOKED: i32 = 5;
+/// Phone number does not match the attestation.
+pub const PHONE_MISMATCH: i32 = 6;
+/// Invalid command-line usage.
+pub const USAGE: i32 = 64;
+
+/// Verification failures that aren't errors of the attestation itself.
+#[derive(Debug, thiserror::Error)]
+pub enum VerifyFailure {
+    /// The issuer has revoked the attestation.
+    #[error("Attestation {0} has been revoked")]
+    Revoked(String),
+
+    /// The attestation was issued for a different phone number.
+    #[error("Phone number does not match the attestation")]
+    PhoneMismatch,
+
+    /// Some attestations in a batch failed; `code` is their shared class.
+    #[error("{failed} of {total} attestations failed verification")]
+    Batch {
+        /// Number of failed attestations.
+        failed: usize,
+        /// Number of attestations checked.
+        total: usize,
+        /// Exit code for the batch.
+        code: i32,
+    },
+}
+
+/// Map an error to its exit code.
+///
+/// The first cause in the chain with a known class decides the code.
+pub fn code_for(error: &anyhow::Error) -> i32 {
+    error.chain().find_map(classify).unwrap_or(FAILURE)
+}
+
+fn classify(cause: &(dyn std::error::Error + 'static)) -> Option<i32> {
+    if let Some(failure) = cause.downcast_ref::<VerifyFailure>() {
+        return Some(match failure {
+            VerifyFailure::Revoked(_) => REVOKED,
+            VerifyFailure::PhoneMismatch => PHONE_MISMATCH,
+            VerifyFailure::Batch { code, .. } => *code,
+        });
+    }
+
+    if let Some(error) = cause.downcast_ref::<HeshaError>() {
+        return match error {
+            HeshaError::AttestationExpired(_) => Some(EXPIRED),
+            HeshaError::NetworkError(_) => Some(NETWORK),
+            // Undecodable JWT parts are a malformed attestation too
+            HeshaError::InvalidAttestation(_)
+            | HeshaError::SerializationError(_)
+            | HeshaError::InvalidSignature
+            | HeshaError::InvalidBindingProof
+            | HeshaError::InvalidNonce => Some(INVALID_ATTESTATION),
+            _ => None,
+        };
+    }
+
+    if let Some(error) = cause.downcast_ref::<ClientError>() {
+        return match error {
+            ClientError::HttpError(_) | ClientError::Timeout => Some(NETWORK),
+            _ => None,
+        };
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_code_for_each_class() {
+        let code = |error: anyhow::Error| code_for(&error);
+
+        assert_eq!(code(HeshaError::InvalidAttestation("bad".to_string()).into()), INVALID_ATTESTATION);
+        assert_eq!(code(HeshaError::SerializationError("bad json".to_string()).into()), INVALID_ATTESTATION);
+        assert_eq!(code(HeshaError::InvalidSignature.into()), INVALID_ATTESTATION);
+        assert_eq!(code(HeshaError::InvalidBindingProof.into()), INVALID_ATTESTATION);
+        assert_eq!(code(HeshaError::InvalidNonce.into()), INVALID_ATTESTATION);
+        assert_eq!(code(HeshaError::AttestationExpired(chrono::Utc::now()).into()), EXPIRED);
+        assert_eq!(code(HeshaError::NetworkError("down".to_string()).into()), NETWORK);
+        assert_eq!(code(ClientError::Timeout.into()), NETWORK);
+        assert_eq!(code(VerifyFailure::Revoked("jti".to_string()).into()), REVOKED);
+        assert_eq!(code(VerifyFailure::PhoneMismatch.into()), PHONE_MISMATCH);
+        assert_eq!(code(VerifyFailure::Batch { failed: 1, total: 2, code: EXPIRED }.into()), EXPIRED);
+
+        // Unclassified errors fall back, and context doesn't hide the cause
+        assert_eq!(code(HeshaError::ConfigError("bad".to_string()).into()), FAILURE);
+        assert_eq!(code(ClientError::InvalidUrl("bad".to_string()).into()), FAILURE);
+        assert_eq!(code(anyhow::anyhow!("other")), FAILURE);
+        let wrapped = Err::<(), _>(HeshaError::InvalidSignature).context("Verifying attestation").unwrap_err();
+        assert_eq!(code(wrapped), INVALID_ATTESTATION);
+    }
+}
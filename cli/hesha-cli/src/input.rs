@@ -0,0 +1,65 @@
+//! Reading command input from files, arguments or stdin.
+//!
+//! Any file argument may be `-` to read from stdin, so commands compose
+//! in pipelines (`cat att.jwt | hesha verify -a -`).
+
+use anyhow::Context;
+use std::fs;
+use std::io::{self, BufRead, BufReader, IsTerminal, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// File argument that reads from stdin.
+pub const STDIN: &str = "-";
+
+/// Set once stdin has been consumed.
+static STDIN_READ: AtomicBool = AtomicBool::new(false);
+
+/// Check if a file argument refers to stdin.
+pub fn is_stdin(path: &str) -> bool {
+    path == STDIN
+}
+
+/// Read a file argument, or all of stdin for `-`.
+pub fn read_input(path: &str) -> anyhow::Result<String> {
+    if !is_stdin(path) {
+        return fs::read_to_string(path).with_context(|| format!("Failed to read {}", path));
+    }
+
+    if STDIN_READ.swap(true, Ordering::SeqCst) {
+        anyhow::bail!("Only one argument can be read from stdin ('-')");
+    }
+    let mut data = String::new();
+    io::stdin()
+        .read_to_string(&mut data)
+        .context("Failed to read from stdin")?;
+    Ok(data)
+}
+
+/// Open a file argument, or stdin for `-`, to read incrementally.
+pub fn open_input(path: &str) -> anyhow::Result<Box<dyn BufRead + Send>> {
+    if !is_stdin(path) {
+        let file = fs::File::open(path).with_context(|| format!("Failed to open {}", path))?;
+        return Ok(Box::new(BufReader::new(file)));
+    }
+
+    if STDIN_READ.swap(true, Ordering::SeqCst) {
+        anyhow::bail!("Only one argument can be read from stdin ('-')");
+    }
+    Ok(Box::new(BufReader::new(io::stdin())))
+}
+
+/// Get an attestation JWT given inline, as a file path, or as `-`.
+pub fn read_attestation(attestation: &str) -> anyhow::Result<String> {
+    if attestation.starts_with("eyJ") {
+        return Ok(attestation.to_string());
+    }
+    Ok(read_input(attestation)?.trim().to_string())
+}
+
+/// Check if the user can answer prompts.
+///
+/// Prompts are skipped (and their values must come from flags or the
+/// environment) when stdin is not a terminal or has been read as input.
+pub fn is_interactive() -> bool {
+    !STDIN_READ.load(Ordering::SeqCst) && io::stdin().is_terminal()
+}
@@ -0,0 +1,230 @@
+//! Named key store under ~/.hesha/keys.
+//!
+//! Each key lives in its own `<name>.json` file holding either a plain
+//! (`private_key`/`public_key`) or passphrase-encrypted key, plus `name` and
+//! `created_at` metadata. Rotated keys are moved to `keys/archive/`.
+
+use crate::config::{config_dir, write_private};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use hesha_crypto::{encrypt_keypair, EncryptedKey};
+use hesha_types::{KeyPair, PublicKey};
+use serde_json::{json, Value};
+use std::{fs, path::{Path, PathBuf}};
+
+/// Name of the key used when none is specified.
+pub const DEFAULT_KEY_NAME: &str = "default";
+
+/// Summary of a key in the store.
+#[derive(Debug, Clone)]
+pub struct StoredKey {
+    /// Key name.
+    pub name: String,
+    /// When the key was created, if recorded.
+    pub created_at: Option<DateTime<Utc>>,
+    /// The public half of the key.
+    pub public_key: PublicKey,
+    /// Whether the private key is passphrase-encrypted.
+    pub encrypted: bool,
+    /// Location of the key file.
+    pub path: PathBuf,
+}
+
+/// Directory-backed store of named keys.
+pub struct KeyStore {
+    dir: PathBuf,
+}
+
+impl KeyStore {
+    /// Open the key store in the default location (~/.hesha/keys).
+    pub fn open_default() -> Self {
+        Self::new(config_dir().join("keys"))
+    }
+
+    /// Open a key store rooted at the given directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Path of the file for a named key.
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+
+    /// Check if a named key exists.
+    pub fn exists(&self, name: &str) -> bool {
+        is_valid_key_name(name) && self.path(name).is_file()
+    }
+
+    /// Read the raw contents of a named key file.
+    pub fn read(&self, name: &str) -> anyhow::Result<String> {
+        validate_key_name(name)?;
+        let path = self.path(name);
+        fs::read_to_string(&path)
+            .with_context(|| format!("Key '{}' not found in {}", name, self.dir.display()))
+    }
+
+    /// Get the summary of a named key.
+    pub fn get(&self, name: &str) -> anyhow::Result<StoredKey> {
+        let data = self.read(name)?;
+        summarize(name, &self.path(name), &data)
+    }
+
+    /// List all keys in the store, sorted by name.
+    pub fn list(&self) -> anyhow::Result<Vec<StoredKey>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut keys = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let data = fs::read_to_string(&path)?;
+            keys.push(summarize(name, &path, &data)?);
+        }
+
+        keys.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(keys)
+    }
+
+    /// Save a key pair under a name, optionally encrypting it.
+    pub fn save(
+        &self,
+        name: &str,
+        keypair: &KeyPair,
+        passphrase: Option<&str>,
+    ) -> anyhow::Result<PathBuf> {
+        validate_key_name(name)?;
+
+        let mut document = match passphrase {
+            Some(passphrase) => serde_json::to_value(encrypt_keypair(keypair, passphrase)?)?,
+            None => json!({
+                "private_key": keypair.private.to_base64(),
+                "public_key": keypair.public.to_base64(),
+            }),
+        };
+        document["name"] = json!(name);
+        document["created_at"] = json!(Utc::now().to_rfc3339());
+
+        self.write(name, &document)
+    }
+
+    /// Save an existing JSON key file under a name.
+    /// 
+    /// The key material is kept as is, encrypted or not; only the `name` and
+    /// a missing `created_at` are filled in.
+    pub fn save_document(&self, name: &str, data: &str) -> anyhow::Result<PathBuf> {
+        validate_key_name(name)?;
+
+        let mut document: Value = serde_json::from_str(data).context("Key file is not valid JSON")?;
+        anyhow::ensure!(document["public_key"].is_string(), "Key file has no public_key");
+        document["name"] = json!(name);
+        if !document["created_at"].is_string() {
+            document["created_at"] = json!(Utc::now().to_rfc3339());
+        }
+
+        self.write(name, &document)
+    }
+
+    /// Move a named key to the archive, returning its new location.
+    pub fn archive(&self, name: &str) -> anyhow::Result<PathBuf> {
+        validate_key_name(name)?;
+        let archive_dir = self.dir.join("archive");
+        fs::create_dir_all(&archive_dir)?;
+
+        let target = archive_dir.join(format!(
+            "{}-{}.json",
+            name,
+            Utc::now().format("%Y%m%dT%H%M%SZ")
+        ));
+        fs::rename(self.path(name), &target)
+            .with_context(|| format!("Failed to archive key '{}'", name))?;
+        Ok(target)
+    }
+
+    fn write(&self, name: &str, document: &Value) -> anyhow::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create key directory {}", self.dir.display()))?;
+
+        // Private keys are only readable by the owner
+        let path = self.path(name);
+        write_private(&path, &serde_json::to_string_pretty(document)?)
+            .with_context(|| format!("Failed to write key file {}", path.display()))?;
+
+        Ok(path)
+    }
+}
+
+/// Check if a key name is safe to use as a file name.
+pub fn is_valid_key_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Fail with a helpful message if a key name is invalid.
+pub fn validate_key_name(name: &str) -> anyhow::Result<()> {
+    if !is_valid_key_name(name) {
+        anyhow::bail!(
+            "Invalid key name '{}'. Use letters, digits, '-' or '_' (max 64 characters)",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Build a key summary from a key file without decrypting it.
+fn summarize(name: &str, path: &Path, data: &str) -> anyhow::Result<StoredKey> {
+    let json: Value = serde_json::from_str(data)
+        .with_context(|| format!("Key file {} is not valid JSON", path.display()))?;
+
+    let public_key = json["public_key"]
+        .as_str()
+        .with_context(|| format!("Key file {} has no public_key", path.display()))?;
+
+    let created_at = json["created_at"]
+        .as_str()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    Ok(StoredKey {
+        name: name.to_string(),
+        created_at,
+        public_key: PublicKey::from_base64(public_key)?,
+        encrypted: EncryptedKey::is_encrypted(data),
+        path: path.to_path_buf(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hesha_crypto::generate_keypair;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_key_files_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = KeyStore::new(dir.path());
+        let keypair = generate_keypair().unwrap();
+
+        // A world-readable leftover from an interrupted write is replaced
+        fs::write(dir.path().join(".default.json.tmp"), "stale").unwrap();
+        fs::set_permissions(dir.path().join(".default.json.tmp"), fs::Permissions::from_mode(0o644)).unwrap();
+
+        for _ in 0..2 {
+            let path = store.save(DEFAULT_KEY_NAME, &keypair, None).unwrap();
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        assert!(!dir.path().join(".default.json.tmp").exists());
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+}
@@ -2,9 +2,17 @@
 
 mod commands;
 mod config;
+mod exit;
+mod input;
+mod keystore;
+mod os_keyring;
 mod output;
+mod parallel;
+mod process;
+mod profile;
+mod wallet;
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(name = "hesha")]
@@ -16,6 +24,33 @@ The Hesha Protocol CLI allows you to:
 - Verify attestations and challenge responses
 - Inspect attestation details
 
+Use --output json for machine-readable results, --quiet to print only
+results and errors, and --profile NAME to take default issuer, key,
+scope and validity values from ~/.hesha/profiles.toml.
+
+Settings are layered: profile < environment < flags.
+
+Environment:
+  HESHA_PROFILE            Profile to use (--profile)
+  HESHA_ISSUER             Issuer URL for attest (--issuer)
+  HESHA_KEY                Key file or stored key name for attest (--key)
+  HESHA_SCOPE              Proxy number scope for attest (--scope)
+  HESHA_VALIDITY_DAYS      Attestation validity for attest (--validity-days)
+  HESHA_VERIFY_POLICY      Verification policy file for verify (--policy)
+  HESHA_PRIVATE_KEY        Private key contents, used when no key is given
+  HESHA_KEY_PASSPHRASE     Passphrase for encrypted keys
+  HESHA_BACKUP_PASSPHRASE  Passphrase for issuer backups
+
+Exit codes:
+  0   Success
+  1   Other error
+  2   Invalid attestation (format or signature)
+  3   Attestation expired
+  4   Network error
+  5   Attestation revoked
+  6   Phone number does not match the attestation
+  64  Invalid command-line usage
+
 For more information about the Hesha Protocol, visit: https://github.com/hesha-protocol
 ")]
 #[command(version)]
@@ -27,18 +62,80 @@ struct Cli {
     /// Enable verbose output
     #[arg(short, long, global = true)]
     verbose: bool,
+    
+    /// Output mode (text, json)
+    #[arg(long, global = true, value_parser = output::OutputArgParser, default_value = "text", value_name = "MODE")]
+    output: output::OutputArg,
+    
+    /// Only print results, warnings and errors
+    #[arg(short, long, global = true)]
+    quiet: bool,
+    
+    /// Profile from ~/.hesha/profiles.toml supplying default issuer, key and scope
+    #[arg(long, global = true, env = profile::PROFILE_ENV, value_name = "NAME")]
+    profile: Option<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Generate a new Ed25519 keypair for attestations
-    #[command(long_about = "\nGenerate a new Ed25519 keypair for use with the Hesha Protocol.\n\nThe private key is used to sign challenge responses, while the public key\nis included in attestation requests.\n\nOutput formats:\n  json   - JSON object with base64url-encoded keys (default)\n  hex    - Hexadecimal encoding\n  base64 - Base64url encoding (no padding)\n\nExamples:\n  # Generate and save to file\n  hesha keygen > keys.json\n  \n  # Generate in hex format\n  hesha keygen -f hex\n  \n  # Set as environment variable\n  export HESHA_PRIVATE_KEY=$(hesha keygen -f base64 | grep 'Private' | cut -d' ' -f3)\n")]
+    #[command(long_about = "\nGenerate a new Ed25519 keypair for use with the Hesha Protocol.\n\nThe private key is used to sign challenge responses, while the public key\nis included in attestation requests.\n\nOutput formats:\n  json   - JSON object with base64url-encoded keys (default)\n  hex    - Hexadecimal encoding\n  base64 - Base64url encoding (no padding)\n\nExamples:\n  # Generate and save to file\n  hesha keygen > keys.json\n  \n  # Generate in hex format\n  hesha keygen -f hex\n  \n  # Set as environment variable\n  export HESHA_PRIVATE_KEY=$(hesha keygen -f base64 | grep 'Private' | cut -d' ' -f3)\n  \n  # Generate a passphrase-encrypted key file\n  hesha keygen --encrypt > key.json\n  \n  # Keep the key in the OS keyring and use it by reference\n  hesha keygen --store keyring --name laptop\n  hesha attest -i https://issuer.example.com -p +1234567890 -s 1 -k keyring:laptop\n  \n  # Restore the same key on a new machine from a backup phrase\n  hesha keygen --from-mnemonic --derive-path \"m/0'\" > keys.json\n\nWith --from-mnemonic the phrase is read from HESHA_MNEMONIC, stdin or a\nprompt, and the key is derived with SLIP-0010 along --derive-path\n(default m/0'). A BIP-39 passphrase can be given in HESHA_MNEMONIC_PASSPHRASE.\n\nEncrypted keys are decrypted transparently when loaded; the passphrase is\nread from HESHA_KEY_PASSPHRASE or prompted for.\n")]
     Keygen {
         /// Output format (json, hex, base64)
         #[arg(short, long, default_value = "json", value_name = "FORMAT")]
         format: String,
+        
+        /// Encrypt the private key with a passphrase
+        #[arg(long, conflicts_with = "store")]
+        encrypt: bool,
+        
+        /// Where to put the generated key (stdout, keyring)
+        #[arg(long, value_enum, default_value = "stdout", value_name = "STORE")]
+        store: commands::keygen::KeyDestination,
+        
+        /// Name of the key in the OS keyring
+        #[arg(long, value_name = "NAME", default_value = "default")]
+        name: String,
+        
+        /// Replace a key already stored in the OS keyring under --name
+        #[arg(long)]
+        force: bool,
+        
+        /// Restore the key from a BIP-39 backup phrase instead of generating one
+        #[arg(long)]
+        from_mnemonic: bool,
+        
+        /// Derivation path for --from-mnemonic (hardened segments only)
+        #[arg(long, value_name = "PATH", requires = "from_mnemonic")]
+        derive_path: Option<String>,
     },
     
+    /// Manage named keys in ~/.hesha/keys
+    #[command(long_about = "
+Manage named keys in the local key store (~/.hesha/keys).
+
+Each key is stored as ~/.hesha/keys/<name>.json with its creation date.
+Commands that take --key accept either a file path or a stored key name,
+and fall back to the key named 'default'.
+
+Examples:
+  # Create (or replace) the default key
+  hesha key rotate
+  
+  # Import an existing key file under a name
+  hesha key import work keys.json
+  
+  # List stored keys with fingerprints
+  hesha key list
+  
+  # Print a key's public key
+  hesha key export work --public
+  
+  # Show the fingerprint of a stored key or key file
+  hesha key fingerprint work
+")]
+    Key(commands::key::KeyCmd),
+    
     /// Request attestation from an issuer
     #[command(long_about = "
 Request a proxy phone number attestation from an issuer.
@@ -56,33 +153,85 @@ Examples:
   # Save attestation to file
   hesha attest -i https://issuer.example.com -p +1234567890 -o attestation.jwt
   
+  # Print the result as JSON for scripts
+  hesha attest -i https://issuer.example.com -p +1234567890 -s 1 --output json
+  
   # Request with custom validity (7 days)
   hesha attest -i https://issuer.example.com -p +1234567890 -s 1 -d 7
+  
+  # Verify the number with an SMS code sent by the issuer
+  hesha attest -i https://issuer.example.com -p +1234567890 -s 1 --otp
+  
+  # Take issuer, key and scope from the 'work' profile
+  hesha --profile work attest -p +1234567890
+  
+  # Request attestations for every phone number in a CSV file
+  # (columns: phone, and optionally scope and validity_days)
+  hesha attest -i https://issuer.example.com -s 1 --batch phones.csv --jobs 8
+  
+  # Retry the rows that failed in a previous batch run
+  hesha attest -i https://issuer.example.com -s 1 --batch phones.csv --resume
+  
+  # Resume a request that failed on a flaky connection
+  hesha attest --resume-id 3f9c2a...
+
+Network failures are retried with exponential backoff (see --retries). Each
+request carries an idempotency key, so a retry never yields a second
+attestation. If all retries fail, the request is kept in ~/.hesha/pending
+and the error prints the reference to pass to --resume-id.
 ")]
     Attest {
         /// Issuer URL (e.g., https://issuer.example.com)
         #[arg(short, long, value_name = "URL")]
-        issuer: String,
+        issuer: Option<String>,
         
         /// Phone number to attest (E.164 format)
-        #[arg(short, long, value_name = "PHONE")]
-        phone: String,
+        #[arg(short, long, value_name = "PHONE", required_unless_present_any = ["batch", "resume_id"], conflicts_with = "batch")]
+        phone: Option<String>,
         
         /// Scope - country calling code for proxy number (e.g., 1, 44, 234)
         #[arg(short, long, value_name = "CODE")]
-        scope: String,
+        scope: Option<String>,
         
-        /// Private key file (or use HASHA_PRIVATE_KEY env)
+        /// Private key file ('-' for stdin), stored key name or keyring:NAME (or use HESHA_PRIVATE_KEY env)
         #[arg(short, long, value_name = "FILE")]
         key: Option<String>,
         
         /// Output file for attestation
-        #[arg(short, long, value_name = "FILE")]
-        output: Option<String>,
+        #[arg(short = 'o', long = "out", value_name = "FILE")]
+        out: Option<String>,
         
         /// Validity period in days (optional, uses issuer default if not specified)
-        #[arg(short = 'd', long, value_name = "DAYS", value_parser = clap::value_parser!(i64).range(1..=730))]
+        #[arg(short = 'd', long, value_name = "DAYS", value_parser = clap::value_parser!(i64).range(1..=hesha_types::MAX_VALIDITY_DAYS))]
         validity_days: Option<i64>,
+        
+        /// Request attestations for every row of a CSV or JSON file ('-' for stdin)
+        #[arg(long, value_name = "FILE")]
+        batch: Option<std::path::PathBuf>,
+        
+        /// Maximum concurrent requests in batch mode
+        #[arg(short = 'j', long, value_name = "N", default_value_t = parallel::DEFAULT_ATTEST_JOBS, visible_alias = "concurrency", requires = "batch")]
+        jobs: usize,
+        
+        /// Batch results file (defaults to <FILE>.results.csv)
+        #[arg(long, value_name = "FILE", requires = "batch")]
+        results: Option<std::path::PathBuf>,
+        
+        /// Skip rows already completed in the results file
+        #[arg(long, requires = "batch")]
+        resume: bool,
+        
+        /// Have the issuer verify the number by SMS code before attesting
+        #[arg(long, conflicts_with = "batch")]
+        otp: bool,
+        
+        /// Retries after a transient network failure, with exponential backoff
+        #[arg(long, value_name = "N", default_value_t = 3)]
+        retries: u32,
+        
+        /// Resume a request that failed with a network error, by the reference it printed
+        #[arg(long, value_name = "ID", conflicts_with_all = ["batch", "otp", "phone", "scope", "validity_days"])]
+        resume_id: Option<String>,
     },
     
     /// Verify an attestation's cryptographic validity
@@ -104,15 +253,90 @@ Examples:
   
   # Verify inline JWT
   hesha verify -a eyJ0eXAiOiJKV1Q...
+  
+  # Verify an attestation piped from another command
+  cat attestation.jwt | hesha verify -a -
+  
+  # Use as a gate in shell scripts (see 'hesha --help' for exit codes)
+  if hesha verify -q -a attestation.jwt -p +1234567890 --check-revocation; then
+    echo \"trusted\"
+  fi
+  
+  # Verify offline against a pinned issuer key
+  hesha verify -a attestation.jwt --offline --issuer-key pubkey.json
+  
+  # Verify offline against a trust bundle
+  hesha verify -a attestation.jwt --offline --trust-bundle bundle.json
+  
+  # Audit a directory of attestations and print a summary table
+  hesha verify --all 'attestations/*.jwt' --jobs 16
+  
+  # Audit offline on 4 threads (pinned keys are checked in parallel)
+  hesha verify --all 'attestations/*.jwt' --offline --trust-bundle bundle.json --threads 4
+  
+  # Stream a large export (one JWT per line) and keep only the failures
+  hesha verify --all export.txt --stream --offline --issuer-key pubkey.json | grep -v '\"valid\"'
+  
+  # Apply a shared verification policy
+  hesha verify -a attestation.jwt --policy policy.toml
+
+A trust bundle lists pinned keys per trust domain:
+  {\"version\": 1, \"issuers\": [{\"domain\": \"example.com\", \"public_key\": \"...\"}]}
+
+A policy restricts which attestations are accepted (all keys optional):
+  allowed_issuers = [\"issuer.example.com\"]
+  allowed_audiences = [\"https://app.example.com\"]
+  max_validity_days = 90
+  clock_skew_seconds = 60
 ")]
     Verify {
-        /// Attestation file or JWT string
-        #[arg(short, long, value_name = "FILE_OR_JWT")]
-        attestation: String,
+        /// Attestation file, JWT string, or '-' for stdin
+        #[arg(short, long, value_name = "FILE_OR_JWT", required_unless_present = "all", conflicts_with = "all")]
+        attestation: Option<String>,
         
         /// Expected phone number to verify (optional)
-        #[arg(short, long, value_name = "PHONE")]
+        #[arg(short, long, value_name = "PHONE", conflicts_with = "all")]
         phone: Option<String>,
+        
+        /// Verify every attestation file matching these glob patterns
+        #[arg(long, value_name = "PATTERN", num_args = 1..)]
+        all: Vec<String>,
+        
+        /// Maximum number of concurrent verifications with --all
+        #[arg(short = 'j', long, value_name = "N", default_value_t = parallel::DEFAULT_VERIFY_JOBS, visible_alias = "concurrency", requires = "all")]
+        jobs: usize,
+        
+        /// Threads checking signatures with --all against pinned keys.
+        /// Defaults to one per CPU
+        #[arg(long, value_name = "N", default_value_t = 0, hide_default_value = true, requires = "all")]
+        threads: usize,
+        
+        /// With --all, read attestations one per line from the given files
+        /// ('-' for stdin) and print each result as a JSON line as it is
+        /// verified, without loading the input first
+        #[arg(long, requires = "all")]
+        stream: bool,
+        
+        /// Verify without network access using pinned keys
+        #[arg(long)]
+        offline: bool,
+        
+        /// Pinned issuer key (pubkey.json or base64url key)
+        #[arg(long, value_name = "FILE", conflicts_with = "trust_bundle")]
+        issuer_key: Option<String>,
+        
+        /// Trust bundle of pinned keys per trust domain
+        #[arg(long, value_name = "FILE")]
+        trust_bundle: Option<String>,
+        
+        /// Also fail if the issuer has revoked the attestation
+        #[arg(long, conflicts_with = "offline")]
+        check_revocation: bool,
+        
+        /// Verification policy file (TOML) with allowed issuers, audiences,
+        /// max validity and clock skew
+        #[arg(long, env = "HESHA_VERIFY_POLICY", value_name = "FILE")]
+        policy: Option<std::path::PathBuf>,
     },
     
     /// Display attestation details without verification
@@ -135,13 +359,82 @@ Examples:
   
   # Inspect inline JWT
   hesha inspect eyJ0eXAiOiJKV1Q...
+  
+  # Dump the exact claim JSON
+  hesha inspect attestation.jwt --raw
 ")]
     Inspect {
-        /// Attestation file or JWT string
+        /// Attestation file, JWT string, or '-' for stdin
         #[arg(value_name = "FILE_OR_JWT")]
         attestation: String,
+        
+        /// Print only the decoded claim JSON
+        #[arg(long)]
+        raw: bool,
     },
     
+    /// Compare the claims of two attestations
+    #[command(long_about = "
+Compare two attestations claim by claim and highlight what changed
+(proxy number, keys, expiry, issuer, ...). Useful when auditing renewals
+and migrations. Neither attestation is verified.
+
+Examples:
+  # Compare a renewed attestation with the previous one
+  hesha diff old.jwt new.jwt
+  
+  # Machine-readable list of changes
+  hesha --output json diff old.jwt new.jwt
+")]
+    Diff(commands::diff::DiffCmd),
+    
+    /// Manage attestations in ~/.hesha/wallet
+    #[command(long_about = "
+Store attestations in a local wallet (~/.hesha/wallet) together with the
+name of the key they were issued to.
+
+Examples:
+  # Add an attestation (the matching stored key is detected)
+  hesha wallet add attestation.jwt --label personal
+  
+  # List attestations, soonest expiry first
+  hesha wallet list
+  
+  # Show attestations that expire in the next 30 days
+  hesha wallet list --expiring-within 30
+  
+  # Show, export or remove by ID prefix, label or proxy number
+  hesha wallet show personal
+  hesha wallet export personal -o attestation.jwt
+  hesha wallet remove personal
+")]
+    Wallet(commands::wallet::WalletCmd),
+    
+    /// Show an attestation as a QR code, or decode one
+    #[cfg(feature = "qr")]
+    #[command(long_about = "
+Encode an attestation as a QR code for in-person presentation, or decode a
+scanned QR payload and inspect the attestation it contains.
+
+The QR payload is the attestation in compact form: 'HESHA1:' followed by
+the deflated JWT parts in Base45, which any QR scanner returns as text.
+Payloads holding 'hesha:' and the JWT are also accepted.
+
+Examples:
+  # Show a QR code in the terminal
+  hesha qr -a attestation.jwt
+  
+  # Save the QR code as a PNG image
+  hesha qr -a attestation.jwt -o qr.png
+  
+  # Decode and inspect a QR code image or photo
+  hesha qr --decode qr.png
+  
+  # Decode a payload from another scanner
+  zbarimg --raw -q qr.png | hesha qr --decode -
+")]
+    Qr(commands::qr::QrCmd),
+    
     /// Display information about the Hesha Protocol
     #[command(long_about = "
 Display information about the Hesha Protocol, including:
@@ -176,11 +469,106 @@ Examples:
   # Setup in specific directory
   hesha setup -o /path/to/issuer
   
-  # Non-interactive setup with defaults
-  hesha setup --non-interactive
+  # Non-interactive setup for provisioning scripts
+  hesha setup --non-interactive --issuer-name \"Acme\" \\
+    --trust-domain issuer.acme.com --contact-email ops@acme.com --port 8080
+  
+  # The same, configured through the environment (e.g., in a container)
+  HESHA_ISSUER_NAME=Acme HESHA_TRUST_DOMAIN=issuer.acme.com \\
+    HESHA_CONTACT_EMAIL=ops@acme.com hesha setup --non-interactive
+  
+  # Rotate the signing key, keeping the old key published for 30 days
+  hesha setup rotate --overlap-days 30
 ")]
     Setup(commands::setup_issuer::SetupIssuerCmd),
     
+    /// Regenerate the issuer's well-known documents
+    #[command(name = "export-issuer-info")]
+    #[command(long_about = "
+Regenerate the documents served under /.well-known/hesha/ from the local
+issuer configuration and keys, without re-running setup:
+
+- pubkey.json: the public key endpoint, listing current and previous keys
+- jwks.json: the same keys as a JSON Web Key Set
+
+With --service-url, pubkey.json also carries service_info so verifiers
+resolving the trust domain are pointed at a separately hosted service.
+
+Use this to redeploy a static endpoint after a config change or rotation.
+
+Examples:
+  # Write to ~/.hesha/issuer/default/public/.well-known/hesha
+  hesha export-issuer-info
+  
+  # Write straight into a web root
+  hesha export-issuer-info -n myissuer --out-dir /var/www/html/.well-known/hesha
+  
+  # Delegate from the trust domain to a separate service
+  hesha export-issuer-info --service-url https://api.example.com
+")]
+    ExportIssuerInfo(commands::export_issuer_info::ExportIssuerInfoCmd),
+    
+    /// Check issuer configurations
+    #[command(name = "config")]
+    #[command(long_about = "
+Check an issuer configuration created with 'hesha setup'.
+
+'hesha config validate' parses issuer.toml, checks the trust domain and
+contact email, checks that keys/private.key and keys/public.key exist, that
+the private key is only readable by its owner, and that both match the public
+key in the config. All problems are reported at once; the exit code is
+non-zero if there are any.
+
+Examples:
+  # Validate the default issuer
+  hesha config validate
+  
+  # Validate a named issuer
+  hesha config validate -n myissuer
+  
+  # Validate a config file elsewhere (keys are read from ../keys)
+  hesha config validate /etc/hesha/config/issuer.toml
+")]
+    Config(commands::config::ConfigCmd),
+    
+    /// Work with the issuers configured on this machine
+    #[command(name = "issuer")]
+    #[command(long_about = "
+Work with the issuer instances under ~/.hesha/issuer.
+
+'hesha issuer list' shows each issuer's trust domain, port, current key ID
+and fingerprint, and whether its node is running. Issuers whose config
+can't be read are still listed, with the error.
+
+Examples:
+  # List local issuers
+  hesha issuer list
+  
+  # Names of the running issuers
+  hesha --output json issuer list | jq -r '.[] | select(.running) | .name'
+")]
+    Issuer(commands::issuer::IssuerCmd),
+    
+    /// Upgrade configuration written by an older release
+    #[command(name = "migrate")]
+    #[command(long_about = "
+Upgrade ~/.hesha from the layouts of older releases to the current format.
+
+- ~/.hesha/key.json is moved into the key store as the 'default' key
+
+Every file changed is first copied to ~/.hesha/backups/migrate-<time>/.
+Running it again on an up-to-date directory changes nothing. Issuer
+directories created by 'hesha setup' are already in the current layout.
+
+Examples:
+  # See what would change
+  hesha migrate --dry-run
+  
+  # Upgrade
+  hesha migrate
+")]
+    Migrate(commands::migrate::MigrateCmd),
+    
     /// Start the Hesha issuer node
     #[command(name = "start")]
     #[command(long_about = "
@@ -195,6 +583,9 @@ Examples:
   
   # Start in background (daemon mode)
   hesha start --daemon
+  
+  # Install as a systemd service (Linux servers)
+  sudo -E hesha start --install-systemd --service-user hesha
 ")]
     Start(commands::start::StartCmd),
     
@@ -203,37 +594,380 @@ Examples:
     #[command(long_about = "
 Stop a running Hesha issuer node.
 
+The node is asked to shut down and finishes the requests it is serving
+before exiting. If it is still running after --timeout seconds (default 30)
+it is killed. --force kills it straight away. The PID file is removed only
+once the process has exited.
+
+The PID file is only trusted if it names a running issuer-node process;
+otherwise it is removed without signaling anything.
+
+On Windows the shutdown request is sent over a named pipe, since the
+node runs detached from any console.
+
 Examples:
   # Stop default issuer
   hesha stop
   
   # Stop named issuer
   hesha stop -n myissuer
+  
+  # Allow slow requests up to two minutes to finish
+  hesha stop --timeout 120
+  
+  # Stop immediately
+  hesha stop --force
 ")]
     Stop(commands::stop::StopCmd),
+    
+    /// Restart the Hesha issuer node in the background
+    #[command(name = "restart")]
+    #[command(long_about = "
+Stop the running Hesha issuer node, wait for it to exit and start it again
+in the background, e.g. after 'hesha key rotate' or a config change.
+
+A PID file left behind by a node that crashed, or whose PID has since been
+reused by another program, is cleaned up rather than signaled.
+
+Examples:
+  # Restart default issuer
+  hesha restart
+  
+  # Restart named issuer, allowing 30 seconds for shutdown
+  hesha restart -n myissuer --timeout 30
+")]
+    Restart(commands::restart::RestartCmd),
+    
+    /// Show whether the Hesha issuer node is running
+    #[command(name = "status")]
+    #[command(long_about = "
+Show whether a Hesha issuer node started with 'hesha start --daemon' is running.
+
+Examples:
+  # Check default issuer
+  hesha status
+  
+  # Check named issuer
+  hesha status -n myissuer
+")]
+    Status(commands::status::StatusCmd),
+    
+    /// Watch a running issuer node in a terminal dashboard
+    #[command(name = "dashboard")]
+    #[command(long_about = "
+Show a live view of a running issuer node: issuance rate and totals, the most
+recent attestations (proxy number and JWT ID only, never phone numbers),
+error counts by code, and how long retired keys remain published.
+
+Metrics come from the node's /admin/metrics endpoint, which needs an
+operator key from the node's [admin_auth] in HESHA_ADMIN_TOKEN. Counts
+restart when the node restarts.
+When output is JSON or not a terminal, a single snapshot is printed.
+
+Keys: q or Esc to quit, r to refresh now.
+
+Examples:
+  # Watch the default issuer
+  hesha dashboard
+  
+  # Watch a named issuer, polling every 5 seconds
+  hesha dashboard -n myissuer --interval 5
+  
+  # One metrics snapshot for scripts
+  hesha --output json dashboard
+")]
+    Dashboard(commands::dashboard::DashboardCmd),
+    
+    /// Control a running issuer node through its local admin console
+    #[command(name = "admin")]
+    #[command(long_about = "
+Send commands to a running issuer node over its admin console, a Unix
+socket at issuer.sock in the issuer directory (set another path on the
+node with --control-socket). Only the user the node runs as can connect,
+so the admin API never has to be opened to the network.
+
+Commands:
+  status        Version, uptime, signing key and drain state
+  reload        Re-read keys from issuer.toml (e.g. after 'hesha setup rotate')
+  rotate-key    Generate a new signing key and sign with it from now on
+  activate-key  Sign with another held key, e.g. the one a rotation replaced
+  drain         Report unhealthy on /health, then finish in-flight requests and exit
+
+Examples:
+  # Show what the default issuer is running with
+  hesha admin status
+  
+  # Rotate a named issuer's key, keeping the old key published for 30 days
+  hesha admin -n myissuer rotate-key --overlap-days 30 --yes
+  
+  # Switch back to the key it replaced
+  hesha admin -n myissuer activate-key <KEY_ID>
+  
+  # Give the load balancer 20 seconds to stop routing traffic, then exit
+  hesha admin drain --grace 20
+")]
+    Admin(commands::admin::AdminCmd),
+    
+    /// Export or verify an issuer node's signed audit log
+    #[command(name = "audit")]
+    #[command(long_about = "
+Export the audit log of a running issuer node, or check an exported log.
+
+The node records issuance, revocation, admin actions, issuer key changes and
+refused credentials as hash-chained entries signed with the issuer key.
+Verification checks every link and signature, so an edited, missing or
+reordered entry is reported by sequence number. Export uses /admin/audit,
+which needs an operator key from the node's [admin_auth] in
+HESHA_ADMIN_TOKEN.
+
+Examples:
+  # Export the default issuer's log
+  hesha audit export -o audit.jsonl
+  
+  # Export entries recorded since the last export
+  hesha audit export --after 1200 >> audit.jsonl
+  
+  # Verify against the issuer's current and previous keys
+  hesha audit verify audit.jsonl
+  
+  # Verify against a given key
+  hesha audit verify audit.jsonl --public-key <KEY>
+")]
+    Audit(commands::audit::AuditCmd),
+    
+    /// Back up an issuer to an encrypted archive
+    #[command(name = "backup")]
+    #[command(long_about = "
+Write an issuer's configuration, keys, records and audit log to one archive
+encrypted with a passphrase, so the issuer can be rebuilt after losing its
+host.
+
+Records (attestations, proxy number assignments and revocations) and the
+audit log are fetched from the running node through /admin/backup and
+/admin/audit, which need an operator key from the node's [admin_auth] in
+HESHA_ADMIN_TOKEN. Every file in the archive carries a SHA-256 digest
+checked on restore.
+
+The passphrase is read from HESHA_BACKUP_PASSPHRASE or prompted for.
+
+Examples:
+  # Back up the default issuer
+  hesha backup -o issuer.backup
+  
+  # Back up config and keys of a stopped issuer
+  hesha backup -n myissuer --files-only
+  
+  # Nightly backup from cron
+  HESHA_BACKUP_PASSPHRASE=... hesha backup -o /backups/issuer-$(date +%F).backup
+")]
+    Backup(commands::backup::BackupCmd),
+    
+    /// Restore an issuer from an encrypted archive
+    #[command(name = "restore")]
+    #[command(long_about = "
+Restore an archive made by 'hesha backup'. The archive is decrypted and every
+file checked against its digest before anything is written.
+
+Config and key files go back into the issuer directory; existing files are
+only replaced with --force. Records are loaded into the running node through
+/admin/restore, keeping any record the node already holds; set the operator
+key from the node's [admin_auth] in HESHA_ADMIN_TOKEN. On a new host,
+restore the files first, start the node, then load the records with
+--records-only. The archived audit log is written to audit.jsonl in the
+issuer directory, where 'hesha audit verify' can check it.
+
+The passphrase is read from HESHA_BACKUP_PASSPHRASE or prompted for.
+
+Examples:
+  # Rebuild an issuer on a new host
+  hesha restore issuer.backup
+  hesha start
+  hesha restore issuer.backup --records-only
+  
+  # Put back a named issuer's keys over the current ones
+  hesha restore issuer.backup -n myissuer --files-only --force
+")]
+    Restore(commands::restore::RestoreCmd),
+    
+    /// Generate an issuer key split among custodians
+    #[command(name = "ceremony")]
+    #[command(long_about = "
+Run a key ceremony: generate an issuer signing key in this process and split it
+with Shamir secret sharing so that any K of N custodians can recover it, but
+fewer cannot. Before anything is written, every share is checked to take part
+in a recovery of the key. The key itself is never written out.
+
+Each custodian gets a share file (optionally encrypted with a passphrase they
+choose), and the ceremony produces a transcript recording the public key, the
+custodians, witnesses and a digest of each share, signed with the new key.
+
+Run ceremonies on an offline machine with every participant present.
+
+Examples:
+  # Three custodians, any two of whom can recover the key
+  hesha ceremony run --custodian alice --custodian bob --custodian carol -t 2
+  
+  # Encrypt each share and record a witness
+  hesha ceremony run --custodian alice --custodian bob --custodian carol -t 2 \\
+    --witness auditor --encrypt-shares -o ceremony-2026
+  
+  # A custodian checks their share against the transcript
+  hesha ceremony verify ceremony-2026/transcript.json share-1-alice.json
+  
+  # Recover the key onto the issuer host
+  hesha ceremony recover transcript.json share-1-alice.json share-3-carol.json \\
+    -o ~/.hesha/issuer/default/keys/private.key
+")]
+    Ceremony(commands::ceremony::CeremonyCmd),
+    
+    /// Generate man pages
+    #[command(name = "man")]
+    #[command(long_about = "
+Generate roff man pages for hesha and all of its subcommands.
+
+Examples:
+  # View the top-level page
+  hesha man | man -l -
+  
+  # Write pages for every subcommand (for packaging)
+  hesha man -d target/man
+")]
+    Man(commands::man::ManCmd),
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+    let usage_error = |e: clap::Error| -> ! {
+        // clap uses 2 for usage errors, which is taken by invalid attestations
+        let code = if e.use_stderr() { exit::USAGE } else { 0 };
+        let _ = e.print();
+        std::process::exit(code);
+    };
+    let cli = Cli::try_parse().unwrap_or_else(|e| usage_error(e));
+    let mut command = cli.command;
+    let format = match cli.output {
+        output::OutputArg::Mode(mode) => mode,
+        // `hesha attest --output FILE`, from before the flag was renamed --out
+        output::OutputArg::File(file) => match &mut command {
+            Commands::Attest { out: out @ None, .. } => {
+                *out = Some(file);
+                output::OutputFormat::Text
+            }
+            _ => usage_error(Cli::command().error(
+                clap::error::ErrorKind::InvalidValue,
+                format!("invalid value '{}' for '--output <MODE>' [possible values: text, json]", file),
+            )),
+        },
+    };
     
     // Set up basic logging
     if cli.verbose {
         std::env::set_var("RUST_LOG", "debug");
     }
     
-    match cli.command {
-        Commands::Keygen { format } => {
-            commands::keygen::execute(&format)?;
+    output::set_format(format);
+    output::set_quiet(cli.quiet);
+    
+    if let Err(e) = run(command, cli.profile.as_deref()).await {
+        let code = exit::code_for(&e);
+        if output::is_json() {
+            // Errors are part of the JSON contract so scripts can report them
+            output::json(&serde_json::json!({ "error": { "message": format!("{:#}", e), "code": code } }))?;
+        } else {
+            eprintln!("Error: {:?}", e);
         }
-        Commands::Attest { issuer, phone, scope, key, output, validity_days } => {
-            commands::attest::execute(&issuer, &phone, &scope, key.as_deref(), output.as_deref(), validity_days).await?;
+        std::process::exit(code);
+    }
+    
+    Ok(())
+}
+
+async fn run(command: Commands, profile: Option<&str>) -> anyhow::Result<()> {
+    match command {
+        Commands::Keygen { format, encrypt, store, name, force, from_mnemonic, derive_path } => {
+            let derive_path = from_mnemonic
+                .then(|| derive_path.unwrap_or_else(|| hesha_crypto::DEFAULT_DERIVATION_PATH.to_string()));
+            commands::keygen::execute(&format, encrypt, store, &name, force, derive_path.as_deref())?;
         }
-        Commands::Verify { attestation, phone } => {
-            commands::verify::execute(&attestation, phone.as_deref()).await?;
+        Commands::Key(cmd) => {
+            cmd.execute()?;
         }
-        Commands::Inspect { attestation } => {
-            commands::inspect::execute(&attestation)?;
+        Commands::Attest { issuer, phone, scope, key, out, validity_days, batch, jobs, results, resume, otp, retries, resume_id } => {
+            let profile = profile::load_profile(profile)?.with_env()?;
+            let options = commands::attest::AttestOptions {
+                key: key.as_deref(),
+                out: out.as_deref(),
+                otp,
+                retries,
+            };
+            if let Some(id) = resume_id {
+                return commands::attest::resume(&id, options).await;
+            }
+            
+            let issuer = issuer.or(profile.issuer)
+                .ok_or_else(|| anyhow::anyhow!("No issuer given. Pass --issuer, set HESHA_ISSUER or set one in the profile"))?;
+            let scope = scope.or(profile.scope);
+            let key = key.as_deref().or(profile.key.as_deref());
+            let validity_days = validity_days.or(profile.validity_days);
+            
+            if let Some(batch) = batch {
+                let options = commands::batch::BatchOptions {
+                    issuer: &issuer,
+                    scope: scope.as_deref(),
+                    key,
+                    validity_days,
+                    jobs,
+                    results: results.as_deref(),
+                    resume,
+                };
+                return commands::batch::execute(&batch, options).await;
+            }
+            
+            let phone = phone.expect("clap requires --phone without --batch");
+            let scope = scope
+                .ok_or_else(|| anyhow::anyhow!("No scope given. Pass --scope, set HESHA_SCOPE or set one in the profile"))?;
+            let options = commands::attest::AttestOptions { key, ..options };
+            commands::attest::execute(&issuer, &phone, &scope, validity_days, options).await?;
+        }
+        Commands::Verify { attestation, phone, all, jobs, threads, stream, offline, issuer_key, trust_bundle, check_revocation, policy } => {
+            let policy = match policy {
+                Some(path) => hesha_core::VerificationPolicy::from_file(path)?,
+                None => hesha_core::VerificationPolicy::default(),
+            };
+            let keys = match (issuer_key.as_deref(), trust_bundle.as_deref()) {
+                (Some(path), _) => commands::verify::KeySource::IssuerKey(path),
+                (None, Some(path)) => commands::verify::KeySource::TrustBundle(path),
+                (None, None) if offline => {
+                    anyhow::bail!("--offline requires --issuer-key or --trust-bundle")
+                }
+                (None, None) => commands::verify::KeySource::Discover,
+            };
+            match attestation {
+                Some(attestation) => {
+                    commands::verify::execute(&attestation, phone.as_deref(), keys, &policy, check_revocation).await?;
+                }
+                None => {
+                    let concurrency = commands::verify::Concurrency { jobs, threads };
+                    if stream {
+                        commands::verify::execute_stream(&all, keys, &policy, check_revocation, concurrency).await?;
+                    } else {
+                        commands::verify::execute_all(&all, keys, &policy, check_revocation, concurrency).await?;
+                    }
+                }
+            }
+        }
+        Commands::Inspect { attestation, raw } => {
+            commands::inspect::execute(&attestation, raw)?;
+        }
+        Commands::Diff(cmd) => {
+            cmd.execute()?;
+        }
+        Commands::Wallet(cmd) => {
+            cmd.execute()?;
+        }
+        #[cfg(feature = "qr")]
+        Commands::Qr(cmd) => {
+            cmd.execute()?;
         }
         Commands::Info => {
             commands::info::execute()?;
@@ -241,12 +975,54 @@ async fn main() -> anyhow::Result<()> {
         Commands::Setup(cmd) => {
             cmd.execute()?;
         }
+        Commands::ExportIssuerInfo(cmd) => {
+            cmd.execute()?;
+        }
+        Commands::Config(cmd) => {
+            cmd.execute()?;
+        }
+        Commands::Issuer(cmd) => {
+            cmd.execute()?;
+        }
+        Commands::Migrate(cmd) => {
+            cmd.execute()?;
+        }
         Commands::Start(cmd) => {
             cmd.execute()?;
         }
         Commands::Stop(cmd) => {
             cmd.execute()?;
         }
+        Commands::Restart(cmd) => {
+            cmd.execute()?;
+        }
+        Commands::Status(cmd) => {
+            cmd.execute()?;
+        }
+        Commands::Admin(cmd) => {
+            cmd.execute()?;
+        }
+        Commands::Dashboard(cmd) => {
+            cmd.execute().await?;
+        }
+        Commands::Audit(cmd) => {
+            cmd.execute().await?;
+        }
+        Commands::Backup(cmd) => {
+            cmd.execute().await?;
+        }
+        Commands::Restore(cmd) => {
+            cmd.execute().await?;
+        }
+        Commands::Ceremony(cmd) => {
+            cmd.execute()?;
+        }
+        Commands::Man(cmd) => {
+            let mut command = Cli::command();
+            // Propagate global arguments into subcommands
+            command.build();
+            cmd.execute(command)?;
+        }
     }
     
     Ok(())
@@ -0,0 +1,53 @@
+//! Private keys kept in the platform secret store.
+//!
+//! Keys live in the macOS Keychain, Windows Credential Manager or the
+//! Secret Service (GNOME Keyring, KWallet) under the `hesha` service, and
+//! are referenced on the command line as `keyring:<name>`.
+
+use crate::config::load_keypair_from_string;
+use crate::keystore::validate_key_name;
+use hesha_types::KeyPair;
+use keyring::Entry;
+
+/// Service name used for all keyring entries.
+const SERVICE: &str = "hesha";
+
+/// Prefix marking a key reference as a keyring entry.
+pub const KEYRING_PREFIX: &str = "keyring:";
+
+/// Get the keyring entry name from a `keyring:<name>` reference.
+pub fn parse_reference(key: &str) -> Option<&str> {
+    key.strip_prefix(KEYRING_PREFIX)
+}
+
+/// Whether a key is stored under the given name.
+pub fn exists(name: &str) -> anyhow::Result<bool> {
+    match entry(name)?.get_password() {
+        Ok(_) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(anyhow::anyhow!("Failed to read key '{}' from the OS keyring: {}", name, e)),
+    }
+}
+
+/// Store a keypair's private key under the given name, replacing any
+/// stored key.
+pub fn save(name: &str, keypair: &KeyPair) -> anyhow::Result<()> {
+    entry(name)?
+        .set_password(&keypair.private.to_base64())
+        .map_err(|e| anyhow::anyhow!("Failed to store key '{}' in the OS keyring: {}", name, e))
+}
+
+/// Load a keypair stored under the given name.
+pub fn load(name: &str) -> anyhow::Result<KeyPair> {
+    let secret = entry(name)?.get_password().map_err(|e| match e {
+        keyring::Error::NoEntry => anyhow::anyhow!("No key '{}' in the OS keyring", name),
+        e => anyhow::anyhow!("Failed to read key '{}' from the OS keyring: {}", name, e),
+    })?;
+    load_keypair_from_string(&secret)
+}
+
+fn entry(name: &str) -> anyhow::Result<Entry> {
+    validate_key_name(name)?;
+    Entry::new(SERVICE, name)
+        .map_err(|e| anyhow::anyhow!("OS keyring unavailable: {}", e))
+}
@@ -1,10 +1,109 @@
 //! Output formatting utilities.
 
 use colored::*;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+/// Output mode selected with the global `--output` flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text.
+    #[default]
+    Text,
+    /// A single JSON document on stdout.
+    Json,
+}
+
+/// Value of the global `--output` flag.
+///
+/// `hesha attest` took its output file as `--output` before that became the
+/// global mode flag, so a value that isn't a mode is kept as that file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputArg {
+    Mode(OutputFormat),
+    File(String),
+}
+
+/// Parses `--output`, listing only the modes in help and completions.
+#[derive(Debug, Clone, Copy)]
+pub struct OutputArgParser;
+
+impl clap::builder::TypedValueParser for OutputArgParser {
+    type Value = OutputArg;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        _arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<OutputArg, clap::Error> {
+        let value = value
+            .to_str()
+            .ok_or_else(|| clap::Error::new(clap::error::ErrorKind::InvalidUtf8).with_cmd(cmd))?;
+        Ok(match <OutputFormat as clap::ValueEnum>::from_str(value, true) {
+            Ok(mode) => OutputArg::Mode(mode),
+            Err(_) => OutputArg::File(value.to_string()),
+        })
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = clap::builder::PossibleValue> + '_>> {
+        Some(Box::new(
+            <OutputFormat as clap::ValueEnum>::value_variants()
+                .iter()
+                .filter_map(clap::ValueEnum::to_possible_value),
+        ))
+    }
+}
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+static QUIET: OnceLock<bool> = OnceLock::new();
+
+/// Set the output mode for this process.
+pub fn set_format(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+/// Check if machine-readable JSON output was requested.
+///
+/// In JSON mode stdout carries only the command's JSON result; all
+/// human-readable messages go to stderr.
+pub fn is_json() -> bool {
+    FORMAT.get().copied().unwrap_or_default() == OutputFormat::Json
+}
+
+/// Suppress informational and decorative output for this process.
+pub fn set_quiet(quiet: bool) {
+    let _ = QUIET.set(quiet);
+}
+
+/// Check if `--quiet` was given.
+///
+/// In quiet mode only results, warnings and errors are printed.
+pub fn is_quiet() -> bool {
+    QUIET.get().copied().unwrap_or_default()
+}
+
+/// Print a command result as pretty JSON on stdout.
+pub fn json<T: Serialize>(value: &T) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Print a line of human-readable text (stderr in JSON mode).
+pub fn text(message: &str) {
+    if is_quiet() {
+        return;
+    }
+    if is_json() {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
 
 /// Print a success message.
 pub fn success(message: &str) {
-    println!("{} {}", "✓".green(), message);
+    text(&format!("{} {}", "✓".green(), message));
 }
 
 /// Print an error message.
@@ -19,5 +118,5 @@ pub fn warning(message: &str) {
 
 /// Print an info message.
 pub fn info(message: &str) {
-    println!("{} {}", "ℹ".cyan(), message);
-}
\ No newline at end of file
+    text(&format!("{} {}", "ℹ".cyan(), message));
+}
@@ -0,0 +1,71 @@
+//! Concurrent execution with progress reporting for batch commands.
+//!
+//! Tasks run with a bounded number in flight while a progress bar on
+//! stderr counts completions. The bar is hidden in quiet or JSON mode and
+//! when stderr is not a terminal, so scripted runs see only the results.
+
+use crate::output;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::future::Future;
+use std::io::IsTerminal;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Default number of attestation requests in flight, kept low so a batch
+/// doesn't run into the issuer's rate limits.
+pub const DEFAULT_ATTEST_JOBS: usize = 4;
+
+/// Default number of verifications in flight.
+pub const DEFAULT_VERIFY_JOBS: usize = 8;
+
+/// Run `task` for every item with at most `jobs` running at once.
+///
+/// `on_result` is called for each result as it completes (in completion
+/// order) with the progress bar suspended, so it may print freely.
+pub async fn run<T, R, F, Fut>(
+    items: Vec<T>,
+    jobs: usize,
+    message: &str,
+    task: F,
+    mut on_result: impl FnMut(R) -> anyhow::Result<()>,
+) -> anyhow::Result<()>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = R> + Send + 'static,
+{
+    let progress = progress_bar(items.len() as u64, message);
+    let semaphore = Arc::new(Semaphore::new(jobs.max(1)));
+    let mut tasks = JoinSet::new();
+    for item in items {
+        let semaphore = semaphore.clone();
+        let future = task(item);
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await.expect("semaphore closed");
+            future.await
+        });
+    }
+
+    while let Some(result) = tasks.join_next().await {
+        let result = result?;
+        progress.suspend(|| on_result(result))?;
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+
+    Ok(())
+}
+
+/// Create a progress bar, or a hidden one when progress shouldn't show.
+fn progress_bar(len: u64, message: &str) -> ProgressBar {
+    if output::is_quiet() || output::is_json() || !std::io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+
+    let style = ProgressStyle::with_template("{msg} [{bar:40}] {pos}/{len} ({per_sec}, ETA {eta})")
+        .expect("valid progress template")
+        .progress_chars("=> ");
+    ProgressBar::new(len).with_style(style).with_message(message.to_string())
+}
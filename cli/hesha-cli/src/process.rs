@@ -0,0 +1,273 @@
+//! Cross-platform management of background issuer node processes.
+//!
+//! Unix uses signals. On Windows, where console control events cannot
+//! reach a detached process, the node is asked to stop over a named pipe
+//! and its process is only terminated outright when killing it.
+
+use std::io;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// File name (without extension) of the issuer node binary.
+const ISSUER_BINARY: &str = "issuer-node";
+
+/// Spawn a process detached from the current terminal.
+pub fn spawn_detached(command: &mut Command) -> io::Result<Child> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        use windows_sys::Win32::System::Threading::{CREATE_NEW_PROCESS_GROUP, DETACHED_PROCESS};
+        command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+    }
+
+    command.spawn()
+}
+
+/// Check whether the given PID is a running issuer node.
+///
+/// PIDs are reused, so a PID file left behind by a crashed node may point
+/// at an unrelated process. Only the executable named on the command line
+/// is trusted to tell them apart.
+pub fn is_issuer(pid: u32) -> bool {
+    is_running(pid)
+        && executable(pid).is_some_and(|exe| {
+            Path::new(&exe).file_stem().is_some_and(|stem| stem == ISSUER_BINARY)
+        })
+}
+
+/// Wait for a process to exit.
+///
+/// Returns `false` if it is still running after `timeout`.
+pub fn wait_for_exit(pid: u32, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while is_running(pid) {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    true
+}
+
+/// Executable a process was started with, from its command line.
+#[cfg(target_os = "linux")]
+fn executable(pid: u32) -> Option<String> {
+    let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let argv0 = cmdline.split(|&b| b == 0).next()?;
+    Some(String::from_utf8_lossy(argv0).into_owned())
+}
+
+/// Executable a process was started with, from its command line.
+#[cfg(all(unix, not(target_os = "linux")))]
+fn executable(pid: u32) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-o", "comm=", "-p", &pid.to_string()])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let comm = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!comm.is_empty()).then_some(comm)
+}
+
+/// Check whether a process with the given PID is running.
+#[cfg(unix)]
+pub fn is_running(pid: u32) -> bool {
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+
+    // Signal 0 only checks that the process exists
+    match kill(Pid::from_raw(pid as i32), None) {
+        Ok(()) => true,
+        Err(e) => e == nix::errno::Errno::EPERM,
+    }
+}
+
+/// Ask a process to stop.
+///
+/// Returns `Ok(false)` if the process was not running.
+#[cfg(unix)]
+pub fn terminate(pid: u32) -> io::Result<bool> {
+    use nix::sys::signal::{kill, Signal};
+    use nix::unistd::Pid;
+
+    match kill(Pid::from_raw(pid as i32), Signal::SIGTERM) {
+        Ok(()) => Ok(true),
+        Err(nix::errno::Errno::ESRCH) => Ok(false),
+        Err(e) => Err(io::Error::from_raw_os_error(e as i32)),
+    }
+}
+
+/// Stop a process immediately, without letting it clean up.
+///
+/// Returns `Ok(false)` if the process was not running.
+#[cfg(unix)]
+pub fn kill(pid: u32) -> io::Result<bool> {
+    use nix::sys::signal::{kill, Signal};
+    use nix::unistd::Pid;
+
+    match kill(Pid::from_raw(pid as i32), Signal::SIGKILL) {
+        Ok(()) => Ok(true),
+        Err(nix::errno::Errno::ESRCH) => Ok(false),
+        Err(e) => Err(io::Error::from_raw_os_error(e as i32)),
+    }
+}
+
+/// Check whether a process with the given PID is running.
+#[cfg(windows)]
+pub fn is_running(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::{CloseHandle, STILL_ACTIVE};
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return false;
+        }
+        let mut code = 0u32;
+        let ok = GetExitCodeProcess(handle, &mut code) != 0;
+        CloseHandle(handle);
+        ok && code == STILL_ACTIVE as u32
+    }
+}
+
+/// Executable a process was started with.
+#[cfg(windows)]
+fn executable(pid: u32) -> Option<String> {
+    use windows_sys::Win32::Foundation::{CloseHandle, MAX_PATH};
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return None;
+        }
+        let mut buf = [0u16; MAX_PATH as usize];
+        let mut len = buf.len() as u32;
+        let ok = QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, buf.as_mut_ptr(), &mut len) != 0;
+        CloseHandle(handle);
+        ok.then(|| String::from_utf16_lossy(&buf[..len as usize]))
+    }
+}
+
+/// Ask an issuer node to stop, letting it drain in-flight requests.
+///
+/// Returns `Ok(false)` if the process was not running.
+#[cfg(windows)]
+pub fn terminate(pid: u32) -> io::Result<bool> {
+    use std::io::Write;
+    use windows_sys::Win32::Foundation::ERROR_PIPE_BUSY;
+
+    let name = hesha_types::stop_pipe_name(pid);
+    let deadline = Instant::now() + Duration::from_secs(2);
+    loop {
+        match std::fs::OpenOptions::new().write(true).open(&name) {
+            Ok(mut pipe) => {
+                pipe.write_all(b"stop")?;
+                return Ok(true);
+            }
+            // The node is between two stop pipe connections
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY as i32) && Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            Err(_) if !is_running(pid) => return Ok(false),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("process {} does not accept stop requests; use 'hesha stop --force' to kill it", pid),
+                ));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Stop a process immediately, without letting it clean up.
+///
+/// Returns `Ok(false)` if the process was not running.
+#[cfg(windows)]
+pub fn kill(pid: u32) -> io::Result<bool> {
+    use windows_sys::Win32::Foundation::{CloseHandle, ERROR_INVALID_PARAMETER};
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, TerminateProcess, WaitForSingleObject, PROCESS_SYNCHRONIZE, PROCESS_TERMINATE,
+    };
+
+    /// How long to wait for the process to exit, in milliseconds.
+    const EXIT_TIMEOUT_MS: u32 = 5000;
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_TERMINATE | PROCESS_SYNCHRONIZE, 0, pid);
+        if handle.is_null() {
+            let err = io::Error::last_os_error();
+            // No such process
+            if err.raw_os_error() == Some(ERROR_INVALID_PARAMETER as i32) {
+                return Ok(false);
+            }
+            return Err(err);
+        }
+
+        let result = if TerminateProcess(handle, 1) != 0 {
+            WaitForSingleObject(handle, EXIT_TIMEOUT_MS);
+            Ok(true)
+        } else {
+            Err(io::Error::last_os_error())
+        };
+        CloseHandle(handle);
+        result
+    }
+}
+
+/// Start a stand-in issuer node: a copy of `sleep` named like the node
+/// binary, reaped in the background so it disappears once stopped.
+///
+/// Returns once the new command line is visible, which can lag the spawn.
+#[cfg(all(test, unix))]
+pub(crate) fn spawn_fake_issuer(dir: &Path) -> u32 {
+    let binary = dir.join(ISSUER_BINARY);
+    std::fs::copy("/bin/sleep", &binary).unwrap();
+    let mut child = Command::new(&binary).arg("30").spawn().unwrap();
+    let pid = child.id();
+    std::thread::spawn(move || child.wait());
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while executable(pid).unwrap_or_default().is_empty() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    pid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_other_processes_are_not_issuers() {
+        assert!(is_running(std::process::id()));
+        assert!(!is_issuer(std::process::id()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_issuer() {
+        let temp = tempfile::tempdir().unwrap();
+        let pid = spawn_fake_issuer(temp.path());
+        assert!(is_issuer(pid));
+
+        assert!(terminate(pid).unwrap());
+        assert!(wait_for_exit(pid, Duration::from_secs(5)));
+        assert!(!is_issuer(pid));
+        assert!(!terminate(pid).unwrap());
+    }
+}
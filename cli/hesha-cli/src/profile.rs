@@ -0,0 +1,168 @@
+//! Named configuration profiles stored in ~/.hesha/profiles.toml.
+//!
+//! ```toml
+//! default = "work"
+//!
+//! [profiles.work]
+//! issuer = "https://issuer.example.com"
+//! key = "work"
+//! scope = "1"
+//! validity_days = 90
+//! ```
+//!
+//! Each setting can also come from a `HESHA_*` environment variable, which
+//! overrides the profile; command-line flags override both.
+
+use crate::config::config_dir;
+use anyhow::Context;
+use hesha_types::MAX_VALIDITY_DAYS;
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// Environment variable selecting the active profile.
+pub const PROFILE_ENV: &str = "HESHA_PROFILE";
+
+/// Environment variable overriding the profile's issuer URL.
+pub const ISSUER_ENV: &str = "HESHA_ISSUER";
+
+/// Environment variable overriding the profile's key.
+pub const KEY_ENV: &str = "HESHA_KEY";
+
+/// Environment variable overriding the profile's scope.
+pub const SCOPE_ENV: &str = "HESHA_SCOPE";
+
+/// Environment variable overriding the profile's validity period.
+pub const VALIDITY_DAYS_ENV: &str = "HESHA_VALIDITY_DAYS";
+
+/// Per-profile defaults for command flags.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Default issuer URL.
+    pub issuer: Option<String>,
+    /// Default key file or stored key name.
+    pub key: Option<String>,
+    /// Default proxy number scope.
+    pub scope: Option<String>,
+    /// Default attestation validity in days.
+    pub validity_days: Option<i64>,
+}
+
+impl Profile {
+    /// Apply `HESHA_*` environment variables over the profile's values.
+    pub fn with_env(mut self) -> anyhow::Result<Self> {
+        let var = |name| std::env::var(name).ok().filter(|value: &String| !value.is_empty());
+
+        if let Some(issuer) = var(ISSUER_ENV) {
+            self.issuer = Some(issuer);
+        }
+        if let Some(key) = var(KEY_ENV) {
+            self.key = Some(key);
+        }
+        if let Some(scope) = var(SCOPE_ENV) {
+            self.scope = Some(scope);
+        }
+        if let Some(days) = var(VALIDITY_DAYS_ENV) {
+            let days: i64 = days.parse()
+                .with_context(|| format!("{} must be a number of days, got '{}'", VALIDITY_DAYS_ENV, days))?;
+            anyhow::ensure!(
+                (1..=MAX_VALIDITY_DAYS).contains(&days),
+                "{} must be between 1 and {}",
+                VALIDITY_DAYS_ENV,
+                MAX_VALIDITY_DAYS
+            );
+            self.validity_days = Some(days);
+        }
+
+        Ok(self)
+    }
+}
+
+/// Contents of profiles.toml.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfilesFile {
+    /// Profile used when none is selected.
+    default: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+}
+
+/// Load the selected profile.
+///
+/// An explicitly requested profile (flag or `HESHA_PROFILE`) must exist.
+/// Without one, the file's `default` profile is used if set, otherwise an
+/// empty profile.
+pub fn load_profile(name: Option<&str>) -> anyhow::Result<Profile> {
+    load_profile_from(&config_dir().join("profiles.toml"), name)
+}
+
+fn load_profile_from(path: &Path, name: Option<&str>) -> anyhow::Result<Profile> {
+    let file: ProfilesFile = if path.exists() {
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&data).with_context(|| format!("Invalid profiles file {}", path.display()))?
+    } else {
+        ProfilesFile::default()
+    };
+
+    match name.map(str::to_string).or(file.default) {
+        Some(name) => file.profiles.get(&name).cloned().with_context(|| {
+            format!("Profile '{}' not found in {}", name, path.display())
+        }),
+        None => Ok(Profile::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILES: &str = r#"
+default = "work"
+
+[profiles.work]
+issuer = "https://issuer.example.com"
+key = "work"
+scope = "1"
+validity_days = 90
+
+[profiles.test]
+issuer = "http://localhost:3000"
+"#;
+
+    #[test]
+    fn test_load_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profiles.toml");
+
+        // No file means no defaults, but a requested profile must exist
+        assert!(load_profile_from(&path, None).unwrap().issuer.is_none());
+        assert!(load_profile_from(&path, Some("work")).is_err());
+
+        fs::write(&path, PROFILES).unwrap();
+        let work = load_profile_from(&path, None).unwrap();
+        assert_eq!(work.issuer.as_deref(), Some("https://issuer.example.com"));
+        assert_eq!(work.key.as_deref(), Some("work"));
+        assert_eq!(work.validity_days, Some(90));
+
+        let test = load_profile_from(&path, Some("test")).unwrap();
+        assert_eq!(test.issuer.as_deref(), Some("http://localhost:3000"));
+        assert!(test.scope.is_none());
+
+        let err = load_profile_from(&path, Some("missing")).unwrap_err();
+        assert!(err.to_string().contains("'missing' not found"), "{}", err);
+    }
+
+    #[test]
+    fn test_rejects_unknown_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profiles.toml");
+
+        fs::write(&path, "[profiles.work]\nisuer = \"https://issuer.example.com\"\n").unwrap();
+        assert!(load_profile_from(&path, Some("work")).is_err());
+
+        fs::write(&path, "defualt = \"work\"\n").unwrap();
+        assert!(load_profile_from(&path, None).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+//! Local attestation wallet under ~/.hesha/wallet.
+//!
+//! Each attestation is stored as `<jti>.json` holding the JWT, the
+//! metadata needed to list it without re-parsing, and the name of the
+//! stored key it was issued to (if known).
+
+use crate::config::{config_dir, write_private};
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use hesha_core::parse_attestation_jwt;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+/// An attestation stored in the wallet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletEntry {
+    /// Attestation ID (the JWT `jti`).
+    pub id: String,
+    /// Optional user-supplied label.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Issuer domain.
+    pub issuer: String,
+    /// Assigned proxy number.
+    pub proxy_number: String,
+    /// When the attestation was issued.
+    pub issued_at: DateTime<Utc>,
+    /// When the attestation expires.
+    pub expires_at: DateTime<Utc>,
+    /// Name of the stored key bound to the attestation, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// When the attestation was added to the wallet.
+    pub added_at: DateTime<Utc>,
+    /// The attestation JWT.
+    pub jwt: String,
+}
+
+impl WalletEntry {
+    /// Build a wallet entry from an attestation JWT.
+    pub fn from_jwt(jwt: &str, label: Option<String>, key: Option<String>) -> anyhow::Result<Self> {
+        let jwt = jwt.trim();
+        let attestation = parse_attestation_jwt(jwt)?;
+
+        Ok(Self {
+            id: attestation.jti,
+            label,
+            issuer: attestation.iss,
+            proxy_number: attestation.proxy_number.to_string(),
+            issued_at: attestation.iat,
+            expires_at: attestation.exp,
+            key,
+            added_at: Utc::now(),
+            jwt: jwt.to_string(),
+        })
+    }
+
+    /// Check if the attestation has expired.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+
+    /// Check if the attestation expires within the given window.
+    pub fn expires_within(&self, window: Duration) -> bool {
+        self.expires_at <= Utc::now() + window
+    }
+}
+
+/// Directory-backed store of attestations.
+pub struct Wallet {
+    dir: PathBuf,
+}
+
+impl Wallet {
+    /// Open the wallet in the default location (~/.hesha/wallet).
+    pub fn open_default() -> Self {
+        Self::new(config_dir().join("wallet"))
+    }
+
+    /// Open a wallet rooted at the given directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Add an entry, replacing any entry with the same ID.
+    pub fn add(&self, entry: &WalletEntry) -> anyhow::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create wallet directory {}", self.dir.display()))?;
+
+        // Entries hold the phone number
+        let path = self.path(&entry.id);
+        write_private(&path, &serde_json::to_string_pretty(entry)?)?;
+        Ok(path)
+    }
+
+    /// List all entries, soonest expiry first.
+    pub fn list(&self) -> anyhow::Result<Vec<WalletEntry>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for file in fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let data = fs::read_to_string(&path)?;
+            let entry: WalletEntry = serde_json::from_str(&data)
+                .with_context(|| format!("Invalid wallet entry {}", path.display()))?;
+            entries.push(entry);
+        }
+
+        entries.sort_by_key(|entry| entry.expires_at);
+        Ok(entries)
+    }
+
+    /// Find an entry by ID, unique ID prefix, label or proxy number.
+    pub fn find(&self, query: &str) -> anyhow::Result<WalletEntry> {
+        let mut matches: Vec<WalletEntry> = self
+            .list()?
+            .into_iter()
+            .filter(|entry| {
+                entry.id.starts_with(query)
+                    || entry.label.as_deref() == Some(query)
+                    || entry.proxy_number == query
+            })
+            .collect();
+
+        match matches.len() {
+            0 => anyhow::bail!("No attestation matching '{}' in wallet", query),
+            1 => Ok(matches.remove(0)),
+            n => anyhow::bail!("'{}' matches {} attestations; use a longer ID", query, n),
+        }
+    }
+
+    /// Remove an entry by ID.
+    pub fn remove(&self, id: &str) -> anyhow::Result<()> {
+        fs::remove_file(self.path(id))
+            .with_context(|| format!("Failed to remove attestation {}", id))
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        // jti values are UUIDs, but never trust them as file names
+        let safe: String = id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        self.dir.join(format!("{}.json", safe))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hesha_crypto::generate_keypair;
+    use hesha_types::{PhoneNumber, ProxyNumber};
+
+    fn entry(proxy_number: &str, label: Option<&str>) -> WalletEntry {
+        let issuer = generate_keypair().unwrap();
+        let user = generate_keypair().unwrap();
+        let jwt = hesha_core::create_attestation(
+            "issuer.example.com",
+            &issuer.private,
+            &PhoneNumber::new("+14155552671").unwrap(),
+            &ProxyNumber::new(proxy_number).unwrap(),
+            &user.public,
+        )
+        .unwrap();
+        WalletEntry::from_jwt(&jwt, label.map(str::to_string), None).unwrap()
+    }
+
+    #[test]
+    fn test_add_find_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let wallet = Wallet::new(dir.path().join("wallet"));
+        assert!(wallet.list().unwrap().is_empty());
+
+        let mut later = entry("+10015551234567", Some("work"));
+        later.expires_at = Utc::now() + Duration::days(300);
+        let mut sooner = entry("+10015557654321", None);
+        sooner.expires_at = Utc::now() + Duration::days(10);
+        wallet.add(&later).unwrap();
+        wallet.add(&sooner).unwrap();
+
+        let ids: Vec<String> = wallet.list().unwrap().into_iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, [sooner.id.clone(), later.id.clone()]);
+
+        assert_eq!(wallet.find("work").unwrap().id, later.id);
+        assert_eq!(wallet.find("+10015557654321").unwrap().id, sooner.id);
+        assert_eq!(wallet.find(&later.id[..8]).unwrap().id, later.id);
+        assert!(wallet.find("").is_err());
+        assert!(wallet.find("home").is_err());
+
+        assert!(sooner.expires_within(Duration::days(30)));
+        assert!(!later.expires_within(Duration::days(30)));
+        assert!(!later.is_expired());
+
+        // Adding again replaces the entry
+        later.label = Some("personal".to_string());
+        wallet.add(&later).unwrap();
+        assert_eq!(wallet.list().unwrap().len(), 2);
+        assert_eq!(wallet.find("personal").unwrap().id, later.id);
+
+        wallet.remove(&sooner.id).unwrap();
+        assert!(wallet.find(&sooner.id).is_err());
+        assert!(wallet.remove(&sooner.id).is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_attestations() {
+        assert!(WalletEntry::from_jwt("not-a-jwt", None, None).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let wallet = Wallet::new(dir.path());
+        fs::write(dir.path().join("broken.json"), "{}").unwrap();
+        assert!(wallet.list().is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! Command-line behaviour of the `hesha` binary.
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+
+#[test]
+fn test_keygen_encrypted() {
+    let mut cmd = Command::cargo_bin("hesha").unwrap();
+    cmd.args(["keygen", "--encrypt"])
+        .env("HESHA_KEY_PASSPHRASE", "test-passphrase")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ciphertext"))
+        .stdout(predicate::str::contains("private_key").not());
+}
+
+#[test]
+fn test_keygen_json_output_mode() {
+    let mut cmd = Command::cargo_bin("hesha").unwrap();
+    let output = cmd.args(["--output", "json", "keygen", "--format", "hex"])
+        .output()
+        .unwrap();
+    
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["encoding"], "hex");
+    assert!(json["private_key"].is_string());
+}
+
+#[test]
+fn test_key_fingerprint_from_stdin() {
+    let keygen = Command::cargo_bin("hesha").unwrap()
+        .args(["keygen"])
+        .output()
+        .unwrap();
+    
+    let mut cmd = Command::cargo_bin("hesha").unwrap();
+    cmd.args(["key", "fingerprint", "-"])
+        .write_stdin(keygen.stdout)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(":"));
+}
+
+#[test]
+fn test_verify_exit_codes() {
+    // Malformed attestations exit with the invalid-attestation code
+    let mut cmd = Command::cargo_bin("hesha").unwrap();
+    cmd.args(["verify", "--quiet", "-a", "eyJinvalid"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::is_empty());
+    
+    // Usage errors don't collide with verification failures
+    let mut cmd = Command::cargo_bin("hesha").unwrap();
+    cmd.args(["verify", "--no-such-flag"])
+        .assert()
+        .code(64);
+}
+
+#[test]
+fn test_inspect_raw_rejects_malformed_jwt() {
+    for jwt in ["eyJhbGciOiJFZERTQSJ9", "eyJhbGciOiJFZERTQSJ9.e30", "eyJhbGciOiJFZERTQSJ9.e30!.c2ln", "eyJ9.e30.c2ln.e30"] {
+        let mut cmd = Command::cargo_bin("hesha").unwrap();
+        cmd.args(["inspect", jwt, "--raw"])
+            .assert()
+            .code(2)
+            .stdout(predicate::str::is_empty());
+    }
+    
+    let mut cmd = Command::cargo_bin("hesha").unwrap();
+    cmd.args(["inspect", "eyJhbGciOiJFZERTQSJ9.eyJpc3MiOiJ4In0.c2ln", "--raw"])
+        .assert()
+        .success()
+        .stdout("{\"iss\":\"x\"}\n");
+}
+
+#[test]
+fn test_verify_all_summary() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.jwt"), "eyJinvalid").unwrap();
+    fs::write(dir.path().join("b.jwt"), "not-a-jwt").unwrap();
+    let pattern = dir.path().join("*.jwt");
+    
+    // Every failure is an invalid attestation, so the batch shares its code
+    let mut cmd = Command::cargo_bin("hesha").unwrap();
+    cmd.args(["--output", "json", "verify", "--all", pattern.to_str().unwrap()])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("\"invalid\": 2"))
+        .stdout(predicate::str::contains("\"total\": 2"));
+}
+
+#[test]
+fn test_ceremony_needs_yes_without_terminal() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("ceremony");
+    let run = |extra: &[&str]| {
+        let mut cmd = Command::cargo_bin("hesha").unwrap();
+        cmd.args(["ceremony", "run", "--custodian", "alice", "--custodian", "bob", "-t", "2"])
+            .args(["--out", out.to_str().unwrap()])
+            .args(extra);
+        cmd
+    };
+    
+    // Nobody can confirm that the custodians are present
+    run(&[])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--yes"));
+    assert!(!out.exists());
+    
+    // Quiet runs print nothing but the outcome is on disk
+    run(&["--yes", "--quiet"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+    assert!(out.join("transcript.json").exists());
+}
+
+#[test]
+fn test_attest_accepts_legacy_output_flag() {
+    let home = tempfile::tempdir().unwrap();
+    
+    // Still taken as the attestation file, so parsing gets as far as the issuer
+    let mut cmd = Command::cargo_bin("hesha").unwrap();
+    cmd.args(["attest", "--output", "attestation.jwt", "-p", "+14155552671"])
+        .env("HOME", home.path())
+        .env_remove("HESHA_ISSUER")
+        .env_remove("HESHA_PROFILE")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("No issuer given"));
+    
+    // Other commands only take a mode
+    let mut cmd = Command::cargo_bin("hesha").unwrap();
+    cmd.args(["keygen", "--output", "key.json"])
+        .assert()
+        .code(64)
+        .stderr(predicate::str::contains("invalid value 'key.json'"));
+}
@@ -0,0 +1,205 @@
+//! Load generator for Hesha nodes.
+//!
+//! Sends a weighted mix of the traffic a deployment sees — key discovery,
+//! partner attestation requests, OTP verification flows and relying-party
+//! verification — at a fixed rate, and reports latency percentiles per
+//! kind of traffic so operators can size hardware before launch.
+//!
+//! The rate is open-loop: operations start on schedule whether or not
+//! earlier ones have finished, up to a concurrency limit. Operations due
+//! while the limit is reached are counted as dropped rather than delayed,
+//! so a slow target shows up as drops instead of a lower rate.
+
+pub mod scenario;
+pub mod stats;
+
+pub use scenario::{Mix, Scenario};
+pub use stats::{Report, ScenarioReport};
+
+use anyhow::Context;
+use hesha_client::IssuerClient;
+use scenario::Target;
+use stats::Recorder;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Default mix: mostly key fetches, as verifiers cache little in practice.
+pub const DEFAULT_MIX: &str = "pubkey=60,attest=20,otp=10,verify=10";
+
+/// Settings of a run.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Base URL of the issuer node.
+    pub target: String,
+    /// Base URL of a verifier node, needed for verify traffic.
+    pub verifier: Option<String>,
+    /// Operations started per second.
+    pub rate: f64,
+    /// How long to send traffic for.
+    pub duration: Duration,
+    /// Most operations in flight at once.
+    pub concurrency: usize,
+    /// Weights of the scenarios.
+    pub mix: Mix,
+    /// Bearer token for `/attest`.
+    pub bearer_token: Option<String>,
+    /// Code entered in OTP flows.
+    pub otp_code: String,
+    /// Prefix of the generated phone numbers.
+    pub phone_prefix: String,
+    /// Scope attestations are requested for.
+    pub scope: String,
+}
+
+impl Options {
+    /// Defaults for a target: 10 operations a second for 30 seconds.
+    pub fn new(target: impl Into<String>) -> Self {
+        Self {
+            target: target.into(),
+            verifier: None,
+            rate: 10.0,
+            duration: Duration::from_secs(30),
+            concurrency: 64,
+            mix: DEFAULT_MIX.parse().expect("default mix is valid"),
+            bearer_token: None,
+            otp_code: "123456".into(),
+            phone_prefix: "+1415555".into(),
+            scope: "1".into(),
+        }
+    }
+}
+
+/// Send traffic as configured and report the results.
+pub async fn run(options: &Options) -> anyhow::Result<Report> {
+    if !(options.rate > 0.0 && options.rate.is_finite()) {
+        anyhow::bail!("The rate must be a positive number");
+    }
+    if options.concurrency == 0 {
+        anyhow::bail!("The concurrency must be at least 1");
+    }
+    let verifier_url = options
+        .verifier
+        .as_deref()
+        .map(base_url)
+        .transpose()
+        .context("Invalid verifier URL")?;
+    if options.mix.includes(Scenario::Verify) && verifier_url.is_none() {
+        anyhow::bail!("The mix includes verify traffic but no verifier URL was given");
+    }
+
+    let mut issuer = IssuerClient::new_insecure(&options.target)?;
+    if let Some(token) = &options.bearer_token {
+        issuer = issuer.with_bearer_token(token.clone());
+    }
+    let target = Arc::new(Target::new(
+        issuer,
+        base_url(&options.target).context("Invalid target URL")?,
+        verifier_url,
+        options.phone_prefix.clone(),
+        options.scope.clone(),
+        options.otp_code.clone(),
+    )?);
+
+    let recorder = Arc::new(Mutex::new(Recorder::default()));
+    let permits = Arc::new(Semaphore::new(options.concurrency));
+    let mut tasks = JoinSet::new();
+    let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / options.rate));
+    let started = Instant::now();
+    let deadline = tokio::time::Instant::now() + options.duration;
+
+    loop {
+        let tick = ticker.tick().await;
+        if tick >= deadline {
+            break;
+        }
+        let scenario = options.mix.pick(&mut rand::thread_rng());
+        let Ok(permit) = Arc::clone(&permits).try_acquire_owned() else {
+            lock(&recorder).record_dropped();
+            continue;
+        };
+        let target = Arc::clone(&target);
+        let recorder = Arc::clone(&recorder);
+        tasks.spawn(async move {
+            let _permit = permit;
+            let begun = Instant::now();
+            let result = target.run(scenario).await;
+            lock(&recorder).record(scenario, begun.elapsed(), result);
+        });
+        // Reap finished tasks so the set doesn't grow for the whole run.
+        while tasks.try_join_next().is_some() {}
+    }
+    while tasks.join_next().await.is_some() {}
+
+    let recorder = std::mem::take(&mut *lock(&recorder));
+    Ok(recorder.report(options.target.clone(), options.rate, started.elapsed()))
+}
+
+/// Parse a base URL, making sure relative joins keep its path.
+fn base_url(url: &str) -> anyhow::Result<reqwest::Url> {
+    let mut url = reqwest::Url::parse(url)?;
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    Ok(url)
+}
+
+fn lock(recorder: &Mutex<Recorder>) -> std::sync::MutexGuard<'_, Recorder> {
+    recorder.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hesha_testkit::{Behavior, MockIssuer};
+
+    #[tokio::test]
+    async fn test_run_against_mock_issuer() {
+        let issuer = MockIssuer::start().await.unwrap();
+        let options = Options {
+            rate: 50.0,
+            duration: Duration::from_millis(600),
+            mix: "pubkey=1,attest=1".parse().unwrap(),
+            ..Options::new(issuer.url())
+        };
+
+        let report = run(&options).await.unwrap();
+        assert!(report.requests >= 20, "{:?}", report);
+        assert_eq!(report.errors, 0, "{:?}", report);
+        assert_eq!(report.dropped, 0);
+        assert!(report.scenario(Scenario::Pubkey).unwrap().requests > 0);
+        let attest = report.scenario(Scenario::Attest).unwrap();
+        assert_eq!(attest.requests, issuer.attestations_issued());
+        assert!(attest.max_ms >= attest.p50_ms);
+    }
+
+    #[tokio::test]
+    async fn test_slow_target_drops() {
+        let issuer = MockIssuer::start_with(Behavior {
+            latency: Duration::from_millis(300),
+            ..Behavior::default()
+        })
+        .await
+        .unwrap();
+        let options = Options {
+            rate: 50.0,
+            duration: Duration::from_millis(200),
+            concurrency: 2,
+            mix: "pubkey=1".parse().unwrap(),
+            ..Options::new(issuer.url())
+        };
+
+        let report = run(&options).await.unwrap();
+        assert_eq!(report.requests, 2);
+        assert!(report.dropped > 0);
+    }
+
+    #[tokio::test]
+    async fn test_verify_needs_verifier() {
+        let options = Options::new("http://127.0.0.1:1");
+        let error = run(&options).await.unwrap_err();
+        assert!(error.to_string().contains("verifier"));
+    }
+}
@@ -0,0 +1,103 @@
+//! `hesha-loadgen`: drive mixed traffic against Hesha nodes and report
+//! latency percentiles.
+//!
+//! Prints the report as a table (or JSON with `--format json`) and exits
+//! with status 1 if any operation failed or was dropped.
+
+use clap::{Parser, ValueEnum};
+use hesha_loadgen::{run, Mix, Options, DEFAULT_MIX};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Report formats.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Text,
+}
+
+/// Send realistic mixed traffic to a Hesha issuer and verifier.
+#[derive(Debug, Parser)]
+#[command(name = "hesha-loadgen", version, about)]
+struct Args {
+    /// Base URL of the issuer.
+    target: String,
+
+    /// Base URL of a verifier node, for verify traffic.
+    #[arg(long, env = "HESHA_LOADGEN_VERIFIER")]
+    verifier: Option<String>,
+
+    /// Operations started per second.
+    #[arg(long, default_value_t = 10.0)]
+    rate: f64,
+
+    /// How long to send traffic for, in seconds.
+    #[arg(long, default_value_t = 30)]
+    duration_secs: u64,
+
+    /// Most operations in flight at once.
+    #[arg(long, default_value_t = 64)]
+    concurrency: usize,
+
+    /// Weights of the scenarios: pubkey, attest, otp and verify.
+    #[arg(long, default_value = DEFAULT_MIX)]
+    mix: Mix,
+
+    /// Bearer token for /attest.
+    #[arg(long, env = "HESHA_LOADGEN_BEARER_TOKEN")]
+    bearer_token: Option<String>,
+
+    /// Code entered in OTP flows. The issuer's mock SMS provider accepts
+    /// 123456.
+    #[arg(long, default_value = "123456")]
+    otp_code: String,
+
+    /// Prefix of the generated phone numbers.
+    #[arg(long, default_value = "+1415555")]
+    phone_prefix: String,
+
+    /// Scope to request attestations for.
+    #[arg(long, default_value = "1")]
+    scope: String,
+
+    /// Report format.
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Write the report to this file instead of standard output.
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let options = Options {
+        verifier: args.verifier,
+        rate: args.rate,
+        duration: Duration::from_secs(args.duration_secs),
+        concurrency: args.concurrency,
+        mix: args.mix,
+        bearer_token: args.bearer_token,
+        otp_code: args.otp_code,
+        phone_prefix: args.phone_prefix,
+        scope: args.scope,
+        ..Options::new(args.target)
+    };
+
+    let report = run(&options).await?;
+    let rendered = match args.format {
+        Format::Json => serde_json::to_string_pretty(&report)? + "\n",
+        Format::Text => report.to_text(),
+    };
+    match &args.output {
+        Some(path) => std::fs::write(path, rendered)
+            .map_err(|e| anyhow::anyhow!("Cannot write {}: {}", path.display(), e))?,
+        None => print!("{}", rendered),
+    }
+
+    if report.errors > 0 || report.dropped > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
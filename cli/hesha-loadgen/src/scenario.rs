@@ -0,0 +1,257 @@
+//! The kinds of traffic and how they are mixed.
+
+use anyhow::Context;
+use hesha_client::IssuerClient;
+use hesha_types::{PhoneNumber, VerifyRequest};
+use rand::Rng;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// Attestations kept for verify traffic.
+const POOL_SIZE: usize = 256;
+
+/// One kind of user-facing operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scenario {
+    /// `GET /.well-known/hesha/pubkey.json`, as verifiers discovering keys.
+    Pubkey,
+    /// `POST /attest`, as a partner backend requesting attestations.
+    Attest,
+    /// `/verify/start`, `/verify/check` and `/attest/simple`, as a user
+    /// proving their phone by OTP.
+    Otp,
+    /// `POST /verify` on a verifier node, as a relying party.
+    Verify,
+}
+
+impl Scenario {
+    /// Every scenario.
+    pub const ALL: [Scenario; 4] = [Self::Pubkey, Self::Attest, Self::Otp, Self::Verify];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Pubkey => "pubkey",
+            Self::Attest => "attest",
+            Self::Otp => "otp",
+            Self::Verify => "verify",
+        }
+    }
+}
+
+impl fmt::Display for Scenario {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Scenario {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|scenario| scenario.name() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown scenario '{}' (expected pubkey, attest, otp or verify)", s))
+    }
+}
+
+/// Relative weights of the scenarios, e.g. `pubkey=70,attest=10,otp=10,verify=10`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mix {
+    weights: Vec<(Scenario, u32)>,
+}
+
+impl Mix {
+    /// Pick a scenario at random, in proportion to the weights.
+    pub fn pick(&self, rng: &mut impl Rng) -> Scenario {
+        let total: u32 = self.weights.iter().map(|(_, weight)| weight).sum();
+        let mut roll = rng.gen_range(0..total);
+        for (scenario, weight) in &self.weights {
+            if roll < *weight {
+                return *scenario;
+            }
+            roll -= weight;
+        }
+        self.weights[self.weights.len() - 1].0
+    }
+
+    /// Whether the mix includes `scenario`.
+    pub fn includes(&self, scenario: Scenario) -> bool {
+        self.weights.iter().any(|(s, _)| *s == scenario)
+    }
+}
+
+impl FromStr for Mix {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut weights = Vec::new();
+        for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            let (name, weight) = part
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Expected scenario=weight, got '{}'", part))?;
+            let scenario: Scenario = name.trim().parse()?;
+            let weight: u32 = weight
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid weight for {}", scenario))?;
+            if weights.iter().any(|(s, _)| *s == scenario) {
+                anyhow::bail!("{} is weighted twice", scenario);
+            }
+            if weight > 0 {
+                weights.push((scenario, weight));
+            }
+        }
+        if weights.is_empty() {
+            anyhow::bail!("The mix has no scenario with a weight");
+        }
+        Ok(Self { weights })
+    }
+}
+
+/// What the scenarios talk to.
+pub struct Target {
+    /// Client of the issuer node.
+    pub issuer: IssuerClient,
+    /// Base URL of the issuer node, for the key document.
+    pub issuer_url: reqwest::Url,
+    /// Base URL of the verifier node, for verify traffic.
+    pub verifier_url: Option<reqwest::Url>,
+    /// Plain HTTP client.
+    pub http: reqwest::Client,
+    /// Prefix of the generated phone numbers.
+    pub phone_prefix: String,
+    /// Scope attestations are requested for.
+    pub scope: String,
+    /// Code entered in OTP flows; only an issuer with the mock provider
+    /// accepts a fixed code.
+    pub otp_code: String,
+    /// Recently issued attestations, for verify traffic.
+    pool: Mutex<Vec<String>>,
+}
+
+impl Target {
+    /// Traffic for the given nodes.
+    pub fn new(
+        issuer: IssuerClient,
+        issuer_url: reqwest::Url,
+        verifier_url: Option<reqwest::Url>,
+        phone_prefix: String,
+        scope: String,
+        otp_code: String,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            issuer,
+            issuer_url,
+            verifier_url,
+            http: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(30))
+                .build()?,
+            phone_prefix,
+            scope,
+            otp_code,
+            pool: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Run one operation of `scenario`.
+    ///
+    /// Returns `Ok(false)` if it could not run, such as verify traffic
+    /// before any attestation was issued.
+    pub async fn run(&self, scenario: Scenario) -> anyhow::Result<bool> {
+        match scenario {
+            Scenario::Pubkey => {
+                let url = self.issuer_url.join(".well-known/hesha/pubkey.json")?;
+                self.http.get(url).send().await?.error_for_status()?.bytes().await?;
+            }
+            Scenario::Attest => {
+                let user = hesha_crypto::generate_keypair()?;
+                let response = self
+                    .issuer
+                    .request_attestation(&self.phone()?, &user.public, &self.scope, None)
+                    .await?;
+                self.keep(response.attestation);
+            }
+            Scenario::Otp => {
+                let user = hesha_crypto::generate_keypair()?;
+                let session = self.issuer.start_verification(&self.phone()?).await?;
+                if !self.issuer.check_verification(&session.session_id, &self.otp_code).await? {
+                    anyhow::bail!("Verification code was refused");
+                }
+                let response = self
+                    .issuer
+                    .request_session_attestation(&session.session_id, &user.public, &self.scope, None)
+                    .await?;
+                self.keep(response.attestation);
+            }
+            Scenario::Verify => {
+                let Some(verifier_url) = &self.verifier_url else {
+                    return Ok(false);
+                };
+                let Some(attestation) = self.pooled() else {
+                    return Ok(false);
+                };
+                self.http
+                    .post(verifier_url.join("verify")?)
+                    .json(&VerifyRequest { attestation })
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// A random phone number with the configured prefix.
+    fn phone(&self) -> anyhow::Result<PhoneNumber> {
+        let digits = 12usize.saturating_sub(self.phone_prefix.trim_start_matches('+').len()).max(1);
+        let mut rng = rand::thread_rng();
+        let suffix: String = (0..digits).map(|_| char::from(b'0' + rng.gen_range(0..10))).collect();
+        Ok(PhoneNumber::new(format!("{}{}", self.phone_prefix, suffix))?)
+    }
+
+    fn keep(&self, attestation: String) {
+        let mut pool = self.pool.lock().unwrap_or_else(|e| e.into_inner());
+        if pool.len() == POOL_SIZE {
+            let slot = rand::thread_rng().gen_range(0..POOL_SIZE);
+            pool[slot] = attestation;
+        } else {
+            pool.push(attestation);
+        }
+    }
+
+    fn pooled(&self) -> Option<String> {
+        let pool = self.pool.lock().unwrap_or_else(|e| e.into_inner());
+        if pool.is_empty() {
+            return None;
+        }
+        Some(pool[rand::thread_rng().gen_range(0..pool.len())].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_mix() {
+        let mix: Mix = "pubkey=3, attest=1,otp=0".parse().unwrap();
+        assert!(mix.includes(Scenario::Pubkey));
+        assert!(!mix.includes(Scenario::Otp));
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let picks: Vec<Scenario> = (0..4000).map(|_| mix.pick(&mut rng)).collect();
+        let pubkeys = picks.iter().filter(|s| **s == Scenario::Pubkey).count();
+        assert!((2800..3200).contains(&pubkeys), "{}", pubkeys);
+        assert!(picks.iter().all(|s| *s != Scenario::Otp));
+
+        assert!("pubkey".parse::<Mix>().is_err());
+        assert!("otp=0".parse::<Mix>().is_err());
+        assert!("login=1".parse::<Mix>().is_err());
+        assert!("attest=1,attest=2".parse::<Mix>().is_err());
+    }
+}
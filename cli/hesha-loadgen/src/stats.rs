@@ -0,0 +1,237 @@
+//! Latency recording and the run report.
+
+use crate::scenario::Scenario;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Errors kept per scenario as examples in the report.
+const ERROR_SAMPLES: usize = 5;
+
+/// Outcomes of one scenario, as they come in.
+#[derive(Debug, Default)]
+struct Samples {
+    latencies: Vec<Duration>,
+    errors: u64,
+    skipped: u64,
+    error_samples: Vec<String>,
+}
+
+/// Collects the outcome of every operation of a run.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    scenarios: BTreeMap<Scenario, Samples>,
+    dropped: u64,
+}
+
+impl Recorder {
+    /// Record an operation that completed, successfully or not.
+    pub fn record(&mut self, scenario: Scenario, latency: Duration, result: anyhow::Result<bool>) {
+        let samples = self.scenarios.entry(scenario).or_default();
+        match result {
+            Ok(true) => samples.latencies.push(latency),
+            Ok(false) => samples.skipped += 1,
+            Err(error) => {
+                samples.latencies.push(latency);
+                samples.errors += 1;
+                let message = format!("{:#}", error);
+                if samples.error_samples.len() < ERROR_SAMPLES && !samples.error_samples.contains(&message) {
+                    samples.error_samples.push(message);
+                }
+            }
+        }
+    }
+
+    /// Record an operation that was due but not started because
+    /// `--concurrency` operations were already in flight.
+    pub fn record_dropped(&mut self) {
+        self.dropped += 1;
+    }
+
+    /// Summarise the run.
+    pub fn report(self, target: String, target_rate: f64, elapsed: Duration) -> Report {
+        let elapsed_secs = elapsed.as_secs_f64();
+        let scenarios: Vec<ScenarioReport> = self
+            .scenarios
+            .into_iter()
+            .map(|(scenario, samples)| ScenarioReport::new(scenario, samples, elapsed_secs))
+            .collect();
+        let requests = scenarios.iter().map(|s| s.requests).sum::<u64>();
+        let errors = scenarios.iter().map(|s| s.errors).sum::<u64>();
+        Report {
+            target,
+            target_rate,
+            elapsed_secs,
+            requests,
+            errors,
+            dropped: self.dropped,
+            achieved_rate: rate(requests, elapsed_secs),
+            scenarios,
+        }
+    }
+}
+
+/// Results of one scenario.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScenarioReport {
+    /// The scenario.
+    pub scenario: Scenario,
+    /// Operations that completed, successfully or not.
+    pub requests: u64,
+    /// Operations that failed.
+    pub errors: u64,
+    /// Operations that could not run, such as verify traffic before any
+    /// attestation was issued.
+    pub skipped: u64,
+    /// Completed operations per second.
+    pub rate: f64,
+    /// Median latency, in milliseconds.
+    pub p50_ms: f64,
+    /// 90th percentile latency, in milliseconds.
+    pub p90_ms: f64,
+    /// 99th percentile latency, in milliseconds.
+    pub p99_ms: f64,
+    /// Slowest operation, in milliseconds.
+    pub max_ms: f64,
+    /// Distinct error messages, for diagnosis.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub error_samples: Vec<String>,
+}
+
+impl ScenarioReport {
+    fn new(scenario: Scenario, mut samples: Samples, elapsed_secs: f64) -> Self {
+        samples.latencies.sort_unstable();
+        let latencies = &samples.latencies;
+        let requests = latencies.len() as u64;
+        Self {
+            scenario,
+            requests,
+            errors: samples.errors,
+            skipped: samples.skipped,
+            rate: rate(requests, elapsed_secs),
+            p50_ms: millis(percentile(latencies, 50.0)),
+            p90_ms: millis(percentile(latencies, 90.0)),
+            p99_ms: millis(percentile(latencies, 99.0)),
+            max_ms: millis(latencies.last().copied().unwrap_or_default()),
+            error_samples: samples.error_samples,
+        }
+    }
+}
+
+/// Results of a run.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// Issuer the traffic was sent to.
+    pub target: String,
+    /// Operations per second asked for.
+    pub target_rate: f64,
+    /// How long the run took, including draining in-flight operations.
+    pub elapsed_secs: f64,
+    /// Operations that completed, successfully or not.
+    pub requests: u64,
+    /// Operations that failed.
+    pub errors: u64,
+    /// Operations not started because the concurrency limit was reached.
+    /// A non-zero count means the target could not keep up with the rate.
+    pub dropped: u64,
+    /// Completed operations per second.
+    pub achieved_rate: f64,
+    /// Results per scenario.
+    pub scenarios: Vec<ScenarioReport>,
+}
+
+impl Report {
+    /// The results of one scenario, if it ran.
+    pub fn scenario(&self, scenario: Scenario) -> Option<&ScenarioReport> {
+        self.scenarios.iter().find(|s| s.scenario == scenario)
+    }
+
+    /// Render the report as a table.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{}: {} requests in {:.1}s ({:.1}/s of {:.1}/s asked), {} errors, {} dropped\n",
+            self.target, self.requests, self.elapsed_secs, self.achieved_rate, self.target_rate, self.errors, self.dropped
+        );
+        let _ = writeln!(
+            out,
+            "{:<8} {:>8} {:>7} {:>7} {:>8} {:>9} {:>9} {:>9} {:>9}",
+            "scenario", "requests", "errors", "skipped", "rate/s", "p50 ms", "p90 ms", "p99 ms", "max ms"
+        );
+        for s in &self.scenarios {
+            let _ = writeln!(
+                out,
+                "{:<8} {:>8} {:>7} {:>7} {:>8.1} {:>9.1} {:>9.1} {:>9.1} {:>9.1}",
+                s.scenario.to_string(), s.requests, s.errors, s.skipped, s.rate, s.p50_ms, s.p90_ms, s.p99_ms, s.max_ms
+            );
+        }
+        for s in &self.scenarios {
+            for error in &s.error_samples {
+                let _ = writeln!(out, "\n{} error: {}", s.scenario, error);
+            }
+        }
+        out
+    }
+}
+
+/// Nearest-rank percentile of sorted latencies.
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn rate(count: u64, secs: f64) -> f64 {
+    if secs > 0.0 {
+        count as f64 / secs
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let sorted: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&sorted, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&sorted, 90.0), Duration::from_millis(90));
+        assert_eq!(percentile(&sorted, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&sorted[..1], 99.0), Duration::from_millis(1));
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_report() {
+        let mut recorder = Recorder::default();
+        for ms in [30, 10, 20] {
+            recorder.record(Scenario::Pubkey, Duration::from_millis(ms), Ok(true));
+        }
+        recorder.record(Scenario::Attest, Duration::from_millis(5), Err(anyhow::anyhow!("refused")));
+        recorder.record(Scenario::Attest, Duration::from_millis(5), Err(anyhow::anyhow!("refused")));
+        recorder.record(Scenario::Verify, Duration::ZERO, Ok(false));
+        recorder.record_dropped();
+
+        let report = recorder.report("http://issuer".into(), 10.0, Duration::from_secs(2));
+        assert_eq!(report.requests, 5);
+        assert_eq!(report.errors, 2);
+        assert_eq!(report.dropped, 1);
+        assert_eq!(report.achieved_rate, 2.5);
+
+        let pubkey = report.scenario(Scenario::Pubkey).unwrap();
+        assert_eq!((pubkey.p50_ms, pubkey.max_ms), (20.0, 30.0));
+        assert_eq!(report.scenario(Scenario::Attest).unwrap().error_samples, vec!["refused"]);
+        assert_eq!(report.scenario(Scenario::Verify).unwrap().skipped, 1);
+        assert!(report.to_text().contains("attest error: refused"));
+    }
+}
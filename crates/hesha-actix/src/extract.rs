@@ -0,0 +1,137 @@
+//! The [`ProxyIdentity`] extractor.
+
+use actix_web::dev::Payload;
+use actix_web::http::{header, StatusCode};
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use hesha_core::verification::request_auth::{AuthError, RequestAuthenticator, VerifiedProxyIdentity};
+use hesha_types::{ErrorCode, Problem, PROBLEM_CONTENT_TYPE};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+/// The proxy identity the request authenticated as.
+///
+/// Taken from [`HeshaAuth`](crate::HeshaAuth) when the route is wrapped in
+/// it, otherwise checked with the `web::Data<RequestAuthenticator>`
+/// registered as app data. Requests that can't be authenticated are
+/// refused; an `Option<ProxyIdentity>` is None for them instead, including
+/// ones whose headers don't verify.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyIdentity(pub VerifiedProxyIdentity);
+
+impl FromRequest for ProxyIdentity {
+    type Error = AuthRejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, AuthRejection>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let authenticated = req.extensions().get::<VerifiedProxyIdentity>().cloned();
+        let authenticator = req.app_data::<web::Data<RequestAuthenticator>>().cloned();
+        let req = req.clone();
+
+        Box::pin(async move {
+            if let Some(identity) = authenticated {
+                return Ok(ProxyIdentity(identity));
+            }
+            let Some(authenticator) = authenticator else {
+                tracing::warn!("No RequestAuthenticator registered to authenticate {}", req.path());
+                return Err(AuthRejection(Box::new(Problem::new(
+                    ErrorCode::Unauthorized,
+                    "Request is not authenticated",
+                ))));
+            };
+            let (attestation, response) = crate::credentials(req.headers())?;
+            match authenticator.authenticate(attestation, response).await {
+                Ok(identity) => Ok(ProxyIdentity(identity)),
+                Err(e) => {
+                    tracing::debug!("Refused request to {}: {}", req.path(), e);
+                    Err(e.into())
+                }
+            }
+        })
+    }
+}
+
+/// A refused request, answered as an RFC 7807 problem document.
+#[derive(Debug)]
+pub struct AuthRejection(pub Box<Problem>);
+
+impl From<AuthError> for AuthRejection {
+    fn from(error: AuthError) -> Self {
+        Self(Box::new(error.problem()))
+    }
+}
+
+impl fmt::Display for AuthRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl ResponseError for AuthRejection {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.0.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code())
+            .insert_header((header::CONTENT_TYPE, PROBLEM_CONTENT_TYPE))
+            .body(serde_json::to_vec(&self.0).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Fixture;
+    use actix_web::{test, App};
+    use hesha_core::verification::challenge_response_header;
+
+    async fn me(ProxyIdentity(identity): ProxyIdentity) -> String {
+        identity.proxy_number.as_str().to_string()
+    }
+
+    async fn maybe(identity: Option<ProxyIdentity>) -> String {
+        identity.is_some().to_string()
+    }
+
+    #[actix_web::test]
+    async fn test_extractor() {
+        let fixture = Fixture::new();
+        let request = fixture.request("/me").to_request();
+        let app = test::init_service(
+            App::new()
+                .app_data(fixture.authenticator.clone())
+                .route("/me", web::get().to(me))
+                .route("/maybe", web::get().to(maybe)),
+        )
+        .await;
+
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(test::read_body(response).await, "+23400123456789");
+
+        let anonymous = test::TestRequest::get().uri("/me").to_request();
+        let response = test::call_service(&app, anonymous).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers().get(header::CONTENT_TYPE).unwrap(), PROBLEM_CONTENT_TYPE);
+        let problem: Problem = serde_json::from_slice(&test::read_body(response).await).unwrap();
+        assert_eq!(problem.code(), Some(ErrorCode::Unauthorized));
+
+        let anonymous = test::TestRequest::get().uri("/maybe").to_request();
+        assert_eq!(test::call_and_read_body(&app, anonymous).await, "false");
+
+        let response = challenge_response_header(&fixture.jti, &fixture.user.private, "app.example.com").unwrap();
+        let request = fixture.request_with("/me", &response).to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::OK);
+        let replay = fixture.request_with("/me", &response).to_request();
+        assert_eq!(test::call_service(&app, replay).await.status(), StatusCode::CONFLICT);
+    }
+
+    #[actix_web::test]
+    async fn test_without_authenticator() {
+        let fixture = Fixture::new();
+        let app = test::init_service(App::new().route("/me", web::get().to(me))).await;
+        let response = test::call_service(&app, fixture.request("/me").to_request()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
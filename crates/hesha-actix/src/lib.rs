@@ -0,0 +1,123 @@
+//! Proxy-number authentication for Actix Web services.
+//!
+//! The Actix counterpart of `hesha-tower`: clients send their attestation
+//! and a signed challenge response with each request (see
+//! [`hesha_core::verification::request_auth`]), and handlers take the
+//! [`VerifiedProxyIdentity`] they prove through the [`ProxyIdentity`]
+//! extractor. The extractor authenticates with the [`RequestAuthenticator`]
+//! registered as app data:
+//!
+//! ```no_run
+//! use actix_web::{get, web, App, HttpServer};
+//! use hesha_actix::{ProxyIdentity, RequestAuthenticator};
+//! use hesha_core::VerificationPolicy;
+//!
+//! #[get("/me")]
+//! async fn me(ProxyIdentity(identity): ProxyIdentity) -> String {
+//!     identity.proxy_number.as_str().to_string()
+//! }
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let policy = VerificationPolicy {
+//!     allowed_issuers: vec!["issuer.example.com".to_string()],
+//!     ..Default::default()
+//! };
+//! let auth = web::Data::new(RequestAuthenticator::new("app.example.com").with_policy(policy));
+//! HttpServer::new(move || App::new().app_data(auth.clone()).service(me))
+//!     .bind(("127.0.0.1", 8080))?
+//!     .run()
+//!     .await
+//! # }
+//! ```
+//!
+//! To guard a whole scope instead, wrap it in [`HeshaAuth`]. Actix guards
+//! can't run asynchronous checks, so [`has_credentials`] only routes on
+//! whether the Hesha headers were sent; the extractor or middleware still
+//! verifies them.
+//!
+//! Refused requests are answered with an RFC 7807 problem document.
+
+#![warn(missing_docs)]
+#![forbid(unsafe_code)]
+
+pub mod extract;
+pub mod middleware;
+
+pub use extract::{AuthRejection, ProxyIdentity};
+pub use hesha_core::verification::request_auth::{AuthError, RequestAuthenticator, VerifiedProxyIdentity};
+pub use middleware::{has_credentials, HeshaAuth};
+
+use actix_web::http::header::HeaderMap;
+use hesha_core::verification::request_auth::{ATTESTATION_HEADER, CHALLENGE_RESPONSE_HEADER};
+
+/// The attestation and challenge response headers of a request, if sent.
+pub(crate) fn credentials(headers: &HeaderMap) -> Result<(Option<&str>, Option<&str>), AuthError> {
+    Ok((header(headers, ATTESTATION_HEADER)?, header(headers, CHALLENGE_RESPONSE_HEADER)?))
+}
+
+/// The value of the header `name`, if sent.
+fn header<'a>(headers: &'a HeaderMap, name: &'static str) -> Result<Option<&'a str>, AuthError> {
+    headers
+        .get(name)
+        .map(|value| {
+            value.to_str().map_err(|_| AuthError::MalformedHeader {
+                header: name,
+                reason: "not visible ASCII".to_string(),
+            })
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use hesha_core::verification::{challenge_response_header, ATTESTATION_HEADER, CHALLENGE_RESPONSE_HEADER};
+    use hesha_core::{create_attestation, parse_attestation, RequestAuthenticator, TrustBundle};
+    use hesha_crypto::generate_keypair;
+    use hesha_types::{KeyPair, PhoneNumber, ProxyNumber};
+
+    /// A client holding an attestation, and an authenticator trusting its
+    /// issuer.
+    pub(crate) struct Fixture {
+        pub(crate) user: KeyPair,
+        pub(crate) jwt: String,
+        pub(crate) jti: String,
+        pub(crate) authenticator: web::Data<RequestAuthenticator>,
+    }
+
+    impl Fixture {
+        pub(crate) fn new() -> Self {
+            let issuer = generate_keypair().unwrap();
+            let user = generate_keypair().unwrap();
+            let jwt = create_attestation(
+                "issuer.example.com",
+                &issuer.private,
+                &PhoneNumber::new("+2348012345678").unwrap(),
+                &ProxyNumber::new("+23400123456789").unwrap(),
+                &user.public,
+            )
+            .unwrap();
+            let jti = parse_attestation(&jwt).unwrap().jti;
+            let mut bundle = TrustBundle::new();
+            bundle.add("issuer.example.com", issuer.public, None);
+            let authenticator = web::Data::new(RequestAuthenticator::new("app.example.com").with_trust_bundle(bundle));
+            Self { user, jwt, jti, authenticator }
+        }
+
+        /// A request to `uri` carrying the attestation and a fresh challenge
+        /// response.
+        pub(crate) fn request(&self, uri: &str) -> TestRequest {
+            let response = challenge_response_header(&self.jti, &self.user.private, "app.example.com").unwrap();
+            self.request_with(uri, &response)
+        }
+
+        /// A request to `uri` carrying the attestation and `response`.
+        pub(crate) fn request_with(&self, uri: &str, response: &str) -> TestRequest {
+            TestRequest::get()
+                .uri(uri)
+                .insert_header((ATTESTATION_HEADER, self.jwt.as_str()))
+                .insert_header((CHALLENGE_RESPONSE_HEADER, response))
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! The authenticating middleware and routing guard.
+
+use crate::extract::AuthRejection;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::guard::{self, Guard};
+use actix_web::{HttpMessage, ResponseError};
+use hesha_core::verification::request_auth::{RequestAuthenticator, ATTESTATION_HEADER};
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Authenticates every request before passing it on, adding the
+/// [`VerifiedProxyIdentity`](crate::VerifiedProxyIdentity) it proves to the
+/// request's extensions for [`ProxyIdentity`](crate::ProxyIdentity).
+///
+/// Requests that aren't authenticated are refused, unless the middleware is
+/// [`optional`](HeshaAuth::optional).
+#[derive(Clone)]
+pub struct HeshaAuth {
+    authenticator: Arc<RequestAuthenticator>,
+    optional: bool,
+}
+
+impl HeshaAuth {
+    /// Refuse requests `authenticator` doesn't authenticate.
+    pub fn new(authenticator: impl Into<Arc<RequestAuthenticator>>) -> Self {
+        Self {
+            authenticator: authenticator.into(),
+            optional: false,
+        }
+    }
+
+    /// Pass requests without Hesha headers on unauthenticated. Requests
+    /// whose headers don't verify are still refused.
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for HeshaAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = HeshaAuthMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(HeshaAuthMiddleware {
+            service: Rc::new(service),
+            authenticator: self.authenticator.clone(),
+            optional: self.optional,
+        }))
+    }
+}
+
+/// Service built by [`HeshaAuth`].
+pub struct HeshaAuthMiddleware<S> {
+    service: Rc<S>,
+    authenticator: Arc<RequestAuthenticator>,
+    optional: bool,
+}
+
+impl<S, B> Service<ServiceRequest> for HeshaAuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let authenticator = self.authenticator.clone();
+        let optional = self.optional;
+
+        Box::pin(async move {
+            let authenticated = match crate::credentials(request.headers()) {
+                Ok((None, None)) if optional => None,
+                Ok((attestation, response)) => Some(authenticator.authenticate(attestation, response).await),
+                Err(e) => Some(Err(e)),
+            };
+            match authenticated {
+                Some(Ok(identity)) => {
+                    request.extensions_mut().insert(identity);
+                }
+                Some(Err(e)) => {
+                    tracing::debug!("Refused request to {}: {}", request.path(), e);
+                    let response = AuthRejection::from(e).error_response();
+                    return Ok(request.into_response(response).map_into_right_body());
+                }
+                None => {}
+            }
+            service.call(request).await.map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
+
+/// Matches requests that sent an attestation, to route them apart from
+/// anonymous ones. It doesn't verify anything.
+pub fn has_credentials() -> impl Guard {
+    guard::fn_guard(|ctx| ctx.head().headers().contains_key(ATTESTATION_HEADER))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Fixture;
+    use crate::ProxyIdentity;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, web, App};
+
+    async fn me(ProxyIdentity(identity): ProxyIdentity) -> String {
+        identity.attestation_id
+    }
+
+    #[actix_web::test]
+    async fn test_middleware() {
+        let fixture = Fixture::new();
+        let app = test::init_service(
+            App::new()
+                .service(
+                    web::scope("/private")
+                        .wrap(HeshaAuth::new(fixture.authenticator.clone().into_inner()))
+                        .route("/me", web::get().to(me)),
+                )
+                .service(
+                    web::scope("/open")
+                        .wrap(HeshaAuth::new(fixture.authenticator.clone().into_inner()).optional())
+                        .route("/me", web::get().guard(has_credentials()).to(me))
+                        .route("/me", web::get().to(|| async { "anonymous" })),
+                ),
+        )
+        .await;
+
+        let request = fixture.request("/private/me").to_request();
+        assert_eq!(test::call_and_read_body(&app, request).await, fixture.jti);
+        let anonymous = test::TestRequest::get().uri("/private/me").to_request();
+        assert_eq!(test::call_service(&app, anonymous).await.status(), StatusCode::UNAUTHORIZED);
+
+        let request = fixture.request("/open/me").to_request();
+        assert_eq!(test::call_and_read_body(&app, request).await, fixture.jti);
+        let anonymous = test::TestRequest::get().uri("/open/me").to_request();
+        assert_eq!(test::call_and_read_body(&app, anonymous).await, "anonymous");
+        let forged = test::TestRequest::get()
+            .uri("/open/me")
+            .insert_header((ATTESTATION_HEADER, "not.a.jwt"))
+            .to_request();
+        assert_eq!(test::call_service(&app, forged).await.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,131 @@
+/*
+ * hesha.h - C interface to the Hesha Protocol.
+ *
+ * Parse and verify attestations and check challenge responses from C and
+ * C++. Link against libhesha.
+ *
+ * Conventions:
+ * - Functions return a HeshaStatus; HESHA_OK is zero. On failure,
+ *   hesha_last_error() describes the error on the calling thread.
+ * - Results are written through out-pointers, and only on success.
+ * - Objects are opaque handles released with their _free function; strings
+ *   returned to the caller are released with hesha_string_free().
+ * - Strings passed in are NUL-terminated UTF-8; timestamps are Unix seconds.
+ *
+ * Functions are safe to call from any thread. A handle may be read from
+ * several threads at once, but must not be freed while in use.
+ */
+
+#ifndef HESHA_H
+#define HESHA_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Outcome of a call. */
+typedef enum HeshaStatus {
+    /* Success. */
+    HESHA_OK = 0,
+    /* A required pointer argument was null. */
+    HESHA_ERR_NULL_ARGUMENT = 1,
+    /* A string argument wasn't valid UTF-8. */
+    HESHA_ERR_INVALID_UTF8 = 2,
+    /* An argument was malformed: a key, proxy number or JSON document. */
+    HESHA_ERR_INVALID_ARGUMENT = 3,
+    /* The attestation is malformed or breaks the verification policy. */
+    HESHA_ERR_INVALID_ATTESTATION = 4,
+    /* A signature didn't verify. */
+    HESHA_ERR_INVALID_SIGNATURE = 5,
+    /* The attestation has expired. */
+    HESHA_ERR_EXPIRED = 6,
+    /* The challenge is stale, dated in the future, or not for this
+     * attestation. */
+    HESHA_ERR_INVALID_CHALLENGE = 7,
+    /* Anything else, including internal failures. */
+    HESHA_ERR_INTERNAL = 8
+} HeshaStatus;
+
+/* A parsed (and possibly verified) attestation. */
+typedef struct HeshaAttestation HeshaAttestation;
+
+/* A set of pinned issuer keys. */
+typedef struct HeshaTrustBundle HeshaTrustBundle;
+
+/* ---- Library ---------------------------------------------------------- */
+
+/* Version of the library, e.g. "0.1.0". Static; don't free it. */
+const char *hesha_version(void);
+
+/* Message describing the last failed call on this thread, or NULL if the
+ * last call succeeded. Valid until the next call on this thread; don't
+ * free it. */
+const char *hesha_last_error(void);
+
+/* Release a string returned by the library. NULL is ignored. */
+void hesha_string_free(char *string);
+
+/* ---- Attestations ----------------------------------------------------- */
+
+/* Parse an attestation JWT WITHOUT verifying it, e.g. to show its claims.
+ * Verify it before trusting it. */
+HeshaStatus hesha_attestation_parse(const char *jwt, HeshaAttestation **out);
+
+/* Verify an attestation JWT against an issuer public key (base64url),
+ * under the default policy. */
+HeshaStatus hesha_verify_with_key(const char *jwt, const char *issuer_key, HeshaAttestation **out);
+
+/* Verify an attestation JWT against a trust bundle, under the default
+ * policy. */
+HeshaStatus hesha_verify_with_bundle(const char *jwt, const HeshaTrustBundle *bundle, HeshaAttestation **out);
+
+/* Release an attestation. NULL is ignored. */
+void hesha_attestation_free(HeshaAttestation *attestation);
+
+/* Claims. Strings are valid while the attestation lives; don't free them.
+ * A NULL handle gives NULL (or 0 for timestamps). */
+const char *hesha_attestation_jti(const HeshaAttestation *attestation);
+const char *hesha_attestation_issuer(const HeshaAttestation *attestation);
+const char *hesha_attestation_trust_domain(const HeshaAttestation *attestation);
+const char *hesha_attestation_proxy_number(const HeshaAttestation *attestation);
+const char *hesha_attestation_user_pubkey(const HeshaAttestation *attestation);
+int64_t hesha_attestation_issued_at(const HeshaAttestation *attestation);
+int64_t hesha_attestation_expires_at(const HeshaAttestation *attestation);
+
+/* ---- Trust bundles ---------------------------------------------------- */
+
+/* Load a trust bundle from its JSON form:
+ * {"issuers": [{"domain": "example.com", "public_key": "base64..."}]} */
+HeshaStatus hesha_trust_bundle_from_json(const char *json, HeshaTrustBundle **out);
+
+/* Release a trust bundle. NULL is ignored. */
+void hesha_trust_bundle_free(HeshaTrustBundle *bundle);
+
+/* ---- Challenge-response ----------------------------------------------- */
+
+/* Answer a challenge (JSON) with the attestation attestation_id, signing it
+ * with the attestation's user private key (base64url). On success,
+ * *out_response_json receives the challenge response as JSON; release it
+ * with hesha_string_free(). */
+HeshaStatus hesha_challenge_sign(
+    const char *challenge_json,
+    const char *attestation_id,
+    const char *private_key,
+    char **out_response_json);
+
+/* Check a challenge response (JSON) against the attestation it was made
+ * with, which must already be verified. The challenge may be at most
+ * max_age_secs old, or five minutes if max_age_secs is zero or negative.
+ * Whether the nonce was answered before is the caller's to track. */
+HeshaStatus hesha_challenge_verify(
+    const char *response_json,
+    const HeshaAttestation *attestation,
+    int64_t max_age_secs);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* HESHA_H */
@@ -0,0 +1,325 @@
+//! Attestation parsing and verification.
+
+use crate::{check_out, read_str, run, Failure, HeshaStatus};
+use hesha_core::TrustBundle;
+use hesha_types::{Attestation, PublicKey};
+use std::ffi::{c_char, CString};
+
+/// A parsed (and possibly verified) attestation.
+pub struct HeshaAttestation {
+    pub(crate) attestation: Attestation,
+    jti: CString,
+    issuer: CString,
+    trust_domain: CString,
+    proxy_number: CString,
+    user_pubkey: CString,
+}
+
+impl HeshaAttestation {
+    fn new(attestation: Attestation, issuer: String) -> Result<Self, Failure> {
+        let text = |value: &str| {
+            CString::new(value).map_err(|_| Failure::new(HeshaStatus::InvalidAttestation, "Claim contains NUL"))
+        };
+        Ok(Self {
+            jti: text(&attestation.jti)?,
+            issuer: text(&issuer)?,
+            trust_domain: text(attestation.effective_trust_domain())?,
+            proxy_number: text(attestation.proxy_number.as_str())?,
+            user_pubkey: text(&attestation.user_pubkey.to_base64())?,
+            attestation,
+        })
+    }
+
+    fn boxed(self) -> *mut HeshaAttestation {
+        Box::into_raw(Box::new(self))
+    }
+}
+
+/// A set of pinned issuer keys.
+pub struct HeshaTrustBundle {
+    bundle: TrustBundle,
+}
+
+/// Parse an attestation JWT **without** verifying it, e.g. to show its
+/// claims. Use `hesha_verify_with_key` or `hesha_verify_with_bundle` before
+/// trusting it.
+///
+/// # Safety
+/// `jwt` must be a NUL-terminated string and `out` a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_attestation_parse(jwt: *const c_char, out: *mut *mut HeshaAttestation) -> HeshaStatus {
+    run(|| {
+        // SAFETY: per the function's contract
+        let jwt = unsafe { read_str(jwt, "jwt") }?;
+        check_out(out, "out")?;
+        let attestation = hesha_core::parse_attestation(jwt)?;
+        let issuer = attestation.iss.clone();
+        // SAFETY: checked non-null; writable per the function's contract
+        unsafe { *out = HeshaAttestation::new(attestation, issuer)?.boxed() };
+        Ok(())
+    })
+}
+
+/// Verify an attestation JWT against an issuer public key (base64url),
+/// under the default policy.
+///
+/// # Safety
+/// `jwt` and `issuer_key` must be NUL-terminated strings and `out` a
+/// writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_verify_with_key(
+    jwt: *const c_char,
+    issuer_key: *const c_char,
+    out: *mut *mut HeshaAttestation,
+) -> HeshaStatus {
+    run(|| {
+        // SAFETY: per the function's contract
+        let (jwt, issuer_key) = unsafe { (read_str(jwt, "jwt")?, read_str(issuer_key, "issuer_key")?) };
+        check_out(out, "out")?;
+        let issuer_key = PublicKey::from_base64(issuer_key)?;
+        let verified = hesha_core::verify_attestation_with_key(jwt, &issuer_key)?;
+        // SAFETY: checked non-null; writable per the function's contract
+        unsafe { *out = HeshaAttestation::new(verified.attestation, verified.issuer)?.boxed() };
+        Ok(())
+    })
+}
+
+/// Verify an attestation JWT against a trust bundle, under the default
+/// policy.
+///
+/// # Safety
+/// `jwt` must be a NUL-terminated string, `bundle` a live trust bundle and
+/// `out` a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_verify_with_bundle(
+    jwt: *const c_char,
+    bundle: *const HeshaTrustBundle,
+    out: *mut *mut HeshaAttestation,
+) -> HeshaStatus {
+    run(|| {
+        // SAFETY: per the function's contract
+        let jwt = unsafe { read_str(jwt, "jwt") }?;
+        // SAFETY: null or a live bundle per the function's contract
+        let bundle = unsafe { bundle.as_ref() }
+            .ok_or_else(|| Failure::new(HeshaStatus::NullArgument, "bundle is null"))?;
+        check_out(out, "out")?;
+        let verified = hesha_core::verify_attestation_with_bundle(jwt, &bundle.bundle)?;
+        // SAFETY: checked non-null; writable per the function's contract
+        unsafe { *out = HeshaAttestation::new(verified.attestation, verified.issuer)?.boxed() };
+        Ok(())
+    })
+}
+
+/// Release an attestation. Null is ignored.
+///
+/// # Safety
+/// `attestation` must be null or a live attestation from this library.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_attestation_free(attestation: *mut HeshaAttestation) {
+    if !attestation.is_null() {
+        // SAFETY: allocated by `Box::into_raw` per the function's contract
+        drop(unsafe { Box::from_raw(attestation) });
+    }
+}
+
+/// Read a string claim; null for a null handle.
+///
+/// # Safety
+/// `attestation` must be null or live.
+unsafe fn claim(attestation: *const HeshaAttestation, field: fn(&HeshaAttestation) -> &CString) -> *const c_char {
+    // SAFETY: per the caller's contract
+    unsafe { attestation.as_ref() }.map_or(std::ptr::null(), |attestation| field(attestation).as_ptr())
+}
+
+/// JWT ID. Valid while the attestation lives; don't free it.
+///
+/// # Safety
+/// `attestation` must be null or live.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_attestation_jti(attestation: *const HeshaAttestation) -> *const c_char {
+    // SAFETY: per the function's contract
+    unsafe { claim(attestation, |a| &a.jti) }
+}
+
+/// Issuer domain. Valid while the attestation lives; don't free it.
+///
+/// # Safety
+/// `attestation` must be null or live.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_attestation_issuer(attestation: *const HeshaAttestation) -> *const c_char {
+    // SAFETY: per the function's contract
+    unsafe { claim(attestation, |a| &a.issuer) }
+}
+
+/// Trust domain the issuer's keys are published under. Valid while the
+/// attestation lives; don't free it.
+///
+/// # Safety
+/// `attestation` must be null or live.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_attestation_trust_domain(attestation: *const HeshaAttestation) -> *const c_char {
+    // SAFETY: per the function's contract
+    unsafe { claim(attestation, |a| &a.trust_domain) }
+}
+
+/// Proxy number attested. Valid while the attestation lives; don't free
+/// it.
+///
+/// # Safety
+/// `attestation` must be null or live.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_attestation_proxy_number(attestation: *const HeshaAttestation) -> *const c_char {
+    // SAFETY: per the function's contract
+    unsafe { claim(attestation, |a| &a.proxy_number) }
+}
+
+/// User public key (base64url). Valid while the attestation lives; don't
+/// free it.
+///
+/// # Safety
+/// `attestation` must be null or live.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_attestation_user_pubkey(attestation: *const HeshaAttestation) -> *const c_char {
+    // SAFETY: per the function's contract
+    unsafe { claim(attestation, |a| &a.user_pubkey) }
+}
+
+/// When the attestation was issued (Unix seconds); 0 for a null handle.
+///
+/// # Safety
+/// `attestation` must be null or live.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_attestation_issued_at(attestation: *const HeshaAttestation) -> i64 {
+    // SAFETY: per the function's contract
+    unsafe { attestation.as_ref() }.map_or(0, |a| a.attestation.iat.timestamp())
+}
+
+/// When the attestation expires (Unix seconds); 0 for a null handle.
+///
+/// # Safety
+/// `attestation` must be null or live.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_attestation_expires_at(attestation: *const HeshaAttestation) -> i64 {
+    // SAFETY: per the function's contract
+    unsafe { attestation.as_ref() }.map_or(0, |a| a.attestation.exp.timestamp())
+}
+
+/// Load a trust bundle from its JSON form.
+///
+/// # Safety
+/// `json` must be a NUL-terminated string and `out` a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_trust_bundle_from_json(
+    json: *const c_char,
+    out: *mut *mut HeshaTrustBundle,
+) -> HeshaStatus {
+    run(|| {
+        // SAFETY: per the function's contract
+        let json = unsafe { read_str(json, "json") }?;
+        check_out(out, "out")?;
+        let bundle = TrustBundle::from_json(json)?;
+        // SAFETY: checked non-null; writable per the function's contract
+        unsafe { *out = Box::into_raw(Box::new(HeshaTrustBundle { bundle })) };
+        Ok(())
+    })
+}
+
+/// Release a trust bundle. Null is ignored.
+///
+/// # Safety
+/// `bundle` must be null or a live trust bundle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_trust_bundle_free(bundle: *mut HeshaTrustBundle) {
+    if !bundle.is_null() {
+        // SAFETY: allocated by `Box::into_raw` per the function's contract
+        drop(unsafe { Box::from_raw(bundle) });
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::tests::{c, last_error};
+    use hesha_core::AttestationBuilder;
+    use hesha_types::{KeyPair, PhoneNumber, ProxyNumber};
+    use std::ffi::CStr;
+    use std::ptr;
+
+    pub(crate) fn attestation_jwt(issuer: &KeyPair, user: &KeyPair) -> String {
+        AttestationBuilder::new(
+            "issuer.example.com".to_string(),
+            &issuer.private,
+            PhoneNumber::new("+2348012345678").unwrap(),
+            ProxyNumber::new("+23400123456789").unwrap(),
+            user.public.clone(),
+        )
+        .trust_domain("example.com".to_string())
+        .build_jwt()
+        .unwrap()
+    }
+
+    fn text(ptr: *const c_char) -> String {
+        // SAFETY: test pointers come from live handles
+        unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_verify_with_key() {
+        let issuer = hesha_crypto::generate_keypair().unwrap();
+        let user = hesha_crypto::generate_keypair().unwrap();
+        let jwt = c(&attestation_jwt(&issuer, &user));
+        let mut attestation = ptr::null_mut();
+
+        unsafe {
+            let status = hesha_verify_with_key(jwt.as_ptr(), c(&issuer.public.to_base64()).as_ptr(), &mut attestation);
+            assert_eq!(status, HeshaStatus::Ok);
+            assert_eq!(text(hesha_attestation_issuer(attestation)), "issuer.example.com");
+            assert_eq!(text(hesha_attestation_trust_domain(attestation)), "example.com");
+            assert_eq!(text(hesha_attestation_proxy_number(attestation)), "+23400123456789");
+            assert_eq!(text(hesha_attestation_user_pubkey(attestation)), user.public.to_base64());
+            assert!(hesha_attestation_expires_at(attestation) > hesha_attestation_issued_at(attestation));
+            hesha_attestation_free(attestation);
+
+            let mut rejected = ptr::null_mut();
+            let status = hesha_verify_with_key(jwt.as_ptr(), c(&user.public.to_base64()).as_ptr(), &mut rejected);
+            assert_eq!(status, HeshaStatus::InvalidSignature);
+            assert!(rejected.is_null());
+            assert!(!last_error().is_empty());
+
+            assert_eq!(hesha_verify_with_key(ptr::null(), jwt.as_ptr(), &mut rejected), HeshaStatus::NullArgument);
+            assert_eq!(last_error(), "jwt is null");
+        }
+    }
+
+    #[test]
+    fn test_verify_with_bundle() {
+        let issuer = hesha_crypto::generate_keypair().unwrap();
+        let user = hesha_crypto::generate_keypair().unwrap();
+        let jwt = c(&attestation_jwt(&issuer, &user));
+        let json = c(&format!(
+            r#"{{"issuers": [{{"domain": "example.com", "public_key": "{}"}}]}}"#,
+            issuer.public.to_base64()
+        ));
+        let mut bundle = ptr::null_mut();
+        let mut attestation = ptr::null_mut();
+
+        unsafe {
+            assert_eq!(hesha_trust_bundle_from_json(json.as_ptr(), &mut bundle), HeshaStatus::Ok);
+            assert_eq!(hesha_verify_with_bundle(jwt.as_ptr(), bundle, &mut attestation), HeshaStatus::Ok);
+            let mut parsed = ptr::null_mut();
+            assert_eq!(hesha_attestation_parse(jwt.as_ptr(), &mut parsed), HeshaStatus::Ok);
+            assert_eq!(text(hesha_attestation_jti(parsed)), text(hesha_attestation_jti(attestation)));
+
+            hesha_attestation_free(parsed);
+            hesha_attestation_free(attestation);
+            hesha_trust_bundle_free(bundle);
+
+            assert_eq!(
+                hesha_trust_bundle_from_json(c("not json").as_ptr(), &mut bundle),
+                HeshaStatus::InvalidArgument
+            );
+            assert_eq!(hesha_attestation_parse(c("a.b.c").as_ptr(), &mut parsed), HeshaStatus::InvalidAttestation);
+            assert!(hesha_attestation_jti(ptr::null()).is_null());
+        }
+    }
+}
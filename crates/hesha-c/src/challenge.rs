@@ -0,0 +1,121 @@
+//! Challenge-response signing and verification.
+//!
+//! Challenges and responses are passed as the JSON services exchange with
+//! wallets.
+
+use crate::attestation::HeshaAttestation;
+use crate::{check_out, into_c_string, read_str, run, Failure, HeshaStatus};
+use hesha_core::verification::CHALLENGE_MAX_AGE_SECS;
+use hesha_types::{Challenge, ChallengeResponse, HeshaError, PrivateKey};
+use std::ffi::c_char;
+
+/// Answer a challenge (JSON) with the attestation `attestation_id`, signing
+/// it with the attestation's user private key (base64url). On success,
+/// `out_response_json` receives the challenge response as JSON, to be
+/// released with `hesha_string_free`.
+///
+/// # Safety
+/// The strings must be NUL-terminated and `out_response_json` a writable
+/// pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_challenge_sign(
+    challenge_json: *const c_char,
+    attestation_id: *const c_char,
+    private_key: *const c_char,
+    out_response_json: *mut *mut c_char,
+) -> HeshaStatus {
+    run(|| {
+        // SAFETY: per the function's contract
+        let (challenge_json, attestation_id, private_key) = unsafe {
+            (
+                read_str(challenge_json, "challenge_json")?,
+                read_str(attestation_id, "attestation_id")?,
+                read_str(private_key, "private_key")?,
+            )
+        };
+        check_out(out_response_json, "out_response_json")?;
+        let challenge: Challenge = serde_json::from_str(challenge_json).map_err(HeshaError::from)?;
+        let private_key = PrivateKey::from_base64(private_key)?;
+        let response = hesha_core::sign_challenge(&challenge, attestation_id, &private_key)?;
+        let json = serde_json::to_string(&response).map_err(HeshaError::from)?;
+        // SAFETY: checked non-null; writable per the function's contract
+        unsafe { *out_response_json = into_c_string(json)? };
+        Ok(())
+    })
+}
+
+/// Check a challenge response (JSON) against the attestation it was made
+/// with, which the caller must already have verified. The challenge may be
+/// at most `max_age_secs` old, or five minutes if `max_age_secs` is zero or
+/// negative.
+///
+/// Whether the challenge's nonce was answered before is the caller's to
+/// track.
+///
+/// # Safety
+/// `response_json` must be a NUL-terminated string and `attestation` a live
+/// attestation.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_challenge_verify(
+    response_json: *const c_char,
+    attestation: *const HeshaAttestation,
+    max_age_secs: i64,
+) -> HeshaStatus {
+    run(|| {
+        // SAFETY: per the function's contract
+        let response_json = unsafe { read_str(response_json, "response_json") }?;
+        // SAFETY: null or live per the function's contract
+        let attestation = unsafe { attestation.as_ref() }
+            .ok_or_else(|| Failure::new(HeshaStatus::NullArgument, "attestation is null"))?;
+        let response: ChallengeResponse = serde_json::from_str(response_json).map_err(HeshaError::from)?;
+        let max_age = if max_age_secs > 0 { max_age_secs } else { CHALLENGE_MAX_AGE_SECS };
+        hesha_core::verify_challenge_response(&response, &attestation.attestation, chrono::Duration::seconds(max_age))?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::tests::attestation_jwt;
+    use crate::attestation::{hesha_attestation_free, hesha_attestation_jti, hesha_attestation_parse};
+    use crate::hesha_string_free;
+    use crate::tests::c;
+    use std::ffi::CStr;
+    use std::ptr;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let issuer = hesha_crypto::generate_keypair().unwrap();
+        let user = hesha_crypto::generate_keypair().unwrap();
+        let jwt = c(&attestation_jwt(&issuer, &user));
+        let challenge = c(&format!(
+            r#"{{"nonce": "0123456789abcdef", "service_context": "app.example.com", "timestamp": "{}"}}"#,
+            chrono::Utc::now().to_rfc3339()
+        ));
+        let mut attestation = ptr::null_mut();
+        let mut response = ptr::null_mut();
+
+        unsafe {
+            assert_eq!(hesha_attestation_parse(jwt.as_ptr(), &mut attestation), HeshaStatus::Ok);
+            let status = hesha_challenge_sign(
+                challenge.as_ptr(),
+                hesha_attestation_jti(attestation),
+                c(&user.private.to_base64()).as_ptr(),
+                &mut response,
+            );
+            assert_eq!(status, HeshaStatus::Ok);
+            assert_eq!(hesha_challenge_verify(response, attestation, 0), HeshaStatus::Ok);
+
+            let forged = CStr::from_ptr(response).to_str().unwrap().replace("app.example.com", "evil.example.com");
+            assert_eq!(
+                hesha_challenge_verify(c(&forged).as_ptr(), attestation, 0),
+                HeshaStatus::InvalidSignature
+            );
+            assert_eq!(hesha_challenge_verify(response, ptr::null(), 0), HeshaStatus::NullArgument);
+
+            hesha_string_free(response);
+            hesha_attestation_free(attestation);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! C ABI for the Hesha Protocol.
+//!
+//! Lets C and C++ stacks, such as telephony servers, parse and verify
+//! attestations and check challenge responses. The declarations are in
+//! `include/hesha.h`; link against `libhesha`.
+//!
+//! # Conventions
+//!
+//! - Functions return a [`HeshaStatus`]; `HESHA_OK` is zero. On failure,
+//!   [`hesha_last_error`] describes the error on the calling thread
+//! - Results are written through out-pointers, and only on success
+//! - Objects are opaque handles released with their `_free` function;
+//!   strings returned to the caller are released with [`hesha_string_free`]
+//! - Strings passed in are NUL-terminated UTF-8, and timestamps are Unix
+//!   seconds
+//! - Panics never cross the boundary; they are reported as
+//!   `HESHA_ERR_INTERNAL`
+
+#![warn(missing_docs)]
+
+pub mod attestation;
+pub mod challenge;
+
+pub use attestation::{
+    hesha_attestation_expires_at, hesha_attestation_free, hesha_attestation_issued_at,
+    hesha_attestation_issuer, hesha_attestation_jti, hesha_attestation_parse,
+    hesha_attestation_proxy_number, hesha_attestation_trust_domain,
+    hesha_attestation_user_pubkey, hesha_trust_bundle_free, hesha_trust_bundle_from_json,
+    hesha_verify_with_bundle, hesha_verify_with_key, HeshaAttestation, HeshaTrustBundle,
+};
+pub use challenge::{hesha_challenge_sign, hesha_challenge_verify};
+
+use hesha_types::HeshaError;
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, UnwindSafe};
+
+/// Outcome of a call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeshaStatus {
+    /// Success.
+    Ok = 0,
+    /// A required pointer argument was null.
+    NullArgument = 1,
+    /// A string argument wasn't valid UTF-8.
+    InvalidUtf8 = 2,
+    /// An argument was malformed: a key, proxy number or JSON document.
+    InvalidArgument = 3,
+    /// The attestation is malformed or breaks the verification policy.
+    InvalidAttestation = 4,
+    /// A signature didn't verify.
+    InvalidSignature = 5,
+    /// The attestation has expired.
+    Expired = 6,
+    /// The challenge is stale, dated in the future, or not for this
+    /// attestation.
+    InvalidChallenge = 7,
+    /// Anything else, including internal failures.
+    Internal = 8,
+}
+
+impl From<&HeshaError> for HeshaStatus {
+    fn from(error: &HeshaError) -> Self {
+        match error {
+            HeshaError::InvalidPhoneNumber(_)
+            | HeshaError::InvalidProxyNumber(_)
+            | HeshaError::InvalidPublicKey(_)
+            | HeshaError::InvalidPrivateKey(_)
+            | HeshaError::SerializationError(_)
+            | HeshaError::ConfigError(_) => HeshaStatus::InvalidArgument,
+            HeshaError::InvalidAttestation(_) | HeshaError::InvalidBindingProof => HeshaStatus::InvalidAttestation,
+            HeshaError::InvalidSignature => HeshaStatus::InvalidSignature,
+            HeshaError::AttestationExpired(_) => HeshaStatus::Expired,
+            HeshaError::InvalidNonce => HeshaStatus::InvalidChallenge,
+            HeshaError::CryptoError(_) | HeshaError::NetworkError(_) | HeshaError::InvalidAuditLog { .. } => {
+                HeshaStatus::Internal
+            }
+        }
+    }
+}
+
+/// A failed call: its status and message.
+pub(crate) struct Failure {
+    status: HeshaStatus,
+    message: String,
+}
+
+impl Failure {
+    pub(crate) fn new(status: HeshaStatus, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+}
+
+impl From<HeshaError> for Failure {
+    fn from(error: HeshaError) -> Self {
+        Failure::new(HeshaStatus::from(&error), error.to_string())
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Run an exported function's body, recording its failure (or panic) for
+/// [`hesha_last_error`].
+pub(crate) fn run(body: impl FnOnce() -> Result<(), Failure> + UnwindSafe) -> HeshaStatus {
+    let failure = match catch_unwind(body) {
+        Ok(Ok(())) => {
+            set_last_error(None);
+            return HeshaStatus::Ok;
+        }
+        Ok(Err(failure)) => failure,
+        Err(_) => Failure::new(HeshaStatus::Internal, "Internal error (panic)"),
+    };
+    set_last_error(Some(failure.message));
+    failure.status
+}
+
+fn set_last_error(message: Option<String>) {
+    let message = message.map(|message| {
+        CString::new(message.replace('\0', " ")).unwrap_or_default()
+    });
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+/// Read a string argument.
+///
+/// # Safety
+/// `ptr` must be null or point to a NUL-terminated string that outlives
+/// `'a`.
+pub(crate) unsafe fn read_str<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, Failure> {
+    if ptr.is_null() {
+        return Err(Failure::new(HeshaStatus::NullArgument, format!("{} is null", name)));
+    }
+    // SAFETY: non-null, and NUL-terminated per the caller's contract
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map_err(|_| Failure::new(HeshaStatus::InvalidUtf8, format!("{} is not valid UTF-8", name)))
+}
+
+/// Check an out-pointer is usable.
+pub(crate) fn check_out<T>(out: *mut T, name: &str) -> Result<(), Failure> {
+    if out.is_null() {
+        return Err(Failure::new(HeshaStatus::NullArgument, format!("{} is null", name)));
+    }
+    Ok(())
+}
+
+/// Hand a string to the caller, who releases it with [`hesha_string_free`].
+pub(crate) fn into_c_string(value: String) -> Result<*mut c_char, Failure> {
+    CString::new(value)
+        .map(CString::into_raw)
+        .map_err(|_| Failure::new(HeshaStatus::Internal, "String contains NUL"))
+}
+
+/// Message describing the last failed call on this thread, or null if the
+/// last call succeeded.
+///
+/// The string stays valid until the next call on this thread; don't free
+/// it.
+#[no_mangle]
+pub extern "C" fn hesha_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(std::ptr::null(), |message| message.as_ptr()))
+}
+
+/// Release a string returned by the library. Null is ignored.
+///
+/// # Safety
+/// `string` must be null or a string returned by this library, not
+/// already freed.
+#[no_mangle]
+pub unsafe extern "C" fn hesha_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        // SAFETY: allocated by `CString::into_raw` per the caller's contract
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+/// Version of the library, e.g. `"0.1.0"`. Static; don't free it.
+#[no_mangle]
+pub extern "C" fn hesha_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn c(value: &str) -> CString {
+        CString::new(value).unwrap()
+    }
+
+    pub(crate) fn last_error() -> String {
+        // SAFETY: valid until the next call on this thread
+        unsafe { CStr::from_ptr(hesha_last_error()) }.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_header_declares_exports() {
+        let header = include_str!("../include/hesha.h");
+        let sources = [include_str!("lib.rs"), include_str!("attestation.rs"), include_str!("challenge.rs")];
+        let exports: Vec<&str> = sources
+            .iter()
+            .flat_map(|source| source.lines())
+            .filter_map(|line| line.split("extern \"C\" fn ").nth(1))
+            .filter_map(|rest| rest.split('(').next())
+            .collect();
+
+        assert!(exports.len() > 10);
+        for export in exports {
+            assert!(header.contains(&format!("{}(", export)), "{} is missing from hesha.h", export);
+        }
+    }
+
+    #[test]
+    fn test_version() {
+        // SAFETY: static NUL-terminated string
+        let version = unsafe { CStr::from_ptr(hesha_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+    }
+}
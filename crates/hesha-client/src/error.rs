@@ -1,5 +1,6 @@
 //! Client error types.
 
+use hesha_types::Problem;
 use thiserror::Error;
 
 /// Errors that can occur in the HTTP client.
@@ -18,10 +19,18 @@ pub enum ClientError {
         message: String,
     },
     
+    /// Server refused the request with a problem document.
+    #[error("Server error {}: {}", .0.status, .0)]
+    Problem(Box<Problem>),
+    
     /// Invalid response format.
     #[error("Invalid response: {0}")]
     InvalidResponse(String),
     
+    /// Response wasn't signed by the issuer.
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+    
     /// Serialization/deserialization error.
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
@@ -35,5 +44,50 @@ pub enum ClientError {
     Timeout,
 }
 
+impl ClientError {
+    /// Whether the request may succeed if retried.
+    /// 
+    /// Connection problems, timeouts, rate limiting and server-side (5xx)
+    /// failures are transient; rejected requests and bad responses are not.
+    /// Problem documents are judged by their code when it is a known one.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ClientError::HttpError(e) => e.is_connect() || e.is_timeout() || e.is_request(),
+            ClientError::ServerError { status, .. } => *status == 429 || *status >= 500,
+            ClientError::Problem(problem) => match problem.code() {
+                Some(code) => code.is_transient(),
+                None => problem.status == 429 || problem.status >= 500,
+            },
+            ClientError::Timeout => true,
+            _ => false,
+        }
+    }
+    
+    /// The problem document the server refused the request with, if any.
+    pub fn problem(&self) -> Option<&Problem> {
+        match self {
+            ClientError::Problem(problem) => Some(problem),
+            _ => None,
+        }
+    }
+    
+    /// The error read from a response with a non-success status: its
+    /// problem document, or the body as text for servers that don't send
+    /// one.
+    pub(crate) async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let message = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        match serde_json::from_str::<Problem>(&message) {
+            Ok(mut problem) if !problem.error.is_empty() => {
+                if problem.status == 0 {
+                    problem.status = status;
+                }
+                ClientError::Problem(Box::new(problem))
+            }
+            _ => ClientError::ServerError { status, message },
+        }
+    }
+}
+
 /// Result type for client operations.
 pub type ClientResult<T> = Result<T, ClientError>;
\ No newline at end of file
@@ -1,11 +1,23 @@
 //! Client for interacting with issuer nodes.
 
 use crate::error::{ClientError, ClientResult};
-use hesha_types::{PhoneNumber, PublicKey};
-use reqwest::{Client, Url};
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use hesha_crypto::{
+    MessageSignature, RequestSignature, CONTENT_DIGEST_HEADER, REQUEST_SIGNATURE_HEADER, SIGNATURE_HEADER,
+    SIGNATURE_INPUT_HEADER,
+};
+use hesha_types::{
+    AuditEntry, IssuanceReceipt, IssuanceStats, IssuerInfo, NodeMetrics, PhoneNumber, PublicKey, RestoreSummary, RevocationList,
+    RevocationStatus, StatusResponse, VerificationMethod,
+};
+use reqwest::{header, Client, Method, RequestBuilder, StatusCode, Url};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+/// Header carrying the idempotency key of an attestation request.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
 /// Request for attestation.
 #[derive(Debug, Serialize)]
 pub struct AttestationRequest {
@@ -20,6 +32,10 @@ pub struct AttestationRequest {
     /// Optional validity period in days (defaults to issuer config).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub validity_days: Option<i64>,
+    /// Fresh random value so a captured request can't be replayed.
+    pub nonce: String,
+    /// When the request was made (Unix seconds).
+    pub timestamp: i64,
 }
 
 /// Response containing attestation.
@@ -29,6 +45,146 @@ pub struct AttestationResponse {
     pub attestation: String,
     /// The proxy number assigned.
     pub proxy_number: String,
+    /// Issuer's signed receipt, from issuers that return one.
+    #[serde(default)]
+    pub receipt: Option<IssuanceReceipt>,
+}
+
+/// What an attestation request would produce (`POST /attest/preview`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct AttestationPreview {
+    /// An example proxy number; the one issued will differ.
+    pub proxy_number: String,
+    /// Shape of the proxy number, with `X` for the generated digits.
+    pub format: String,
+    /// Validity the attestation would get, in days.
+    pub validity_days: i64,
+    /// When it would expire if issued now (Unix seconds).
+    pub expires_at: i64,
+}
+
+/// Request to start phone verification (`POST /verify/start`).
+#[derive(Debug, Serialize)]
+pub struct VerificationStartRequest {
+    /// Phone number to send the code to.
+    pub phone_number: String,
+    /// Solved CAPTCHA, for issuers that require one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub captcha_token: Option<String>,
+}
+
+/// A pending phone verification.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VerificationSession {
+    /// Session identifier for the check and attest calls.
+    pub session_id: String,
+    /// Seconds until the code expires, if the issuer reports it.
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+    /// Where the code was sent, if the issuer reports it: by SMS, or by
+    /// email to a contact registered for the number.
+    #[serde(default)]
+    pub channel: Option<VerificationMethod>,
+    /// For carrier verification, a URL to open on the phone over mobile
+    /// data instead of entering a code.
+    #[serde(default)]
+    pub verification_url: Option<String>,
+}
+
+/// Request to check a verification code (`POST /verify/check`).
+#[derive(Debug, Serialize)]
+pub struct VerificationCheckRequest {
+    /// Session from `/verify/start`.
+    pub session_id: String,
+    /// Code received by SMS.
+    pub code: String,
+}
+
+/// Result of checking a verification code.
+#[derive(Debug, Deserialize)]
+pub struct VerificationCheckResponse {
+    /// Whether the code was correct.
+    pub verified: bool,
+}
+
+/// Request for attestation of a verified session (`POST /attest/simple`).
+#[derive(Debug, Serialize)]
+pub struct SessionAttestationRequest {
+    /// Protocol version.
+    pub version: String,
+    /// Verified session from `/verify/start`.
+    pub session_id: String,
+    /// User's public key.
+    pub user_pubkey: String,
+    /// Scope for proxy number generation.
+    pub scope: String,
+    /// Optional validity period in days (defaults to issuer config).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validity_days: Option<i64>,
+    /// JWT ID of an earlier attestation from this issuer being renewed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renews: Option<String>,
+    /// Solved CAPTCHA, for issuers that require one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub captcha_token: Option<String>,
+}
+
+/// Health check response as served by the issuer node.
+#[derive(Debug, Deserialize)]
+struct HealthResponse {
+    status: String,
+    version: String,
+    key_id: String,
+    time: DateTime<Utc>,
+}
+
+/// Structured result of an issuer health check.
+#[derive(Debug, Clone)]
+pub struct IssuerHealth {
+    /// Whether the issuer could be reached at all.
+    pub reachable: bool,
+    /// Status reported by the issuer (e.g., "ok").
+    pub status: Option<String>,
+    /// Identifier of the issuer's active signing key.
+    pub key_id: Option<String>,
+    /// Issuer node software version.
+    pub version: Option<String>,
+    /// Estimated issuer clock offset (issuer time minus local time).
+    pub clock_offset: Option<chrono::Duration>,
+}
+
+impl IssuerHealth {
+    /// Health result for an issuer that could not be reached.
+    fn unreachable() -> Self {
+        Self {
+            reachable: false,
+            status: None,
+            key_id: None,
+            version: None,
+            clock_offset: None,
+        }
+    }
+    
+    /// Check if the issuer is reachable and reports itself healthy.
+    pub fn is_healthy(&self) -> bool {
+        self.reachable && self.status.as_deref() == Some("ok")
+    }
+}
+
+/// Revocation list cached alongside the ETag it was served with.
+#[derive(Debug, Clone)]
+struct CachedRevocationList {
+    etag: Option<String>,
+    list: RevocationList,
+}
+
+/// How POST requests are signed with a partner secret.
+#[derive(Clone)]
+enum RequestSigning {
+    /// The `X-Hesha-Request-Signature` HMAC header.
+    Hmac { partner: String, secret: Vec<u8> },
+    /// HTTP Message Signatures (RFC 9421) with `hmac-sha256`.
+    MessageSignatures { key_id: String, secret: Vec<u8> },
 }
 
 /// Client for issuer node operations.
@@ -36,6 +192,10 @@ pub struct AttestationResponse {
 pub struct IssuerClient {
     client: Client,
     base_url: Url,
+    bearer_token: Option<String>,
+    admin_token: Option<String>,
+    signing: Option<RequestSigning>,
+    revocations: Arc<Mutex<Option<CachedRevocationList>>>,
 }
 
 impl IssuerClient {
@@ -55,7 +215,14 @@ impl IssuerClient {
             .timeout(Duration::from_secs(30))
             .build()?;
         
-        Ok(Self { client, base_url })
+        Ok(Self {
+            client,
+            base_url,
+            bearer_token: None,
+            admin_token: None,
+            signing: None,
+            revocations: Arc::new(Mutex::new(None)),
+        })
     }
     
     /// Create a client for testing (allows HTTP).
@@ -68,7 +235,86 @@ impl IssuerClient {
             .timeout(Duration::from_secs(30))
             .build()?;
         
-        Ok(Self { client, base_url })
+        Ok(Self {
+            client,
+            base_url,
+            bearer_token: None,
+            admin_token: None,
+            signing: None,
+            revocations: Arc::new(Mutex::new(None)),
+        })
+    }
+    
+    /// Authenticate attestation requests with a bearer token: an API key or
+    /// an OIDC access token, as the issuer's `[auth]` setting requires.
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.bearer_token = Some(token.into());
+        self
+    }
+    
+    /// Authenticate requests to the `/admin` routes with an operator
+    /// credential, as the issuer's `[admin_auth]` setting requires.
+    pub fn with_admin_token(mut self, token: impl Into<String>) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+    
+    /// Sign POST requests with a partner secret shared with the issuer, for
+    /// issuers that require HMAC request signatures.
+    pub fn with_request_signing(mut self, partner: impl Into<String>, secret: impl Into<Vec<u8>>) -> Self {
+        self.signing = Some(RequestSigning::Hmac {
+            partner: partner.into(),
+            secret: secret.into(),
+        });
+        self
+    }
+    
+    /// Sign POST requests with HTTP Message Signatures (RFC 9421) instead,
+    /// using the partner secret with `hmac-sha256` and the partner ID as
+    /// `keyid`.
+    pub fn with_message_signatures(mut self, partner: impl Into<String>, secret: impl Into<Vec<u8>>) -> Self {
+        self.signing = Some(RequestSigning::MessageSignatures {
+            key_id: partner.into(),
+            secret: secret.into(),
+        });
+        self
+    }
+    
+    /// POST a JSON body, signing it if a partner secret is set.
+    fn post<T: Serialize>(&self, url: Url, body: &T) -> ClientResult<RequestBuilder> {
+        let body = serde_json::to_vec(body)?;
+        let mut builder = self.client
+            .post(url.clone())
+            .header(header::CONTENT_TYPE, "application/json");
+        match &self.signing {
+            Some(RequestSigning::Hmac { partner, secret }) => {
+                let signature = RequestSignature::sign(
+                    partner,
+                    secret,
+                    Utc::now().timestamp(),
+                    "POST",
+                    url.path(),
+                    &body,
+                );
+                builder = builder.header(REQUEST_SIGNATURE_HEADER, signature.to_header());
+            }
+            Some(RequestSigning::MessageSignatures { key_id, secret }) => {
+                let signature = MessageSignature::sign(
+                    key_id,
+                    secret,
+                    Utc::now().timestamp(),
+                    "POST",
+                    url.path(),
+                    &body,
+                );
+                builder = builder
+                    .header(CONTENT_DIGEST_HEADER, hesha_crypto::content_digest(&body))
+                    .header(SIGNATURE_INPUT_HEADER, signature.signature_input())
+                    .header(SIGNATURE_HEADER, signature.to_header());
+            }
+            None => {}
+        }
+        Ok(builder.body(body))
     }
     
     /// Request attestation for a verified phone number.
@@ -81,6 +327,72 @@ impl IssuerClient {
         user_pubkey: &PublicKey,
         scope: &str,
         validity_days: Option<i64>,
+    ) -> ClientResult<AttestationResponse> {
+        self.send_attestation_request(phone_number, user_pubkey, scope, validity_days, None).await
+    }
+    
+    /// Request attestation with an idempotency key.
+    /// 
+    /// The issuer answers a repeated request with the same key with the
+    /// original attestation, so the call can be retried after a lost
+    /// response without issuing a duplicate.
+    pub async fn request_attestation_idempotent(
+        &self,
+        phone_number: &PhoneNumber,
+        user_pubkey: &PublicKey,
+        scope: &str,
+        validity_days: Option<i64>,
+        idempotency_key: &str,
+    ) -> ClientResult<AttestationResponse> {
+        self.send_attestation_request(phone_number, user_pubkey, scope, validity_days, Some(idempotency_key))
+            .await
+    }
+    
+    /// Validate an attestation request and see what proxy number format it
+    /// would produce, without anything being issued.
+    pub async fn preview_attestation(
+        &self,
+        phone_number: &PhoneNumber,
+        user_pubkey: &PublicKey,
+        scope: &str,
+        validity_days: Option<i64>,
+    ) -> ClientResult<AttestationPreview> {
+        let url = self.base_url.join("attest/preview")
+            .map_err(|e| ClientError::InvalidUrl(e.to_string()))?;
+        
+        let request = AttestationRequest {
+            version: "0.1.0-alpha".to_string(),
+            phone_number: phone_number.to_string(),
+            user_pubkey: user_pubkey.to_base64(),
+            scope: scope.to_string(),
+            validity_days,
+            nonce: hesha_crypto::generate_hex_nonce(),
+            timestamp: Utc::now().timestamp(),
+        };
+        
+        let mut builder = self.post(url, &request)?;
+        if let Some(token) = &self.bearer_token {
+            builder = builder.bearer_auth(token);
+        }
+        let response = builder.send().await?;
+        
+        if !response.status().is_success() {
+            return Err(ClientError::from_response(response).await);
+        }
+        
+        response.json()
+            .await
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+    
+    /// POST an attestation request to `/attest`.
+    async fn send_attestation_request(
+        &self,
+        phone_number: &PhoneNumber,
+        user_pubkey: &PublicKey,
+        scope: &str,
+        validity_days: Option<i64>,
+        idempotency_key: Option<&str>,
     ) -> ClientResult<AttestationResponse> {
         let url = self.base_url.join("attest")
             .map_err(|e| ClientError::InvalidUrl(e.to_string()))?;
@@ -91,25 +403,384 @@ impl IssuerClient {
             user_pubkey: user_pubkey.to_base64(),
             scope: scope.to_string(),
             validity_days,
+            nonce: hesha_crypto::generate_hex_nonce(),
+            timestamp: Utc::now().timestamp(),
         };
         
-        let response = self.client
-            .post(url)
-            .json(&request)
+        let mut builder = self.post(url, &request)?;
+        if let Some(key) = idempotency_key {
+            builder = builder.header(IDEMPOTENCY_KEY_HEADER, key);
+        }
+        if let Some(token) = &self.bearer_token {
+            builder = builder.bearer_auth(token);
+        }
+        let response = builder.send().await?;
+        
+        if !response.status().is_success() {
+            return Err(ClientError::from_response(response).await);
+        }
+        
+        response.json()
+            .await
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+    
+    /// Ask the issuer to send a verification code to a phone number.
+    /// 
+    /// For issuers that verify phone ownership themselves instead of
+    /// accepting pre-verified numbers on `/attest`.
+    pub async fn start_verification(&self, phone_number: &PhoneNumber) -> ClientResult<VerificationSession> {
+        self.start_verification_with_captcha(phone_number, None).await
+    }
+    
+    /// Like [`start_verification`](Self::start_verification), passing the
+    /// token from the issuer's CAPTCHA widget.
+    pub async fn start_verification_with_captcha(
+        &self,
+        phone_number: &PhoneNumber,
+        captcha_token: Option<&str>,
+    ) -> ClientResult<VerificationSession> {
+        let request = VerificationStartRequest {
+            phone_number: phone_number.to_string(),
+            captcha_token: captcha_token.map(str::to_string),
+        };
+        self.post_json("verify/start", &request).await
+    }
+    
+    /// Check the code the user received for a verification session.
+    pub async fn check_verification(&self, session_id: &str, code: &str) -> ClientResult<bool> {
+        let request = VerificationCheckRequest {
+            session_id: session_id.to_string(),
+            code: code.to_string(),
+        };
+        let response: VerificationCheckResponse = self.post_json("verify/check", &request).await?;
+        Ok(response.verified)
+    }
+    
+    /// Request attestation for a phone number verified with
+    /// [`start_verification`](Self::start_verification) and
+    /// [`check_verification`](Self::check_verification).
+    pub async fn request_session_attestation(
+        &self,
+        session_id: &str,
+        user_pubkey: &PublicKey,
+        scope: &str,
+        validity_days: Option<i64>,
+    ) -> ClientResult<AttestationResponse> {
+        self.request_session_attestation_with_captcha(session_id, user_pubkey, scope, validity_days, None)
+            .await
+    }
+    
+    /// Like [`request_session_attestation`](Self::request_session_attestation),
+    /// passing the token from the issuer's CAPTCHA widget.
+    pub async fn request_session_attestation_with_captcha(
+        &self,
+        session_id: &str,
+        user_pubkey: &PublicKey,
+        scope: &str,
+        validity_days: Option<i64>,
+        captcha_token: Option<&str>,
+    ) -> ClientResult<AttestationResponse> {
+        let request = SessionAttestationRequest {
+            version: "0.1.0-alpha".to_string(),
+            session_id: session_id.to_string(),
+            user_pubkey: user_pubkey.to_base64(),
+            scope: scope.to_string(),
+            validity_days,
+            renews: None,
+            captcha_token: captcha_token.map(str::to_string),
+        };
+        self.post_json("attest/simple", &request).await
+    }
+    
+    /// Request a new attestation for a verified session, replacing the
+    /// earlier attestation `renews` from this issuer.
+    /// 
+    /// The renewed attestation doesn't count against the issuer's cap on
+    /// active attestations per phone number.
+    pub async fn renew_session_attestation(
+        &self,
+        session_id: &str,
+        user_pubkey: &PublicKey,
+        scope: &str,
+        validity_days: Option<i64>,
+        renews: &str,
+    ) -> ClientResult<AttestationResponse> {
+        let request = SessionAttestationRequest {
+            version: "0.1.0-alpha".to_string(),
+            session_id: session_id.to_string(),
+            user_pubkey: user_pubkey.to_base64(),
+            scope: scope.to_string(),
+            validity_days,
+            renews: Some(renews.to_string()),
+            captcha_token: None,
+        };
+        self.post_json("attest/simple", &request).await
+    }
+    
+    /// POST a JSON body and decode the JSON response.
+    async fn post_json<T: Serialize, R: DeserializeOwned>(&self, path: &str, body: &T) -> ClientResult<R> {
+        let url = self.base_url.join(path)
+            .map_err(|e| ClientError::InvalidUrl(e.to_string()))?;
+        
+        let response = self.post(url, body)?.send().await?;
+        
+        if !response.status().is_success() {
+            return Err(ClientError::from_response(response).await);
+        }
+        
+        response.json()
+            .await
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+    
+    /// GET a path and decode the JSON response.
+    async fn get_json<R: DeserializeOwned>(&self, path: &str) -> ClientResult<R> {
+        let url = self.base_url.join(path)
+            .map_err(|e| ClientError::InvalidUrl(e.to_string()))?;
+        
+        Self::send_json(self.client.get(url)).await
+    }
+    
+    /// Start a request to an `/admin` route, with the operator token if set.
+    fn admin_request(&self, method: Method, path: &str) -> ClientResult<RequestBuilder> {
+        let url = self.base_url.join(path)
+            .map_err(|e| ClientError::InvalidUrl(e.to_string()))?;
+        
+        let builder = self.client.request(method, url);
+        Ok(match &self.admin_token {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        })
+    }
+    
+    /// Send a request and decode the JSON response.
+    async fn send_json<R: DeserializeOwned>(builder: RequestBuilder) -> ClientResult<R> {
+        let response = builder.send().await?;
+        
+        if !response.status().is_success() {
+            return Err(ClientError::from_response(response).await);
+        }
+        
+        response.json()
+            .await
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+    
+    /// Check the issuer's health before requesting attestations.
+    /// 
+    /// Connection failures are reported as an unreachable status rather than
+    /// an error. The clock offset is estimated against the midpoint of the
+    /// request round trip.
+    pub async fn health(&self) -> ClientResult<IssuerHealth> {
+        let url = self.base_url.join("health")
+            .map_err(|e| ClientError::InvalidUrl(e.to_string()))?;
+        
+        let sent_at = Utc::now();
+        let response = match self.client.get(url).send().await {
+            Ok(response) => response,
+            Err(e) if e.is_connect() || e.is_timeout() => return Ok(IssuerHealth::unreachable()),
+            Err(e) => return Err(e.into()),
+        };
+        let received_at = Utc::now();
+        
+        if !response.status().is_success() {
+            return Err(ClientError::from_response(response).await);
+        }
+        
+        let health: HealthResponse = response.json()
+            .await
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))?;
+        
+        let local_midpoint = sent_at + (received_at - sent_at) / 2;
+        
+        Ok(IssuerHealth {
+            reachable: true,
+            status: Some(health.status),
+            key_id: Some(health.key_id),
+            version: Some(health.version),
+            clock_offset: Some(health.time - local_midpoint),
+        })
+    }
+    
+    /// Fetch the node's operating metrics.
+    /// 
+    /// Like the other admin calls, this needs the operator credential set
+    /// with [`with_admin_token`](Self::with_admin_token).
+    pub async fn metrics(&self) -> ClientResult<NodeMetrics> {
+        Self::send_json(self.admin_request(Method::GET, "admin/metrics")?).await
+    }
+    
+    /// Fetch issuance statistics for the last `days` UTC days.
+    pub async fn stats(&self, days: u32) -> ClientResult<IssuanceStats> {
+        Self::send_json(self.admin_request(Method::GET, &format!("admin/stats?days={}", days))?).await
+    }
+    
+    /// Fetch audit log entries after sequence number `after`, oldest first.
+    /// 
+    /// A single call returns at most the node's export limit; call again
+    /// after the last entry returned until the result is empty.
+    pub async fn audit_entries(&self, after: u64) -> ClientResult<Vec<AuditEntry>> {
+        let response = self.admin_request(Method::GET, &format!("admin/audit?after={}", after))?
             .send()
             .await?;
+        if !response.status().is_success() {
+            return Err(ClientError::from_response(response).await);
+        }
+        
+        response.text().await?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(ClientError::from))
+            .collect()
+    }
+    
+    /// Export the node's attestations, proxy number assignments and
+    /// revocations as JSON, for a backup.
+    /// 
+    /// The body is returned as is, to be handed back to
+    /// [`restore_records`](Self::restore_records).
+    pub async fn backup_records(&self) -> ClientResult<Vec<u8>> {
+        let response = self.admin_request(Method::GET, "admin/backup")?.send().await?;
+        if !response.status().is_success() {
+            return Err(ClientError::from_response(response).await);
+        }
+        
+        Ok(response.bytes().await?.to_vec())
+    }
+    
+    /// Add records exported by [`backup_records`](Self::backup_records) to
+    /// the node. Records it already holds are left unchanged.
+    pub async fn restore_records(&self, records: Vec<u8>) -> ClientResult<RestoreSummary> {
+        let builder = self.admin_request(Method::POST, "admin/restore")?
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(records);
+        Self::send_json(builder).await
+    }
+    
+    /// Fetch the issuer's revocation list, following `next` links to
+    /// collect every page.
+    /// 
+    /// Every page must be signed by one of the keys the issuer publishes
+    /// at `/.well-known/hesha/pubkey.json`; an unsigned or tampered page
+    /// fails the whole fetch with [`ClientError::InvalidSignature`].
+    /// 
+    /// The list is cached together with its ETag. Subsequent calls send
+    /// `If-None-Match` and reuse the cached copy when the server answers
+    /// `304 Not Modified`, so polling is cheap.
+    pub async fn fetch_revocation_list(&self) -> ClientResult<RevocationList> {
+        let url = self.base_url.join(".well-known/hesha/revocations.json")
+            .map_err(|e| ClientError::InvalidUrl(e.to_string()))?;
+        
+        let cached = self.revocations.lock()
+            .map_err(|_| ClientError::InvalidResponse("Revocation cache poisoned".to_string()))?
+            .clone();
+        
+        let mut request = self.client.get(url);
+        if let Some(etag) = cached.as_ref().and_then(|c| c.etag.as_deref()) {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        
+        let response = request.send().await?;
+        
+        // A hit is a cached list the issuer confirmed is current
+        let not_modified = response.status() == StatusCode::NOT_MODIFIED;
+        hesha_metrics::record_cache_lookup(hesha_metrics::REVOCATION_LIST_CACHE, not_modified);
+        if not_modified {
+            return cached
+                .map(|c| c.list)
+                .ok_or_else(|| ClientError::InvalidResponse(
+                    "Server returned 304 without a cached revocation list".to_string()
+                ));
+        }
+        
+        if !response.status().is_success() {
+            return Err(ClientError::from_response(response).await);
+        }
+        
+        let etag = response.headers()
+            .get(header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        
+        let mut list: RevocationList = response.json()
+            .await
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))?;
+        
+        let keys = self.published_keys().await?;
+        verify_page(&list, &keys)?;
         
+        // Gather the remaining pages; the merged list no longer matches any
+        // one page's signature
+        while let Some(next) = list.next.take() {
+            let page: RevocationList = self.get_json(&next).await?;
+            verify_page(&page, &keys)?;
+            list.entries.extend(page.entries);
+            list.next = page.next;
+        }
+        list.signature = None;
+        
+        if let Ok(mut cache) = self.revocations.lock() {
+            *cache = Some(CachedRevocationList { etag, list: list.clone() });
+        }
+        
+        Ok(list)
+    }
+    
+    /// Every key the issuer currently publishes: the current key and
+    /// previous keys still inside their validity window.
+    async fn published_keys(&self) -> ClientResult<Vec<PublicKey>> {
+        let info: IssuerInfo = self.get_json(".well-known/hesha/pubkey.json").await?;
+        let mut keys = vec![info.public_key];
+        keys.extend(info.keys.into_iter().map(|key| key.public_key));
+        Ok(keys)
+    }
+    
+    /// Check whether an attestation has been revoked by this issuer.
+    /// 
+    /// Refreshes the revocation list (conditionally) before looking up the JWT ID.
+    pub async fn check_revocation_status(&self, jti: &str) -> ClientResult<RevocationStatus> {
+        let list = self.fetch_revocation_list().await?;
+        Ok(list.status(jti))
+    }
+    
+    /// Fetch the issuer's signed status of a single attestation.
+    /// 
+    /// Cheaper than the whole revocation list for a one-off check. The
+    /// response is returned as served; check it with
+    /// `hesha_core::verify_status_response` against the issuer key.
+    pub async fn fetch_status(&self, jti: &str) -> ClientResult<StatusResponse> {
+        let mut url = self.base_url.join("status/")
+            .map_err(|e| ClientError::InvalidUrl(e.to_string()))?;
+        url.path_segments_mut()
+            .map_err(|_| ClientError::InvalidUrl(self.base_url.to_string()))?
+            .pop_if_empty()
+            .push(jti);
+        
+        let response = self.client.get(url).send().await?;
         if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let message = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(ClientError::ServerError { status, message });
+            return Err(ClientError::from_response(response).await);
         }
         
         response.json()
             .await
             .map_err(|e| ClientError::InvalidResponse(e.to_string()))
     }
+}
+
+/// Check that a revocation list page is signed by one of `keys`.
+fn verify_page(page: &RevocationList, keys: &[PublicKey]) -> ClientResult<()> {
+    let signature = page.signature.as_ref()
+        .ok_or_else(|| ClientError::InvalidSignature("Revocation list page is not signed".to_string()))?;
+    
+    let payload = page.signing_payload();
+    if !keys.iter().any(|key| hesha_crypto::verify_signature(key, &payload, signature)) {
+        return Err(ClientError::InvalidSignature(
+            "Revocation list page is not signed by the issuer".to_string()
+        ));
+    }
     
+    Ok(())
 }
 
 #[cfg(test)]
@@ -128,4 +799,391 @@ mod tests {
         // Invalid URL
         assert!(IssuerClient::new("not a url").is_err());
     }
-}
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_health_check() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/health"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "status": "ok",
+                "version": "0.1.0",
+                "key_id": "default",
+                "time": Utc::now(),
+            })))
+            .mount(&server)
+            .await;
+        
+        let client = IssuerClient::new_insecure(&server.uri()).unwrap();
+        let health = client.health().await.unwrap();
+        
+        assert!(health.is_healthy());
+        assert_eq!(health.key_id.as_deref(), Some("default"));
+        assert!(health.clock_offset.unwrap().num_seconds().abs() < 5);
+        
+        // Nothing listening on this port
+        let client = IssuerClient::new_insecure("http://127.0.0.1:1").unwrap();
+        let health = client.health().await.unwrap();
+        assert!(!health.reachable);
+    }
+    
+    #[tokio::test]
+    async fn test_metrics() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/admin/metrics"))
+            .and(wiremock::matchers::header("authorization", "Bearer admin-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "started_at": Utc::now(),
+                "attestations_issued": 3,
+                "errors": { "invalid_phone_number": 2 },
+                "recent_attestations": [],
+            })))
+            .mount(&server)
+            .await;
+        
+        let client = IssuerClient::new_insecure(&server.uri()).unwrap()
+            .with_bearer_token("partner-key")
+            .with_admin_token("admin-key");
+        let metrics = client.metrics().await.unwrap();
+        
+        assert_eq!(metrics.attestations_issued, 3);
+        assert_eq!(metrics.errors["invalid_phone_number"], 2);
+    }
+    
+    #[tokio::test]
+    async fn test_problem_responses() {
+        use hesha_types::ErrorCode;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/admin/metrics"))
+            .respond_with(ResponseTemplate::new(503).set_body_raw(
+                serde_json::json!({
+                    "type": "https://hesha.org/problems/maintenance",
+                    "title": "Down for maintenance",
+                    "status": 503,
+                    "detail": "Down for a migration",
+                    "request_id": "req-1",
+                    "error": "maintenance",
+                    "retry_after": 60,
+                })
+                .to_string(),
+                "application/problem+json",
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/health"))
+            .respond_with(ResponseTemplate::new(502).set_body_string("Bad Gateway"))
+            .mount(&server)
+            .await;
+        
+        let client = IssuerClient::new_insecure(&server.uri()).unwrap();
+        let error = client.metrics().await.unwrap_err();
+        let problem = error.problem().unwrap();
+        assert_eq!(problem.code(), Some(ErrorCode::Maintenance));
+        assert_eq!((problem.status, problem.request_id.as_deref()), (503, Some("req-1")));
+        assert_eq!(problem.extensions["retry_after"], 60);
+        assert!(error.is_transient());
+        
+        // Proxies in front of a node answer with their own bodies
+        let error = client.get_json::<serde_json::Value>("/health").await.unwrap_err();
+        assert!(matches!(error, ClientError::ServerError { status: 502, .. }));
+    }
+    
+    #[tokio::test]
+    async fn test_idempotent_attestation_request() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/attest"))
+            .and(header("idempotency-key", "abc123"))
+            .and(header("authorization", "Bearer partner-key"))
+            .and(wiremock::matchers::header_exists(REQUEST_SIGNATURE_HEADER))
+            .and(|request: &wiremock::Request| {
+                let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                body["nonce"].as_str().is_some_and(|nonce| nonce.len() == 32) && body["timestamp"].is_i64()
+            })
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "attestation": "jwt",
+                "proxy_number": "+100123",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = IssuerClient::new_insecure(&server.uri()).unwrap()
+            .with_bearer_token("partner-key")
+            .with_request_signing("acme", "secret");
+        let user_pubkey = PublicKey::from_bytes([1; 32]);
+        let phone = PhoneNumber::new("+1234567890").unwrap();
+        let response = client
+            .request_attestation_idempotent(&phone, &user_pubkey, "1", None, "abc123")
+            .await
+            .unwrap();
+        assert_eq!(response.proxy_number, "+100123");
+    }
+    
+    #[tokio::test]
+    async fn test_message_signatures() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/attest"))
+            .and(|request: &wiremock::Request| {
+                let header = |name: &str| {
+                    request.headers.iter()
+                        .find(|(key, _)| key.as_str().eq_ignore_ascii_case(name))
+                        .map(|(_, values)| values.last().as_str().to_string())
+                };
+                let (Some(input), Some(signature), Some(digest)) =
+                    (header("signature-input"), header("signature"), header("content-digest"))
+                else {
+                    return false;
+                };
+                let Some(signature) = MessageSignature::parse(&input, &signature) else {
+                    return false;
+                };
+                hesha_crypto::verify_content_digest(&digest, &request.body)
+                    && signature.key_id == "acme"
+                    && signature.verify(b"secret", |component| match component {
+                        "@method" => Some(request.method.to_string()),
+                        "@path" => Some(request.url.path().to_string()),
+                        "content-digest" => Some(digest.clone()),
+                        _ => None,
+                    })
+            })
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "attestation": "jwt",
+                "proxy_number": "+100123",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = IssuerClient::new_insecure(&server.uri()).unwrap()
+            .with_message_signatures("acme", "secret");
+        let user_pubkey = PublicKey::from_bytes([1; 32]);
+        let phone = PhoneNumber::new("+1234567890").unwrap();
+        let response = client.request_attestation(&phone, &user_pubkey, "1", None).await.unwrap();
+        assert_eq!(response.proxy_number, "+100123");
+        assert!(server.received_requests().await.unwrap()[0]
+            .headers
+            .keys()
+            .all(|key| !key.as_str().eq_ignore_ascii_case(REQUEST_SIGNATURE_HEADER)));
+    }
+    
+    /// A revocation list page signed with `keypair`.
+    fn signed_revocations(keypair: &hesha_types::KeyPair, revoked: &[&str], next: Option<&str>) -> RevocationList {
+        let mut list = RevocationList::new("issuer.example.com");
+        list.entries = revoked
+            .iter()
+            .map(|jti| hesha_types::RevocationEntry {
+                jti: jti.to_string(),
+                revoked_at: Utc::now(),
+                reason: None,
+            })
+            .collect();
+        list.next = next.map(str::to_string);
+        list.signature = Some(hesha_crypto::sign_message(&keypair.private, &list.signing_payload()).unwrap());
+        list
+    }
+    
+    /// Serve `keypair` as the issuer's published key.
+    async fn mount_issuer_key(server: &wiremock::MockServer, keypair: &hesha_types::KeyPair) {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, ResponseTemplate};
+        
+        let info = IssuerInfo {
+            public_key: keypair.public.clone(),
+            algorithm: "Ed25519".to_string(),
+            created_at: Utc::now(),
+            key_id: Some("key-1".to_string()),
+            service_info: None,
+            keys: Vec::new(),
+        };
+        Mock::given(method("GET"))
+            .and(path("/.well-known/hesha/pubkey.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(info))
+            .mount(server)
+            .await;
+    }
+    
+    #[tokio::test]
+    async fn test_revocation_list_conditional_fetch() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        let keypair = hesha_crypto::generate_keypair().unwrap();
+        mount_issuer_key(&server, &keypair).await;
+        let body = signed_revocations(&keypair, &["revoked-jti"], None);
+        
+        // Conditional request is answered with 304
+        Mock::given(method("GET"))
+            .and(path("/.well-known/hesha/revocations.json"))
+            .and(header("if-none-match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(2)
+            .mount(&server)
+            .await;
+        
+        // Initial request returns the full list
+        Mock::given(method("GET"))
+            .and(path("/.well-known/hesha/revocations.json"))
+            .respond_with(ResponseTemplate::new(200)
+                .insert_header("etag", "\"v1\"")
+                .set_body_json(body))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        
+        let client = IssuerClient::new_insecure(&server.uri()).unwrap();
+        
+        let list = client.fetch_revocation_list().await.unwrap();
+        assert_eq!(list.entries.len(), 1);
+        
+        // Later checks are served from cache after the 304
+        let status = client.check_revocation_status("revoked-jti").await.unwrap();
+        assert!(status.is_revoked());
+        let status = client.check_revocation_status("other-jti").await.unwrap();
+        assert!(!status.is_revoked());
+    }
+    
+    #[tokio::test]
+    async fn test_revocation_list_pages_are_verified() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        let keypair = hesha_crypto::generate_keypair().unwrap();
+        mount_issuer_key(&server, &keypair).await;
+        
+        let first = signed_revocations(
+            &keypair,
+            &["first-jti"],
+            Some("/.well-known/hesha/revocations.json?page=2"),
+        );
+        let second = signed_revocations(&keypair, &["second-jti"], None);
+        let mut tampered = second.clone();
+        tampered.entries.clear();
+        
+        Mock::given(method("GET"))
+            .and(path("/.well-known/hesha/revocations.json"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&second))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/hesha/revocations.json"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&tampered))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/hesha/revocations.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&first))
+            .mount(&server)
+            .await;
+        
+        let client = IssuerClient::new_insecure(&server.uri()).unwrap();
+        let list = client.fetch_revocation_list().await.unwrap();
+        assert!(list.find("first-jti").is_some());
+        assert!(list.find("second-jti").is_some());
+        
+        // A page emptied on the way is refused rather than merged
+        let result = client.fetch_revocation_list().await;
+        assert!(matches!(result, Err(ClientError::InvalidSignature(_))));
+        
+        // As is an unsigned one
+        let mut unsigned = first.clone();
+        unsigned.next = None;
+        unsigned.signature = None;
+        assert!(matches!(verify_page(&unsigned, std::slice::from_ref(&keypair.public)), Err(ClientError::InvalidSignature(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_fetch_status() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/status/some%20jti"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "issuer": "issuer.example.com",
+                "jti": "some jti",
+                "status": "revoked",
+                "revoked_at": "2024-01-01T00:00:00Z",
+                "produced_at": "2024-01-02T00:00:00Z",
+                "next_update": "2024-01-02T00:05:00Z"
+            })))
+            .mount(&server)
+            .await;
+        
+        let client = IssuerClient::new_insecure(&server.uri()).unwrap();
+        let response = client.fetch_status("some jti").await.unwrap();
+        assert_eq!(response.jti, "some jti");
+        assert!(matches!(response.status, hesha_types::AttestationStatus::Revoked { .. }));
+        assert!(client.fetch_status("missing").await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_otp_attestation_flow() {
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/verify/start"))
+            .and(body_partial_json(serde_json::json!({ "phone_number": "+1234567890" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "session_id": "session-1",
+                "expires_in": 300,
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/verify/check"))
+            .and(body_partial_json(serde_json::json!({ "session_id": "session-1", "code": "123456" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "verified": true })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/attest/simple"))
+            .and(body_partial_json(serde_json::json!({ "session_id": "session-1", "scope": "1" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "attestation": "eyJ.test.jwt",
+                "proxy_number": "+100123456789",
+            })))
+            .mount(&server)
+            .await;
+        
+        let client = IssuerClient::new_insecure(&server.uri()).unwrap();
+        let user_pubkey = PublicKey::from_bytes([7u8; 32]);
+        
+        let session = client.start_verification(&PhoneNumber::new("+1234567890").unwrap()).await.unwrap();
+        assert_eq!(session.expires_in, Some(300));
+        assert!(client.check_verification(&session.session_id, "123456").await.unwrap());
+        
+        let response = client
+            .request_session_attestation(&session.session_id, &user_pubkey, "1", None)
+            .await
+            .unwrap();
+        assert_eq!(response.proxy_number, "+100123456789");
+    }
+}
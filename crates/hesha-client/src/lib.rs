@@ -3,8 +3,10 @@
 //! This crate provides typed HTTP clients for interacting with
 //! Hesha Protocol nodes:
 //! 
-//! - `IssuerClient`: For requesting attestations
+//! - `IssuerClient`: For requesting attestations and revocation lists
 //! - Helper types for requests and responses
+//! - `WebhookReceiver`: For consuming signed issuer webhook deliveries
+//! - The node's error catalogue ([`ErrorCode`], [`Problem`])
 //! 
 //! # Security
 //! 
@@ -17,6 +19,13 @@
 
 pub mod error;
 pub mod issuer;
+pub mod webhooks;
 
 pub use error::{ClientError, ClientResult};
-pub use issuer::{AttestationRequest, AttestationResponse, IssuerClient};
\ No newline at end of file
+pub use hesha_types::{ErrorCode, Problem};
+pub use issuer::{
+    AttestationPreview, AttestationRequest, AttestationResponse, IssuerClient, IssuerHealth,
+    SessionAttestationRequest, IDEMPOTENCY_KEY_HEADER,
+    VerificationCheckRequest, VerificationCheckResponse, VerificationSession, VerificationStartRequest,
+};
+pub use webhooks::{WebhookError, WebhookReceiver};
@@ -0,0 +1,265 @@
+//! Receiving issuer webhooks.
+//!
+//! Issuers sign each delivery with the endpoint's secret (see
+//! [`WEBHOOK_SIGNATURE_HEADER`]) and retry deliveries that aren't
+//! acknowledged, repeating the event ID. [`WebhookReceiver`] checks the
+//! signature and its timestamp, drops deliveries it has already accepted,
+//! and hands back the typed [`WebhookEvent`]:
+//!
+//! ```no_run
+//! # use hesha_client::webhooks::WebhookReceiver;
+//! # use hesha_types::WebhookEventData;
+//! # fn handle(signature: Option<&str>, body: &[u8]) -> Result<(), hesha_client::webhooks::WebhookError> {
+//! let receiver = WebhookReceiver::new("endpoint-secret");
+//! if let Some(event) = receiver.receive(signature, body)? {
+//!     if let WebhookEventData::AttestationRevoked { jti, .. } = &event.data {
+//!         println!("{} was revoked", jti);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::Utc;
+use hesha_crypto::{constant_time_compare, hmac_sha256};
+use hesha_types::WebhookEvent;
+pub use hesha_types::{WebhookEventData, WebhookEventKind, WEBHOOK_SIGNATURE_HEADER};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// How far a delivery's signature timestamp may be from now by default.
+pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// How long accepted event IDs are remembered by default.
+///
+/// Comfortably longer than issuers keep retrying a delivery.
+pub const DEFAULT_DEDUP_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Why a webhook delivery was refused.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WebhookError {
+    /// The delivery carried no signature header.
+    #[error("Missing {} header", WEBHOOK_SIGNATURE_HEADER)]
+    MissingSignature,
+
+    /// The signature header isn't `t=<unix seconds>,v1=<hex>`.
+    #[error("Malformed signature header: {0}")]
+    MalformedSignature(String),
+
+    /// No signature in the header matches the body under the secret.
+    #[error("Webhook signature does not match")]
+    InvalidSignature,
+
+    /// The signature timestamp is outside the tolerance.
+    #[error("Webhook signature timestamp {0} is outside the tolerance")]
+    StaleTimestamp(i64),
+
+    /// The body isn't a webhook event.
+    #[error("Invalid webhook payload: {0}")]
+    InvalidPayload(String),
+}
+
+/// Check a delivery's signature header against its body.
+///
+/// `header` is the value of [`WEBHOOK_SIGNATURE_HEADER`]. Any `v1`
+/// signature in it may match, so deliveries stay valid while an endpoint's
+/// secret is being rotated. The timestamp must be within `tolerance` of
+/// `now` (unix seconds), which stops old deliveries from being replayed.
+pub fn verify_signature(
+    secret: &str,
+    header: &str,
+    body: &[u8],
+    now: i64,
+    tolerance: Duration,
+) -> Result<(), WebhookError> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => {
+                let parsed = value
+                    .parse::<i64>()
+                    .map_err(|_| WebhookError::MalformedSignature(format!("bad timestamp {:?}", value)))?;
+                timestamp = Some(parsed);
+            }
+            Some(("v1", value)) => signatures.push(value),
+            // Unknown schemes are skipped, so issuers can add new ones
+            Some(_) => {}
+            None => return Err(WebhookError::MalformedSignature(format!("unexpected {:?}", part))),
+        }
+    }
+    let timestamp = timestamp.ok_or_else(|| WebhookError::MalformedSignature("no timestamp".to_string()))?;
+    if signatures.is_empty() {
+        return Err(WebhookError::MalformedSignature("no v1 signature".to_string()));
+    }
+
+    let mut message = format!("{}.", timestamp).into_bytes();
+    message.extend_from_slice(body);
+    let expected = hmac_sha256(secret.as_bytes(), &message);
+    let matches = signatures
+        .iter()
+        .filter_map(|signature| hex::decode(signature).ok())
+        .any(|signature| constant_time_compare(&signature, &expected));
+    if !matches {
+        return Err(WebhookError::InvalidSignature);
+    }
+
+    if timestamp.abs_diff(now) > tolerance.as_secs() {
+        return Err(WebhookError::StaleTimestamp(timestamp));
+    }
+    Ok(())
+}
+
+/// Parse a delivery's body into its event.
+pub fn parse_event(body: &[u8]) -> Result<WebhookEvent, WebhookError> {
+    serde_json::from_slice(body).map_err(|e| WebhookError::InvalidPayload(e.to_string()))
+}
+
+/// Verifies, deduplicates and parses deliveries to one webhook endpoint.
+///
+/// Accepted event IDs are kept in memory; consumers running several
+/// replicas should deduplicate again where they store the events.
+pub struct WebhookReceiver {
+    secret: String,
+    tolerance: Duration,
+    dedup_window: Duration,
+    seen: Mutex<HashMap<String, Instant>>,
+}
+
+impl WebhookReceiver {
+    /// A receiver for deliveries signed with the endpoint's `secret`.
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self {
+            secret: secret.into(),
+            tolerance: DEFAULT_TOLERANCE,
+            dedup_window: DEFAULT_DEDUP_WINDOW,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Accept signature timestamps up to `tolerance` from now.
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Remember accepted event IDs for `window`.
+    pub fn with_dedup_window(mut self, window: Duration) -> Self {
+        self.dedup_window = window;
+        self
+    }
+
+    /// Check a delivery and parse its event.
+    ///
+    /// Returns `None` for an event already accepted: answer it with a 2xx
+    /// all the same, so the issuer stops retrying.
+    pub fn receive(&self, signature: Option<&str>, body: &[u8]) -> Result<Option<WebhookEvent>, WebhookError> {
+        let signature = signature.ok_or(WebhookError::MissingSignature)?;
+        verify_signature(&self.secret, signature, body, Utc::now().timestamp(), self.tolerance)?;
+        let event = parse_event(body)?;
+
+        let now = Instant::now();
+        let mut seen = self.seen();
+        seen.retain(|_, expires| *expires > now);
+        if seen.contains_key(&event.id) {
+            return Ok(None);
+        }
+        seen.insert(event.id.clone(), now + self.dedup_window);
+        Ok(Some(event))
+    }
+
+    /// Forget an accepted event, so a retried delivery of it is accepted
+    /// again. Call it when handling the event failed.
+    pub fn forget(&self, event_id: &str) {
+        self.seen().remove(event_id);
+    }
+
+    fn seen(&self) -> std::sync::MutexGuard<'_, HashMap<String, Instant>> {
+        self.seen.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hesha_types::IssuedAttestation;
+
+    fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+        let mut message = format!("{}.", timestamp).into_bytes();
+        message.extend_from_slice(body);
+        format!("t={},v1={}", timestamp, hex::encode(hmac_sha256(secret.as_bytes(), &message)))
+    }
+
+    fn body(id: &str) -> Vec<u8> {
+        serde_json::to_vec(&WebhookEvent {
+            id: id.to_string(),
+            created_at: Utc::now(),
+            data: WebhookEventData::AttestationIssued(IssuedAttestation {
+                jti: "jti-1".to_string(),
+                proxy_number: "+1001234567890".to_string(),
+                scope: "default".to_string(),
+                expires_at: Utc::now(),
+            }),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let body = body("evt_1");
+        let now = Utc::now().timestamp();
+        let tolerance = DEFAULT_TOLERANCE;
+        let header = sign("secret", now, &body);
+        verify_signature("secret", &header, &body, now, tolerance).unwrap();
+
+        // Any of several signatures may match, during secret rotation
+        let rotated = format!("{},v1={}", sign("old", now, &body), &header[header.find("v1=").unwrap() + 3..]);
+        verify_signature("secret", &rotated, &body, now, tolerance).unwrap();
+
+        assert_eq!(verify_signature("other", &header, &body, now, tolerance), Err(WebhookError::InvalidSignature));
+        assert_eq!(verify_signature("secret", &header, b"{}", now, tolerance), Err(WebhookError::InvalidSignature));
+        assert_eq!(
+            verify_signature("secret", &header, &body, now + 301, tolerance),
+            Err(WebhookError::StaleTimestamp(now))
+        );
+        assert!(matches!(
+            verify_signature("secret", "v1=abcd", &body, now, tolerance),
+            Err(WebhookError::MalformedSignature(_))
+        ));
+        assert!(matches!(
+            verify_signature("secret", &format!("t={}", now), &body, now, tolerance),
+            Err(WebhookError::MalformedSignature(_))
+        ));
+    }
+
+    #[test]
+    fn test_receive_deduplicates() {
+        let receiver = WebhookReceiver::new("secret");
+        let body = body("evt_1");
+        let header = sign("secret", Utc::now().timestamp(), &body);
+
+        assert_eq!(receiver.receive(None, &body), Err(WebhookError::MissingSignature));
+        let event = receiver.receive(Some(&header), &body).unwrap().unwrap();
+        assert_eq!(event.id, "evt_1");
+        assert_eq!(event.data.kind(), WebhookEventKind::AttestationIssued);
+        assert!(receiver.receive(Some(&header), &body).unwrap().is_none());
+
+        receiver.forget("evt_1");
+        assert!(receiver.receive(Some(&header), &body).unwrap().is_some());
+
+        let other = br#"{"id":"evt_2","created_at":"2024-01-01T00:00:00Z","type":"unknown"}"#;
+        let header = sign("secret", Utc::now().timestamp(), other);
+        assert!(matches!(receiver.receive(Some(&header), other), Err(WebhookError::InvalidPayload(_))));
+    }
+
+    #[test]
+    fn test_dedup_window() {
+        let receiver = WebhookReceiver::new("secret").with_dedup_window(Duration::ZERO);
+        let body = body("evt_1");
+        let header = sign("secret", Utc::now().timestamp(), &body);
+        assert!(receiver.receive(Some(&header), &body).unwrap().is_some());
+        assert!(receiver.receive(Some(&header), &body).unwrap().is_some());
+    }
+}
@@ -0,0 +1,612 @@
+//! The checks, grouped as the specification's sections are.
+//!
+//! Checks run in order and later ones build on what earlier ones fetched:
+//! the key document found by discovery verifies revocation lists, status
+//! responses and the attestation. A check whose prerequisite failed is
+//! skipped rather than failed again.
+
+use crate::report::{CheckResult, Outcome};
+use crate::Options;
+use hesha_core::attestation::jwt::decode_header_unverified;
+use hesha_core::{parse_attestation_jwt, verify_revocation_list, verify_status_response};
+use hesha_crypto::{generate_hex_nonce, generate_keypair, verify_binding_signature};
+use hesha_types::{
+    AttestationStatus, IssuerInfo, Problem, PublicKey, RevocationList, StatusResponse,
+    PROBLEM_CONTENT_TYPE,
+};
+use reqwest::{header, Client, Response, StatusCode, Url};
+
+/// Path of the key document.
+const PUBKEY_PATH: &str = ".well-known/hesha/pubkey.json";
+
+/// Path of the revocation list.
+const REVOCATIONS_PATH: &str = ".well-known/hesha/revocations.json";
+
+/// Result of a check before it is recorded.
+enum Verdict {
+    Pass,
+    Fail(String),
+    Skip(String),
+}
+
+/// A conformance run in progress.
+pub(crate) struct Suite<'a> {
+    options: &'a Options,
+    client: Client,
+    base: Url,
+    results: Vec<CheckResult>,
+    info: Option<IssuerInfo>,
+    attestation: Option<String>,
+}
+
+impl<'a> Suite<'a> {
+    pub(crate) fn new(options: &'a Options, base: Url) -> anyhow::Result<Self> {
+        let client = Client::builder().timeout(options.timeout).build()?;
+        Ok(Self {
+            options,
+            client,
+            base,
+            results: Vec::new(),
+            info: None,
+            attestation: options.attestation.clone(),
+        })
+    }
+
+    /// Run every check, returning the results.
+    pub(crate) async fn run(mut self) -> Vec<CheckResult> {
+        self.discovery().await;
+        self.health().await;
+        self.errors().await;
+        self.revocations().await;
+        self.attestation().await;
+        self.status().await;
+        self.results
+    }
+
+    fn record(&mut self, id: &str, title: &str, verdict: Verdict) {
+        let (outcome, detail) = match verdict {
+            Verdict::Pass => (Outcome::Pass, None),
+            Verdict::Fail(detail) => (Outcome::Fail, Some(detail)),
+            Verdict::Skip(detail) => (Outcome::Skip, Some(detail)),
+        };
+        self.results.push(CheckResult {
+            id: id.to_string(),
+            title: title.to_string(),
+            outcome,
+            detail,
+        });
+    }
+
+    async fn get(&self, path: &str) -> Result<Response, String> {
+        let url = self.base.join(path).map_err(|e| e.to_string())?;
+        self.client.get(url).send().await.map_err(|e| e.to_string())
+    }
+
+    /// Domains an issuer at this URL may name itself by.
+    fn domains(&self) -> Vec<String> {
+        let host = self.base.host_str().unwrap_or_default().to_string();
+        let mut domains = vec![host.clone()];
+        if let Some(port) = self.base.port() {
+            domains.push(format!("{}:{}", host, port));
+        }
+        domains
+    }
+
+    /// Published keys, the top-level one first.
+    fn published_keys(&self) -> Vec<PublicKey> {
+        let Some(info) = &self.info else {
+            return Vec::new();
+        };
+        let mut keys = vec![info.public_key.clone()];
+        keys.extend(info.keys.iter().map(|key| key.public_key.clone()));
+        keys
+    }
+
+    /// Key discovery: the key document and its keys.
+    async fn discovery(&mut self) {
+        const TITLE: &str = "Key document is served at /.well-known/hesha/pubkey.json";
+        let response = match self.get(PUBKEY_PATH).await {
+            Ok(response) => response,
+            Err(e) => {
+                self.record("discovery.document", TITLE, Verdict::Fail(e));
+                return;
+            }
+        };
+        let status = response.status();
+        let json_type = is_json(&response, "application/json");
+        let verdict = match status {
+            StatusCode::OK => match response.json::<IssuerInfo>().await {
+                Ok(info) => {
+                    self.info = Some(info);
+                    Verdict::Pass
+                }
+                Err(e) => Verdict::Fail(format!("Not a key document: {}", e)),
+            },
+            status => Verdict::Fail(format!("Answered {}", status)),
+        };
+        self.record("discovery.document", TITLE, verdict);
+
+        let Some(info) = self.info.clone() else {
+            for (id, title) in [
+                (
+                    "discovery.content_type",
+                    "Key document is served as application/json",
+                ),
+                ("discovery.algorithm", "Every published key is Ed25519"),
+                (
+                    "discovery.key_ids",
+                    "Key IDs are unique and include the current key",
+                ),
+            ] {
+                self.record(id, title, Verdict::Skip("No key document".to_string()));
+            }
+            return;
+        };
+
+        self.record(
+            "discovery.content_type",
+            "Key document is served as application/json",
+            if json_type {
+                Verdict::Pass
+            } else {
+                Verdict::Fail("Wrong Content-Type".to_string())
+            },
+        );
+
+        let algorithms = std::iter::once(info.algorithm.as_str())
+            .chain(info.keys.iter().map(|key| key.algorithm.as_str()))
+            .find(|algorithm| *algorithm != "Ed25519");
+        self.record(
+            "discovery.algorithm",
+            "Every published key is Ed25519",
+            match algorithms {
+                None => Verdict::Pass,
+                Some(algorithm) => Verdict::Fail(format!("Key with algorithm {}", algorithm)),
+            },
+        );
+
+        let mut ids: Vec<&str> = info.keys.iter().map(|key| key.key_id.as_str()).collect();
+        ids.sort_unstable();
+        let duplicate = ids.windows(2).find(|pair| pair[0] == pair[1]);
+        let current_listed = match &info.key_id {
+            Some(key_id) if !info.keys.is_empty() => info
+                .keys
+                .iter()
+                .any(|key| key.key_id == *key_id && key.public_key == info.public_key),
+            _ => true,
+        };
+        self.record(
+            "discovery.key_ids",
+            "Key IDs are unique and include the current key",
+            match (duplicate, current_listed) {
+                (Some(pair), _) => Verdict::Fail(format!("Key ID {} is published twice", pair[0])),
+                (None, false) => Verdict::Fail("Current key is missing from keys".to_string()),
+                (None, true) => Verdict::Pass,
+            },
+        );
+    }
+
+    /// `GET /health`.
+    async fn health(&mut self) {
+        let verdict = match self.get("health").await {
+            Ok(response) if response.status() == StatusCode::OK => {
+                match response.json::<serde_json::Value>().await {
+                    Ok(body) if body.get("status").is_some_and(|s| s.is_string()) => Verdict::Pass,
+                    Ok(_) => Verdict::Fail("No status in the body".to_string()),
+                    Err(e) => Verdict::Fail(format!("Body is not JSON: {}", e)),
+                }
+            }
+            Ok(response) => Verdict::Fail(format!("Answered {}", response.status())),
+            Err(e) => Verdict::Fail(e),
+        };
+        self.record("health.status", "GET /health reports a status", verdict);
+    }
+
+    /// Errors are RFC 7807 problem documents.
+    async fn errors(&mut self) {
+        let verdict = match self.get("hesha-conformance/no-such-endpoint").await {
+            Ok(response) if response.status() == StatusCode::NOT_FOUND => {
+                problem_verdict(response).await
+            }
+            Ok(response) => Verdict::Fail(format!("Answered {}", response.status())),
+            Err(e) => Verdict::Fail(e),
+        };
+        self.record(
+            "errors.not_found",
+            "Unknown endpoints answer 404 with a problem document",
+            verdict,
+        );
+
+        let url = match self.base.join("attest") {
+            Ok(url) => url,
+            Err(e) => {
+                let title = "Malformed requests answer 4xx with a problem document";
+                self.record(
+                    "errors.malformed_request",
+                    title,
+                    Verdict::Fail(e.to_string()),
+                );
+                return;
+            }
+        };
+        let response = self
+            .client
+            .post(url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body("{\"version\":")
+            .send()
+            .await;
+        let verdict = match response {
+            Ok(response) if response.status().is_client_error() => problem_verdict(response).await,
+            Ok(response) => Verdict::Fail(format!("Answered {}", response.status())),
+            Err(e) => Verdict::Fail(e.to_string()),
+        };
+        self.record(
+            "errors.malformed_request",
+            "Malformed requests answer 4xx with a problem document",
+            verdict,
+        );
+    }
+
+    /// The signed revocation list.
+    async fn revocations(&mut self) {
+        const TITLE: &str = "Revocation list is served at /.well-known/hesha/revocations.json";
+        let response = match self.get(REVOCATIONS_PATH).await {
+            Ok(response) => response,
+            Err(e) => {
+                self.record("revocations.document", TITLE, Verdict::Fail(e));
+                return;
+            }
+        };
+        let etag = response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let list = match response.status() {
+            StatusCode::OK => match response.json::<RevocationList>().await {
+                Ok(list) => Some(list),
+                Err(e) => {
+                    let detail = format!("Not a revocation list: {}", e);
+                    self.record("revocations.document", TITLE, Verdict::Fail(detail));
+                    None
+                }
+            },
+            status => {
+                self.record(
+                    "revocations.document",
+                    TITLE,
+                    Verdict::Fail(format!("Answered {}", status)),
+                );
+                None
+            }
+        };
+        let Some(list) = list else {
+            return;
+        };
+        let domains = self.domains();
+        self.record(
+            "revocations.document",
+            TITLE,
+            if domains.contains(&list.issuer)
+                || self
+                    .info
+                    .as_ref()
+                    .is_some_and(|info| info.service_info.is_some())
+            {
+                Verdict::Pass
+            } else {
+                Verdict::Fail(format!("List names issuer {}", list.issuer))
+            },
+        );
+
+        let keys = self.published_keys();
+        let signed = |page: &RevocationList| {
+            keys.iter()
+                .any(|key| verify_revocation_list(page, key).is_ok())
+        };
+        const SIGNED: &str = "Every revocation list page is signed with a published key";
+        let mut verdict = if keys.is_empty() {
+            Verdict::Skip("No key document".to_string())
+        } else if signed(&list) {
+            Verdict::Pass
+        } else {
+            Verdict::Fail("First page is not signed with a published key".to_string())
+        };
+
+        // Follow the pages, a bounded number of them
+        let mut next = list.next.clone();
+        let mut pages = 1;
+        while let (Some(path), Verdict::Pass) = (next.take(), &verdict) {
+            if pages == 50 {
+                break;
+            }
+            pages += 1;
+            match self.get(path.trim_start_matches('/')).await {
+                Ok(response) if response.status() == StatusCode::OK => {
+                    match response.json::<RevocationList>().await {
+                        Ok(page) if signed(&page) => next = page.next,
+                        Ok(_) => verdict = Verdict::Fail(format!("Page {} is not signed", path)),
+                        Err(e) => verdict = Verdict::Fail(format!("Page {}: {}", path, e)),
+                    }
+                }
+                Ok(response) => {
+                    verdict = Verdict::Fail(format!("Page {} answered {}", path, response.status()))
+                }
+                Err(e) => verdict = Verdict::Fail(e),
+            }
+        }
+        self.record("revocations.signature", SIGNED, verdict);
+
+        const CONDITIONAL: &str = "A current ETag is answered with 304 Not Modified";
+        let verdict = match etag {
+            None => Verdict::Skip("List is served without an ETag".to_string()),
+            Some(etag) => match self.base.join(REVOCATIONS_PATH) {
+                Ok(url) => match self
+                    .client
+                    .get(url)
+                    .header(header::IF_NONE_MATCH, &etag)
+                    .send()
+                    .await
+                {
+                    Ok(response) if response.status() == StatusCode::NOT_MODIFIED => Verdict::Pass,
+                    Ok(response) => Verdict::Fail(format!("Answered {}", response.status())),
+                    Err(e) => Verdict::Fail(e.to_string()),
+                },
+                Err(e) => Verdict::Fail(e.to_string()),
+            },
+        };
+        self.record("revocations.conditional", CONDITIONAL, verdict);
+    }
+
+    /// An attestation, supplied or requested from `/attest`.
+    async fn attestation(&mut self) {
+        const ISSUE: &str = "POST /attest issues an attestation";
+        if self.attestation.is_some() {
+            self.record(
+                "attestation.issue",
+                ISSUE,
+                Verdict::Skip("Attestation supplied".to_string()),
+            );
+        } else {
+            let verdict = match self.request_attestation().await {
+                Ok(jwt) => {
+                    self.attestation = Some(jwt);
+                    Verdict::Pass
+                }
+                Err(verdict) => verdict,
+            };
+            self.record("attestation.issue", ISSUE, verdict);
+        }
+
+        const HEADER: &str = "JWT header names EdDSA and a published kid";
+        const CLAIMS: &str = "Claims are complete, unexpired and name this issuer";
+        const SIGNATURE: &str = "JWT is signed with the key its kid names";
+        const BINDING: &str = "Binding proof verifies with the issuer key";
+        let Some(jwt) = self.attestation.clone() else {
+            for (id, title) in [
+                ("attestation.header", HEADER),
+                ("attestation.claims", CLAIMS),
+                ("attestation.signature", SIGNATURE),
+                ("attestation.binding_proof", BINDING),
+            ] {
+                self.record(id, title, Verdict::Skip("No attestation".to_string()));
+            }
+            return;
+        };
+
+        // The key named by the header
+        let mut key = None;
+        let verdict = match decode_header_unverified(&jwt) {
+            Ok(header) => {
+                let kid = header.get("kid").and_then(|kid| kid.as_str());
+                match (header.get("alg").and_then(|alg| alg.as_str()), &self.info) {
+                    (Some("EdDSA"), Some(info)) => match info.key(kid) {
+                        Some(found) => {
+                            key = Some(found.clone());
+                            Verdict::Pass
+                        }
+                        None => Verdict::Fail(format!("kid {:?} is not published", kid)),
+                    },
+                    (Some("EdDSA"), None) => Verdict::Skip("No key document".to_string()),
+                    (alg, _) => Verdict::Fail(format!("alg is {:?}", alg)),
+                }
+            }
+            Err(e) => Verdict::Fail(e.to_string()),
+        };
+        self.record("attestation.header", HEADER, verdict);
+
+        let attestation = parse_attestation_jwt(&jwt);
+        let verdict = match &attestation {
+            Ok(attestation) => {
+                let named =
+                    attestation.trust_domain.is_some() || self.domains().contains(&attestation.iss);
+                if attestation.jti.is_empty() {
+                    Verdict::Fail("jti is empty".to_string())
+                } else if attestation.exp <= attestation.iat {
+                    Verdict::Fail("exp is not after iat".to_string())
+                } else if attestation.exp <= chrono::Utc::now() {
+                    Verdict::Fail(format!("Expired at {}", attestation.exp))
+                } else if !named {
+                    Verdict::Fail(format!("iss is {}", attestation.iss))
+                } else {
+                    Verdict::Pass
+                }
+            }
+            Err(e) => Verdict::Fail(e.to_string()),
+        };
+        self.record("attestation.claims", CLAIMS, verdict);
+
+        let Some(key) = key else {
+            for (id, title) in [
+                ("attestation.signature", SIGNATURE),
+                ("attestation.binding_proof", BINDING),
+            ] {
+                self.record(id, title, Verdict::Skip("Signing key unknown".to_string()));
+            }
+            return;
+        };
+        let signed =
+            hesha_core::attestation::jwt::decode_jwt::<hesha_core::attestation::Claims>(&jwt, &key);
+        let verdict = match &signed {
+            Ok(_) => Verdict::Pass,
+            Err(e) => Verdict::Fail(e.to_string()),
+        };
+        self.record("attestation.signature", SIGNATURE, verdict);
+
+        let verdict = match (&signed, &attestation) {
+            (Ok(claims), Ok(attestation)) => {
+                if verify_binding_signature(
+                    &claims.phone_hash,
+                    &claims.user_pubkey,
+                    &claims.sub,
+                    attestation.iat.timestamp(),
+                    &claims.binding_proof,
+                    &key,
+                ) {
+                    Verdict::Pass
+                } else {
+                    Verdict::Fail("Binding proof does not verify".to_string())
+                }
+            }
+            _ => Verdict::Skip("Attestation did not verify".to_string()),
+        };
+        self.record("attestation.binding_proof", BINDING, verdict);
+    }
+
+    /// Request an attestation for the configured phone number.
+    async fn request_attestation(&self) -> Result<String, Verdict> {
+        let user = generate_keypair().map_err(|e| Verdict::Fail(e.to_string()))?;
+        let url = self
+            .base
+            .join("attest")
+            .map_err(|e| Verdict::Fail(e.to_string()))?;
+        let body = serde_json::json!({
+            "version": "0.1.0-alpha",
+            "phone_number": self.options.phone_number,
+            "user_pubkey": user.public.to_base64(),
+            "scope": self.options.scope,
+            "nonce": generate_hex_nonce(),
+            "timestamp": chrono::Utc::now().timestamp(),
+        });
+        let mut request = self.client.post(url).json(&body);
+        if let Some(token) = &self.options.bearer_token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| Verdict::Fail(e.to_string()))?;
+        match response.status() {
+            StatusCode::OK | StatusCode::CREATED => {}
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                return Err(Verdict::Skip(
+                    "Issuer requires authentication; pass a bearer token or an attestation"
+                        .to_string(),
+                ))
+            }
+            status => return Err(Verdict::Fail(format!("Answered {}", status))),
+        }
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| Verdict::Fail(e.to_string()))?;
+        body.get("attestation")
+            .and_then(|jwt| jwt.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| Verdict::Fail("No attestation in the response".to_string()))
+    }
+
+    /// The optional `GET /status/{jti}` responder.
+    async fn status(&mut self) {
+        const UNKNOWN: &str = "Status of an unknown attestation is a signed unknown";
+        const ISSUED: &str = "Status of an issued attestation is a signed good";
+        let keys = self.published_keys();
+        let unknown = format!("hesha-conformance-{}", generate_hex_nonce());
+        let (verdict, served) = match self.fetch_status(&unknown, &keys).await {
+            Ok(None) => (Verdict::Skip("No status responder".to_string()), false),
+            Ok(Some(AttestationStatus::Unknown)) => (Verdict::Pass, true),
+            Ok(Some(status)) => (Verdict::Fail(format!("Answered {:?}", status)), true),
+            Err(verdict) => (verdict, true),
+        };
+        self.record("status.unknown", UNKNOWN, verdict);
+
+        let jti = self
+            .attestation
+            .as_deref()
+            .and_then(|jwt| parse_attestation_jwt(jwt).ok())
+            .map(|attestation| attestation.jti);
+        let verdict = match jti {
+            _ if !served => Verdict::Skip("No status responder".to_string()),
+            None => Verdict::Skip("No attestation".to_string()),
+            Some(jti) => match self.fetch_status(&jti, &keys).await {
+                Ok(Some(AttestationStatus::Good)) => Verdict::Pass,
+                Ok(Some(status)) => Verdict::Fail(format!("Answered {:?}", status)),
+                Ok(None) => Verdict::Fail("Answered 404".to_string()),
+                Err(verdict) => verdict,
+            },
+        };
+        self.record("status.issued", ISSUED, verdict);
+    }
+
+    /// A verified status, or `None` if the issuer has no responder.
+    async fn fetch_status(
+        &self,
+        jti: &str,
+        keys: &[PublicKey],
+    ) -> Result<Option<AttestationStatus>, Verdict> {
+        let response = self
+            .get(&format!("status/{}", jti))
+            .await
+            .map_err(Verdict::Fail)?;
+        match response.status() {
+            StatusCode::OK => {}
+            StatusCode::NOT_FOUND => return Ok(None),
+            status => return Err(Verdict::Fail(format!("Answered {}", status))),
+        }
+        let status: StatusResponse = response
+            .json()
+            .await
+            .map_err(|e| Verdict::Fail(format!("Not a status response: {}", e)))?;
+        if status.jti != jti {
+            return Err(Verdict::Fail(format!("Answered for {}", status.jti)));
+        }
+        if !keys
+            .iter()
+            .any(|key| verify_status_response(&status, key).is_ok())
+        {
+            return Err(Verdict::Fail(
+                "Not signed with a published key, or stale".to_string(),
+            ));
+        }
+        Ok(Some(status.status))
+    }
+}
+
+/// Whether the response's content type is `expected`.
+fn is_json(response: &Response, expected: &str) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(';').next().unwrap_or_default().trim() == expected)
+}
+
+/// Check that an error response is a problem document matching its status.
+async fn problem_verdict(response: Response) -> Verdict {
+    let status = response.status();
+    if !is_json(&response, PROBLEM_CONTENT_TYPE) {
+        return Verdict::Fail(format!("Not served as {}", PROBLEM_CONTENT_TYPE));
+    }
+    match response.json::<Problem>().await {
+        Ok(problem) if problem.status != status.as_u16() => Verdict::Fail(format!(
+            "Problem status {} differs from HTTP status {}",
+            problem.status, status
+        )),
+        Ok(problem) if problem.error.is_empty() => {
+            Verdict::Fail("Problem has no error code".to_string())
+        }
+        Ok(_) => Verdict::Pass,
+        Err(e) => Verdict::Fail(format!("Not a problem document: {}", e)),
+    }
+}
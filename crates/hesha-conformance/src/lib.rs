@@ -0,0 +1,188 @@
+//! Protocol conformance suite for Hesha issuers.
+//!
+//! [`run`] checks an issuer against the specification over HTTP: the key
+//! document, attestations (JWT structure, signature, binding proof), error
+//! responses, the revocation list and the optional status responder. The
+//! [`Report`] it returns serializes to JSON, so third-party issuers can
+//! publish it as evidence of compatibility.
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! let report = hesha_conformance::run(&hesha_conformance::Options::new("https://issuer.example.com")).await?;
+//! println!("{}", serde_json::to_string_pretty(&report)?);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Attestation checks need an attestation: one is requested from
+//! `/attest` for [`Options::phone_number`], with
+//! [`Options::bearer_token`] if the issuer requires one, unless
+//! [`Options::attestation`] supplies it.
+
+#![warn(missing_docs)]
+#![forbid(unsafe_code)]
+
+mod checks;
+pub mod report;
+
+pub use report::{CheckResult, Outcome, Report, Summary};
+
+use chrono::Utc;
+use reqwest::Url;
+use std::time::Duration;
+
+/// Phone number attestations are requested for unless another is given.
+pub const DEFAULT_PHONE_NUMBER: &str = "+14155552671";
+
+/// What to check and how.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Base URL of the issuer.
+    pub url: String,
+
+    /// Attestation from the issuer to check, instead of requesting one.
+    pub attestation: Option<String>,
+
+    /// Bearer token for `/attest`.
+    pub bearer_token: Option<String>,
+
+    /// Phone number attestations are requested for.
+    pub phone_number: String,
+
+    /// Scope attestations are requested for.
+    pub scope: String,
+
+    /// Timeout of each request.
+    pub timeout: Duration,
+}
+
+impl Options {
+    /// Defaults for the issuer at `url`.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            attestation: None,
+            bearer_token: None,
+            phone_number: DEFAULT_PHONE_NUMBER.to_string(),
+            scope: "1".to_string(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Run every check against the issuer.
+///
+/// Only an unusable URL is an error; an issuer that can't be reached fails
+/// its checks.
+pub async fn run(options: &Options) -> anyhow::Result<Report> {
+    let mut base = Url::parse(&options.url)
+        .map_err(|e| anyhow::anyhow!("Invalid issuer URL {}: {}", options.url, e))?;
+    if !base.path().ends_with('/') {
+        let path = format!("{}/", base.path());
+        base.set_path(&path);
+    }
+
+    let started_at = Utc::now();
+    let checks = checks::Suite::new(options, base)?.run().await;
+    let mut summary = Summary::default();
+    for check in &checks {
+        match check.outcome {
+            Outcome::Pass => summary.passed += 1,
+            Outcome::Fail => summary.failed += 1,
+            Outcome::Skip => summary.skipped += 1,
+        }
+    }
+    Ok(Report {
+        issuer_url: options.url.clone(),
+        suite_version: env!("CARGO_PKG_VERSION").to_string(),
+        started_at,
+        finished_at: Utc::now(),
+        checks,
+        summary,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hesha_testkit::{Behavior, MockIssuer};
+    use hesha_types::ErrorCode;
+
+    fn failed(report: &Report) -> Vec<&str> {
+        report
+            .checks
+            .iter()
+            .filter(|check| check.outcome == Outcome::Fail)
+            .map(|check| check.id.as_str())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_conforming_issuer() {
+        let issuer = MockIssuer::start().await.unwrap();
+        let report = run(&Options::new(issuer.url())).await.unwrap();
+        assert!(report.passed(), "{}", report.to_text());
+        for id in [
+            "attestation.binding_proof",
+            "revocations.signature",
+            "status.issued",
+        ] {
+            assert_eq!(report.check(id).unwrap().outcome, Outcome::Pass, "{}", id);
+        }
+
+        // Round trip through the JSON report
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<Report>(&json).unwrap(), report);
+    }
+
+    #[tokio::test]
+    async fn test_nonconforming_issuer() {
+        let issuer = MockIssuer::start_with(Behavior {
+            bad_signatures: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let report = run(&Options::new(issuer.url())).await.unwrap();
+        assert_eq!(failed(&report), ["attestation.signature"]);
+        assert_eq!(
+            report.check("attestation.binding_proof").unwrap().outcome,
+            Outcome::Skip
+        );
+
+        issuer.set_behavior(Behavior {
+            fail_with: Some(ErrorCode::Maintenance),
+            ..Default::default()
+        });
+        let report = run(&Options::new(issuer.url())).await.unwrap();
+        assert!(failed(&report).contains(&"discovery.document"));
+        assert!(report.check("attestation.header").unwrap().outcome == Outcome::Skip);
+    }
+
+    #[tokio::test]
+    async fn test_supplied_attestation() {
+        let issuer = MockIssuer::start_with(Behavior {
+            expired_attestations: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let user = hesha_crypto::generate_keypair().unwrap();
+        let phone = hesha_types::PhoneNumber::new(DEFAULT_PHONE_NUMBER).unwrap();
+        let options = Options {
+            attestation: Some(issuer.issue(&phone, &user.public).unwrap()),
+            ..Options::new(issuer.url())
+        };
+        let report = run(&options).await.unwrap();
+        assert_eq!(
+            report.check("attestation.issue").unwrap().outcome,
+            Outcome::Skip
+        );
+        assert_eq!(failed(&report), ["attestation.claims"]);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_url() {
+        assert!(run(&Options::new("not a url")).await.is_err());
+    }
+}
@@ -0,0 +1,81 @@
+//! `hesha-conformance`: check an issuer against the Hesha specification.
+//!
+//! Prints the report as JSON (or text with `--format text`) and exits with
+//! status 1 if any check failed.
+
+use clap::{Parser, ValueEnum};
+use hesha_conformance::{run, Options, DEFAULT_PHONE_NUMBER};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Report formats.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Text,
+}
+
+/// Run the Hesha protocol conformance suite against an issuer.
+#[derive(Debug, Parser)]
+#[command(name = "hesha-conformance", version, about)]
+struct Args {
+    /// Base URL of the issuer.
+    url: String,
+
+    /// Attestation from the issuer to check, instead of requesting one.
+    #[arg(long, env = "HESHA_CONFORMANCE_ATTESTATION")]
+    attestation: Option<String>,
+
+    /// Bearer token for /attest.
+    #[arg(long, env = "HESHA_CONFORMANCE_BEARER_TOKEN")]
+    bearer_token: Option<String>,
+
+    /// Phone number to request an attestation for.
+    #[arg(long, default_value = DEFAULT_PHONE_NUMBER)]
+    phone: String,
+
+    /// Scope to request the attestation for.
+    #[arg(long, default_value = "1")]
+    scope: String,
+
+    /// Timeout of each request, in seconds.
+    #[arg(long, default_value_t = 10)]
+    timeout_secs: u64,
+
+    /// Report format.
+    #[arg(long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+
+    /// Write the report to this file instead of standard output.
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let options = Options {
+        attestation: args.attestation,
+        bearer_token: args.bearer_token,
+        phone_number: args.phone,
+        scope: args.scope,
+        timeout: Duration::from_secs(args.timeout_secs),
+        ..Options::new(args.url)
+    };
+
+    let report = run(&options).await?;
+    let rendered = match args.format {
+        Format::Json => serde_json::to_string_pretty(&report)? + "\n",
+        Format::Text => report.to_text(),
+    };
+    match &args.output {
+        Some(path) => std::fs::write(path, rendered)
+            .map_err(|e| anyhow::anyhow!("Cannot write {}: {}", path.display(), e))?,
+        None => print!("{}", rendered),
+    }
+
+    if !report.passed() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
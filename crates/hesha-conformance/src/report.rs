@@ -0,0 +1,102 @@
+//! Machine-readable conformance report.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+
+/// Result of one check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    /// The issuer behaves as the specification requires.
+    Pass,
+    /// The issuer departs from the specification.
+    Fail,
+    /// The check could not run, e.g. because the issuer requires
+    /// authentication to issue attestations.
+    Skip,
+}
+
+/// One check and its result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckResult {
+    /// Stable identifier, e.g. `discovery.algorithm`.
+    pub id: String,
+
+    /// What the check requires of the issuer.
+    pub title: String,
+
+    /// Its result.
+    pub outcome: Outcome,
+
+    /// Why it failed or was skipped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Counts of check outcomes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Summary {
+    /// Checks passed.
+    pub passed: usize,
+    /// Checks failed.
+    pub failed: usize,
+    /// Checks skipped.
+    pub skipped: usize,
+}
+
+/// Results of a conformance run against one issuer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report {
+    /// Base URL of the issuer.
+    pub issuer_url: String,
+
+    /// Version of the suite that produced the report.
+    pub suite_version: String,
+
+    /// When the run started.
+    pub started_at: DateTime<Utc>,
+
+    /// When the run finished.
+    pub finished_at: DateTime<Utc>,
+
+    /// Every check, in the order run.
+    pub checks: Vec<CheckResult>,
+
+    /// Counts of outcomes.
+    pub summary: Summary,
+}
+
+impl Report {
+    /// Whether no check failed.
+    pub fn passed(&self) -> bool {
+        self.summary.failed == 0
+    }
+
+    /// The result of the check `id`.
+    pub fn check(&self, id: &str) -> Option<&CheckResult> {
+        self.checks.iter().find(|check| check.id == id)
+    }
+
+    /// The report as lines of text for a terminal.
+    pub fn to_text(&self) -> String {
+        let mut text = format!("Conformance of {}\n\n", self.issuer_url);
+        for check in &self.checks {
+            let mark = match check.outcome {
+                Outcome::Pass => "PASS",
+                Outcome::Fail => "FAIL",
+                Outcome::Skip => "SKIP",
+            };
+            let _ = writeln!(text, "{}  {:<32} {}", mark, check.id, check.title);
+            if let Some(detail) = &check.detail {
+                let _ = writeln!(text, "      {}", detail);
+            }
+        }
+        let _ = writeln!(
+            text,
+            "\n{} passed, {} failed, {} skipped",
+            self.summary.passed, self.summary.failed, self.summary.skipped
+        );
+        text
+    }
+}
@@ -0,0 +1,47 @@
+//! Batch verification throughput by thread budget.
+//!
+//! Run with `cargo bench -p hesha-core --features parallel`. Each
+//! attestation costs two Ed25519 verifications, so throughput should
+//! scale close to linearly with threads up to the number of cores.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hesha_core::{create_attestation, BatchVerifier, PhoneNumber, ProxyNumber, VerificationPolicy};
+use hesha_crypto::generate_keypair;
+
+const BATCH: usize = 4096;
+
+fn batch_verify(c: &mut Criterion) {
+    let issuer = generate_keypair().unwrap();
+    let user = generate_keypair().unwrap();
+    let phone = PhoneNumber::new("+14155552671").unwrap();
+    let jwts: Vec<String> = (0..BATCH)
+        .map(|i| {
+            let proxy = ProxyNumber::new(format!("+1001555{:07}", i)).unwrap();
+            create_attestation("issuer.example.com", &issuer.private, &phone, &proxy, &user.public).unwrap()
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("batch_verify");
+    group.throughput(Throughput::Elements(BATCH as u64));
+    group.sample_size(10);
+
+    let sequential = BatchVerifier::new(VerificationPolicy::default());
+    group.bench_function("sequential", |b| {
+        b.iter(|| sequential.verify_with_key(&jwts, &issuer.public))
+    });
+
+    let cores = std::thread::available_parallelism().map_or(1, usize::from);
+    let mut budgets = vec![1, 2, 4, 8];
+    budgets.retain(|&threads| threads < cores);
+    budgets.push(cores);
+    for threads in budgets {
+        let verifier = BatchVerifier::new(VerificationPolicy::default()).with_threads(threads);
+        group.bench_with_input(BenchmarkId::new("threads", threads), &threads, |b, _| {
+            b.iter(|| verifier.verify_with_key(&jwts, &issuer.public))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, batch_verify);
+criterion_main!(benches);
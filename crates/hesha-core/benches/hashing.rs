@@ -0,0 +1,75 @@
+//! SHA-256 on the issuer's hot paths, by hashing backend.
+//!
+//! Compare the three backends on the same machine:
+//!
+//! ```text
+//! cargo bench -p hesha-core --bench hashing --features hesha-crypto/soft-hash -- --save-baseline soft
+//! cargo bench -p hesha-core --bench hashing -- --baseline soft
+//! cargo bench -p hesha-core --bench hashing --features asm-hash -- --baseline soft
+//! ```
+//!
+//! Measured on an Intel(R) Xeon(R) Processor (x86-64 with SHA-NI), median
+//! of criterion's estimate with `--warm-up-time 1 --measurement-time 3`:
+//!
+//! ```text
+//!                          soft-hash     default       asm-hash
+//! sha256/64                657.85 ns     128.47 ns     125.77 ns
+//! sha256/1024              5.99 µs       1.07 µs       0.93 µs
+//! sha256/65536             424.77 µs     59.15 µs      55.81 µs
+//! issuance/phone_hash      705.25 ns     490.98 ns     526.25 ns
+//! issuance/proxy_number    788.81 µs     898.39 µs     808.25 µs
+//! issuance/binding_proof   28.28 µs      25.16 µs      24.42 µs
+//! ```
+//!
+//! The default build already uses SHA-NI when the CPU has it, so on this
+//! host `asm-hash` is within 3–12% of it on the raw hash and within noise
+//! on issuance, where proxy numbers are dominated by compiling the input
+//! validation regexes and binding proofs by the Ed25519 signature. That
+//! is why the feature stays off by default; aarch64 hosts have not been
+//! measured.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hesha_core::{generate_proxy_number, PhoneNumber, ProxyGenerationInput};
+use hesha_crypto::{
+    create_binding_signature, generate_hex_nonce, generate_keypair, hash_phone_number_spec, sha256,
+    CachedSigner,
+};
+
+fn raw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sha256");
+    for size in [64, 1024, 64 * 1024] {
+        let data = vec![0x5a; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &data, |b, data| b.iter(|| sha256(data)));
+    }
+    group.finish();
+}
+
+fn issuance(c: &mut Criterion) {
+    let issuer = generate_keypair().unwrap();
+    let user = generate_keypair().unwrap();
+    let signer = CachedSigner::from(&issuer);
+    let phone = PhoneNumber::new("+14155552671").unwrap();
+    let phone_hash = hash_phone_number_spec(&phone);
+    let input = ProxyGenerationInput {
+        phone_number: phone.as_str().to_string(),
+        user_pubkey: user.public.to_base64(),
+        issuer_domain: "issuer.example.com".to_string(),
+        scope: "1".to_string(),
+        nonce: generate_hex_nonce(),
+    };
+    let proxy_number = generate_proxy_number(&input).unwrap();
+
+    let mut group = c.benchmark_group("issuance");
+    group.bench_function("phone_hash", |b| b.iter(|| hash_phone_number_spec(&phone)));
+    group.bench_function("proxy_number", |b| b.iter(|| generate_proxy_number(&input)));
+    group.bench_function("binding_proof", |b| {
+        b.iter(|| {
+            create_binding_signature(&phone_hash, &input.user_pubkey, proxy_number.as_str(), 1_700_000_000, &signer)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, raw, issuance);
+criterion_main!(benches);
@@ -2,6 +2,7 @@
 
 use hesha_types::{BindingProof, Nonce, PhoneHash, ProxyNumber, PublicKey};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use hex;
 
 /// JWT claims for Hesha attestations.
@@ -36,6 +37,47 @@ pub struct Claims {
     /// Trust domain (optional).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub trust_domain: Option<String>,
+    
+    /// Intended audience (standard JWT claim, optional).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<Audience>,
+    
+    /// Extension claims (optional), e.g. `verification_method`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub ext: BTreeMap<String, serde_json::Value>,
+}
+
+/// The `aud` claim: a single audience or an array of them (RFC 7519 §4.1.3).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Audience {
+    /// One audience, serialized as a string.
+    One(String),
+    /// Several audiences, serialized as an array.
+    Many(Vec<String>),
+}
+
+impl Audience {
+    /// The audiences listed.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        let audiences = match self {
+            Audience::One(audience) => std::slice::from_ref(audience),
+            Audience::Many(audiences) => audiences.as_slice(),
+        };
+        audiences.iter().map(String::as_str)
+    }
+}
+
+impl From<String> for Audience {
+    fn from(audience: String) -> Self {
+        Audience::One(audience)
+    }
+}
+
+impl std::fmt::Display for Audience {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.iter().collect::<Vec<_>>().join(", "))
+    }
 }
 
 impl Claims {
@@ -75,6 +117,7 @@ impl Claims {
             salt: vec![],  // Not stored in JWT
             jti: self.jti.clone(),
             nonce: Nonce::new(""),  // Not stored in JWT
+            ext: self.ext.clone(),
         })
     }
     
@@ -92,6 +135,8 @@ impl Claims {
             // For signatures, we need to reconstruct from the attestation
             // This is a temporary solution - in production, store the full signature
             binding_proof: "sig:placeholder".to_string(),
+            aud: None,
+            ext: attestation.ext.clone(),
         }
     }
 }
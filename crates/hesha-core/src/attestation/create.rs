@@ -1,11 +1,13 @@
 //! Attestation creation logic.
 
-use crate::attestation::claims::Claims;
-use crate::attestation::jwt::encode_jwt;
-use hesha_crypto::{create_binding_signature, generate_nonce, hash_phone_number_spec};
+use crate::attestation::claims::{Audience, Claims};
+use crate::attestation::jwt::encode_jwt_with_key_id;
+use hesha_crypto::{create_binding_signature, generate_nonce, hash_phone_number_spec, MessageSigner};
 use hesha_types::{
-    Attestation, HeshaResult, PhoneNumber, PrivateKey, ProxyNumber, PublicKey,
+    Attestation, HeshaResult, PhoneNumber, PrivateKey, ProxyNumber, PublicKey, VerificationMethod,
+    VERIFICATION_METHOD_EXT,
 };
+use std::collections::BTreeMap;
 use chrono::{Duration, Utc};
 use uuid::Uuid;
 use hex;
@@ -15,18 +17,24 @@ use base64::{Engine as _, engine::general_purpose};
 pub struct AttestationBuilder<'a> {
     issuer_domain: String,
     trust_domain: Option<String>,
-    issuer_private_key: &'a PrivateKey,
+    audience: Option<String>,
+    key_id: Option<String>,
+    issuer_signer: &'a dyn MessageSigner,
     phone_number: PhoneNumber,
     proxy_number: ProxyNumber,
     user_pubkey: PublicKey,
     validity_days: i64,
+    ext: BTreeMap<String, serde_json::Value>,
 }
 
 impl<'a> AttestationBuilder<'a> {
     /// Create a new attestation builder.
+    ///
+    /// `issuer_signer` is usually the issuer's [`PrivateKey`]; long-running
+    /// issuers pass a [`hesha_crypto::CachedSigner`] instead.
     pub fn new(
         issuer_domain: String,
-        issuer_private_key: &'a PrivateKey,
+        issuer_signer: &'a dyn MessageSigner,
         phone_number: PhoneNumber,
         proxy_number: ProxyNumber,
         user_pubkey: PublicKey,
@@ -34,11 +42,14 @@ impl<'a> AttestationBuilder<'a> {
         Self {
             issuer_domain,
             trust_domain: None,
-            issuer_private_key,
+            audience: None,
+            key_id: None,
+            issuer_signer,
             phone_number,
             proxy_number,
             user_pubkey,
             validity_days: 30, // Default 30 days
+            ext: BTreeMap::new(),
         }
     }
     
@@ -54,6 +65,29 @@ impl<'a> AttestationBuilder<'a> {
         self
     }
     
+    /// Set the intended audience (`aud` claim) of the JWT.
+    pub fn audience(mut self, audience: String) -> Self {
+        self.audience = Some(audience);
+        self
+    }
+    
+    /// Name the signing key in the JWT `kid` header.
+    pub fn key_id(mut self, key_id: String) -> Self {
+        self.key_id = Some(key_id);
+        self
+    }
+    
+    /// Add an extension claim.
+    pub fn ext(mut self, name: impl Into<String>, value: serde_json::Value) -> Self {
+        self.ext.insert(name.into(), value);
+        self
+    }
+    
+    /// Record how the phone number was verified.
+    pub fn verification_method(self, method: VerificationMethod) -> Self {
+        self.ext(VERIFICATION_METHOD_EXT, method.as_str().into())
+    }
+    
     /// Build the attestation.
     pub fn build(self) -> HeshaResult<Attestation> {
         let now = Utc::now();
@@ -68,7 +102,7 @@ impl<'a> AttestationBuilder<'a> {
             &self.user_pubkey.to_base64(),
             self.proxy_number.as_str(),
             iat,
-            self.issuer_private_key,
+            self.issuer_signer,
         )?;
         
         // Parse hash from "sha256:..." format
@@ -109,13 +143,16 @@ impl<'a> AttestationBuilder<'a> {
             salt: vec![],  // Not used in spec-compliant version
             jti: Uuid::new_v4().to_string(),
             nonce: generate_nonce(),  // Not included in JWT per spec
+            ext: self.ext,
         })
     }
     
     /// Build the attestation and encode as JWT.
     pub fn build_jwt(self) -> HeshaResult<String> {
         // Store reference to issuer key before consuming self
-        let issuer_key = self.issuer_private_key;
+        let issuer_key = self.issuer_signer;
+        let audience = self.audience.clone();
+        let key_id = self.key_id.clone();
         let attestation = self.build()?;
         
         // Create binding signature for JWT
@@ -130,9 +167,10 @@ impl<'a> AttestationBuilder<'a> {
         // Convert to JWT claims with binding signature
         let mut claims = Claims::from_attestation(&attestation);
         claims.binding_proof = binding_signature;
+        claims.aud = audience.map(Audience::from);
         
         // Encode with our Ed25519 JWT implementation
-        encode_jwt(&claims, issuer_key)
+        encode_jwt_with_key_id(&claims, issuer_key, key_id.as_deref())
     }
 }
 
@@ -249,4 +287,32 @@ mod tests {
         assert_eq!(attestation.trust_domain, Some("example.com".to_string()));
         assert_eq!(attestation.effective_trust_domain(), "example.com");
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_verification_method_claim() {
+        let issuer_key = generate_keypair().unwrap();
+        let user_key = generate_keypair().unwrap();
+        
+        let jwt = AttestationBuilder::new(
+            "issuer.com".to_string(),
+            &issuer_key.private,
+            PhoneNumber::new("+1234567890").unwrap(),
+            ProxyNumber::new("+23400123456789").unwrap(),
+            user_key.public.clone(),
+        )
+        .verification_method(VerificationMethod::Email)
+        .build_jwt().unwrap();
+        
+        let attestation = crate::attestation::parse::parse_attestation(&jwt).unwrap();
+        assert_eq!(attestation.verification_method(), Some(VerificationMethod::Email));
+        
+        let plain = create_attestation(
+            "issuer.com",
+            &issuer_key.private,
+            &PhoneNumber::new("+1234567890").unwrap(),
+            &ProxyNumber::new("+23400123456789").unwrap(),
+            &user_key.public,
+        ).unwrap();
+        assert_eq!(crate::attestation::parse::parse_attestation(&plain).unwrap().verification_method(), None);
+    }
+}
@@ -1,8 +1,8 @@
 //! Simple JWT implementation for Ed25519.
 
 use base64::{Engine as _, engine::general_purpose};
-use hesha_crypto::{sign_message, verify_signature};
-use hesha_types::{HeshaError, HeshaResult, PrivateKey, PublicKey, Signature};
+use hesha_crypto::{verify_signature, MessageSigner};
+use hesha_types::{HeshaError, HeshaResult, PublicKey, Signature};
 use serde::{Deserialize, Serialize};
 
 /// JWT header for Ed25519.
@@ -10,6 +10,8 @@ use serde::{Deserialize, Serialize};
 struct Header {
     alg: String,
     typ: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
 }
 
 impl Default for Header {
@@ -17,6 +19,7 @@ impl Default for Header {
         Header {
             alg: "EdDSA".to_string(),
             typ: "JWT".to_string(),
+            kid: None,
         }
     }
 }
@@ -24,10 +27,23 @@ impl Default for Header {
 /// Encode a JWT with Ed25519 signature.
 pub fn encode_jwt<T: Serialize>(
     claims: &T,
-    private_key: &PrivateKey,
+    signer: &(impl MessageSigner + ?Sized),
+) -> HeshaResult<String> {
+    encode_jwt_with_key_id(claims, signer, None)
+}
+
+/// Encode a JWT with Ed25519 signature, naming the signing key in the
+/// `kid` header so verifiers can pick it from the issuer's published keys.
+pub fn encode_jwt_with_key_id<T: Serialize>(
+    claims: &T,
+    signer: &(impl MessageSigner + ?Sized),
+    key_id: Option<&str>,
 ) -> HeshaResult<String> {
     // Create header
-    let header = Header::default();
+    let header = Header {
+        kid: key_id.map(str::to_string),
+        ..Header::default()
+    };
     let header_json = serde_json::to_string(&header)
         .map_err(|e| HeshaError::SerializationError(e.to_string()))?;
     let header_b64 = general_purpose::URL_SAFE_NO_PAD.encode(header_json);
@@ -41,7 +57,7 @@ pub fn encode_jwt<T: Serialize>(
     let message = format!("{}.{}", header_b64, claims_b64);
     
     // Sign with Ed25519
-    let signature = sign_message(private_key, message.as_bytes())?;
+    let signature = signer.sign_message(message.as_bytes())?;
     let signature_b64 = general_purpose::URL_SAFE_NO_PAD.encode(signature.as_bytes());
     
     // Combine into JWT
@@ -106,6 +122,21 @@ pub fn decode_jwt_unverified<T: for<'de> Deserialize<'de>>(
         .map_err(|e| HeshaError::InvalidAttestation(format!("Invalid claims: {}", e)))
 }
 
+/// Read the `kid` header of a JWT without verification.
+pub fn decode_key_id_unverified(jwt: &str) -> HeshaResult<Option<String>> {
+    let header = decode_header_unverified(jwt)?;
+    Ok(header.get("kid").and_then(|kid| kid.as_str()).map(str::to_string))
+}
+
+/// Decode the JWT header without verification (for inspection).
+pub fn decode_header_unverified(jwt: &str) -> HeshaResult<serde_json::Value> {
+    let header_b64 = jwt.split('.').next().unwrap_or_default();
+    let header_json = general_purpose::URL_SAFE_NO_PAD.decode(header_b64)
+        .map_err(|_| HeshaError::InvalidAttestation("Invalid header encoding".to_string()))?;
+    serde_json::from_slice(&header_json)
+        .map_err(|e| HeshaError::InvalidAttestation(format!("Invalid header: {}", e)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -150,5 +181,24 @@ mod tests {
         let jwt = encode_jwt(&claims, &keypair.private).unwrap();
         let decoded: TestClaims = decode_jwt_unverified(&jwt).unwrap();
         assert_eq!(decoded, claims);
+        
+        let header = decode_header_unverified(&jwt).unwrap();
+        assert_eq!(header["alg"], "EdDSA");
+        assert_eq!(header["typ"], "JWT");
+        assert!(header.get("kid").is_none());
+    }
+    
+    #[test]
+    fn test_key_id_header() {
+        let keypair = generate_keypair().unwrap();
+        let claims = TestClaims {
+            sub: "test".to_string(),
+            exp: 1234567890,
+        };
+        
+        let jwt = encode_jwt_with_key_id(&claims, &keypair.private, Some("key-2024-01")).unwrap();
+        assert_eq!(decode_key_id_unverified(&jwt).unwrap().as_deref(), Some("key-2024-01"));
+        let decoded: TestClaims = decode_jwt(&jwt, &keypair.public).unwrap();
+        assert_eq!(decoded, claims);
     }
 }
\ No newline at end of file
@@ -2,7 +2,8 @@
 
 use crate::attestation::claims::Claims;
 use crate::attestation::jwt::decode_jwt_unverified;
-use hesha_types::{Attestation, HeshaError, HeshaResult};
+use crate::verification::VerificationPolicy;
+use hesha_types::{Attestation, HeshaResult};
 
 /// Parse a JWT attestation without verifying the signature.
 /// 
@@ -20,43 +21,11 @@ pub fn parse_attestation(jwt: &str) -> HeshaResult<Attestation> {
 /// - All required fields are present
 /// - Proxy number format is valid
 /// - Timestamps are reasonable
+/// 
+/// Uses the default [`VerificationPolicy`]; call
+/// [`VerificationPolicy::validate`] to apply stricter rules.
 pub fn validate_attestation(attestation: &Attestation) -> HeshaResult<()> {
-    // Check expiry
-    if attestation.is_expired() {
-        return Err(HeshaError::AttestationExpired(attestation.exp));
-    }
-    
-    // Check issued time is not in future
-    let now = chrono::Utc::now();
-    if attestation.iat > now {
-        return Err(HeshaError::InvalidAttestation(
-            "Attestation issued in the future".to_string()
-        ));
-    }
-    
-    // Check validity period is reasonable (not more than 1 year)
-    let max_validity = chrono::Duration::days(365);
-    if attestation.exp - attestation.iat > max_validity {
-        return Err(HeshaError::InvalidAttestation(
-            "Attestation validity period too long".to_string()
-        ));
-    }
-    
-    // Validate issuer domain format
-    if attestation.iss.is_empty() {
-        return Err(HeshaError::InvalidAttestation(
-            "Empty issuer domain".to_string()
-        ));
-    }
-    
-    // Allow localhost for testing, otherwise require a proper domain
-    if !attestation.iss.starts_with("localhost") && !attestation.iss.contains('.') {
-        return Err(HeshaError::InvalidAttestation(
-            "Invalid issuer domain".to_string()
-        ));
-    }
-    
-    Ok(())
+    VerificationPolicy::default().validate(attestation)
 }
 
 /// Parse a JWT attestation without verifying the signature.
@@ -133,6 +102,7 @@ mod tests {
             salt: vec![0u8; 16],
             jti: "test".to_string(),
             nonce: Nonce::new("test"),
+            ext: Default::default(),
         };
         
         assert!(validate_attestation(&expired_attestation).is_err());
@@ -0,0 +1,348 @@
+//! Encrypted backups of an issuer.
+//!
+//! A backup bundles the files of an issuer directory (`config/` and
+//! `keys/`) with whatever the running node exported, such as its records
+//! and audit log. Every file carries a SHA-256 digest that is checked on
+//! restore, and the whole bundle is sealed with a passphrase, so the archive
+//! can be kept off the host without exposing the signing keys.
+//!
+//! # Security Considerations
+//! - Private keys are restored readable by their owner only
+//! - Restoring refuses to replace existing files unless asked to
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use hesha_crypto::{decrypt_data, encrypt_data, sha256, EncryptedData, KdfParams};
+use hesha_types::{HeshaError, HeshaResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Current version of the backup format.
+pub const BACKUP_VERSION: u32 = 1;
+
+/// Marker identifying an encrypted backup file.
+pub const BACKUP_FORMAT: &str = "hesha-backup";
+
+/// Archive path of the node's records (attestations, proxy numbers and
+/// revocations), restored through the node rather than written to disk.
+pub const RECORDS_FILE: &str = "records.json";
+
+/// Archive path of the node's audit log, as JSON lines.
+pub const AUDIT_FILE: &str = "audit.jsonl";
+
+/// Issuer directory subdirectories included in a backup.
+const ISSUER_DIRS: &[&str] = &["config", "keys"];
+
+/// The decrypted contents of a backup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backup {
+    /// Format version.
+    pub version: u32,
+    /// When the backup was made.
+    pub created_at: DateTime<Utc>,
+    /// Archived files.
+    pub files: Vec<BackupFile>,
+}
+
+/// A file in a backup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupFile {
+    /// Path relative to the issuer directory, with `/` separators.
+    pub path: String,
+    /// SHA-256 of the contents (hex).
+    pub sha256: String,
+    /// Contents (base64url).
+    pub data: String,
+}
+
+/// A backup as written to disk.
+///
+/// Only the format marker and creation time are readable without the
+/// passphrase.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedBackup {
+    /// Always [`BACKUP_FORMAT`].
+    pub format: String,
+    /// When the backup was made.
+    pub created_at: DateTime<Utc>,
+    /// The sealed [`Backup`].
+    pub sealed: EncryptedData,
+}
+
+impl Backup {
+    /// Start an empty backup.
+    pub fn new() -> Self {
+        Self {
+            version: BACKUP_VERSION,
+            created_at: Utc::now(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Back up the configuration and keys of an issuer directory.
+    pub fn from_issuer_dir(base_dir: impl AsRef<Path>) -> HeshaResult<Self> {
+        let base_dir = base_dir.as_ref();
+        if !base_dir.join("config").join("issuer.toml").is_file() {
+            return Err(HeshaError::ConfigError(format!(
+                "{} has no config/issuer.toml",
+                base_dir.display()
+            )));
+        }
+
+        let mut backup = Self::new();
+        for dir in ISSUER_DIRS {
+            backup.add_dir(base_dir, &base_dir.join(dir))?;
+        }
+        Ok(backup)
+    }
+
+    fn add_dir(&mut self, base_dir: &Path, dir: &Path) -> HeshaResult<()> {
+        if !dir.is_dir() {
+            return Ok(());
+        }
+        let mut entries = fs::read_dir(dir)
+            .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| HeshaError::ConfigError(format!("Failed to read {}: {}", dir.display(), e)))?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            if path.is_dir() {
+                self.add_dir(base_dir, &path)?;
+                continue;
+            }
+            let data = fs::read(&path)
+                .map_err(|e| HeshaError::ConfigError(format!("Failed to read {}: {}", path.display(), e)))?;
+            let relative = path
+                .strip_prefix(base_dir)
+                .map_err(|_| HeshaError::ConfigError(format!("{} is outside the issuer directory", path.display())))?;
+            let relative: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+            self.add_file(&relative.join("/"), &data);
+        }
+        Ok(())
+    }
+
+    /// Add a file, replacing any earlier file with the same path.
+    pub fn add_file(&mut self, path: &str, data: &[u8]) {
+        self.files.retain(|file| file.path != path);
+        self.files.push(BackupFile {
+            path: path.to_string(),
+            sha256: hex::encode(sha256(data)),
+            data: general_purpose::URL_SAFE_NO_PAD.encode(data),
+        });
+    }
+
+    /// The contents of a file, checked against its digest.
+    pub fn file(&self, path: &str) -> HeshaResult<Option<Vec<u8>>> {
+        self.files.iter().find(|file| file.path == path).map(BackupFile::contents).transpose()
+    }
+
+    /// Check the format version, paths and digests of every file.
+    pub fn verify(&self) -> HeshaResult<()> {
+        if self.version != BACKUP_VERSION {
+            return Err(HeshaError::ConfigError(format!("Unsupported backup version: {}", self.version)));
+        }
+        let mut seen = HashSet::new();
+        for file in &self.files {
+            if !is_safe_path(&file.path) {
+                return Err(HeshaError::ConfigError(format!("Unsafe path in backup: {}", file.path)));
+            }
+            if !seen.insert(file.path.as_str()) {
+                return Err(HeshaError::ConfigError(format!("Duplicate file in backup: {}", file.path)));
+            }
+            file.contents()?;
+        }
+        Ok(())
+    }
+
+    /// Encrypt the backup with a passphrase.
+    pub fn seal(&self, passphrase: &str) -> HeshaResult<EncryptedBackup> {
+        self.seal_with_params(passphrase, KdfParams::default())
+    }
+
+    /// Encrypt the backup with explicit KDF parameters.
+    pub fn seal_with_params(&self, passphrase: &str, params: KdfParams) -> HeshaResult<EncryptedBackup> {
+        let plaintext = serde_json::to_vec(self)?;
+        Ok(EncryptedBackup {
+            format: BACKUP_FORMAT.to_string(),
+            created_at: self.created_at,
+            sealed: encrypt_data(&plaintext, passphrase, params)?,
+        })
+    }
+
+    /// Write the archived files (all but [`RECORDS_FILE`]) into an issuer
+    /// directory, returning the paths written.
+    ///
+    /// Nothing is written if any target exists and `overwrite` is false.
+    pub fn restore_files(&self, base_dir: impl AsRef<Path>, overwrite: bool) -> HeshaResult<Vec<PathBuf>> {
+        let base_dir = base_dir.as_ref();
+        self.verify()?;
+
+        let files: Vec<_> = self.files.iter().filter(|file| file.path != RECORDS_FILE).collect();
+        if !overwrite {
+            if let Some(existing) = files.iter().map(|file| base_dir.join(&file.path)).find(|path| path.exists()) {
+                return Err(HeshaError::ConfigError(format!(
+                    "{} already exists",
+                    existing.display()
+                )));
+            }
+        }
+
+        let mut written = Vec::new();
+        for file in files {
+            let path = base_dir.join(&file.path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| HeshaError::ConfigError(format!("Failed to create {}: {}", parent.display(), e)))?;
+            }
+            write_file(&path, &file.contents()?, is_private_key(&file.path))?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+}
+
+impl Default for Backup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackupFile {
+    /// The decoded contents, checked against the digest.
+    pub fn contents(&self) -> HeshaResult<Vec<u8>> {
+        let data = general_purpose::URL_SAFE_NO_PAD
+            .decode(&self.data)
+            .map_err(|_| HeshaError::ConfigError(format!("Invalid encoding of {} in backup", self.path)))?;
+        if hex::encode(sha256(&data)) != self.sha256 {
+            return Err(HeshaError::ConfigError(format!("{} in backup does not match its digest", self.path)));
+        }
+        Ok(data)
+    }
+}
+
+impl EncryptedBackup {
+    /// Parse an encrypted backup file.
+    pub fn from_json(data: &str) -> HeshaResult<Self> {
+        let backup: EncryptedBackup = serde_json::from_str(data)
+            .map_err(|_| HeshaError::ConfigError("Not a Hesha backup file".to_string()))?;
+        if backup.format != BACKUP_FORMAT {
+            return Err(HeshaError::ConfigError(format!("Unknown backup format: {}", backup.format)));
+        }
+        Ok(backup)
+    }
+
+    /// Serialize to pretty-printed JSON.
+    pub fn to_json(&self) -> HeshaResult<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Decrypt the backup and verify every file.
+    ///
+    /// Fails if the passphrase is wrong or the archive was altered.
+    pub fn open(&self, passphrase: &str) -> HeshaResult<Backup> {
+        let plaintext = decrypt_data(&self.sealed, passphrase)?;
+        let backup: Backup = serde_json::from_slice(&plaintext)?;
+        if backup.created_at != self.created_at {
+            return Err(HeshaError::ConfigError("Backup creation time was altered".to_string()));
+        }
+        backup.verify()?;
+        Ok(backup)
+    }
+}
+
+/// Whether an archive path stays inside the directory it is restored to.
+fn is_safe_path(path: &str) -> bool {
+    !path.is_empty()
+        && !path.contains('\\')
+        && Path::new(path).components().all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Private keys are `keys/private*.key`, current and archived.
+fn is_private_key(path: &str) -> bool {
+    path.strip_prefix("keys/").is_some_and(|name| name.starts_with("private"))
+}
+
+fn write_file(path: &Path, data: &[u8], private: bool) -> HeshaResult<()> {
+    fs::write(path, data)
+        .map_err(|e| HeshaError::ConfigError(format!("Failed to write {}: {}", path.display(), e)))?;
+
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| HeshaError::ConfigError(format!("Failed to set key permissions: {}", e)))?;
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IssuerSetupBuilder;
+
+    // Cheap parameters keep the tests fast
+    const TEST_PARAMS: KdfParams = KdfParams { m_cost: 64, t_cost: 1, p_cost: 1 };
+
+    #[test]
+    fn test_backup_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        IssuerSetupBuilder::new()
+            .name("Test Issuer")
+            .trust_domain("issuer.example.com")
+            .contact_email("admin@example.com")
+            .build()
+            .unwrap()
+            .save(dir.path())
+            .unwrap();
+
+        let mut backup = Backup::from_issuer_dir(dir.path()).unwrap();
+        backup.add_file(RECORDS_FILE, b"{\"attestations\":[]}");
+        let paths: Vec<_> = backup.files.iter().map(|file| file.path.as_str()).collect();
+        assert!(paths.contains(&"config/issuer.toml"));
+        assert!(paths.contains(&"keys/private.key"));
+
+        let json = backup.seal_with_params("correct horse", TEST_PARAMS).unwrap().to_json().unwrap();
+        let private_key = fs::read_to_string(dir.path().join("keys/private.key")).unwrap();
+        assert!(!json.contains(&private_key));
+        let sealed = EncryptedBackup::from_json(&json).unwrap();
+        assert!(sealed.open("battery staple").is_err());
+        let opened = sealed.open("correct horse").unwrap();
+        assert_eq!(opened.file(RECORDS_FILE).unwrap().unwrap(), b"{\"attestations\":[]}");
+
+        // Existing files are kept unless overwriting is asked for
+        assert!(opened.restore_files(dir.path(), false).is_err());
+        let target = tempfile::tempdir().unwrap();
+        let written = opened.restore_files(target.path(), false).unwrap();
+        assert_eq!(written.len(), paths.len() - 1);
+        assert!(!target.path().join(RECORDS_FILE).exists());
+        assert_eq!(fs::read_to_string(target.path().join("keys/private.key")).unwrap(), private_key);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(target.path().join("keys/private.key")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert!(opened.restore_files(target.path(), true).is_ok());
+    }
+
+    #[test]
+    fn test_tampered_backup() {
+        let mut backup = Backup::new();
+        backup.add_file("config/issuer.toml", b"name = 'a'");
+        backup.files[0].data = general_purpose::URL_SAFE_NO_PAD.encode(b"name = 'b'");
+        assert!(backup.verify().is_err());
+
+        for path in ["../issuer.toml", "/etc/passwd", "config/../../x", "keys\\..\\x", ""] {
+            let mut backup = Backup::new();
+            backup.add_file(path, b"x");
+            assert!(backup.verify().is_err(), "{}", path);
+        }
+        assert!(EncryptedBackup::from_json("{\"format\":\"other\"}").is_err());
+    }
+}
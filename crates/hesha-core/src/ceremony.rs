@@ -0,0 +1,358 @@
+//! Issuer key ceremonies.
+//!
+//! Issuers with formal key-management requirements generate their signing
+//! key in front of several custodians, none of whom may hold it alone. A
+//! [`KeyCeremony`] generates the key in memory, splits it with Shamir
+//! secret sharing so that any `threshold` custodians can recover it, and
+//! checks before returning that every share takes part in a successful
+//! recovery and that fewer than `threshold` shares don't recover it.
+//!
+//! The key itself is never returned. What comes out is one
+//! [`CustodianShare`] per custodian and a [`CeremonyTranscript`]: the
+//! public key, who took part, a digest of each share and the recoveries
+//! checked, signed with the new key to show the shares recover it. The
+//! key is put back together with [`recover_key`] on the host that will
+//! sign with it.
+
+use chrono::{DateTime, Utc};
+use hesha_crypto::{combine_shares, generate_keypair, keypair_from_private, split_secret, SecretShare};
+use hesha_types::{HeshaError, HeshaResult, KeyPair, PrivateKey, PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Format version of ceremony transcripts.
+pub const TRANSCRIPT_VERSION: u32 = 1;
+
+/// A custodian's share of a ceremony key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustodianShare {
+    /// Ceremony the share came from.
+    pub ceremony_id: String,
+    /// Custodian holding the share.
+    pub custodian: String,
+    /// Shares needed to recover the key.
+    pub threshold: u8,
+    /// Shares made.
+    pub total: u8,
+    /// Public key of the shared key.
+    pub public_key: PublicKey,
+    /// The share.
+    pub share: SecretShare,
+}
+
+/// A custodian as recorded in the transcript.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranscriptCustodian {
+    /// Custodian name.
+    pub name: String,
+    /// Index of the custodian's share.
+    pub index: u8,
+    /// SHA-256 of the share (hex), see [`SecretShare::digest`].
+    pub share_digest: String,
+}
+
+/// Signed record of a key ceremony.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CeremonyTranscript {
+    /// Format version, [`TRANSCRIPT_VERSION`].
+    pub version: u32,
+    /// Unique ceremony ID.
+    pub ceremony_id: String,
+    /// Key ID the issuer will publish the key under.
+    pub key_id: String,
+    /// Public key generated.
+    pub public_key: PublicKey,
+    /// Shares needed to recover the key.
+    pub threshold: u8,
+    /// Custodians, in share order.
+    pub custodians: Vec<TranscriptCustodian>,
+    /// Witnesses present who hold no share.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub witnesses: Vec<String>,
+    /// Share indices of each recovery checked during the ceremony.
+    pub recoveries_checked: Vec<Vec<u8>>,
+    /// When the ceremony started.
+    pub started_at: DateTime<Utc>,
+    /// When the shares were checked and the transcript signed.
+    pub completed_at: DateTime<Utc>,
+    /// Signature over [`signing_payload`](Self::signing_payload) by the
+    /// generated key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Signature>,
+}
+
+impl CeremonyTranscript {
+    /// Bytes signed: the transcript without its signature, as JSON with
+    /// sorted keys.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(object) = value.as_object_mut() {
+            object.remove("signature");
+        }
+        serde_json::to_vec(&value).unwrap_or_default()
+    }
+
+    /// Check the transcript's signature by the key it records.
+    pub fn verify(&self) -> HeshaResult<()> {
+        if self.version != TRANSCRIPT_VERSION {
+            return Err(HeshaError::ConfigError(format!("Unsupported transcript version {}", self.version)));
+        }
+        let signature = self.signature.as_ref().ok_or(HeshaError::InvalidSignature)?;
+        if !hesha_crypto::verify_signature(&self.public_key, &self.signing_payload(), signature) {
+            return Err(HeshaError::InvalidSignature);
+        }
+        Ok(())
+    }
+
+    /// Check that `share` is one this ceremony handed out, unaltered.
+    pub fn verify_share(&self, share: &CustodianShare) -> HeshaResult<()> {
+        if share.ceremony_id != self.ceremony_id {
+            return Err(HeshaError::ConfigError(format!(
+                "Share is from ceremony {}, not {}",
+                share.ceremony_id, self.ceremony_id
+            )));
+        }
+        let digest = hex::encode(share.share.digest());
+        let listed = self.custodians.iter().any(|custodian| {
+            custodian.name == share.custodian
+                && custodian.index == share.share.index
+                && hesha_crypto::constant_time_compare(custodian.share_digest.as_bytes(), digest.as_bytes())
+        });
+        if !listed {
+            return Err(HeshaError::ConfigError(format!(
+                "Share {} of {} does not match the transcript",
+                share.share.index, share.custodian
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// What a ceremony hands out.
+#[derive(Debug)]
+pub struct CeremonyOutcome {
+    /// The signed transcript.
+    pub transcript: CeremonyTranscript,
+    /// One share per custodian, in the transcript's order.
+    pub shares: Vec<CustodianShare>,
+}
+
+/// A key generation split across custodians.
+pub struct KeyCeremony {
+    key_id: String,
+    custodians: Vec<String>,
+    threshold: u8,
+    witnesses: Vec<String>,
+}
+
+impl KeyCeremony {
+    /// A ceremony sharing the key among `custodians`, any `threshold` of
+    /// whom can recover it.
+    pub fn new(custodians: Vec<String>, threshold: u8) -> Self {
+        Self {
+            key_id: "default".to_string(),
+            custodians,
+            threshold,
+            witnesses: Vec::new(),
+        }
+    }
+
+    /// Record the key ID the issuer will publish the key under.
+    pub fn with_key_id(mut self, key_id: impl Into<String>) -> Self {
+        self.key_id = key_id.into();
+        self
+    }
+
+    /// Record witnesses who hold no share.
+    pub fn with_witnesses(mut self, witnesses: Vec<String>) -> Self {
+        self.witnesses = witnesses;
+        self
+    }
+
+    /// Generate and share the key, check the shares and sign the
+    /// transcript.
+    pub fn run(self) -> HeshaResult<CeremonyOutcome> {
+        let started_at = Utc::now();
+        let total = u8::try_from(self.custodians.len())
+            .map_err(|_| HeshaError::ConfigError("At most 255 custodians can hold shares".into()))?;
+        for (i, name) in self.custodians.iter().enumerate() {
+            if name.trim().is_empty() {
+                return Err(HeshaError::ConfigError("Custodian names can't be empty".into()));
+            }
+            if self.custodians[..i].contains(name) {
+                return Err(HeshaError::ConfigError(format!("Custodian {} is listed twice", name)));
+            }
+        }
+
+        let keypair = generate_keypair()?;
+        let shares = split_secret(keypair.private.as_bytes(), self.threshold, total)?;
+        let recoveries_checked = check_recovery(&keypair, &shares, self.threshold)?;
+
+        let ceremony_id = Uuid::new_v4().to_string();
+        let mut transcript = CeremonyTranscript {
+            version: TRANSCRIPT_VERSION,
+            ceremony_id: ceremony_id.clone(),
+            key_id: self.key_id,
+            public_key: keypair.public.clone(),
+            threshold: self.threshold,
+            custodians: self
+                .custodians
+                .iter()
+                .zip(&shares)
+                .map(|(name, share)| TranscriptCustodian {
+                    name: name.clone(),
+                    index: share.index,
+                    share_digest: hex::encode(share.digest()),
+                })
+                .collect(),
+            witnesses: self.witnesses,
+            recoveries_checked,
+            started_at,
+            completed_at: Utc::now(),
+            signature: None,
+        };
+        transcript.signature = Some(hesha_crypto::sign_message(&keypair.private, &transcript.signing_payload())?);
+
+        let shares = self
+            .custodians
+            .into_iter()
+            .zip(shares)
+            .map(|(custodian, share)| CustodianShare {
+                ceremony_id: ceremony_id.clone(),
+                custodian,
+                threshold: self.threshold,
+                total,
+                public_key: keypair.public.clone(),
+                share,
+            })
+            .collect();
+        Ok(CeremonyOutcome { transcript, shares })
+    }
+}
+
+/// Check that every share takes part in a recovery of the key, and that a
+/// share short of the threshold doesn't recover it. Returns the share
+/// indices of each recovery checked.
+fn check_recovery(keypair: &KeyPair, shares: &[SecretShare], threshold: u8) -> HeshaResult<Vec<Vec<u8>>> {
+    let threshold = usize::from(threshold);
+    let recovers = |subset: &[SecretShare]| -> HeshaResult<bool> {
+        Ok(recover_public_key(subset)? == keypair.public)
+    };
+
+    // Windows of `threshold` consecutive shares, wrapping around, so each
+    // share is in `threshold` recoveries
+    let mut checked = Vec::new();
+    for start in 0..shares.len() {
+        let subset: Vec<SecretShare> =
+            (0..threshold).map(|offset| shares[(start + offset) % shares.len()].clone()).collect();
+        if !recovers(&subset)? {
+            return Err(HeshaError::CryptoError("Shares failed to recover the key".into()));
+        }
+        checked.push(subset.iter().map(|share| share.index).collect());
+        if threshold == shares.len() {
+            break;
+        }
+    }
+    if recovers(&shares[..threshold - 1])? {
+        return Err(HeshaError::CryptoError("Fewer shares than the threshold recovered the key".into()));
+    }
+    Ok(checked)
+}
+
+/// Public key of the private key `shares` combine to.
+fn recover_public_key(shares: &[SecretShare]) -> HeshaResult<PublicKey> {
+    Ok(recover_keypair(shares)?.public)
+}
+
+fn recover_keypair(shares: &[SecretShare]) -> HeshaResult<KeyPair> {
+    let mut secret = combine_shares(shares)?;
+    let bytes: Result<[u8; 32], _> = secret.as_slice().try_into();
+    secret.iter_mut().for_each(|byte| *byte = 0);
+    let bytes = bytes.map_err(|_| HeshaError::CryptoError("Shares are not of an Ed25519 key".into()))?;
+    keypair_from_private(&PrivateKey::from_bytes(bytes))
+}
+
+/// Put a ceremony key back together from its custodians' shares.
+///
+/// Each share is checked against the transcript, and the key recovered
+/// against the transcript's public key.
+pub fn recover_key(transcript: &CeremonyTranscript, shares: &[CustodianShare]) -> HeshaResult<KeyPair> {
+    transcript.verify()?;
+    for share in shares {
+        transcript.verify_share(share)?;
+    }
+    if shares.len() < usize::from(transcript.threshold) {
+        return Err(HeshaError::ConfigError(format!(
+            "{} shares are needed to recover the key, {} given",
+            transcript.threshold,
+            shares.len()
+        )));
+    }
+    let shares: Vec<SecretShare> = shares.iter().map(|share| share.share.clone()).collect();
+    let keypair = recover_keypair(&shares)?;
+    if keypair.public != transcript.public_key {
+        return Err(HeshaError::CryptoError("Shares recovered a different key".into()));
+    }
+    Ok(keypair)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custodians() -> Vec<String> {
+        ["alice", "bob", "carol", "dave"].map(String::from).to_vec()
+    }
+
+    #[test]
+    fn test_ceremony_and_recovery() {
+        let outcome = KeyCeremony::new(custodians(), 3)
+            .with_key_id("2026-q4")
+            .with_witnesses(vec!["auditor".to_string()])
+            .run()
+            .unwrap();
+        let transcript = &outcome.transcript;
+        transcript.verify().unwrap();
+        assert_eq!(transcript.custodians.len(), 4);
+        assert_eq!(transcript.recoveries_checked.len(), 4);
+        for share in &outcome.shares {
+            transcript.verify_share(share).unwrap();
+        }
+
+        // Survives being written out and read back
+        let json = serde_json::to_string(transcript).unwrap();
+        let transcript: CeremonyTranscript = serde_json::from_str(&json).unwrap();
+        transcript.verify().unwrap();
+
+        let keypair = recover_key(&transcript, &outcome.shares[1..]).unwrap();
+        assert_eq!(keypair.public, transcript.public_key);
+        assert!(recover_key(&transcript, &outcome.shares[..2]).is_err());
+
+        let mut tampered = transcript.clone();
+        tampered.threshold = 2;
+        assert!(tampered.verify().is_err());
+    }
+
+    #[test]
+    fn test_foreign_shares_refused() {
+        let first = KeyCeremony::new(custodians(), 2).run().unwrap();
+        let second = KeyCeremony::new(custodians(), 2).run().unwrap();
+        assert!(first.transcript.verify_share(&second.shares[0]).is_err());
+
+        let mut altered = first.shares[0].clone();
+        altered.share.value[0] ^= 1;
+        assert!(first.transcript.verify_share(&altered).is_err());
+        let mut renamed = first.shares[0].clone();
+        renamed.custodian = "mallory".to_string();
+        assert!(recover_key(&first.transcript, &[renamed, first.shares[1].clone()]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_ceremonies() {
+        assert!(KeyCeremony::new(custodians(), 1).run().is_err());
+        assert!(KeyCeremony::new(custodians(), 5).run().is_err());
+        let mut repeated = custodians();
+        repeated[1] = "alice".to_string();
+        assert!(KeyCeremony::new(repeated, 2).run().is_err());
+    }
+}
@@ -1,10 +1,11 @@
 //! Issuer setup and initialization functionality.
 
-use hesha_crypto::signing::generate_keypair;
-use hesha_types::{IssuerConfig, IssuerIdentity, HeshaResult, HeshaError, KeyPair};
-use std::path::Path;
+use hesha_crypto::signing::{generate_keypair, keypair_from_private};
+use hesha_types::{IssuerConfig, IssuerIdentity, HeshaResult, HeshaError, KeyPair, PreviousKey, PrivateKey, PublicKey};
+use hesha_types::attestation::ServiceDiscovery;
+use std::path::{Path, PathBuf};
 use std::fs;
-use chrono::Utc;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
 use base64::{Engine as _, engine::general_purpose};
 
 /// Issuer setup builder for interactive configuration.
@@ -95,6 +96,20 @@ impl IssuerSetupBuilder {
             },
             port: self.port,
             attestation_validity_days: 365,
+            node: Default::default(),
+            previous_keys: Vec::new(),
+            verification: Default::default(),
+            auth: Default::default(),
+            admin_auth: Default::default(),
+            request_signing: None,
+            cors: None,
+            tls: None,
+            acme: None,
+            webhooks: Vec::new(),
+            scopes: None,
+            captcha: None,
+            ip_access: None,
+            oidc_provider: None,
         };
         
         Ok(IssuerSetup {
@@ -126,49 +141,9 @@ impl IssuerSetup {
         fs::create_dir_all(&keys_dir)
             .map_err(|e| HeshaError::ConfigError(format!("Failed to create keys directory: {}", e)))?;
         
-        // Save configuration
-        let config_path = config_dir.join("issuer.toml");
-        let config_toml = toml::to_string_pretty(&self.config)
-            .map_err(|e| HeshaError::ConfigError(format!("Failed to serialize config: {}", e)))?;
-        fs::write(&config_path, config_toml)
-            .map_err(|e| HeshaError::ConfigError(format!("Failed to write config: {}", e)))?;
-        
-        // Save private key as base64 (with restricted permissions)
-        let private_key_path = keys_dir.join("private.key");
-        let private_key_base64 = self.keypair.private.to_base64();
-        fs::write(&private_key_path, private_key_base64)
-            .map_err(|e| HeshaError::ConfigError(format!("Failed to write private key: {}", e)))?;
-        
-        // Set restrictive permissions on private key (Unix only)
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let permissions = fs::Permissions::from_mode(0o600);
-            fs::set_permissions(&private_key_path, permissions)
-                .map_err(|e| HeshaError::ConfigError(format!("Failed to set key permissions: {}", e)))?;
-        }
-        
-        // Save public key as base64
-        let public_key_path = keys_dir.join("public.key");
-        let public_key_base64 = self.keypair.public.to_base64();
-        fs::write(&public_key_path, public_key_base64)
-            .map_err(|e| HeshaError::ConfigError(format!("Failed to write public key: {}", e)))?;
-        
-        // Generate public key endpoint JSON
-        let pubkey_endpoint = serde_json::json!({
-            "public_key": self.config.identity.public_key_base64url,
-            "algorithm": "Ed25519",
-            "key_id": self.config.identity.key_id,
-            "created_at": self.config.identity.created_at,
-        });
-        
-        let pubkey_path = config_dir.join("public-key-endpoint.json");
-        let pubkey_json = serde_json::to_string_pretty(&pubkey_endpoint)
-            .map_err(|e| HeshaError::ConfigError(format!("Failed to serialize pubkey endpoint: {}", e)))?;
-        fs::write(&pubkey_path, pubkey_json)
-            .map_err(|e| HeshaError::ConfigError(format!("Failed to write pubkey endpoint: {}", e)))?;
-        
-        Ok(())
+        let mut files = config_files(&config_dir, &self.config)?;
+        files.extend(key_files(&keys_dir, &self.keypair));
+        commit_files(stage_files(&files)?)
     }
     
     /// Get the public key discovery URL.
@@ -177,6 +152,413 @@ impl IssuerSetup {
     }
 }
 
+/// Result of rotating an issuer's signing key.
+pub struct KeyRotation {
+    /// The updated configuration.
+    pub config: IssuerConfig,
+    /// The new signing keypair.
+    pub keypair: KeyPair,
+    /// The key that was retired.
+    pub retired: PreviousKey,
+    /// Where the retired private key was moved.
+    pub archived_private_key: PathBuf,
+}
+
+/// Rotate the signing key of an issuer created with [`IssuerSetup::save`].
+/// 
+/// Generates a new keypair, archives the old key files as
+/// `keys/private-<key_id>.key`, and lists the old key in `issuer.toml` and
+/// the public key endpoint JSON until `overlap` from now, so attestations it
+/// signed keep verifying while they are still valid. Previous keys whose
+/// window has passed are dropped.
+/// 
+/// The new key and config are written to temporary files first; the old key
+/// is archived only once they are all on disk, and nothing in place changes
+/// if any of those steps fails.
+/// 
+/// # Security Considerations
+/// - The new private key is written with restricted permissions
+/// - Archived private keys should be moved to offline storage
+pub fn rotate_issuer_key(base_dir: impl AsRef<Path>, overlap: Duration) -> HeshaResult<KeyRotation> {
+    let base_dir = base_dir.as_ref();
+    let config_dir = base_dir.join("config");
+    let keys_dir = base_dir.join("keys");
+    
+    let mut config: IssuerConfig = IssuerConfig::from_file(config_dir.join("issuer.toml"))
+        .map_err(|e| HeshaError::ConfigError(format!("Failed to read issuer config: {}", e)))?;
+    
+    let now = Utc::now();
+    let timestamp = now.to_rfc3339_opts(SecondsFormat::Secs, true);
+    let keypair = generate_keypair()
+        .map_err(|e| HeshaError::CryptoError(e.to_string()))?;
+    
+    let retired = PreviousKey {
+        key_id: config.identity.key_id.clone(),
+        public_key_base64url: config.identity.public_key_base64url.clone(),
+        created_at: config.identity.created_at.clone(),
+        retired_at: timestamp.clone(),
+        valid_until: (now + overlap).to_rfc3339_opts(SecondsFormat::Secs, true),
+    };
+    
+    // Drop keys whose publication window has passed
+    config.previous_keys.retain(|key| {
+        DateTime::parse_from_rfc3339(&key.valid_until)
+            .map(|until| until > now)
+            .unwrap_or(true)
+    });
+    config.previous_keys.insert(0, retired.clone());
+    
+    config.identity.public_key_base64url = keypair.public.to_base64();
+    config.identity.key_id = format!(
+        "key-{}-{}",
+        now.format("%Y%m%d"),
+        &config.identity.public_key_base64url[..8]
+    );
+    config.identity.created_at = timestamp;
+    
+    // The config goes in first: if the key files don't follow, the node
+    // still signs with a key the published config lists
+    let mut files = config_files(&config_dir, &config)?;
+    files.extend(key_files(&keys_dir, &keypair));
+    let staged = stage_files(&files)?;
+    
+    // Archive the old key files under the retired key ID
+    let archived_private_key = keys_dir.join(format!("private-{}.key", retired.key_id));
+    let archived = archive_key_file(&keys_dir.join("private.key"), &archived_private_key, true)
+        .and_then(|_| archive_key_file(
+            &keys_dir.join("public.key"),
+            &keys_dir.join(format!("public-{}.key", retired.key_id)),
+            false,
+        ));
+    if let Err(e) = archived {
+        discard_staged(&staged);
+        return Err(e);
+    }
+    
+    commit_files(staged)?;
+    
+    Ok(KeyRotation {
+        config,
+        keypair,
+        retired,
+        archived_private_key,
+    })
+}
+
+/// Build the public key endpoint document for a configuration.
+/// 
+/// The top-level fields describe the current signing key; `keys` lists it
+/// together with any previous keys and their validity windows.
+pub fn public_key_endpoint(config: &IssuerConfig) -> serde_json::Value {
+    let mut keys = vec![serde_json::json!({
+        "key_id": config.identity.key_id,
+        "public_key": config.identity.public_key_base64url,
+        "algorithm": "Ed25519",
+        "valid_from": config.identity.created_at,
+    })];
+    keys.extend(config.previous_keys.iter().map(|key| serde_json::json!({
+        "key_id": key.key_id,
+        "public_key": key.public_key_base64url,
+        "algorithm": "Ed25519",
+        "valid_from": key.created_at,
+        "valid_until": key.valid_until,
+    })));
+    
+    serde_json::json!({
+        "public_key": config.identity.public_key_base64url,
+        "algorithm": "Ed25519",
+        "key_id": config.identity.key_id,
+        "created_at": config.identity.created_at,
+        "keys": keys,
+    })
+}
+
+/// Build a JSON Web Key Set (RFC 8037 OKP keys) for a configuration.
+/// 
+/// Lists the current signing key followed by any previous keys, so JOSE
+/// tooling can verify attestations signed before a rotation.
+pub fn jwks(config: &IssuerConfig) -> serde_json::Value {
+    let jwk = |key_id: &str, public_key: &str| serde_json::json!({
+        "kty": "OKP",
+        "crv": "Ed25519",
+        "x": public_key,
+        "kid": key_id,
+        "alg": "EdDSA",
+        "use": "sig",
+    });
+    
+    let mut keys = vec![jwk(&config.identity.key_id, &config.identity.public_key_base64url)];
+    keys.extend(config.previous_keys.iter().map(|key| jwk(&key.key_id, &key.public_key_base64url)));
+    
+    serde_json::json!({ "keys": keys })
+}
+
+/// Files written by [`export_issuer_info`].
+pub struct IssuerInfoExport {
+    /// The configuration the documents were generated from.
+    pub config: IssuerConfig,
+    /// Path of the written `pubkey.json`.
+    pub pubkey_path: PathBuf,
+    /// Path of the written `jwks.json`.
+    pub jwks_path: PathBuf,
+}
+
+/// Regenerate the well-known documents of an issuer created with
+/// [`IssuerSetup::save`].
+/// 
+/// Writes `pubkey.json` (the public key endpoint, with `service_info` for
+/// trust domain delegation if given) and `jwks.json` to `out_dir`, ready to
+/// be served under `/.well-known/hesha/`. Fails if `keys/public.key` does
+/// not match the key in `issuer.toml`, since verifiers would then be given
+/// a key the node doesn't sign with.
+pub fn export_issuer_info(
+    base_dir: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+    service_info: Option<&ServiceDiscovery>,
+) -> HeshaResult<IssuerInfoExport> {
+    let base_dir = base_dir.as_ref();
+    let out_dir = out_dir.as_ref();
+    
+    let config: IssuerConfig = IssuerConfig::from_file(base_dir.join("config").join("issuer.toml"))
+        .map_err(|e| HeshaError::ConfigError(format!("Failed to read issuer config: {}", e)))?;
+    
+    let public_key_path = base_dir.join("keys").join("public.key");
+    if public_key_path.exists() {
+        let public_key = fs::read_to_string(&public_key_path)
+            .map_err(|e| HeshaError::ConfigError(format!("Failed to read public key: {}", e)))?;
+        if public_key.trim() != config.identity.public_key_base64url {
+            return Err(HeshaError::ConfigError(format!(
+                "{} does not match the public key in issuer.toml",
+                public_key_path.display()
+            )));
+        }
+    }
+    
+    let mut pubkey = public_key_endpoint(&config);
+    if let Some(service_info) = service_info {
+        pubkey["service_info"] = serde_json::to_value(service_info)
+            .map_err(|e| HeshaError::ConfigError(format!("Failed to serialize service info: {}", e)))?;
+    }
+    
+    fs::create_dir_all(out_dir)
+        .map_err(|e| HeshaError::ConfigError(format!("Failed to create output directory: {}", e)))?;
+    
+    let pubkey_path = out_dir.join("pubkey.json");
+    let jwks_path = out_dir.join("jwks.json");
+    for (path, document) in [(&pubkey_path, pubkey), (&jwks_path, jwks(&config))] {
+        let json = serde_json::to_string_pretty(&document)
+            .map_err(|e| HeshaError::ConfigError(format!("Failed to serialize {}: {}", path.display(), e)))?;
+        fs::write(path, json)
+            .map_err(|e| HeshaError::ConfigError(format!("Failed to write {}: {}", path.display(), e)))?;
+    }
+    
+    Ok(IssuerInfoExport {
+        config,
+        pubkey_path,
+        jwks_path,
+    })
+}
+
+/// Check an issuer.toml and the key files saved next to it by
+/// [`IssuerSetup::save`].
+/// 
+/// Returns every problem found rather than stopping at the first, so an
+/// operator can fix them in one pass. An empty list means the configuration
+/// is usable.
+pub fn validate_issuer_config(config_path: impl AsRef<Path>) -> Vec<String> {
+    let config_path = config_path.as_ref();
+    let mut problems = Vec::new();
+    
+    let config = match IssuerConfig::from_file(config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            problems.push(format!("Failed to read {}: {}", config_path.display(), e));
+            return problems;
+        }
+    };
+    let identity = &config.identity;
+    
+    if identity.name.trim().is_empty() {
+        problems.push("Issuer name is empty".to_string());
+    }
+    if !is_valid_domain(&identity.trust_domain) {
+        problems.push(format!("Invalid trust domain format: '{}'", identity.trust_domain));
+    }
+    if !is_valid_email(&identity.contact_email) {
+        problems.push(format!("Invalid email format: '{}'", identity.contact_email));
+    }
+    if identity.key_id.trim().is_empty() {
+        problems.push("Key ID is empty".to_string());
+    }
+    let public_key = PublicKey::from_base64(&identity.public_key_base64url)
+        .map_err(|e| problems.push(format!("Invalid public key in config: {}", e)))
+        .ok();
+    for key in &config.previous_keys {
+        if PublicKey::from_base64(&key.public_key_base64url).is_err() {
+            problems.push(format!("Invalid public key for previous key '{}'", key.key_id));
+        }
+        if DateTime::parse_from_rfc3339(&key.valid_until).is_err() {
+            problems.push(format!("Invalid valid_until for previous key '{}': '{}'", key.key_id, key.valid_until));
+        }
+    }
+    
+    // config/issuer.toml sits next to keys/
+    let keys_dir = config_path
+        .parent()
+        .and_then(Path::parent)
+        .unwrap_or_else(|| Path::new("."))
+        .join("keys");
+    
+    let private_key_path = keys_dir.join("private.key");
+    match fs::read_to_string(&private_key_path) {
+        Ok(contents) => {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                if let Ok(metadata) = fs::metadata(&private_key_path) {
+                    let mode = metadata.permissions().mode() & 0o777;
+                    if mode & 0o077 != 0 {
+                        problems.push(format!(
+                            "{} is accessible by other users (mode {:o}); run chmod 600",
+                            private_key_path.display(),
+                            mode
+                        ));
+                    }
+                }
+            }
+            
+            let derived = PrivateKey::from_base64(contents.trim())
+                .and_then(|private_key| keypair_from_private(&private_key));
+            match (derived, &public_key) {
+                (Err(e), _) => problems.push(format!("Invalid private key in {}: {}", private_key_path.display(), e)),
+                (Ok(keypair), Some(public_key)) if keypair.public != *public_key => problems.push(format!(
+                    "{} does not match the public key in the config",
+                    private_key_path.display()
+                )),
+                _ => {}
+            }
+        }
+        Err(e) => problems.push(format!("Failed to read {}: {}", private_key_path.display(), e)),
+    }
+    
+    let public_key_path = keys_dir.join("public.key");
+    match fs::read_to_string(&public_key_path) {
+        Ok(contents) => match PublicKey::from_base64(contents.trim()) {
+            Ok(on_disk) if public_key.as_ref().is_some_and(|key| *key != on_disk) => problems.push(format!(
+                "{} does not match the public key in the config",
+                public_key_path.display()
+            )),
+            Ok(_) => {}
+            Err(e) => problems.push(format!("Invalid public key in {}: {}", public_key_path.display(), e)),
+        },
+        Err(e) => problems.push(format!("Failed to read {}: {}", public_key_path.display(), e)),
+    }
+    
+    problems
+}
+
+/// A file to write: its path, contents, and whether only the owner may
+/// read it.
+type PendingFile = (PathBuf, String, bool);
+
+/// issuer.toml and the public key endpoint JSON.
+fn config_files(config_dir: &Path, config: &IssuerConfig) -> HeshaResult<Vec<PendingFile>> {
+    let config_toml = toml::to_string_pretty(config)
+        .map_err(|e| HeshaError::ConfigError(format!("Failed to serialize config: {}", e)))?;
+    let pubkey_json = serde_json::to_string_pretty(&public_key_endpoint(config))
+        .map_err(|e| HeshaError::ConfigError(format!("Failed to serialize pubkey endpoint: {}", e)))?;
+    
+    Ok(vec![
+        (config_dir.join("issuer.toml"), config_toml, false),
+        (config_dir.join("public-key-endpoint.json"), pubkey_json, false),
+    ])
+}
+
+/// The private and public key files, as base64.
+fn key_files(keys_dir: &Path, keypair: &KeyPair) -> Vec<PendingFile> {
+    vec![
+        (keys_dir.join("private.key"), keypair.private.to_base64(), true),
+        (keys_dir.join("public.key"), keypair.public.to_base64(), false),
+    ]
+}
+
+/// Temporary path a file is written to before it's renamed into place.
+fn staging_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Write each file next to its destination, returning `(staged, target)`
+/// pairs for [`commit_files`]. Nothing is left behind on failure.
+fn stage_files(files: &[PendingFile]) -> HeshaResult<Vec<(PathBuf, PathBuf)>> {
+    let mut staged = Vec::with_capacity(files.len());
+    for (path, contents, private) in files {
+        let staging = staging_path(path);
+        if let Err(e) = write_new_file(&staging, contents, *private) {
+            discard_staged(&staged);
+            return Err(HeshaError::ConfigError(format!("Failed to write {}: {}", path.display(), e)));
+        }
+        staged.push((staging, path.clone()));
+    }
+    Ok(staged)
+}
+
+/// Rename staged files into place, in order.
+fn commit_files(staged: Vec<(PathBuf, PathBuf)>) -> HeshaResult<()> {
+    for (index, (staging, path)) in staged.iter().enumerate() {
+        if let Err(e) = fs::rename(staging, path) {
+            discard_staged(&staged[index..]);
+            return Err(HeshaError::ConfigError(format!("Failed to write {}: {}", path.display(), e)));
+        }
+    }
+    Ok(())
+}
+
+/// Remove staged files that won't be committed.
+fn discard_staged(staged: &[(PathBuf, PathBuf)]) {
+    for (staging, _) in staged {
+        let _ = fs::remove_file(staging);
+    }
+}
+
+/// Create a file, restricting it to its owner from the start if `private`
+/// (Unix only). Replaces a leftover from an interrupted write.
+fn write_new_file(path: &Path, contents: &str, private: bool) -> std::io::Result<()> {
+    use std::io::Write;
+    
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+    
+    let mut file = options.open(path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()
+}
+
+/// Copy a key file into the archive, keeping a private key owner-only.
+/// A missing public key file is skipped.
+fn archive_key_file(path: &Path, archived: &Path, private: bool) -> HeshaResult<()> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if !private && e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(HeshaError::ConfigError(format!("Failed to read {}: {}", path.display(), e))),
+    };
+    write_new_file(archived, &contents, private)
+        .map_err(|e| HeshaError::ConfigError(format!("Failed to archive {}: {}", path.display(), e)))
+}
+
 /// Validate domain format (basic validation).
 fn is_valid_domain(domain: &str) -> bool {
     // Allow localhost for development
@@ -204,6 +586,7 @@ fn is_valid_email(email: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use hesha_types::IssuerInfo;
     
     #[test]
     fn test_setup_builder() {
@@ -229,4 +612,139 @@ mod tests {
         
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_rotate_issuer_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let setup = IssuerSetupBuilder::new()
+            .name("Test Issuer")
+            .trust_domain("issuer.example.com")
+            .contact_email("admin@example.com")
+            .build()
+            .unwrap();
+        setup.save(dir.path()).unwrap();
+        
+        let rotation = rotate_issuer_key(dir.path(), Duration::days(30)).unwrap();
+        
+        assert_eq!(rotation.retired.public_key_base64url, setup.config.identity.public_key_base64url);
+        assert_ne!(rotation.config.identity.public_key_base64url, setup.config.identity.public_key_base64url);
+        assert!(rotation.archived_private_key.exists());
+        
+        let saved = IssuerConfig::from_file(dir.path().join("config/issuer.toml")).unwrap();
+        assert_eq!(saved.previous_keys.len(), 1);
+        assert_eq!(saved.identity.key_id, rotation.config.identity.key_id);
+        
+        let endpoint: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(dir.path().join("config/public-key-endpoint.json")).unwrap()
+        ).unwrap();
+        assert_eq!(endpoint["keys"].as_array().unwrap().len(), 2);
+        assert_eq!(endpoint["public_key"], rotation.config.identity.public_key_base64url.as_str());
+        
+        // Verifiers find the retired key by the `kid` of old attestations
+        let info: IssuerInfo = serde_json::from_value(endpoint).unwrap();
+        let retired = info.key(Some(&rotation.retired.key_id)).unwrap();
+        assert_eq!(retired.to_base64(), rotation.retired.public_key_base64url);
+    }
+    
+    #[test]
+    fn test_failed_rotation_leaves_issuer_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let setup = IssuerSetupBuilder::new()
+            .name("Test Issuer")
+            .trust_domain("issuer.example.com")
+            .contact_email("admin@example.com")
+            .build()
+            .unwrap();
+        setup.save(dir.path()).unwrap();
+        let snapshot = |dir: &Path| -> Vec<(PathBuf, String)> {
+            let mut files: Vec<_> = ["config", "keys"].iter()
+                .flat_map(|sub| fs::read_dir(dir.join(sub)).unwrap())
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.is_file())
+                .map(|path| (path.clone(), fs::read_to_string(&path).unwrap()))
+                .collect();
+            files.sort();
+            files
+        };
+        
+        // Archiving fails when the archive name is taken by a directory
+        let archive = dir.path().join(format!("keys/private-{}.key", setup.config.identity.key_id));
+        fs::create_dir(&archive).unwrap();
+        let before = snapshot(dir.path());
+        
+        assert!(rotate_issuer_key(dir.path(), Duration::days(30)).is_err());
+        assert_eq!(snapshot(dir.path()), before);
+        assert!(validate_issuer_config(dir.path().join("config/issuer.toml")).is_empty());
+        
+        fs::remove_dir(&archive).unwrap();
+        let rotation = rotate_issuer_key(dir.path(), Duration::days(30)).unwrap();
+        assert_eq!(fs::read_to_string(&rotation.archived_private_key).unwrap(), setup.keypair.private.to_base64());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            for path in [rotation.archived_private_key.clone(), dir.path().join("keys/private.key")] {
+                assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+            }
+        }
+        assert!(validate_issuer_config(dir.path().join("config/issuer.toml")).is_empty());
+    }
+    
+    #[test]
+    fn test_export_issuer_info() {
+        let dir = tempfile::tempdir().unwrap();
+        let setup = IssuerSetupBuilder::new()
+            .name("Test Issuer")
+            .trust_domain("issuer.example.com")
+            .contact_email("admin@example.com")
+            .build()
+            .unwrap();
+        setup.save(dir.path()).unwrap();
+        
+        let service_info = ServiceDiscovery {
+            service_url: "https://api.example.com".to_string(),
+            relationship: "subdomain".to_string(),
+            metadata: None,
+        };
+        let out_dir = dir.path().join("public");
+        let export = export_issuer_info(dir.path(), &out_dir, Some(&service_info)).unwrap();
+        
+        let pubkey: serde_json::Value = serde_json::from_str(&fs::read_to_string(&export.pubkey_path).unwrap()).unwrap();
+        assert_eq!(pubkey["public_key"], setup.config.identity.public_key_base64url.as_str());
+        assert_eq!(pubkey["service_info"]["service_url"], "https://api.example.com");
+        
+        let jwks: serde_json::Value = serde_json::from_str(&fs::read_to_string(&export.jwks_path).unwrap()).unwrap();
+        assert_eq!(jwks["keys"][0]["x"], setup.config.identity.public_key_base64url.as_str());
+        assert_eq!(jwks["keys"][0]["kid"], setup.config.identity.key_id.as_str());
+        
+        // A key file that disagrees with the config is refused
+        fs::write(dir.path().join("keys/public.key"), "bogus").unwrap();
+        assert!(export_issuer_info(dir.path(), &out_dir, None).is_err());
+    }
+    
+    #[test]
+    fn test_validate_issuer_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let setup = IssuerSetupBuilder::new()
+            .name("Test Issuer")
+            .trust_domain("issuer.example.com")
+            .contact_email("admin@example.com")
+            .build()
+            .unwrap();
+        setup.save(dir.path()).unwrap();
+        let config_path = dir.path().join("config/issuer.toml");
+        
+        assert!(validate_issuer_config(&config_path).is_empty());
+        
+        // Every problem is reported, not just the first
+        let mut config = setup.config.clone();
+        config.identity.trust_domain = "not a domain".to_string();
+        config.identity.contact_email = "nobody".to_string();
+        fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+        fs::remove_file(dir.path().join("keys/public.key")).unwrap();
+        
+        let problems = validate_issuer_config(&config_path);
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        
+        assert_eq!(validate_issuer_config(dir.path().join("missing.toml")).len(), 1);
+    }
+}
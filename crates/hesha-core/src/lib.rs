@@ -4,8 +4,16 @@
 //! 
 //! - Attestation creation and parsing
 //! - Attestation verification with key discovery
+//! - Batch verification, in parallel with the `parallel` feature
 //! - Proxy number generation
 //! - Challenge-response verification
+//! - Request authentication for services
+//! - Revocation list signing
+//! - Audit log sealing and verification
+//! - Key ceremonies sharing issuer keys among custodians
+//! - Transparency log trees and proofs
+//! - Matrix 3PID associations for proxy numbers
+//! - OpenID Connect ID tokens for proxy numbers
 //! 
 //! # Security Design
 //! 
@@ -18,8 +26,16 @@
 #![forbid(unsafe_code)]
 
 pub mod attestation;
+pub mod audit;
+pub mod backup;
+pub mod ceremony;
 pub mod generator;
 pub mod issuer_setup;
+pub mod matrix;
+pub mod oidc;
+pub mod receipt;
+pub mod revocation;
+pub mod transparency;
 pub mod verification;
 
 // Re-export main functionality
@@ -27,10 +43,28 @@ pub use attestation::{
     create_attestation, create_attestation_with_trust_domain, parse_attestation, 
     parse_attestation_jwt, validate_attestation, AttestationBuilder,
 };
+pub use audit::{seal_audit_entry, verify_audit_log};
+pub use backup::{Backup, BackupFile, EncryptedBackup};
+pub use ceremony::{recover_key, CeremonyOutcome, CeremonyTranscript, CustodianShare, KeyCeremony};
 pub use generator::{generate_proxy_number, ProxyGenerationInput};
-pub use issuer_setup::{IssuerSetup, IssuerSetupBuilder};
+pub use issuer_setup::{
+    export_issuer_info, jwks, public_key_endpoint, rotate_issuer_key, validate_issuer_config,
+    IssuerInfoExport, IssuerSetup, IssuerSetupBuilder, KeyRotation,
+};
+pub use oidc::{oidc_service_context, sign_id_token, verify_id_token, IdTokenClaims};
+pub use receipt::{receipt_hash, sign_receipt, verify_receipt};
+pub use revocation::{
+    sign_revocation_list, sign_status_response, verify_revocation_list, verify_status_response,
+};
+pub use transparency::{
+    attestation_hash, check_consistency, check_inclusion, sign_log_submission, sign_tree_head,
+    verify_log_submission, verify_tree_head, MerkleTree,
+};
 pub use verification::{
-    discover_issuer_key, verify_attestation, verify_attestation_with_key, IssuerKeyCache,
+    discover_issuer_key, sign_challenge, verify_attestation, verify_attestation_with_bundle,
+    verify_attestation_with_key, verify_challenge_response, AuthError, BatchVerifier, IssuerKeyCache,
+    RequestAuthenticator, TrustBundle, TrustedIssuer, VerificationCache, VerificationPolicy,
+    VerifiedProxyIdentity,
 };
 // Re-export types from hesha-types for convenience
 pub use hesha_types::{
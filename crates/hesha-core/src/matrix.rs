@@ -0,0 +1,267 @@
+//! Matrix third-party identifier (3PID) integration.
+//!
+//! Matrix homeservers and identity servers bind phone numbers to Matrix
+//! IDs as `msisdn` 3PIDs, normally after texting a code to the number.
+//! These helpers let them bind proxy numbers instead, without ever seeing
+//! the real number:
+//!
+//! 1. The server sends the user a [`matrix_challenge`] naming their Matrix
+//!    ID.
+//! 2. The wallet answers it with the attestation's user key, as for any
+//!    other service (see [`sign_challenge`](crate::sign_challenge)).
+//! 3. The server verifies the attestation as usual, then calls
+//!    [`associate`] to check the response and get a
+//!    [`ThreePidAssociation`] for the proxy number.
+//! 4. Identity servers sign the association with [`sign_association`],
+//!    in the same canonical JSON form as their other associations, so
+//!    Matrix clients and servers can check it with [`verify_association`]
+//!    or their own tooling.
+//!
+//! Associations expire with the attestation they rest on, and carry its
+//! JWT ID so the server can drop them when it is revoked.
+
+use crate::verification::challenge::verify_challenge_response;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use hesha_types::{
+    Attestation, Challenge, ChallengeResponse, HeshaError, HeshaResult, PrivateKey, ProxyNumber,
+    PublicKey, Signature,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 3PID medium of phone numbers.
+pub const MSISDN_MEDIUM: &str = "msisdn";
+
+/// Matrix `msisdn` address of a proxy number: its digits, without the `+`.
+pub fn msisdn(proxy_number: &ProxyNumber) -> String {
+    proxy_number.as_str().trim_start_matches('+').to_string()
+}
+
+/// The proxy number an `msisdn` address stands for, if it is one.
+pub fn proxy_number_from_msisdn(address: &str) -> HeshaResult<ProxyNumber> {
+    ProxyNumber::new(format!("+{}", address.trim().trim_start_matches('+')))
+}
+
+/// Service context of challenges binding a proxy number to `mxid`.
+///
+/// Naming the Matrix ID in the challenge stops a response made for one
+/// account from being replayed to bind the number to another.
+pub fn matrix_service_context(mxid: &str) -> String {
+    format!("matrix:{}", mxid)
+}
+
+/// A fresh challenge for binding a proxy number to `mxid`.
+pub fn matrix_challenge(mxid: &str) -> Challenge {
+    Challenge {
+        nonce: hesha_crypto::generate_nonce(),
+        service_context: matrix_service_context(mxid),
+        timestamp: Utc::now(),
+    }
+}
+
+/// A proxy number bound to a Matrix ID.
+///
+/// Serializes to the fields of a Matrix 3PID association, plus the
+/// attestation it rests on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThreePidAssociation {
+    /// Always [`MSISDN_MEDIUM`].
+    pub medium: String,
+    /// The proxy number, as [`msisdn`] formats it.
+    pub address: String,
+    /// The Matrix user ID.
+    pub mxid: String,
+    /// When the association becomes valid, in milliseconds since the epoch.
+    pub not_before: i64,
+    /// When the association expires, in milliseconds since the epoch.
+    pub not_after: i64,
+    /// When the association was made, in milliseconds since the epoch.
+    pub ts: i64,
+    /// JWT ID of the attestation.
+    pub hesha_attestation_id: String,
+    /// Issuer of the attestation.
+    pub hesha_issuer: String,
+    /// Signatures by server name, then key ID (`ed25519:<version>`), in
+    /// unpadded base64.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub signatures: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl ThreePidAssociation {
+    /// An unsigned association of the attestation's proxy number with
+    /// `mxid`, valid from `now` until the attestation expires.
+    pub fn new(attestation: &Attestation, mxid: impl Into<String>, now: DateTime<Utc>) -> Self {
+        Self {
+            medium: MSISDN_MEDIUM.to_string(),
+            address: msisdn(&attestation.proxy_number),
+            mxid: mxid.into(),
+            not_before: now.timestamp_millis(),
+            not_after: attestation.exp.timestamp_millis(),
+            ts: now.timestamp_millis(),
+            hesha_attestation_id: attestation.jti.clone(),
+            hesha_issuer: attestation.iss.clone(),
+            signatures: BTreeMap::new(),
+        }
+    }
+
+    /// The proxy number bound.
+    pub fn proxy_number(&self) -> HeshaResult<ProxyNumber> {
+        proxy_number_from_msisdn(&self.address)
+    }
+
+    /// Whether the association is valid at `now`.
+    pub fn is_current(&self, now: DateTime<Utc>) -> bool {
+        let now = now.timestamp_millis();
+        self.not_before <= now && now < self.not_after
+    }
+
+    /// Bytes signed: the Matrix canonical JSON of the association without
+    /// its signatures (keys sorted, no insignificant whitespace).
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(object) = value.as_object_mut() {
+            object.remove("signatures");
+        }
+        // serde_json maps are sorted by key, which is what canonical JSON needs
+        serde_json::to_vec(&value).unwrap_or_default()
+    }
+}
+
+/// Check a challenge response binding the proxy number of `attestation`
+/// to `mxid`, and make the association.
+///
+/// The attestation must already be verified and the response checked
+/// against it as [`verify_challenge_response`] does, for a challenge made
+/// with [`matrix_challenge`] for the same Matrix ID. Whether the nonce was
+/// used before is the caller's to track.
+pub fn associate(
+    attestation: &Attestation,
+    response: &ChallengeResponse,
+    mxid: &str,
+    max_age: Duration,
+) -> HeshaResult<ThreePidAssociation> {
+    if response.challenge.service_context != matrix_service_context(mxid) {
+        return Err(HeshaError::InvalidAttestation(format!(
+            "Challenge was issued for {}, not {}",
+            response.challenge.service_context,
+            matrix_service_context(mxid)
+        )));
+    }
+    if attestation.is_expired() {
+        return Err(HeshaError::AttestationExpired(attestation.exp));
+    }
+    verify_challenge_response(response, attestation, max_age)?;
+    Ok(ThreePidAssociation::new(attestation, mxid, Utc::now()))
+}
+
+/// Sign an association as identity server `server_name` with the key
+/// `ed25519:<key_version>`, keeping any other signatures.
+pub fn sign_association(
+    association: &mut ThreePidAssociation,
+    server_name: &str,
+    key_version: &str,
+    private_key: &PrivateKey,
+) -> HeshaResult<()> {
+    let signature = hesha_crypto::sign_message(private_key, &association.signing_payload())?;
+    association
+        .signatures
+        .entry(server_name.to_string())
+        .or_default()
+        .insert(key_id(key_version), general_purpose::STANDARD_NO_PAD.encode(signature.as_bytes()));
+    Ok(())
+}
+
+/// Check that an association was signed by `server_name` with the key
+/// `ed25519:<key_version>`, and is valid now.
+pub fn verify_association(
+    association: &ThreePidAssociation,
+    server_name: &str,
+    key_version: &str,
+    public_key: &PublicKey,
+) -> HeshaResult<()> {
+    let encoded = association
+        .signatures
+        .get(server_name)
+        .and_then(|keys| keys.get(&key_id(key_version)))
+        .ok_or(HeshaError::InvalidSignature)?;
+    let bytes: [u8; 64] = general_purpose::STANDARD_NO_PAD
+        .decode(encoded.trim_end_matches('='))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(HeshaError::InvalidSignature)?;
+    if !hesha_crypto::verify_signature(public_key, &association.signing_payload(), &Signature::from_bytes(bytes)) {
+        return Err(HeshaError::InvalidSignature);
+    }
+    if !association.is_current(Utc::now()) {
+        return Err(HeshaError::InvalidAttestation(format!(
+            "Association of {} with {} is not currently valid",
+            association.address, association.mxid
+        )));
+    }
+    Ok(())
+}
+
+fn key_id(key_version: &str) -> String {
+    format!("ed25519:{}", key_version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::create::create_attestation;
+    use crate::attestation::parse::parse_attestation;
+    use crate::verification::challenge::{sign_challenge, CHALLENGE_MAX_AGE_SECS};
+    use hesha_crypto::generate_keypair;
+    use hesha_types::PhoneNumber;
+
+    #[test]
+    fn test_msisdn_round_trip() {
+        let proxy = ProxyNumber::new("+100555123456").unwrap();
+        assert_eq!(msisdn(&proxy), "100555123456");
+        assert_eq!(proxy_number_from_msisdn("100555123456").unwrap(), proxy);
+        assert!(proxy_number_from_msisdn("14155552671").is_err());
+    }
+
+    #[test]
+    fn test_associate_and_sign() {
+        let issuer_key = generate_keypair().unwrap();
+        let user_key = generate_keypair().unwrap();
+        let jwt = create_attestation(
+            "issuer.com",
+            &issuer_key.private,
+            &PhoneNumber::new("+14155552671").unwrap(),
+            &ProxyNumber::new("+100555123456").unwrap(),
+            &user_key.public,
+        ).unwrap();
+        let attestation = parse_attestation(&jwt).unwrap();
+        let max_age = Duration::seconds(CHALLENGE_MAX_AGE_SECS);
+        let mxid = "@alice:example.org";
+
+        let challenge = matrix_challenge(mxid);
+        let response = sign_challenge(&challenge, &attestation.jti, &user_key.private).unwrap();
+        let mut association = associate(&attestation, &response, mxid, max_age).unwrap();
+        assert_eq!(association.medium, MSISDN_MEDIUM);
+        assert_eq!(association.proxy_number().unwrap(), attestation.proxy_number);
+        assert_eq!(association.not_after, attestation.exp.timestamp_millis());
+
+        // A response for one account doesn't bind the number to another
+        assert!(associate(&attestation, &response, "@mallory:example.org", max_age).is_err());
+
+        let server_key = generate_keypair().unwrap();
+        assert!(verify_association(&association, "id.example.org", "0", &server_key.public).is_err());
+        sign_association(&mut association, "id.example.org", "0", &server_key.private).unwrap();
+        verify_association(&association, "id.example.org", "0", &server_key.public).unwrap();
+        assert!(association.signatures["id.example.org"].contains_key("ed25519:0"));
+
+        // Signed over canonical JSON, so it survives a round trip
+        let json = serde_json::to_string(&association).unwrap();
+        let parsed: ThreePidAssociation = serde_json::from_str(&json).unwrap();
+        verify_association(&parsed, "id.example.org", "0", &server_key.public).unwrap();
+
+        let mut altered = parsed.clone();
+        altered.mxid = "@mallory:example.org".to_string();
+        assert!(verify_association(&altered, "id.example.org", "0", &server_key.public).is_err());
+        assert!(verify_association(&parsed, "id.example.org", "1", &server_key.public).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! OpenID Connect ID tokens vouching for proxy numbers.
+//!
+//! An issuer acting as an OpenID provider signs an ID token once the
+//! holder of an attestation has answered a challenge with it. Relying
+//! parties that only speak OIDC read the proxy number from the
+//! [`PROXY_NUMBER_CLAIM`] claim; those that know Hesha can also verify the
+//! attestation itself, carried in [`ATTESTATION_CLAIM`].
+//!
+//! Tokens are EdDSA JWTs signed with the issuer key and name it in `kid`,
+//! so relying parties find it in the provider's JWKS.
+
+use crate::attestation::jwt::{decode_jwt, encode_jwt_with_key_id};
+use chrono::{DateTime, Duration, Utc};
+use hesha_crypto::MessageSigner;
+use hesha_types::{Attestation, HeshaError, HeshaResult, PublicKey};
+use serde::{Deserialize, Serialize};
+
+/// Claim carrying the proxy number.
+pub const PROXY_NUMBER_CLAIM: &str = "hesha_proxy_number";
+
+/// Claim carrying the attestation JWT.
+pub const ATTESTATION_CLAIM: &str = "hesha_attestation";
+
+/// Service context of challenges answered to sign in to `client_id` at the
+/// provider `issuer`.
+///
+/// Naming the relying party stops a response made to sign in to one from
+/// being used to sign in to another.
+pub fn oidc_service_context(issuer: &str, client_id: &str) -> String {
+    format!("oidc:{}#{}", issuer, client_id)
+}
+
+/// Claims of an ID token for a proxy number.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    /// Provider URL.
+    pub iss: String,
+    /// Subject: the proxy number.
+    pub sub: String,
+    /// Client ID of the relying party.
+    pub aud: String,
+    /// Expiry, in seconds since the epoch.
+    pub exp: i64,
+    /// Issue time, in seconds since the epoch.
+    pub iat: i64,
+    /// When the holder answered the challenge, in seconds since the epoch.
+    pub auth_time: i64,
+    /// Nonce from the relying party's authorization request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// The proxy number.
+    pub hesha_proxy_number: String,
+    /// The attestation the holder answered with.
+    pub hesha_attestation: String,
+}
+
+impl IdTokenClaims {
+    /// Claims for the holder of `attestation` (parsed from `jwt`), signing
+    /// in to `client_id` at `auth_time`.
+    ///
+    /// The token is valid for `ttl`, or until the attestation expires if
+    /// that is sooner.
+    pub fn new(
+        issuer: impl Into<String>,
+        client_id: impl Into<String>,
+        jwt: &str,
+        attestation: &Attestation,
+        nonce: Option<String>,
+        auth_time: DateTime<Utc>,
+        ttl: Duration,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            iss: issuer.into(),
+            sub: attestation.proxy_number.to_string(),
+            aud: client_id.into(),
+            exp: (now + ttl).min(attestation.exp).timestamp(),
+            iat: now.timestamp(),
+            auth_time: auth_time.timestamp(),
+            nonce,
+            hesha_proxy_number: attestation.proxy_number.to_string(),
+            hesha_attestation: jwt.to_string(),
+        }
+    }
+}
+
+/// Sign an ID token with the issuer key `key_id`.
+pub fn sign_id_token(
+    claims: &IdTokenClaims,
+    signer: &(impl MessageSigner + ?Sized),
+    key_id: &str,
+) -> HeshaResult<String> {
+    encode_jwt_with_key_id(claims, signer, Some(key_id))
+}
+
+/// Check an ID token's signature, issuer, audience, expiry and, when the
+/// relying party sent one, nonce.
+pub fn verify_id_token(
+    token: &str,
+    public_key: &PublicKey,
+    issuer: &str,
+    client_id: &str,
+    nonce: Option<&str>,
+) -> HeshaResult<IdTokenClaims> {
+    let claims: IdTokenClaims = decode_jwt(token, public_key)?;
+    let invalid = |detail: String| Err(HeshaError::InvalidAttestation(detail));
+    if claims.iss != issuer {
+        return invalid(format!("ID token is from {}, not {}", claims.iss, issuer));
+    }
+    if claims.aud != client_id {
+        return invalid(format!("ID token is for {}, not {}", claims.aud, client_id));
+    }
+    if claims.exp <= Utc::now().timestamp() {
+        return invalid("ID token has expired".to_string());
+    }
+    if nonce.is_some() && claims.nonce.as_deref() != nonce {
+        return invalid("ID token nonce does not match the request".to_string());
+    }
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::create::create_attestation;
+    use crate::attestation::parse::parse_attestation;
+    use hesha_crypto::generate_keypair;
+    use hesha_types::{PhoneNumber, ProxyNumber};
+
+    #[test]
+    fn test_sign_and_verify() {
+        let issuer_key = generate_keypair().unwrap();
+        let user_key = generate_keypair().unwrap();
+        let jwt = create_attestation(
+            "issuer.com",
+            &issuer_key.private,
+            &PhoneNumber::new("+14155552671").unwrap(),
+            &ProxyNumber::new("+1001234567890").unwrap(),
+            &user_key.public,
+        ).unwrap();
+        let attestation = parse_attestation(&jwt).unwrap();
+        let issuer = "https://issuer.com";
+
+        let claims = IdTokenClaims::new(
+            issuer, "forum", &jwt, &attestation, Some("n-0S6".to_string()), Utc::now(), Duration::minutes(5),
+        );
+        assert!(claims.exp <= attestation.exp.timestamp());
+        let token = sign_id_token(&claims, &issuer_key.private, "default").unwrap();
+
+        let verified = verify_id_token(&token, &issuer_key.public, issuer, "forum", Some("n-0S6")).unwrap();
+        assert_eq!(verified.sub, "+1001234567890");
+        assert_eq!(verified.hesha_attestation, jwt);
+        let raw: serde_json::Value = crate::attestation::jwt::decode_jwt(&token, &issuer_key.public).unwrap();
+        assert_eq!(raw[PROXY_NUMBER_CLAIM], "+1001234567890");
+        assert_eq!(raw[ATTESTATION_CLAIM], jwt);
+
+        assert!(verify_id_token(&token, &user_key.public, issuer, "forum", None).is_err());
+        assert!(verify_id_token(&token, &issuer_key.public, "https://other.com", "forum", None).is_err());
+        assert!(verify_id_token(&token, &issuer_key.public, issuer, "shop", None).is_err());
+        assert!(verify_id_token(&token, &issuer_key.public, issuer, "forum", Some("other")).is_err());
+
+        let expired = IdTokenClaims { exp: Utc::now().timestamp() - 1, ..claims };
+        let token = sign_id_token(&expired, &issuer_key.private, "default").unwrap();
+        assert!(verify_id_token(&token, &issuer_key.public, issuer, "forum", None).is_err());
+    }
+}
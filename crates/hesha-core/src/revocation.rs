@@ -0,0 +1,119 @@
+//! Signing and verification of published revocation lists and status
+//! responses.
+
+use chrono::Utc;
+use hesha_crypto::{verify_signature, MessageSigner};
+use hesha_types::{HeshaError, HeshaResult, PublicKey, RevocationList, StatusResponse};
+
+/// Sign a revocation list (or one page of it) with the issuer key.
+pub fn sign_revocation_list(list: &mut RevocationList, signer: &(impl MessageSigner + ?Sized)) -> HeshaResult<()> {
+    list.signature = Some(signer.sign_message(&list.signing_payload())?);
+    Ok(())
+}
+
+/// Check that a revocation list page was signed by the issuer.
+/// 
+/// Unsigned lists are rejected, so a verifier can't be fed an empty list
+/// by anyone but the issuer.
+pub fn verify_revocation_list(list: &RevocationList, public_key: &PublicKey) -> HeshaResult<()> {
+    let signature = list.signature.as_ref().ok_or(HeshaError::InvalidSignature)?;
+    if !verify_signature(public_key, &list.signing_payload(), signature) {
+        return Err(HeshaError::InvalidSignature);
+    }
+    Ok(())
+}
+
+/// Sign a status response with the issuer key.
+pub fn sign_status_response(response: &mut StatusResponse, signer: &(impl MessageSigner + ?Sized)) -> HeshaResult<()> {
+    response.signature = Some(signer.sign_message(&response.signing_payload())?);
+    Ok(())
+}
+
+/// Check that a status response was signed by the issuer and is still
+/// current.
+/// 
+/// A stale response is rejected even when its signature holds, so an old
+/// "good" can't be replayed after the attestation is revoked.
+pub fn verify_status_response(response: &StatusResponse, public_key: &PublicKey) -> HeshaResult<()> {
+    let signature = response.signature.as_ref().ok_or(HeshaError::InvalidSignature)?;
+    if !verify_signature(public_key, &response.signing_payload(), signature) {
+        return Err(HeshaError::InvalidSignature);
+    }
+    if !response.is_current(Utc::now()) {
+        return Err(HeshaError::InvalidAttestation(format!(
+            "Status response for {} is not valid after {}",
+            response.jti, response.next_update
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use hesha_crypto::generate_keypair;
+    use hesha_types::{AttestationStatus, RevocationEntry};
+    
+    #[test]
+    fn test_sign_and_verify() {
+        let keypair = generate_keypair().unwrap();
+        let mut list = RevocationList::new("issuer.example.com");
+        list.entries.push(RevocationEntry {
+            jti: "revoked-jti".to_string(),
+            revoked_at: Utc::now(),
+            reason: None,
+        });
+        
+        assert!(verify_revocation_list(&list, &keypair.public).is_err());
+        sign_revocation_list(&mut list, &keypair.private).unwrap();
+        verify_revocation_list(&list, &keypair.public).unwrap();
+        
+        // Survives a round trip through JSON
+        let parsed: RevocationList = serde_json::from_str(&serde_json::to_string(&list).unwrap()).unwrap();
+        verify_revocation_list(&parsed, &keypair.public).unwrap();
+        
+        // Dropping an entry breaks the signature
+        let mut tampered = parsed;
+        tampered.entries.clear();
+        assert!(verify_revocation_list(&tampered, &keypair.public).is_err());
+        
+        let other = generate_keypair().unwrap();
+        assert!(verify_revocation_list(&list, &other.public).is_err());
+    }
+    
+    #[test]
+    fn test_sign_and_verify_status_response() {
+        let keypair = generate_keypair().unwrap();
+        let mut response = StatusResponse::new(
+            "issuer.example.com",
+            "jti",
+            AttestationStatus::Good,
+            chrono::Duration::minutes(5),
+        );
+        
+        assert!(verify_status_response(&response, &keypair.public).is_err());
+        sign_status_response(&mut response, &keypair.private).unwrap();
+        verify_status_response(&response, &keypair.public).unwrap();
+        
+        // Claiming another status breaks the signature
+        let mut tampered = response.clone();
+        tampered.status = AttestationStatus::Unknown;
+        assert!(verify_status_response(&tampered, &keypair.public).is_err());
+        
+        // A stale response is rejected even when signed
+        let mut stale = StatusResponse::new(
+            "issuer.example.com",
+            "jti",
+            AttestationStatus::Good,
+            chrono::Duration::minutes(5),
+        );
+        stale.produced_at -= chrono::Duration::minutes(10);
+        stale.next_update -= chrono::Duration::minutes(10);
+        sign_status_response(&mut stale, &keypair.private).unwrap();
+        assert!(matches!(
+            verify_status_response(&stale, &keypair.public),
+            Err(HeshaError::InvalidAttestation(_))
+        ));
+    }
+}
@@ -0,0 +1,464 @@
+//! Transparency log trees, proofs and signatures.
+//!
+//! The log is an RFC 6962 Merkle tree: leaves hash as `SHA-256(0x00 ||
+//! entry)` and nodes as `SHA-256(0x01 || left || right)`, where an entry is
+//! the JSON encoding of a signed [`LogSubmission`]. Proofs are checked with
+//! the algorithms of RFC 9162, section 2.1.
+
+use hesha_crypto::{sha256, verify_signature, MessageSigner};
+use hesha_types::{
+    ConsistencyProof, HeshaError, HeshaResult, InclusionProof, LogSubmission,
+    PublicKey, SignedTreeHead,
+};
+
+/// A tree or leaf hash.
+pub type Hash = [u8; 32];
+
+/// Hex SHA-256 of an attestation JWT, as submitted to logs.
+pub fn attestation_hash(jwt: &str) -> String {
+    hex::encode(sha256(jwt.as_bytes()))
+}
+
+/// Leaf hash of a log entry's bytes.
+pub fn leaf_hash(entry: &[u8]) -> Hash {
+    let mut data = Vec::with_capacity(entry.len() + 1);
+    data.push(0x00);
+    data.extend_from_slice(entry);
+    sha256(&data)
+}
+
+/// Leaf hash of a signed submission.
+pub fn submission_leaf_hash(submission: &LogSubmission) -> Hash {
+    leaf_hash(&serde_json::to_vec(submission).unwrap_or_default())
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut data = [0u8; 65];
+    data[0] = 0x01;
+    data[1..33].copy_from_slice(left);
+    data[33..].copy_from_slice(right);
+    sha256(&data)
+}
+
+/// Largest power of two smaller than `n` (which must be at least 2).
+fn split(n: u64) -> u64 {
+    1 << (63 - (n - 1).leading_zeros())
+}
+
+/// An append-only Merkle tree of leaf hashes.
+///
+/// The hash of every complete, aligned subtree is kept as leaves are
+/// appended, so roots and proofs for any tree size take a logarithmic
+/// number of hashes.
+#[derive(Debug, Clone, Default)]
+pub struct MerkleTree {
+    /// `levels[k][i]` hashes leaves `i * 2^k .. (i + 1) * 2^k`.
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    /// An empty tree.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of leaves.
+    pub fn len(&self) -> u64 {
+        self.levels.first().map_or(0, |leaves| leaves.len() as u64)
+    }
+
+    /// Whether the tree has no leaves.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Append a leaf hash, returning its index.
+    pub fn push(&mut self, leaf: Hash) -> u64 {
+        let index = self.len();
+        let mut hash = leaf;
+        let mut level = 0;
+        loop {
+            if self.levels.len() == level {
+                self.levels.push(Vec::new());
+            }
+            self.levels[level].push(hash);
+            let nodes = &self.levels[level];
+            if nodes.len() % 2 == 1 {
+                break;
+            }
+            hash = node_hash(&nodes[nodes.len() - 2], &nodes[nodes.len() - 1]);
+            level += 1;
+        }
+        index
+    }
+
+    /// The leaf hash at `index`.
+    pub fn leaf(&self, index: u64) -> Option<Hash> {
+        self.levels.first()?.get(usize::try_from(index).ok()?).copied()
+    }
+
+    /// Root of the whole tree.
+    pub fn root(&self) -> Hash {
+        self.root_at(self.len()).unwrap_or_else(|| sha256(&[]))
+    }
+
+    /// Root of the tree of the first `size` leaves, if it has that many.
+    pub fn root_at(&self, size: u64) -> Option<Hash> {
+        match size {
+            0 => Some(sha256(&[])),
+            size if size <= self.len() => Some(self.subtree(0, size)),
+            _ => None,
+        }
+    }
+
+    /// Audit path of the leaf `index` in the tree of the first `size`
+    /// leaves.
+    pub fn inclusion_proof(&self, index: u64, size: u64) -> Option<Vec<Hash>> {
+        if index >= size || size > self.len() {
+            return None;
+        }
+        let mut path = Vec::new();
+        self.path(index, 0, size, &mut path);
+        Some(path)
+    }
+
+    /// Proof that the tree of the first `first` leaves is a prefix of the
+    /// tree of the first `second`.
+    pub fn consistency_proof(&self, first: u64, second: u64) -> Option<Vec<Hash>> {
+        if first > second || second > self.len() {
+            return None;
+        }
+        let mut proof = Vec::new();
+        if first > 0 && first < second {
+            self.subproof(first, 0, second, true, &mut proof);
+        }
+        Some(proof)
+    }
+
+    /// Hash of leaves `lo..hi`, as RFC 6962's MTH.
+    fn subtree(&self, lo: u64, hi: u64) -> Hash {
+        let n = hi - lo;
+        if n.is_power_of_two() && lo % n == 0 {
+            return self.levels[n.trailing_zeros() as usize][(lo / n) as usize];
+        }
+        let k = split(n);
+        node_hash(&self.subtree(lo, lo + k), &self.subtree(lo + k, hi))
+    }
+
+    /// RFC 6962's PATH(m, D[lo:hi]).
+    fn path(&self, m: u64, lo: u64, hi: u64, path: &mut Vec<Hash>) {
+        let n = hi - lo;
+        if n == 1 {
+            return;
+        }
+        let k = split(n);
+        if m < k {
+            self.path(m, lo, lo + k, path);
+            path.push(self.subtree(lo + k, hi));
+        } else {
+            self.path(m - k, lo + k, hi, path);
+            path.push(self.subtree(lo, lo + k));
+        }
+    }
+
+    /// RFC 6962's SUBPROOF(m, D[lo:hi], b).
+    fn subproof(&self, m: u64, lo: u64, hi: u64, complete: bool, proof: &mut Vec<Hash>) {
+        let n = hi - lo;
+        if m == n {
+            if !complete {
+                proof.push(self.subtree(lo, hi));
+            }
+            return;
+        }
+        let k = split(n);
+        if m <= k {
+            self.subproof(m, lo, lo + k, complete, proof);
+            proof.push(self.subtree(lo + k, hi));
+        } else {
+            self.subproof(m - k, lo + k, hi, false, proof);
+            proof.push(self.subtree(lo, lo + k));
+        }
+    }
+}
+
+/// Check that `leaf` is at `index` in the tree of `size` leaves with root
+/// `root`.
+pub fn verify_inclusion(leaf: &Hash, index: u64, size: u64, path: &[Hash], root: &Hash) -> bool {
+    if index >= size {
+        return false;
+    }
+    // Variables named as in RFC 9162
+    let (mut fn_, mut sn) = (index, size - 1);
+    let mut r = *leaf;
+    for p in path {
+        if sn == 0 {
+            return false;
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            r = node_hash(p, &r);
+            while fn_ & 1 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            r = node_hash(&r, p);
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    sn == 0 && r == *root
+}
+
+/// Check that the tree of `first` leaves with root `first_root` is a prefix
+/// of the tree of `second` leaves with root `second_root`.
+pub fn verify_consistency(
+    first: u64,
+    second: u64,
+    first_root: &Hash,
+    second_root: &Hash,
+    proof: &[Hash],
+) -> bool {
+    if first > second {
+        return false;
+    }
+    if first == second {
+        return proof.is_empty() && first_root == second_root;
+    }
+    if first == 0 {
+        return proof.is_empty();
+    }
+    if proof.is_empty() {
+        return false;
+    }
+
+    let mut path: Vec<&Hash> = proof.iter().collect();
+    if first.is_power_of_two() {
+        path.insert(0, first_root);
+    }
+    // Variables named as in RFC 9162
+    let (mut fn_, mut sn) = (first - 1, second - 1);
+    while fn_ & 1 == 1 {
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    let (mut fr, mut sr) = (*path[0], *path[0]);
+    for c in &path[1..] {
+        if sn == 0 {
+            return false;
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            fr = node_hash(c, &fr);
+            sr = node_hash(c, &sr);
+            while fn_ & 1 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            sr = node_hash(&sr, c);
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    fr == *first_root && sr == *second_root && sn == 0
+}
+
+/// Sign a submission with the issuer key.
+pub fn sign_log_submission(submission: &mut LogSubmission, signer: &(impl MessageSigner + ?Sized)) -> HeshaResult<()> {
+    submission.signature = Some(signer.sign_message(&submission.signing_payload())?);
+    Ok(())
+}
+
+/// Check that a submission was signed by the issuer.
+pub fn verify_log_submission(submission: &LogSubmission, public_key: &PublicKey) -> HeshaResult<()> {
+    let signature = submission.signature.as_ref().ok_or(HeshaError::InvalidSignature)?;
+    if !verify_signature(public_key, &submission.signing_payload(), signature) {
+        return Err(HeshaError::InvalidSignature);
+    }
+    Ok(())
+}
+
+/// Sign a tree head with the log key.
+pub fn sign_tree_head(head: &mut SignedTreeHead, signer: &(impl MessageSigner + ?Sized)) -> HeshaResult<()> {
+    head.signature = Some(signer.sign_message(&head.signing_payload())?);
+    Ok(())
+}
+
+/// Check that a tree head was signed by the log.
+pub fn verify_tree_head(head: &SignedTreeHead, public_key: &PublicKey) -> HeshaResult<()> {
+    let signature = head.signature.as_ref().ok_or(HeshaError::InvalidSignature)?;
+    if !verify_signature(public_key, &head.signing_payload(), signature) {
+        return Err(HeshaError::InvalidSignature);
+    }
+    Ok(())
+}
+
+/// Check an inclusion proof for `submission` against a (verified) tree
+/// head.
+pub fn check_inclusion(
+    submission: &LogSubmission,
+    proof: &InclusionProof,
+    head: &SignedTreeHead,
+) -> HeshaResult<()> {
+    let path = proof.audit_path.iter().map(|hash| parse_hash(hash)).collect::<HeshaResult<Vec<_>>>()?;
+    let leaf = submission_leaf_hash(submission);
+    if proof.tree_size != head.tree_size
+        || !verify_inclusion(&leaf, proof.leaf_index, proof.tree_size, &path, &parse_hash(&head.root_hash)?)
+    {
+        return Err(HeshaError::CryptoError("Inclusion proof does not match the tree head".to_string()));
+    }
+    Ok(())
+}
+
+/// Check a consistency proof between two (verified) tree heads.
+pub fn check_consistency(
+    proof: &ConsistencyProof,
+    first: &SignedTreeHead,
+    second: &SignedTreeHead,
+) -> HeshaResult<()> {
+    let path = proof.proof.iter().map(|hash| parse_hash(hash)).collect::<HeshaResult<Vec<_>>>()?;
+    if proof.first != first.tree_size
+        || proof.second != second.tree_size
+        || !verify_consistency(
+            first.tree_size,
+            second.tree_size,
+            &parse_hash(&first.root_hash)?,
+            &parse_hash(&second.root_hash)?,
+            &path,
+        )
+    {
+        return Err(HeshaError::CryptoError("Tree heads are not consistent".to_string()));
+    }
+    Ok(())
+}
+
+/// Parse a hex hash.
+pub fn parse_hash(hex_hash: &str) -> HeshaResult<Hash> {
+    hex::decode(hex_hash)
+        .ok()
+        .and_then(|bytes| Hash::try_from(bytes.as_slice()).ok())
+        .ok_or_else(|| HeshaError::SerializationError(format!("Invalid hash: {}", hex_hash)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use hesha_crypto::generate_keypair;
+
+    /// RFC 6962's MTH, computed directly.
+    fn reference_root(leaves: &[Hash]) -> Hash {
+        match leaves.len() {
+            0 => sha256(&[]),
+            1 => leaves[0],
+            n => {
+                let k = split(n as u64) as usize;
+                node_hash(&reference_root(&leaves[..k]), &reference_root(&leaves[k..]))
+            }
+        }
+    }
+
+    fn tree(size: u64) -> (MerkleTree, Vec<Hash>) {
+        let mut tree = MerkleTree::new();
+        let leaves: Vec<Hash> = (0..size).map(|i| leaf_hash(&i.to_be_bytes())).collect();
+        for leaf in &leaves {
+            tree.push(*leaf);
+        }
+        (tree, leaves)
+    }
+
+    #[test]
+    fn test_roots() {
+        assert_eq!(hex::encode(MerkleTree::new().root()), hex::encode(sha256(&[])));
+        let (tree, leaves) = tree(33);
+        for size in 0..=33 {
+            assert_eq!(tree.root_at(size).unwrap(), reference_root(&leaves[..size as usize]));
+        }
+        assert_eq!(tree.root_at(34), None);
+    }
+
+    #[test]
+    fn test_inclusion_proofs() {
+        let (tree, leaves) = tree(21);
+        for size in 1..=21 {
+            let root = tree.root_at(size).unwrap();
+            for index in 0..size {
+                let path = tree.inclusion_proof(index, size).unwrap();
+                assert!(verify_inclusion(&leaves[index as usize], index, size, &path, &root));
+                assert!(!verify_inclusion(&leaf_hash(b"forged"), index, size, &path, &root));
+            }
+        }
+        assert_eq!(tree.inclusion_proof(21, 21), None);
+    }
+
+    #[test]
+    fn test_consistency_proofs() {
+        let (tree, _) = tree(21);
+        for second in 0..=21 {
+            for first in 0..=second {
+                let proof = tree.consistency_proof(first, second).unwrap();
+                let (first_root, second_root) = (tree.root_at(first).unwrap(), tree.root_at(second).unwrap());
+                assert!(verify_consistency(first, second, &first_root, &second_root, &proof), "{} {}", first, second);
+                if first > 0 && first < second {
+                    let forged = leaf_hash(b"forged");
+                    assert!(!verify_consistency(first, second, &forged, &second_root, &proof));
+                    assert!(!verify_consistency(first, second, &first_root, &forged, &proof));
+                }
+            }
+        }
+        assert_eq!(tree.consistency_proof(3, 22), None);
+    }
+
+    #[test]
+    fn test_signed_heads_and_proofs() {
+        let issuer = generate_keypair().unwrap();
+        let log = generate_keypair().unwrap();
+        let mut tree = MerkleTree::new();
+        let mut submissions = Vec::new();
+        for i in 0..5 {
+            let mut submission = LogSubmission {
+                issuer: "issuer.example.com".to_string(),
+                attestation_hash: attestation_hash(&format!("jwt-{}", i)),
+                key_id: None,
+                submitted_at: Utc::now(),
+                signature: None,
+            };
+            sign_log_submission(&mut submission, &issuer.private).unwrap();
+            verify_log_submission(&submission, &issuer.public).unwrap();
+            tree.push(submission_leaf_hash(&submission));
+            submissions.push(submission);
+        }
+        assert!(verify_log_submission(&submissions[0], &log.public).is_err());
+
+        let head = |size: u64| {
+            let mut head = SignedTreeHead {
+                log_id: "log.example.com".to_string(),
+                tree_size: size,
+                root_hash: hex::encode(tree.root_at(size).unwrap()),
+                timestamp: Utc::now(),
+                signature: None,
+            };
+            sign_tree_head(&mut head, &log.private).unwrap();
+            head
+        };
+        let (old, new) = (head(3), head(5));
+        verify_tree_head(&new, &log.public).unwrap();
+        assert!(verify_tree_head(&new, &issuer.public).is_err());
+
+        let proof = InclusionProof {
+            leaf_index: 1,
+            tree_size: 5,
+            audit_path: tree.inclusion_proof(1, 5).unwrap().iter().map(hex::encode).collect(),
+        };
+        check_inclusion(&submissions[1], &proof, &new).unwrap();
+        assert!(check_inclusion(&submissions[2], &proof, &new).is_err());
+        assert!(check_inclusion(&submissions[1], &proof, &old).is_err());
+
+        let proof = ConsistencyProof {
+            first: 3,
+            second: 5,
+            proof: tree.consistency_proof(3, 5).unwrap().iter().map(hex::encode).collect(),
+        };
+        check_consistency(&proof, &old, &new).unwrap();
+        assert!(check_consistency(&proof, &new, &old).is_err());
+    }
+}
@@ -0,0 +1,248 @@
+//! Verification of many attestations against known keys.
+//!
+//! Checking an attestation against a pinned key or trust bundle is pure
+//! CPU work: two Ed25519 signature checks and a few hashes. Audits of
+//! stored attestations run millions of them, so with the `parallel`
+//! feature a [`BatchVerifier`] can spread a batch over a rayon thread pool
+//! of a chosen size. Results always come back in input order.
+//!
+//! The `verify_stream_*` methods take attestations from an iterator and
+//! yield each one back with its result as soon as it is checked, holding
+//! at most one chunk at a time, so inputs too large for memory (a reader's
+//! lines, a database cursor) can be audited without loading them first.
+//!
+//! Batches that need key discovery or revocation checks are network-bound
+//! and are better served by running [`VerificationPolicy::verify`]
+//! concurrently on an async runtime.
+
+use crate::verification::policy::VerificationPolicy;
+use crate::verification::trust_bundle::TrustBundle;
+use hesha_types::{HeshaResult, PublicKey, VerifiedAttestation};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "parallel")]
+use std::sync::OnceLock;
+
+/// Attestations verified together when streaming on a thread pool.
+#[cfg(feature = "parallel")]
+const STREAM_CHUNK: usize = 1024;
+
+/// Verifies batches of attestations under one policy.
+///
+/// Runs on the calling thread unless given a thread budget with
+/// [`with_threads`](Self::with_threads).
+pub struct BatchVerifier {
+    policy: VerificationPolicy,
+    #[cfg(feature = "parallel")]
+    threads: Option<usize>,
+    // Built on first use; None if the threads couldn't be started
+    #[cfg(feature = "parallel")]
+    pool: OnceLock<Option<rayon::ThreadPool>>,
+}
+
+impl BatchVerifier {
+    /// A verifier applying `policy`.
+    pub fn new(policy: VerificationPolicy) -> Self {
+        Self {
+            policy,
+            #[cfg(feature = "parallel")]
+            threads: None,
+            #[cfg(feature = "parallel")]
+            pool: OnceLock::new(),
+        }
+    }
+
+    /// Verify on a pool of `threads` threads; 0 uses one per CPU.
+    ///
+    /// The pool is the verifier's own, so a batch never takes more than
+    /// its budget from other work in the process.
+    #[cfg(feature = "parallel")]
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self.pool = OnceLock::new();
+        self
+    }
+
+    /// The policy applied.
+    pub fn policy(&self) -> &VerificationPolicy {
+        &self.policy
+    }
+
+    /// Verify every attestation in `jwts` with the issuer key `issuer_key`.
+    pub fn verify_with_key<S>(&self, jwts: &[S], issuer_key: &PublicKey) -> Vec<HeshaResult<VerifiedAttestation>>
+    where
+        S: AsRef<str> + Sync,
+    {
+        self.run(jwts, |jwt| self.policy.verify_with_key(jwt, issuer_key))
+    }
+
+    /// Verify every attestation in `jwts` against a trust bundle.
+    pub fn verify_with_bundle<S>(&self, jwts: &[S], bundle: &TrustBundle) -> Vec<HeshaResult<VerifiedAttestation>>
+    where
+        S: AsRef<str> + Sync,
+    {
+        self.run(jwts, |jwt| self.policy.verify_with_bundle(jwt, bundle))
+    }
+
+    /// Verify attestations from `jwts` with the issuer key `issuer_key` as
+    /// they are consumed, yielding each with its result in input order.
+    pub fn verify_stream_with_key<'a, I>(
+        &'a self,
+        jwts: I,
+        issuer_key: &'a PublicKey,
+    ) -> impl Iterator<Item = (I::Item, HeshaResult<VerifiedAttestation>)> + 'a
+    where
+        I: IntoIterator,
+        I::IntoIter: 'a,
+        I::Item: AsRef<str> + Sync + 'a,
+    {
+        self.stream(jwts, move |jwt| self.policy.verify_with_key(jwt, issuer_key))
+    }
+
+    /// Verify attestations from `jwts` against a trust bundle as they are
+    /// consumed, yielding each with its result in input order.
+    pub fn verify_stream_with_bundle<'a, I>(
+        &'a self,
+        jwts: I,
+        bundle: &'a TrustBundle,
+    ) -> impl Iterator<Item = (I::Item, HeshaResult<VerifiedAttestation>)> + 'a
+    where
+        I: IntoIterator,
+        I::IntoIter: 'a,
+        I::Item: AsRef<str> + Sync + 'a,
+    {
+        self.stream(jwts, move |jwt| self.policy.verify_with_bundle(jwt, bundle))
+    }
+
+    fn stream<'a, I, F>(&'a self, jwts: I, verify: F) -> impl Iterator<Item = (I::Item, HeshaResult<VerifiedAttestation>)> + 'a
+    where
+        I: IntoIterator,
+        I::IntoIter: 'a,
+        I::Item: AsRef<str> + Sync + 'a,
+        F: Fn(&str) -> HeshaResult<VerifiedAttestation> + Sync + 'a,
+    {
+        let chunk_size = self.chunk_size();
+        let mut jwts = jwts.into_iter();
+        std::iter::from_fn(move || {
+            let chunk: Vec<I::Item> = jwts.by_ref().take(chunk_size).collect();
+            if chunk.is_empty() {
+                return None;
+            }
+            let results = self.run(&chunk, &verify);
+            Some(chunk.into_iter().zip(results))
+        })
+        .flatten()
+    }
+
+    /// Attestations to read ahead when streaming: a chunk to share among
+    /// the pool's threads, or one at a time on the calling thread.
+    fn chunk_size(&self) -> usize {
+        #[cfg(feature = "parallel")]
+        if self.threads.is_some() {
+            return STREAM_CHUNK;
+        }
+        1
+    }
+
+    fn run<S, F>(&self, jwts: &[S], verify: F) -> Vec<HeshaResult<VerifiedAttestation>>
+    where
+        S: AsRef<str> + Sync,
+        F: Fn(&str) -> HeshaResult<VerifiedAttestation> + Sync,
+    {
+        #[cfg(feature = "parallel")]
+        if let Some(pool) = self.pool() {
+            return pool.install(|| jwts.par_iter().map(|jwt| verify(jwt.as_ref())).collect());
+        }
+        jwts.iter().map(|jwt| verify(jwt.as_ref())).collect()
+    }
+
+    #[cfg(feature = "parallel")]
+    fn pool(&self) -> Option<&rayon::ThreadPool> {
+        let threads = self.threads?;
+        self.pool
+            .get_or_init(|| {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .thread_name(|i| format!("hesha-verify-{}", i))
+                    .build()
+                    .ok()
+            })
+            .as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::create::create_attestation;
+    use hesha_crypto::generate_keypair;
+    use hesha_types::{HeshaError, PhoneNumber, ProxyNumber};
+
+    fn attestations(count: usize) -> (PublicKey, Vec<String>) {
+        let issuer = generate_keypair().unwrap();
+        let user = generate_keypair().unwrap();
+        let jwts = (0..count)
+            .map(|i| {
+                create_attestation(
+                    "issuer.example.com",
+                    &issuer.private,
+                    &PhoneNumber::new("+14155552671").unwrap(),
+                    &ProxyNumber::new(format!("+1001555{:07}", i)).unwrap(),
+                    &user.public,
+                )
+                .unwrap()
+            })
+            .collect();
+        (issuer.public, jwts)
+    }
+
+    #[test]
+    fn test_batch_keeps_input_order() {
+        let (key, mut jwts) = attestations(6);
+        jwts[3] = "not-a-jwt".to_string();
+        let results = BatchVerifier::new(VerificationPolicy::default()).verify_with_key(&jwts, &key);
+        assert_eq!(results.len(), 6);
+        for (i, result) in results.iter().enumerate() {
+            match result {
+                Ok(verified) => assert_eq!(verified.attestation.proxy_number.as_str(), format!("+1001555{:07}", i)),
+                Err(e) => {
+                    assert_eq!(i, 3);
+                    assert!(matches!(e, HeshaError::InvalidAttestation(_)));
+                }
+            }
+        }
+
+        let other = generate_keypair().unwrap().public;
+        let results = BatchVerifier::new(VerificationPolicy::default()).verify_with_key(&jwts, &other);
+        assert!(results.iter().all(Result::is_err));
+    }
+
+    #[test]
+    fn test_stream_yields_inputs_with_results() {
+        let (key, mut jwts) = attestations(5);
+        jwts[1] = String::new();
+        let verifier = BatchVerifier::new(VerificationPolicy::default());
+        #[cfg(feature = "parallel")]
+        let verifier = verifier.with_threads(2);
+        let results: Vec<_> = verifier.verify_stream_with_key(jwts.iter(), &key).collect();
+        assert_eq!(results.len(), 5);
+        for (i, (jwt, result)) in results.iter().enumerate() {
+            assert_eq!(*jwt, &jwts[i]);
+            assert_eq!(result.is_ok(), i != 1);
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let (key, jwts) = attestations(64);
+        let sequential = BatchVerifier::new(VerificationPolicy::default()).verify_with_key(&jwts, &key);
+        let parallel = BatchVerifier::new(VerificationPolicy::default())
+            .with_threads(4)
+            .verify_with_key(&jwts, &key);
+        let jtis = |results: &[HeshaResult<VerifiedAttestation>]| -> Vec<String> {
+            results.iter().map(|result| result.as_ref().unwrap().attestation.jti.clone()).collect()
+        };
+        assert_eq!(jtis(&sequential), jtis(&parallel));
+    }
+}
@@ -0,0 +1,299 @@
+//! Cached verification results for high-traffic relying parties.
+//!
+//! A service that sees the same attestation on every request of a session
+//! doesn't need to check its signatures, or look up its issuer's keys,
+//! each time. [`VerificationCache`] keeps successful verifications by JWT
+//! ID for a configurable TTL, never past the attestation's own expiry.
+//!
+//! Revocations are the cache's to honour, since a cached result outlives
+//! the check it came from: feed it the issuer's revocation lists with
+//! [`VerificationCache::apply_revocations`], or single revocations (from a
+//! webhook, say) with [`VerificationCache::revoke`]. Revoked attestations
+//! are dropped and refused from then on, cached or not.
+
+use crate::attestation::parse::parse_attestation;
+use crate::verification::policy::VerificationPolicy;
+use crate::verification::trust_bundle::TrustBundle;
+use chrono::Utc;
+use hesha_types::{HeshaError, HeshaResult, PublicKey, RevocationList, VerifiedAttestation};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a verification is reused by default.
+pub const DEFAULT_VERIFICATION_TTL: Duration = Duration::from_secs(300);
+
+/// Most verifications kept by default.
+pub const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// A cached verification.
+struct Entry {
+    /// SHA-256 of the JWT, so another token reusing the JWT ID misses.
+    digest: [u8; 32],
+    verified: VerifiedAttestation,
+    expires: Instant,
+}
+
+/// Verification results cached by JWT ID.
+pub struct VerificationCache {
+    policy: VerificationPolicy,
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<String, Entry>>,
+    revoked: Mutex<HashSet<String>>,
+}
+
+impl VerificationCache {
+    /// A cache reusing verifications for `ttl`, under the baseline policy.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            policy: VerificationPolicy::default(),
+            ttl,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            entries: Mutex::new(HashMap::new()),
+            revoked: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Verify under `policy` instead of the baseline.
+    pub fn with_policy(mut self, policy: VerificationPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Keep at most `max_entries` verifications, dropping the ones closest
+    /// to expiry first.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Verify an attestation, discovering the issuer's key, unless it was
+    /// verified within the TTL.
+    pub async fn verify(&self, jwt: &str) -> HeshaResult<VerifiedAttestation> {
+        if let Some(verified) = self.lookup(jwt)? {
+            return Ok(verified);
+        }
+        self.store(jwt, self.policy.verify(jwt).await?)
+    }
+
+    /// Verify an attestation with a known issuer key, unless it was
+    /// verified within the TTL.
+    pub fn verify_with_key(&self, jwt: &str, issuer_key: &PublicKey) -> HeshaResult<VerifiedAttestation> {
+        if let Some(verified) = self.lookup(jwt)? {
+            return Ok(verified);
+        }
+        self.store(jwt, self.policy.verify_with_key(jwt, issuer_key)?)
+    }
+
+    /// Verify an attestation against a trust bundle, unless it was
+    /// verified within the TTL.
+    pub fn verify_with_bundle(&self, jwt: &str, bundle: &TrustBundle) -> HeshaResult<VerifiedAttestation> {
+        if let Some(verified) = self.lookup(jwt)? {
+            return Ok(verified);
+        }
+        self.store(jwt, self.policy.verify_with_bundle(jwt, bundle)?)
+    }
+
+    /// Drop and refuse from now on every attestation on `list`.
+    ///
+    /// Pass each list the issuer publishes; entries from earlier lists are
+    /// kept, since issuers don't take revocations back.
+    pub fn apply_revocations(&self, list: &RevocationList) -> usize {
+        let jtis = list.entries.iter().map(|entry| entry.jti.clone()).collect::<Vec<_>>();
+        self.revoked_set().extend(jtis.iter().cloned());
+        let mut entries = self.entries();
+        jtis.iter().filter(|jti| entries.remove(jti.as_str()).is_some()).count()
+    }
+
+    /// Drop and refuse from now on the attestation `jti`.
+    pub fn revoke(&self, jti: &str) {
+        self.revoked_set().insert(jti.to_string());
+        self.entries().remove(jti);
+    }
+
+    /// Drop a cached verification, so the next use checks it again.
+    pub fn invalidate(&self, jti: &str) {
+        self.entries().remove(jti);
+    }
+
+    /// Drop every cached verification. Revocations are kept.
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    /// Number of cached verifications, including expired ones not yet
+    /// dropped.
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Whether no verifications are cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+
+    /// The cached verification of `jwt`, if current. Refuses revoked
+    /// attestations.
+    fn lookup(&self, jwt: &str) -> HeshaResult<Option<VerifiedAttestation>> {
+        // Reading the claims is far cheaper than checking the signatures
+        let jti = parse_attestation(jwt)?.jti;
+        self.check_revoked(&jti)?;
+        let digest = hesha_crypto::sha256(jwt.as_bytes());
+        let mut entries = self.entries();
+        let hit = match entries.get(&jti) {
+            Some(entry) if entry.digest == digest && entry.expires > Instant::now() => Some(entry.verified.clone()),
+            Some(_) => {
+                entries.remove(&jti);
+                None
+            }
+            None => None,
+        };
+        hesha_metrics::record_cache_lookup(hesha_metrics::VERIFICATION_CACHE, hit.is_some());
+        Ok(hit)
+    }
+
+    /// Cache a fresh verification of `jwt` and pass it through.
+    fn store(&self, jwt: &str, verified: VerifiedAttestation) -> HeshaResult<VerifiedAttestation> {
+        let jti = verified.attestation.jti.clone();
+        // Revoked while it was being verified
+        self.check_revoked(&jti)?;
+        let remaining = (verified.attestation.exp - Utc::now()).to_std().unwrap_or_default();
+        let entry = Entry {
+            digest: hesha_crypto::sha256(jwt.as_bytes()),
+            verified: verified.clone(),
+            expires: Instant::now() + self.ttl.min(remaining),
+        };
+        let mut entries = self.entries();
+        if entries.len() >= self.max_entries && !entries.contains_key(&jti) {
+            let now = Instant::now();
+            entries.retain(|_, entry| entry.expires > now);
+            if entries.len() >= self.max_entries {
+                let soonest = entries.iter().min_by_key(|(_, entry)| entry.expires).map(|(jti, _)| jti.clone());
+                if let Some(soonest) = soonest {
+                    entries.remove(&soonest);
+                }
+            }
+        }
+        if self.max_entries > 0 {
+            entries.insert(jti, entry);
+        }
+        Ok(verified)
+    }
+
+    fn check_revoked(&self, jti: &str) -> HeshaResult<()> {
+        if self.revoked_set().contains(jti) {
+            return Err(HeshaError::InvalidAttestation(format!("Attestation {} has been revoked", jti)));
+        }
+        Ok(())
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn revoked_set(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.revoked.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for VerificationCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_VERIFICATION_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::create::create_attestation;
+    use hesha_crypto::generate_keypair;
+    use hesha_types::{KeyPair, PhoneNumber, ProxyNumber, RevocationEntry};
+
+    fn issue(issuer: &KeyPair) -> String {
+        create_attestation(
+            "issuer.com",
+            &issuer.private,
+            &PhoneNumber::new("+14155552671").unwrap(),
+            &ProxyNumber::new("+1001234567890").unwrap(),
+            &generate_keypair().unwrap().public,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_reuses_verifications() {
+        let issuer = generate_keypair().unwrap();
+        let other = generate_keypair().unwrap();
+        let cache = VerificationCache::default();
+        let jwt = issue(&issuer);
+
+        assert!(cache.verify_with_key(&jwt, &other.public).is_err());
+        assert!(cache.is_empty());
+        let verified = cache.verify_with_key(&jwt, &issuer.public).unwrap();
+        assert_eq!(cache.len(), 1);
+        // Served from the cache, so the key isn't consulted
+        let cached = cache.verify_with_key(&jwt, &other.public).unwrap();
+        assert_eq!(cached.verified_at, verified.verified_at);
+
+        // A different token isn't served another's result
+        let forged = format!("{}x", jwt);
+        assert!(cache.verify_with_key(&forged, &other.public).is_err());
+
+        cache.invalidate(&verified.attestation.jti);
+        assert!(cache.verify_with_key(&jwt, &other.public).is_err());
+    }
+
+    #[test]
+    fn test_expires_after_ttl() {
+        let issuer = generate_keypair().unwrap();
+        let other = generate_keypair().unwrap();
+        let cache = VerificationCache::new(Duration::ZERO);
+        let jwt = issue(&issuer);
+        cache.verify_with_key(&jwt, &issuer.public).unwrap();
+        assert!(cache.verify_with_key(&jwt, &other.public).is_err());
+    }
+
+    #[test]
+    fn test_revocations() {
+        let issuer = generate_keypair().unwrap();
+        let cache = VerificationCache::default();
+        let (first, second) = (issue(&issuer), issue(&issuer));
+        let first_jti = cache.verify_with_key(&first, &issuer.public).unwrap().attestation.jti;
+        let second_jti = cache.verify_with_key(&second, &issuer.public).unwrap().attestation.jti;
+
+        let list = RevocationList {
+            issuer: "issuer.com".to_string(),
+            updated_at: Utc::now(),
+            entries: vec![
+                RevocationEntry { jti: first_jti, revoked_at: Utc::now(), reason: None },
+                RevocationEntry { jti: "never-seen".to_string(), revoked_at: Utc::now(), reason: None },
+            ],
+            next: None,
+            signature: None,
+        };
+        assert_eq!(cache.apply_revocations(&list), 1);
+        assert_eq!(cache.len(), 1);
+        // Refused even though the signature still checks out
+        assert!(cache.verify_with_key(&first, &issuer.public).is_err());
+        cache.verify_with_key(&second, &issuer.public).unwrap();
+
+        cache.revoke(&second_jti);
+        assert!(cache.is_empty());
+        assert!(cache.verify_with_key(&second, &issuer.public).is_err());
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let issuer = generate_keypair().unwrap();
+        let cache = VerificationCache::default().with_max_entries(2);
+        for _ in 0..3 {
+            cache.verify_with_key(&issue(&issuer), &issuer.public).unwrap();
+        }
+        assert_eq!(cache.len(), 2);
+
+        let disabled = VerificationCache::default().with_max_entries(0);
+        disabled.verify_with_key(&issue(&issuer), &issuer.public).unwrap();
+        assert!(disabled.is_empty());
+    }
+}
@@ -0,0 +1,119 @@
+//! Challenge-response verification.
+//!
+//! A service sends the user a [`Challenge`]; the wallet answers with a
+//! [`ChallengeResponse`] signed with the attestation's user key over the
+//! challenge's [`signing_message`](Challenge::signing_message). Checked
+//! together with the attestation's issuer signature, it shows the holder of
+//! the attestation approved this verification.
+
+use hesha_types::{
+    Attestation, Challenge, ChallengeResponse, HeshaError, HeshaResult, PrivateKey,
+};
+use chrono::{Duration, Utc};
+
+/// How long a challenge can be answered, per the protocol.
+pub const CHALLENGE_MAX_AGE_SECS: i64 = 300;
+
+/// Answer a challenge with the user key of the attestation `attestation_id`.
+pub fn sign_challenge(
+    challenge: &Challenge,
+    attestation_id: &str,
+    user_key: &PrivateKey,
+) -> HeshaResult<ChallengeResponse> {
+    Ok(ChallengeResponse {
+        challenge: challenge.clone(),
+        signature: hesha_crypto::sign_message(user_key, &challenge.signing_message())?,
+        attestation_id: attestation_id.to_string(),
+    })
+}
+
+/// Check a challenge response against the (already verified) attestation
+/// it was made with.
+///
+/// The response must name the attestation, be signed with its user key and
+/// answer a challenge at most `max_age` old. Challenges dated in the future
+/// are refused. Whether the nonce was used before is the caller's to track.
+pub fn verify_challenge_response(
+    response: &ChallengeResponse,
+    attestation: &Attestation,
+    max_age: Duration,
+) -> HeshaResult<()> {
+    if response.attestation_id != attestation.jti {
+        return Err(HeshaError::InvalidAttestation(format!(
+            "Challenge response is for attestation {}, not {}",
+            response.attestation_id, attestation.jti
+        )));
+    }
+
+    let age = Utc::now() - response.challenge.timestamp;
+    if age > max_age || age < Duration::zero() {
+        return Err(HeshaError::InvalidNonce);
+    }
+
+    if !hesha_crypto::verify_signature(
+        &attestation.user_pubkey,
+        &response.challenge.signing_message(),
+        &response.signature,
+    ) {
+        return Err(HeshaError::InvalidSignature);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::create::create_attestation;
+    use crate::attestation::parse::parse_attestation;
+    use hesha_crypto::generate_keypair;
+    use hesha_types::{Nonce, PhoneNumber, ProxyNumber};
+
+    #[test]
+    fn test_challenge_response() {
+        let issuer_key = generate_keypair().unwrap();
+        let user_key = generate_keypair().unwrap();
+        let jwt = create_attestation(
+            "issuer.com",
+            &issuer_key.private,
+            &PhoneNumber::new("+1234567890").unwrap(),
+            &ProxyNumber::new("+23400123456789").unwrap(),
+            &user_key.public,
+        ).unwrap();
+        let attestation = parse_attestation(&jwt).unwrap();
+        let max_age = Duration::seconds(CHALLENGE_MAX_AGE_SECS);
+        let challenge = |age: i64| Challenge {
+            nonce: Nonce::new("a1b2c3d4e5f6a1b2c3d4e5f6"),
+            service_context: "app.example.com".to_string(),
+            timestamp: Utc::now() - Duration::seconds(age),
+        };
+
+        let response = sign_challenge(&challenge(10), &attestation.jti, &user_key.private).unwrap();
+        verify_challenge_response(&response, &attestation, max_age).unwrap();
+
+        // Signed by someone else
+        let other = generate_keypair().unwrap();
+        let forged = sign_challenge(&challenge(10), &attestation.jti, &other.private).unwrap();
+        assert!(matches!(
+            verify_challenge_response(&forged, &attestation, max_age),
+            Err(HeshaError::InvalidSignature)
+        ));
+
+        // Answering a different challenge than signed
+        let mut altered = response.clone();
+        altered.challenge.service_context = "evil.example.com".to_string();
+        assert!(verify_challenge_response(&altered, &attestation, max_age).is_err());
+
+        // Too old, or from the future
+        for age in [CHALLENGE_MAX_AGE_SECS + 1, -60] {
+            let response = sign_challenge(&challenge(age), &attestation.jti, &user_key.private).unwrap();
+            assert!(matches!(
+                verify_challenge_response(&response, &attestation, max_age),
+                Err(HeshaError::InvalidNonce)
+            ));
+        }
+
+        // Naming another attestation
+        let response = sign_challenge(&challenge(10), "other-jti", &user_key.private).unwrap();
+        assert!(verify_challenge_response(&response, &attestation, max_age).is_err());
+    }
+}
@@ -1,18 +1,38 @@
 //! Issuer public key discovery via .well-known.
 
+use hesha_metrics::DiscoveryResult;
 use hesha_types::{HeshaError, HeshaResult, IssuerInfo, PublicKey};
-use reqwest::Client;
+use reqwest::{header, Client, StatusCode};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 /// Cache for issuer public keys.
+/// 
+/// Expired entries are kept with the ETag and Last-Modified values they
+/// were served with, so the next lookup can ask the issuer whether its keys
+/// changed instead of downloading them again.
 #[derive(Clone)]
 pub struct IssuerKeyCache {
-    cache: Arc<Mutex<HashMap<String, (PublicKey, Instant)>>>,
+    cache: Arc<Mutex<HashMap<String, CachedKey>>>,
     ttl: Duration,
 }
 
+/// A cached key and the validators of the document it came from.
+#[derive(Clone)]
+struct CachedKey {
+    key: PublicKey,
+    validators: Validators,
+    fetched: Instant,
+}
+
+/// Values for revalidating a discovery document.
+#[derive(Debug, Clone, Default)]
+struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
 impl IssuerKeyCache {
     /// Create a new cache with the given TTL.
     pub fn new(ttl: Duration) -> Self {
@@ -25,10 +45,10 @@ impl IssuerKeyCache {
     /// Get a key from the cache if not expired.
     pub fn get(&self, domain: &str) -> Option<PublicKey> {
         let cache = self.cache.lock().ok()?;
-        let (key, inserted) = cache.get(domain)?;
+        let entry = cache.get(domain)?;
         
-        if inserted.elapsed() < self.ttl {
-            Some(key.clone())
+        if entry.fetched.elapsed() < self.ttl {
+            Some(entry.key.clone())
         } else {
             None
         }
@@ -36,9 +56,7 @@ impl IssuerKeyCache {
     
     /// Insert a key into the cache.
     pub fn insert(&self, domain: String, key: PublicKey) {
-        if let Ok(mut cache) = self.cache.lock() {
-            cache.insert(domain, (key, Instant::now()));
-        }
+        self.store(domain, key, Validators::default());
     }
     
     /// Clear the cache.
@@ -47,6 +65,26 @@ impl IssuerKeyCache {
             cache.clear();
         }
     }
+    
+    /// The entry for a domain, expired or not.
+    fn entry(&self, domain: &str) -> Option<CachedKey> {
+        self.cache.lock().ok()?.get(domain).cloned()
+    }
+    
+    fn store(&self, domain: String, key: PublicKey, validators: Validators) {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(domain, CachedKey { key, validators, fetched: Instant::now() });
+        }
+    }
+    
+    /// Restart the TTL of an entry the issuer confirmed is current.
+    fn refresh(&self, domain: &str) {
+        if let Ok(mut cache) = self.cache.lock() {
+            if let Some(entry) = cache.get_mut(domain) {
+                entry.fetched = Instant::now();
+            }
+        }
+    }
 }
 
 impl Default for IssuerKeyCache {
@@ -62,71 +100,71 @@ impl Default for IssuerKeyCache {
 /// - Validate the response format
 /// - Cache results to prevent DoS
 pub async fn discover_issuer_key(domain: &str) -> HeshaResult<PublicKey> {
-    // Build URL - use HTTP for localhost, HTTPS for everything else
-    let url = if domain.starts_with("http://") || domain.starts_with("https://") {
-        return Err(HeshaError::InvalidAttestation(
-            "Domain should not include protocol".to_string()
-        ));
-    } else if domain.starts_with("localhost") || domain.starts_with("127.0.0.1") {
-        format!("http://{}/.well-known/hesha/pubkey.json", domain)
-    } else {
-        format!("https://{}/.well-known/hesha/pubkey.json", domain)
-    };
-    
-    // Make request with timeout
-    let client = Client::builder()
-        .timeout(Duration::from_secs(10))
-        .build()
-        .map_err(|e| HeshaError::CryptoError(format!("HTTP client error: {}", e)))?;
-    
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| HeshaError::CryptoError(format!("Key discovery failed: {}", e)))?;
-    
-    if !response.status().is_success() {
-        return Err(HeshaError::CryptoError(format!(
-            "Key discovery failed with status: {}",
-            response.status()
-        )));
-    }
-    
-    let issuer_info: IssuerInfo = response
-        .json()
-        .await
-        .map_err(|e| HeshaError::CryptoError(format!("Invalid issuer info JSON: {}", e)))?;
-    
-    // Validate algorithm
-    if issuer_info.algorithm != "Ed25519" {
-        return Err(HeshaError::CryptoError(format!(
-            "Unsupported algorithm: {}",
-            issuer_info.algorithm
-        )));
-    }
-    
-    Ok(issuer_info.public_key)
+    Ok(discover_issuer_info(domain).await?.public_key)
 }
 
 /// Discover an issuer's public key with caching.
+/// 
+/// Once the cached key expires, the issuer is asked with `If-None-Match`
+/// and `If-Modified-Since` whether its keys changed; on `304 Not Modified`
+/// the cached key is kept for another TTL.
 pub async fn discover_issuer_key_cached(
     domain: &str,
     cache: &IssuerKeyCache,
 ) -> HeshaResult<PublicKey> {
     // Check cache first
-    if let Some(key) = cache.get(domain) {
+    let cached = cache.get(domain);
+    hesha_metrics::record_cache_lookup(hesha_metrics::ISSUER_KEY_CACHE, cached.is_some());
+    if let Some(key) = cached {
         return Ok(key);
     }
     
-    // Discover and cache
-    let key = discover_issuer_key(domain).await?;
-    cache.insert(domain.to_string(), key.clone());
-    
-    Ok(key)
+    // Revalidate or discover, and cache
+    let stale = cache.entry(domain);
+    match fetch_issuer_info(domain, stale.as_ref().map(|entry| &entry.validators)).await? {
+        Some((info, validators)) => {
+            cache.store(domain.to_string(), info.public_key.clone(), validators);
+            Ok(info.public_key)
+        }
+        None => {
+            cache.refresh(domain);
+            stale.map(|entry| entry.key).ok_or_else(|| {
+                HeshaError::NetworkError("Key discovery returned 304 for an uncached key".to_string())
+            })
+        }
+    }
 }
 
 /// Discover issuer information including service discovery metadata.
 pub async fn discover_issuer_info(domain: &str) -> HeshaResult<IssuerInfo> {
+    let (info, _) = fetch_issuer_info(domain, None).await?.ok_or_else(|| {
+        HeshaError::NetworkError("Key discovery returned 304 for an unconditional request".to_string())
+    })?;
+    Ok(info)
+}
+
+/// Fetch an issuer's discovery document, conditionally when `validators`
+/// are given. Returns None when the issuer answers `304 Not Modified`.
+async fn fetch_issuer_info(
+    domain: &str,
+    validators: Option<&Validators>,
+) -> HeshaResult<Option<(IssuerInfo, Validators)>> {
+    let started = Instant::now();
+    let result = request_issuer_info(domain, validators).await;
+    let outcome = match &result {
+        Ok(Some(_)) => DiscoveryResult::Fetched,
+        Ok(None) => DiscoveryResult::NotModified,
+        Err(_) => DiscoveryResult::Failed,
+    };
+    hesha_metrics::record_discovery(outcome, started.elapsed());
+    result
+}
+
+/// [`fetch_issuer_info`] without the timing.
+async fn request_issuer_info(
+    domain: &str,
+    validators: Option<&Validators>,
+) -> HeshaResult<Option<(IssuerInfo, Validators)>> {
     // Build URL - use HTTP for localhost, HTTPS for everything else
     let url = if domain.starts_with("http://") || domain.starts_with("https://") {
         return Err(HeshaError::InvalidAttestation(
@@ -144,19 +182,41 @@ pub async fn discover_issuer_info(domain: &str) -> HeshaResult<IssuerInfo> {
         .build()
         .map_err(|e| HeshaError::CryptoError(format!("HTTP client error: {}", e)))?;
     
-    let response = client
-        .get(&url)
+    let mut request = client.get(&url);
+    if let Some(etag) = validators.and_then(|v| v.etag.as_deref()) {
+        request = request.header(header::IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = validators.and_then(|v| v.last_modified.as_deref()) {
+        request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+    }
+    
+    let response = request
         .send()
         .await
-        .map_err(|e| HeshaError::CryptoError(format!("Key discovery failed: {}", e)))?;
+        .map_err(|e| HeshaError::NetworkError(format!("Key discovery failed: {}", e)))?;
+    
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
     
     if !response.status().is_success() {
-        return Err(HeshaError::CryptoError(format!(
+        return Err(HeshaError::NetworkError(format!(
             "Key discovery failed with status: {}",
             response.status()
         )));
     }
     
+    let header_value = |name| {
+        response.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    let validators = Validators {
+        etag: header_value(header::ETAG),
+        last_modified: header_value(header::LAST_MODIFIED),
+    };
+    
     let issuer_info: IssuerInfo = response
         .json()
         .await
@@ -170,13 +230,39 @@ pub async fn discover_issuer_info(domain: &str) -> HeshaResult<IssuerInfo> {
         )));
     }
     
-    Ok(issuer_info)
+    Ok(Some((issuer_info, validators)))
 }
 
 /// Resolve trust domain to actual service domain.
 /// This handles the case where a trust domain (e.g., example.com) delegates
 /// to a service domain (e.g., api.example.com).
 pub async fn resolve_trust_domain(trust_domain: &str) -> HeshaResult<(String, PublicKey)> {
+    let (service_domain, info) = resolve_issuer_info(trust_domain).await?;
+    Ok((service_domain, info.public_key))
+}
+
+/// Resolve a trust domain and pick the published key named `key_id`.
+/// 
+/// `key_id` is the `kid` header of the attestation being verified; without
+/// one, the issuer's current key is returned.
+pub async fn resolve_issuer_key(
+    trust_domain: &str,
+    key_id: Option<&str>,
+) -> HeshaResult<(String, PublicKey)> {
+    let (service_domain, info) = resolve_issuer_info(trust_domain).await?;
+    let key = info.key(key_id).cloned().ok_or_else(|| {
+        HeshaError::InvalidAttestation(format!(
+            "Issuer {} does not publish key {}",
+            trust_domain,
+            key_id.unwrap_or_default()
+        ))
+    })?;
+    Ok((service_domain, key))
+}
+
+/// Resolve a trust domain to its service domain and every key the issuer
+/// publishes, for verifiers caching them.
+pub async fn resolve_issuer_info(trust_domain: &str) -> HeshaResult<(String, IssuerInfo)> {
     // First, try to discover issuer info from the trust domain
     match discover_issuer_info(trust_domain).await {
         Ok(info) => {
@@ -196,24 +282,24 @@ pub async fn resolve_trust_domain(trust_domain: &str) -> HeshaResult<(String, Pu
                             )));
                         }
                         
-                        // Return the service domain and the public key
-                        Ok((service_domain, info.public_key))
+                        // Return the service domain and its keys
+                        Ok((service_domain, info))
                     }
                     _ => {
                         // Unknown relationship type, use trust domain directly
-                        Ok((trust_domain.to_string(), info.public_key))
+                        Ok((trust_domain.to_string(), info))
                     }
                 }
             } else {
                 // No service info, use trust domain directly
-                Ok((trust_domain.to_string(), info.public_key))
+                Ok((trust_domain.to_string(), info))
             }
         }
         Err(_) => {
             // Failed to get info from trust domain, maybe it's the service domain itself
-            // Try to discover the key directly
-            let key = discover_issuer_key(trust_domain).await?;
-            Ok((trust_domain.to_string(), key))
+            // Try to discover the keys directly
+            let info = discover_issuer_info(trust_domain).await?;
+            Ok((trust_domain.to_string(), info))
         }
     }
 }
@@ -279,6 +365,54 @@ mod tests {
         assert!(cache.get("example.com").is_none());
     }
     
+    #[tokio::test]
+    async fn test_conditional_refresh() {
+        use wiremock::matchers::{header, header_exists, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        let key = PublicKey::from_bytes([42u8; 32]);
+        let info = IssuerInfo {
+            public_key: key.clone(),
+            algorithm: "Ed25519".to_string(),
+            created_at: chrono::Utc::now(),
+            key_id: None,
+            service_info: None,
+            keys: Vec::new(),
+        };
+        Mock::given(method("GET"))
+            .and(path("/.well-known/hesha/pubkey.json"))
+            .and(header("if-none-match", "\"v1\""))
+            .and(header_exists("if-modified-since"))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/hesha/pubkey.json"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", "\"v1\"")
+                    .insert_header("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT")
+                    .set_body_json(&info),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+        
+        // Every lookup finds the entry expired and revalidates it
+        let domain = server.address().to_string();
+        let cache = IssuerKeyCache::new(Duration::ZERO);
+        for _ in 0..3 {
+            assert_eq!(discover_issuer_key_cached(&domain, &cache).await.unwrap(), key);
+        }
+        
+        // A key inserted by hand has nothing to revalidate with and is replaced
+        let cache = IssuerKeyCache::new(Duration::ZERO);
+        cache.insert(domain.clone(), PublicKey::from_bytes([7u8; 32]));
+        assert_eq!(discover_issuer_key_cached(&domain, &cache).await.unwrap(), key);
+    }
+    
     #[test]
     fn test_extract_domain_from_url() {
         assert_eq!(
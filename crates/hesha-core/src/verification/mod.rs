@@ -1,7 +1,25 @@
 //! Attestation verification logic.
 
+pub mod batch;
+pub mod cache;
+pub mod challenge;
 pub mod discovery;
+pub mod policy;
+pub mod request_auth;
+pub mod trust_bundle;
 pub mod verify;
 
-pub use discovery::{discover_issuer_key, discover_issuer_info, resolve_trust_domain, IssuerKeyCache};
+pub use batch::BatchVerifier;
+pub use cache::{VerificationCache, DEFAULT_VERIFICATION_TTL};
+pub use challenge::{sign_challenge, verify_challenge_response, CHALLENGE_MAX_AGE_SECS};
+pub use discovery::{
+    discover_issuer_key, discover_issuer_info, resolve_issuer_info, resolve_issuer_key,
+    resolve_trust_domain, IssuerKeyCache,
+};
+pub use policy::VerificationPolicy;
+pub use request_auth::{
+    challenge_response_header, AuthError, RequestAuthenticator, VerifiedProxyIdentity,
+    ATTESTATION_HEADER, CHALLENGE_RESPONSE_HEADER,
+};
+pub use trust_bundle::{verify_attestation_with_bundle, TrustBundle, TrustedIssuer};
 pub use verify::{verify_attestation, verify_attestation_with_key};
\ No newline at end of file
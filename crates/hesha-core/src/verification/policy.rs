@@ -0,0 +1,258 @@
+//! Verification policies.
+
+use crate::attestation::Audience;
+use crate::verification::trust_bundle::{verify_with_bundle, TrustBundle};
+use crate::verification::verify::{verify_with_discovery, verify_with_key};
+use hesha_types::{Attestation, HeshaError, HeshaResult, PublicKey, VerifiedAttestation};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Rules an attestation must satisfy beyond a valid signature.
+/// 
+/// The default policy is the protocol's baseline: any issuer, any audience,
+/// at most a year of validity and no clock skew. A policy can be loaded from
+/// a TOML file so verification rules can be shared across deployments:
+/// 
+/// ```toml
+/// allowed_issuers = ["issuer.example.com"]
+/// allowed_audiences = ["https://app.example.com"]
+/// max_validity_days = 90
+/// clock_skew_seconds = 60
+/// ```
+/// 
+/// Unknown keys are rejected, so a misspelt rule can't silently loosen the
+/// policy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VerificationPolicy {
+    /// Issuer or trust domains to accept. Empty accepts any issuer.
+    /// 
+    /// An attestation is accepted if either its `iss` or its effective trust
+    /// domain is listed.
+    pub allowed_issuers: Vec<String>,
+    
+    /// Audiences to accept. If set, attestations must carry one of them in
+    /// their `aud` claim.
+    pub allowed_audiences: Vec<String>,
+    
+    /// Longest accepted validity period (expiry minus issue time), in days.
+    pub max_validity_days: i64,
+    
+    /// Clock difference to tolerate when checking expiry and issue time, in
+    /// seconds.
+    pub clock_skew_seconds: i64,
+}
+
+impl Default for VerificationPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_issuers: Vec::new(),
+            allowed_audiences: Vec::new(),
+            max_validity_days: 365,
+            clock_skew_seconds: 0,
+        }
+    }
+}
+
+impl VerificationPolicy {
+    /// Parse a policy from TOML.
+    pub fn from_toml(data: &str) -> HeshaResult<Self> {
+        let policy: Self = toml::from_str(data)
+            .map_err(|e| HeshaError::ConfigError(format!("Invalid verification policy: {}", e)))?;
+        
+        if policy.max_validity_days <= 0 {
+            return Err(HeshaError::ConfigError("max_validity_days must be positive".to_string()));
+        }
+        if policy.clock_skew_seconds < 0 {
+            return Err(HeshaError::ConfigError("clock_skew_seconds cannot be negative".to_string()));
+        }
+        
+        Ok(policy)
+    }
+    
+    /// Load a policy from a TOML file.
+    pub fn from_file(path: impl AsRef<Path>) -> HeshaResult<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| HeshaError::ConfigError(format!("Failed to read {}: {}", path.display(), e)))?;
+        Self::from_toml(&data)
+    }
+    
+    /// Verify an attestation under this policy, discovering the issuer's key.
+    pub async fn verify(&self, jwt: &str) -> HeshaResult<VerifiedAttestation> {
+        hesha_metrics::record_verification(verify_with_discovery(jwt, self).await)
+    }
+    
+    /// Verify an attestation under this policy with a known issuer key.
+    pub fn verify_with_key(&self, jwt: &str, issuer_key: &PublicKey) -> HeshaResult<VerifiedAttestation> {
+        hesha_metrics::record_verification(verify_with_key(jwt, issuer_key, self))
+    }
+    
+    /// Verify an attestation under this policy against a trust bundle.
+    pub fn verify_with_bundle(&self, jwt: &str, bundle: &TrustBundle) -> HeshaResult<VerifiedAttestation> {
+        hesha_metrics::record_verification(verify_with_bundle(jwt, bundle, self))
+    }
+    
+    /// Check an attestation's fields against the policy, without verifying
+    /// its signature.
+    pub fn validate(&self, attestation: &Attestation) -> HeshaResult<()> {
+        let now = chrono::Utc::now();
+        let skew = chrono::Duration::seconds(self.clock_skew_seconds);
+        
+        // Check expiry
+        if now > attestation.exp + skew {
+            return Err(HeshaError::AttestationExpired(attestation.exp));
+        }
+        
+        // Check issued time is not in future
+        if attestation.iat > now + skew {
+            return Err(HeshaError::InvalidAttestation(
+                "Attestation issued in the future".to_string()
+            ));
+        }
+        
+        // Check validity period is reasonable
+        if attestation.exp - attestation.iat > chrono::Duration::days(self.max_validity_days) {
+            return Err(HeshaError::InvalidAttestation(
+                "Attestation validity period too long".to_string()
+            ));
+        }
+        
+        // Validate issuer domain format
+        if attestation.iss.is_empty() {
+            return Err(HeshaError::InvalidAttestation(
+                "Empty issuer domain".to_string()
+            ));
+        }
+        
+        // Allow localhost for testing, otherwise require a proper domain
+        if !attestation.iss.starts_with("localhost") && !attestation.iss.contains('.') {
+            return Err(HeshaError::InvalidAttestation(
+                "Invalid issuer domain".to_string()
+            ));
+        }
+        
+        if !self.allowed_issuers.is_empty() {
+            let trust_domain = attestation.effective_trust_domain();
+            let allowed = self.allowed_issuers.iter().any(|issuer| {
+                issuer.eq_ignore_ascii_case(&attestation.iss) || issuer.eq_ignore_ascii_case(trust_domain)
+            });
+            if !allowed {
+                return Err(HeshaError::InvalidAttestation(format!(
+                    "Issuer {} is not allowed by the verification policy",
+                    attestation.iss
+                )));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Check an attestation's `aud` claim against the allowed audiences.
+    /// 
+    /// With several audiences in the claim, one allowed audience suffices.
+    pub(crate) fn check_audience(&self, audience: Option<&Audience>) -> HeshaResult<()> {
+        if self.allowed_audiences.is_empty() {
+            return Ok(());
+        }
+        let allowed = |aud: &str| self.allowed_audiences.iter().any(|allowed| allowed == aud);
+        match audience {
+            Some(audience) if audience.iter().any(allowed) => Ok(()),
+            Some(audience) => Err(HeshaError::InvalidAttestation(format!(
+                "Audience {} is not allowed by the verification policy",
+                audience
+            ))),
+            None => Err(HeshaError::InvalidAttestation(
+                "Attestation has no audience, but the verification policy requires one".to_string()
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::create::AttestationBuilder;
+    use hesha_crypto::generate_keypair;
+    use hesha_types::{KeyPair, PhoneNumber, ProxyNumber};
+    
+    fn builder(issuer: &KeyPair) -> AttestationBuilder<'_> {
+        let user_key = generate_keypair().unwrap();
+        AttestationBuilder::new(
+            "issuer.com".to_string(),
+            &issuer.private,
+            PhoneNumber::new("+1234567890").unwrap(),
+            ProxyNumber::new("+23400123456789").unwrap(),
+            user_key.public,
+        )
+    }
+    
+    #[test]
+    fn test_policy_from_toml() {
+        let policy = VerificationPolicy::from_toml(
+            "allowed_issuers = [\"issuer.com\"]\nmax_validity_days = 90\n"
+        ).unwrap();
+        assert_eq!(policy.allowed_issuers, vec!["issuer.com".to_string()]);
+        assert_eq!(policy.max_validity_days, 90);
+        assert_eq!(policy.clock_skew_seconds, 0);
+        
+        // Typos must not silently loosen the policy
+        assert!(VerificationPolicy::from_toml("allowed_issuer = [\"issuer.com\"]").is_err());
+        assert!(VerificationPolicy::from_toml("clock_skew_seconds = -1").is_err());
+    }
+    
+    #[test]
+    fn test_policy_issuers_and_validity() {
+        let issuer = generate_keypair().unwrap();
+        let jwt = builder(&issuer).validity_days(30).build_jwt().unwrap();
+        
+        let allowed = VerificationPolicy {
+            allowed_issuers: vec!["ISSUER.com".to_string()],
+            ..Default::default()
+        };
+        assert!(allowed.verify_with_key(&jwt, &issuer.public).is_ok());
+        
+        let other = VerificationPolicy {
+            allowed_issuers: vec!["other.com".to_string()],
+            ..Default::default()
+        };
+        assert!(other.verify_with_key(&jwt, &issuer.public).is_err());
+        
+        let short = VerificationPolicy { max_validity_days: 7, ..Default::default() };
+        assert!(short.verify_with_key(&jwt, &issuer.public).is_err());
+    }
+    
+    #[test]
+    fn test_policy_audience() {
+        let issuer = generate_keypair().unwrap();
+        let policy = VerificationPolicy {
+            allowed_audiences: vec!["https://app.example.com".to_string()],
+            ..Default::default()
+        };
+        
+        let jwt = builder(&issuer).audience("https://app.example.com".to_string()).build_jwt().unwrap();
+        assert!(policy.verify_with_key(&jwt, &issuer.public).is_ok());
+        
+        let jwt = builder(&issuer).audience("https://evil.example.com".to_string()).build_jwt().unwrap();
+        assert!(policy.verify_with_key(&jwt, &issuer.public).is_err());
+        
+        let jwt = builder(&issuer).build_jwt().unwrap();
+        assert!(policy.verify_with_key(&jwt, &issuer.public).is_err());
+        assert!(VerificationPolicy::default().verify_with_key(&jwt, &issuer.public).is_ok());
+    }
+    
+    #[test]
+    fn test_policy_audience_array() {
+        let policy = VerificationPolicy {
+            allowed_audiences: vec!["https://app.example.com".to_string()],
+            ..Default::default()
+        };
+        let audience = |json: &str| serde_json::from_str::<Audience>(json).unwrap();
+        
+        assert_eq!(audience(r#""https://app.example.com""#), Audience::One("https://app.example.com".to_string()));
+        assert!(policy.check_audience(Some(&audience(r#"["https://other.example.com", "https://app.example.com"]"#))).is_ok());
+        assert!(policy.check_audience(Some(&audience(r#"["https://other.example.com"]"#))).is_err());
+        assert!(policy.check_audience(Some(&audience("[]"))).is_err());
+        assert!(serde_json::from_str::<Audience>("42").is_err());
+    }
+}
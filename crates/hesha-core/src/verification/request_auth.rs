@@ -0,0 +1,423 @@
+//! Authenticating requests with an attestation and a challenge response.
+//!
+//! A client proves it holds a proxy number by sending two headers with a
+//! request:
+//!
+//! - [`ATTESTATION_HEADER`]: the attestation JWT
+//! - [`CHALLENGE_RESPONSE_HEADER`]: a [`ChallengeResponse`] as unpadded
+//!   base64url JSON, answering a challenge the client made for the service,
+//!   as [`challenge_response_header`] builds it
+//!
+//! The challenge must name the service's context and be recent, and each
+//! nonce is accepted once. Nonces are remembered per [`RequestAuthenticator`]:
+//! services running several replicas should route a client to the same one
+//! or keep challenges short-lived.
+//!
+//! The framework adapters (`hesha-tower` and others) are thin wrappers over
+//! [`RequestAuthenticator`].
+
+use crate::attestation::jwt::decode_key_id_unverified;
+use crate::attestation::parse::parse_attestation;
+use crate::verification::challenge::{sign_challenge, verify_challenge_response, CHALLENGE_MAX_AGE_SECS};
+use crate::verification::discovery::resolve_issuer_info;
+use crate::verification::policy::VerificationPolicy;
+use crate::verification::trust_bundle::TrustBundle;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use hesha_types::{
+    Challenge, ChallengeResponse, ErrorCode, HeshaError, HeshaResult, IssuerInfo, PrivateKey,
+    Problem, ProxyNumber, PublicKey, VerifiedAttestation,
+};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Header carrying the attestation JWT.
+pub const ATTESTATION_HEADER: &str = "hesha-attestation";
+
+/// Header carrying the challenge response.
+pub const CHALLENGE_RESPONSE_HEADER: &str = "hesha-challenge-response";
+
+/// Shortest time between two fetches of an issuer's keys, so requests
+/// naming unknown keys can't make the service hammer an issuer.
+const MIN_REFRESH: Duration = Duration::from_secs(10);
+
+/// Header value answering a fresh challenge for `service_context` with the
+/// attestation `attestation_id`, signed with its user key.
+pub fn challenge_response_header(
+    attestation_id: &str,
+    user_key: &PrivateKey,
+    service_context: &str,
+) -> HeshaResult<String> {
+    let challenge = Challenge {
+        nonce: hesha_crypto::generate_nonce(),
+        service_context: service_context.to_string(),
+        timestamp: Utc::now(),
+    };
+    let response = sign_challenge(&challenge, attestation_id, user_key)?;
+    Ok(general_purpose::URL_SAFE_NO_PAD.encode(serde_json::to_vec(&response)?))
+}
+
+/// The proxy identity an authenticated request proved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedProxyIdentity {
+    /// Proxy number attested.
+    pub proxy_number: ProxyNumber,
+    /// Issuer that signed the attestation.
+    pub issuer: String,
+    /// Domain the issuer's keys are published under.
+    pub trust_domain: String,
+    /// JWT ID of the attestation.
+    pub attestation_id: String,
+    /// User key the challenge was signed with.
+    pub user_pubkey: PublicKey,
+    /// When the attestation expires.
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<VerifiedAttestation> for VerifiedProxyIdentity {
+    fn from(verified: VerifiedAttestation) -> Self {
+        let attestation = verified.attestation;
+        Self {
+            trust_domain: attestation.effective_trust_domain().to_string(),
+            proxy_number: attestation.proxy_number,
+            issuer: verified.issuer,
+            attestation_id: attestation.jti,
+            user_pubkey: attestation.user_pubkey,
+            expires_at: attestation.exp,
+        }
+    }
+}
+
+/// Why a request wasn't authenticated.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AuthError {
+    /// A header is missing.
+    #[error("Missing {0} header")]
+    MissingHeader(&'static str),
+
+    /// A header can't be decoded.
+    #[error("Malformed {header} header: {reason}")]
+    MalformedHeader {
+        /// The header.
+        header: &'static str,
+        /// What is wrong with it.
+        reason: String,
+    },
+
+    /// The attestation or challenge response didn't verify.
+    #[error("{0}")]
+    Invalid(String),
+
+    /// The challenge was answered before.
+    #[error("Challenge was already answered")]
+    Replayed,
+
+    /// The issuer's keys couldn't be fetched.
+    #[error("Could not fetch keys of {trust_domain}: {reason}")]
+    IssuerUnavailable {
+        /// Trust domain of the attestation.
+        trust_domain: String,
+        /// Why the fetch failed.
+        reason: String,
+    },
+}
+
+impl AuthError {
+    /// The problem code to answer with: `unauthorized` (401) for missing
+    /// credentials and ones that don't verify, `invalid_request` (400) for
+    /// malformed ones, `replayed_request` (409) and `issuer_unavailable`
+    /// (502).
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AuthError::MissingHeader(_) | AuthError::Invalid(_) => ErrorCode::Unauthorized,
+            AuthError::MalformedHeader { .. } => ErrorCode::InvalidRequest,
+            AuthError::Replayed => ErrorCode::ReplayedRequest,
+            AuthError::IssuerUnavailable { .. } => ErrorCode::IssuerUnavailable,
+        }
+    }
+
+    /// The refusal as an RFC 7807 problem document.
+    pub fn problem(&self) -> Problem {
+        Problem::new(self.code(), self.to_string())
+    }
+}
+
+/// Where issuer keys come from.
+enum IssuerKeys {
+    Pinned(TrustBundle),
+    Discovered {
+        ttl: Duration,
+        cache: Mutex<HashMap<String, (IssuerInfo, Instant)>>,
+    },
+}
+
+/// Authenticates requests carrying an attestation and a challenge response.
+///
+/// Issuer keys are discovered and cached for an hour unless a trust bundle
+/// is pinned. List the accepted issuers in the policy when discovering
+/// keys, so clients can't make the service fetch from arbitrary domains.
+pub struct RequestAuthenticator {
+    service_context: String,
+    policy: VerificationPolicy,
+    max_age: chrono::Duration,
+    keys: IssuerKeys,
+    nonces: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl RequestAuthenticator {
+    /// Accept challenges made for `service_context`, under the default
+    /// policy, at most five minutes old.
+    pub fn new(service_context: impl Into<String>) -> Self {
+        Self {
+            service_context: service_context.into(),
+            policy: VerificationPolicy::default(),
+            max_age: chrono::Duration::seconds(CHALLENGE_MAX_AGE_SECS),
+            keys: IssuerKeys::Discovered {
+                ttl: Duration::from_secs(3600),
+                cache: Mutex::new(HashMap::new()),
+            },
+            nonces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Verify attestations under `policy`.
+    pub fn with_policy(mut self, policy: VerificationPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Verify attestations against pinned keys only, without network access.
+    pub fn with_trust_bundle(mut self, bundle: TrustBundle) -> Self {
+        self.keys = IssuerKeys::Pinned(bundle);
+        self
+    }
+
+    /// Keep discovered issuer keys for `ttl`.
+    pub fn with_key_ttl(mut self, ttl: Duration) -> Self {
+        if let IssuerKeys::Discovered { ttl: current, .. } = &mut self.keys {
+            *current = ttl;
+        }
+        self
+    }
+
+    /// Accept challenges at most `max_age` old.
+    pub fn with_max_age(mut self, max_age: chrono::Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// The service context challenges must name.
+    pub fn service_context(&self) -> &str {
+        &self.service_context
+    }
+
+    /// Authenticate a request from its [`ATTESTATION_HEADER`] and
+    /// [`CHALLENGE_RESPONSE_HEADER`] values.
+    pub async fn authenticate(
+        &self,
+        attestation: Option<&str>,
+        challenge_response: Option<&str>,
+    ) -> Result<VerifiedProxyIdentity, AuthError> {
+        let jwt = attestation.ok_or(AuthError::MissingHeader(ATTESTATION_HEADER))?.trim();
+        let response = decode_challenge_response(
+            challenge_response.ok_or(AuthError::MissingHeader(CHALLENGE_RESPONSE_HEADER))?,
+        )?;
+        if response.challenge.service_context != self.service_context {
+            return Err(AuthError::Invalid(format!(
+                "Challenge was issued for {}",
+                response.challenge.service_context
+            )));
+        }
+
+        let verified = self.verify_attestation(jwt).await?;
+        verify_challenge_response(&response, &verified.attestation, self.max_age).map_err(|e| {
+            AuthError::Invalid(match e {
+                HeshaError::InvalidNonce => "Challenge expired or dated in the future".to_string(),
+                HeshaError::InvalidSignature => "Response is not signed with the attestation's user key".to_string(),
+                e => e.to_string(),
+            })
+        })?;
+
+        // Only nonces of otherwise valid responses are spent
+        let challenge = &response.challenge;
+        if !self.use_nonce(challenge.nonce.as_str(), challenge.timestamp + self.max_age) {
+            return Err(AuthError::Replayed);
+        }
+        Ok(verified.into())
+    }
+
+    async fn verify_attestation(&self, jwt: &str) -> Result<VerifiedAttestation, AuthError> {
+        let invalid = |e: HeshaError| AuthError::Invalid(e.to_string());
+        match &self.keys {
+            IssuerKeys::Pinned(bundle) => self.policy.verify_with_bundle(jwt, bundle).map_err(invalid),
+            IssuerKeys::Discovered { ttl, cache } => {
+                // Refuse what the policy would before fetching anything
+                let attestation = parse_attestation(jwt).map_err(invalid)?;
+                self.policy.validate(&attestation).map_err(invalid)?;
+                let key_id = decode_key_id_unverified(jwt).map_err(invalid)?;
+                let trust_domain = attestation.effective_trust_domain();
+
+                let cached = cache.lock().unwrap_or_else(|e| e.into_inner()).get(trust_domain).cloned();
+                if let Some((info, fetched)) = cached {
+                    match info.key(key_id.as_deref()) {
+                        Some(key) if fetched.elapsed() < *ttl => {
+                            hesha_metrics::record_cache_lookup(hesha_metrics::ISSUER_KEY_CACHE, true);
+                            return self.policy.verify_with_key(jwt, key).map_err(invalid);
+                        }
+                        None if fetched.elapsed() < MIN_REFRESH => {
+                            hesha_metrics::record_cache_lookup(hesha_metrics::ISSUER_KEY_CACHE, true);
+                            return Err(unknown_key(trust_domain, key_id.as_deref()));
+                        }
+                        _ => {}
+                    }
+                }
+                hesha_metrics::record_cache_lookup(hesha_metrics::ISSUER_KEY_CACHE, false);
+
+                let (_, info) = resolve_issuer_info(trust_domain).await.map_err(|e| match e {
+                    HeshaError::InvalidAttestation(detail) => AuthError::Invalid(detail),
+                    e => AuthError::IssuerUnavailable {
+                        trust_domain: trust_domain.to_string(),
+                        reason: e.to_string(),
+                    },
+                })?;
+                let key = info.key(key_id.as_deref()).cloned();
+                cache
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(trust_domain.to_string(), (info, Instant::now()));
+                let key = key.ok_or_else(|| unknown_key(trust_domain, key_id.as_deref()))?;
+                self.policy.verify_with_key(jwt, &key).map_err(invalid)
+            }
+        }
+    }
+
+    /// Record `nonce` as answered, returning false if it already was.
+    fn use_nonce(&self, nonce: &str, expires_at: DateTime<Utc>) -> bool {
+        let mut nonces = self.nonces.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Utc::now();
+        nonces.retain(|_, expiry| *expiry > now);
+        if nonces.contains_key(nonce) {
+            return false;
+        }
+        nonces.insert(nonce.to_string(), expires_at);
+        true
+    }
+}
+
+fn decode_challenge_response(value: &str) -> Result<ChallengeResponse, AuthError> {
+    let malformed = |reason: String| AuthError::MalformedHeader {
+        header: CHALLENGE_RESPONSE_HEADER,
+        reason,
+    };
+    let json = general_purpose::URL_SAFE_NO_PAD
+        .decode(value.trim())
+        .map_err(|e| malformed(e.to_string()))?;
+    serde_json::from_slice(&json).map_err(|e| malformed(e.to_string()))
+}
+
+fn unknown_key(trust_domain: &str, key_id: Option<&str>) -> AuthError {
+    AuthError::Invalid(format!(
+        "Issuer {} does not publish key {}",
+        trust_domain,
+        key_id.unwrap_or_default()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::create::create_attestation;
+    use hesha_crypto::generate_keypair;
+    use hesha_types::{KeyPair, PhoneNumber};
+
+    fn attestation(issuer: &KeyPair, user: &KeyPair) -> String {
+        create_attestation(
+            "issuer.example.com",
+            &issuer.private,
+            &PhoneNumber::new("+2348012345678").unwrap(),
+            &ProxyNumber::new("+23400123456789").unwrap(),
+            &user.public,
+        )
+        .unwrap()
+    }
+
+    fn authenticator(issuer: &KeyPair) -> RequestAuthenticator {
+        let mut bundle = TrustBundle::new();
+        bundle.add("issuer.example.com", issuer.public.clone(), None);
+        RequestAuthenticator::new("app.example.com").with_trust_bundle(bundle)
+    }
+
+    #[tokio::test]
+    async fn test_authenticate() {
+        let issuer = generate_keypair().unwrap();
+        let user = generate_keypair().unwrap();
+        let jwt = attestation(&issuer, &user);
+        let jti = parse_attestation(&jwt).unwrap().jti;
+        let auth = authenticator(&issuer);
+
+        let header = challenge_response_header(&jti, &user.private, "app.example.com").unwrap();
+        let identity = auth.authenticate(Some(&jwt), Some(&header)).await.unwrap();
+        assert_eq!(identity.proxy_number.as_str(), "+23400123456789");
+        assert_eq!(identity.trust_domain, "issuer.example.com");
+        assert_eq!(identity.attestation_id, jti);
+        assert_eq!(identity.user_pubkey, user.public);
+
+        // Each challenge is answered once
+        assert_eq!(auth.authenticate(Some(&jwt), Some(&header)).await, Err(AuthError::Replayed));
+        let header = challenge_response_header(&jti, &user.private, "app.example.com").unwrap();
+        assert!(auth.authenticate(Some(&jwt), Some(&header)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rejections() {
+        let issuer = generate_keypair().unwrap();
+        let user = generate_keypair().unwrap();
+        let jwt = attestation(&issuer, &user);
+        let jti = parse_attestation(&jwt).unwrap().jti;
+        let auth = authenticator(&issuer);
+        let header = challenge_response_header(&jti, &user.private, "app.example.com").unwrap();
+
+        let error = auth.authenticate(None, Some(&header)).await.unwrap_err();
+        assert_eq!(error, AuthError::MissingHeader(ATTESTATION_HEADER));
+        assert_eq!(error.problem().status, 401);
+
+        let error = auth.authenticate(Some(&jwt), Some("not base64!")).await.unwrap_err();
+        assert_eq!(error.code(), ErrorCode::InvalidRequest);
+
+        let other_service = challenge_response_header(&jti, &user.private, "evil.example.com").unwrap();
+        let error = auth.authenticate(Some(&jwt), Some(&other_service)).await.unwrap_err();
+        assert_eq!(error, AuthError::Invalid("Challenge was issued for evil.example.com".to_string()));
+
+        let wrong_user = generate_keypair().unwrap();
+        let forged = challenge_response_header(&jti, &wrong_user.private, "app.example.com").unwrap();
+        assert!(matches!(auth.authenticate(Some(&jwt), Some(&forged)).await, Err(AuthError::Invalid(_))));
+
+        let untrusted = authenticator(&generate_keypair().unwrap());
+        assert!(matches!(untrusted.authenticate(Some(&jwt), Some(&header)).await, Err(AuthError::Invalid(_))));
+
+        // Rejected responses don't spend their nonce
+        assert!(auth.authenticate(Some(&jwt), Some(&header)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_stale_challenge() {
+        let issuer = generate_keypair().unwrap();
+        let user = generate_keypair().unwrap();
+        let jwt = attestation(&issuer, &user);
+        let jti = parse_attestation(&jwt).unwrap().jti;
+        let auth = authenticator(&issuer).with_max_age(chrono::Duration::seconds(60));
+
+        let challenge = Challenge {
+            nonce: hesha_crypto::generate_nonce(),
+            service_context: "app.example.com".to_string(),
+            timestamp: Utc::now() - chrono::Duration::seconds(120),
+        };
+        let response = sign_challenge(&challenge, &jti, &user.private).unwrap();
+        let header = general_purpose::URL_SAFE_NO_PAD.encode(serde_json::to_vec(&response).unwrap());
+        assert_eq!(
+            auth.authenticate(Some(&jwt), Some(&header)).await,
+            Err(AuthError::Invalid("Challenge expired or dated in the future".to_string()))
+        );
+    }
+}
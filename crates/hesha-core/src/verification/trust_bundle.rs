@@ -0,0 +1,178 @@
+//! Pinned issuer keys for offline verification.
+
+use crate::attestation::parse::parse_attestation;
+use crate::verification::policy::VerificationPolicy;
+use crate::verification::verify::verify_with_key;
+use hesha_types::{HeshaError, HeshaResult, IssuerInfo, PublicKey, VerifiedAttestation};
+use serde::{Deserialize, Serialize};
+
+/// Current version of the trust bundle format.
+pub const TRUST_BUNDLE_VERSION: u32 = 1;
+
+/// A set of pinned issuer keys.
+///
+/// Lets verifiers check attestations without network access by looking up
+/// the issuer key by trust domain instead of fetching it from
+/// `/.well-known/hesha/pubkey.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustBundle {
+    /// Bundle format version.
+    #[serde(default = "default_version")]
+    pub version: u32,
+
+    /// Trusted issuer keys.
+    #[serde(default)]
+    pub issuers: Vec<TrustedIssuer>,
+}
+
+/// A pinned key for a single trust domain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustedIssuer {
+    /// Trust domain the key is valid for.
+    pub domain: String,
+
+    /// Issuer public key.
+    pub public_key: PublicKey,
+
+    /// Optional key ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+}
+
+fn default_version() -> u32 {
+    TRUST_BUNDLE_VERSION
+}
+
+impl Default for TrustBundle {
+    fn default() -> Self {
+        Self {
+            version: TRUST_BUNDLE_VERSION,
+            issuers: Vec::new(),
+        }
+    }
+}
+
+impl TrustBundle {
+    /// Create an empty trust bundle.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a trust bundle from JSON.
+    pub fn from_json(data: &str) -> HeshaResult<Self> {
+        let bundle: TrustBundle = serde_json::from_str(data)?;
+        if bundle.version != TRUST_BUNDLE_VERSION {
+            return Err(HeshaError::ConfigError(format!(
+                "Unsupported trust bundle version: {}",
+                bundle.version
+            )));
+        }
+        Ok(bundle)
+    }
+
+    /// Pin a key for a trust domain.
+    pub fn add(&mut self, domain: impl Into<String>, public_key: PublicKey, key_id: Option<String>) {
+        self.issuers.push(TrustedIssuer {
+            domain: domain.into(),
+            public_key,
+            key_id,
+        });
+    }
+
+    /// Pin the key from an issuer's published key document.
+    pub fn add_issuer_info(&mut self, domain: impl Into<String>, info: &IssuerInfo) {
+        self.add(domain, info.public_key.clone(), info.key_id.clone());
+    }
+
+    /// Get all keys pinned for a trust domain.
+    pub fn keys_for<'a>(&'a self, domain: &'a str) -> impl Iterator<Item = &'a PublicKey> + 'a {
+        self.issuers
+            .iter()
+            .filter(move |issuer| issuer.domain.eq_ignore_ascii_case(domain))
+            .map(|issuer| &issuer.public_key)
+    }
+}
+
+/// Verify an attestation against a trust bundle without network access.
+///
+/// The attestation's effective trust domain selects the candidate keys;
+/// verification succeeds if any of them validates the signature.
+pub fn verify_attestation_with_bundle(
+    jwt: &str,
+    bundle: &TrustBundle,
+) -> HeshaResult<VerifiedAttestation> {
+    hesha_metrics::record_verification(verify_with_bundle(jwt, bundle, &VerificationPolicy::default()))
+}
+
+/// Verify an attestation against a trust bundle under a policy.
+pub(crate) fn verify_with_bundle(
+    jwt: &str,
+    bundle: &TrustBundle,
+    policy: &VerificationPolicy,
+) -> HeshaResult<VerifiedAttestation> {
+    let attestation = parse_attestation(jwt)?;
+    let trust_domain = attestation.effective_trust_domain();
+
+    let mut last_error = None;
+    for key in bundle.keys_for(trust_domain) {
+        match verify_with_key(jwt, key, policy) {
+            Ok(verified) => return Ok(verified),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        HeshaError::InvalidAttestation(format!(
+            "No pinned key for trust domain {}",
+            trust_domain
+        ))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::create::create_attestation;
+    use hesha_crypto::generate_keypair;
+    use hesha_types::{PhoneNumber, ProxyNumber};
+
+    fn test_jwt(issuer: &hesha_types::KeyPair) -> String {
+        let user_key = generate_keypair().unwrap();
+        create_attestation(
+            "issuer.com",
+            &issuer.private,
+            &PhoneNumber::new("+1234567890").unwrap(),
+            &ProxyNumber::new("+23400123456789").unwrap(),
+            &user_key.public,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_with_bundle() {
+        let old_key = generate_keypair().unwrap();
+        let issuer_key = generate_keypair().unwrap();
+        let jwt = test_jwt(&issuer_key);
+
+        let mut bundle = TrustBundle::new();
+        bundle.add("issuer.com", old_key.public, Some("old".to_string()));
+        bundle.add("issuer.com", issuer_key.public, None);
+
+        let json = serde_json::to_string(&bundle).unwrap();
+        let bundle = TrustBundle::from_json(&json).unwrap();
+
+        let verified = verify_attestation_with_bundle(&jwt, &bundle).unwrap();
+        assert_eq!(verified.issuer, "issuer.com");
+    }
+
+    #[test]
+    fn test_verify_with_bundle_unknown_domain() {
+        let issuer_key = generate_keypair().unwrap();
+        let jwt = test_jwt(&issuer_key);
+
+        let mut bundle = TrustBundle::new();
+        bundle.add("other.com", issuer_key.public, None);
+
+        assert!(verify_attestation_with_bundle(&jwt, &bundle).is_err());
+    }
+}
@@ -1,9 +1,10 @@
 //! Attestation verification logic.
 
-use crate::attestation::parse::{parse_attestation, validate_attestation};
+use crate::attestation::parse::parse_attestation;
 use crate::attestation::claims::Claims;
-use crate::attestation::jwt::decode_jwt;
-use crate::verification::discovery::resolve_trust_domain;
+use crate::attestation::jwt::{decode_jwt, decode_key_id_unverified};
+use crate::verification::discovery::resolve_issuer_key;
+use crate::verification::policy::VerificationPolicy;
 use hesha_types::{HeshaResult, PublicKey, VerifiedAttestation};
 use chrono::Utc;
 
@@ -15,31 +16,50 @@ use chrono::Utc;
 /// - Checks expiry and other fields
 /// - Supports trust domain resolution for subdomain deployments
 pub async fn verify_attestation(jwt: &str) -> HeshaResult<VerifiedAttestation> {
+    hesha_metrics::record_verification(verify_with_discovery(jwt, &VerificationPolicy::default()).await)
+}
+
+/// Verify an attestation with a known issuer public key.
+pub fn verify_attestation_with_key(
+    jwt: &str,
+    issuer_key: &PublicKey,
+) -> HeshaResult<VerifiedAttestation> {
+    hesha_metrics::record_verification(verify_with_key(jwt, issuer_key, &VerificationPolicy::default()))
+}
+
+/// Discover the issuer's key and verify an attestation under a policy.
+pub(crate) async fn verify_with_discovery(
+    jwt: &str,
+    policy: &VerificationPolicy,
+) -> HeshaResult<VerifiedAttestation> {
     // Parse attestation to get issuer
     let attestation = parse_attestation(jwt)?;
-    validate_attestation(&attestation)?;
+    policy.validate(&attestation)?;
     
     // Get the effective trust domain for verification
     let trust_domain = attestation.effective_trust_domain();
     
-    // Resolve trust domain to get the actual issuer key
-    let (_service_domain, issuer_key) = resolve_trust_domain(trust_domain).await?;
+    // Resolve trust domain to get the issuer key named in the `kid` header
+    let key_id = decode_key_id_unverified(jwt)?;
+    let (_service_domain, issuer_key) = resolve_issuer_key(trust_domain, key_id.as_deref()).await?;
     
     // Verify with discovered key
-    verify_attestation_with_key(jwt, &issuer_key)
+    verify_with_key(jwt, &issuer_key, policy)
 }
 
-/// Verify an attestation with a known issuer public key.
-pub fn verify_attestation_with_key(
+/// Verify an attestation with a known issuer public key under a policy.
+pub(crate) fn verify_with_key(
     jwt: &str,
     issuer_key: &PublicKey,
+    policy: &VerificationPolicy,
 ) -> HeshaResult<VerifiedAttestation> {
     // Verify JWT signature using our implementation
     let claims: Claims = decode_jwt(jwt, issuer_key)?;
     
     // Convert to attestation and validate
     let attestation = claims.to_attestation()?;
-    validate_attestation(&attestation)?;
+    policy.validate(&attestation)?;
+    policy.check_audience(claims.aud.as_ref())?;
     
     // Verify binding signature with issuer's public key
     // The binding proof is stored in the claims as the full "sig:..." string
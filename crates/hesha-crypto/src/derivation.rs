@@ -0,0 +1,158 @@
+//! Deterministic key derivation from a backup phrase.
+//!
+//! A BIP-39 mnemonic is turned into a seed, and Ed25519 keys are derived
+//! from the seed along a path with SLIP-0010. The same phrase and path
+//! always give the same keypair, so a key can be restored on a new machine
+//! from the phrase alone.
+//!
+//! SLIP-0010 only defines hardened derivation for Ed25519, so every path
+//! segment must be hardened (`0'` or `0h`).
+
+use bip39::Mnemonic;
+use hesha_types::{HeshaError, HeshaResult, KeyPair, PrivateKey};
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+
+use crate::signing::keypair_from_private;
+
+/// Path used when none is given.
+///
+/// Changing this would change the key restored from every existing phrase.
+pub const DEFAULT_DERIVATION_PATH: &str = "m/0'";
+
+/// Offset added to an index to mark it hardened.
+const HARDENED_OFFSET: u32 = 0x8000_0000;
+
+/// HMAC key for the SLIP-0010 master key of the Ed25519 curve.
+const ED25519_CURVE_KEY: &[u8] = b"ed25519 seed";
+
+type HmacSha512 = Hmac<Sha512>;
+
+/// Parse a derivation path like `m/44'/0'/1'` into hardened indices.
+pub fn parse_derivation_path(path: &str) -> HeshaResult<Vec<u32>> {
+    let invalid = |reason: &str| HeshaError::CryptoError(format!("Invalid derivation path '{}': {}", path, reason));
+
+    let mut segments = path.trim().split('/');
+    if segments.next() != Some("m") {
+        return Err(invalid("must start with 'm'"));
+    }
+
+    segments
+        .map(|segment| {
+            let index = segment
+                .strip_suffix('\'')
+                .or_else(|| segment.strip_suffix('h'))
+                .ok_or_else(|| invalid("Ed25519 only supports hardened segments (e.g. 0')"))?;
+            let index: u32 = index.parse().map_err(|_| invalid("segments must be numbers"))?;
+            if index >= HARDENED_OFFSET {
+                return Err(invalid("segment index too large"));
+            }
+            Ok(index | HARDENED_OFFSET)
+        })
+        .collect()
+}
+
+/// Derive a keypair from a BIP-39 mnemonic.
+///
+/// `passphrase` is the optional BIP-39 passphrase ("25th word"); pass an
+/// empty string if the phrase has none.
+///
+/// # Security Considerations
+/// - The mnemonic's checksum is verified, so typos are rejected rather
+///   than silently producing a different key
+/// - Anyone holding the phrase can derive every key under it
+pub fn keypair_from_mnemonic(phrase: &str, passphrase: &str, path: &str) -> HeshaResult<KeyPair> {
+    // Tolerate case and spacing differences from copying the phrase by hand
+    let phrase = phrase
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mnemonic = Mnemonic::parse_normalized(&phrase)
+        .map_err(|e| HeshaError::CryptoError(format!("Invalid mnemonic: {}", e)))?;
+    keypair_from_seed(&mnemonic.to_seed(passphrase), path)
+}
+
+/// Derive a keypair from a seed with SLIP-0010.
+pub fn keypair_from_seed(seed: &[u8], path: &str) -> HeshaResult<KeyPair> {
+    let indices = parse_derivation_path(path)?;
+
+    let (mut key, mut chain_code) = hmac_split(ED25519_CURVE_KEY, &[seed]);
+    for index in indices {
+        (key, chain_code) = hmac_split(&chain_code, &[&[0u8], &key, &index.to_be_bytes()]);
+    }
+
+    keypair_from_private(&PrivateKey::from_bytes(key))
+}
+
+/// HMAC-SHA512 the parts under `key`, splitting the output into the child
+/// key and chain code.
+fn hmac_split(key: &[u8], parts: &[&[u8]]) -> ([u8; 32], [u8; 32]) {
+    let mut mac = HmacSha512::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    let output = mac.finalize().into_bytes();
+
+    let mut child_key = [0u8; 32];
+    let mut chain_code = [0u8; 32];
+    child_key.copy_from_slice(&output[..32]);
+    chain_code.copy_from_slice(&output[32..]);
+    (child_key, chain_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_slip10_vectors() {
+        // SLIP-0010 test vector 1 for ed25519
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+
+        let master = keypair_from_seed(&seed, "m").unwrap();
+        assert_eq!(
+            hex::encode(master.private.as_bytes()),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+        assert_eq!(
+            hex::encode(master.public.as_bytes()),
+            "a4b2856bfec510abab89753fac1ac0e1112364e7d250545963f135f2a33188ed"
+        );
+
+        let child = keypair_from_seed(&seed, "m/0'/1'/2'/2'/1000000000'").unwrap();
+        assert_eq!(
+            hex::encode(child.private.as_bytes()),
+            "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793"
+        );
+        assert_eq!(
+            hex::encode(child.public.as_bytes()),
+            "3c24da049451555d51a7014a37337aa4e12d41e485abccfa46b47dfb2af54b7a"
+        );
+    }
+
+    #[test]
+    fn test_keypair_from_mnemonic_is_deterministic() {
+        let a = keypair_from_mnemonic(PHRASE, "", DEFAULT_DERIVATION_PATH).unwrap();
+        let b = keypair_from_mnemonic(&PHRASE.to_uppercase(), "", DEFAULT_DERIVATION_PATH).unwrap();
+        assert_eq!(a.public, b.public);
+
+        assert_ne!(keypair_from_mnemonic(PHRASE, "", "m/1'").unwrap().public, a.public);
+        assert_ne!(keypair_from_mnemonic(PHRASE, "extra", DEFAULT_DERIVATION_PATH).unwrap().public, a.public);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        // Bad checksum
+        let typo = PHRASE.replace("about", "abandon");
+        assert!(keypair_from_mnemonic(&typo, "", DEFAULT_DERIVATION_PATH).is_err());
+
+        assert!(parse_derivation_path("44'/0'").is_err());
+        assert!(parse_derivation_path("m/44'/0").is_err());
+        assert!(parse_derivation_path("m/x'").is_err());
+        assert_eq!(parse_derivation_path("m/1h/2'").unwrap(), vec![HARDENED_OFFSET | 1, HARDENED_OFFSET | 2]);
+    }
+}
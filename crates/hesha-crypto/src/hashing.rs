@@ -1,10 +1,16 @@
 //! SHA256 operations and binding signatures for the Hesha Protocol.
+//! 
+//! SHA-256 runs on the x86-64 SHA extensions (SHA-NI) when the CPU has
+//! them. The `asm-hash` feature adds the ARMv8 SHA2 instructions on
+//! aarch64 and an assembly fallback for x86 CPUs without SHA-NI; both are
+//! chosen at runtime, so accelerated builds still run on any CPU.
 
 use hesha_types::{HeshaResult, PhoneHash, PhoneNumber};
 use sha2::{Sha256, Digest};
+use hmac::{Hmac, Mac};
 use rand::{RngCore, rngs::OsRng};
 use base64::{Engine, engine::general_purpose};
-use crate::signing::sign_message;
+use crate::signing::MessageSigner;
 
 /// Generate a cryptographically secure random salt.
 /// 
@@ -66,7 +72,7 @@ pub fn create_binding_signature(
     user_pubkey: &str,     // Base64url encoded public key
     proxy_number: &str,    // Full proxy number with '+'
     iat: i64,              // Issued-at timestamp
-    private_key: &hesha_types::PrivateKey,  // Issuer's Ed25519 private key
+    signer: &(impl MessageSigner + ?Sized),  // Issuer's Ed25519 key
 ) -> HeshaResult<String> {
     // Construct canonical message according to spec v1.1
     let message = format!(
@@ -78,7 +84,7 @@ pub fn create_binding_signature(
     let message_hash = sha256(message.as_bytes());
     
     // Sign the hash with issuer's private key
-    let signature = sign_message(private_key, &message_hash)?;
+    let signature = signer.sign_message(&message_hash)?;
     
     // Format as "sig:base64url"
     Ok(format!("sig:{}", general_purpose::URL_SAFE_NO_PAD.encode(signature.as_bytes())))
@@ -140,6 +146,16 @@ pub fn sha256(data: &[u8]) -> [u8; 32] {
     hash
 }
 
+/// Compute HMAC-SHA256 of data under a shared secret.
+/// 
+/// Used to sign webhook payloads and partner requests, where both sides
+/// hold the same secret.
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
 /// Constant-time comparison to prevent timing attacks.
 /// 
 /// # Security Considerations
@@ -268,4 +284,15 @@ mod tests {
         let hash3 = sha256(different_data);
         assert_ne!(hash1, hash3);
     }
+    
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231 test case 2
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            hex::encode(mac),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_ne!(mac, hmac_sha256(b"other", b"what do ya want for nothing?"));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,371 @@
+//! Passphrase-encrypted private keys and data.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{Engine as _, engine::general_purpose};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hesha_types::{HeshaError, HeshaResult, KeyPair, PrivateKey, PublicKey};
+use rand::{RngCore, rngs::OsRng};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+/// Current version of the encrypted key file format.
+pub const ENCRYPTED_KEY_VERSION: u32 = 1;
+
+/// Argon2id cost parameters used to derive the encryption key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory cost in KiB.
+    pub m_cost: u32,
+    /// Number of iterations.
+    pub t_cost: u32,
+    /// Degree of parallelism.
+    pub p_cost: u32,
+}
+
+impl KdfParams {
+    /// Highest costs accepted, so a crafted key file can't make decryption
+    /// exhaust memory or CPU: 1 GiB, 10 iterations, 16 lanes.
+    pub const MAX: KdfParams = KdfParams { m_cost: 1024 * 1024, t_cost: 10, p_cost: 16 };
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// Encrypted private key file.
+///
+/// The private key is sealed with XChaCha20-Poly1305 under a key derived
+/// from the passphrase with Argon2id. The public key is stored in the clear
+/// so tools can identify the key without the passphrase.
+///
+/// # Security Considerations
+/// - A fresh salt and nonce are generated for every encryption
+/// - The public key is authenticated as associated data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedKey {
+    /// File format version.
+    pub version: u32,
+    /// Key derivation function (always "argon2id").
+    pub kdf: String,
+    /// Key derivation parameters.
+    pub kdf_params: KdfParams,
+    /// KDF salt (base64url).
+    pub salt: String,
+    /// Cipher (always "xchacha20poly1305").
+    pub cipher: String,
+    /// Cipher nonce (base64url).
+    pub nonce: String,
+    /// Encrypted private key (base64url).
+    pub ciphertext: String,
+    /// Public key (base64url).
+    pub public_key: String,
+}
+
+impl EncryptedKey {
+    /// Check whether a string looks like an encrypted key file.
+    pub fn is_encrypted(data: &str) -> bool {
+        serde_json::from_str::<serde_json::Value>(data)
+            .map(|json| json.get("ciphertext").is_some() && json.get("kdf").is_some())
+            .unwrap_or(false)
+    }
+
+    /// Parse an encrypted key file from JSON.
+    pub fn from_json(data: &str) -> HeshaResult<Self> {
+        let key: EncryptedKey = serde_json::from_str(data)?;
+        if key.version != ENCRYPTED_KEY_VERSION {
+            return Err(HeshaError::InvalidPrivateKey(
+                format!("Unsupported encrypted key version: {}", key.version)
+            ));
+        }
+        Ok(key)
+    }
+
+    /// Serialize to pretty-printed JSON.
+    pub fn to_json(&self) -> HeshaResult<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Get the public key stored alongside the ciphertext.
+    pub fn public_key(&self) -> HeshaResult<PublicKey> {
+        PublicKey::from_base64(&self.public_key)
+    }
+}
+
+/// Encrypt a key pair's private key with a passphrase.
+pub fn encrypt_keypair(keypair: &KeyPair, passphrase: &str) -> HeshaResult<EncryptedKey> {
+    encrypt_keypair_with_params(keypair, passphrase, KdfParams::default())
+}
+
+/// Encrypt a key pair's private key with explicit KDF parameters.
+pub fn encrypt_keypair_with_params(
+    keypair: &KeyPair,
+    passphrase: &str,
+    params: KdfParams,
+) -> HeshaResult<EncryptedKey> {
+    if passphrase.is_empty() {
+        return Err(HeshaError::CryptoError("Passphrase cannot be empty".to_string()));
+    }
+
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; 24];
+    OsRng.fill_bytes(&mut nonce);
+
+    let cipher = derive_cipher(passphrase, &salt, params)?;
+    let public_key = keypair.public.to_base64();
+    let ciphertext = cipher
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            chacha20poly1305::aead::Payload {
+                msg: keypair.private.as_bytes(),
+                aad: public_key.as_bytes(),
+            },
+        )
+        .map_err(|_| HeshaError::CryptoError("Failed to encrypt private key".to_string()))?;
+
+    Ok(EncryptedKey {
+        version: ENCRYPTED_KEY_VERSION,
+        kdf: "argon2id".to_string(),
+        kdf_params: params,
+        salt: general_purpose::URL_SAFE_NO_PAD.encode(salt),
+        cipher: "xchacha20poly1305".to_string(),
+        nonce: general_purpose::URL_SAFE_NO_PAD.encode(nonce),
+        ciphertext: general_purpose::URL_SAFE_NO_PAD.encode(ciphertext),
+        public_key,
+    })
+}
+
+/// Decrypt an encrypted key file with a passphrase.
+///
+/// Fails with a crypto error if the passphrase is wrong or the file was
+/// tampered with.
+pub fn decrypt_keypair(encrypted: &EncryptedKey, passphrase: &str) -> HeshaResult<KeyPair> {
+    if encrypted.kdf != "argon2id" || encrypted.cipher != "xchacha20poly1305" {
+        return Err(HeshaError::InvalidPrivateKey(format!(
+            "Unsupported key encryption: {}/{}",
+            encrypted.kdf, encrypted.cipher
+        )));
+    }
+
+    let salt = decode_field(&encrypted.salt, "salt")?;
+    let nonce = decode_field(&encrypted.nonce, "nonce")?;
+    let ciphertext = decode_field(&encrypted.ciphertext, "ciphertext")?;
+    if nonce.len() != 24 {
+        return Err(HeshaError::InvalidPrivateKey("Invalid nonce length".to_string()));
+    }
+
+    let cipher = derive_cipher(passphrase, &salt, encrypted.kdf_params)?;
+    let plaintext = cipher
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            chacha20poly1305::aead::Payload {
+                msg: &ciphertext,
+                aad: encrypted.public_key.as_bytes(),
+            },
+        )
+        .map_err(|_| HeshaError::CryptoError(
+            "Failed to decrypt private key (wrong passphrase?)".to_string()
+        ))?;
+
+    let key_bytes: [u8; 32] = plaintext
+        .as_slice()
+        .try_into()
+        .map_err(|_| HeshaError::InvalidPrivateKey("Invalid decrypted key length".to_string()))?;
+    let keypair = crate::signing::keypair_from_private(&PrivateKey::from_bytes(key_bytes))?;
+
+    if keypair.public != encrypted.public_key()? {
+        return Err(HeshaError::InvalidPrivateKey(
+            "Decrypted key does not match stored public key".to_string()
+        ));
+    }
+
+    Ok(keypair)
+}
+
+/// Arbitrary data sealed with a passphrase, e.g. a backup archive.
+///
+/// Uses the same Argon2id and XChaCha20-Poly1305 construction as
+/// [`EncryptedKey`], with a fresh salt and nonce for every encryption.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedData {
+    /// Format version.
+    pub version: u32,
+    /// Key derivation function (always "argon2id").
+    pub kdf: String,
+    /// Key derivation parameters.
+    pub kdf_params: KdfParams,
+    /// KDF salt (base64url).
+    pub salt: String,
+    /// Cipher (always "xchacha20poly1305").
+    pub cipher: String,
+    /// Cipher nonce (base64url).
+    pub nonce: String,
+    /// Encrypted data (base64url).
+    pub ciphertext: String,
+}
+
+/// Encrypt data with a passphrase.
+pub fn encrypt_data(plaintext: &[u8], passphrase: &str, params: KdfParams) -> HeshaResult<EncryptedData> {
+    if passphrase.is_empty() {
+        return Err(HeshaError::CryptoError("Passphrase cannot be empty".to_string()));
+    }
+
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; 24];
+    OsRng.fill_bytes(&mut nonce);
+
+    let cipher = derive_cipher(passphrase, &salt, params)?;
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce), plaintext)
+        .map_err(|_| HeshaError::CryptoError("Failed to encrypt data".to_string()))?;
+
+    Ok(EncryptedData {
+        version: ENCRYPTED_KEY_VERSION,
+        kdf: "argon2id".to_string(),
+        kdf_params: params,
+        salt: general_purpose::URL_SAFE_NO_PAD.encode(salt),
+        cipher: "xchacha20poly1305".to_string(),
+        nonce: general_purpose::URL_SAFE_NO_PAD.encode(nonce),
+        ciphertext: general_purpose::URL_SAFE_NO_PAD.encode(ciphertext),
+    })
+}
+
+/// Decrypt data sealed with [`encrypt_data`].
+///
+/// Fails with a crypto error if the passphrase is wrong or the data was
+/// tampered with.
+pub fn decrypt_data(encrypted: &EncryptedData, passphrase: &str) -> HeshaResult<Vec<u8>> {
+    if encrypted.version != ENCRYPTED_KEY_VERSION
+        || encrypted.kdf != "argon2id"
+        || encrypted.cipher != "xchacha20poly1305"
+    {
+        return Err(HeshaError::CryptoError(format!(
+            "Unsupported encryption: version {} {}/{}",
+            encrypted.version, encrypted.kdf, encrypted.cipher
+        )));
+    }
+
+    let salt = decode_field(&encrypted.salt, "salt")?;
+    let nonce = decode_field(&encrypted.nonce, "nonce")?;
+    let ciphertext = decode_field(&encrypted.ciphertext, "ciphertext")?;
+    if nonce.len() != 24 {
+        return Err(HeshaError::CryptoError("Invalid nonce length".to_string()));
+    }
+
+    let cipher = derive_cipher(passphrase, &salt, encrypted.kdf_params)?;
+    cipher
+        .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| HeshaError::CryptoError(
+            "Failed to decrypt data (wrong passphrase or corrupted file?)".to_string()
+        ))
+}
+
+/// Derive the AEAD cipher from a passphrase.
+fn derive_cipher(passphrase: &str, salt: &[u8], params: KdfParams) -> HeshaResult<XChaCha20Poly1305> {
+    let max = KdfParams::MAX;
+    if params.m_cost > max.m_cost || params.t_cost > max.t_cost || params.p_cost > max.p_cost {
+        return Err(HeshaError::CryptoError(format!(
+            "KDF parameters exceed the supported maximum (m_cost {}, t_cost {}, p_cost {})",
+            max.m_cost, max.t_cost, max.p_cost
+        )));
+    }
+    let params = Params::new(params.m_cost, params.t_cost, params.p_cost, Some(32))
+        .map_err(|e| HeshaError::CryptoError(format!("Invalid KDF parameters: {}", e)))?;
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    argon2
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| HeshaError::CryptoError(format!("Key derivation failed: {}", e)))?;
+
+    Ok(XChaCha20Poly1305::new((&*key).into()))
+}
+
+fn decode_field(value: &str, name: &str) -> HeshaResult<Vec<u8>> {
+    general_purpose::URL_SAFE_NO_PAD
+        .decode(value)
+        .map_err(|_| HeshaError::InvalidPrivateKey(format!("Invalid {} encoding", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::generate_keypair;
+
+    // Cheap parameters keep the tests fast
+    const TEST_PARAMS: KdfParams = KdfParams { m_cost: 64, t_cost: 1, p_cost: 1 };
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let keypair = generate_keypair().unwrap();
+        let encrypted = encrypt_keypair_with_params(&keypair, "correct horse", TEST_PARAMS).unwrap();
+
+        let json = encrypted.to_json().unwrap();
+        assert!(EncryptedKey::is_encrypted(&json));
+        assert!(!json.contains(&keypair.private.to_base64()));
+
+        let parsed = EncryptedKey::from_json(&json).unwrap();
+        let decrypted = decrypt_keypair(&parsed, "correct horse").unwrap();
+        assert_eq!(decrypted.public, keypair.public);
+        assert_eq!(decrypted.private.as_bytes(), keypair.private.as_bytes());
+    }
+
+    #[test]
+    fn test_wrong_passphrase() {
+        let keypair = generate_keypair().unwrap();
+        let encrypted = encrypt_keypair_with_params(&keypair, "correct horse", TEST_PARAMS).unwrap();
+
+        assert!(decrypt_keypair(&encrypted, "battery staple").is_err());
+        assert!(encrypt_keypair_with_params(&keypair, "", TEST_PARAMS).is_err());
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_data() {
+        let encrypted = encrypt_data(b"issuer state", "correct horse", TEST_PARAMS).unwrap();
+        assert_eq!(decrypt_data(&encrypted, "correct horse").unwrap(), b"issuer state");
+        assert!(decrypt_data(&encrypted, "battery staple").is_err());
+
+        let mut tampered = encrypted.clone();
+        tampered.ciphertext = encrypt_data(b"other state!", "correct horse", TEST_PARAMS).unwrap().ciphertext;
+        assert!(decrypt_data(&tampered, "correct horse").is_err());
+        assert!(encrypt_data(b"x", "", TEST_PARAMS).is_err());
+    }
+
+    #[test]
+    fn test_rejects_excessive_kdf_params() {
+        let keypair = generate_keypair().unwrap();
+        let encrypted = encrypt_keypair_with_params(&keypair, "correct horse", TEST_PARAMS).unwrap();
+
+        for params in [
+            KdfParams { m_cost: KdfParams::MAX.m_cost + 1, ..TEST_PARAMS },
+            KdfParams { t_cost: KdfParams::MAX.t_cost + 1, ..TEST_PARAMS },
+            KdfParams { p_cost: KdfParams::MAX.p_cost + 1, ..TEST_PARAMS },
+        ] {
+            let mut crafted = encrypted.clone();
+            crafted.kdf_params = params;
+            let err = decrypt_keypair(&crafted, "correct horse").unwrap_err();
+            assert!(err.to_string().contains("exceed"), "{}", err);
+            assert!(encrypt_data(b"x", "correct horse", params).is_err());
+        }
+    }
+
+    #[test]
+    fn test_plain_key_not_detected_as_encrypted() {
+        let keypair = generate_keypair().unwrap();
+        let plain = serde_json::json!({
+            "private_key": keypair.private.to_base64(),
+            "public_key": keypair.public.to_base64(),
+        });
+
+        assert!(!EncryptedKey::is_encrypted(&plain.to_string()));
+        assert!(!EncryptedKey::is_encrypted(&keypair.private.to_base64()));
+    }
+}
@@ -7,6 +7,11 @@
 //! - SHA256 hashing for phone numbers
 //! - Ed25519 signatures for binding proofs
 //! - Nonce generation and validation
+//! - Passphrase-encrypted key storage
+//! - Deterministic key derivation from a backup phrase
+//! - HMAC request signatures for partner integrations
+//! - HTTP Message Signatures (RFC 9421) for the same partners
+//! - Shamir secret sharing for key ceremonies
 //! - Timing-attack resistant comparisons
 //! 
 //! # Security Design
@@ -19,20 +24,37 @@
 #![warn(missing_docs)]
 #![forbid(unsafe_code)]
 
+pub mod derivation;
 pub mod hashing;
+pub mod keystore;
+pub mod message_signatures;
 pub mod nonce;
+pub mod request_signing;
+pub mod shamir;
 pub mod signing;
 
 // Re-export commonly used functions
+pub use derivation::{
+    keypair_from_mnemonic, keypair_from_seed, parse_derivation_path, DEFAULT_DERIVATION_PATH,
+};
 pub use hashing::{
     constant_time_compare, create_binding_signature, generate_salt, 
-    hash_phone_number, hash_phone_number_spec, sha256, 
+    hash_phone_number, hash_phone_number_spec, hmac_sha256, sha256, 
     verify_binding_signature,
 };
+pub use keystore::{
+    decrypt_data, decrypt_keypair, encrypt_data, encrypt_keypair, EncryptedData, EncryptedKey, KdfParams,
+};
+pub use message_signatures::{
+    content_digest, verify_content_digest, MessageSignature, CONTENT_DIGEST_HEADER, SIGNATURE_HEADER,
+    SIGNATURE_INPUT_HEADER,
+};
 pub use nonce::{
     generate_nonce, generate_hex_nonce, generate_timestamped_nonce, validate_timestamped_nonce, NonceTracker,
 };
+pub use request_signing::{RequestSignature, REQUEST_SIGNATURE_HEADER};
+pub use shamir::{combine_shares, split_secret, SecretShare};
 pub use signing::{
-    generate_keypair, keypair_from_private, sign_challenge_response, sign_message, 
-    verify_challenge_response, verify_signature,
+    generate_keypair, keypair_from_private, public_key_fingerprint, sign_challenge_response, 
+    sign_message, verify_challenge_response, verify_signature, CachedSigner, MessageSigner,
 };
\ No newline at end of file
@@ -0,0 +1,345 @@
+//! HTTP Message Signatures (RFC 9421) for partner integrations.
+//!
+//! The standard alternative to [`RequestSignature`](crate::RequestSignature):
+//! partners sign with the same shared secret using the `hmac-sha256`
+//! algorithm, naming themselves in `keyid`, so off-the-shelf RFC 9421
+//! libraries can talk to the issuer. The body is bound through a
+//! `Content-Digest` header (RFC 9530) covered by the signature.
+//!
+//! Requests signed here cover `@method`, `@path` and `content-digest`,
+//! under the label `sig1`:
+//!
+//! ```text
+//! Content-Digest: sha-256=:<base64>:
+//! Signature-Input: sig1=("@method" "@path" "content-digest");created=1700000000;keyid="acme";alg="hmac-sha256"
+//! Signature: sig1=:<base64>:
+//! ```
+//!
+//! Only the structured-field syntax these headers use in practice is
+//! parsed: string components without parameters, and integer or string
+//! signature parameters.
+
+use crate::hashing::{constant_time_compare, hmac_sha256, sha256};
+use base64::{engine::general_purpose, Engine as _};
+
+/// Header declaring what a signature covers.
+pub const SIGNATURE_INPUT_HEADER: &str = "Signature-Input";
+
+/// Header carrying the signature.
+pub const SIGNATURE_HEADER: &str = "Signature";
+
+/// Header carrying the body digest.
+pub const CONTENT_DIGEST_HEADER: &str = "Content-Digest";
+
+/// Algorithm of signatures made with a shared secret.
+pub const HMAC_SHA256: &str = "hmac-sha256";
+
+/// Label of signatures made by [`MessageSignature::sign`].
+pub const SIGNATURE_LABEL: &str = "sig1";
+
+/// Components [`MessageSignature::sign`] covers.
+pub const SIGNED_COMPONENTS: [&str; 3] = ["@method", "@path", "content-digest"];
+
+/// `Content-Digest` value for a body: its SHA-256, as RFC 9530 writes it.
+pub fn content_digest(body: &[u8]) -> String {
+    format!("sha-256=:{}:", general_purpose::STANDARD.encode(sha256(body)))
+}
+
+/// Whether a `Content-Digest` value has a SHA-256 digest matching `body`.
+///
+/// Digests in other algorithms are ignored; one of them alone doesn't
+/// match.
+pub fn verify_content_digest(header: &str, body: &[u8]) -> bool {
+    let expected = sha256(body);
+    split_outside_quotes(header, ',').into_iter().any(|member| {
+        member
+            .trim()
+            .strip_prefix("sha-256=")
+            .and_then(byte_sequence)
+            .is_some_and(|digest| constant_time_compare(&digest, &expected))
+    })
+}
+
+/// A signature parsed from `Signature-Input` and `Signature`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageSignature {
+    /// Label naming the signature in both headers.
+    pub label: String,
+    /// Covered components, in order.
+    pub components: Vec<String>,
+    /// When it was signed (Unix seconds).
+    pub created: i64,
+    /// When it expires (Unix seconds), if the signer said.
+    pub expires: Option<i64>,
+    /// Key that signed: the partner ID.
+    pub key_id: String,
+    /// Algorithm, if the signer said.
+    pub alg: Option<String>,
+    /// Signature bytes.
+    pub signature: Vec<u8>,
+    /// The `Signature-Input` member value as signed.
+    params: String,
+}
+
+impl MessageSignature {
+    /// Sign a request over [`SIGNED_COMPONENTS`], with the digest of
+    /// `body` as its `Content-Digest`.
+    pub fn sign(key_id: &str, secret: &[u8], created: i64, method: &str, path: &str, body: &[u8]) -> Self {
+        let components = SIGNED_COMPONENTS.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        let params = format!(
+            "({});created={};keyid=\"{}\";alg=\"{}\"",
+            components.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(" "),
+            created,
+            key_id,
+            HMAC_SHA256
+        );
+        let mut signature = Self {
+            label: SIGNATURE_LABEL.to_string(),
+            components,
+            created,
+            expires: None,
+            key_id: key_id.to_string(),
+            alg: Some(HMAC_SHA256.to_string()),
+            signature: Vec::new(),
+            params,
+        };
+        let digest = content_digest(body);
+        let base = signature
+            .signature_base(|component| match component {
+                "@method" => Some(method.to_ascii_uppercase()),
+                "@path" => Some(path.to_string()),
+                "content-digest" => Some(digest.clone()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        signature.signature = hmac_sha256(secret, base.as_bytes()).to_vec();
+        signature
+    }
+
+    /// Parse the first signature declared in `Signature-Input` that has a
+    /// value in `Signature`.
+    pub fn parse(signature_input: &str, signature: &str) -> Option<Self> {
+        let signatures = split_outside_quotes(signature, ',')
+            .into_iter()
+            .filter_map(|member| {
+                let (label, value) = member.trim().split_once('=')?;
+                Some((label.to_string(), byte_sequence(value)?))
+            })
+            .collect::<Vec<_>>();
+        split_outside_quotes(signature_input, ',').into_iter().find_map(|member| {
+            let (label, params) = member.trim().split_once('=')?;
+            let (_, bytes) = signatures.iter().find(|(l, _)| l == label)?;
+            Self::parse_params(label, params, bytes.clone())
+        })
+    }
+
+    fn parse_params(label: &str, params: &str, signature: Vec<u8>) -> Option<Self> {
+        let inner = params.strip_prefix('(')?;
+        let (list, rest) = inner.split_once(')')?;
+        let components = list
+            .split_whitespace()
+            .map(|item| string_item(item).map(str::to_string))
+            .collect::<Option<Vec<_>>>()?;
+        let (mut created, mut expires, mut key_id, mut alg) = (None, None, None, None);
+        for param in split_outside_quotes(rest, ';').into_iter().filter(|p| !p.is_empty()) {
+            match param.split_once('=')? {
+                ("created", value) => created = value.parse().ok(),
+                ("expires", value) => expires = Some(value.parse().ok()?),
+                ("keyid", value) => key_id = string_item(value).map(str::to_string),
+                ("alg", value) => alg = string_item(value).map(str::to_string),
+                _ => {}
+            }
+        }
+        Some(Self {
+            label: label.to_string(),
+            components,
+            created: created?,
+            expires,
+            key_id: key_id.filter(|key_id| !key_id.is_empty())?,
+            alg,
+            signature,
+            params: params.to_string(),
+        })
+    }
+
+    /// `Signature-Input` header value.
+    pub fn signature_input(&self) -> String {
+        format!("{}={}", self.label, self.params)
+    }
+
+    /// `Signature` header value.
+    pub fn to_header(&self) -> String {
+        format!("{}=:{}:", self.label, general_purpose::STANDARD.encode(&self.signature))
+    }
+
+    /// Whether the signature covers `component`.
+    pub fn covers(&self, component: &str) -> bool {
+        self.components.iter().any(|c| c == component)
+    }
+
+    /// Check the signature against a request, in constant time.
+    ///
+    /// `component` gives the value of each covered component: derived ones
+    /// like `@method`, and header fields by lowercase name. A component it
+    /// can't give fails the check, as does an algorithm other than
+    /// [`HMAC_SHA256`]. What must be covered, the freshness of `created`
+    /// and whether `content-digest` matches the body are left to the caller.
+    pub fn verify(&self, secret: &[u8], component: impl Fn(&str) -> Option<String>) -> bool {
+        if self.alg.as_deref().is_some_and(|alg| alg != HMAC_SHA256) {
+            return false;
+        }
+        let Some(base) = self.signature_base(component) else {
+            return false;
+        };
+        constant_time_compare(&hmac_sha256(secret, base.as_bytes()), &self.signature)
+    }
+
+    /// The signature base (RFC 9421 section 2.5).
+    fn signature_base(&self, component: impl Fn(&str) -> Option<String>) -> Option<String> {
+        let mut base = String::new();
+        for name in &self.components {
+            let value = component(name)?;
+            // A line break in a value would let it forge the next line
+            if value.contains(['\n', '\r']) {
+                return None;
+            }
+            base.push_str(&format!("\"{}\": {}\n", name, value.trim()));
+        }
+        base.push_str(&format!("\"@signature-params\": {}", self.params));
+        Some(base)
+    }
+}
+
+/// The contents of a quoted string item.
+fn string_item(item: &str) -> Option<&str> {
+    item.strip_prefix('"')?.strip_suffix('"')
+}
+
+/// Decode a `:base64:` byte sequence item.
+fn byte_sequence(item: &str) -> Option<Vec<u8>> {
+    let encoded = item.trim().strip_prefix(':')?.strip_suffix(':')?;
+    general_purpose::STANDARD.decode(encoded).ok()
+}
+
+/// Split on `separator` where it isn't inside a quoted string or an inner
+/// list.
+fn split_outside_quotes(value: &str, separator: char) -> Vec<&str> {
+    let (mut parts, mut start, mut quoted, mut depth) = (Vec::new(), 0, false, 0usize);
+    let mut chars = value.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quoted => {
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth = depth.saturating_sub(1),
+            c if c == separator && !quoted && depth == 0 => {
+                parts.push(&value[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn components<'a>(method: &'a str, path: &'a str, body: &'a [u8]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |component| match component {
+            "@method" => Some(method.to_string()),
+            "@path" => Some(path.to_string()),
+            "content-digest" => Some(content_digest(body)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signature = MessageSignature::sign("acme", b"secret", 1700000000, "post", "/attest", b"{}");
+        assert_eq!(
+            signature.signature_input(),
+            "sig1=(\"@method\" \"@path\" \"content-digest\");created=1700000000;keyid=\"acme\";alg=\"hmac-sha256\""
+        );
+        let parsed = MessageSignature::parse(&signature.signature_input(), &signature.to_header()).unwrap();
+        assert_eq!(parsed, signature);
+        assert_eq!(parsed.key_id, "acme");
+        assert!(parsed.covers("content-digest"));
+
+        assert!(parsed.verify(b"secret", components("POST", "/attest", b"{}")));
+        assert!(!parsed.verify(b"other", components("POST", "/attest", b"{}")));
+        assert!(!parsed.verify(b"secret", components("POST", "/attest/simple", b"{}")));
+        assert!(!parsed.verify(b"secret", components("POST", "/attest", b"{\"a\":1}")));
+        assert!(!parsed.verify(b"secret", |_| None));
+    }
+
+    #[test]
+    fn test_signature_base() {
+        let signature = MessageSignature::sign("acme", b"secret", 1700000000, "POST", "/attest", b"{}");
+        let base = signature.signature_base(components("POST", "/attest", b"{}")).unwrap();
+        assert_eq!(
+            base,
+            format!(
+                "\"@method\": POST\n\"@path\": /attest\n\"content-digest\": {}\n\"@signature-params\": {}",
+                content_digest(b"{}"),
+                signature.signature_input().trim_start_matches("sig1=")
+            )
+        );
+        assert!(signature.signature_base(|_| Some("a\nb".to_string())).is_none());
+    }
+
+    #[test]
+    fn test_parse_from_other_signers() {
+        // Other tools order parameters differently, add their own and may
+        // send several signatures; the parameters are signed as sent
+        let secret = b"secret";
+        let input = "sig0=(\"@method\");created=1;keyid=\"other\", \
+                     req=(\"@method\" \"@path\" \"content-type\");keyid=\"acme\";created=1700000000;nonce=\"a;b\";expires=1700000300";
+        let mut signature = MessageSignature::parse(input, "req=:AAAA:").unwrap();
+        assert_eq!(signature.label, "req");
+        assert_eq!(signature.components, ["@method", "@path", "content-type"]);
+        assert_eq!(signature.expires, Some(1700000300));
+        assert_eq!(signature.alg, None);
+
+        let component = |name: &str| match name {
+            "@method" => Some("POST".to_string()),
+            "@path" => Some("/attest".to_string()),
+            "content-type" => Some("application/json".to_string()),
+            _ => None,
+        };
+        let base = signature.signature_base(component).unwrap();
+        assert!(base.ends_with(
+            "\"@signature-params\": (\"@method\" \"@path\" \"content-type\");keyid=\"acme\";created=1700000000;nonce=\"a;b\";expires=1700000300"
+        ));
+        signature.signature = hmac_sha256(secret, base.as_bytes()).to_vec();
+        assert!(signature.verify(secret, component));
+
+        signature.alg = Some("ed25519".to_string());
+        assert!(!signature.verify(secret, component));
+    }
+
+    #[test]
+    fn test_parse_rejects_incomplete() {
+        let signature = "sig1=:AAAA:";
+        assert!(MessageSignature::parse("sig1=(\"@method\");keyid=\"acme\"", signature).is_none());
+        assert!(MessageSignature::parse("sig1=(\"@method\");created=1", signature).is_none());
+        assert!(MessageSignature::parse("sig1=(@method);created=1;keyid=\"acme\"", signature).is_none());
+        assert!(MessageSignature::parse("sig2=(\"@method\");created=1;keyid=\"acme\"", signature).is_none());
+        assert!(MessageSignature::parse("sig1=(\"@method\");created=1;keyid=\"acme\"", "sig1=AAAA").is_none());
+        assert!(MessageSignature::parse("garbage", "garbage").is_none());
+    }
+
+    #[test]
+    fn test_content_digest() {
+        let digest = content_digest(b"{\"hello\": \"world\"}");
+        assert_eq!(digest, "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:");
+        assert!(verify_content_digest(&digest, b"{\"hello\": \"world\"}"));
+        assert!(verify_content_digest(&format!("sha-512=:AAAA:, {}", digest), b"{\"hello\": \"world\"}"));
+        assert!(!verify_content_digest(&digest, b"{}"));
+        assert!(!verify_content_digest("sha-512=:AAAA:", b"{}"));
+    }
+}
@@ -1,9 +1,9 @@
 //! Nonce generation and validation for replay protection.
 
+use dashmap::DashSet;
 use hesha_types::{HeshaError, HeshaResult, Nonce};
 use rand::{RngCore, rngs::OsRng};
-use std::collections::HashSet;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use base64::{Engine as _, engine::general_purpose};
 use hex;
@@ -52,50 +52,45 @@ pub fn generate_timestamped_nonce() -> HeshaResult<Nonce> {
 
 /// Simple in-memory nonce tracking for replay protection.
 /// 
+/// Nonces are spread over independently locked shards, so concurrent
+/// checks of different nonces rarely wait on each other.
+/// 
 /// # Security Considerations
 /// - This is a basic implementation for testing
 /// - Production systems should use distributed storage
 /// - Nonces should expire after reasonable time
 #[derive(Debug, Clone)]
 pub struct NonceTracker {
-    used_nonces: Arc<Mutex<HashSet<String>>>,
+    used_nonces: Arc<DashSet<String>>,
 }
 
 impl NonceTracker {
     /// Create a new nonce tracker.
     pub fn new() -> Self {
         Self {
-            used_nonces: Arc::new(Mutex::new(HashSet::new())),
+            used_nonces: Arc::new(DashSet::new()),
         }
     }
     
     /// Check if a nonce has been used and mark it as used.
     /// 
-    /// Returns Ok(()) if nonce is new, Err if already used.
+    /// Returns Ok(()) if nonce is new, Err if already used. Checking and
+    /// marking are atomic, so concurrent callers can't both succeed.
     pub fn use_nonce(&self, nonce: &Nonce) -> HeshaResult<()> {
-        let mut used = self.used_nonces.lock()
-            .map_err(|_| HeshaError::CryptoError("Lock poisoned".to_string()))?;
-        
-        if used.contains(nonce.as_str()) {
+        if !self.used_nonces.insert(nonce.as_str().to_string()) {
             return Err(HeshaError::InvalidNonce);
         }
-        
-        used.insert(nonce.as_str().to_string());
         Ok(())
     }
     
     /// Check if a nonce has been used without marking it.
     pub fn is_used(&self, nonce: &Nonce) -> bool {
-        self.used_nonces.lock()
-            .map(|used| used.contains(nonce.as_str()))
-            .unwrap_or(true) // Fail safe
+        self.used_nonces.contains(nonce.as_str())
     }
     
     /// Clear all tracked nonces (for testing).
     pub fn clear(&self) {
-        if let Ok(mut used) = self.used_nonces.lock() {
-            used.clear();
-        }
+        self.used_nonces.clear();
     }
 }
 
@@ -212,6 +207,21 @@ mod tests {
         assert!(!tracker.is_used(&nonce));
     }
     
+    #[test]
+    fn test_tracker_concurrent_use() {
+        let tracker = NonceTracker::new();
+        let nonce = generate_nonce();
+        
+        // Exactly one of many racing threads may use the same nonce
+        let accepted = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| s.spawn(|| tracker.use_nonce(&nonce).is_ok()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).filter(|ok| *ok).count()
+        });
+        assert_eq!(accepted, 1);
+    }
+    
     #[test]
     fn test_future_nonce_rejection() {
         // Create a "future" nonce manually
@@ -0,0 +1,208 @@
+//! Shamir secret sharing over GF(2^8).
+//!
+//! Splits a secret into `n` shares so that any `k` of them recover it and
+//! fewer reveal nothing about it. Each byte of the secret is shared with
+//! its own random polynomial of degree `k - 1`, evaluated at the share's
+//! index (1 to 255).
+//!
+//! Polynomial coefficients are drawn uniformly from all 256 byte values.
+//! Implementations that skip zero (as the `sharks` crate did, see
+//! RUSTSEC-2024-0398) bias the shares and leak information about the
+//! secret, which is why this lives here rather than behind a dependency.
+//! Field arithmetic runs without secret-dependent branches or tables.
+
+use hesha_types::{HeshaError, HeshaResult};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// One share of a secret.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretShare {
+    /// Point the share was evaluated at, from 1.
+    pub index: u8,
+    /// Evaluations, one per byte of the secret (hex).
+    #[serde(with = "hex_bytes")]
+    pub value: Vec<u8>,
+}
+
+impl std::fmt::Debug for SecretShare {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Shares are secret material; never print them in logs
+        f.debug_struct("SecretShare").field("index", &self.index).finish_non_exhaustive()
+    }
+}
+
+impl SecretShare {
+    /// Bytes identifying the share: its index followed by its value.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.value.len() + 1);
+        bytes.push(self.index);
+        bytes.extend_from_slice(&self.value);
+        bytes
+    }
+
+    /// SHA-256 of [`to_bytes`](Self::to_bytes), which can be published to
+    /// let each holder check their share without revealing it.
+    pub fn digest(&self) -> [u8; 32] {
+        crate::sha256(&self.to_bytes())
+    }
+}
+
+/// Split `secret` into `shares` shares, any `threshold` of which recover it.
+pub fn split_secret(secret: &[u8], threshold: u8, shares: u8) -> HeshaResult<Vec<SecretShare>> {
+    if threshold < 2 {
+        return Err(HeshaError::CryptoError("Threshold must be at least 2".into()));
+    }
+    if shares < threshold {
+        return Err(HeshaError::CryptoError(format!(
+            "Cannot recover with {} of only {} shares",
+            threshold, shares
+        )));
+    }
+    if secret.is_empty() {
+        return Err(HeshaError::CryptoError("Secret is empty".into()));
+    }
+
+    let mut result: Vec<SecretShare> = (1..=shares)
+        .map(|index| SecretShare { index, value: Vec::with_capacity(secret.len()) })
+        .collect();
+    let mut coefficients = vec![0u8; usize::from(threshold)];
+    for &byte in secret {
+        coefficients[0] = byte;
+        OsRng.fill_bytes(&mut coefficients[1..]);
+        for share in &mut result {
+            // Horner's rule, highest coefficient first
+            let y = coefficients.iter().rev().fold(0, |acc, &c| gf_mul(acc, share.index) ^ c);
+            share.value.push(y);
+        }
+    }
+    coefficients.iter_mut().for_each(|c| *c = 0);
+    Ok(result)
+}
+
+/// Recover a secret from at least as many shares as its threshold.
+///
+/// Fewer shares, or shares of different secrets, yield a wrong secret
+/// rather than an error: check the result against something known, such
+/// as the public key of a shared private key.
+pub fn combine_shares(shares: &[SecretShare]) -> HeshaResult<Vec<u8>> {
+    let Some(first) = shares.first() else {
+        return Err(HeshaError::CryptoError("No shares given".into()));
+    };
+    let len = first.value.len();
+    for (i, share) in shares.iter().enumerate() {
+        if share.index == 0 {
+            return Err(HeshaError::CryptoError("Share index 0 is not valid".into()));
+        }
+        if share.value.len() != len {
+            return Err(HeshaError::CryptoError("Shares are of different secrets".into()));
+        }
+        if shares[..i].iter().any(|other| other.index == share.index) {
+            return Err(HeshaError::CryptoError(format!("Share {} was given twice", share.index)));
+        }
+    }
+
+    // Lagrange basis at x = 0; subtraction in GF(2^8) is XOR
+    let basis: Vec<u8> = shares
+        .iter()
+        .map(|share| {
+            let (numerator, denominator) = shares
+                .iter()
+                .filter(|other| other.index != share.index)
+                .fold((1, 1), |(num, den), other| {
+                    (gf_mul(num, other.index), gf_mul(den, other.index ^ share.index))
+                });
+            gf_mul(numerator, gf_inv(denominator))
+        })
+        .collect();
+    Ok((0..len)
+        .map(|i| shares.iter().zip(&basis).fold(0, |acc, (share, &l)| acc ^ gf_mul(share.value[i], l)))
+        .collect())
+}
+
+/// Multiply in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// Multiplicative inverse in GF(2^8), as a^254.
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1;
+    let mut base = a;
+    // 254 = 0b11111110
+    for bit in 0..8 {
+        if (254 >> bit) & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+    }
+    result
+}
+
+mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        hex::decode(String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_arithmetic() {
+        // Known product from FIPS-197
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+        }
+    }
+
+    #[test]
+    fn test_any_threshold_subset_recovers() {
+        let secret = b"issuer signing key seed material";
+        let shares = split_secret(secret, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+        for a in 0..5 {
+            for b in a + 1..5 {
+                for c in b + 1..5 {
+                    let subset = [shares[a].clone(), shares[b].clone(), shares[c].clone()];
+                    assert_eq!(combine_shares(&subset).unwrap(), secret);
+                }
+            }
+        }
+        assert_eq!(combine_shares(&shares).unwrap(), secret);
+        assert_ne!(combine_shares(&shares[..2]).unwrap(), secret);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(split_secret(b"secret", 1, 3).is_err());
+        assert!(split_secret(b"secret", 4, 3).is_err());
+        assert!(split_secret(b"", 2, 3).is_err());
+
+        let shares = split_secret(b"secret", 2, 3).unwrap();
+        assert!(combine_shares(&[]).is_err());
+        assert!(combine_shares(&[shares[0].clone(), shares[0].clone()]).is_err());
+        let other = split_secret(b"longer secret", 2, 3).unwrap();
+        assert!(combine_shares(&[shares[0].clone(), other[1].clone()]).is_err());
+
+        let json = serde_json::to_string(&shares[1]).unwrap();
+        assert_eq!(serde_json::from_str::<SecretShare>(&json).unwrap(), shares[1]);
+        assert!(!format!("{:?}", shares[1]).contains(&hex::encode(&shares[1].value)));
+    }
+}
@@ -37,6 +37,20 @@ pub fn keypair_from_private(private_key: &PrivateKey) -> HeshaResult<KeyPair> {
     Ok(KeyPair::new(public_key, private_key.to_owned()))
 }
 
+/// Compute a short fingerprint for a public key.
+/// 
+/// Format: first 16 bytes of SHA256(public key) as colon-separated hex,
+/// e.g. "3f:a2:...". Suitable for display and comparison, not as an identifier
+/// in the protocol.
+pub fn public_key_fingerprint(public_key: &PublicKey) -> String {
+    let hash = crate::hashing::sha256(public_key.as_bytes());
+    hash[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
 /// Sign a message with a private key.
 /// 
 /// # Security Considerations
@@ -49,6 +63,63 @@ pub fn sign_message(private_key: &PrivateKey, message: &[u8]) -> HeshaResult<Sig
     Ok(Signature::from_bytes(signature.to_bytes()))
 }
 
+/// Signs messages with an Ed25519 private key.
+///
+/// Implemented by [`PrivateKey`], which expands the key for every
+/// signature, and by [`CachedSigner`], which expands it once.
+pub trait MessageSigner: Send + Sync {
+    /// Sign `message`.
+    fn sign_message(&self, message: &[u8]) -> HeshaResult<Signature>;
+}
+
+impl MessageSigner for PrivateKey {
+    fn sign_message(&self, message: &[u8]) -> HeshaResult<Signature> {
+        sign_message(self, message)
+    }
+}
+
+/// A private key expanded once for repeated signing.
+///
+/// Building an Ed25519 signing key hashes the seed and derives the public
+/// key, which costs about as much as a signature. Long-running signers
+/// such as the issuer node keep one of these per key instead.
+pub struct CachedSigner {
+    signing_key: SigningKey,
+    public: PublicKey,
+}
+
+impl CachedSigner {
+    /// Expand `private_key`.
+    pub fn new(private_key: &PrivateKey) -> Self {
+        let signing_key = SigningKey::from_bytes(private_key.as_bytes());
+        let public = PublicKey::from_bytes(signing_key.verifying_key().to_bytes());
+        Self { signing_key, public }
+    }
+
+    /// Public key of the signing key.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public
+    }
+}
+
+impl MessageSigner for CachedSigner {
+    fn sign_message(&self, message: &[u8]) -> HeshaResult<Signature> {
+        Ok(Signature::from_bytes(self.signing_key.sign(message).to_bytes()))
+    }
+}
+
+impl From<&KeyPair> for CachedSigner {
+    fn from(keypair: &KeyPair) -> Self {
+        Self::new(&keypair.private)
+    }
+}
+
+impl std::fmt::Debug for CachedSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachedSigner").field("public", &self.public).finish_non_exhaustive()
+    }
+}
+
 /// Verify a signature against a message and public key.
 /// 
 /// # Security Considerations
@@ -162,6 +233,31 @@ mod tests {
         ));
     }
     
+    #[test]
+    fn test_cached_signer() {
+        let keypair = generate_keypair().unwrap();
+        let signer = CachedSigner::from(&keypair);
+        assert_eq!(signer.public_key(), &keypair.public);
+
+        // Ed25519 is deterministic, so both paths give the same signature
+        let message = b"test message";
+        let cached = signer.sign_message(message).unwrap();
+        assert_eq!(cached, sign_message(&keypair.private, message).unwrap());
+        assert!(verify_signature(&keypair.public, message, &cached));
+        assert!(!format!("{:?}", signer).contains(&keypair.private.to_base64()));
+    }
+    
+    #[test]
+    fn test_public_key_fingerprint() {
+        let key = PublicKey::from_bytes([42u8; 32]);
+        let fingerprint = public_key_fingerprint(&key);
+        
+        // 16 bytes as hex pairs joined by colons
+        assert_eq!(fingerprint.len(), 47);
+        assert_eq!(fingerprint, public_key_fingerprint(&key));
+        assert_ne!(fingerprint, public_key_fingerprint(&PublicKey::from_bytes([1u8; 32])));
+    }
+    
     #[test]
     fn test_invalid_signatures() {
         let keypair = generate_keypair().unwrap();
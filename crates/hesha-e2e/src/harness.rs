@@ -0,0 +1,195 @@
+//! The in-process nodes.
+
+use hesha_client::IssuerClient;
+use hesha_types::{ChallengeVerifyRequest, VerificationReport, VerifyRequest};
+use issuer_node::verification::MOCK_VERIFICATION_CODE;
+use std::net::SocketAddr;
+use std::path::Path;
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+/// Operator key the harness serves the issuer's `/admin` routes to.
+const ADMIN_KEY: &str = "harness-admin-key";
+
+/// An issuer node and a verifier node serving on loopback ports until
+/// dropped.
+pub struct Harness {
+    issuer_addr: SocketAddr,
+    verifier_addr: SocketAddr,
+    issuer: issuer_node::AppState,
+    http: reqwest::Client,
+    dir: TempDir,
+    shutdown: Vec<oneshot::Sender<()>>,
+}
+
+impl Harness {
+    /// Start both nodes with the harness defaults.
+    pub async fn start() -> anyhow::Result<Self> {
+        Self::start_with(|_| {}).await
+    }
+
+    /// Start both nodes, letting `configure` adjust the issuer's
+    /// configuration first.
+    ///
+    /// The defaults differ from a fresh issuer's in three ways: records are
+    /// kept in SQLite in the harness directory, rate limits are off so
+    /// scenarios can repeat, and the `/admin` routes accept the harness's
+    /// operator key unless `[admin_auth]` is configured. The bind address
+    /// and domain are set by the harness; changing them breaks discovery.
+    pub async fn start_with(configure: impl FnOnce(&mut issuer_node::Config)) -> anyhow::Result<Self> {
+        let dir = tempfile::tempdir()?;
+        let issuer_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let issuer_addr = issuer_listener.local_addr()?;
+        let verifier_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let verifier_addr = verifier_listener.local_addr()?;
+
+        let mut config = issuer_node::Config {
+            storage_url: format!("sqlite:{}", dir.path().join("issuer.db").display()),
+            ip_rate_limit: 0,
+            phone_rate_limit: 0,
+            ..issuer_node::Config::default()
+        };
+        configure(&mut config);
+        config.bind_address = issuer_addr;
+        config.domain = issuer_addr.to_string();
+        let admin_unset = config.admin_auth.is_none();
+        let mut issuer = issuer_node::build_state(config).await?;
+        if admin_unset {
+            issuer = issuer.with_admin_auth(issuer_node::auth::Authenticator::ApiKeys(vec![ADMIN_KEY.to_string()]));
+        }
+        let app = issuer_node::router(issuer.clone())?;
+
+        // Revocations show up in the next verification, not a minute later
+        let verifier_config = verifier_node::Config {
+            bind_address: verifier_addr,
+            revocation_ttl_secs: 0,
+            ..verifier_node::Config::default()
+        };
+        let policy = verifier_config.policy()?;
+        let verifier = verifier_node::app(verifier_node::AppState::new(verifier_config, policy));
+
+        let (issuer_shutdown, issuer_stopped) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let server = axum::serve(issuer_listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                .with_graceful_shutdown(async {
+                    issuer_stopped.await.ok();
+                });
+            if let Err(e) = server.await {
+                tracing::error!("In-process issuer failed: {}", e);
+            }
+        });
+        let (verifier_shutdown, verifier_stopped) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let server = axum::serve(verifier_listener, verifier).with_graceful_shutdown(async {
+                verifier_stopped.await.ok();
+            });
+            if let Err(e) = server.await {
+                tracing::error!("In-process verifier failed: {}", e);
+            }
+        });
+
+        Ok(Self {
+            issuer_addr,
+            verifier_addr,
+            issuer,
+            http: reqwest::Client::new(),
+            dir,
+            shutdown: vec![issuer_shutdown, verifier_shutdown],
+        })
+    }
+
+    /// Issuer base URL, e.g. `http://127.0.0.1:41234`.
+    pub fn issuer_url(&self) -> String {
+        format!("http://{}", self.issuer_addr)
+    }
+
+    /// Issuer domain, as attestations name it.
+    pub fn issuer_domain(&self) -> String {
+        self.issuer_addr.to_string()
+    }
+
+    /// Verifier base URL.
+    pub fn verifier_url(&self) -> String {
+        format!("http://{}", self.verifier_addr)
+    }
+
+    /// A client for the issuer, holding the operator key for its `/admin`
+    /// routes.
+    pub fn issuer_client(&self) -> anyhow::Result<IssuerClient> {
+        // Plain HTTP is only accepted for `localhost`, which is this address
+        Ok(IssuerClient::new(&format!("http://localhost:{}", self.issuer_addr.port()))?.with_admin_token(ADMIN_KEY))
+    }
+
+    /// The issuer's state, for looking at its storage, audit log or
+    /// metrics behind the API.
+    pub fn issuer_state(&self) -> &issuer_node::AppState {
+        &self.issuer
+    }
+
+    /// Code the issuer's mock phone verification accepts.
+    pub fn otp_code(&self) -> &'static str {
+        MOCK_VERIFICATION_CODE
+    }
+
+    /// Directory holding the issuer's records, removed on drop.
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Ask the verifier node about an attestation.
+    pub async fn verify(&self, jwt: &str) -> anyhow::Result<VerificationReport> {
+        let request = VerifyRequest {
+            attestation: jwt.to_string(),
+        };
+        self.post_verifier("verify", &request).await
+    }
+
+    /// Ask the verifier node about an attestation and a wallet's answer to
+    /// a challenge made with it.
+    pub async fn verify_challenge(&self, request: &ChallengeVerifyRequest) -> anyhow::Result<VerificationReport> {
+        self.post_verifier("verify/challenge-response", request).await
+    }
+
+    /// Revoke an attestation as an operator would.
+    pub async fn revoke(&self, jti: &str, reason: Option<&str>) -> anyhow::Result<()> {
+        let response = self
+            .http
+            .post(format!("{}/admin/revoke", self.issuer_url()))
+            .bearer_auth(ADMIN_KEY)
+            .json(&serde_json::json!({ "jti": jti, "reason": reason }))
+            .send()
+            .await?;
+        anyhow::ensure!(
+            response.status().is_success(),
+            "Revocation failed with {}: {}",
+            response.status(),
+            response.text().await.unwrap_or_default()
+        );
+        Ok(())
+    }
+
+    async fn post_verifier<T: serde::Serialize + ?Sized>(&self, path: &str, body: &T) -> anyhow::Result<VerificationReport> {
+        let response = self
+            .http
+            .post(format!("{}/{}", self.verifier_url(), path))
+            .json(body)
+            .send()
+            .await?;
+        anyhow::ensure!(
+            response.status().is_success(),
+            "Verifier answered {}: {}",
+            response.status(),
+            response.text().await.unwrap_or_default()
+        );
+        Ok(response.json().await?)
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        for shutdown in self.shutdown.drain(..) {
+            shutdown.send(()).ok();
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! End-to-end harness running a real issuer and verifier in-process.
+//!
+//! [`Harness`] boots the issuer node and the verifier node from their own
+//! crates on ephemeral loopback ports, inside the test's tokio runtime,
+//! with the issuer's records in a temporary directory. Phone verification
+//! uses the issuer's mock provider, so every OTP flow completes.
+//!
+//! The [`scenarios`] module drives the protocol through the nodes' HTTP
+//! APIs as wallets and operators do, one step per function, so other
+//! crates' tests can run a full flow or stop at the step they care about:
+//!
+//! ```no_run
+//! use hesha_e2e::scenarios;
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let (harness, user) = scenarios::setup("+14155552671").await?;
+//! let issued = scenarios::attest(&harness, &user, "1").await?;
+//! assert!(scenarios::verify(&harness, &issued).await?.valid);
+//!
+//! scenarios::revoke(&harness, &issued).await?;
+//! assert!(!scenarios::verify(&harness, &issued).await?.valid);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The issuer's domain is its `127.0.0.1:PORT` address, which key
+//! discovery reaches over plain HTTP, so attestations it issues also verify
+//! through [`hesha_core::verify_attestation`].
+
+#![warn(missing_docs)]
+#![forbid(unsafe_code)]
+
+pub mod harness;
+pub mod scenarios;
+
+pub use harness::Harness;
+pub use scenarios::{Issued, User};
@@ -0,0 +1,169 @@
+//! Protocol flows through the harness nodes, one step per function.
+//!
+//! Each step talks to the nodes over HTTP as a wallet, relying party or
+//! operator would, and fails with the node's answer when a step doesn't
+//! go through, so tests can check refusals as well as successes.
+
+use crate::harness::Harness;
+use chrono::Utc;
+use hesha_core::{parse_attestation, sign_challenge};
+use hesha_crypto::generate_keypair;
+use hesha_types::{
+    Attestation, Challenge, ChallengeVerifyRequest, KeyPair, PhoneNumber, VerificationReport,
+};
+
+/// A wallet holder: a phone number and the key attestations bind to it.
+pub struct User {
+    /// The real phone number.
+    pub phone: PhoneNumber,
+    /// The wallet key.
+    pub keypair: KeyPair,
+}
+
+impl User {
+    /// A user of `phone` with a fresh wallet key.
+    pub fn new(phone: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            phone: PhoneNumber::new(phone)?,
+            keypair: generate_keypair()?,
+        })
+    }
+}
+
+/// An attestation the issuer returned.
+#[derive(Debug, Clone)]
+pub struct Issued {
+    /// The JWT.
+    pub jwt: String,
+    /// Its claims.
+    pub attestation: Attestation,
+    /// The scope it was requested for.
+    pub scope: String,
+}
+
+impl Issued {
+    fn new(jwt: String, scope: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            attestation: parse_attestation(&jwt)?,
+            jwt,
+            scope: scope.to_string(),
+        })
+    }
+}
+
+/// Start the nodes and a user of `phone`.
+pub async fn setup(phone: &str) -> anyhow::Result<(Harness, User)> {
+    Ok((Harness::start().await?, User::new(phone)?))
+}
+
+/// Verify the user's phone number by OTP, returning the verified session.
+pub async fn otp(harness: &Harness, user: &User) -> anyhow::Result<String> {
+    let client = harness.issuer_client()?;
+    let session = client.start_verification(&user.phone).await?;
+    let verified = client.check_verification(&session.session_id, harness.otp_code()).await?;
+    anyhow::ensure!(verified, "Issuer refused the mock verification code");
+    Ok(session.session_id)
+}
+
+/// Verify the user's phone number by OTP and get an attestation for
+/// `scope`.
+pub async fn attest(harness: &Harness, user: &User, scope: &str) -> anyhow::Result<Issued> {
+    let session = otp(harness, user).await?;
+    let response = harness
+        .issuer_client()?
+        .request_session_attestation(&session, &user.keypair.public, scope, None)
+        .await?;
+    Issued::new(response.attestation, scope)
+}
+
+/// Ask the verifier node about an attestation.
+pub async fn verify(harness: &Harness, issued: &Issued) -> anyhow::Result<VerificationReport> {
+    harness.verify(&issued.jwt).await
+}
+
+/// Answer a fresh challenge for `service_context` with the user's key and
+/// ask the verifier node about the response.
+pub async fn prove(
+    harness: &Harness,
+    user: &User,
+    issued: &Issued,
+    service_context: &str,
+) -> anyhow::Result<VerificationReport> {
+    let challenge = Challenge {
+        nonce: hesha_crypto::generate_nonce(),
+        service_context: service_context.to_string(),
+        timestamp: Utc::now(),
+    };
+    let response = sign_challenge(&challenge, &issued.attestation.jti, &user.keypair.private)?;
+    harness
+        .verify_challenge(&ChallengeVerifyRequest {
+            attestation: issued.jwt.clone(),
+            response,
+            service_context: Some(service_context.to_string()),
+        })
+        .await
+}
+
+/// Verify the user's phone number again and renew `previous`, keeping its
+/// scope.
+pub async fn renew(harness: &Harness, user: &User, previous: &Issued) -> anyhow::Result<Issued> {
+    let session = otp(harness, user).await?;
+    let response = harness
+        .issuer_client()?
+        .renew_session_attestation(
+            &session,
+            &user.keypair.public,
+            &previous.scope,
+            None,
+            &previous.attestation.jti,
+        )
+        .await?;
+    Issued::new(response.attestation, &previous.scope)
+}
+
+/// Revoke an attestation at the issuer.
+pub async fn revoke(harness: &Harness, issued: &Issued) -> anyhow::Result<()> {
+    harness.revoke(&issued.attestation.jti, Some("end-to-end test")).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_full_lifecycle() {
+        let (harness, user) = setup("+14155552671").await.unwrap();
+
+        let issued = attest(&harness, &user, "1").await.unwrap();
+        assert_eq!(issued.attestation.iss, harness.issuer_domain());
+        assert_eq!(issued.attestation.user_pubkey, user.keypair.public);
+        let report = verify(&harness, &issued).await.unwrap();
+        assert!(report.valid, "{:?}", report.failure());
+        hesha_core::verify_attestation(&issued.jwt).await.unwrap();
+        assert!(prove(&harness, &user, &issued, "shop.example").await.unwrap().valid);
+
+        let renewed = renew(&harness, &user, &issued).await.unwrap();
+        assert_ne!(renewed.attestation.jti, issued.attestation.jti);
+        assert!(verify(&harness, &renewed).await.unwrap().valid);
+
+        revoke(&harness, &renewed).await.unwrap();
+        let report = verify(&harness, &renewed).await.unwrap();
+        assert!(!report.valid);
+        assert!(report.revocation.unwrap().revoked);
+        let stored = harness.issuer_state().storage.attestations.get(&renewed.attestation.jti).await.unwrap();
+        assert!(stored.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_refusals_surface_as_errors() {
+        let (harness, user) = setup("+14155552671").await.unwrap();
+        let client = harness.issuer_client().unwrap();
+        let session = client.start_verification(&user.phone).await.unwrap();
+        assert!(!client.check_verification(&session.session_id, "000000").await.unwrap_or(false));
+
+        // Sessions are used up by the attestation they yield
+        let session = otp(&harness, &user).await.unwrap();
+        client.request_session_attestation(&session, &user.keypair.public, "1", None).await.unwrap();
+        assert!(client.request_session_attestation(&session, &user.keypair.public, "1", None).await.is_err());
+    }
+}
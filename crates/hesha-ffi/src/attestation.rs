@@ -0,0 +1,154 @@
+//! Attestation parsing, verification and challenge signing.
+
+use crate::error::HeshaError;
+use hesha_core::TrustBundle;
+use hesha_types::{Attestation, Challenge, PrivateKey, PublicKey, VerifiedAttestation};
+use std::time::SystemTime;
+
+/// Claims of an attestation.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct AttestationInfo {
+    /// JWT ID.
+    pub jti: String,
+    /// Issuer domain.
+    pub issuer: String,
+    /// Domain the issuer's keys are published under.
+    pub trust_domain: String,
+    /// Issuer key named in the JWT header.
+    pub key_id: Option<String>,
+    /// Proxy number attested.
+    pub proxy_number: String,
+    /// User public key (base64url) challenge responses are signed with.
+    pub user_pubkey: String,
+    /// When the attestation was issued.
+    pub issued_at: SystemTime,
+    /// When it expires.
+    pub expires_at: SystemTime,
+}
+
+impl AttestationInfo {
+    fn new(jwt: &str, attestation: &Attestation) -> Result<Self, HeshaError> {
+        Ok(Self {
+            jti: attestation.jti.clone(),
+            issuer: attestation.iss.clone(),
+            trust_domain: attestation.effective_trust_domain().to_string(),
+            key_id: hesha_core::attestation::jwt::decode_key_id_unverified(jwt)?,
+            proxy_number: attestation.proxy_number.as_str().to_string(),
+            user_pubkey: attestation.user_pubkey.to_base64(),
+            issued_at: attestation.iat.into(),
+            expires_at: attestation.exp.into(),
+        })
+    }
+
+    fn verified(jwt: &str, verified: hesha_types::HeshaResult<VerifiedAttestation>) -> Result<Self, HeshaError> {
+        let verified = verified.map_err(HeshaError::verification)?;
+        let mut info = Self::new(jwt, &verified.attestation)?;
+        info.issuer = verified.issuer;
+        Ok(info)
+    }
+}
+
+/// Read an attestation's claims without verifying it, e.g. to show the
+/// user what they hold.
+#[uniffi::export]
+pub fn parse_attestation(jwt: String) -> Result<AttestationInfo, HeshaError> {
+    AttestationInfo::new(&jwt, &hesha_core::parse_attestation(&jwt)?)
+}
+
+/// Verify an attestation, fetching the issuer's key from its trust domain.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn verify_attestation(jwt: String) -> Result<AttestationInfo, HeshaError> {
+    AttestationInfo::verified(&jwt, hesha_core::verify_attestation(&jwt).await)
+}
+
+/// Verify an attestation against one issuer public key (base64url).
+#[uniffi::export]
+pub fn verify_attestation_with_key(jwt: String, issuer_key: String) -> Result<AttestationInfo, HeshaError> {
+    let issuer_key = PublicKey::from_base64(&issuer_key)?;
+    AttestationInfo::verified(&jwt, hesha_core::verify_attestation_with_key(&jwt, &issuer_key))
+}
+
+/// Verify an attestation offline against a trust bundle (JSON) of pinned
+/// issuer keys.
+#[uniffi::export]
+pub fn verify_attestation_with_bundle(jwt: String, bundle_json: String) -> Result<AttestationInfo, HeshaError> {
+    let bundle = TrustBundle::from_json(&bundle_json)?;
+    AttestationInfo::verified(&jwt, hesha_core::verify_attestation_with_bundle(&jwt, &bundle))
+}
+
+/// Answer a service's challenge (JSON) with the attestation
+/// `attestation_id`, signing it with the attestation's user private key
+/// (base64url). Returns the challenge response as JSON.
+#[uniffi::export]
+pub fn sign_challenge(
+    challenge_json: String,
+    attestation_id: String,
+    private_key: String,
+) -> Result<String, HeshaError> {
+    let challenge: Challenge = serde_json::from_str(&challenge_json).map_err(HeshaError::invalid)?;
+    let response = hesha_core::sign_challenge(&challenge, &attestation_id, &PrivateKey::from_base64(&private_key)?)?;
+    serde_json::to_string(&response).map_err(HeshaError::invalid)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use hesha_core::AttestationBuilder;
+    use hesha_types::{ChallengeResponse, KeyPair, PhoneNumber, ProxyNumber};
+
+    pub(crate) fn attestation_jwt(issuer: &KeyPair, user_pubkey: &PublicKey, validity_days: i64) -> String {
+        AttestationBuilder::new(
+            "issuer.example.com".to_string(),
+            &issuer.private,
+            PhoneNumber::new("+2348012345678").unwrap(),
+            ProxyNumber::new("+23400123456789").unwrap(),
+            user_pubkey.clone(),
+        )
+        .trust_domain("example.com".to_string())
+        .validity_days(validity_days)
+        .build_jwt()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify() {
+        let issuer = hesha_crypto::generate_keypair().unwrap();
+        let user = hesha_crypto::generate_keypair().unwrap();
+        let jwt = attestation_jwt(&issuer, &user.public, 30);
+
+        let info = verify_attestation_with_key(jwt.clone(), issuer.public.to_base64()).unwrap();
+        assert_eq!(info, parse_attestation(jwt.clone()).unwrap());
+        assert_eq!(info.trust_domain, "example.com");
+        assert_eq!(info.user_pubkey, user.public.to_base64());
+
+        let bundle = format!(
+            r#"{{"issuers": [{{"domain": "example.com", "public_key": "{}"}}]}}"#,
+            issuer.public.to_base64()
+        );
+        assert_eq!(verify_attestation_with_bundle(jwt.clone(), bundle).unwrap(), info);
+
+        assert!(matches!(
+            verify_attestation_with_key(jwt.clone(), user.public.to_base64()),
+            Err(HeshaError::Verification { .. })
+        ));
+        assert!(matches!(
+            verify_attestation_with_key(jwt, "not a key".to_string()),
+            Err(HeshaError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn test_sign_challenge() {
+        let issuer = hesha_crypto::generate_keypair().unwrap();
+        let user = hesha_crypto::generate_keypair().unwrap();
+        let attestation = hesha_core::parse_attestation(&attestation_jwt(&issuer, &user.public, 30)).unwrap();
+        let challenge = format!(
+            r#"{{"nonce": "0123456789abcdef", "service_context": "app.example.com", "timestamp": "{}"}}"#,
+            chrono::Utc::now().to_rfc3339()
+        );
+
+        let response = sign_challenge(challenge, attestation.jti.clone(), user.private.to_base64()).unwrap();
+        let response: ChallengeResponse = serde_json::from_str(&response).unwrap();
+        hesha_core::verify_challenge_response(&response, &attestation, chrono::Duration::minutes(5)).unwrap();
+    }
+}
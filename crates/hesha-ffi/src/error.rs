@@ -0,0 +1,65 @@
+//! Errors thrown to Swift and Kotlin.
+
+use hesha_client::ClientError;
+use hesha_wallet::WalletError;
+use thiserror::Error;
+
+/// Errors raised by the bindings (`HeshaException` in Kotlin).
+#[derive(Debug, Error, uniffi::Error)]
+pub enum HeshaError {
+    /// An argument was malformed: a key, JWT, phone number or JSON document.
+    #[error("{message}")]
+    Invalid {
+        /// What was wrong.
+        message: String,
+    },
+
+    /// The attestation failed verification.
+    #[error("{message}")]
+    Verification {
+        /// Why it was rejected.
+        message: String,
+    },
+
+    /// The issuer couldn't be reached or refused the request.
+    #[error("{message}")]
+    Issuer {
+        /// The issuer's answer or the network failure.
+        message: String,
+    },
+
+    /// A wallet operation failed.
+    #[error("{message}")]
+    Wallet {
+        /// What went wrong.
+        message: String,
+    },
+}
+
+impl HeshaError {
+    pub(crate) fn invalid(error: impl ToString) -> Self {
+        HeshaError::Invalid { message: error.to_string() }
+    }
+
+    pub(crate) fn verification(error: hesha_types::HeshaError) -> Self {
+        HeshaError::Verification { message: error.to_string() }
+    }
+}
+
+impl From<hesha_types::HeshaError> for HeshaError {
+    fn from(error: hesha_types::HeshaError) -> Self {
+        HeshaError::invalid(error)
+    }
+}
+
+impl From<ClientError> for HeshaError {
+    fn from(error: ClientError) -> Self {
+        HeshaError::Issuer { message: error.to_string() }
+    }
+}
+
+impl From<WalletError> for HeshaError {
+    fn from(error: WalletError) -> Self {
+        HeshaError::Wallet { message: error.to_string() }
+    }
+}
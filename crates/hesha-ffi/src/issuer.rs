@@ -0,0 +1,134 @@
+//! Requesting attestations from an issuer.
+
+use crate::error::HeshaError;
+use hesha_types::{PhoneNumber, PublicKey};
+
+/// A phone verification started with an issuer.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct VerificationSession {
+    /// Session to check the code against and request the attestation for.
+    pub session_id: String,
+    /// Seconds until the code expires, if the issuer reports it.
+    pub expires_in_secs: Option<u64>,
+    /// For carrier verification, a URL to open over mobile data instead of
+    /// entering a code.
+    pub verification_url: Option<String>,
+}
+
+/// An attestation the issuer issued.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct IssuedAttestation {
+    /// The attestation JWT.
+    pub attestation: String,
+    /// The proxy number assigned.
+    pub proxy_number: String,
+}
+
+/// Client for an issuer node.
+///
+/// Users prove they own their phone number with a code the issuer sends:
+/// [`start_verification`](Self::start_verification), then
+/// [`check_verification`](Self::check_verification), then
+/// [`request_attestation`](Self::request_attestation) for the session.
+#[derive(uniffi::Object)]
+pub struct IssuerClient {
+    pub(crate) inner: hesha_client::IssuerClient,
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+impl IssuerClient {
+    /// Client for the issuer at `base_url`, which must use HTTPS.
+    #[uniffi::constructor]
+    pub fn new(base_url: String) -> Result<Self, HeshaError> {
+        let inner = hesha_client::IssuerClient::new(&base_url).map_err(HeshaError::invalid)?;
+        Ok(Self { inner })
+    }
+
+    /// Ask the issuer to send a verification code to `phone_number` (E.164).
+    pub async fn start_verification(&self, phone_number: String) -> Result<VerificationSession, HeshaError> {
+        let phone_number = PhoneNumber::new(phone_number)?;
+        let session = self.inner.start_verification(&phone_number).await?;
+        Ok(VerificationSession {
+            session_id: session.session_id,
+            expires_in_secs: session.expires_in,
+            verification_url: session.verification_url,
+        })
+    }
+
+    /// Check the code the user received. Returns whether it was right.
+    pub async fn check_verification(&self, session_id: String, code: String) -> Result<bool, HeshaError> {
+        Ok(self.inner.check_verification(&session_id, &code).await?)
+    }
+
+    /// Request an attestation for a verified session, for the user public
+    /// key `user_pubkey` (base64url) and the calling code `scope`.
+    pub async fn request_attestation(
+        &self,
+        session_id: String,
+        user_pubkey: String,
+        scope: String,
+        validity_days: Option<i64>,
+    ) -> Result<IssuedAttestation, HeshaError> {
+        let user_pubkey = PublicKey::from_base64(&user_pubkey)?;
+        let response = self
+            .inner
+            .request_session_attestation(&session_id, &user_pubkey, &scope, validity_days)
+            .await?;
+        Ok(IssuedAttestation { attestation: response.attestation, proxy_number: response.proxy_number })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_verification_flow() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/verify/start"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "session_id": "session-1",
+                "expires_in": 300,
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/verify/check"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"verified": true})))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/attest/simple"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "attestation": "eyJ...",
+                "proxy_number": "+23400123456789",
+            })))
+            .mount(&server)
+            .await;
+
+        let client = IssuerClient { inner: hesha_client::IssuerClient::new_insecure(&server.uri()).unwrap() };
+        let session = client.start_verification("+2348012345678".to_string()).await.unwrap();
+        assert_eq!(session.session_id, "session-1");
+        assert_eq!(session.expires_in_secs, Some(300));
+        assert!(client.check_verification(session.session_id.clone(), "123456".to_string()).await.unwrap());
+
+        let user = crate::keys::generate_keypair().unwrap();
+        let issued = client
+            .request_attestation(session.session_id, user.public_key, "234".to_string(), None)
+            .await
+            .unwrap();
+        assert_eq!(issued.proxy_number, "+23400123456789");
+
+        assert!(matches!(
+            client.start_verification("not a number".to_string()).await,
+            Err(HeshaError::Invalid { .. })
+        ));
+        assert!(matches!(
+            IssuerClient::new("http://issuer.example.com".to_string()),
+            Err(HeshaError::Invalid { .. })
+        ));
+    }
+}
@@ -0,0 +1,41 @@
+//! Key generation.
+
+use crate::error::HeshaError;
+
+/// An Ed25519 key pair, both halves base64url.
+///
+/// Keep the private key in the platform keystore, or let a [`Wallet`]
+/// hold it.
+///
+/// [`Wallet`]: crate::Wallet
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct KeyPair {
+    /// Public key, sent to issuers when requesting attestations.
+    pub public_key: String,
+    /// Private key.
+    pub private_key: String,
+}
+
+/// Generate a user key pair.
+#[uniffi::export]
+pub fn generate_keypair() -> Result<KeyPair, HeshaError> {
+    let keypair = hesha_crypto::generate_keypair()?;
+    Ok(KeyPair {
+        public_key: keypair.public.to_base64(),
+        private_key: keypair.private.to_base64(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hesha_types::PrivateKey;
+
+    #[test]
+    fn test_generate_keypair() {
+        let keypair = generate_keypair().unwrap();
+        let private_key = PrivateKey::from_base64(&keypair.private_key).unwrap();
+        let derived = hesha_crypto::keypair_from_private(&private_key).unwrap();
+        assert_eq!(derived.public.to_base64(), keypair.public_key);
+    }
+}
@@ -0,0 +1,39 @@
+//! Swift and Kotlin bindings for the Hesha Protocol.
+//!
+//! Exposes key generation, attestation requests and verification, and the
+//! on-device wallet to iOS and Android apps through
+//! [UniFFI](https://mozilla.github.io/uniffi-rs/), so messengers can adopt
+//! the protocol without reimplementing its cryptography.
+//!
+//! Build the library for the device targets and generate the bindings from
+//! it:
+//!
+//! ```bash
+//! cargo build -p hesha-ffi --release --target aarch64-apple-ios
+//! cargo run -p hesha-ffi --features bindgen --bin uniffi-bindgen -- \
+//!     generate --library target/aarch64-apple-ios/release/libhesha_ffi.a \
+//!     --language swift --out-dir bindings/swift
+//! ```
+//!
+//! Timestamps cross the boundary as `Date` (Swift) and `Instant`
+//! (Kotlin). Challenges and challenge responses are passed as the JSON
+//! services send and expect, so apps can forward them unchanged.
+
+#![warn(missing_docs)]
+
+pub mod attestation;
+pub mod error;
+pub mod issuer;
+pub mod keys;
+pub mod wallet;
+
+pub use attestation::{
+    parse_attestation, sign_challenge, verify_attestation, verify_attestation_with_bundle,
+    verify_attestation_with_key, AttestationInfo,
+};
+pub use error::HeshaError;
+pub use issuer::{IssuedAttestation, IssuerClient, VerificationSession};
+pub use keys::{generate_keypair, KeyPair};
+pub use wallet::{AttestationState, HeldAttestation, Identity, Renewal, Wallet};
+
+uniffi::setup_scaffolding!();
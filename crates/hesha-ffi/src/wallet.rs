@@ -0,0 +1,319 @@
+//! The on-device wallet.
+
+use crate::error::HeshaError;
+use crate::issuer::IssuerClient;
+use chrono::Duration;
+use hesha_types::{Challenge, PrivateKey};
+use hesha_wallet::FileStorage;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// A named key pair and the attestations issued to it.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Identity {
+    /// Name chosen by the user.
+    pub name: String,
+    /// When the identity was created or imported.
+    pub created_at: SystemTime,
+    /// Public key (base64url) attestations are issued to.
+    pub public_key: String,
+    /// Attestations held, oldest first.
+    pub attestations: Vec<HeldAttestation>,
+}
+
+impl From<&hesha_wallet::Identity> for Identity {
+    fn from(identity: &hesha_wallet::Identity) -> Self {
+        Self {
+            name: identity.name.clone(),
+            created_at: identity.created_at.into(),
+            public_key: identity.public_key.to_base64(),
+            attestations: identity.attestations.iter().map(HeldAttestation::from).collect(),
+        }
+    }
+}
+
+/// An attestation held by the wallet.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct HeldAttestation {
+    /// JWT ID.
+    pub jti: String,
+    /// Issuer domain.
+    pub issuer: String,
+    /// Domain the issuer's keys are published under.
+    pub trust_domain: String,
+    /// Proxy number attested.
+    pub proxy_number: String,
+    /// Calling code it was requested for, if known.
+    pub scope: Option<String>,
+    /// When it was issued.
+    pub issued_at: SystemTime,
+    /// When it expires.
+    pub expires_at: SystemTime,
+    /// Where it stands, with renewal due within 7 days of expiry.
+    pub state: AttestationState,
+    /// JWT ID of the attestation it renewed.
+    pub renews: Option<String>,
+    /// The attestation JWT, as presented to services.
+    pub jwt: String,
+}
+
+/// How long before expiry [`HeldAttestation::state`] reports an
+/// attestation as expiring soon.
+const EXPIRING_SOON_DAYS: i64 = 7;
+
+impl From<&hesha_wallet::HeldAttestation> for HeldAttestation {
+    fn from(held: &hesha_wallet::HeldAttestation) -> Self {
+        Self {
+            jti: held.jti.clone(),
+            issuer: held.issuer.clone(),
+            trust_domain: held.trust_domain.clone(),
+            proxy_number: held.proxy_number.clone(),
+            scope: held.scope.clone(),
+            issued_at: held.issued_at.into(),
+            expires_at: held.expires_at.into(),
+            state: held.state(chrono::Utc::now(), Duration::days(EXPIRING_SOON_DAYS)).into(),
+            renews: held.renews.clone(),
+            jwt: held.jwt.clone(),
+        }
+    }
+}
+
+/// Where an attestation stands in its lifetime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum AttestationState {
+    /// Valid, and not expiring soon.
+    Valid,
+    /// Valid, but due for renewal.
+    ExpiringSoon,
+    /// Past its expiry.
+    Expired,
+}
+
+impl From<hesha_wallet::AttestationState> for AttestationState {
+    fn from(state: hesha_wallet::AttestationState) -> Self {
+        match state {
+            hesha_wallet::AttestationState::Valid => AttestationState::Valid,
+            hesha_wallet::AttestationState::ExpiringSoon => AttestationState::ExpiringSoon,
+            hesha_wallet::AttestationState::Expired => AttestationState::Expired,
+        }
+    }
+}
+
+/// An attestation due for renewal.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Renewal {
+    /// Identity holding the attestation.
+    pub identity: String,
+    /// JWT ID of the attestation to renew.
+    pub renews: String,
+    /// Issuer to request the new attestation from.
+    pub issuer: String,
+    /// Proxy number attested.
+    pub proxy_number: String,
+    /// Calling code to request, if known.
+    pub scope: Option<String>,
+    /// When the attestation expires (or expired).
+    pub expires_at: SystemTime,
+}
+
+impl From<hesha_wallet::Renewal> for Renewal {
+    fn from(renewal: hesha_wallet::Renewal) -> Self {
+        Self {
+            identity: renewal.identity,
+            renews: renewal.renews,
+            issuer: renewal.issuer,
+            proxy_number: renewal.proxy_number,
+            scope: renewal.scope,
+            expires_at: renewal.expires_at.into(),
+        }
+    }
+}
+
+impl From<Renewal> for hesha_wallet::Renewal {
+    fn from(renewal: Renewal) -> Self {
+        Self {
+            identity: renewal.identity,
+            renews: renewal.renews,
+            issuer: renewal.issuer,
+            proxy_number: renewal.proxy_number,
+            scope: renewal.scope,
+            expires_at: renewal.expires_at.into(),
+        }
+    }
+}
+
+/// The user's identities and attestations, sealed with a passphrase in a
+/// file in the app's private storage.
+///
+/// Every change is saved before the call returns.
+#[derive(uniffi::Object)]
+pub struct Wallet {
+    inner: Mutex<hesha_wallet::Wallet>,
+}
+
+impl Wallet {
+    fn lock(&self) -> MutexGuard<'_, hesha_wallet::Wallet> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+impl Wallet {
+    /// Create an empty wallet at `path`.
+    #[uniffi::constructor]
+    pub fn create(path: String, passphrase: String) -> Result<Self, HeshaError> {
+        let wallet = hesha_wallet::Wallet::create(FileStorage::new(path), &passphrase)?;
+        Ok(Self { inner: Mutex::new(wallet) })
+    }
+
+    /// Open the wallet at `path`.
+    #[uniffi::constructor]
+    pub fn open(path: String, passphrase: String) -> Result<Self, HeshaError> {
+        let wallet = hesha_wallet::Wallet::open(FileStorage::new(path), &passphrase)?;
+        Ok(Self { inner: Mutex::new(wallet) })
+    }
+
+    /// Seal the wallet with a new passphrase.
+    pub fn change_passphrase(&self, passphrase: String) -> Result<(), HeshaError> {
+        Ok(self.lock().change_passphrase(&passphrase)?)
+    }
+
+    /// All identities, oldest first.
+    pub fn identities(&self) -> Vec<Identity> {
+        self.lock().identities().iter().map(Identity::from).collect()
+    }
+
+    /// Create an identity with a fresh key pair.
+    pub fn create_identity(&self, name: String) -> Result<Identity, HeshaError> {
+        Ok(self.lock().create_identity(&name)?.into())
+    }
+
+    /// Add an identity for an existing private key (base64url).
+    pub fn import_identity(&self, name: String, private_key: String) -> Result<Identity, HeshaError> {
+        let private_key = PrivateKey::from_base64(&private_key)?;
+        Ok(self.lock().import_identity(&name, &private_key)?.into())
+    }
+
+    /// Remove an identity with its keys and attestations.
+    pub fn remove_identity(&self, name: String) -> Result<(), HeshaError> {
+        self.lock().remove_identity(&name)?;
+        Ok(())
+    }
+
+    /// Keep an attestation issued to the identity `identity`, with the
+    /// calling code it was requested for.
+    pub fn add_attestation(
+        &self,
+        identity: String,
+        jwt: String,
+        scope: Option<String>,
+    ) -> Result<HeldAttestation, HeshaError> {
+        Ok(self.lock().add_attestation(&identity, &jwt, scope.as_deref())?.into())
+    }
+
+    /// Remove the attestation with the JWT ID `jti`.
+    pub fn remove_attestation(&self, jti: String) -> Result<(), HeshaError> {
+        self.lock().remove_attestation(&jti)?;
+        Ok(())
+    }
+
+    /// Attestations expiring within `days`, soonest first.
+    pub fn expiring_within(&self, days: u32) -> Vec<HeldAttestation> {
+        self.lock()
+            .expiring_within(Duration::days(days.into()))
+            .into_iter()
+            .map(|(_, held)| held.into())
+            .collect()
+    }
+
+    /// Remove expired attestations, returning them.
+    pub fn prune_expired(&self) -> Result<Vec<HeldAttestation>, HeshaError> {
+        Ok(self.lock().prune_expired()?.iter().map(HeldAttestation::from).collect())
+    }
+
+    /// Attestations expiring within `days`, or expired, that haven't been
+    /// renewed yet.
+    pub fn renewals_due(&self, days: u32) -> Vec<Renewal> {
+        self.lock()
+            .renewals_due(Duration::days(days.into()))
+            .into_iter()
+            .map(Renewal::from)
+            .collect()
+    }
+
+    /// Keep the attestation `jwt` issued for `renewal`.
+    pub fn complete_renewal(&self, renewal: Renewal, jwt: String) -> Result<HeldAttestation, HeshaError> {
+        Ok(self.lock().complete_renewal(&renewal.into(), &jwt)?.into())
+    }
+
+    /// Request the renewal from its issuer for a session the user verified
+    /// with it, and keep the new attestation.
+    pub async fn renew(
+        &self,
+        renewal: Renewal,
+        issuer: Arc<IssuerClient>,
+        session_id: String,
+        validity_days: Option<i64>,
+    ) -> Result<HeldAttestation, HeshaError> {
+        let scope = renewal.scope.clone().ok_or_else(|| {
+            HeshaError::from(hesha_wallet::WalletError::UnknownScope(renewal.renews.clone()))
+        })?;
+        let public_key = self.lock().identity(&renewal.identity)?.public_key.clone();
+        let response = issuer
+            .inner
+            .renew_session_attestation(&session_id, &public_key, &scope, validity_days, &renewal.renews)
+            .await?;
+        self.complete_renewal(renewal, response.attestation)
+    }
+
+    /// Answer a service's challenge (JSON) with the attestation `jti`.
+    /// Returns the challenge response as JSON.
+    pub fn answer_challenge(&self, jti: String, challenge_json: String) -> Result<String, HeshaError> {
+        let challenge: Challenge = serde_json::from_str(&challenge_json).map_err(HeshaError::invalid)?;
+        let response = self.lock().answer_challenge(&jti, &challenge)?;
+        serde_json::to_string(&response).map_err(HeshaError::invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::tests::attestation_jwt;
+    use hesha_types::PublicKey;
+
+    #[test]
+    fn test_wallet() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallet.json").to_string_lossy().into_owned();
+        let issuer = hesha_crypto::generate_keypair().unwrap();
+
+        let wallet = Wallet::create(path.clone(), "passphrase".to_string()).unwrap();
+        let identity = wallet.create_identity("personal".to_string()).unwrap();
+        let user_pubkey = PublicKey::from_base64(&identity.public_key).unwrap();
+        let held = wallet
+            .add_attestation("personal".to_string(), attestation_jwt(&issuer, &user_pubkey, 3), Some("234".to_string()))
+            .unwrap();
+        assert_eq!(held.state, AttestationState::ExpiringSoon);
+
+        let wallet = Wallet::open(path.clone(), "passphrase".to_string()).unwrap();
+        assert_eq!(wallet.identities()[0].attestations[0], held);
+        assert!(matches!(
+            Wallet::open(path, "wrong".to_string()),
+            Err(HeshaError::Wallet { .. })
+        ));
+
+        let due = wallet.renewals_due(7);
+        assert_eq!(due.len(), 1);
+        let renewed = wallet.complete_renewal(due[0].clone(), attestation_jwt(&issuer, &user_pubkey, 90)).unwrap();
+        assert_eq!(renewed.renews.as_deref(), Some(held.jti.as_str()));
+        assert_eq!(renewed.state, AttestationState::Valid);
+        assert!(wallet.renewals_due(7).is_empty());
+
+        let challenge = format!(
+            r#"{{"nonce": "0123456789abcdef", "service_context": "app.example.com", "timestamp": "{}"}}"#,
+            chrono::Utc::now().to_rfc3339()
+        );
+        let response = wallet.answer_challenge(renewed.jti.clone(), challenge).unwrap();
+        assert!(response.contains(&renewed.jti));
+    }
+}
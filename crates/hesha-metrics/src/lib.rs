@@ -0,0 +1,221 @@
+//! Metric definitions shared by Hesha crates.
+//!
+//! Counters and histograms are recorded through the [`metrics`] facade, so
+//! an application picks them up by installing whichever exporter it uses
+//! (Prometheus, statsd, OpenTelemetry). Without one, recording costs next
+//! to nothing. Call [`describe`] once after installing it to publish units
+//! and help text.
+//!
+//! | Name | Kind | Labels |
+//! |------|------|--------|
+//! | [`ATTESTATIONS_ISSUED`] | counter | `kind` |
+//! | [`ISSUANCE_DURATION`] | histogram (seconds) | `kind` |
+//! | [`VERIFICATIONS`] | counter | `outcome` |
+//! | [`DISCOVERY_DURATION`] | histogram (seconds) | `result` |
+//! | [`CACHE_LOOKUPS`] | counter | `cache`, `result` |
+//!
+//! Label values are fixed sets, never domains or identifiers, so series
+//! stay bounded.
+
+#![warn(missing_docs)]
+#![forbid(unsafe_code)]
+
+use hesha_types::{HeshaError, HeshaResult};
+use metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+use std::time::Duration;
+
+/// Attestations an issuer signed.
+pub const ATTESTATIONS_ISSUED: &str = "hesha_attestations_issued_total";
+
+/// Time an issuer took to sign and record an attestation.
+pub const ISSUANCE_DURATION: &str = "hesha_issuance_duration_seconds";
+
+/// Attestation verifications, by outcome.
+pub const VERIFICATIONS: &str = "hesha_verifications_total";
+
+/// Time taken fetching issuer discovery documents.
+pub const DISCOVERY_DURATION: &str = "hesha_discovery_duration_seconds";
+
+/// Lookups in client-side caches, by cache and hit or miss.
+pub const CACHE_LOOKUPS: &str = "hesha_cache_lookups_total";
+
+/// `cache` label of the issuer key cache.
+pub const ISSUER_KEY_CACHE: &str = "issuer_keys";
+
+/// `cache` label of the revocation list cache.
+pub const REVOCATION_LIST_CACHE: &str = "revocation_list";
+
+/// `cache` label of relying parties' cache of verification results.
+pub const VERIFICATION_CACHE: &str = "verifications";
+
+/// Publish units and help text for every metric to the installed recorder.
+pub fn describe() {
+    describe_counter!(ATTESTATIONS_ISSUED, Unit::Count, "Attestations signed by the issuer");
+    describe_histogram!(ISSUANCE_DURATION, Unit::Seconds, "Time to sign and record an attestation");
+    describe_counter!(VERIFICATIONS, Unit::Count, "Attestation verifications by outcome");
+    describe_histogram!(DISCOVERY_DURATION, Unit::Seconds, "Time to fetch an issuer discovery document");
+    describe_counter!(CACHE_LOOKUPS, Unit::Count, "Cache lookups by cache and result");
+}
+
+/// How an attestation was issued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssuanceKind {
+    /// A pre-verified number on `/attest`.
+    Direct,
+    /// A number verified by the issuer on `/attest/simple`.
+    Session,
+    /// A renewal of an earlier attestation.
+    Renewal,
+}
+
+impl IssuanceKind {
+    /// Label value.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Direct => "direct",
+            Self::Session => "session",
+            Self::Renewal => "renewal",
+        }
+    }
+}
+
+/// Outcome of verifying an attestation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationOutcome {
+    /// Every check passed.
+    Valid,
+    /// The attestation had expired.
+    Expired,
+    /// A signature didn't match.
+    BadSignature,
+    /// The attestation was malformed or failed a policy check.
+    Invalid,
+    /// The issuer couldn't be reached or answered badly.
+    Unavailable,
+}
+
+impl VerificationOutcome {
+    /// The outcome a verification result stands for.
+    pub fn of<T>(result: &HeshaResult<T>) -> Self {
+        match result {
+            Ok(_) => Self::Valid,
+            Err(HeshaError::AttestationExpired(_)) => Self::Expired,
+            Err(HeshaError::InvalidSignature | HeshaError::InvalidBindingProof) => Self::BadSignature,
+            Err(HeshaError::NetworkError(_)) => Self::Unavailable,
+            Err(_) => Self::Invalid,
+        }
+    }
+
+    /// Label value.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Valid => "valid",
+            Self::Expired => "expired",
+            Self::BadSignature => "bad_signature",
+            Self::Invalid => "invalid",
+            Self::Unavailable => "unavailable",
+        }
+    }
+}
+
+/// Result of fetching a discovery document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscoveryResult {
+    /// A new document was fetched.
+    Fetched,
+    /// The issuer confirmed the cached document is current.
+    NotModified,
+    /// The fetch failed.
+    Failed,
+}
+
+impl DiscoveryResult {
+    /// Label value.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Fetched => "fetched",
+            Self::NotModified => "not_modified",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// Record an attestation issued in `elapsed`.
+pub fn record_issuance(kind: IssuanceKind, elapsed: Duration) {
+    counter!(ATTESTATIONS_ISSUED, "kind" => kind.as_str()).increment(1);
+    histogram!(ISSUANCE_DURATION, "kind" => kind.as_str()).record(elapsed.as_secs_f64());
+}
+
+/// Record the outcome of a verification and pass its result through.
+pub fn record_verification<T>(result: HeshaResult<T>) -> HeshaResult<T> {
+    counter!(VERIFICATIONS, "outcome" => VerificationOutcome::of(&result).as_str()).increment(1);
+    result
+}
+
+/// Record a discovery document fetch that took `elapsed`.
+pub fn record_discovery(result: DiscoveryResult, elapsed: Duration) {
+    histogram!(DISCOVERY_DURATION, "result" => result.as_str()).record(elapsed.as_secs_f64());
+}
+
+/// Record a lookup in `cache`.
+pub fn record_cache_lookup(cache: &'static str, hit: bool) {
+    counter!(CACHE_LOOKUPS, "cache" => cache, "result" => if hit { "hit" } else { "miss" }).increment(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use metrics_util::MetricKind;
+
+    #[test]
+    fn test_recorded_series() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, || {
+            describe();
+            record_issuance(IssuanceKind::Session, Duration::from_millis(20));
+            assert!(record_verification::<()>(Ok(())).is_ok());
+            assert!(record_verification::<()>(Err(HeshaError::InvalidSignature)).is_err());
+            assert!(record_verification::<()>(Err(HeshaError::InvalidSignature)).is_err());
+            record_discovery(DiscoveryResult::Fetched, Duration::from_millis(5));
+            record_cache_lookup(ISSUER_KEY_CACHE, true);
+        });
+
+        let series = snapshotter.snapshot().into_vec();
+        let find = |name: &str, label: (&str, &str)| {
+            series
+                .iter()
+                .find(|(key, _, _, _)| {
+                    key.key().name() == name
+                        && key.key().labels().any(|l| l.key() == label.0 && l.value() == label.1)
+                })
+                .map(|(key, unit, _, value)| (key.kind(), *unit, value))
+        };
+        assert_eq!(
+            find(ATTESTATIONS_ISSUED, ("kind", "session")),
+            Some((MetricKind::Counter, Some(Unit::Count), &DebugValue::Counter(1)))
+        );
+        assert_eq!(
+            find(VERIFICATIONS, ("outcome", "bad_signature")).map(|(_, _, value)| value),
+            Some(&DebugValue::Counter(2))
+        );
+        assert!(find(VERIFICATIONS, ("outcome", "valid")).is_some());
+        assert!(matches!(
+            find(DISCOVERY_DURATION, ("result", "fetched")),
+            Some((MetricKind::Histogram, Some(Unit::Seconds), DebugValue::Histogram(values))) if values.len() == 1
+        ));
+        assert!(find(CACHE_LOOKUPS, ("cache", ISSUER_KEY_CACHE)).is_some());
+    }
+
+    #[test]
+    fn test_outcomes() {
+        use chrono::Utc;
+        let expired: HeshaResult<()> = Err(HeshaError::AttestationExpired(Utc::now()));
+        assert_eq!(VerificationOutcome::of(&expired), VerificationOutcome::Expired);
+        let offline: HeshaResult<()> = Err(HeshaError::NetworkError("down".to_string()));
+        assert_eq!(VerificationOutcome::of(&offline), VerificationOutcome::Unavailable);
+        let garbage: HeshaResult<()> = Err(HeshaError::InvalidAttestation("bad".to_string()));
+        assert_eq!(VerificationOutcome::of(&garbage), VerificationOutcome::Invalid);
+    }
+}
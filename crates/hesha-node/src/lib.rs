@@ -0,0 +1,16 @@
+//! Pieces shared by the Hesha node binaries.
+//!
+//! The verifier node, transparency log and status responder refuse
+//! requests with the same [`ApiError`] problem documents and drain
+//! in-flight requests on the same [`shutdown_signal`]. The issuer node has
+//! richer versions of both (request IDs, retry headers, admin-requested
+//! drains) and keeps its own.
+
+#![warn(missing_docs)]
+#![forbid(unsafe_code)]
+
+pub mod problem;
+pub mod shutdown;
+
+pub use problem::{not_found, ApiError};
+pub use shutdown::shutdown_signal;
@@ -0,0 +1,54 @@
+//! Error responses as RFC 7807 problem documents.
+//!
+//! Requests that can't be answered are refused with an [`ApiError`],
+//! served as `application/problem+json` like the issuer node's refusals.
+
+use axum::{
+    extract::rejection::JsonRejection,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use hesha_types::{ErrorCode, Problem, PROBLEM_CONTENT_TYPE};
+
+/// A refused request.
+#[derive(Debug)]
+pub struct ApiError {
+    problem: Box<Problem>,
+}
+
+impl ApiError {
+    /// A refusal with `code`, explained by `detail`.
+    pub fn new(code: ErrorCode, detail: impl Into<String>) -> Self {
+        Self {
+            problem: Box::new(Problem::new(code, detail)),
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        let code = match rejection.status() {
+            StatusCode::PAYLOAD_TOO_LARGE => ErrorCode::PayloadTooLarge,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => ErrorCode::UnsupportedMediaType,
+            _ => ErrorCode::InvalidRequest,
+        };
+        Self::new(code, rejection.body_text())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.problem.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (
+            status,
+            [(header::CONTENT_TYPE, PROBLEM_CONTENT_TYPE)],
+            serde_json::to_vec(&self.problem).unwrap_or_default(),
+        )
+            .into_response()
+    }
+}
+
+/// Fallback for requests no route matches.
+pub async fn not_found() -> ApiError {
+    ApiError::new(ErrorCode::NotFound, "No such endpoint")
+}
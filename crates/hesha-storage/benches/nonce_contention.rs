@@ -0,0 +1,93 @@
+//! Nonce checks from many tasks at once, as under concurrent issuance.
+//!
+//! Run with `cargo bench -p hesha-storage --bench nonce_contention`.
+//! The memory backend is compared with a single `Mutex<HashMap>`, the
+//! layout it used before nonces were sharded.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hesha_storage::{MemoryStore, NonceStore, StorageResult};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Nonces each task checks per iteration.
+const PER_TASK: u64 = 1000;
+
+/// The pre-sharding layout, for comparison.
+#[derive(Default)]
+struct SingleLock(Mutex<HashMap<String, DateTime<Utc>>>);
+
+#[async_trait]
+impl NonceStore for SingleLock {
+    async fn check_and_insert(&self, nonce: &str, expires_at: DateTime<Utc>) -> StorageResult<bool> {
+        let mut nonces = self.0.lock().unwrap();
+        match nonces.get(nonce) {
+            Some(existing) if *existing > Utc::now() => Ok(false),
+            _ => {
+                nonces.insert(nonce.to_string(), expires_at);
+                Ok(true)
+            }
+        }
+    }
+
+    async fn purge_expired(&self, now: DateTime<Utc>) -> StorageResult<u64> {
+        let mut nonces = self.0.lock().unwrap();
+        let before = nonces.len();
+        nonces.retain(|_, expires_at| *expires_at > now);
+        Ok((before - nonces.len()) as u64)
+    }
+}
+
+fn nonce_contention(c: &mut Criterion) {
+    let expires_at = Utc::now() + Duration::minutes(5);
+    let counter = Arc::new(AtomicU64::new(0));
+
+    let mut group = c.benchmark_group("nonce_contention");
+    group.sample_size(20);
+    for threads in [1, 2, 4, 8, 16] {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(threads)
+            .build()
+            .unwrap();
+        group.throughput(Throughput::Elements(threads as u64 * PER_TASK));
+
+        let stores: [(&str, Arc<dyn NonceStore>); 2] = [
+            ("single_lock", Arc::new(SingleLock::default())),
+            ("memory_store", Arc::new(MemoryStore::new())),
+        ];
+        for (name, store) in stores {
+            group.bench_with_input(BenchmarkId::new(name, threads), &threads, |b, &threads| {
+                b.iter_custom(|iters| {
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        runtime.block_on(async {
+                            let tasks: Vec<_> = (0..threads)
+                                .map(|_| {
+                                    let store = store.clone();
+                                    let counter = counter.clone();
+                                    tokio::spawn(async move {
+                                        for _ in 0..PER_TASK {
+                                            let nonce = format!("nonce-{}", counter.fetch_add(1, Ordering::Relaxed));
+                                            assert!(store.check_and_insert(&nonce, expires_at).await.unwrap());
+                                        }
+                                    })
+                                })
+                                .collect();
+                            for task in tasks {
+                                task.await.unwrap();
+                            }
+                        });
+                    }
+                    start.elapsed()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, nonce_contention);
+criterion_main!(benches);
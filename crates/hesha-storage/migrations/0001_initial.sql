@@ -0,0 +1,19 @@
+-- Issued attestations, used nonces and revocations.
+-- Timestamps are microseconds since the Unix epoch.
+CREATE TABLE IF NOT EXISTS attestations (
+    jti TEXT PRIMARY KEY,
+    proxy_number TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    issued_at BIGINT NOT NULL,
+    expires_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS attestations_proxy_number ON attestations (proxy_number);
+CREATE TABLE IF NOT EXISTS nonces (
+    nonce TEXT PRIMARY KEY,
+    expires_at BIGINT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS revocations (
+    jti TEXT PRIMARY KEY,
+    revoked_at BIGINT NOT NULL,
+    reason TEXT
+);
@@ -0,0 +1,5 @@
+-- Signed audit log entries, kept as the JSON they were hashed from.
+CREATE TABLE IF NOT EXISTS audit_log (
+    seq BIGINT PRIMARY KEY,
+    entry TEXT NOT NULL
+);
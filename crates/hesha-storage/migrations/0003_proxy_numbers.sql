@@ -0,0 +1,6 @@
+-- Which phone hash holds each proxy number; the primary key rejects collisions.
+CREATE TABLE IF NOT EXISTS proxy_numbers (
+    proxy_number TEXT PRIMARY KEY,
+    phone_hash TEXT NOT NULL,
+    assigned_at BIGINT NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Issuance caps look up every attestation held by a phone.
+CREATE INDEX IF NOT EXISTS proxy_numbers_phone_hash ON proxy_numbers (phone_hash);
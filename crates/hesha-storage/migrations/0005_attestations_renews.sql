@@ -0,0 +1,2 @@
+-- Renewals name the attestation they replace, for issuance statistics.
+ALTER TABLE attestations ADD COLUMN renews TEXT;
@@ -0,0 +1,9 @@
+-- Short-lived state replicas share: verification sessions, idempotency
+-- records, rate-limit buckets and leases.
+CREATE TABLE IF NOT EXISTS node_state (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    expires_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS node_state_expires_at ON node_state (expires_at);
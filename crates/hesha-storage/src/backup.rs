@@ -0,0 +1,62 @@
+//! Export and import of a node's records, for backups.
+//!
+//! A [`RecordSnapshot`] holds the attestations, proxy number assignments
+//! and revocations a node needs to carry on after losing its host. Nonces
+//! are short-lived and left out; the audit log is exported separately
+//! because its hash chain can't be merged into another node's log.
+
+use crate::error::{StorageError, StorageResult};
+use crate::store::{ProxyAssignment, StoredAttestation};
+use crate::Storage;
+use hesha_types::{RestoreSummary, RevocationEntry};
+use serde::{Deserialize, Serialize};
+
+/// The records of a node at one point in time.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordSnapshot {
+    /// Issued attestations, oldest first.
+    pub attestations: Vec<StoredAttestation>,
+    /// Proxy numbers claimed for phones.
+    pub proxy_numbers: Vec<ProxyAssignment>,
+    /// Revoked attestations, oldest first.
+    pub revocations: Vec<RevocationEntry>,
+}
+
+impl Storage {
+    /// Export every attestation, proxy number assignment and revocation.
+    pub async fn snapshot(&self) -> StorageResult<RecordSnapshot> {
+        Ok(RecordSnapshot {
+            attestations: self.attestations.all().await?,
+            proxy_numbers: self.attestations.proxy_assignments().await?,
+            revocations: self.revocations.list().await?,
+        })
+    }
+    
+    /// Add the records of a snapshot.
+    /// 
+    /// Records already held are kept unchanged, so restoring the same
+    /// snapshot twice, or into a node that has issued since, is safe.
+    pub async fn restore(&self, snapshot: &RecordSnapshot) -> StorageResult<RestoreSummary> {
+        let mut summary = RestoreSummary::default();
+        for attestation in &snapshot.attestations {
+            match self.attestations.insert(attestation).await {
+                Ok(()) => summary.attestations += 1,
+                Err(StorageError::Conflict(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        for assignment in &snapshot.proxy_numbers {
+            if self.attestations.assign_proxy_number(&assignment.proxy_number, &assignment.phone_hash).await? {
+                summary.proxy_numbers += 1;
+            } else {
+                summary.proxy_conflicts += 1;
+            }
+        }
+        for entry in &snapshot.revocations {
+            if self.revocations.revoke(entry).await? {
+                summary.revocations += 1;
+            }
+        }
+        Ok(summary)
+    }
+}
@@ -0,0 +1,41 @@
+//! Storage error types.
+
+use thiserror::Error;
+
+/// Errors that can occur in a storage backend.
+#[derive(Debug, Error)]
+pub enum StorageError {
+    /// The storage URL is malformed or names an unknown backend.
+    #[error("Invalid storage URL: {0}")]
+    InvalidUrl(String),
+    
+    /// The backend was not compiled in.
+    #[error("Storage backend '{0}' is not enabled in this build")]
+    BackendDisabled(String),
+    
+    /// A record with the same key already exists.
+    #[error("Already exists: {0}")]
+    Conflict(String),
+    
+    /// A stored value could not be decoded.
+    #[error("Corrupt stored value: {0}")]
+    Corrupt(String),
+    
+    /// The schema could not be brought up to date.
+    #[error("Schema migration failed: {0}")]
+    Migration(String),
+    
+    /// Database operation failed.
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+impl From<sqlx::Error> for StorageError {
+    fn from(e: sqlx::Error) -> Self {
+        StorageError::Database(e.to_string())
+    }
+}
+
+/// Result type for storage operations.
+pub type StorageResult<T> = Result<T, StorageError>;
@@ -0,0 +1,262 @@
+//! In-memory backend, for development and single-process deployments.
+//!
+//! Everything is lost when the process exits.
+
+use crate::error::{StorageError, StorageResult};
+use crate::store::{
+    AttestationStore, AuditStore, IssuanceCount, NonceStore, ProxyAssignment, RevocationStore,
+    StateStore, StoredAttestation,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use hesha_types::{AuditEntry, RevocationEntry};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+
+/// Backend keeping all records in process memory.
+#[derive(Default)]
+pub struct MemoryStore {
+    attestations: Mutex<Vec<StoredAttestation>>,
+    proxy_numbers: Mutex<HashMap<String, String>>,
+    // Sharded so concurrent issuance doesn't queue on one lock
+    nonces: DashMap<String, DateTime<Utc>>,
+    revocations: Mutex<Vec<RevocationEntry>>,
+    audit: Mutex<Vec<AuditEntry>>,
+    state: Mutex<HashMap<String, (String, DateTime<Utc>)>>,
+}
+
+impl MemoryStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Lock a mutex, recovering the data if another thread panicked holding it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[async_trait]
+impl AttestationStore for MemoryStore {
+    async fn insert(&self, attestation: &StoredAttestation) -> StorageResult<()> {
+        let mut attestations = lock(&self.attestations);
+        if attestations.iter().any(|stored| stored.jti == attestation.jti) {
+            return Err(StorageError::Conflict(format!("attestation {}", attestation.jti)));
+        }
+        attestations.push(attestation.clone());
+        Ok(())
+    }
+    
+    async fn get(&self, jti: &str) -> StorageResult<Option<StoredAttestation>> {
+        Ok(lock(&self.attestations).iter().find(|stored| stored.jti == jti).cloned())
+    }
+    
+    async fn find_by_proxy_number(&self, proxy_number: &str) -> StorageResult<Vec<StoredAttestation>> {
+        let mut found: Vec<_> = lock(&self.attestations)
+            .iter()
+            .filter(|stored| stored.proxy_number == proxy_number)
+            .cloned()
+            .collect();
+        found.sort_by_key(|a| Reverse(a.issued_at));
+        Ok(found)
+    }
+    
+    async fn recent(&self, limit: usize) -> StorageResult<Vec<StoredAttestation>> {
+        let mut recent = lock(&self.attestations).clone();
+        recent.sort_by_key(|a| Reverse(a.issued_at));
+        recent.truncate(limit);
+        Ok(recent)
+    }
+    
+    async fn assign_proxy_number(&self, proxy_number: &str, phone_hash: &str) -> StorageResult<bool> {
+        let mut proxy_numbers = lock(&self.proxy_numbers);
+        let owner = proxy_numbers
+            .entry(proxy_number.to_string())
+            .or_insert_with(|| phone_hash.to_string());
+        Ok(owner == phone_hash)
+    }
+    
+    async fn find_by_phone_hash(&self, phone_hash: &str) -> StorageResult<Vec<StoredAttestation>> {
+        let proxy_numbers = lock(&self.proxy_numbers);
+        let mut found: Vec<_> = lock(&self.attestations)
+            .iter()
+            .filter(|stored| proxy_numbers.get(&stored.proxy_number).is_some_and(|owner| owner == phone_hash))
+            .cloned()
+            .collect();
+        found.sort_by_key(|a| Reverse(a.issued_at));
+        Ok(found)
+    }
+    
+    async fn all(&self) -> StorageResult<Vec<StoredAttestation>> {
+        let mut all = lock(&self.attestations).clone();
+        all.sort_by_key(|a| a.issued_at);
+        Ok(all)
+    }
+    
+    async fn proxy_assignments(&self) -> StorageResult<Vec<ProxyAssignment>> {
+        let mut assignments: Vec<_> = lock(&self.proxy_numbers)
+            .iter()
+            .map(|(proxy_number, phone_hash)| ProxyAssignment {
+                proxy_number: proxy_number.clone(),
+                phone_hash: phone_hash.clone(),
+            })
+            .collect();
+        assignments.sort_by(|a, b| a.proxy_number.cmp(&b.proxy_number));
+        Ok(assignments)
+    }
+    
+    async fn count_expiring(&self, after: DateTime<Utc>, until: Option<DateTime<Utc>>) -> StorageResult<u64> {
+        let count = lock(&self.attestations)
+            .iter()
+            .filter(|a| a.expires_at > after && until.map_or(true, |until| a.expires_at <= until))
+            .count();
+        Ok(count as u64)
+    }
+    
+    async fn issuance_counts(&self, since: DateTime<Utc>) -> StorageResult<Vec<IssuanceCount>> {
+        let mut counts: BTreeMap<(NaiveDate, String), IssuanceCount> = BTreeMap::new();
+        for attestation in lock(&self.attestations).iter().filter(|a| a.issued_at >= since) {
+            let day = attestation.issued_at.date_naive();
+            let count = counts
+                .entry((day, attestation.scope.clone()))
+                .or_insert_with(|| IssuanceCount {
+                    day,
+                    scope: attestation.scope.clone(),
+                    issued: 0,
+                    renewals: 0,
+                });
+            count.issued += 1;
+            count.renewals += u64::from(attestation.renews.is_some());
+        }
+        Ok(counts.into_values().collect())
+    }
+}
+
+#[async_trait]
+impl NonceStore for MemoryStore {
+    async fn check_and_insert(&self, nonce: &str, expires_at: DateTime<Utc>) -> StorageResult<bool> {
+        // The entry holds its shard's lock, keeping the check atomic
+        match self.nonces.entry(nonce.to_string()) {
+            Entry::Occupied(existing) if *existing.get() > Utc::now() => Ok(false),
+            Entry::Occupied(mut existing) => {
+                existing.insert(expires_at);
+                Ok(true)
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(expires_at);
+                Ok(true)
+            }
+        }
+    }
+    
+    async fn purge_expired(&self, now: DateTime<Utc>) -> StorageResult<u64> {
+        let mut purged = 0;
+        self.nonces.retain(|_, expires_at| {
+            let keep = *expires_at > now;
+            purged += u64::from(!keep);
+            keep
+        });
+        Ok(purged)
+    }
+}
+
+#[async_trait]
+impl RevocationStore for MemoryStore {
+    async fn revoke(&self, entry: &RevocationEntry) -> StorageResult<bool> {
+        let mut revocations = lock(&self.revocations);
+        if revocations.iter().any(|revoked| revoked.jti == entry.jti) {
+            return Ok(false);
+        }
+        revocations.push(entry.clone());
+        Ok(true)
+    }
+    
+    async fn get(&self, jti: &str) -> StorageResult<Option<RevocationEntry>> {
+        Ok(lock(&self.revocations).iter().find(|revoked| revoked.jti == jti).cloned())
+    }
+    
+    async fn list(&self) -> StorageResult<Vec<RevocationEntry>> {
+        let mut list = lock(&self.revocations).clone();
+        list.sort_by_key(|a| a.revoked_at);
+        Ok(list)
+    }
+}
+
+#[async_trait]
+impl AuditStore for MemoryStore {
+    async fn append(&self, entry: &AuditEntry) -> StorageResult<()> {
+        let mut audit = lock(&self.audit);
+        if audit.iter().any(|existing| existing.seq == entry.seq) {
+            return Err(StorageError::Conflict(format!("audit entry {}", entry.seq)));
+        }
+        audit.push(entry.clone());
+        Ok(())
+    }
+    
+    async fn last(&self) -> StorageResult<Option<AuditEntry>> {
+        Ok(lock(&self.audit).iter().max_by_key(|entry| entry.seq).cloned())
+    }
+    
+    async fn list(&self, after_seq: u64, limit: usize) -> StorageResult<Vec<AuditEntry>> {
+        let mut entries: Vec<_> = lock(&self.audit)
+            .iter()
+            .filter(|entry| entry.seq > after_seq)
+            .cloned()
+            .collect();
+        entries.sort_by_key(|entry| entry.seq);
+        entries.truncate(limit);
+        Ok(entries)
+    }
+}
+
+#[async_trait]
+impl StateStore for MemoryStore {
+    async fn get(&self, key: &str) -> StorageResult<Option<String>> {
+        let now = Utc::now();
+        Ok(lock(&self.state)
+            .get(key)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(value, _)| value.clone()))
+    }
+    
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        current: Option<&str>,
+        value: &str,
+        expires_at: DateTime<Utc>,
+    ) -> StorageResult<bool> {
+        let mut state = lock(&self.state);
+        let now = Utc::now();
+        let held = state
+            .get(key)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(value, _)| value.as_str());
+        if held != current {
+            return Ok(false);
+        }
+        state.insert(key.to_string(), (value.to_string(), expires_at));
+        Ok(true)
+    }
+    
+    async fn compare_and_remove(&self, key: &str, current: &str) -> StorageResult<bool> {
+        let mut state = lock(&self.state);
+        let now = Utc::now();
+        if !state.get(key).is_some_and(|(value, expires_at)| value == current && *expires_at > now) {
+            return Ok(false);
+        }
+        state.remove(key);
+        Ok(true)
+    }
+    
+    async fn purge_expired(&self, now: DateTime<Utc>) -> StorageResult<u64> {
+        let mut state = lock(&self.state);
+        let before = state.len();
+        state.retain(|_, (_, expires_at)| *expires_at > now);
+        Ok((before - state.len()) as u64)
+    }
+}
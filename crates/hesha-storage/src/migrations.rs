@@ -0,0 +1,245 @@
+//! Versioned schema migrations for the SQL backend.
+//!
+//! Migrations are the SQL files in `migrations/`, embedded in the binary and
+//! applied in order when a store connects. Each runs in its own transaction
+//! together with its row in `schema_migrations`, so a failed upgrade leaves
+//! the database at the last good version. Nodes sharing a database take
+//! turns: PostgreSQL holds an advisory lock for the whole run, SQLite takes
+//! the write lock (`BEGIN IMMEDIATE`) per migration and re-checks whether
+//! another process got there first.
+//!
+//! A database migrated by a newer release is refused rather than used with
+//! a schema this build doesn't know.
+//!
+//! Migration files are split into statements at `;` once `--` comment
+//! lines are dropped, so they must not contain semicolons inside string
+//! literals.
+
+use crate::error::{StorageError, StorageResult};
+use chrono::Utc;
+use sqlx::pool::PoolConnection;
+use sqlx::{Any, AnyPool, Row};
+
+/// A schema change.
+struct Migration {
+    version: i64,
+    name: &'static str,
+    sql: &'static str,
+}
+
+/// Every migration, oldest first. Never edit one that has been released;
+/// add a new file instead.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_str!("../migrations/0001_initial.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "audit_log",
+        sql: include_str!("../migrations/0002_audit_log.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "proxy_numbers",
+        sql: include_str!("../migrations/0003_proxy_numbers.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "proxy_numbers_phone_hash",
+        sql: include_str!("../migrations/0004_proxy_numbers_phone_hash.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "attestations_renews",
+        sql: include_str!("../migrations/0005_attestations_renews.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "node_state",
+        sql: include_str!("../migrations/0006_node_state.sql"),
+    },
+];
+
+/// Schema version this build migrates databases to.
+pub const SCHEMA_VERSION: i64 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// PostgreSQL advisory lock key held while migrating ("hesha" in ASCII).
+const POSTGRES_LOCK_KEY: i64 = 0x68_65_73_68_61;
+
+const MIGRATIONS_TABLE: &str = "CREATE TABLE IF NOT EXISTS schema_migrations (
+    version BIGINT PRIMARY KEY,
+    name TEXT NOT NULL,
+    applied_at BIGINT NOT NULL
+)";
+
+/// Bring the database up to [`SCHEMA_VERSION`].
+///
+/// Databases created before migrations existed already hold the tables of
+/// the early migrations; those use `IF NOT EXISTS` and are simply recorded.
+pub(crate) async fn run(pool: &AnyPool) -> StorageResult<()> {
+    let mut conn = pool.acquire().await?;
+    let postgres = conn.backend_name().eq_ignore_ascii_case("postgresql");
+
+    if postgres {
+        sqlx::query("SELECT pg_advisory_lock($1)")
+            .bind(POSTGRES_LOCK_KEY)
+            .execute(&mut *conn)
+            .await?;
+    }
+    let result = apply_pending(&mut conn, postgres).await;
+    if postgres {
+        let unlocked = sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(POSTGRES_LOCK_KEY)
+            .execute(&mut *conn)
+            .await;
+        if result.is_ok() {
+            unlocked?;
+        }
+    }
+    result
+}
+
+async fn apply_pending(conn: &mut PoolConnection<Any>, postgres: bool) -> StorageResult<()> {
+    sqlx::query(MIGRATIONS_TABLE).execute(&mut **conn).await?;
+
+    let current = current_version(conn).await?;
+    if current > SCHEMA_VERSION {
+        return Err(StorageError::Migration(format!(
+            "database schema is at version {}, newer than this build supports ({}); upgrade the node",
+            current, SCHEMA_VERSION
+        )));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > current) {
+        let begin = if postgres { "BEGIN" } else { "BEGIN IMMEDIATE" };
+        sqlx::query(begin).execute(&mut **conn).await?;
+        match apply(conn, migration).await {
+            Ok(()) => {
+                sqlx::query("COMMIT").execute(&mut **conn).await?;
+            }
+            Err(e) => {
+                let _ = sqlx::query("ROLLBACK").execute(&mut **conn).await;
+                return Err(StorageError::Migration(format!(
+                    "{:04}_{}: {}",
+                    migration.version, migration.name, e
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn apply(conn: &mut PoolConnection<Any>, migration: &Migration) -> StorageResult<()> {
+    // Another process may have applied it while we waited for the lock
+    let applied = sqlx::query("SELECT version FROM schema_migrations WHERE version = $1")
+        .bind(migration.version)
+        .fetch_optional(&mut **conn)
+        .await?;
+    if applied.is_some() {
+        return Ok(());
+    }
+
+    for statement in statements(migration.sql) {
+        sqlx::query(&statement).execute(&mut **conn).await?;
+    }
+    sqlx::query("INSERT INTO schema_migrations (version, name, applied_at) VALUES ($1, $2, $3)")
+        .bind(migration.version)
+        .bind(migration.name)
+        .bind(Utc::now().timestamp_micros())
+        .execute(&mut **conn)
+        .await?;
+    Ok(())
+}
+
+/// Highest applied migration, 0 for a new database.
+async fn current_version(conn: &mut PoolConnection<Any>) -> StorageResult<i64> {
+    let row = sqlx::query("SELECT MAX(version) AS version FROM schema_migrations")
+        .fetch_one(&mut **conn)
+        .await?;
+    Ok(row.try_get::<Option<i64>, _>("version")?.unwrap_or(0))
+}
+
+/// The statements of a migration file, without `--` comment lines.
+fn statements(sql: &str) -> Vec<String> {
+    let code: Vec<&str> = sql
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("--"))
+        .collect();
+    code.join("\n")
+        .split(';')
+        .map(str::trim)
+        .filter(|statement| !statement.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, index as i64 + 1);
+            assert!(!statements(migration.sql).is_empty());
+        }
+        let statements = statements("-- a comment; with a semicolon\nCREATE TABLE a (x INT);\n-- trailing\n");
+        assert_eq!(statements, ["CREATE TABLE a (x INT)"]);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_sqlite_migrations() {
+        use sqlx::any::AnyPoolOptions;
+
+        sqlx::any::install_default_drivers();
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite:{}?mode=rwc", dir.path().join("hesha.db").display());
+        let pool = AnyPoolOptions::new().connect(&url).await.unwrap();
+
+        // A database from before migrations keeps its records
+        sqlx::query("CREATE TABLE attestations (
+            jti TEXT PRIMARY KEY, proxy_number TEXT NOT NULL, scope TEXT NOT NULL,
+            issued_at BIGINT NOT NULL, expires_at BIGINT NOT NULL
+        )")
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO attestations VALUES ('a', '+100111', '1', 0, 0)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Concurrent runs apply each migration once
+        let (first, second) = tokio::join!(run(&pool), run(&pool));
+        first.unwrap();
+        second.unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+        assert_eq!(current_version(&mut conn).await.unwrap(), SCHEMA_VERSION);
+        let applied: i64 = sqlx::query("SELECT COUNT(*) AS applied FROM schema_migrations")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap()
+            .try_get("applied")
+            .unwrap();
+        assert_eq!(applied, SCHEMA_VERSION);
+        let kept: i64 = sqlx::query("SELECT COUNT(*) AS kept FROM attestations")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap()
+            .try_get("kept")
+            .unwrap();
+        assert_eq!(kept, 1);
+
+        // A database from a newer release is refused
+        sqlx::query("INSERT INTO schema_migrations (version, name, applied_at) VALUES ($1, 'future', 0)")
+            .bind(SCHEMA_VERSION + 1)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        drop(conn);
+        assert!(matches!(run(&pool).await, Err(StorageError::Migration(_))));
+    }
+}
@@ -0,0 +1,123 @@
+//! Redis backend for state shared between replicas.
+//!
+//! Only [`StateStore`] is kept in Redis; records stay in the SQL backend.
+//! Keys are prefixed with `hesha:` so the instance can be shared, and
+//! Redis expires values itself (`PX`), so there is nothing to purge.
+//! Compare-and-swap runs as Lua scripts, which Redis executes atomically.
+
+use crate::error::{StorageError, StorageResult};
+use crate::store::StateStore;
+use ::redis::aio::ConnectionManager;
+use ::redis::Script;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+/// Prefix of every key the node writes.
+const KEY_PREFIX: &str = "hesha:";
+
+const SWAP_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    redis.call("SET", KEYS[1], ARGV[2], "PX", ARGV[3])
+    return 1
+end
+return 0
+"#;
+
+const REMOVE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+/// Shared state in Redis.
+pub struct RedisStore {
+    connection: ConnectionManager,
+}
+
+impl RedisStore {
+    /// Connect to a `redis://` or `rediss://` URL. The connection is
+    /// re-established if it drops.
+    pub async fn connect(url: &str) -> StorageResult<Self> {
+        let client = ::redis::Client::open(url).map_err(|e| StorageError::InvalidUrl(e.to_string()))?;
+        let connection = ConnectionManager::new(client).await?;
+        Ok(Self { connection })
+    }
+}
+
+impl From<::redis::RedisError> for StorageError {
+    fn from(e: ::redis::RedisError) -> Self {
+        StorageError::Database(e.to_string())
+    }
+}
+
+fn prefixed(key: &str) -> String {
+    format!("{}{}", KEY_PREFIX, key)
+}
+
+/// Milliseconds until `expires_at`, at least one.
+fn ttl_millis(expires_at: DateTime<Utc>) -> i64 {
+    (expires_at - Utc::now()).num_milliseconds().max(1)
+}
+
+#[async_trait]
+impl StateStore for RedisStore {
+    #[tracing::instrument(name = "state.get", skip_all)]
+    async fn get(&self, key: &str) -> StorageResult<Option<String>> {
+        let mut connection = self.connection.clone();
+        let value: Option<String> = ::redis::cmd("GET")
+            .arg(prefixed(key))
+            .query_async(&mut connection)
+            .await?;
+        Ok(value)
+    }
+    
+    #[tracing::instrument(name = "state.compare_and_swap", skip_all)]
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        current: Option<&str>,
+        value: &str,
+        expires_at: DateTime<Utc>,
+    ) -> StorageResult<bool> {
+        let mut connection = self.connection.clone();
+        match current {
+            None => {
+                let stored: Option<String> = ::redis::cmd("SET")
+                    .arg(prefixed(key))
+                    .arg(value)
+                    .arg("NX")
+                    .arg("PX")
+                    .arg(ttl_millis(expires_at))
+                    .query_async(&mut connection)
+                    .await?;
+                Ok(stored.is_some())
+            }
+            Some(current) => {
+                let swapped: i64 = Script::new(SWAP_SCRIPT)
+                    .key(prefixed(key))
+                    .arg(current)
+                    .arg(value)
+                    .arg(ttl_millis(expires_at))
+                    .invoke_async(&mut connection)
+                    .await?;
+                Ok(swapped == 1)
+            }
+        }
+    }
+    
+    #[tracing::instrument(name = "state.compare_and_remove", skip_all)]
+    async fn compare_and_remove(&self, key: &str, current: &str) -> StorageResult<bool> {
+        let mut connection = self.connection.clone();
+        let removed: i64 = Script::new(REMOVE_SCRIPT)
+            .key(prefixed(key))
+            .arg(current)
+            .invoke_async(&mut connection)
+            .await?;
+        Ok(removed == 1)
+    }
+    
+    async fn purge_expired(&self, _now: DateTime<Utc>) -> StorageResult<u64> {
+        Ok(0)
+    }
+}
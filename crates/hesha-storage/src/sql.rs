@@ -0,0 +1,420 @@
+//! SQL backend for SQLite and PostgreSQL.
+//!
+//! Both databases are driven through sqlx's `Any` driver with the same
+//! queries: parameters are written `$1, $2, ...` in order of appearance,
+//! and timestamps are stored as microseconds since the Unix epoch in
+//! `BIGINT` columns. The schema is migrated to the current version on
+//! connect (see `migrations`). Audit entries are kept as the JSON they were
+//! hashed from. Each call runs in a tracing span named after its store and
+//! method (`attestations.insert`), so queries show up in request traces.
+
+use crate::error::{StorageError, StorageResult};
+use crate::store::{
+    AttestationStore, AuditStore, IssuanceCount, NonceStore, ProxyAssignment, RevocationStore,
+    StateStore, StoredAttestation,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hesha_types::{AuditEntry, RevocationEntry};
+use sqlx::any::{AnyPoolOptions, AnyRow};
+use sqlx::{AnyPool, Row};
+
+/// Backend storing records in an SQL database.
+pub struct SqlStore {
+    pool: AnyPool,
+}
+
+impl SqlStore {
+    /// Connect to a `sqlite:` or `postgres://` URL and apply pending
+    /// schema migrations.
+    pub async fn connect(url: &str) -> StorageResult<Self> {
+        sqlx::any::install_default_drivers();
+        
+        // Each connection to an in-memory SQLite database gets its own
+        // database, so there must only be one
+        let max_connections = if url.contains(":memory:") || url.contains("mode=memory") { 1 } else { 10 };
+        let pool = AnyPoolOptions::new()
+            .max_connections(max_connections)
+            .connect(url)
+            .await?;
+        
+        crate::migrations::run(&pool).await?;
+        
+        Ok(Self { pool })
+    }
+}
+
+const MICROS_PER_DAY: i64 = 86_400_000_000;
+
+fn to_micros(time: DateTime<Utc>) -> i64 {
+    time.timestamp_micros()
+}
+
+fn from_micros(micros: i64) -> StorageResult<DateTime<Utc>> {
+    DateTime::from_timestamp_micros(micros)
+        .ok_or_else(|| StorageError::Corrupt(format!("timestamp {}", micros)))
+}
+
+fn attestation_from_row(row: &AnyRow) -> StorageResult<StoredAttestation> {
+    Ok(StoredAttestation {
+        jti: row.try_get("jti")?,
+        proxy_number: row.try_get("proxy_number")?,
+        scope: row.try_get("scope")?,
+        issued_at: from_micros(row.try_get("issued_at")?)?,
+        expires_at: from_micros(row.try_get("expires_at")?)?,
+        renews: row.try_get("renews")?,
+    })
+}
+
+fn revocation_from_row(row: &AnyRow) -> StorageResult<RevocationEntry> {
+    Ok(RevocationEntry {
+        jti: row.try_get("jti")?,
+        revoked_at: from_micros(row.try_get("revoked_at")?)?,
+        reason: row.try_get("reason")?,
+    })
+}
+
+fn audit_entry_from_row(row: &AnyRow) -> StorageResult<AuditEntry> {
+    let entry: String = row.try_get("entry")?;
+    serde_json::from_str(&entry).map_err(|e| StorageError::Corrupt(format!("audit entry: {}", e)))
+}
+
+#[async_trait]
+impl AttestationStore for SqlStore {
+    #[tracing::instrument(name = "attestations.insert", skip_all)]
+    async fn insert(&self, attestation: &StoredAttestation) -> StorageResult<()> {
+        let result = sqlx::query(
+            "INSERT INTO attestations (jti, proxy_number, scope, issued_at, expires_at, renews)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (jti) DO NOTHING",
+        )
+        .bind(&attestation.jti)
+        .bind(&attestation.proxy_number)
+        .bind(&attestation.scope)
+        .bind(to_micros(attestation.issued_at))
+        .bind(to_micros(attestation.expires_at))
+        .bind(&attestation.renews)
+        .execute(&self.pool)
+        .await?;
+        
+        if result.rows_affected() == 0 {
+            return Err(StorageError::Conflict(format!("attestation {}", attestation.jti)));
+        }
+        Ok(())
+    }
+    
+    #[tracing::instrument(name = "attestations.get", skip_all)]
+    async fn get(&self, jti: &str) -> StorageResult<Option<StoredAttestation>> {
+        sqlx::query("SELECT * FROM attestations WHERE jti = $1")
+            .bind(jti)
+            .fetch_optional(&self.pool)
+            .await?
+            .as_ref()
+            .map(attestation_from_row)
+            .transpose()
+    }
+    
+    #[tracing::instrument(name = "attestations.find_by_proxy_number", skip_all)]
+    async fn find_by_proxy_number(&self, proxy_number: &str) -> StorageResult<Vec<StoredAttestation>> {
+        sqlx::query("SELECT * FROM attestations WHERE proxy_number = $1 ORDER BY issued_at DESC")
+            .bind(proxy_number)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(attestation_from_row)
+            .collect()
+    }
+    
+    #[tracing::instrument(name = "attestations.recent", skip_all)]
+    async fn recent(&self, limit: usize) -> StorageResult<Vec<StoredAttestation>> {
+        sqlx::query("SELECT * FROM attestations ORDER BY issued_at DESC LIMIT $1")
+            .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(attestation_from_row)
+            .collect()
+    }
+    
+    #[tracing::instrument(name = "attestations.assign_proxy_number", skip_all)]
+    async fn assign_proxy_number(&self, proxy_number: &str, phone_hash: &str) -> StorageResult<bool> {
+        // The primary key lets only one phone hold a proxy number
+        sqlx::query(
+            "INSERT INTO proxy_numbers (proxy_number, phone_hash, assigned_at) VALUES ($1, $2, $3)
+             ON CONFLICT (proxy_number) DO NOTHING",
+        )
+        .bind(proxy_number)
+        .bind(phone_hash)
+        .bind(to_micros(Utc::now()))
+        .execute(&self.pool)
+        .await?;
+        
+        let owner: String = sqlx::query("SELECT phone_hash FROM proxy_numbers WHERE proxy_number = $1")
+            .bind(proxy_number)
+            .fetch_one(&self.pool)
+            .await?
+            .try_get("phone_hash")?;
+        Ok(owner == phone_hash)
+    }
+    
+    #[tracing::instrument(name = "attestations.find_by_phone_hash", skip_all)]
+    async fn find_by_phone_hash(&self, phone_hash: &str) -> StorageResult<Vec<StoredAttestation>> {
+        sqlx::query(
+            "SELECT attestations.* FROM attestations
+             JOIN proxy_numbers ON proxy_numbers.proxy_number = attestations.proxy_number
+             WHERE proxy_numbers.phone_hash = $1
+             ORDER BY attestations.issued_at DESC",
+        )
+        .bind(phone_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(attestation_from_row)
+        .collect()
+    }
+    
+    #[tracing::instrument(name = "attestations.all", skip_all)]
+    async fn all(&self) -> StorageResult<Vec<StoredAttestation>> {
+        sqlx::query("SELECT * FROM attestations ORDER BY issued_at")
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(attestation_from_row)
+            .collect()
+    }
+    
+    #[tracing::instrument(name = "attestations.proxy_assignments", skip_all)]
+    async fn proxy_assignments(&self) -> StorageResult<Vec<ProxyAssignment>> {
+        sqlx::query("SELECT proxy_number, phone_hash FROM proxy_numbers ORDER BY proxy_number")
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| {
+                Ok(ProxyAssignment {
+                    proxy_number: row.try_get("proxy_number")?,
+                    phone_hash: row.try_get("phone_hash")?,
+                })
+            })
+            .collect()
+    }
+    
+    #[tracing::instrument(name = "attestations.count_expiring", skip_all)]
+    async fn count_expiring(&self, after: DateTime<Utc>, until: Option<DateTime<Utc>>) -> StorageResult<u64> {
+        let query = match until {
+            Some(until) => sqlx::query(
+                "SELECT COUNT(*) AS expiring FROM attestations WHERE expires_at > $1 AND expires_at <= $2",
+            )
+            .bind(to_micros(after))
+            .bind(to_micros(until)),
+            None => sqlx::query("SELECT COUNT(*) AS expiring FROM attestations WHERE expires_at > $1")
+                .bind(to_micros(after)),
+        };
+        let count: i64 = query.fetch_one(&self.pool).await?.try_get("expiring")?;
+        Ok(count.max(0) as u64)
+    }
+    
+    #[tracing::instrument(name = "attestations.issuance_counts", skip_all)]
+    async fn issuance_counts(&self, since: DateTime<Utc>) -> StorageResult<Vec<IssuanceCount>> {
+        sqlx::query(
+            "SELECT issued_at / $1 AS day, scope, COUNT(*) AS issued, COUNT(renews) AS renewals
+             FROM attestations WHERE issued_at >= $2
+             GROUP BY day, scope ORDER BY day, scope",
+        )
+        .bind(MICROS_PER_DAY)
+        .bind(to_micros(since))
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| {
+            let day: i64 = row.try_get("day")?;
+            let issued: i64 = row.try_get("issued")?;
+            let renewals: i64 = row.try_get("renewals")?;
+            Ok(IssuanceCount {
+                day: from_micros(day * MICROS_PER_DAY)?.date_naive(),
+                scope: row.try_get("scope")?,
+                issued: issued.max(0) as u64,
+                renewals: renewals.max(0) as u64,
+            })
+        })
+        .collect()
+    }
+}
+
+#[async_trait]
+impl NonceStore for SqlStore {
+    #[tracing::instrument(name = "nonces.check_and_insert", skip_all)]
+    async fn check_and_insert(&self, nonce: &str, expires_at: DateTime<Utc>) -> StorageResult<bool> {
+        // An expired entry for the same nonce is replaced; a live one is kept
+        let result = sqlx::query(
+            "INSERT INTO nonces (nonce, expires_at) VALUES ($1, $2)
+             ON CONFLICT (nonce) DO UPDATE SET expires_at = excluded.expires_at
+             WHERE nonces.expires_at <= $3",
+        )
+        .bind(nonce)
+        .bind(to_micros(expires_at))
+        .bind(to_micros(Utc::now()))
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(result.rows_affected() == 1)
+    }
+    
+    #[tracing::instrument(name = "nonces.purge_expired", skip_all)]
+    async fn purge_expired(&self, now: DateTime<Utc>) -> StorageResult<u64> {
+        let result = sqlx::query("DELETE FROM nonces WHERE expires_at <= $1")
+            .bind(to_micros(now))
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+impl RevocationStore for SqlStore {
+    #[tracing::instrument(name = "revocations.revoke", skip_all)]
+    async fn revoke(&self, entry: &RevocationEntry) -> StorageResult<bool> {
+        let result = sqlx::query(
+            "INSERT INTO revocations (jti, revoked_at, reason) VALUES ($1, $2, $3)
+             ON CONFLICT (jti) DO NOTHING",
+        )
+        .bind(&entry.jti)
+        .bind(to_micros(entry.revoked_at))
+        .bind(entry.reason.clone())
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(result.rows_affected() == 1)
+    }
+    
+    #[tracing::instrument(name = "revocations.get", skip_all)]
+    async fn get(&self, jti: &str) -> StorageResult<Option<RevocationEntry>> {
+        sqlx::query("SELECT * FROM revocations WHERE jti = $1")
+            .bind(jti)
+            .fetch_optional(&self.pool)
+            .await?
+            .as_ref()
+            .map(revocation_from_row)
+            .transpose()
+    }
+    
+    #[tracing::instrument(name = "revocations.list", skip_all)]
+    async fn list(&self) -> StorageResult<Vec<RevocationEntry>> {
+        sqlx::query("SELECT * FROM revocations ORDER BY revoked_at")
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(revocation_from_row)
+            .collect()
+    }
+}
+
+#[async_trait]
+impl AuditStore for SqlStore {
+    #[tracing::instrument(name = "audit.append", skip_all)]
+    async fn append(&self, entry: &AuditEntry) -> StorageResult<()> {
+        let json = serde_json::to_string(entry).map_err(|e| StorageError::Corrupt(e.to_string()))?;
+        let result = sqlx::query(
+            "INSERT INTO audit_log (seq, entry) VALUES ($1, $2)
+             ON CONFLICT (seq) DO NOTHING",
+        )
+        .bind(i64::try_from(entry.seq).unwrap_or(i64::MAX))
+        .bind(json)
+        .execute(&self.pool)
+        .await?;
+        
+        if result.rows_affected() == 0 {
+            return Err(StorageError::Conflict(format!("audit entry {}", entry.seq)));
+        }
+        Ok(())
+    }
+    
+    #[tracing::instrument(name = "audit.last", skip_all)]
+    async fn last(&self) -> StorageResult<Option<AuditEntry>> {
+        sqlx::query("SELECT entry FROM audit_log ORDER BY seq DESC LIMIT 1")
+            .fetch_optional(&self.pool)
+            .await?
+            .as_ref()
+            .map(audit_entry_from_row)
+            .transpose()
+    }
+    
+    #[tracing::instrument(name = "audit.list", skip_all)]
+    async fn list(&self, after_seq: u64, limit: usize) -> StorageResult<Vec<AuditEntry>> {
+        sqlx::query("SELECT entry FROM audit_log WHERE seq > $1 ORDER BY seq LIMIT $2")
+            .bind(i64::try_from(after_seq).unwrap_or(i64::MAX))
+            .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(audit_entry_from_row)
+            .collect()
+    }
+}
+
+#[async_trait]
+impl StateStore for SqlStore {
+    #[tracing::instrument(name = "state.get", skip_all)]
+    async fn get(&self, key: &str) -> StorageResult<Option<String>> {
+        let row = sqlx::query("SELECT value FROM node_state WHERE key = $1 AND expires_at > $2")
+            .bind(key)
+            .bind(to_micros(Utc::now()))
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| row.try_get("value")).transpose()?)
+    }
+    
+    #[tracing::instrument(name = "state.compare_and_swap", skip_all)]
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        current: Option<&str>,
+        value: &str,
+        expires_at: DateTime<Utc>,
+    ) -> StorageResult<bool> {
+        let result = match current {
+            // An expired value is replaced like a missing one
+            None => sqlx::query(
+                "INSERT INTO node_state (key, value, expires_at) VALUES ($1, $2, $3)
+                 ON CONFLICT (key) DO UPDATE SET value = excluded.value, expires_at = excluded.expires_at
+                 WHERE node_state.expires_at <= $4",
+            )
+            .bind(key)
+            .bind(value)
+            .bind(to_micros(expires_at))
+            .bind(to_micros(Utc::now())),
+            Some(current) => sqlx::query(
+                "UPDATE node_state SET value = $1, expires_at = $2
+                 WHERE key = $3 AND value = $4 AND expires_at > $5",
+            )
+            .bind(value)
+            .bind(to_micros(expires_at))
+            .bind(key)
+            .bind(current)
+            .bind(to_micros(Utc::now())),
+        }
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(result.rows_affected() == 1)
+    }
+    
+    #[tracing::instrument(name = "state.compare_and_remove", skip_all)]
+    async fn compare_and_remove(&self, key: &str, current: &str) -> StorageResult<bool> {
+        let result = sqlx::query("DELETE FROM node_state WHERE key = $1 AND value = $2 AND expires_at > $3")
+            .bind(key)
+            .bind(current)
+            .bind(to_micros(Utc::now()))
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() == 1)
+    }
+    
+    #[tracing::instrument(name = "state.purge_expired", skip_all)]
+    async fn purge_expired(&self, now: DateTime<Utc>) -> StorageResult<u64> {
+        let result = sqlx::query("DELETE FROM node_state WHERE expires_at <= $1")
+            .bind(to_micros(now))
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
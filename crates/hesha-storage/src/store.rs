@@ -0,0 +1,174 @@
+//! Storage traits implemented by every backend.
+
+use crate::error::StorageResult;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use hesha_types::{AuditEntry, RevocationEntry};
+use serde::{Deserialize, Serialize};
+
+/// An attestation issued by the node.
+/// 
+/// Only what the issuer needs to look attestations up again is kept; the
+/// subject's phone number is never stored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredAttestation {
+    /// JWT ID of the attestation.
+    pub jti: String,
+    /// Proxy number assigned.
+    pub proxy_number: String,
+    /// Scope the proxy number was generated for.
+    pub scope: String,
+    /// When it was issued.
+    pub issued_at: DateTime<Utc>,
+    /// When it expires.
+    pub expires_at: DateTime<Utc>,
+    /// JWT ID of the attestation it replaced, for renewals.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renews: Option<String>,
+}
+
+/// A proxy number claimed for a phone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxyAssignment {
+    /// The proxy number.
+    pub proxy_number: String,
+    /// Hash of the phone it belongs to.
+    pub phone_hash: String,
+}
+
+/// Attestations issued on one UTC day for one scope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssuanceCount {
+    /// The day.
+    pub day: NaiveDate,
+    /// The scope.
+    pub scope: String,
+    /// Attestations issued, renewals included.
+    pub issued: u64,
+    /// How many of them were renewals.
+    pub renewals: u64,
+}
+
+/// Record of issued attestations.
+#[async_trait]
+pub trait AttestationStore: Send + Sync {
+    /// Record a newly issued attestation.
+    /// 
+    /// Fails with [`StorageError::Conflict`](crate::StorageError::Conflict)
+    /// if an attestation with the same JWT ID is already stored.
+    async fn insert(&self, attestation: &StoredAttestation) -> StorageResult<()>;
+    
+    /// Look up an attestation by JWT ID.
+    async fn get(&self, jti: &str) -> StorageResult<Option<StoredAttestation>>;
+    
+    /// All attestations issued for a proxy number, newest first.
+    async fn find_by_proxy_number(&self, proxy_number: &str) -> StorageResult<Vec<StoredAttestation>>;
+    
+    /// The most recently issued attestations, newest first.
+    async fn recent(&self, limit: usize) -> StorageResult<Vec<StoredAttestation>>;
+    
+    /// Claim a proxy number for the phone with hash `phone_hash`.
+    /// 
+    /// Returns `false` if the proxy number is already assigned to a
+    /// different phone. Claiming again for the same phone succeeds.
+    /// Checking and claiming happen atomically, so concurrent issuances
+    /// can't both take the same proxy number.
+    async fn assign_proxy_number(&self, proxy_number: &str, phone_hash: &str) -> StorageResult<bool>;
+    
+    /// All attestations issued for proxy numbers assigned to the phone with
+    /// hash `phone_hash`, newest first.
+    async fn find_by_phone_hash(&self, phone_hash: &str) -> StorageResult<Vec<StoredAttestation>>;
+    
+    /// Every stored attestation, oldest first.
+    async fn all(&self) -> StorageResult<Vec<StoredAttestation>>;
+    
+    /// Every proxy number assignment, ordered by proxy number.
+    async fn proxy_assignments(&self) -> StorageResult<Vec<ProxyAssignment>>;
+    
+    /// Number of attestations expiring after `after` and, if `until` is
+    /// given, no later than `until`.
+    async fn count_expiring(&self, after: DateTime<Utc>, until: Option<DateTime<Utc>>) -> StorageResult<u64>;
+    
+    /// Attestations issued since `since`, counted per UTC day and scope,
+    /// ordered by day and then scope.
+    async fn issuance_counts(&self, since: DateTime<Utc>) -> StorageResult<Vec<IssuanceCount>>;
+}
+
+/// Single-use values that must not be accepted twice while they are valid.
+#[async_trait]
+pub trait NonceStore: Send + Sync {
+    /// Record a nonce as used until `expires_at`.
+    /// 
+    /// Returns `false` if the nonce was already recorded and has not yet
+    /// expired. Checking and recording happen atomically, so concurrent
+    /// callers can't both succeed with the same nonce.
+    async fn check_and_insert(&self, nonce: &str, expires_at: DateTime<Utc>) -> StorageResult<bool>;
+    
+    /// Forget nonces that expired before `now`, returning how many.
+    async fn purge_expired(&self, now: DateTime<Utc>) -> StorageResult<u64>;
+}
+
+/// Revoked attestations.
+#[async_trait]
+pub trait RevocationStore: Send + Sync {
+    /// Revoke an attestation.
+    /// 
+    /// Returns `false` (keeping the original entry) if it was already revoked.
+    async fn revoke(&self, entry: &RevocationEntry) -> StorageResult<bool>;
+    
+    /// Look up the revocation of an attestation by JWT ID.
+    async fn get(&self, jti: &str) -> StorageResult<Option<RevocationEntry>>;
+    
+    /// All revocations, oldest first.
+    async fn list(&self) -> StorageResult<Vec<RevocationEntry>>;
+}
+
+/// Append-only audit log.
+#[async_trait]
+pub trait AuditStore: Send + Sync {
+    /// Append an entry.
+    /// 
+    /// Fails with [`StorageError::Conflict`](crate::StorageError::Conflict)
+    /// if an entry with the same sequence number exists, so concurrent
+    /// writers can't fork the chain; the loser reads the new last entry and
+    /// tries again.
+    async fn append(&self, entry: &AuditEntry) -> StorageResult<()>;
+    
+    /// The newest entry.
+    async fn last(&self) -> StorageResult<Option<AuditEntry>>;
+    
+    /// Up to `limit` entries with sequence numbers above `after_seq`,
+    /// oldest first.
+    async fn list(&self, after_seq: u64, limit: usize) -> StorageResult<Vec<AuditEntry>>;
+}
+
+/// Short-lived node state that replicas behind a load balancer share:
+/// verification sessions, idempotency records, rate-limit buckets and
+/// leases.
+/// 
+/// Values are opaque strings under namespaced keys, and vanish once they
+/// expire. Updates are compare-and-swap, so replicas racing on a key can't
+/// both win.
+#[async_trait]
+pub trait StateStore: Send + Sync {
+    /// The unexpired value under `key`.
+    async fn get(&self, key: &str) -> StorageResult<Option<String>>;
+    
+    /// Store `value` under `key` until `expires_at`, if the key holds
+    /// `current` (`None` meaning no value, or an expired one).
+    /// 
+    /// Returns `false`, storing nothing, if the key holds anything else.
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        current: Option<&str>,
+        value: &str,
+        expires_at: DateTime<Utc>,
+    ) -> StorageResult<bool>;
+    
+    /// Remove `key` if it holds `current`, returning whether it did.
+    async fn compare_and_remove(&self, key: &str, current: &str) -> StorageResult<bool>;
+    
+    /// Forget values that expired before `now`, returning how many.
+    async fn purge_expired(&self, now: DateTime<Utc>) -> StorageResult<u64>;
+}
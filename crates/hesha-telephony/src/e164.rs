@@ -0,0 +1,74 @@
+//! E.164 normalization of dialed numbers.
+
+use hesha_types::{HeshaError, HeshaResult};
+
+/// Longest E.164 number, in digits.
+const MAX_DIGITS: usize = 15;
+
+/// Shortest number accepted, in digits, as the issuer's phone validation.
+const MIN_DIGITS: usize = 7;
+
+/// Normalize a dialed number to E.164 (`+` and digits).
+///
+/// Visual separators (spaces, `-`, `.`, `/`, parentheses) are dropped. A
+/// number in international form is taken as is, whether it starts with
+/// `+` or the ITU international prefix `00`. A national number, starting
+/// with the trunk prefix `0` or not, needs `default_country` (the calling
+/// code, e.g. `"44"`) to be completed.
+pub fn normalize_e164(input: &str, default_country: Option<&str>) -> HeshaResult<String> {
+    let invalid = |reason: &str| HeshaError::InvalidPhoneNumber(format!("{}: {}", reason, input));
+
+    let mut international = false;
+    let mut digits = String::with_capacity(input.len());
+    for (i, c) in input.trim().chars().enumerate() {
+        match c {
+            '+' if i == 0 => international = true,
+            '0'..='9' => digits.push(c),
+            ' ' | '-' | '.' | '/' | '(' | ')' => {}
+            _ => return Err(invalid("Unexpected character in number")),
+        }
+    }
+
+    let number = if international {
+        digits
+    } else if let Some(rest) = digits.strip_prefix("00") {
+        rest.to_string()
+    } else {
+        let country = default_country
+            .map(|cc| cc.trim_start_matches('+'))
+            .ok_or_else(|| invalid("National number without a default country"))?;
+        if country.is_empty() || country.len() > 3 || !country.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(HeshaError::InvalidPhoneNumber(format!("Invalid calling code: {}", country)));
+        }
+        format!("{}{}", country, digits.strip_prefix('0').unwrap_or(&digits))
+    };
+
+    if number.starts_with('0') {
+        return Err(invalid("Calling code cannot start with 0"));
+    }
+    if !(MIN_DIGITS..=MAX_DIGITS).contains(&number.len()) {
+        return Err(invalid("Number must have 7 to 15 digits"));
+    }
+    Ok(format!("+{}", number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize_e164("+1 (415) 555-2671", None).unwrap(), "+14155552671");
+        assert_eq!(normalize_e164("0044 20 7946 0958", None).unwrap(), "+442079460958");
+        assert_eq!(normalize_e164("020 7946 0958", Some("44")).unwrap(), "+442079460958");
+        assert_eq!(normalize_e164("415.555.2671", Some("+1")).unwrap(), "+14155552671");
+        assert_eq!(normalize_e164("+44 00 1234567890", None).unwrap(), "+44001234567890");
+
+        assert!(normalize_e164("020 7946 0958", None).is_err());
+        assert!(normalize_e164("+1 415 555 2671 ext 3", None).is_err());
+        assert!(normalize_e164("+1234", None).is_err());
+        assert!(normalize_e164("+0123456789", None).is_err());
+        assert!(normalize_e164("+1234567890123456", None).is_err());
+        assert!(normalize_e164("1+4155552671", None).is_err());
+    }
+}
@@ -0,0 +1,32 @@
+//! Telephony helpers for routing calls to Hesha proxy numbers.
+//!
+//! VoIP providers and PBXs see proxy numbers in dial strings, `tel:` URIs
+//! and SIP Request-URIs. This crate turns those into validated proxy
+//! numbers and back:
+//!
+//! - [`normalize_e164`] cleans up dialed numbers into E.164
+//! - [`ProxyRoute`] validates a proxy number and splits it into its scope
+//!   (the calling code it was issued for), its routing prefix
+//!   (`+{scope}00`) and subscriber digits
+//! - [`SipRouter`] maps proxy numbers to SIP URIs by scope, and parses
+//!   proxy numbers out of incoming SIP and `tel:` URIs
+//!
+//! ```
+//! use hesha_telephony::SipRouter;
+//!
+//! let router = SipRouter::new("proxy.voip.example").with_scope("44", "uk.voip.example");
+//! let route = router.route("sip:+44 00 1234567890@pbx.local;user=phone").unwrap();
+//! assert_eq!(route.scope(), "44");
+//! assert_eq!(router.sip_uri(&route), "sip:+44001234567890@uk.voip.example;user=phone");
+//! ```
+
+#![warn(missing_docs)]
+#![forbid(unsafe_code)]
+
+pub mod e164;
+pub mod route;
+pub mod sip;
+
+pub use e164::normalize_e164;
+pub use route::{ProxyRoute, ROUTING_MARKER};
+pub use sip::{number_from_uri, tel_uri, SipRouter};
@@ -0,0 +1,153 @@
+//! Validating proxy numbers and splitting them for routing.
+
+use crate::e164::normalize_e164;
+use hesha_types::{HeshaError, HeshaResult, ProxyNumber};
+use std::fmt;
+
+/// Digits between the scope and the subscriber digits of a proxy number.
+pub const ROUTING_MARKER: &str = "00";
+
+/// A proxy number split into the parts a dial plan routes on.
+///
+/// Proxy numbers are `+{scope}00{subscriber}`, 13 digits for a one-digit
+/// scope and 14 otherwise, as the issuer generates them: the scope is the
+/// calling code the number was issued for, and `+{scope}00` the prefix
+/// calls to it are routed by. Numbers that aren't laid out this way are
+/// refused.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProxyRoute {
+    number: String,
+    scope_len: usize,
+}
+
+impl ProxyRoute {
+    /// Validate a proxy number in E.164 form.
+    ///
+    /// A few numbers fit the layout with two different scope lengths (say
+    /// `+44000123456789` with scope 44 or 440). The shortest scope is
+    /// taken; use [`with_scope`](Self::with_scope) when the scope is known.
+    pub fn parse(number: &str) -> HeshaResult<Self> {
+        let number = validate(number)?;
+        (1..=4)
+            .find(|&len| layout_fits(&number, len))
+            .map(|scope_len| Self { number: number.clone(), scope_len })
+            .ok_or_else(|| {
+                HeshaError::InvalidProxyNumber(format!("{} is not laid out as +{{scope}}00{{digits}}", number))
+            })
+    }
+
+    /// Validate a proxy number issued for `scope`.
+    pub fn with_scope(number: &str, scope: &str) -> HeshaResult<Self> {
+        let number = validate(number)?;
+        if !number[1..].starts_with(scope) || !layout_fits(&number, scope.len()) {
+            return Err(HeshaError::InvalidProxyNumber(format!(
+                "{} is not a proxy number for scope {}",
+                number, scope
+            )));
+        }
+        Ok(Self { number, scope_len: scope.len() })
+    }
+
+    /// Validate a dialed proxy number, normalizing it to E.164 first.
+    pub fn from_dialed(input: &str) -> HeshaResult<Self> {
+        Self::parse(&normalize_e164(input, None)?)
+    }
+
+    /// The proxy number, in E.164.
+    pub fn number(&self) -> &str {
+        &self.number
+    }
+
+    /// The calling code the number was issued for.
+    pub fn scope(&self) -> &str {
+        &self.number[1..1 + self.scope_len]
+    }
+
+    /// The prefix calls to the number are routed by: `+{scope}00`.
+    pub fn routing_prefix(&self) -> &str {
+        &self.number[..1 + self.scope_len + ROUTING_MARKER.len()]
+    }
+
+    /// The digits after the routing prefix.
+    pub fn subscriber(&self) -> &str {
+        &self.number[1 + self.scope_len + ROUTING_MARKER.len()..]
+    }
+
+    /// The number as a [`ProxyNumber`].
+    pub fn proxy_number(&self) -> ProxyNumber {
+        ProxyNumber::new(self.number.clone()).expect("validated as a proxy number")
+    }
+}
+
+impl TryFrom<&ProxyNumber> for ProxyRoute {
+    type Error = HeshaError;
+
+    fn try_from(proxy_number: &ProxyNumber) -> HeshaResult<Self> {
+        Self::parse(proxy_number.as_str())
+    }
+}
+
+impl fmt::Display for ProxyRoute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.number)
+    }
+}
+
+fn validate(number: &str) -> HeshaResult<String> {
+    let proxy = ProxyNumber::new(number)?;
+    let digits = &proxy.as_str()[1..];
+    if digits.starts_with('0') || digits.len() > 15 {
+        return Err(HeshaError::InvalidProxyNumber(format!("{} is not an E.164 number", proxy)));
+    }
+    Ok(proxy.as_str().to_string())
+}
+
+/// Whether `number` is `+{scope}00{subscriber}` with a scope of `scope_len`
+/// digits and as many subscriber digits as the issuer generates for it.
+fn layout_fits(number: &str, scope_len: usize) -> bool {
+    let digits = &number[1..];
+    let subscriber_len = (12 - scope_len as i64).clamp(8, 10) as usize;
+    digits.len() == scope_len + ROUTING_MARKER.len() + subscriber_len
+        && !digits.starts_with('0')
+        && digits[scope_len..].starts_with(ROUTING_MARKER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        for (number, scope, subscriber) in [
+            ("+1001234567890", "1", "1234567890"),
+            ("+44001234567890", "44", "1234567890"),
+            ("+23400123456789", "234", "123456789"),
+            ("+12640012345678", "1264", "12345678"),
+        ] {
+            let route = ProxyRoute::parse(number).unwrap();
+            assert_eq!(route.scope(), scope, "{}", number);
+            assert_eq!(route.routing_prefix(), format!("+{}00", scope));
+            assert_eq!(route.subscriber(), subscriber);
+            assert_eq!(route.proxy_number().as_str(), number);
+        }
+        // Four-digit scopes get eight subscriber digits, not seven
+        assert!(ProxyRoute::with_scope("+1264001234567", "1264").is_err());
+    }
+
+    #[test]
+    fn test_ambiguous_and_invalid() {
+        let route = ProxyRoute::parse("+44000123456789").unwrap();
+        assert_eq!(route.scope(), "44");
+        assert_eq!(ProxyRoute::with_scope("+44000123456789", "440").unwrap().scope(), "440");
+
+        // Real numbers, wrong lengths and malformed input
+        assert!(ProxyRoute::parse("+14155552671").is_err());
+        assert!(ProxyRoute::parse("+100123").is_err());
+        assert!(ProxyRoute::parse("+4400123456789012").is_err());
+        assert!(ProxyRoute::parse("4400123456789").is_err());
+        assert!(ProxyRoute::with_scope("+44001234567890", "1").is_err());
+
+        let dialed = ProxyRoute::from_dialed("0044 00 123 456 7890").unwrap();
+        assert_eq!(dialed.number(), "+44001234567890");
+    }
+}
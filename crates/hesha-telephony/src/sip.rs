@@ -0,0 +1,123 @@
+//! SIP and `tel:` URI mapping.
+
+use crate::e164::normalize_e164;
+use crate::route::ProxyRoute;
+use hesha_types::{HeshaError, HeshaResult};
+use std::collections::BTreeMap;
+
+/// The E.164 number in a SIP, SIPS or `tel:` URI, or in a plain dial
+/// string.
+///
+/// Name-addr brackets (`<sip:...>`), URI parameters and the host are
+/// dropped; the user part must be a number in international form.
+pub fn number_from_uri(uri: &str) -> HeshaResult<String> {
+    let uri = uri.trim().trim_start_matches('<').trim_end_matches('>');
+    let lower = uri.to_ascii_lowercase();
+    let user = if let Some(scheme_len) = ["sips:", "sip:"].iter().find(|s| lower.starts_with(*s)).map(|s| s.len()) {
+        let rest = &uri[scheme_len..];
+        let (user, _host) = rest
+            .split_once('@')
+            .ok_or_else(|| HeshaError::InvalidPhoneNumber(format!("SIP URI without a user part: {}", uri)))?;
+        user
+    } else if lower.starts_with("tel:") {
+        &uri[4..]
+    } else {
+        uri
+    };
+    let number = user.split(';').next().unwrap_or_default();
+    normalize_e164(number, None)
+}
+
+/// `tel:` URI of a proxy number (RFC 3966).
+pub fn tel_uri(route: &ProxyRoute) -> String {
+    format!("tel:{}", route.number())
+}
+
+/// Maps proxy numbers to SIP destinations by scope.
+///
+/// Calls to a proxy number go to the domain configured for its scope, or
+/// the default domain. The gateway at that domain resolves the proxy
+/// number to the subscriber.
+#[derive(Debug, Clone)]
+pub struct SipRouter {
+    default_domain: String,
+    scopes: BTreeMap<String, String>,
+}
+
+impl SipRouter {
+    /// Route every scope to `default_domain`.
+    pub fn new(default_domain: impl Into<String>) -> Self {
+        Self {
+            default_domain: default_domain.into(),
+            scopes: BTreeMap::new(),
+        }
+    }
+
+    /// Route proxy numbers of `scope` to `domain`.
+    pub fn with_scope(mut self, scope: impl Into<String>, domain: impl Into<String>) -> Self {
+        self.scopes.insert(scope.into(), domain.into());
+        self
+    }
+
+    /// Validate the proxy number called by a SIP or `tel:` URI or a dial
+    /// string.
+    ///
+    /// Numbers that fit several scopes are resolved in favour of a
+    /// configured one, the longest first.
+    pub fn route(&self, destination: &str) -> HeshaResult<ProxyRoute> {
+        let number = number_from_uri(destination)?;
+        let mut scopes: Vec<&String> = self.scopes.keys().collect();
+        scopes.sort_by_key(|scope| std::cmp::Reverse(scope.len()));
+        scopes
+            .into_iter()
+            .find_map(|scope| ProxyRoute::with_scope(&number, scope).ok())
+            .map_or_else(|| ProxyRoute::parse(&number), Ok)
+    }
+
+    /// The SIP domain calls to `route` go to.
+    pub fn domain(&self, route: &ProxyRoute) -> &str {
+        self.scopes.get(route.scope()).unwrap_or(&self.default_domain)
+    }
+
+    /// SIP URI calls to `route` are sent to, with `user=phone` so the
+    /// gateway treats the user part as a telephone number (RFC 3261).
+    pub fn sip_uri(&self, route: &ProxyRoute) -> String {
+        format!("sip:{}@{};user=phone", route.number(), self.domain(route))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_from_uri() {
+        for uri in [
+            "sip:+44001234567890@pbx.example;user=phone",
+            "<SIPS:+44-00-1234567890;isub=1@pbx.example>",
+            "tel:+44001234567890;phone-context=example",
+            "+44 00 123 456 7890",
+        ] {
+            assert_eq!(number_from_uri(uri).unwrap(), "+44001234567890", "{}", uri);
+        }
+        assert!(number_from_uri("sip:pbx.example").is_err());
+        assert!(number_from_uri("sip:alice@pbx.example").is_err());
+    }
+
+    #[test]
+    fn test_router() {
+        let router = SipRouter::new("gw.example").with_scope("440", "special.example");
+
+        let route = router.route("sip:+1001234567890@in.example").unwrap();
+        assert_eq!(router.sip_uri(&route), "sip:+1001234567890@gw.example;user=phone");
+        assert_eq!(tel_uri(&route), "tel:+1001234567890");
+
+        // Fits scopes 44 and 440; the configured one wins
+        let route = router.route("tel:+44000123456789").unwrap();
+        assert_eq!(route.scope(), "440");
+        assert_eq!(router.domain(&route), "special.example");
+        assert_eq!(SipRouter::new("gw.example").route("tel:+44000123456789").unwrap().scope(), "44");
+
+        assert!(router.route("sip:+14155552671@in.example").is_err());
+    }
+}
@@ -0,0 +1,30 @@
+//! Failure modes of the mock issuer.
+
+use hesha_types::ErrorCode;
+use std::time::Duration;
+
+/// How a [`MockIssuer`](crate::MockIssuer) misbehaves.
+///
+/// The default is a well-behaved issuer. Behavior can be changed while the
+/// issuer runs with [`MockIssuer::set_behavior`](crate::MockIssuer::set_behavior).
+#[derive(Debug, Clone, Default)]
+pub struct Behavior {
+    /// Delay before every response.
+    pub latency: Duration,
+
+    /// Sign attestations with a key the issuer doesn't publish.
+    pub bad_signatures: bool,
+
+    /// Issue attestations that have already expired.
+    pub expired_attestations: bool,
+
+    /// Rotate the signing key after this many attestations.
+    pub rotate_keys_every: Option<u64>,
+
+    /// Publish only the current key, so attestations signed before a
+    /// rotation no longer verify.
+    pub forget_previous_keys: bool,
+
+    /// Refuse every request with this error.
+    pub fail_with: Option<ErrorCode>,
+}
@@ -0,0 +1,489 @@
+//! The mock issuer server.
+
+use crate::behavior::Behavior;
+use axum::{
+    extract::{rejection::JsonRejection, Path, Request, State},
+    http::header,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use hesha_core::{
+    generate_proxy_number, sign_revocation_list, sign_status_response, AttestationBuilder,
+    ProxyGenerationInput,
+};
+use hesha_crypto::{generate_hex_nonce, generate_keypair};
+use hesha_types::{
+    AttestationStatus, ErrorCode, HeshaError, HeshaResult, IssuerInfo, IssuerKey, KeyPair,
+    PhoneNumber, Problem, PublicKey, RevocationEntry, RevocationList, StatusResponse,
+    PROBLEM_CONTENT_TYPE,
+};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::oneshot;
+
+/// Validity of status responses, in seconds.
+const STATUS_VALIDITY_SECS: i64 = 300;
+
+/// A mock issuer serving on a loopback port until dropped.
+pub struct MockIssuer {
+    addr: SocketAddr,
+    inner: Arc<Inner>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+struct Inner {
+    domain: String,
+    state: Mutex<IssuerState>,
+}
+
+struct IssuerState {
+    behavior: Behavior,
+    key_id: String,
+    keypair: KeyPair,
+    created_at: DateTime<Utc>,
+    previous_keys: Vec<IssuerKey>,
+    issued: HashSet<String>,
+    issued_count: u64,
+    revocations: Vec<RevocationEntry>,
+}
+
+impl MockIssuer {
+    /// Start a well-behaved issuer.
+    pub async fn start() -> std::io::Result<Self> {
+        Self::start_with(Behavior::default()).await
+    }
+
+    /// Start an issuer behaving as `behavior` says.
+    pub async fn start_with(behavior: Behavior) -> std::io::Result<Self> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let keypair =
+            generate_keypair().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        let inner = Arc::new(Inner {
+            domain: addr.to_string(),
+            state: Mutex::new(IssuerState {
+                behavior,
+                key_id: "key-1".to_string(),
+                keypair,
+                created_at: Utc::now(),
+                previous_keys: Vec::new(),
+                issued: HashSet::new(),
+                issued_count: 0,
+                revocations: Vec::new(),
+            }),
+        });
+
+        let app = Router::new()
+            .route("/.well-known/hesha/pubkey.json", get(pubkey))
+            .route("/.well-known/hesha/revocations.json", get(revocations))
+            .route("/status/:jti", get(status))
+            .route("/attest", post(attest))
+            .route("/health", get(health))
+            .fallback(|| async { problem(ErrorCode::NotFound, "No such endpoint") })
+            .layer(middleware::from_fn_with_state(inner.clone(), misbehave))
+            .with_state(inner.clone());
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let server = axum::serve(listener, app).with_graceful_shutdown(async {
+                stopped.await.ok();
+            });
+            if let Err(e) = server.await {
+                tracing::error!("Mock issuer failed: {}", e);
+            }
+        });
+
+        Ok(Self {
+            addr,
+            inner,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    /// Base URL, e.g. `http://127.0.0.1:41234`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Issuer domain put in attestations, e.g. `127.0.0.1:41234`.
+    pub fn domain(&self) -> &str {
+        &self.inner.domain
+    }
+
+    /// Current behavior.
+    pub fn behavior(&self) -> Behavior {
+        self.inner.lock().behavior.clone()
+    }
+
+    /// Change the behavior for the requests that follow.
+    pub fn set_behavior(&self, behavior: Behavior) {
+        self.inner.lock().behavior = behavior;
+    }
+
+    /// Identifier of the current signing key.
+    pub fn key_id(&self) -> String {
+        self.inner.lock().key_id.clone()
+    }
+
+    /// Current signing key.
+    pub fn public_key(&self) -> PublicKey {
+        self.inner.lock().keypair.public.clone()
+    }
+
+    /// Replace the signing key with a new one, returning its identifier.
+    ///
+    /// The old key stays published unless
+    /// [`Behavior::forget_previous_keys`] is set.
+    pub fn rotate_key(&self) -> HeshaResult<String> {
+        self.inner.lock().rotate()
+    }
+
+    /// Issue an attestation directly, as `/attest` would.
+    pub fn issue(
+        &self,
+        phone_number: &PhoneNumber,
+        user_pubkey: &PublicKey,
+    ) -> HeshaResult<String> {
+        self.inner
+            .issue(phone_number, user_pubkey, "1")
+            .map(|(jwt, _)| jwt)
+    }
+
+    /// Revoke an attestation.
+    pub fn revoke(&self, jti: &str, reason: Option<&str>) {
+        let mut state = self.inner.lock();
+        if !state.revocations.iter().any(|entry| entry.jti == jti) {
+            state.revocations.push(RevocationEntry {
+                jti: jti.to_string(),
+                revoked_at: Utc::now(),
+                reason: reason.map(str::to_string),
+            });
+        }
+    }
+
+    /// Number of attestations issued so far.
+    pub fn attestations_issued(&self) -> u64 {
+        self.inner.lock().issued_count
+    }
+}
+
+impl Drop for MockIssuer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+impl Inner {
+    fn lock(&self) -> MutexGuard<'_, IssuerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Issue an attestation under the current behavior, returning it and
+    /// its proxy number.
+    fn issue(
+        &self,
+        phone_number: &PhoneNumber,
+        user_pubkey: &PublicKey,
+        scope: &str,
+    ) -> HeshaResult<(String, String)> {
+        let mut state = self.lock();
+        let proxy_number = generate_proxy_number(&ProxyGenerationInput {
+            phone_number: phone_number.as_str().to_string(),
+            user_pubkey: user_pubkey.to_base64(),
+            issuer_domain: self.domain.clone(),
+            scope: scope.to_string(),
+            nonce: generate_hex_nonce(),
+        })?;
+
+        // A stranger's key under the issuer's key ID
+        let stranger;
+        let signing_key = if state.behavior.bad_signatures {
+            stranger = generate_keypair()?;
+            &stranger.private
+        } else {
+            &state.keypair.private
+        };
+        let validity_days = if state.behavior.expired_attestations {
+            -1
+        } else {
+            30
+        };
+        let jwt = AttestationBuilder::new(
+            self.domain.clone(),
+            signing_key,
+            phone_number.clone(),
+            proxy_number.clone(),
+            user_pubkey.clone(),
+        )
+        .validity_days(validity_days)
+        .key_id(state.key_id.clone())
+        .build_jwt()?;
+
+        let jti = hesha_core::parse_attestation_jwt(&jwt)?.jti;
+        state.issued.insert(jti);
+        state.issued_count += 1;
+        if let Some(every) = state.behavior.rotate_keys_every {
+            if every > 0 && state.issued_count % every == 0 {
+                state.rotate()?;
+            }
+        }
+        Ok((jwt, proxy_number.as_str().to_string()))
+    }
+}
+
+impl IssuerState {
+    fn rotate(&mut self) -> HeshaResult<String> {
+        let generation = self.previous_keys.len() + 2;
+        let old = std::mem::replace(&mut self.keypair, generate_keypair()?);
+        let now = Utc::now();
+        self.previous_keys.push(IssuerKey {
+            key_id: std::mem::replace(&mut self.key_id, format!("key-{}", generation)),
+            public_key: old.public,
+            algorithm: "Ed25519".to_string(),
+            valid_from: self.created_at,
+            valid_until: None,
+        });
+        self.created_at = now;
+        Ok(self.key_id.clone())
+    }
+}
+
+/// Apply the latency and forced failures of the behavior.
+async fn misbehave(State(inner): State<Arc<Inner>>, request: Request, next: Next) -> Response {
+    let (latency, fail_with) = {
+        let state = inner.lock();
+        (state.behavior.latency, state.behavior.fail_with)
+    };
+    if !latency.is_zero() {
+        tokio::time::sleep(latency).await;
+    }
+    if let Some(code) = fail_with {
+        return problem(code, "Failure injected by the mock issuer");
+    }
+    next.run(request).await
+}
+
+/// An RFC 7807 problem document for `code`.
+fn problem(code: ErrorCode, detail: &str) -> Response {
+    let problem = Problem::new(code, detail);
+    let status = axum::http::StatusCode::from_u16(problem.status)
+        .unwrap_or(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+    (
+        status,
+        [(header::CONTENT_TYPE, PROBLEM_CONTENT_TYPE)],
+        serde_json::to_vec(&problem).unwrap_or_default(),
+    )
+        .into_response()
+}
+
+/// `GET /.well-known/hesha/pubkey.json`.
+async fn pubkey(State(inner): State<Arc<Inner>>) -> Json<IssuerInfo> {
+    let state = inner.lock();
+    let current = IssuerKey {
+        key_id: state.key_id.clone(),
+        public_key: state.keypair.public.clone(),
+        algorithm: "Ed25519".to_string(),
+        valid_from: state.created_at,
+        valid_until: None,
+    };
+    let mut keys = vec![current];
+    if !state.behavior.forget_previous_keys {
+        keys.extend(state.previous_keys.iter().cloned());
+    }
+    Json(IssuerInfo {
+        public_key: state.keypair.public.clone(),
+        algorithm: "Ed25519".to_string(),
+        created_at: state.created_at,
+        key_id: Some(state.key_id.clone()),
+        service_info: None,
+        keys,
+    })
+}
+
+/// Body of `POST /attest`; other members are accepted and ignored.
+#[derive(Deserialize)]
+struct AttestRequest {
+    phone_number: String,
+    user_pubkey: String,
+    scope: String,
+}
+
+/// `POST /attest`.
+async fn attest(
+    State(inner): State<Arc<Inner>>,
+    request: Result<Json<AttestRequest>, JsonRejection>,
+) -> Response {
+    let request = match request {
+        Ok(Json(request)) => request,
+        Err(rejection) => return problem(ErrorCode::InvalidRequest, &rejection.body_text()),
+    };
+    let phone_number = match PhoneNumber::new(request.phone_number) {
+        Ok(phone_number) => phone_number,
+        Err(e) => return problem(ErrorCode::InvalidPhoneNumber, &e.to_string()),
+    };
+    let user_pubkey = match PublicKey::from_base64(&request.user_pubkey) {
+        Ok(key) => key,
+        Err(e) => return problem(ErrorCode::InvalidPublicKey, &e.to_string()),
+    };
+    match inner.issue(&phone_number, &user_pubkey, &request.scope) {
+        Ok((attestation, proxy_number)) => Json(serde_json::json!({
+            "attestation": attestation,
+            "proxy_number": proxy_number,
+        }))
+        .into_response(),
+        Err(HeshaError::InvalidProxyNumber(detail)) => problem(ErrorCode::InvalidRequest, &detail),
+        Err(e) => problem(ErrorCode::AttestationFailed, &e.to_string()),
+    }
+}
+
+/// `GET /.well-known/hesha/revocations.json`, as a single page.
+async fn revocations(State(inner): State<Arc<Inner>>) -> Response {
+    let state = inner.lock();
+    let mut list = RevocationList::new(inner.domain.clone());
+    list.entries = state.revocations.clone();
+    if let Err(e) = sign_revocation_list(&mut list, &state.keypair.private) {
+        return problem(ErrorCode::SigningFailed, &e.to_string());
+    }
+    Json(list).into_response()
+}
+
+/// `GET /status/{jti}`.
+async fn status(State(inner): State<Arc<Inner>>, Path(jti): Path<String>) -> Response {
+    let state = inner.lock();
+    let status = match state.revocations.iter().find(|entry| entry.jti == jti) {
+        Some(entry) => entry.clone().into(),
+        None if state.issued.contains(&jti) => AttestationStatus::Good,
+        None => AttestationStatus::Unknown,
+    };
+    let mut response = StatusResponse::new(
+        inner.domain.clone(),
+        jti,
+        status,
+        chrono::Duration::seconds(STATUS_VALIDITY_SECS),
+    );
+    response.key_id = Some(state.key_id.clone());
+    if let Err(e) = sign_status_response(&mut response, &state.keypair.private) {
+        return problem(ErrorCode::SigningFailed, &e.to_string());
+    }
+    Json(response).into_response()
+}
+
+/// `GET /health`.
+async fn health(State(inner): State<Arc<Inner>>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+        "key_id": inner.lock().key_id,
+        "time": Utc::now(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hesha_client::IssuerClient;
+    use hesha_core::{verify_attestation, verify_status_response};
+    use std::time::{Duration, Instant};
+
+    fn phone() -> PhoneNumber {
+        PhoneNumber::new("+14155552671").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_well_behaved() {
+        let issuer = MockIssuer::start().await.unwrap();
+        let user = generate_keypair().unwrap();
+        let client = IssuerClient::new_insecure(&issuer.url()).unwrap();
+        assert!(client.health().await.unwrap().is_healthy());
+
+        let response = client
+            .request_attestation(&phone(), &user.public, "1", None)
+            .await
+            .unwrap();
+        let verified = verify_attestation(&response.attestation).await.unwrap();
+        assert_eq!(
+            verified.attestation.proxy_number.as_str(),
+            response.proxy_number
+        );
+
+        let jti = verified.attestation.jti;
+        issuer.revoke(&jti, Some("test"));
+        assert!(client
+            .check_revocation_status(&jti)
+            .await
+            .unwrap()
+            .is_revoked());
+        let status = client.fetch_status(&jti).await.unwrap();
+        verify_status_response(&status, &issuer.public_key()).unwrap();
+        assert!(matches!(status.status, AttestationStatus::Revoked { .. }));
+        let status = client.fetch_status("unknown").await.unwrap();
+        assert_eq!(status.status, AttestationStatus::Unknown);
+    }
+
+    #[tokio::test]
+    async fn test_failure_modes() {
+        let issuer = MockIssuer::start_with(Behavior {
+            bad_signatures: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let user = generate_keypair().unwrap();
+        let jwt = issuer.issue(&phone(), &user.public).unwrap();
+        assert!(verify_attestation(&jwt).await.is_err());
+
+        issuer.set_behavior(Behavior {
+            expired_attestations: true,
+            ..Default::default()
+        });
+        let jwt = issuer.issue(&phone(), &user.public).unwrap();
+        assert!(verify_attestation(&jwt).await.is_err());
+
+        issuer.set_behavior(Behavior {
+            latency: Duration::from_millis(200),
+            fail_with: Some(ErrorCode::Maintenance),
+            ..Default::default()
+        });
+        let client = IssuerClient::new_insecure(&issuer.url()).unwrap();
+        let started = Instant::now();
+        let error = client.health().await.unwrap_err();
+        assert_eq!(
+            error.problem().and_then(Problem::code),
+            Some(ErrorCode::Maintenance)
+        );
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_key_rotation() {
+        let issuer = MockIssuer::start_with(Behavior {
+            rotate_keys_every: Some(1),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let user = generate_keypair().unwrap();
+        let first = issuer.issue(&phone(), &user.public).unwrap();
+        assert_eq!(issuer.key_id(), "key-2");
+        let second = issuer.issue(&phone(), &user.public).unwrap();
+        assert_eq!(issuer.key_id(), "key-3");
+        assert_eq!(issuer.attestations_issued(), 2);
+
+        // Earlier keys stay published, until they're forgotten
+        verify_attestation(&first).await.unwrap();
+        verify_attestation(&second).await.unwrap();
+        issuer.set_behavior(Behavior {
+            forget_previous_keys: true,
+            ..Default::default()
+        });
+        assert!(verify_attestation(&first).await.is_err());
+        assert_eq!(issuer.rotate_key().unwrap(), "key-4");
+        assert!(verify_attestation(&second).await.is_err());
+    }
+}
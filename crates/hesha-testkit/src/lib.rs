@@ -0,0 +1,38 @@
+//! Mock Hesha issuer for integration tests.
+//!
+//! [`MockIssuer`] serves the issuer endpoints a relying party or wallet
+//! talks to (key discovery, `/attest`, revocations, status, health) from
+//! the test process, on an ephemeral loopback port. Its [`Behavior`]
+//! injects the failures integrations must survive: slow responses,
+//! attestations with bad signatures or already expired, key rotations and
+//! outright errors.
+//!
+//! ```no_run
+//! use hesha_testkit::{Behavior, MockIssuer};
+//! use hesha_types::PhoneNumber;
+//!
+//! # async fn example() {
+//! let issuer = MockIssuer::start_with(Behavior {
+//!     expired_attestations: true,
+//!     ..Default::default()
+//! })
+//! .await
+//! .unwrap();
+//! let user = hesha_crypto::generate_keypair().unwrap();
+//! let jwt = issuer.issue(&PhoneNumber::new("+14155552671").unwrap(), &user.public).unwrap();
+//! assert!(hesha_core::verify_attestation(&jwt).await.is_err());
+//! # }
+//! ```
+//!
+//! The issuer's domain is its `127.0.0.1:PORT` address, which key discovery
+//! reaches over plain HTTP, so attestations it issues verify through the
+//! usual [`hesha_core::verify_attestation`].
+
+#![warn(missing_docs)]
+#![forbid(unsafe_code)]
+
+pub mod behavior;
+pub mod issuer;
+
+pub use behavior::Behavior;
+pub use issuer::MockIssuer;
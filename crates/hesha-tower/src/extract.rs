@@ -0,0 +1,83 @@
+//! The [`ProxyIdentity`] extractor.
+
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::{header, request::Parts, StatusCode};
+use axum::response::{IntoResponse, Response};
+use hesha_core::verification::request_auth::{AuthError, VerifiedProxyIdentity};
+use hesha_types::{ErrorCode, Problem, PROBLEM_CONTENT_TYPE};
+
+/// The proxy identity the request was authenticated as by
+/// [`HeshaAuthLayer`](crate::HeshaAuthLayer).
+///
+/// Refuses requests the layer let through unauthenticated; take an
+/// `Option<ProxyIdentity>` on routes open to anonymous requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyIdentity(pub VerifiedProxyIdentity);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ProxyIdentity {
+    type Rejection = AuthRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<VerifiedProxyIdentity>()
+            .cloned()
+            .map(ProxyIdentity)
+            .ok_or_else(|| AuthRejection(Box::new(Problem::new(ErrorCode::Unauthorized, "Request is not authenticated"))))
+    }
+}
+
+/// A refused request, answered as an RFC 7807 problem document.
+#[derive(Debug)]
+pub struct AuthRejection(pub Box<Problem>);
+
+impl From<AuthError> for AuthRejection {
+    fn from(error: AuthError) -> Self {
+        Self(Box::new(error.problem()))
+    }
+}
+
+impl IntoResponse for AuthRejection {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.0.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (
+            status,
+            [(header::CONTENT_TYPE, PROBLEM_CONTENT_TYPE)],
+            serde_json::to_vec(&self.0).unwrap_or_default(),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{read, Fixture};
+    use axum::{routing::get, Router};
+    use tower::ServiceExt;
+
+    async fn me(identity: Option<ProxyIdentity>) -> String {
+        identity.map_or("anonymous".to_string(), |ProxyIdentity(identity)| identity.attestation_id)
+    }
+
+    #[tokio::test]
+    async fn test_unauthenticated() {
+        let fixture = Fixture::new();
+        let app = Router::new()
+            .route("/me", get(me))
+            .route("/private", get(|ProxyIdentity(identity): ProxyIdentity| async move { identity.issuer }));
+
+        // Without the layer, nothing is authenticated
+        let (status, body) = read(app.clone().oneshot(fixture.request("/me")).await.unwrap()).await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "anonymous"));
+
+        let response = app.oneshot(fixture.request("/private")).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], PROBLEM_CONTENT_TYPE);
+        let (status, body) = read(response).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let problem: Problem = serde_json::from_str(&body).unwrap();
+        assert_eq!(problem.code(), Some(ErrorCode::Unauthorized));
+    }
+}
@@ -0,0 +1,188 @@
+//! The authenticating middleware.
+
+use crate::extract::AuthRejection;
+use axum::body::Body;
+use axum::http::{HeaderMap, Request};
+use axum::response::{IntoResponse, Response};
+use hesha_core::verification::request_auth::{
+    AuthError, RequestAuthenticator, ATTESTATION_HEADER, CHALLENGE_RESPONSE_HEADER,
+};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Authenticates every request before passing it on, adding the
+/// [`VerifiedProxyIdentity`](crate::VerifiedProxyIdentity) it proves to the
+/// request's extensions.
+///
+/// Requests that aren't authenticated are refused, unless the layer is
+/// [`optional`](HeshaAuthLayer::optional).
+#[derive(Clone)]
+pub struct HeshaAuthLayer {
+    authenticator: Arc<RequestAuthenticator>,
+    optional: bool,
+}
+
+impl HeshaAuthLayer {
+    /// Refuse requests `authenticator` doesn't authenticate.
+    pub fn new(authenticator: impl Into<Arc<RequestAuthenticator>>) -> Self {
+        Self {
+            authenticator: authenticator.into(),
+            optional: false,
+        }
+    }
+
+    /// Pass requests without Hesha headers on unauthenticated. Requests
+    /// whose headers don't verify are still refused.
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+}
+
+impl<S> Layer<S> for HeshaAuthLayer {
+    type Service = HeshaAuth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HeshaAuth {
+            inner,
+            authenticator: self.authenticator.clone(),
+            optional: self.optional,
+        }
+    }
+}
+
+/// Service built by [`HeshaAuthLayer`].
+#[derive(Clone)]
+pub struct HeshaAuth<S> {
+    inner: S,
+    authenticator: Arc<RequestAuthenticator>,
+    optional: bool,
+}
+
+impl<S> Service<Request<Body>> for HeshaAuth<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        // Call the instance that was polled ready, leaving a fresh clone
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let authenticator = self.authenticator.clone();
+        let optional = self.optional;
+
+        Box::pin(async move {
+            let headers = request.headers();
+            let (attestation, response) = match (
+                header(headers, ATTESTATION_HEADER),
+                header(headers, CHALLENGE_RESPONSE_HEADER),
+            ) {
+                (Ok(None), Ok(None)) if optional => return inner.call(request).await,
+                (Ok(attestation), Ok(response)) => (attestation, response),
+                (Err(e), _) | (_, Err(e)) => return Ok(AuthRejection::from(e).into_response()),
+            };
+
+            match authenticator.authenticate(attestation, response).await {
+                Ok(identity) => {
+                    request.extensions_mut().insert(identity);
+                    inner.call(request).await
+                }
+                Err(e) => {
+                    tracing::debug!("Refused request to {}: {}", request.uri().path(), e);
+                    Ok(AuthRejection::from(e).into_response())
+                }
+            }
+        })
+    }
+}
+
+/// The value of the header `name`, if sent.
+fn header<'a>(headers: &'a HeaderMap, name: &'static str) -> Result<Option<&'a str>, AuthError> {
+    headers
+        .get(name)
+        .map(|value| {
+            value.to_str().map_err(|_| AuthError::MalformedHeader {
+                header: name,
+                reason: "not visible ASCII".to_string(),
+            })
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{read, Fixture};
+    use crate::ProxyIdentity;
+    use axum::http::StatusCode;
+    use axum::{routing::get, Router};
+    use hesha_types::{ErrorCode, Problem};
+    use tower::ServiceExt;
+
+    async fn me(ProxyIdentity(identity): ProxyIdentity) -> String {
+        identity.proxy_number.as_str().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_layer() {
+        let fixture = Fixture::new();
+        let request = fixture.request("/me");
+        let app = Router::new().route("/me", get(me)).layer(HeshaAuthLayer::new(fixture.authenticator));
+
+        let (status, body) = read(app.clone().oneshot(request).await.unwrap()).await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "+23400123456789"));
+
+        let anonymous = Request::get("/me").body(Body::empty()).unwrap();
+        let (status, body) = read(app.oneshot(anonymous).await.unwrap()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let problem: Problem = serde_json::from_str(&body).unwrap();
+        assert_eq!(problem.code(), Some(ErrorCode::Unauthorized));
+        assert!(problem.detail.unwrap().contains(ATTESTATION_HEADER));
+    }
+
+    #[tokio::test]
+    async fn test_replayed_request() {
+        let fixture = Fixture::new();
+        let request = fixture.request("/me");
+        let replay = Request::get("/me")
+            .header(ATTESTATION_HEADER, request.headers()[ATTESTATION_HEADER].clone())
+            .header(CHALLENGE_RESPONSE_HEADER, request.headers()[CHALLENGE_RESPONSE_HEADER].clone())
+            .body(Body::empty())
+            .unwrap();
+        let app = Router::new().route("/me", get(me)).layer(HeshaAuthLayer::new(fixture.authenticator));
+
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(app.oneshot(replay).await.unwrap().status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_optional_layer() {
+        let fixture = Fixture::new();
+        let request = fixture.request("/me");
+        let app = Router::new()
+            .route("/me", get(|identity: Option<ProxyIdentity>| async move { identity.is_some().to_string() }))
+            .layer(HeshaAuthLayer::new(fixture.authenticator).optional());
+
+        let (status, body) = read(app.clone().oneshot(request).await.unwrap()).await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "true"));
+
+        let anonymous = Request::get("/me").body(Body::empty()).unwrap();
+        let (status, body) = read(app.clone().oneshot(anonymous).await.unwrap()).await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "false"));
+
+        // Credentials that don't verify are refused all the same
+        let forged = Request::get("/me").header(ATTESTATION_HEADER, "not.a.jwt").body(Body::empty()).unwrap();
+        assert_eq!(app.oneshot(forged).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,93 @@
+//! Proxy-number authentication for tower and axum services.
+//!
+//! Clients send their attestation and a signed challenge response with
+//! each request (see [`hesha_core::verification::request_auth`]).
+//! [`HeshaAuthLayer`] checks them and hands handlers the
+//! [`VerifiedProxyIdentity`] they prove through the [`ProxyIdentity`]
+//! extractor:
+//!
+//! ```no_run
+//! use axum::{routing::get, Router};
+//! use hesha_core::{RequestAuthenticator, VerificationPolicy};
+//! use hesha_tower::{HeshaAuthLayer, ProxyIdentity};
+//!
+//! async fn me(ProxyIdentity(identity): ProxyIdentity) -> String {
+//!     identity.proxy_number.as_str().to_string()
+//! }
+//!
+//! let policy = VerificationPolicy {
+//!     allowed_issuers: vec!["issuer.example.com".to_string()],
+//!     ..Default::default()
+//! };
+//! let auth = RequestAuthenticator::new("app.example.com").with_policy(policy);
+//! let app: Router = Router::new().route("/me", get(me)).layer(HeshaAuthLayer::new(auth));
+//! ```
+//!
+//! Refused requests are answered with an RFC 7807 problem document.
+
+#![warn(missing_docs)]
+#![forbid(unsafe_code)]
+
+pub mod extract;
+pub mod layer;
+
+pub use extract::{AuthRejection, ProxyIdentity};
+pub use hesha_core::verification::request_auth::{AuthError, RequestAuthenticator, VerifiedProxyIdentity};
+pub use layer::{HeshaAuth, HeshaAuthLayer};
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::response::Response;
+    use hesha_core::verification::{challenge_response_header, ATTESTATION_HEADER, CHALLENGE_RESPONSE_HEADER};
+    use hesha_core::{create_attestation, parse_attestation, RequestAuthenticator, TrustBundle};
+    use hesha_crypto::generate_keypair;
+    use hesha_types::{KeyPair, PhoneNumber, ProxyNumber};
+
+    /// A client holding an attestation, and an authenticator trusting its
+    /// issuer.
+    pub(crate) struct Fixture {
+        pub(crate) user: KeyPair,
+        pub(crate) jwt: String,
+        pub(crate) jti: String,
+        pub(crate) authenticator: RequestAuthenticator,
+    }
+
+    impl Fixture {
+        pub(crate) fn new() -> Self {
+            let issuer = generate_keypair().unwrap();
+            let user = generate_keypair().unwrap();
+            let jwt = create_attestation(
+                "issuer.example.com",
+                &issuer.private,
+                &PhoneNumber::new("+2348012345678").unwrap(),
+                &ProxyNumber::new("+23400123456789").unwrap(),
+                &user.public,
+            )
+            .unwrap();
+            let jti = parse_attestation(&jwt).unwrap().jti;
+            let mut bundle = TrustBundle::new();
+            bundle.add("issuer.example.com", issuer.public, None);
+            let authenticator = RequestAuthenticator::new("app.example.com").with_trust_bundle(bundle);
+            Self { user, jwt, jti, authenticator }
+        }
+
+        /// A request to `uri` carrying the attestation and a fresh challenge
+        /// response.
+        pub(crate) fn request(&self, uri: &str) -> Request<Body> {
+            let response = challenge_response_header(&self.jti, &self.user.private, "app.example.com").unwrap();
+            Request::get(uri)
+                .header(ATTESTATION_HEADER, &self.jwt)
+                .header(CHALLENGE_RESPONSE_HEADER, response)
+                .body(Body::empty())
+                .unwrap()
+        }
+    }
+
+    pub(crate) async fn read(response: Response) -> (StatusCode, String) {
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+}
@@ -6,6 +6,37 @@ use crate::{
 };
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Extension claim naming how the issuer verified the phone number.
+pub const VERIFICATION_METHOD_EXT: &str = "verification_method";
+
+/// Longest validity an attestation may be requested or issued for, in days.
+pub const MAX_VALIDITY_DAYS: i64 = 730;
+
+/// How an issuer confirmed control of a phone number before attesting it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationMethod {
+    /// A code texted to the number.
+    Sms,
+    /// A code emailed to an address the issuer has on record for the
+    /// number, for lines that can't receive SMS.
+    Email,
+    /// The carrier recognized the line from its network.
+    Carrier,
+}
+
+impl VerificationMethod {
+    /// Name used in the [`VERIFICATION_METHOD_EXT`] claim.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sms => "sms",
+            Self::Email => "email",
+            Self::Carrier => "carrier",
+        }
+    }
+}
 
 /// JWT attestation claims.
 /// 
@@ -47,6 +78,10 @@ pub struct Attestation {
     
     /// Nonce for replay protection.
     pub nonce: Nonce,
+    
+    /// Extension claims, such as [`VERIFICATION_METHOD_EXT`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub ext: BTreeMap<String, serde_json::Value>,
 }
 
 impl Attestation {
@@ -65,6 +100,13 @@ impl Attestation {
     pub fn effective_trust_domain(&self) -> &str {
         self.trust_domain.as_deref().unwrap_or(&self.iss)
     }
+    
+    /// How the issuer verified the phone number, if it said.
+    pub fn verification_method(&self) -> Option<VerificationMethod> {
+        self.ext
+            .get(VERIFICATION_METHOD_EXT)
+            .and_then(|method| serde_json::from_value(method.clone()).ok())
+    }
 }
 
 /// Challenge sent by a service for verification.
@@ -80,6 +122,21 @@ pub struct Challenge {
     pub timestamp: DateTime<Utc>,
 }
 
+impl Challenge {
+    /// Bytes the user signs to answer the challenge: nonce, service context
+    /// and timestamp concatenated, the timestamp in RFC 3339 with whole
+    /// seconds and a `Z` offset.
+    pub fn signing_message(&self) -> Vec<u8> {
+        format!(
+            "{}{}{}",
+            self.nonce.as_str(),
+            self.service_context,
+            self.timestamp.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        )
+        .into_bytes()
+    }
+}
+
 /// User's response to a challenge.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChallengeResponse {
@@ -111,6 +168,47 @@ pub struct IssuerInfo {
     /// Service discovery information for subdomain deployments.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub service_info: Option<ServiceDiscovery>,
+    
+    /// Every key attestations may currently be signed with: the key above
+    /// and previous keys still inside their validity window.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<IssuerKey>,
+}
+
+impl IssuerInfo {
+    /// Public key for the `kid` header of an attestation.
+    /// 
+    /// Attestations without a `kid` are checked against the top-level key,
+    /// as are those naming it.
+    pub fn key(&self, key_id: Option<&str>) -> Option<&PublicKey> {
+        match key_id {
+            None => Some(&self.public_key),
+            Some(key_id) if self.key_id.as_deref() == Some(key_id) => Some(&self.public_key),
+            Some(key_id) => self.keys.iter()
+                .find(|key| key.key_id == key_id)
+                .map(|key| &key.public_key),
+        }
+    }
+}
+
+/// A published issuer key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuerKey {
+    /// Key identifier, matched against the JWT `kid` header.
+    pub key_id: String,
+    
+    /// Ed25519 public key.
+    pub public_key: PublicKey,
+    
+    /// Algorithm (always "Ed25519" for now).
+    pub algorithm: String,
+    
+    /// When the key was created.
+    pub valid_from: DateTime<Utc>,
+    
+    /// When a retired key stops being published.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<DateTime<Utc>>,
 }
 
 /// Service discovery information for trust domain delegation.
@@ -180,6 +278,7 @@ mod tests {
             salt: vec![0u8; 16],
             jti: "test-jti".to_string(),
             nonce: Nonce::new("test-nonce"),
+            ext: BTreeMap::new(),
         };
         
         assert!(attestation.is_expired());
@@ -199,6 +298,7 @@ mod tests {
             salt: vec![3u8; 16],
             jti: "unique-id".to_string(),
             nonce: Nonce::new("random-nonce"),
+            ext: BTreeMap::from([(VERIFICATION_METHOD_EXT.to_string(), "email".into())]),
         };
         
         let json = serde_json::to_string(&attestation).unwrap();
@@ -206,5 +306,6 @@ mod tests {
         
         assert_eq!(attestation.jti, decoded.jti);
         assert_eq!(attestation.proxy_number, decoded.proxy_number);
+        assert_eq!(decoded.verification_method(), Some(VerificationMethod::Email));
     }
 }
\ No newline at end of file
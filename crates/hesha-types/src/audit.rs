@@ -0,0 +1,139 @@
+//! Audit log types.
+
+use crate::crypto::{PublicKey, Signature};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// `prev_hash` of the first entry in a log.
+pub const AUDIT_GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// One record in an issuer's append-only audit log.
+///
+/// Entries are chained: each carries the hash of the one before, and the
+/// issuer signs each entry's hash. Editing, dropping or reordering entries
+/// breaks the chain, and forging new ones needs the issuer key. Like
+/// webhook events, entries never contain phone numbers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the log, starting at 1.
+    pub seq: u64,
+
+    /// When the event was recorded.
+    pub recorded_at: DateTime<Utc>,
+
+    /// What happened.
+    #[serde(flatten)]
+    pub event: AuditEvent,
+
+    /// Hash of the previous entry, or [`AUDIT_GENESIS_HASH`] for the first.
+    pub prev_hash: String,
+
+    /// Issuer key that signed the entry.
+    pub signer: PublicKey,
+
+    /// Hex SHA-256 of [`chain_payload`](Self::chain_payload).
+    pub hash: String,
+
+    /// Signer's Ed25519 signature over the hex `hash`.
+    pub signature: Signature,
+}
+
+impl AuditEntry {
+    /// Bytes covered by `hash`: the JSON encoding of every other field
+    /// except the signature.
+    pub fn chain_payload(&self) -> Vec<u8> {
+        #[derive(Serialize)]
+        struct Unsealed<'a> {
+            seq: u64,
+            recorded_at: &'a DateTime<Utc>,
+            #[serde(flatten)]
+            event: &'a AuditEvent,
+            prev_hash: &'a str,
+            signer: &'a PublicKey,
+        }
+        serde_json::to_vec(&Unsealed {
+            seq: self.seq,
+            recorded_at: &self.recorded_at,
+            event: &self.event,
+            prev_hash: &self.prev_hash,
+            signer: &self.signer,
+        })
+        .unwrap_or_default()
+    }
+}
+
+/// Security-relevant event, serialized as `type` and `data`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum AuditEvent {
+    /// An attestation was issued.
+    #[serde(rename = "attestation.issued")]
+    AttestationIssued {
+        /// JWT ID of the attestation.
+        jti: String,
+        /// Scope the proxy number was generated for.
+        scope: String,
+        /// Authenticated caller that requested it.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        caller: Option<String>,
+        /// JWT ID of the attestation it replaces, for renewals.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        renews: Option<String>,
+        /// Hash of the receipt returned with it.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        receipt: Option<String>,
+    },
+
+    /// An attestation request was refused on issuance policy grounds.
+    #[serde(rename = "attestation.denied")]
+    AttestationDenied {
+        /// Error code returned.
+        error: String,
+        /// Scope requested.
+        scope: String,
+        /// Authenticated caller that requested it.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        caller: Option<String>,
+        /// Hash of the receipt returned with the refusal.
+        receipt: String,
+    },
+
+    /// An attestation was revoked.
+    #[serde(rename = "attestation.revoked")]
+    AttestationRevoked {
+        /// JWT ID of the revoked attestation.
+        jti: String,
+        /// Reason published with the revocation.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+
+    /// An operator used an admin endpoint.
+    #[serde(rename = "admin.action")]
+    AdminAction {
+        /// What was done, e.g. `revoke` or `audit.export`.
+        action: String,
+        /// Address the request came from.
+        peer: String,
+    },
+
+    /// The node started signing with a different issuer key.
+    #[serde(rename = "key.rotated")]
+    KeyRotated {
+        /// Key that signed the previous entry.
+        previous: PublicKey,
+        /// Key now in use.
+        current: PublicKey,
+    },
+
+    /// A request was refused for missing or invalid credentials.
+    #[serde(rename = "auth.failed")]
+    AuthFailed {
+        /// Error code returned, e.g. `unauthorized`.
+        error: String,
+        /// Why the credentials were refused.
+        reason: String,
+        /// Request path.
+        path: String,
+    },
+}
@@ -0,0 +1,24 @@
+//! Issuer node record backups.
+
+use serde::{Deserialize, Serialize};
+
+/// Records added to a node by a restore.
+///
+/// Returned by `/admin/restore`. Records the node already held are kept as
+/// they are and not counted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestoreSummary {
+    /// Attestations added.
+    pub attestations: u64,
+
+    /// Proxy number assignments added or confirmed.
+    pub proxy_numbers: u64,
+
+    /// Revocations added.
+    pub revocations: u64,
+
+    /// Proxy numbers the node had already assigned to a different phone;
+    /// the node's assignment is kept.
+    #[serde(default)]
+    pub proxy_conflicts: u64,
+}
@@ -0,0 +1,161 @@
+//! Messages of the issuer node's local admin console.
+//!
+//! The node listens on a Unix domain socket next to its PID file. Each
+//! connection sends one [`ControlRequest`] as a line of JSON and reads one
+//! [`ControlResponse`] line back. Access is governed by the socket file's
+//! permissions, so the admin API never has to be reachable over the network.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// File name of the console socket inside an issuer directory.
+pub const CONTROL_SOCKET_FILE: &str = "issuer.sock";
+
+/// Named pipe a node on Windows listens on for stop requests.
+///
+/// Windows has no SIGTERM, and console control events can't reach a node
+/// started detached, so `hesha stop` writes to this pipe instead.
+pub fn stop_pipe_name(pid: u32) -> String {
+    format!(r"\\.\pipe\hesha-issuer-{}", pid)
+}
+
+/// A command for the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlRequest {
+    /// Report what the node is running with.
+    Status,
+    /// Re-read the current and previous keys from issuer.toml and the key
+    /// files, e.g. after `hesha setup rotate`.
+    Reload,
+    /// Generate a new signing key, keep the old one published for
+    /// `overlap_days` (default: the attestation validity) and sign with the
+    /// new key from now on.
+    RotateKey {
+        /// Days to keep publishing the old key.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        overlap_days: Option<i64>,
+    },
+    /// Sign with another key the node holds, e.g. to switch back to the
+    /// previous key after a rotation, without a restart.
+    ActivateKey {
+        /// Identifier of the key, as published in the key listing.
+        key_id: String,
+    },
+    /// Report unhealthy for `grace_secs` so load balancers stop sending
+    /// traffic, then finish in-flight requests and exit.
+    Drain {
+        /// Seconds to keep serving while reporting unhealthy.
+        #[serde(default)]
+        grace_secs: u64,
+    },
+    /// Turn maintenance mode on or off for every replica sharing state.
+    /// While on, the attest and verify endpoints answer 503; `.well-known`
+    /// documents and `/health` keep being served.
+    Maintenance {
+        /// Whether attestation requests are refused.
+        enabled: bool,
+        /// Note for operators on why, shown in the status.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+        /// Seconds clients are told to wait before retrying.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retry_after_secs: Option<u64>,
+    },
+}
+
+/// The node's answer to a [`ControlRequest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum ControlResponse {
+    /// Answer to [`ControlRequest::Status`].
+    Status(NodeStatus),
+    /// The keys now held, after a reload, rotation or activation.
+    Keys(KeysStatus),
+    /// The node is draining and will exit.
+    Draining {
+        /// Seconds until in-flight requests are finished and the node exits.
+        grace_secs: u64,
+    },
+    /// Maintenance mode after a change.
+    Maintenance(MaintenanceStatus),
+    /// The command failed.
+    Error {
+        /// Error code (e.g. "reload_failed").
+        error: String,
+        /// Human-readable explanation.
+        error_description: String,
+    },
+}
+
+/// What a running node is serving with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeStatus {
+    /// Node software version.
+    pub version: String,
+    /// Process ID.
+    pub pid: u32,
+    /// When the node started.
+    pub started_at: DateTime<Utc>,
+    /// Address the HTTP API listens on.
+    pub bind_address: String,
+    /// Identifier of the active signing key.
+    pub key_id: String,
+    /// Number of published keys, the active one included.
+    pub published_keys: usize,
+    /// Attestations issued since start.
+    pub attestations_issued: u64,
+    /// Whether the node is draining.
+    pub draining: bool,
+    /// Whether attestation requests are refused for maintenance.
+    #[serde(default)]
+    pub maintenance: bool,
+}
+
+/// Whether maintenance mode is on, and since when.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceStatus {
+    /// Whether attestation requests are refused.
+    pub enabled: bool,
+    /// Note for operators on why.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Seconds clients are told to wait before retrying.
+    pub retry_after_secs: u64,
+    /// When maintenance started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+}
+
+/// Keys held after a reload, rotation or activation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeysStatus {
+    /// Identifier of the active signing key.
+    pub key_id: String,
+    /// Number of published keys, the active one included.
+    pub published_keys: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_serialization() {
+        let request: ControlRequest = serde_json::from_str(r#"{"command":"drain"}"#).unwrap();
+        assert_eq!(request, ControlRequest::Drain { grace_secs: 0 });
+        let json = serde_json::to_value(ControlRequest::RotateKey { overlap_days: Some(30) }).unwrap();
+        assert_eq!(json, serde_json::json!({"command": "rotate_key", "overlap_days": 30}));
+        let request: ControlRequest = serde_json::from_str(r#"{"command":"activate_key","key_id":"key-1"}"#).unwrap();
+        assert_eq!(request, ControlRequest::ActivateKey { key_id: "key-1".to_string() });
+
+        let response = ControlResponse::Keys(KeysStatus {
+            key_id: "key-2".to_string(),
+            published_keys: 2,
+        });
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["result"], "keys");
+        assert_eq!(json["key_id"], "key-2");
+        assert_eq!(serde_json::from_value::<ControlResponse>(json).unwrap(), response);
+    }
+}
@@ -52,6 +52,19 @@ pub enum HeshaError {
     /// Configuration error.
     #[error("Configuration error: {0}")]
     ConfigError(String),
+    
+    /// Network request failed (e.g., during key discovery).
+    #[error("Network error: {0}")]
+    NetworkError(String),
+    
+    /// An audit log entry failed verification.
+    #[error("Audit log entry {seq} is invalid: {reason}")]
+    InvalidAuditLog {
+        /// Sequence number of the first bad entry.
+        seq: u64,
+        /// What is wrong with it.
+        reason: String,
+    },
 }
 
 impl From<serde_json::Error> for HeshaError {
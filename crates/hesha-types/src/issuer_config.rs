@@ -1,6 +1,10 @@
 //! Issuer configuration types for the Hesha protocol.
 
+use crate::webhook::WebhookEventKind;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 
 /// Complete issuer configuration focused on core protocol requirements.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +19,63 @@ pub struct IssuerConfig {
     /// Attestation validity in days.
     #[serde(default = "default_attestation_validity")]
     pub attestation_validity_days: u32,
+    
+    /// Node settings that can also be given as flags or `HESHA_*` variables.
+    #[serde(default, skip_serializing_if = "NodeSettings::is_empty")]
+    pub node: NodeSettings,
+    
+    /// Retired keys still published so attestations they signed keep verifying.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub previous_keys: Vec<PreviousKey>,
+    
+    /// How the node verifies phone numbers for `/attest/simple`.
+    #[serde(default, skip_serializing_if = "VerificationConfig::is_mock")]
+    pub verification: VerificationConfig,
+    
+    /// How callers of `/attest` authenticate.
+    #[serde(default, skip_serializing_if = "AuthConfig::is_none")]
+    pub auth: AuthConfig,
+    
+    /// How operators authenticate to the `/admin` routes; unset, those
+    /// routes are refused and only the control socket administers the node.
+    #[serde(default, skip_serializing_if = "AuthConfig::is_none")]
+    pub admin_auth: AuthConfig,
+    
+    /// Partners whose attest requests must carry an HMAC signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_signing: Option<RequestSigningConfig>,
+    
+    /// Browser origins allowed to call the node; none when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors: Option<CorsConfig>,
+    
+    /// Serve HTTPS directly instead of behind a reverse proxy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+    
+    /// Obtain and renew the HTTPS certificate automatically over ACME.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acme: Option<AcmeConfig>,
+    
+    /// Endpoints notified of issuance, revocation and verification events.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<WebhookConfig>,
+    
+    /// Which scopes may be requested, and for how long.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<ScopePolicyConfig>,
+    
+    /// CAPTCHA required before sending verification codes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub captcha: Option<CaptchaConfig>,
+    
+    /// Networks allowed to reach each group of routes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_access: Option<IpAccessConfig>,
+    
+    /// Relying parties the node serves as an OpenID provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oidc_provider: Option<OidcProviderConfig>,
 }
 
 /// Issuer identity information.
@@ -39,10 +100,602 @@ pub struct IssuerIdentity {
     pub created_at: String,
 }
 
+/// A retired issuer key kept in the key listing during a rotation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviousKey {
+    /// Key identifier.
+    pub key_id: String,
+    
+    /// Ed25519 public key in base64url format.
+    pub public_key_base64url: String,
+    
+    /// When the key was created.
+    pub created_at: String,
+    
+    /// When the key stopped being used for signing.
+    pub retired_at: String,
+    
+    /// When the key should stop being published (RFC 3339).
+    pub valid_until: String,
+}
+
+/// Phone verification provider, from the `[verification]` table.
+/// 
+/// ```toml
+/// [verification]
+/// provider = "twilio"
+/// account_sid = "AC..."
+/// service_sid = "VA..."
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum VerificationConfig {
+    /// Development verifier: sends nothing and accepts a fixed code.
+    #[default]
+    Mock,
+    
+    /// Twilio Verify.
+    Twilio {
+        /// Twilio account SID.
+        account_sid: String,
+        
+        /// Verify service SID.
+        service_sid: String,
+        
+        /// Environment variable holding the auth token, which is kept out
+        /// of the config file.
+        #[serde(default = "default_twilio_auth_token_env")]
+        auth_token_env: String,
+    },
+    
+    /// Vonage (formerly Nexmo) Verify.
+    Vonage {
+        /// Vonage API key.
+        api_key: String,
+        
+        /// Name shown to the user in the SMS (e.g., "Acme").
+        brand: String,
+        
+        /// Environment variable holding the API secret.
+        #[serde(default = "default_vonage_api_secret_env")]
+        api_secret_env: String,
+    },
+    
+    /// MessageBird Verify.
+    MessageBird {
+        /// Default sender ID shown to the user (up to 11 characters).
+        originator: String,
+        
+        /// Sender IDs by calling-code prefix (e.g., "234" for Nigeria),
+        /// for countries that require registered local sender IDs. The
+        /// longest matching prefix wins.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        regional_originators: BTreeMap<String, String>,
+        
+        /// Environment variable holding the access key.
+        #[serde(default = "default_messagebird_access_key_env")]
+        access_key_env: String,
+    },
+    
+    /// Carrier number verification (GSMA Number Verify / CAMARA style).
+    /// 
+    /// The phone opens the carrier's authorization URL over mobile data;
+    /// the carrier recognizes the line from the network and redirects to
+    /// the node's `/verify/callback` with a token, so no SMS is sent.
+    Carrier {
+        /// Carrier OAuth authorization endpoint.
+        authorize_url: String,
+        
+        /// Carrier OAuth token endpoint.
+        token_url: String,
+        
+        /// Number verification API endpoint.
+        verify_url: String,
+        
+        /// OAuth client ID registered with the carrier.
+        client_id: String,
+        
+        /// This node's `/verify/callback` URL, as registered with the carrier.
+        redirect_url: String,
+        
+        /// Environment variable holding the OAuth client secret.
+        #[serde(default = "default_carrier_client_secret_env")]
+        client_secret_env: String,
+    },
+    
+    /// One-time codes emailed to an address the operator registered for
+    /// the number, for landlines and SIP trunks that can't receive SMS.
+    /// 
+    /// Numbers without a registered address are refused, so put this
+    /// before an SMS provider in a `failover` list.
+    Email {
+        /// SMTP relay host.
+        smtp_host: String,
+        
+        /// SMTP port, using STARTTLS.
+        #[serde(default = "default_smtp_port")]
+        smtp_port: u16,
+        
+        /// SMTP user name, if the relay requires authentication.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        smtp_username: Option<String>,
+        
+        /// Environment variable holding the SMTP password.
+        #[serde(default = "default_smtp_password_env")]
+        smtp_password_env: String,
+        
+        /// Sender address (e.g., "Acme <verify@acme.example>").
+        from: String,
+        
+        /// Contact addresses by phone number (E.164).
+        contacts: BTreeMap<String, String>,
+    },
+    
+    /// Several providers, each tried in turn when sending a code fails.
+    Failover {
+        /// Providers in order of preference.
+        providers: Vec<VerificationConfig>,
+    },
+}
+
+impl VerificationConfig {
+    /// Whether this is the development verifier.
+    pub fn is_mock(&self) -> bool {
+        matches!(self, Self::Mock)
+    }
+}
+
+/// Authentication required of `/attest` callers, configured as `[auth]`.
+/// 
+/// `/attest` trusts the phone number in the request, so an issuer that
+/// exposes it should only accept it from its own backends:
+/// 
+/// ```toml
+/// [auth]
+/// method = "oidc"
+/// issuer = "https://login.example.com/"
+/// audience = "hesha-issuer"
+/// jwks_url = "https://login.example.com/.well-known/jwks.json"
+/// ```
+/// 
+/// The same methods configure `[admin_auth]`, which operators' tools use
+/// for the `/admin` routes:
+/// 
+/// ```toml
+/// [admin_auth]
+/// method = "api_key"
+/// keys_env = "HESHA_ADMIN_KEYS"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum AuthConfig {
+    /// Anyone may call `/attest`.
+    #[default]
+    None,
+    
+    /// Static API keys, sent as bearer tokens.
+    ApiKey {
+        /// Environment variable holding the accepted keys, comma-separated.
+        #[serde(default = "default_api_keys_env")]
+        keys_env: String,
+    },
+    
+    /// JWT bearer tokens from an OpenID Connect provider.
+    Oidc {
+        /// Expected `iss` claim.
+        issuer: String,
+        
+        /// Expected `aud` claim.
+        audience: String,
+        
+        /// Where the provider publishes its signing keys.
+        jwks_url: String,
+    },
+}
+
+impl AuthConfig {
+    /// Whether no credentials are configured.
+    pub fn is_none(&self) -> bool {
+        matches!(self, Self::None)
+    }
+}
+
+/// HMAC request signing on the attest endpoints, configured as:
+/// 
+/// ```toml
+/// [request_signing]
+/// max_skew_secs = 300
+/// 
+/// [[request_signing.partners]]
+/// id = "acme"
+/// secret_env = "ACME_SIGNING_SECRET"
+/// ```
+/// 
+/// When set, every attest request must be signed by one of the partners.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestSigningConfig {
+    /// Partners and where their shared secrets are read from.
+    pub partners: Vec<SigningPartner>,
+    
+    /// How far a signature's timestamp may be from the node's clock.
+    #[serde(default = "default_max_skew_secs")]
+    pub max_skew_secs: u64,
+}
+
+/// A partner allowed to sign requests.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningPartner {
+    /// Partner ID sent in the signature header.
+    pub id: String,
+    
+    /// Environment variable holding the shared secret.
+    pub secret_env: String,
+}
+
+/// Cross-origin access for browser apps, configured as:
+/// 
+/// ```toml
+/// [cors]
+/// allowed_origins = ["https://app.example.com"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorsConfig {
+    /// Origins allowed to make requests, or `["*"]` for any.
+    pub allowed_origins: Vec<String>,
+    
+    /// Methods allowed in cross-origin requests.
+    #[serde(default = "default_cors_methods")]
+    pub allowed_methods: Vec<String>,
+    
+    /// Request headers allowed in cross-origin requests.
+    #[serde(default = "default_cors_headers")]
+    pub allowed_headers: Vec<String>,
+    
+    /// How long browsers may cache a preflight answer, in seconds.
+    #[serde(default = "default_cors_max_age_secs")]
+    pub max_age_secs: u64,
+}
+
+/// HTTPS served by the node itself, configured as:
+/// 
+/// ```toml
+/// [tls]
+/// cert_path = "/etc/hesha/tls/fullchain.pem"
+/// key_path = "/etc/hesha/tls/privkey.pem"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// PEM certificate chain, leaf first.
+    pub cert_path: String,
+    
+    /// PEM private key for the certificate.
+    pub key_path: String,
+    
+    /// PEM CA certificates; when set, clients must present a certificate
+    /// signed by one of them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_ca_path: Option<String>,
+}
+
+/// Certificates obtained from an ACME certificate authority such as
+/// Let's Encrypt, configured as:
+/// 
+/// ```toml
+/// [acme]
+/// contact = "ops@example.com"
+/// ```
+/// 
+/// The certificate covers the trust domain unless `domains` is set.
+/// Ownership is proven with HTTP-01 challenges, so `http_port` must be
+/// reachable from the internet as port 80.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AcmeConfig {
+    /// Email the certificate authority sends expiry warnings to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contact: Option<String>,
+    
+    /// Domains on the certificate; the trust domain when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub domains: Vec<String>,
+    
+    /// ACME directory of the certificate authority.
+    #[serde(default = "default_acme_directory_url")]
+    pub directory_url: String,
+    
+    /// Directory holding the account key, certificate and its key.
+    #[serde(default = "default_acme_cache_dir")]
+    pub cache_dir: String,
+    
+    /// Port answering HTTP-01 challenges and redirecting other requests
+    /// to HTTPS.
+    #[serde(default = "default_acme_http_port")]
+    pub http_port: u16,
+    
+    /// Renew when the certificate expires within this many days.
+    #[serde(default = "default_acme_renew_before_days")]
+    pub renew_before_days: u32,
+}
+
+/// A webhook endpoint, configured as a `[[webhooks]]` table:
+/// 
+/// ```toml
+/// [[webhooks]]
+/// url = "https://fraud.example.com/hesha"
+/// events = ["attestation.issued", "attestation.revoked"]
+/// secret_env = "FRAUD_WEBHOOK_SECRET"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// URL events are POSTed to.
+    pub url: String,
+    
+    /// Events to deliver; all events when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<WebhookEventKind>,
+    
+    /// Environment variable holding the HMAC signing secret.
+    #[serde(default = "default_webhook_secret_env")]
+    pub secret_env: String,
+}
+
+impl WebhookConfig {
+    /// Whether this endpoint wants events of a kind.
+    pub fn wants(&self, kind: WebhookEventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+}
+
+/// Issuance policy by scope (calling code), configured as:
+/// 
+/// ```toml
+/// [scopes]
+/// allowed = ["1", "44"]
+/// match_phone_country = true
+/// 
+/// [scopes.validity_days]
+/// "44" = 90
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScopePolicyConfig {
+    /// Scopes that may be requested; any scope when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed: Vec<String>,
+    
+    /// Only issue a scope for phone numbers with that calling code.
+    #[serde(default)]
+    pub match_phone_country: bool,
+    
+    /// Attestation validity by scope, in place of `attestation_validity_days`.
+    /// Requests can't ask for longer.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub validity_days: BTreeMap<String, u32>,
+}
+
+impl ScopePolicyConfig {
+    /// Whether a scope may be requested.
+    pub fn allows(&self, scope: &str) -> bool {
+        self.allowed.is_empty() || self.allowed.iter().any(|allowed| allowed == scope)
+    }
+}
+
+/// CAPTCHA on `/verify/start` and `/attest/simple`, configured as:
+/// 
+/// ```toml
+/// [captcha]
+/// provider = "turnstile"
+/// secret_env = "TURNSTILE_SECRET_KEY"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptchaConfig {
+    /// Service that issued the tokens.
+    pub provider: CaptchaProvider,
+    
+    /// Environment variable holding the provider's secret key.
+    #[serde(default = "default_captcha_secret_env")]
+    pub secret_env: String,
+}
+
+/// CAPTCHA services whose tokens the node can check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptchaProvider {
+    /// Cloudflare Turnstile.
+    Turnstile,
+    /// hCaptcha.
+    Hcaptcha,
+}
+
+/// Networks allowed to reach each group of routes, configured as:
+/// 
+/// ```toml
+/// [ip_access.attest]
+/// allow = ["10.20.0.0/16", "2001:db8::/32"]
+/// deny = ["10.20.99.0/24"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IpAccessConfig {
+    /// `/attest` and `/verify` endpoints.
+    #[serde(default, skip_serializing_if = "IpRules::is_empty")]
+    pub attest: IpRules,
+    
+    /// `/admin` endpoints, on top of their `[admin_auth]` credentials.
+    #[serde(default, skip_serializing_if = "IpRules::is_empty")]
+    pub admin: IpRules,
+    
+    /// `/.well-known/hesha` documents.
+    #[serde(default, skip_serializing_if = "IpRules::is_empty")]
+    pub well_known: IpRules,
+}
+
+/// The node as an OpenID provider vouching for proxy numbers, configured
+/// as:
+/// 
+/// ```toml
+/// [oidc_provider]
+/// id_token_ttl_secs = 300
+/// 
+/// [[oidc_provider.clients]]
+/// client_id = "forum"
+/// secret_env = "HESHA_OIDC_FORUM_SECRET"
+/// redirect_uris = ["https://forum.example.com/oidc/callback"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OidcProviderConfig {
+    /// Relying parties allowed to request ID tokens.
+    pub clients: Vec<OidcClient>,
+    
+    /// Seconds an ID token is valid, capped by the attestation's expiry.
+    #[serde(default = "default_id_token_ttl_secs")]
+    pub id_token_ttl_secs: u64,
+}
+
+/// A relying party registered with the OpenID provider.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OidcClient {
+    /// Client ID the relying party sends.
+    pub client_id: String,
+    
+    /// Environment variable holding the client secret.
+    pub secret_env: String,
+    
+    /// Where authorization codes may be sent, compared exactly.
+    pub redirect_uris: Vec<String>,
+}
+
+/// Settings of the running node, from the `[node]` table. Each can also be
+/// given as an `issuer-node` flag or `HESHA_*` environment variable, which
+/// win over the file.
+/// 
+/// ```toml
+/// [node]
+/// bind_address = "0.0.0.0:3000"
+/// trust_domain = "example.com"
+/// service_url = "https://api.example.com"
+/// storage_url = "sqlite:/var/lib/hesha/issuer.db"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeSettings {
+    /// Address to listen on, instead of 127.0.0.1 and `port`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind_address: Option<SocketAddr>,
+    
+    /// Trust domain put in attestations, when different from the identity's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trust_domain: Option<String>,
+    
+    /// Public URL of the service, for service discovery.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_url: Option<String>,
+    
+    /// Sign with this held key instead of the current one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_key_id: Option<String>,
+    
+    /// Attestation requests allowed per source IP per minute (0 disables).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_rate_limit: Option<u32>,
+    
+    /// Attestation requests allowed per phone number per hour (0 disables).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone_rate_limit: Option<u32>,
+    
+    /// Unexpired, unrevoked attestations one phone may hold (0 disables).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_active_per_phone: Option<u32>,
+    
+    /// Seconds a phone must wait between attestations (0 disables).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reissue_interval_secs: Option<u64>,
+    
+    /// Largest request body accepted, in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_body_bytes: Option<usize>,
+    
+    /// Seconds a response is replayed for a repeated `Idempotency-Key`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_window_secs: Option<u64>,
+    
+    /// Requests handled at once before others queue (0 disables).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_in_flight: Option<usize>,
+    
+    /// Milliseconds a queued request waits before it is refused.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_timeout_ms: Option<u64>,
+    
+    /// Refuse `/attest` requests without a fresh `nonce` and `timestamp`
+    /// (default true).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require_request_nonce: Option<bool>,
+    
+    /// Where records are persisted: `memory`, `sqlite:PATH` or `postgres://...`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_url: Option<String>,
+    
+    /// Where state shared between replicas is kept, when not with the records.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_url: Option<String>,
+    
+    /// Unix socket for the admin console.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub control_socket: Option<PathBuf>,
+}
+
+impl NodeSettings {
+    /// Whether nothing is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// CIDR blocks or single addresses allowed and denied for a route group.
+/// Deny entries win; when `allow` is empty every other address is allowed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IpRules {
+    /// Networks allowed; any address when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    
+    /// Networks refused even if allowed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+}
+
+impl IpRules {
+    /// Whether no network is listed.
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+}
 
 // Default functions for serde
 fn default_port() -> u16 { 3000 }
 fn default_attestation_validity() -> u32 { 365 }
+fn default_twilio_auth_token_env() -> String { "TWILIO_AUTH_TOKEN".to_string() }
+fn default_vonage_api_secret_env() -> String { "VONAGE_API_SECRET".to_string() }
+fn default_messagebird_access_key_env() -> String { "MESSAGEBIRD_ACCESS_KEY".to_string() }
+fn default_carrier_client_secret_env() -> String { "CARRIER_CLIENT_SECRET".to_string() }
+fn default_smtp_port() -> u16 { 587 }
+fn default_smtp_password_env() -> String { "SMTP_PASSWORD".to_string() }
+fn default_webhook_secret_env() -> String { "HESHA_WEBHOOK_SECRET".to_string() }
+fn default_api_keys_env() -> String { "HESHA_API_KEYS".to_string() }
+fn default_captcha_secret_env() -> String { "HESHA_CAPTCHA_SECRET".to_string() }
+fn default_max_skew_secs() -> u64 { 300 }
+fn default_id_token_ttl_secs() -> u64 { 300 }
+fn default_cors_methods() -> Vec<String> { vec!["GET".to_string(), "POST".to_string()] }
+fn default_cors_headers() -> Vec<String> { vec!["content-type".to_string()] }
+fn default_cors_max_age_secs() -> u64 { 3600 }
+fn default_acme_directory_url() -> String { LETS_ENCRYPT_DIRECTORY.to_string() }
+fn default_acme_cache_dir() -> String { "acme".to_string() }
+fn default_acme_http_port() -> u16 { 80 }
+fn default_acme_renew_before_days() -> u32 { 30 }
+
+/// Let's Encrypt's production ACME directory.
+pub const LETS_ENCRYPT_DIRECTORY: &str = "https://acme-v02.api.letsencrypt.org/directory";
+
+/// Let's Encrypt's staging ACME directory, for testing without rate limits.
+pub const LETS_ENCRYPT_STAGING_DIRECTORY: &str = "https://acme-staging-v02.api.letsencrypt.org/directory";
 
 impl IssuerConfig {
     /// Load configuration from a TOML file.
@@ -81,6 +734,20 @@ mod tests {
             },
             port: 3000,
             attestation_validity_days: 365,
+            node: NodeSettings::default(),
+            previous_keys: Vec::new(),
+            verification: VerificationConfig::Mock,
+            auth: AuthConfig::None,
+            admin_auth: AuthConfig::None,
+            request_signing: None,
+            cors: None,
+            tls: None,
+            acme: None,
+            webhooks: Vec::new(),
+            scopes: None,
+            captcha: None,
+            ip_access: None,
+            oidc_provider: None,
         };
         
         // Test serialization
@@ -90,5 +757,166 @@ mod tests {
         // Test deserialization
         let parsed: IssuerConfig = toml::from_str(&toml_str).unwrap();
         assert_eq!(parsed.identity.name, "Test Issuer");
+        assert!(!toml_str.contains("[verification]"));
+    }
+    
+    #[test]
+    fn test_twilio_verification() {
+        let toml_str = r#"
+            [identity]
+            name = "Test Issuer"
+            trust_domain = "issuer.example.com"
+            contact_email = "admin@example.com"
+            public_key_base64url = "test-key"
+            key_id = "default"
+            created_at = "2024-01-01T00:00:00Z"
+            
+            [verification]
+            provider = "twilio"
+            account_sid = "AC123"
+            service_sid = "VA456"
+        "#;
+        let config: IssuerConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.verification, VerificationConfig::Twilio {
+            account_sid: "AC123".to_string(),
+            service_sid: "VA456".to_string(),
+            auth_token_env: "TWILIO_AUTH_TOKEN".to_string(),
+        });
+    }
+    
+    #[test]
+    fn test_failover_verification() {
+        let toml_str = r#"
+            provider = "failover"
+            
+            [[providers]]
+            provider = "twilio"
+            account_sid = "AC123"
+            service_sid = "VA456"
+            
+            [[providers]]
+            provider = "vonage"
+            api_key = "abc123"
+            brand = "Acme"
+            
+            [[providers]]
+            provider = "messagebird"
+            originator = "Acme"
+            regional_originators = { "234" = "AcmeNG" }
+        "#;
+        let config: VerificationConfig = toml::from_str(toml_str).unwrap();
+        let VerificationConfig::Failover { providers } = config else {
+            panic!("expected failover");
+        };
+        assert_eq!(providers.len(), 3);
+        assert_eq!(providers[1], VerificationConfig::Vonage {
+            api_key: "abc123".to_string(),
+            brand: "Acme".to_string(),
+            api_secret_env: "VONAGE_API_SECRET".to_string(),
+        });
+        assert!(matches!(
+            &providers[2],
+            VerificationConfig::MessageBird { regional_originators, .. } if regional_originators["234"] == "AcmeNG"
+        ));
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_email_verification() {
+        let toml_str = r#"
+            provider = "email"
+            smtp_host = "smtp.acme.example"
+            from = "Acme <verify@acme.example>"
+            contacts = { "+14155550100" = "noc@acme.example" }
+        "#;
+        let config: VerificationConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config, VerificationConfig::Email {
+            smtp_host: "smtp.acme.example".to_string(),
+            smtp_port: 587,
+            smtp_username: None,
+            smtp_password_env: "SMTP_PASSWORD".to_string(),
+            from: "Acme <verify@acme.example>".to_string(),
+            contacts: BTreeMap::from([("+14155550100".to_string(), "noc@acme.example".to_string())]),
+        });
+    }
+    
+    #[test]
+    fn test_webhooks() {
+        let toml_str = r#"
+            [[webhooks]]
+            url = "https://fraud.example.com/hesha"
+            events = ["attestation.issued", "attestation.revoked"]
+            
+            [[webhooks]]
+            url = "https://billing.example.com/hesha"
+            secret_env = "BILLING_SECRET"
+        "#;
+        #[derive(Deserialize)]
+        struct Webhooks {
+            webhooks: Vec<WebhookConfig>,
+        }
+        let Webhooks { webhooks } = toml::from_str(toml_str).unwrap();
+        assert_eq!(webhooks[0].secret_env, "HESHA_WEBHOOK_SECRET");
+        assert!(webhooks[0].wants(WebhookEventKind::AttestationRevoked));
+        assert!(!webhooks[0].wants(WebhookEventKind::VerificationFailed));
+        assert!(webhooks[1].wants(WebhookEventKind::VerificationFailed));
+    }
+    
+    #[test]
+    fn test_auth() {
+        let config: AuthConfig = toml::from_str(r#"method = "api_key""#).unwrap();
+        assert_eq!(config, AuthConfig::ApiKey { keys_env: "HESHA_API_KEYS".to_string() });
+        
+        let toml_str = r#"
+            method = "oidc"
+            issuer = "https://login.example.com/"
+            audience = "hesha-issuer"
+            jwks_url = "https://login.example.com/.well-known/jwks.json"
+        "#;
+        let config: AuthConfig = toml::from_str(toml_str).unwrap();
+        assert!(matches!(config, AuthConfig::Oidc { audience, .. } if audience == "hesha-issuer"));
+    }
+    
+    #[test]
+    fn test_request_signing() {
+        let toml_str = r#"
+            [[partners]]
+            id = "acme"
+            secret_env = "ACME_SIGNING_SECRET"
+        "#;
+        let config: RequestSigningConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.max_skew_secs, 300);
+        assert_eq!(config.partners[0].id, "acme");
+    }
+    
+    #[test]
+    fn test_node_settings() {
+        let toml_str = r#"
+            trust_domain = "example.com"
+            bind_address = "0.0.0.0:8080"
+            require_request_nonce = true
+        "#;
+        let node: NodeSettings = toml::from_str(toml_str).unwrap();
+        assert_eq!(node.trust_domain.as_deref(), Some("example.com"));
+        assert_eq!(node.bind_address, Some("0.0.0.0:8080".parse().unwrap()));
+        assert_eq!(node.require_request_nonce, Some(true));
+        assert!(!node.is_empty());
+        
+        // Misspelled settings are refused rather than ignored
+        assert!(toml::from_str::<NodeSettings>("trust_domian = \"example.com\"").is_err());
+    }
+    
+    #[test]
+    fn test_ip_access() {
+        let toml_str = r#"
+            [attest]
+            allow = ["10.20.0.0/16"]
+            
+            [admin]
+            deny = ["192.0.2.1"]
+        "#;
+        let config: IpAccessConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.attest.allow, ["10.20.0.0/16"]);
+        assert_eq!(config.admin.deny, ["192.0.2.1"]);
+        assert!(config.well_known.is_empty());
+    }
+}
@@ -14,21 +14,64 @@
 #![forbid(unsafe_code)]
 
 pub mod attestation;
+pub mod audit;
+pub mod backup;
+pub mod control;
 pub mod crypto;
 pub mod error;
 pub mod issuer_config;
+pub mod metrics;
 pub mod phone;
+pub mod problem;
+pub mod receipt;
+pub mod revocation;
+pub mod transparency;
+pub mod verifier;
+pub mod webhook;
 
 // Re-export commonly used types
 pub use attestation::{
-    Attestation, Challenge, ChallengeResponse, IssuerInfo, VerifiedAttestation,
+    Attestation, Challenge, ChallengeResponse, IssuerInfo, IssuerKey, VerificationMethod,
+    VerifiedAttestation, MAX_VALIDITY_DAYS, VERIFICATION_METHOD_EXT,
+};
+pub use audit::{AuditEntry, AuditEvent, AUDIT_GENESIS_HASH};
+pub use backup::RestoreSummary;
+pub use control::{
+    stop_pipe_name, ControlRequest, ControlResponse, KeysStatus, MaintenanceStatus, NodeStatus,
+    CONTROL_SOCKET_FILE,
 };
 pub use crypto::{
     BindingProof, KeyPair, Nonce, PrivateKey, PublicKey, Signature,
 };
 pub use error::{HeshaError, HeshaResult};
-pub use issuer_config::{IssuerConfig, IssuerIdentity};
+pub use issuer_config::{
+    AcmeConfig, AuthConfig, CaptchaConfig, CaptchaProvider, CorsConfig, IpAccessConfig,
+    IpRules, IssuerConfig, IssuerIdentity, NodeSettings, OidcClient, OidcProviderConfig, PreviousKey,
+    RequestSigningConfig, ScopePolicyConfig,
+    SigningPartner, TlsConfig, VerificationConfig, WebhookConfig,
+    LETS_ENCRYPT_DIRECTORY, LETS_ENCRYPT_STAGING_DIRECTORY,
+};
+pub use metrics::{
+    DailyIssuance, ExpiryStats, IssuanceStats, JobStats, NodeMetrics, RecentAttestation,
+    VerificationStats,
+};
 pub use phone::{PhoneHash, PhoneNumber, ProxyNumber};
+pub use problem::{ErrorCode, Problem, PROBLEM_CONTENT_TYPE, PROBLEM_TYPE_BASE};
+pub use receipt::{IssuanceReceipt, ReceiptOutcome};
+pub use revocation::{
+    AttestationStatus, RevocationEntry, RevocationList, RevocationStatus, StatusResponse,
+};
+pub use transparency::{
+    ConsistencyProof, InclusionProof, LogEntry, LogInclusion, LogSubmission, SignedTreeHead,
+    SubmissionResponse,
+};
+pub use verifier::{
+    AttestationSummary, ChallengeVerifyRequest, RevocationSummary, VerificationCheck,
+    VerificationReport, VerifyRequest,
+};
+pub use webhook::{
+    IssuedAttestation, WebhookEvent, WebhookEventData, WebhookEventKind, WEBHOOK_SIGNATURE_HEADER,
+};
 
 #[cfg(test)]
 mod tests;
\ No newline at end of file
@@ -0,0 +1,170 @@
+//! Issuer node operating metrics.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Snapshot of an issuer node's activity since it started.
+///
+/// Served to local operators at `/admin/metrics`. Attestations are listed
+/// by proxy number and JWT ID only; raw phone numbers never appear here.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeMetrics {
+    /// When the node started.
+    pub started_at: DateTime<Utc>,
+
+    /// Attestations issued since start (replayed responses are not counted).
+    pub attestations_issued: u64,
+
+    /// Generated proxy numbers discarded because another phone already had
+    /// them; each was regenerated with a fresh nonce.
+    #[serde(default)]
+    pub proxy_collisions: u64,
+
+    /// Failed attestation requests by error code (e.g. "invalid_phone_number").
+    #[serde(default)]
+    pub errors: BTreeMap<String, u64>,
+
+    /// Verification codes and carrier results checked.
+    #[serde(default)]
+    pub verification_checks: u64,
+
+    /// Checks that failed: wrong codes, too many attempts, carrier refusals.
+    #[serde(default)]
+    pub verification_failures: u64,
+
+    /// Most recently issued attestations, newest first.
+    #[serde(default)]
+    pub recent_attestations: Vec<RecentAttestation>,
+
+    /// Stored attestations by expiry, as of the last statistics run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation_expiry: Option<ExpiryStats>,
+
+    /// When the certificate the node serves HTTPS with expires, if it
+    /// terminates TLS itself (`[tls]` or `[acme]`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate_expires_at: Option<DateTime<Utc>>,
+
+    /// Background jobs by name.
+    #[serde(default)]
+    pub jobs: BTreeMap<String, JobStats>,
+}
+
+/// How many stored attestations are still valid and how soon they expire.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpiryStats {
+    /// When the counts were taken.
+    pub computed_at: DateTime<Utc>,
+
+    /// Attestations not yet expired.
+    pub valid: u64,
+
+    /// Valid attestations expiring within 7 days.
+    pub expiring_within_7_days: u64,
+
+    /// Valid attestations expiring within 30 days.
+    pub expiring_within_30_days: u64,
+}
+
+/// Runs of a background job since the node started.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobStats {
+    /// Completed runs, failed ones included.
+    pub runs: u64,
+
+    /// Runs that failed.
+    pub failures: u64,
+
+    /// When the last run finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run_at: Option<DateTime<Utc>>,
+
+    /// How long the last run took, in milliseconds.
+    #[serde(default)]
+    pub last_duration_ms: u64,
+
+    /// Error of the last run, if it failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// An attestation issued by the node, without the subject's phone number.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentAttestation {
+    /// JWT ID of the attestation.
+    pub jti: String,
+
+    /// Proxy number assigned.
+    pub proxy_number: String,
+
+    /// When it was issued.
+    pub issued_at: DateTime<Utc>,
+
+    /// When it expires.
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Aggregate issuance figures, served to local operators at `/admin/stats`.
+///
+/// Only counts are reported; no JWT ID, proxy number or phone number appears.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IssuanceStats {
+    /// When the figures were computed.
+    pub computed_at: DateTime<Utc>,
+
+    /// Start of the window the issuance figures cover.
+    pub since: DateTime<Utc>,
+
+    /// Attestations issued in the window, renewals included.
+    pub issued: u64,
+
+    /// Attestations issued in the window that renewed an earlier one.
+    pub renewals: u64,
+
+    /// Renewals as a share of issued attestations (0 when none were issued).
+    pub renewal_rate: f64,
+
+    /// Attestations revoked in the window.
+    pub revocations: u64,
+
+    /// Issuance per UTC day, oldest first, days without any included.
+    pub per_day: Vec<DailyIssuance>,
+
+    /// Attestations issued in the window by scope.
+    pub per_scope: BTreeMap<String, u64>,
+
+    /// Phone verification outcomes since the node started.
+    pub verification: VerificationStats,
+}
+
+/// Attestations issued on one UTC day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyIssuance {
+    /// The day.
+    pub date: NaiveDate,
+
+    /// Attestations issued, renewals included.
+    pub issued: u64,
+
+    /// How many of them were renewals.
+    pub renewals: u64,
+}
+
+/// Phone verification outcomes.
+///
+/// Verification sessions are held in memory, so these count from node start.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerificationStats {
+    /// When counting started.
+    pub since: DateTime<Utc>,
+
+    /// Codes and carrier results checked.
+    pub checks: u64,
+
+    /// Checks that failed.
+    pub failures: u64,
+
+    /// Failures as a share of checks (0 when nothing was checked).
+    pub failure_rate: f64,
+}
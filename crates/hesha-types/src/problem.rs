@@ -0,0 +1,338 @@
+//! Error responses of Hesha nodes.
+//!
+//! Nodes answer failed requests with an RFC 7807 problem document served as
+//! `application/problem+json`:
+//!
+//! ```json
+//! {
+//!   "type": "https://hesha.org/problems/rate_limited",
+//!   "title": "Too many requests",
+//!   "status": 429,
+//!   "detail": "Too many requests from this address",
+//!   "request_id": "5f0c...",
+//!   "error": "rate_limited"
+//! }
+//! ```
+//!
+//! `error` holds the [`ErrorCode`], under the member name earlier node
+//! versions used. Some problems carry further members, such as a signed
+//! denial `receipt` or a `retry_after` hint.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// Media type of problem documents.
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+/// Prefix of the `type` URI of every problem, followed by its code.
+pub const PROBLEM_TYPE_BASE: &str = "https://hesha.org/problems/";
+
+/// Reasons a node refuses a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The body or query string could not be read.
+    InvalidRequest,
+    /// The protocol version is not supported.
+    InvalidVersion,
+    /// The phone number is not in E.164 form.
+    InvalidPhoneNumber,
+    /// The user public key is not an Ed25519 key.
+    InvalidPublicKey,
+    /// The requested validity is out of range.
+    InvalidValidityDays,
+    /// The renewed attestation was not issued by this issuer.
+    InvalidRenewal,
+    /// No held key has the requested key ID.
+    InvalidKey,
+    /// The request timestamp is too far from the node's clock.
+    StaleRequest,
+    /// A CAPTCHA token is required and was not sent.
+    CaptchaRequired,
+    /// The caller could not be authenticated.
+    Unauthorized,
+    /// The partner request signature is missing or wrong.
+    InvalidSignature,
+    /// The endpoint is not served to this client.
+    Forbidden,
+    /// Requests from the client's network are not accepted.
+    IpNotAllowed,
+    /// The CAPTCHA token was not accepted.
+    CaptchaFailed,
+    /// The issuer does not issue the requested scope.
+    ScopeNotAllowed,
+    /// The scope is not the phone number's calling code.
+    ScopeCountryMismatch,
+    /// The phone already holds as many attestations as allowed.
+    ActiveAttestationLimit,
+    /// The verification session has not been confirmed.
+    PhoneNotVerified,
+    /// No route matches the request.
+    NotFound,
+    /// The verification session is unknown or expired.
+    SessionNotFound,
+    /// The request nonce was already used.
+    ReplayedRequest,
+    /// A request with the same idempotency key is still being handled.
+    IdempotencyKeyInProgress,
+    /// The body is over the node's size limit.
+    PayloadTooLarge,
+    /// The body is not JSON.
+    UnsupportedMediaType,
+    /// The idempotency key was used for a different request.
+    IdempotencyKeyReused,
+    /// The client sent too many requests; see `Retry-After`.
+    RateLimited,
+    /// The phone number was attested too recently.
+    ReissueTooSoon,
+    /// Too many wrong codes were entered for the session.
+    TooManyAttempts,
+    /// Records or shared state could not be read or written.
+    StorageFailed,
+    /// The attestation or its receipt could not be signed.
+    AttestationFailed,
+    /// No unassigned proxy number could be generated.
+    ProxyGenerationFailed,
+    /// A document other than an attestation could not be signed.
+    SigningFailed,
+    /// The phone verification provider could not be reached.
+    VerificationUnavailable,
+    /// The attestation's issuer could not be reached for its keys or
+    /// revocation list.
+    IssuerUnavailable,
+    /// The node is handling too many requests; see `Retry-After`.
+    Overloaded,
+    /// The node is down for maintenance; see `Retry-After`.
+    Maintenance,
+    /// The CAPTCHA provider could not be reached.
+    CaptchaUnavailable,
+}
+
+impl ErrorCode {
+    /// Every code, in catalogue order.
+    pub const ALL: [ErrorCode; 37] = [
+        ErrorCode::InvalidRequest,
+        ErrorCode::InvalidVersion,
+        ErrorCode::InvalidPhoneNumber,
+        ErrorCode::InvalidPublicKey,
+        ErrorCode::InvalidValidityDays,
+        ErrorCode::InvalidRenewal,
+        ErrorCode::InvalidKey,
+        ErrorCode::StaleRequest,
+        ErrorCode::CaptchaRequired,
+        ErrorCode::Unauthorized,
+        ErrorCode::InvalidSignature,
+        ErrorCode::Forbidden,
+        ErrorCode::IpNotAllowed,
+        ErrorCode::CaptchaFailed,
+        ErrorCode::ScopeNotAllowed,
+        ErrorCode::ScopeCountryMismatch,
+        ErrorCode::ActiveAttestationLimit,
+        ErrorCode::PhoneNotVerified,
+        ErrorCode::NotFound,
+        ErrorCode::SessionNotFound,
+        ErrorCode::ReplayedRequest,
+        ErrorCode::IdempotencyKeyInProgress,
+        ErrorCode::PayloadTooLarge,
+        ErrorCode::UnsupportedMediaType,
+        ErrorCode::IdempotencyKeyReused,
+        ErrorCode::RateLimited,
+        ErrorCode::ReissueTooSoon,
+        ErrorCode::TooManyAttempts,
+        ErrorCode::StorageFailed,
+        ErrorCode::AttestationFailed,
+        ErrorCode::ProxyGenerationFailed,
+        ErrorCode::SigningFailed,
+        ErrorCode::VerificationUnavailable,
+        ErrorCode::IssuerUnavailable,
+        ErrorCode::Overloaded,
+        ErrorCode::Maintenance,
+        ErrorCode::CaptchaUnavailable,
+    ];
+
+    /// The code as sent in `error` and at the end of `type`.
+    pub fn as_str(self) -> &'static str {
+        self.entry().0
+    }
+
+    /// HTTP status the code is served with.
+    pub fn status(self) -> u16 {
+        self.entry().1
+    }
+
+    /// Short summary, the same for every occurrence.
+    pub fn title(self) -> &'static str {
+        self.entry().2
+    }
+
+    /// The code's catalogue entry: code, status and title.
+    fn entry(self) -> (&'static str, u16, &'static str) {
+        match self {
+            ErrorCode::InvalidRequest => ("invalid_request", 400, "Invalid request"),
+            ErrorCode::InvalidVersion => ("invalid_version", 400, "Unsupported protocol version"),
+            ErrorCode::InvalidPhoneNumber => ("invalid_phone_number", 400, "Invalid phone number"),
+            ErrorCode::InvalidPublicKey => ("invalid_public_key", 400, "Invalid public key"),
+            ErrorCode::InvalidValidityDays => ("invalid_validity_days", 400, "Invalid validity"),
+            ErrorCode::InvalidRenewal => ("invalid_renewal", 400, "Invalid renewal"),
+            ErrorCode::InvalidKey => ("invalid_key", 400, "Unknown signing key"),
+            ErrorCode::StaleRequest => ("stale_request", 400, "Stale request"),
+            ErrorCode::CaptchaRequired => ("captcha_required", 400, "CAPTCHA required"),
+            ErrorCode::Unauthorized => ("unauthorized", 401, "Unauthorized"),
+            ErrorCode::InvalidSignature => ("invalid_signature", 401, "Invalid request signature"),
+            ErrorCode::Forbidden => ("forbidden", 403, "Forbidden"),
+            ErrorCode::IpNotAllowed => ("ip_not_allowed", 403, "Network not allowed"),
+            ErrorCode::CaptchaFailed => ("captcha_failed", 403, "CAPTCHA failed"),
+            ErrorCode::ScopeNotAllowed => ("scope_not_allowed", 403, "Scope not allowed"),
+            ErrorCode::ScopeCountryMismatch => ("scope_country_mismatch", 403, "Scope does not match phone number"),
+            ErrorCode::ActiveAttestationLimit => ("active_attestation_limit", 403, "Too many active attestations"),
+            ErrorCode::PhoneNotVerified => ("phone_not_verified", 403, "Phone number not verified"),
+            ErrorCode::NotFound => ("not_found", 404, "Not found"),
+            ErrorCode::SessionNotFound => ("session_not_found", 404, "Verification session not found"),
+            ErrorCode::ReplayedRequest => ("replayed_request", 409, "Replayed request"),
+            ErrorCode::IdempotencyKeyInProgress => ("idempotency_key_in_progress", 409, "Idempotent request in progress"),
+            ErrorCode::PayloadTooLarge => ("payload_too_large", 413, "Request body too large"),
+            ErrorCode::UnsupportedMediaType => ("unsupported_media_type", 415, "Unsupported media type"),
+            ErrorCode::IdempotencyKeyReused => ("idempotency_key_reused", 422, "Idempotency key reused"),
+            ErrorCode::RateLimited => ("rate_limited", 429, "Too many requests"),
+            ErrorCode::ReissueTooSoon => ("reissue_too_soon", 429, "Reissued too soon"),
+            ErrorCode::TooManyAttempts => ("too_many_attempts", 429, "Too many verification attempts"),
+            ErrorCode::StorageFailed => ("storage_failed", 500, "Storage failure"),
+            ErrorCode::AttestationFailed => ("attestation_failed", 500, "Attestation failed"),
+            ErrorCode::ProxyGenerationFailed => ("proxy_generation_failed", 500, "Proxy number generation failed"),
+            ErrorCode::SigningFailed => ("signing_failed", 500, "Signing failed"),
+            ErrorCode::VerificationUnavailable => ("verification_unavailable", 502, "Verification provider unavailable"),
+            ErrorCode::IssuerUnavailable => ("issuer_unavailable", 502, "Issuer unavailable"),
+            ErrorCode::Overloaded => ("overloaded", 503, "Overloaded"),
+            ErrorCode::Maintenance => ("maintenance", 503, "Down for maintenance"),
+            ErrorCode::CaptchaUnavailable => ("captcha_unavailable", 503, "CAPTCHA provider unavailable"),
+        }
+    }
+
+    /// The `type` URI of problems with this code.
+    pub fn type_uri(self) -> String {
+        format!("{}{}", PROBLEM_TYPE_BASE, self.as_str())
+    }
+
+    /// Whether a request refused with this code may succeed if retried
+    /// unchanged, after a wait.
+    pub fn is_transient(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::IdempotencyKeyInProgress | ErrorCode::RateLimited | ErrorCode::ReissueTooSoon
+        ) || self.status() >= 500
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ErrorCode {
+    type Err = ();
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        ErrorCode::ALL.into_iter().find(|known| known.as_str() == code).ok_or(())
+    }
+}
+
+/// An RFC 7807 problem document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Problem {
+    /// URI naming the kind of problem.
+    #[serde(rename = "type", default = "about_blank")]
+    pub type_uri: String,
+    /// Short summary of the kind of problem.
+    #[serde(default)]
+    pub title: String,
+    /// HTTP status code.
+    #[serde(default)]
+    pub status: u16,
+    /// What went wrong with this request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// ID of the request, as in its `X-Request-Id` header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Machine-readable code; see [`ErrorCode`].
+    #[serde(default)]
+    pub error: String,
+    /// Members particular to the problem.
+    #[serde(flatten)]
+    pub extensions: Map<String, Value>,
+}
+
+fn about_blank() -> String {
+    "about:blank".to_string()
+}
+
+impl Problem {
+    /// A problem with the catalogue's type, title and status for `code`.
+    pub fn new(code: ErrorCode, detail: impl Into<String>) -> Self {
+        Self {
+            type_uri: code.type_uri(),
+            title: code.title().to_string(),
+            status: code.status(),
+            detail: Some(detail.into()),
+            request_id: None,
+            error: code.as_str().to_string(),
+            extensions: Map::new(),
+        }
+    }
+
+    /// Add a member particular to this problem.
+    pub fn with_extension(mut self, name: &str, value: impl Serialize) -> Self {
+        self.extensions.insert(name.to_string(), serde_json::to_value(value).unwrap_or_default());
+        self
+    }
+
+    /// The code, if it is one this version knows.
+    pub fn code(&self) -> Option<ErrorCode> {
+        self.error.parse().ok()
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.detail {
+            Some(detail) => write!(f, "{} ({}): {}", self.title, self.error, detail),
+            None => write!(f, "{} ({})", self.title, self.error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalogue() {
+        for code in ErrorCode::ALL {
+            assert_eq!(code.as_str().parse::<ErrorCode>(), Ok(code));
+            assert_eq!(serde_json::to_value(code).unwrap(), code.as_str());
+            assert!((400..600).contains(&code.status()));
+        }
+        assert!(ErrorCode::RateLimited.is_transient());
+        assert!(!ErrorCode::InvalidPhoneNumber.is_transient());
+        assert!(!ErrorCode::ReplayedRequest.is_transient());
+        assert!("no_such_code".parse::<ErrorCode>().is_err());
+    }
+
+    #[test]
+    fn test_problem_document() {
+        let problem = Problem::new(ErrorCode::Maintenance, "Down for a migration").with_extension("retry_after", 60);
+        let json = serde_json::to_value(&problem).unwrap();
+        assert_eq!(json["type"], "https://hesha.org/problems/maintenance");
+        assert_eq!(json["status"], 503);
+        assert_eq!(json["error"], "maintenance");
+        assert_eq!(json["retry_after"], 60);
+        assert!(json.get("request_id").is_none());
+
+        let parsed: Problem = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, problem);
+        assert_eq!(parsed.code(), Some(ErrorCode::Maintenance));
+    }
+}
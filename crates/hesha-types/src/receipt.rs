@@ -0,0 +1,69 @@
+//! Signed issuance receipts.
+
+use crate::crypto::Signature;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Issuer's signed statement of how it answered an attestation request.
+///
+/// Returned with each attestation, and with refusals on issuance policy
+/// grounds, so a user can later prove what the issuer did. Its hash is
+/// recorded in the issuer's audit log. It names no phone number.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssuanceReceipt {
+    /// Issuer domain.
+    pub issuer: String,
+
+    /// Whether the attestation was issued.
+    pub outcome: ReceiptOutcome,
+
+    /// JWT ID of the issued attestation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+
+    /// Proxy number assigned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_number: Option<String>,
+
+    /// Error code the request was refused with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Public key the attestation was requested for.
+    pub user_pubkey: String,
+
+    /// Scope requested.
+    pub scope: String,
+
+    /// When the request was answered.
+    pub issued_at: DateTime<Utc>,
+
+    /// Issuer key that signed the receipt.
+    pub key_id: String,
+
+    /// Issuer's Ed25519 signature over [`signing_payload`](Self::signing_payload).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Signature>,
+}
+
+/// How an attestation request was answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReceiptOutcome {
+    /// An attestation was issued.
+    Issued,
+    /// The request was refused.
+    Denied,
+}
+
+impl IssuanceReceipt {
+    /// Bytes covered by the issuer's signature: the JSON encoding of the
+    /// receipt without its signature.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let unsigned = Self {
+            signature: None,
+            ..self.clone()
+        };
+        serde_json::to_vec(&unsigned).unwrap_or_default()
+    }
+}
@@ -0,0 +1,252 @@
+//! Revocation list types.
+
+use crate::crypto::Signature;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Revocation list published by an issuer.
+///
+/// Served at `/.well-known/hesha/revocations.json` so verifiers can
+/// reject attestations that were revoked before their expiry. Long lists
+/// are split into pages linked by `next`, each signed on its own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationList {
+    /// Issuer domain that published the list.
+    pub issuer: String,
+
+    /// When the list was last updated.
+    pub updated_at: DateTime<Utc>,
+
+    /// Revoked attestations.
+    #[serde(default)]
+    pub entries: Vec<RevocationEntry>,
+
+    /// URL of the next page, relative to the issuer, if there is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+
+    /// Issuer's Ed25519 signature over [`signing_payload`](Self::signing_payload).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Signature>,
+}
+
+impl RevocationList {
+    /// Create an empty revocation list for an issuer.
+    pub fn new(issuer: impl Into<String>) -> Self {
+        Self {
+            issuer: issuer.into(),
+            updated_at: Utc::now(),
+            entries: Vec::new(),
+            next: None,
+            signature: None,
+        }
+    }
+
+    /// Bytes covered by the issuer's signature: the JSON encoding of the
+    /// list without its signature.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let unsigned = Self {
+            signature: None,
+            ..self.clone()
+        };
+        serde_json::to_vec(&unsigned).unwrap_or_default()
+    }
+
+    /// Find the revocation entry for an attestation ID.
+    pub fn find(&self, jti: &str) -> Option<&RevocationEntry> {
+        self.entries.iter().find(|entry| entry.jti == jti)
+    }
+
+    /// Check if an attestation ID has been revoked.
+    pub fn is_revoked(&self, jti: &str) -> bool {
+        self.find(jti).is_some()
+    }
+
+    /// Get the revocation status of an attestation ID.
+    pub fn status(&self, jti: &str) -> RevocationStatus {
+        match self.find(jti) {
+            Some(entry) => RevocationStatus::Revoked(entry.clone()),
+            None => RevocationStatus::Good,
+        }
+    }
+}
+
+/// A single revoked attestation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationEntry {
+    /// JWT ID of the revoked attestation.
+    pub jti: String,
+
+    /// When the attestation was revoked.
+    pub revoked_at: DateTime<Utc>,
+
+    /// Optional human-readable reason.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Revocation status of a single attestation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevocationStatus {
+    /// The attestation is not on the revocation list.
+    Good,
+    /// The attestation has been revoked.
+    Revoked(RevocationEntry),
+}
+
+impl RevocationStatus {
+    /// Check if this status represents a revoked attestation.
+    pub fn is_revoked(&self) -> bool {
+        matches!(self, RevocationStatus::Revoked(_))
+    }
+}
+
+/// Status of one attestation as answered by an issuer's status responder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum AttestationStatus {
+    /// Issued by the issuer and not revoked.
+    Good,
+    /// Revoked by the issuer.
+    Revoked {
+        /// When the attestation was revoked.
+        revoked_at: DateTime<Utc>,
+        /// Optional human-readable reason.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+    /// Not an attestation the issuer knows of.
+    Unknown,
+}
+
+impl From<RevocationEntry> for AttestationStatus {
+    fn from(entry: RevocationEntry) -> Self {
+        AttestationStatus::Revoked {
+            revoked_at: entry.revoked_at,
+            reason: entry.reason,
+        }
+    }
+}
+
+/// Signed, short-lived answer to `GET /status/{jti}`.
+///
+/// Lets a verifier check a single attestation without downloading the
+/// whole revocation list. The answer is only good until `next_update`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusResponse {
+    /// Issuer domain the status is for.
+    pub issuer: String,
+
+    /// JWT ID of the attestation.
+    pub jti: String,
+
+    /// Its status.
+    #[serde(flatten)]
+    pub status: AttestationStatus,
+
+    /// When the response was produced.
+    pub produced_at: DateTime<Utc>,
+
+    /// When the response stops being valid.
+    pub next_update: DateTime<Utc>,
+
+    /// Issuer key that signed the response, if the issuer names its keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+
+    /// Issuer's Ed25519 signature over [`signing_payload`](Self::signing_payload).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Signature>,
+}
+
+impl StatusResponse {
+    /// An unsigned response produced now and valid for `validity`.
+    pub fn new(
+        issuer: impl Into<String>,
+        jti: impl Into<String>,
+        status: AttestationStatus,
+        validity: chrono::Duration,
+    ) -> Self {
+        let produced_at = Utc::now();
+        Self {
+            issuer: issuer.into(),
+            jti: jti.into(),
+            status,
+            produced_at,
+            next_update: produced_at + validity,
+            key_id: None,
+            signature: None,
+        }
+    }
+
+    /// Bytes covered by the issuer's signature: the JSON encoding of the
+    /// response without its signature.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let unsigned = Self {
+            signature: None,
+            ..self.clone()
+        };
+        serde_json::to_vec(&unsigned).unwrap_or_default()
+    }
+
+    /// Check whether the response is still valid at `now`.
+    pub fn is_current(&self, now: DateTime<Utc>) -> bool {
+        self.produced_at <= now && now < self.next_update
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revocation_lookup() {
+        let mut list = RevocationList::new("issuer.example.com");
+        list.entries.push(RevocationEntry {
+            jti: "revoked-jti".to_string(),
+            revoked_at: Utc::now(),
+            reason: Some("key compromise".to_string()),
+        });
+
+        assert!(list.is_revoked("revoked-jti"));
+        assert!(!list.is_revoked("other-jti"));
+        assert!(list.status("revoked-jti").is_revoked());
+        assert_eq!(list.status("other-jti"), RevocationStatus::Good);
+    }
+
+    #[test]
+    fn test_revocation_list_serialization() {
+        let json = r#"{"issuer":"issuer.example.com","updated_at":"2024-01-01T00:00:00Z"}"#;
+        let list: RevocationList = serde_json::from_str(json).unwrap();
+
+        assert_eq!(list.issuer, "issuer.example.com");
+        assert!(list.entries.is_empty());
+    }
+
+    #[test]
+    fn test_status_response_serialization() {
+        let entry = RevocationEntry {
+            jti: "revoked-jti".to_string(),
+            revoked_at: Utc::now(),
+            reason: Some("key compromise".to_string()),
+        };
+        let response = StatusResponse::new(
+            "issuer.example.com",
+            "revoked-jti",
+            entry.into(),
+            chrono::Duration::minutes(5),
+        );
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["status"], "revoked");
+        assert_eq!(json["reason"], "key compromise");
+        let parsed: StatusResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, response);
+
+        assert!(response.is_current(Utc::now()));
+        assert!(!response.is_current(response.next_update));
+
+        let json = r#"{"issuer":"i","jti":"j","status":"unknown","produced_at":"2024-01-01T00:00:00Z","next_update":"2024-01-01T00:05:00Z"}"#;
+        let parsed: StatusResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed.status, AttestationStatus::Unknown);
+    }
+}
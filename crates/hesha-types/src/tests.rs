@@ -28,6 +28,7 @@ mod integration_tests {
             salt: vec![3u8; 16],
             jti: uuid::Uuid::new_v4().to_string(),
             nonce: Nonce::new(uuid::Uuid::new_v4().to_string()),
+            ext: Default::default(),
         };
         
         assert!(!attestation.is_expired());
@@ -61,6 +62,13 @@ mod integration_tests {
             created_at: Utc::now(),
             key_id: Some("key-2024-01".to_string()),
             service_info: None,
+            keys: vec![IssuerKey {
+                key_id: "key-2023-01".to_string(),
+                public_key: PublicKey::from_bytes([7u8; 32]),
+                algorithm: "Ed25519".to_string(),
+                valid_from: Utc::now(),
+                valid_until: None,
+            }],
         };
         
         let json = serde_json::to_string(&issuer).unwrap();
@@ -68,6 +76,10 @@ mod integration_tests {
         
         assert_eq!(decoded.algorithm, "Ed25519");
         assert_eq!(decoded.key_id, Some("key-2024-01".to_string()));
+        assert_eq!(decoded.key(None), Some(&issuer.public_key));
+        assert_eq!(decoded.key(Some("key-2024-01")), Some(&issuer.public_key));
+        assert_eq!(decoded.key(Some("key-2023-01")), Some(&PublicKey::from_bytes([7u8; 32])));
+        assert_eq!(decoded.key(Some("key-2022-01")), None);
     }
     
     #[test]
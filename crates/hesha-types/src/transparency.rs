@@ -0,0 +1,140 @@
+//! Transparency log types.
+//!
+//! Issuers submit a hash of each attestation they issue to a transparency
+//! log, an append-only Merkle tree (RFC 6962 hashing). The log signs tree
+//! heads, and proves that an attestation is included in a tree and that
+//! each tree extends the previous ones, so an issuer can't quietly issue an
+//! attestation or take one back.
+
+use crate::crypto::Signature;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// An issuer's signed request to log an attestation.
+///
+/// It carries only a hash of the attestation JWT, so the log learns
+/// nothing about the attestation itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogSubmission {
+    /// Issuer domain.
+    pub issuer: String,
+
+    /// Hex SHA-256 of the attestation JWT.
+    pub attestation_hash: String,
+
+    /// Issuer key that signed the submission, if the issuer names its keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+
+    /// When the issuer submitted it.
+    pub submitted_at: DateTime<Utc>,
+
+    /// Issuer's Ed25519 signature over [`signing_payload`](Self::signing_payload).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Signature>,
+}
+
+impl LogSubmission {
+    /// Bytes covered by the issuer's signature: the JSON encoding of the
+    /// submission without its signature.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let unsigned = Self {
+            signature: None,
+            ..self.clone()
+        };
+        serde_json::to_vec(&unsigned).unwrap_or_default()
+    }
+}
+
+/// An entry of the log: a submission and where it sits in the tree.
+///
+/// The tree's leaf is the JSON encoding of the signed submission, so
+/// anyone holding the entries can recompute the tree and check each
+/// issuer's signature.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// Position in the log, from zero.
+    pub index: u64,
+
+    /// The signed submission.
+    pub submission: LogSubmission,
+}
+
+/// The log's signed statement of its tree's size and root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedTreeHead {
+    /// Name of the log, usually its domain.
+    pub log_id: String,
+
+    /// Number of entries in the tree.
+    pub tree_size: u64,
+
+    /// Hex Merkle tree hash of the first `tree_size` entries.
+    pub root_hash: String,
+
+    /// When the head was signed.
+    pub timestamp: DateTime<Utc>,
+
+    /// Log's Ed25519 signature over [`signing_payload`](Self::signing_payload).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Signature>,
+}
+
+impl SignedTreeHead {
+    /// Bytes covered by the log's signature: the JSON encoding of the head
+    /// without its signature.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let unsigned = Self {
+            signature: None,
+            ..self.clone()
+        };
+        serde_json::to_vec(&unsigned).unwrap_or_default()
+    }
+}
+
+/// Proof that an entry is included in a tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    /// Position of the entry.
+    pub leaf_index: u64,
+
+    /// Size of the tree the proof is for.
+    pub tree_size: u64,
+
+    /// Hex hashes of the sibling subtrees, from the leaf up.
+    pub audit_path: Vec<String>,
+}
+
+/// Proof that a tree is a prefix of a larger one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsistencyProof {
+    /// Size of the older tree.
+    pub first: u64,
+
+    /// Size of the newer tree.
+    pub second: u64,
+
+    /// Hex subtree hashes, as RFC 6962 defines them.
+    pub proof: Vec<String>,
+}
+
+/// The log's answer to a submission.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmissionResponse {
+    /// Position of the entry; an attestation submitted again keeps its
+    /// first position.
+    pub leaf_index: u64,
+
+    /// Tree head including the entry.
+    pub tree_head: SignedTreeHead,
+}
+
+/// An entry and the proof that it is included in a tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogInclusion {
+    /// The entry.
+    pub entry: LogEntry,
+
+    /// Proof of its inclusion.
+    pub proof: InclusionProof,
+}
@@ -0,0 +1,119 @@
+//! Requests and reports of the verifier node API.
+
+use crate::attestation::ChallengeResponse;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /verify`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyRequest {
+    /// Attestation JWT to verify.
+    pub attestation: String,
+}
+
+/// Body of `POST /verify/challenge-response`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChallengeVerifyRequest {
+    /// Attestation JWT the response was made with.
+    pub attestation: String,
+
+    /// The wallet's signed answer to the challenge.
+    pub response: ChallengeResponse,
+
+    /// Service context the challenge must have been issued for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_context: Option<String>,
+}
+
+/// Outcome of verifying an attestation, and of each check made.
+///
+/// Checks run in order and stop at the first failure, so a failed report
+/// ends with the check that failed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationReport {
+    /// Whether every check passed.
+    pub valid: bool,
+
+    /// When the checks were made.
+    pub checked_at: DateTime<Utc>,
+
+    /// The attestation's claims, once it could be read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<AttestationSummary>,
+
+    /// Whether the issuer revoked the attestation, once that was looked up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revocation: Option<RevocationSummary>,
+
+    /// Checks made, in order.
+    pub checks: Vec<VerificationCheck>,
+}
+
+impl VerificationReport {
+    /// The first failed check, if any.
+    pub fn failure(&self) -> Option<&VerificationCheck> {
+        self.checks.iter().find(|check| !check.passed)
+    }
+}
+
+/// Claims of a verified (or partly verified) attestation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttestationSummary {
+    /// JWT ID.
+    pub jti: String,
+
+    /// Issuer domain.
+    pub issuer: String,
+
+    /// Domain the issuer's keys were looked up at.
+    pub trust_domain: String,
+
+    /// Issuer key named in the JWT header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+
+    /// Proxy number attested.
+    pub proxy_number: String,
+
+    /// User public key challenge responses must be signed with (base64).
+    pub user_pubkey: String,
+
+    /// When the attestation was issued.
+    pub issued_at: DateTime<Utc>,
+
+    /// When the attestation expires.
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Revocation status of an attestation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationSummary {
+    /// Whether the attestation is on the issuer's revocation list.
+    pub revoked: bool,
+
+    /// When it was revoked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<DateTime<Utc>>,
+
+    /// Reason given by the issuer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// When the revocation list consulted was last updated by the issuer.
+    pub list_updated_at: DateTime<Utc>,
+}
+
+/// One check of a report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationCheck {
+    /// What was checked: `format`, `policy`, `issuer_key`, `signature`,
+    /// `revocation` or `challenge`.
+    pub name: String,
+
+    /// Whether it passed.
+    pub passed: bool,
+
+    /// Why it failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
@@ -0,0 +1,137 @@
+//! Webhook event types.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Header carrying the HMAC signature of a webhook delivery.
+///
+/// The value is `t=<unix seconds>,v1=<hex HMAC-SHA256>`, where the MAC
+/// covers `<unix seconds>.<request body>` under the endpoint's secret.
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Hesha-Signature";
+
+/// Event delivered to operator webhooks.
+///
+/// Payloads never contain phone numbers, only proxy numbers and JWT IDs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    /// Unique event ID; retried deliveries repeat it.
+    pub id: String,
+
+    /// When the event happened.
+    pub created_at: DateTime<Utc>,
+
+    /// What happened.
+    #[serde(flatten)]
+    pub data: WebhookEventData,
+}
+
+/// Event type and its details, serialized as `type` and `data`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum WebhookEventData {
+    /// An attestation was issued.
+    #[serde(rename = "attestation.issued")]
+    AttestationIssued(IssuedAttestation),
+
+    /// An attestation was issued to replace an earlier one.
+    #[serde(rename = "attestation.renewed")]
+    AttestationRenewed {
+        /// JWT ID of the attestation being replaced.
+        renews: String,
+        /// The new attestation.
+        #[serde(flatten)]
+        attestation: IssuedAttestation,
+    },
+
+    /// An attestation was revoked.
+    #[serde(rename = "attestation.revoked")]
+    AttestationRevoked {
+        /// JWT ID of the revoked attestation.
+        jti: String,
+        /// Reason published with the revocation.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+
+    /// A phone verification attempt failed.
+    #[serde(rename = "verification.failed")]
+    VerificationFailed {
+        /// Verification session.
+        session_id: String,
+        /// Why it failed, e.g. `wrong_code`.
+        reason: String,
+    },
+}
+
+/// Details of an issued attestation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssuedAttestation {
+    /// JWT ID of the attestation.
+    pub jti: String,
+    /// Proxy number assigned.
+    pub proxy_number: String,
+    /// Scope the proxy number was generated for.
+    pub scope: String,
+    /// When it expires.
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Kinds of webhook event, for choosing which an endpoint receives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WebhookEventKind {
+    /// `attestation.issued`
+    #[serde(rename = "attestation.issued")]
+    AttestationIssued,
+    /// `attestation.renewed`
+    #[serde(rename = "attestation.renewed")]
+    AttestationRenewed,
+    /// `attestation.revoked`
+    #[serde(rename = "attestation.revoked")]
+    AttestationRevoked,
+    /// `verification.failed`
+    #[serde(rename = "verification.failed")]
+    VerificationFailed,
+}
+
+impl WebhookEventData {
+    /// The kind of this event.
+    pub fn kind(&self) -> WebhookEventKind {
+        match self {
+            Self::AttestationIssued(_) => WebhookEventKind::AttestationIssued,
+            Self::AttestationRenewed { .. } => WebhookEventKind::AttestationRenewed,
+            Self::AttestationRevoked { .. } => WebhookEventKind::AttestationRevoked,
+            Self::VerificationFailed { .. } => WebhookEventKind::VerificationFailed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_serialization() {
+        let event = WebhookEvent {
+            id: "evt_1".to_string(),
+            created_at: Utc::now(),
+            data: WebhookEventData::AttestationRenewed {
+                renews: "old-jti".to_string(),
+                attestation: IssuedAttestation {
+                    jti: "new-jti".to_string(),
+                    proxy_number: "+99012345678".to_string(),
+                    scope: "1".to_string(),
+                    expires_at: Utc::now(),
+                },
+            },
+        };
+
+        let json: serde_json::Value = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "attestation.renewed");
+        assert_eq!(json["data"]["renews"], "old-jti");
+        assert_eq!(json["data"]["jti"], "new-jti");
+
+        let parsed: WebhookEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, event);
+        assert_eq!(parsed.data.kind(), WebhookEventKind::AttestationRenewed);
+    }
+}
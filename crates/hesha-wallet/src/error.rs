@@ -0,0 +1,64 @@
+//! Wallet error types.
+
+use hesha_types::HeshaError;
+use thiserror::Error;
+
+/// Errors that can occur managing a wallet.
+#[derive(Debug, Error)]
+pub enum WalletError {
+    /// The storage holds no wallet.
+    #[error("No wallet found")]
+    NotFound,
+
+    /// The storage already holds a wallet.
+    #[error("A wallet already exists")]
+    AlreadyExists,
+
+    /// The passphrase is wrong or the sealed wallet was tampered with.
+    #[error("Wrong passphrase or corrupted wallet")]
+    WrongPassphrase,
+
+    /// The stored wallet could not be read.
+    #[error("Invalid wallet: {0}")]
+    Invalid(String),
+
+    /// No identity has the name.
+    #[error("No identity named '{0}'")]
+    UnknownIdentity(String),
+
+    /// An identity with the name already exists.
+    #[error("An identity named '{0}' already exists")]
+    DuplicateIdentity(String),
+
+    /// No attestation matches the query.
+    #[error("No attestation matching '{0}'")]
+    UnknownAttestation(String),
+
+    /// The attestation was issued to a key the identity doesn't hold.
+    #[error("Attestation {0} was not issued to identity '{1}'")]
+    KeyMismatch(String, String),
+
+    /// The attestation has expired.
+    #[error("Attestation {0} has expired")]
+    Expired(String),
+
+    /// A renewal can't be requested without knowing the attestation's scope.
+    #[error("Scope of attestation {0} is unknown")]
+    UnknownScope(String),
+
+    /// Protocol or cryptographic error.
+    #[error(transparent)]
+    Protocol(#[from] HeshaError),
+
+    /// Reading or writing the wallet failed.
+    #[error("Wallet storage failed: {0}")]
+    Storage(#[from] std::io::Error),
+
+    /// The issuer refused or failed a request.
+    #[cfg(feature = "client")]
+    #[error(transparent)]
+    Client(#[from] hesha_client::ClientError),
+}
+
+/// Result type for wallet operations.
+pub type WalletResult<T> = Result<T, WalletError>;
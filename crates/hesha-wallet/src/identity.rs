@@ -0,0 +1,152 @@
+//! Identities and the attestations they hold.
+
+use chrono::{DateTime, Duration, Utc};
+use hesha_types::{Attestation, HeshaResult, KeyPair, PrivateKey, PublicKey};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A named key pair and the attestations issued to it.
+///
+/// Users keep separate identities to stop services linking them, e.g. one
+/// for work and one for dating apps.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Identity {
+    /// Name chosen by the user, unique within the wallet.
+    pub name: String,
+    /// When the identity was created or imported.
+    pub created_at: DateTime<Utc>,
+    /// The identity's public key, which attestations are issued to.
+    pub public_key: PublicKey,
+    /// The identity's private key (base64url).
+    private_key: String,
+    /// Attestations issued to the identity, oldest first.
+    #[serde(default)]
+    pub attestations: Vec<HeldAttestation>,
+}
+
+impl Identity {
+    pub(crate) fn new(name: &str, keypair: &KeyPair) -> Self {
+        Self {
+            name: name.to_string(),
+            created_at: Utc::now(),
+            public_key: keypair.public.clone(),
+            private_key: keypair.private.to_base64(),
+            attestations: Vec::new(),
+        }
+    }
+
+    /// The identity's key pair.
+    pub fn keypair(&self) -> HeshaResult<KeyPair> {
+        hesha_crypto::keypair_from_private(&PrivateKey::from_base64(&self.private_key)?)
+    }
+
+    /// The attestation with the JWT ID `jti`.
+    pub fn attestation(&self, jti: &str) -> Option<&HeldAttestation> {
+        self.attestations.iter().find(|held| held.jti == jti)
+    }
+}
+
+impl fmt::Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Identity")
+            .field("name", &self.name)
+            .field("created_at", &self.created_at)
+            .field("public_key", &self.public_key)
+            .field("attestations", &self.attestations)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An attestation kept in the wallet, with its claims unpacked for display
+/// and expiry tracking.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeldAttestation {
+    /// JWT ID.
+    pub jti: String,
+    /// Issuer domain.
+    pub issuer: String,
+    /// Trust domain the issuer's key is published under.
+    pub trust_domain: String,
+    /// Proxy number the attestation vouches for.
+    pub proxy_number: String,
+    /// Calling code the attestation was requested for, needed to renew it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// When the issuer issued it.
+    pub issued_at: DateTime<Utc>,
+    /// When it expires.
+    pub expires_at: DateTime<Utc>,
+    /// When it was added to the wallet.
+    pub added_at: DateTime<Utc>,
+    /// JWT ID of the attestation this one renewed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renews: Option<String>,
+    /// The attestation JWT, as presented to services.
+    pub jwt: String,
+}
+
+impl HeldAttestation {
+    /// Unpack an attestation JWT. The signature isn't checked; services
+    /// verify it when the attestation is presented.
+    pub fn from_jwt(jwt: &str, scope: Option<String>) -> HeshaResult<(Self, Attestation)> {
+        let attestation = hesha_core::parse_attestation(jwt)?;
+        let held = Self {
+            jti: attestation.jti.clone(),
+            issuer: attestation.iss.clone(),
+            trust_domain: attestation.effective_trust_domain().to_string(),
+            proxy_number: attestation.proxy_number.to_string(),
+            scope,
+            issued_at: attestation.iat,
+            expires_at: attestation.exp,
+            added_at: Utc::now(),
+            renews: None,
+            jwt: jwt.to_string(),
+        };
+        Ok((held, attestation))
+    }
+
+    /// Where the attestation stands at `now`, counting it as expiring soon
+    /// within `window` of its expiry.
+    pub fn state(&self, now: DateTime<Utc>, window: Duration) -> AttestationState {
+        if self.expires_at <= now {
+            AttestationState::Expired
+        } else if self.expires_at - now <= window {
+            AttestationState::ExpiringSoon
+        } else {
+            AttestationState::Valid
+        }
+    }
+
+    /// Whether the attestation has expired.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
+
+/// Where an attestation stands in its lifetime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttestationState {
+    /// Valid, and not expiring soon.
+    Valid,
+    /// Valid, but due for renewal.
+    ExpiringSoon,
+    /// Past its expiry.
+    Expired,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_hides_private_key() {
+        let keypair = hesha_crypto::generate_keypair().unwrap();
+        let identity = Identity::new("work", &keypair);
+
+        let debug = format!("{:?}", identity);
+        assert!(debug.contains("work"));
+        assert!(!debug.contains(&keypair.private.to_base64()));
+        assert_eq!(identity.keypair().unwrap().public, keypair.public);
+    }
+}
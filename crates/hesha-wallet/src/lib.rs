@@ -0,0 +1,39 @@
+//! On-device wallet for Hesha Protocol users.
+//!
+//! The shared core of mobile and desktop wallet apps: it holds the user's
+//! identities (each a key pair and the attestations issued to it), seals
+//! them with a passphrase wherever the app keeps them, tracks when
+//! attestations expire, plans and completes their renewal, and answers
+//! services' challenges.
+//!
+//! ```no_run
+//! use hesha_wallet::{FileStorage, Wallet};
+//!
+//! # fn main() -> hesha_wallet::WalletResult<()> {
+//! let mut wallet = Wallet::create(FileStorage::new("wallet.json"), "correct horse")?;
+//! let identity = wallet.create_identity("personal")?;
+//! println!("Request attestations for {}", identity.public_key.to_base64());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Security
+//!
+//! - Private keys only ever leave memory sealed with the passphrase
+//!   (Argon2id and XChaCha20-Poly1305)
+//! - Attestations are only accepted for keys the wallet holds
+
+#![warn(missing_docs)]
+#![forbid(unsafe_code)]
+
+pub mod error;
+pub mod identity;
+pub mod renewal;
+pub mod storage;
+pub mod wallet;
+
+pub use error::{WalletError, WalletResult};
+pub use identity::{AttestationState, HeldAttestation, Identity};
+pub use renewal::Renewal;
+pub use storage::{FileStorage, MemoryStorage, WalletStorage};
+pub use wallet::{SealedWallet, Wallet, WALLET_FORMAT, WALLET_VERSION};
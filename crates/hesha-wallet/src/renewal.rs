@@ -0,0 +1,173 @@
+//! Renewing attestations before they expire.
+//!
+//! [`Wallet::renewals_due`] lists attestations needing a replacement. The
+//! issuer's new attestation takes the old one's place in the issuer's caps,
+//! so renewals name the attestation they replace. Apps verify the phone
+//! number again with the issuer and then either call [`Wallet::renew`] or
+//! request the attestation themselves and hand it to
+//! [`Wallet::complete_renewal`].
+
+use crate::error::{WalletError, WalletResult};
+use crate::identity::HeldAttestation;
+use crate::wallet::Wallet;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// An attestation due for renewal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Renewal {
+    /// Identity holding the attestation.
+    pub identity: String,
+    /// JWT ID of the attestation to renew.
+    pub renews: String,
+    /// Issuer to request the new attestation from.
+    pub issuer: String,
+    /// Proxy number the attestation vouches for.
+    pub proxy_number: String,
+    /// Calling code to request, if known.
+    pub scope: Option<String>,
+    /// When the attestation expires (or expired).
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Wallet {
+    /// Attestations expiring within `window`, or already expired, that
+    /// haven't been renewed yet; soonest first.
+    pub fn renewals_due(&self, window: Duration) -> Vec<Renewal> {
+        let deadline = Utc::now() + window;
+        let mut due: Vec<_> = self
+            .held()
+            .filter(|(identity, held)| {
+                held.expires_at <= deadline
+                    && !identity.attestations.iter().any(|other| other.renews.as_deref() == Some(held.jti.as_str()))
+            })
+            .map(|(identity, held)| Renewal {
+                identity: identity.name.clone(),
+                renews: held.jti.clone(),
+                issuer: held.issuer.clone(),
+                proxy_number: held.proxy_number.clone(),
+                scope: held.scope.clone(),
+                expires_at: held.expires_at,
+            })
+            .collect();
+        due.sort_by_key(|renewal| renewal.expires_at);
+        due
+    }
+
+    /// Keep the attestation `jwt` issued for `renewal`.
+    ///
+    /// It must be issued to the same identity by the same issuer. The
+    /// replaced attestation stays in the wallet until it expires, for
+    /// services that haven't seen the new one yet.
+    pub fn complete_renewal(&mut self, renewal: &Renewal, jwt: &str) -> WalletResult<&HeldAttestation> {
+        let index = self.identity_index(&renewal.identity)?;
+        let (mut held, attestation) = HeldAttestation::from_jwt(jwt, renewal.scope.clone())?;
+        if attestation.user_pubkey != self.identities()[index].public_key {
+            return Err(WalletError::KeyMismatch(held.jti, renewal.identity.clone()));
+        }
+        if held.issuer != renewal.issuer {
+            return Err(WalletError::Protocol(hesha_types::HeshaError::InvalidAttestation(format!(
+                "Renewal of {} was issued by {}, not {}",
+                renewal.renews, held.issuer, renewal.issuer
+            ))));
+        }
+        held.renews = Some(renewal.renews.clone());
+        self.insert_attestation(index, held)
+    }
+
+    /// Request the renewal from the issuer for a session the user verified
+    /// with it, and keep the new attestation.
+    ///
+    /// `client` must point at the renewal's issuer.
+    #[cfg(feature = "client")]
+    pub async fn renew(
+        &mut self,
+        renewal: &Renewal,
+        client: &hesha_client::IssuerClient,
+        session_id: &str,
+        validity_days: Option<i64>,
+    ) -> WalletResult<&HeldAttestation> {
+        let scope = renewal
+            .scope
+            .as_deref()
+            .ok_or_else(|| WalletError::UnknownScope(renewal.renews.clone()))?;
+        let public_key = self.identity(&renewal.identity)?.public_key.clone();
+        let response = client
+            .renew_session_attestation(session_id, &public_key, scope, validity_days, &renewal.renews)
+            .await?;
+        self.complete_renewal(renewal, &response.attestation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallet::tests::{attestation_jwt, wallet};
+
+    #[test]
+    fn test_renewals_due() {
+        let (mut wallet, _) = wallet();
+        let issuer = hesha_crypto::generate_keypair().unwrap();
+        let user = wallet.create_identity("personal").unwrap().keypair().unwrap();
+        let old = wallet.add_attestation("personal", &attestation_jwt(&issuer, &user, 3), Some("234")).unwrap().jti.clone();
+        wallet.add_attestation("personal", &attestation_jwt(&issuer, &user, 90), Some("234")).unwrap();
+
+        let due = wallet.renewals_due(Duration::days(7));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].renews, old);
+        assert_eq!(due[0].identity, "personal");
+        assert_eq!(due[0].scope.as_deref(), Some("234"));
+
+        let renewed = wallet.complete_renewal(&due[0], &attestation_jwt(&issuer, &user, 90)).unwrap();
+        assert_eq!(renewed.renews.as_deref(), Some(old.as_str()));
+        assert_eq!(renewed.scope.as_deref(), Some("234"));
+        assert!(wallet.renewals_due(Duration::days(7)).is_empty());
+        assert!(wallet.attestation(&old).is_ok());
+
+        let stranger = hesha_crypto::generate_keypair().unwrap();
+        assert!(matches!(
+            wallet.complete_renewal(&due[0], &attestation_jwt(&issuer, &stranger, 90)),
+            Err(WalletError::KeyMismatch(..))
+        ));
+    }
+
+    #[cfg(feature = "client")]
+    #[tokio::test]
+    async fn test_renew() {
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let (mut wallet, _) = wallet();
+        let issuer = hesha_crypto::generate_keypair().unwrap();
+        let user = wallet.create_identity("personal").unwrap().keypair().unwrap();
+        let old = wallet.add_attestation("personal", &attestation_jwt(&issuer, &user, 3), Some("234")).unwrap().jti.clone();
+        let renewal = wallet.renewals_due(Duration::days(7)).remove(0);
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/attest/simple"))
+            .and(body_partial_json(serde_json::json!({
+                "session_id": "session-1",
+                "user_pubkey": user.public.to_base64(),
+                "scope": "234",
+                "renews": old,
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "attestation": attestation_jwt(&issuer, &user, 90),
+                "proxy_number": "+23400123456789",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = hesha_client::IssuerClient::new_insecure(&server.uri()).unwrap();
+        let renewed = wallet.renew(&renewal, &client, "session-1", None).await.unwrap();
+        assert_eq!(renewed.renews.as_deref(), Some(old.as_str()));
+
+        let unscoped = Renewal { scope: None, ..renewal };
+        assert!(matches!(
+            wallet.renew(&unscoped, &client, "session-1", None).await,
+            Err(WalletError::UnknownScope(_))
+        ));
+    }
+}
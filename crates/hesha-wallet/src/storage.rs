@@ -0,0 +1,112 @@
+//! Where sealed wallets are kept.
+//!
+//! A wallet is saved as one sealed document, so any place that can hold a
+//! blob will do: a file on desktop, the app's sandbox or a platform
+//! keystore-backed store on mobile. Apps implement [`WalletStorage`] for
+//! anything not covered here.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Place a sealed wallet is loaded from and saved to.
+pub trait WalletStorage: Send + Sync {
+    /// The saved wallet, if there is one.
+    fn load(&self) -> io::Result<Option<Vec<u8>>>;
+
+    /// Replace the saved wallet.
+    fn save(&self, data: &[u8]) -> io::Result<()>;
+}
+
+/// A wallet file.
+///
+/// Saves write a temporary file next to it and rename it into place, so an
+/// interrupted save leaves the previous wallet intact. On Unix the file is
+/// readable by its owner only.
+#[derive(Debug, Clone)]
+pub struct FileStorage {
+    path: PathBuf,
+}
+
+impl FileStorage {
+    /// Storage in the file at `path`, created on first save.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl WalletStorage for FileStorage {
+    fn load(&self) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(&self.path) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn save(&self, data: &[u8]) -> io::Result<()> {
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        let temporary = PathBuf::from(temporary);
+        fs::write(&temporary, data)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&temporary, fs::Permissions::from_mode(0o600))?;
+        }
+        fs::rename(&temporary, &self.path)
+    }
+}
+
+/// A wallet kept in memory, for tests and apps persisting it themselves.
+/// Clones share the same contents.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    data: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl MemoryStorage {
+    /// Empty storage.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl WalletStorage for MemoryStorage {
+    fn load(&self) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.data.lock().unwrap_or_else(|e| e.into_inner()).clone())
+    }
+
+    fn save(&self, data: &[u8]) -> io::Result<()> {
+        *self.data.lock().unwrap_or_else(|e| e.into_inner()) = Some(data.to_vec());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FileStorage::new(dir.path().join("nested").join("wallet.json"));
+        assert_eq!(storage.load().unwrap(), None);
+
+        storage.save(b"one").unwrap();
+        storage.save(b"two").unwrap();
+        assert_eq!(storage.load().unwrap().as_deref(), Some(&b"two"[..]));
+        assert!(!dir.path().join("nested").join("wallet.json.tmp").exists());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.path().join("nested").join("wallet.json")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
@@ -0,0 +1,474 @@
+//! The wallet: identities sealed with a passphrase.
+
+use crate::error::{WalletError, WalletResult};
+use crate::identity::{HeldAttestation, Identity};
+use crate::storage::WalletStorage;
+use chrono::{DateTime, Duration, Utc};
+use hesha_crypto::{decrypt_data, encrypt_data, EncryptedData, KdfParams};
+use hesha_types::{Challenge, ChallengeResponse, PrivateKey};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Current version of the wallet contents.
+pub const WALLET_VERSION: u32 = 1;
+
+/// Marker identifying a sealed wallet.
+pub const WALLET_FORMAT: &str = "hesha-wallet";
+
+/// A wallet as saved: its contents sealed with the passphrase.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedWallet {
+    /// Always [`WALLET_FORMAT`].
+    pub format: String,
+    /// When the wallet was last saved.
+    pub updated_at: DateTime<Utc>,
+    /// The encrypted contents.
+    pub sealed: EncryptedData,
+}
+
+/// What a sealed wallet holds.
+#[derive(Serialize, Deserialize)]
+struct Contents {
+    version: u32,
+    identities: Vec<Identity>,
+}
+
+/// A user's identities and attestations, open for use.
+///
+/// Every change is sealed and saved before the call returns, so an app can
+/// be killed at any point without losing what it was told to keep.
+pub struct Wallet {
+    storage: Box<dyn WalletStorage>,
+    passphrase: String,
+    kdf: KdfParams,
+    identities: Vec<Identity>,
+}
+
+impl Wallet {
+    /// Create an empty wallet in `storage`, sealed with `passphrase`.
+    ///
+    /// Fails if the storage already holds a wallet.
+    pub fn create(storage: impl WalletStorage + 'static, passphrase: &str) -> WalletResult<Self> {
+        Self::create_with_params(storage, passphrase, KdfParams::default())
+    }
+
+    /// Create an empty wallet with custom key derivation parameters, e.g.
+    /// lighter ones on low-memory devices.
+    pub fn create_with_params(
+        storage: impl WalletStorage + 'static,
+        passphrase: &str,
+        kdf: KdfParams,
+    ) -> WalletResult<Self> {
+        if storage.load()?.is_some() {
+            return Err(WalletError::AlreadyExists);
+        }
+        let wallet = Self {
+            storage: Box::new(storage),
+            passphrase: passphrase.to_string(),
+            kdf,
+            identities: Vec::new(),
+        };
+        wallet.save()?;
+        Ok(wallet)
+    }
+
+    /// Open the wallet in `storage`.
+    pub fn open(storage: impl WalletStorage + 'static, passphrase: &str) -> WalletResult<Self> {
+        let data = storage.load()?.ok_or(WalletError::NotFound)?;
+        let sealed: SealedWallet = serde_json::from_slice(&data)
+            .map_err(|e| WalletError::Invalid(e.to_string()))?;
+        if sealed.format != WALLET_FORMAT {
+            return Err(WalletError::Invalid(format!("Unknown format '{}'", sealed.format)));
+        }
+
+        let plaintext = decrypt_data(&sealed.sealed, passphrase)
+            .map_err(|_| WalletError::WrongPassphrase)?;
+        let contents: Contents = serde_json::from_slice(&plaintext)
+            .map_err(|e| WalletError::Invalid(e.to_string()))?;
+        if contents.version != WALLET_VERSION {
+            return Err(WalletError::Invalid(format!(
+                "Unsupported wallet version {}",
+                contents.version
+            )));
+        }
+
+        Ok(Self {
+            storage: Box::new(storage),
+            passphrase: passphrase.to_string(),
+            kdf: sealed.sealed.kdf_params,
+            identities: contents.identities,
+        })
+    }
+
+    /// Seal the wallet with a new passphrase.
+    pub fn change_passphrase(&mut self, passphrase: &str) -> WalletResult<()> {
+        let previous = std::mem::replace(&mut self.passphrase, passphrase.to_string());
+        self.commit(|wallet| wallet.passphrase = previous)
+    }
+
+    /// All identities, oldest first.
+    pub fn identities(&self) -> &[Identity] {
+        &self.identities
+    }
+
+    /// The identity named `name`.
+    pub fn identity(&self, name: &str) -> WalletResult<&Identity> {
+        self.identities
+            .iter()
+            .find(|identity| identity.name == name)
+            .ok_or_else(|| WalletError::UnknownIdentity(name.to_string()))
+    }
+
+    /// Create an identity with a fresh key pair.
+    pub fn create_identity(&mut self, name: &str) -> WalletResult<&Identity> {
+        let keypair = hesha_crypto::generate_keypair()?;
+        self.insert_identity(Identity::new(name, &keypair))
+    }
+
+    /// Add an identity for an existing private key, e.g. one restored from
+    /// a recovery phrase.
+    pub fn import_identity(&mut self, name: &str, private_key: &PrivateKey) -> WalletResult<&Identity> {
+        let keypair = hesha_crypto::keypair_from_private(private_key)?;
+        if let Some(existing) = self.identities.iter().find(|identity| identity.public_key == keypair.public) {
+            return Err(WalletError::DuplicateIdentity(existing.name.clone()));
+        }
+        self.insert_identity(Identity::new(name, &keypair))
+    }
+
+    /// Remove an identity with its keys and attestations.
+    pub fn remove_identity(&mut self, name: &str) -> WalletResult<Identity> {
+        let index = self.identity_index(name)?;
+        let removed = self.identities.remove(index);
+        self.commit(|wallet| wallet.identities.insert(index, removed.clone()))?;
+        Ok(removed)
+    }
+
+    /// Keep an attestation issued to the identity `name`.
+    ///
+    /// `scope` is the calling code the attestation was requested for; it
+    /// isn't part of the attestation, and renewing needs it. Adding an
+    /// attestation the wallet already holds replaces it.
+    pub fn add_attestation(
+        &mut self,
+        name: &str,
+        jwt: &str,
+        scope: Option<&str>,
+    ) -> WalletResult<&HeldAttestation> {
+        let index = self.identity_index(name)?;
+        let (held, attestation) = HeldAttestation::from_jwt(jwt, scope.map(str::to_string))?;
+        if attestation.user_pubkey != self.identities[index].public_key {
+            return Err(WalletError::KeyMismatch(held.jti, name.to_string()));
+        }
+        self.insert_attestation(index, held)
+    }
+
+    /// Remove the attestation with the JWT ID `jti`.
+    pub fn remove_attestation(&mut self, jti: &str) -> WalletResult<HeldAttestation> {
+        let (index, position) = self.attestation_index(jti)?;
+        let removed = self.identities[index].attestations.remove(position);
+        self.commit(|wallet| wallet.identities[index].attestations.insert(position, removed.clone()))?;
+        Ok(removed)
+    }
+
+    /// The attestation with the JWT ID `jti` and the identity holding it.
+    pub fn attestation(&self, jti: &str) -> WalletResult<(&Identity, &HeldAttestation)> {
+        let (index, position) = self.attestation_index(jti)?;
+        let identity = &self.identities[index];
+        Ok((identity, &identity.attestations[position]))
+    }
+
+    /// Attestations that haven't expired but will within `window`, soonest
+    /// first.
+    pub fn expiring_within(&self, window: Duration) -> Vec<(&Identity, &HeldAttestation)> {
+        let now = Utc::now();
+        let mut expiring: Vec<_> = self
+            .held()
+            .filter(|(_, held)| held.expires_at > now && held.expires_at - now <= window)
+            .collect();
+        expiring.sort_by_key(|(_, held)| held.expires_at);
+        expiring
+    }
+
+    /// Remove expired attestations, returning them.
+    pub fn prune_expired(&mut self) -> WalletResult<Vec<HeldAttestation>> {
+        let now = Utc::now();
+        let previous = self.identities.clone();
+        let mut pruned = Vec::new();
+        for identity in &mut self.identities {
+            let (expired, kept) = identity.attestations.drain(..).partition(|held| held.expires_at <= now);
+            identity.attestations = kept;
+            pruned.extend::<Vec<_>>(expired);
+        }
+        if !pruned.is_empty() {
+            self.commit(|wallet| wallet.identities = previous)?;
+        }
+        Ok(pruned)
+    }
+
+    /// Answer a service's challenge with the attestation `jti`, signing it
+    /// with the key of the identity holding it.
+    ///
+    /// Refuses expired attestations, which no service would accept.
+    pub fn answer_challenge(&self, jti: &str, challenge: &Challenge) -> WalletResult<ChallengeResponse> {
+        let (identity, held) = self.attestation(jti)?;
+        if held.is_expired() {
+            return Err(WalletError::Expired(held.jti.clone()));
+        }
+        let keypair = identity.keypair()?;
+        Ok(hesha_core::sign_challenge(challenge, &held.jti, &keypair.private)?)
+    }
+
+    /// Every held attestation with its identity.
+    pub(crate) fn held(&self) -> impl Iterator<Item = (&Identity, &HeldAttestation)> {
+        self.identities
+            .iter()
+            .flat_map(|identity| identity.attestations.iter().map(move |held| (identity, held)))
+    }
+
+    /// Add `held` to the identity at `index`, replacing an attestation with
+    /// the same JWT ID.
+    pub(crate) fn insert_attestation(&mut self, index: usize, held: HeldAttestation) -> WalletResult<&HeldAttestation> {
+        let attestations = &mut self.identities[index].attestations;
+        let previous = match attestations.iter().position(|existing| existing.jti == held.jti) {
+            Some(position) => Some((position, std::mem::replace(&mut attestations[position], held))),
+            None => {
+                attestations.push(held);
+                None
+            }
+        };
+        let position = match &previous {
+            Some((position, _)) => *position,
+            None => attestations.len() - 1,
+        };
+        self.commit(|wallet| {
+            let attestations = &mut wallet.identities[index].attestations;
+            match previous {
+                Some((position, previous)) => attestations[position] = previous,
+                None => {
+                    attestations.pop();
+                }
+            }
+        })?;
+        Ok(&self.identities[index].attestations[position])
+    }
+
+    pub(crate) fn identity_index(&self, name: &str) -> WalletResult<usize> {
+        self.identities
+            .iter()
+            .position(|identity| identity.name == name)
+            .ok_or_else(|| WalletError::UnknownIdentity(name.to_string()))
+    }
+
+    fn attestation_index(&self, jti: &str) -> WalletResult<(usize, usize)> {
+        self.identities
+            .iter()
+            .enumerate()
+            .find_map(|(index, identity)| {
+                identity.attestations.iter().position(|held| held.jti == jti).map(|position| (index, position))
+            })
+            .ok_or_else(|| WalletError::UnknownAttestation(jti.to_string()))
+    }
+
+    fn insert_identity(&mut self, identity: Identity) -> WalletResult<&Identity> {
+        if self.identity_index(&identity.name).is_ok() {
+            return Err(WalletError::DuplicateIdentity(identity.name));
+        }
+        self.identities.push(identity);
+        self.commit(|wallet| {
+            wallet.identities.pop();
+        })?;
+        Ok(&self.identities[self.identities.len() - 1])
+    }
+
+    /// Save a change already made in memory, undoing it if the save fails so
+    /// the open wallet never holds more than the saved one.
+    fn commit(&mut self, undo: impl FnOnce(&mut Self)) -> WalletResult<()> {
+        if let Err(e) = self.save() {
+            undo(self);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> WalletResult<()> {
+        let contents = Contents { version: WALLET_VERSION, identities: self.identities.clone() };
+        let plaintext = serde_json::to_vec(&contents)
+            .map_err(|e| WalletError::Invalid(e.to_string()))?;
+        let sealed = SealedWallet {
+            format: WALLET_FORMAT.to_string(),
+            updated_at: Utc::now(),
+            sealed: encrypt_data(&plaintext, &self.passphrase, self.kdf)?,
+        };
+        let data = serde_json::to_vec_pretty(&sealed)
+            .map_err(|e| WalletError::Invalid(e.to_string()))?;
+        self.storage.save(&data)?;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Wallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Wallet")
+            .field("identities", &self.identities)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use hesha_core::AttestationBuilder;
+    use hesha_types::{KeyPair, Nonce, PhoneNumber, ProxyNumber};
+
+    pub(crate) const TEST_PARAMS: KdfParams = KdfParams { m_cost: 64, t_cost: 1, p_cost: 1 };
+
+    /// An attestation for `user` from `issuer.example.com`, valid for
+    /// `validity_days`.
+    pub(crate) fn attestation_jwt(issuer: &KeyPair, user: &KeyPair, validity_days: i64) -> String {
+        AttestationBuilder::new(
+            "issuer.example.com".to_string(),
+            &issuer.private,
+            PhoneNumber::new("+2348012345678").unwrap(),
+            ProxyNumber::new("+23400123456789").unwrap(),
+            user.public.clone(),
+        )
+        .validity_days(validity_days)
+        .build_jwt()
+        .unwrap()
+    }
+
+    pub(crate) fn wallet() -> (Wallet, MemoryStorage) {
+        let storage = MemoryStorage::new();
+        let wallet = Wallet::create_with_params(storage.clone(), "passphrase", TEST_PARAMS).unwrap();
+        (wallet, storage)
+    }
+
+    #[test]
+    fn test_create_and_open() {
+        let (mut wallet, storage) = wallet();
+        let public_key = wallet.create_identity("personal").unwrap().public_key.clone();
+
+        assert!(matches!(
+            Wallet::create(storage.clone(), "passphrase"),
+            Err(WalletError::AlreadyExists)
+        ));
+        assert!(matches!(
+            Wallet::open(storage.clone(), "wrong"),
+            Err(WalletError::WrongPassphrase)
+        ));
+        assert!(matches!(
+            Wallet::open(MemoryStorage::new(), "passphrase"),
+            Err(WalletError::NotFound)
+        ));
+
+        let reopened = Wallet::open(storage.clone(), "passphrase").unwrap();
+        let identity = reopened.identity("personal").unwrap();
+        assert_eq!(identity.public_key, public_key);
+        assert_eq!(identity.keypair().unwrap().public, public_key);
+
+        wallet.change_passphrase("new passphrase").unwrap();
+        assert!(Wallet::open(storage.clone(), "passphrase").is_err());
+        assert!(Wallet::open(storage, "new passphrase").is_ok());
+    }
+
+    #[test]
+    fn test_identities() {
+        let (mut wallet, _) = wallet();
+        let keypair = hesha_crypto::generate_keypair().unwrap();
+
+        wallet.create_identity("work").unwrap();
+        wallet.import_identity("restored", &keypair.private).unwrap();
+        assert!(matches!(
+            wallet.create_identity("work"),
+            Err(WalletError::DuplicateIdentity(_))
+        ));
+        assert!(matches!(
+            wallet.import_identity("again", &keypair.private),
+            Err(WalletError::DuplicateIdentity(name)) if name == "restored"
+        ));
+        assert_eq!(wallet.identity("restored").unwrap().public_key, keypair.public);
+
+        wallet.remove_identity("work").unwrap();
+        let names: Vec<_> = wallet.identities().iter().map(|identity| identity.name.as_str()).collect();
+        assert_eq!(names, ["restored"]);
+        assert!(matches!(
+            wallet.remove_identity("work"),
+            Err(WalletError::UnknownIdentity(_))
+        ));
+    }
+
+    #[test]
+    fn test_attestations() {
+        let (mut wallet, storage) = wallet();
+        let issuer = hesha_crypto::generate_keypair().unwrap();
+        let user = wallet.create_identity("personal").unwrap().keypair().unwrap();
+        let stranger = hesha_crypto::generate_keypair().unwrap();
+
+        let jwt = attestation_jwt(&issuer, &user, 30);
+        let jti = wallet.add_attestation("personal", &jwt, Some("234")).unwrap().jti.clone();
+        wallet.add_attestation("personal", &jwt, Some("234")).unwrap();
+        assert_eq!(wallet.identity("personal").unwrap().attestations.len(), 1);
+        assert!(matches!(
+            wallet.add_attestation("personal", &attestation_jwt(&issuer, &stranger, 30), None),
+            Err(WalletError::KeyMismatch(..))
+        ));
+
+        let reopened = Wallet::open(storage, "passphrase").unwrap();
+        let (identity, held) = reopened.attestation(&jti).unwrap();
+        assert_eq!(identity.name, "personal");
+        assert_eq!(held.issuer, "issuer.example.com");
+        assert_eq!(held.proxy_number, "+23400123456789");
+        assert_eq!(held.scope.as_deref(), Some("234"));
+        assert_eq!(held.jwt, jwt);
+
+        wallet.remove_attestation(&jti).unwrap();
+        assert!(matches!(
+            wallet.attestation(&jti),
+            Err(WalletError::UnknownAttestation(_))
+        ));
+    }
+
+    #[test]
+    fn test_expiry_tracking() {
+        let (mut wallet, _) = wallet();
+        let issuer = hesha_crypto::generate_keypair().unwrap();
+        let user = wallet.create_identity("personal").unwrap().keypair().unwrap();
+
+        let soon = wallet.add_attestation("personal", &attestation_jwt(&issuer, &user, 3), None).unwrap().jti.clone();
+        wallet.add_attestation("personal", &attestation_jwt(&issuer, &user, 90), None).unwrap();
+        let expired = wallet.add_attestation("personal", &attestation_jwt(&issuer, &user, 0), None).unwrap().jti.clone();
+
+        let expiring: Vec<_> = wallet.expiring_within(Duration::days(7)).into_iter().map(|(_, held)| held.jti.clone()).collect();
+        assert_eq!(expiring, [soon]);
+
+        let pruned = wallet.prune_expired().unwrap();
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(pruned[0].jti, expired);
+        assert_eq!(wallet.identity("personal").unwrap().attestations.len(), 2);
+    }
+
+    #[test]
+    fn test_answer_challenge() {
+        let (mut wallet, _) = wallet();
+        let issuer = hesha_crypto::generate_keypair().unwrap();
+        let user = wallet.create_identity("personal").unwrap().keypair().unwrap();
+        let jwt = attestation_jwt(&issuer, &user, 30);
+        let jti = wallet.add_attestation("personal", &jwt, None).unwrap().jti.clone();
+        let challenge = Challenge {
+            nonce: Nonce::new("0123456789abcdef"),
+            service_context: "app.example.com".to_string(),
+            timestamp: Utc::now(),
+        };
+
+        let response = wallet.answer_challenge(&jti, &challenge).unwrap();
+        let attestation = hesha_core::parse_attestation(&jwt).unwrap();
+        hesha_core::verify_challenge_response(&response, &attestation, Duration::minutes(5)).unwrap();
+
+        let expired = wallet.add_attestation("personal", &attestation_jwt(&issuer, &user, 0), None).unwrap().jti.clone();
+        assert!(matches!(
+            wallet.answer_challenge(&expired, &challenge),
+            Err(WalletError::Expired(_))
+        ));
+    }
+}
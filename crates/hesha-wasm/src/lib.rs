@@ -0,0 +1,195 @@
+//! WebAssembly bindings for the Hesha Protocol.
+//!
+//! Lets web apps check attestations in the browser, without a round trip
+//! to their backend or a verifier node. Verification is offline: issuer
+//! keys are pinned by the app, as a [`TrustBundle`] or a single key, rather
+//! than discovered over the network.
+//!
+//! Build the JavaScript package with
+//! `wasm-pack build crates/hesha-wasm --target web`.
+//!
+//! ```js
+//! import init, { verifyAttestation } from "hesha-wasm";
+//!
+//! await init();
+//! const attestation = verifyAttestation(jwt, {
+//!   issuers: [{ domain: "example.com", public_key: "base64..." }],
+//! });
+//! console.log(attestation.proxy_number, attestation.expires_at);
+//! ```
+//!
+//! Functions throw an `Error` carrying the protocol error's message when
+//! they fail. Objects follow the JSON shapes of the protocol types, with
+//! `snake_case` fields and RFC 3339 timestamps.
+
+#![warn(missing_docs)]
+
+use hesha_core::{TrustBundle, VerificationPolicy};
+use hesha_types::{
+    AttestationSummary, Challenge, ChallengeResponse, HeshaError, HeshaResult, PrivateKey,
+    ProxyNumber, PublicKey, VerifiedAttestation,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Read an attestation's claims without verifying it.
+///
+/// For display only; use `verifyAttestation` before trusting them.
+#[wasm_bindgen(js_name = parseAttestation)]
+pub fn parse_attestation(jwt: &str) -> Result<JsValue, JsError> {
+    to_js(&summarize(jwt, &hesha_core::parse_attestation(jwt)?)?)
+}
+
+/// Verify an attestation against pinned issuer keys.
+///
+/// `bundle` is a trust bundle object. `policy`, if given, is a
+/// verification policy object; the default accepts any issuer in the
+/// bundle and validity periods of up to 365 days. Returns the verified
+/// attestation's claims.
+#[wasm_bindgen(js_name = verifyAttestation)]
+pub fn verify_attestation(jwt: &str, bundle: JsValue, policy: JsValue) -> Result<JsValue, JsError> {
+    let bundle: TrustBundle = from_js(bundle)?;
+    let policy: VerificationPolicy = if policy.is_undefined() || policy.is_null() {
+        VerificationPolicy::default()
+    } else {
+        from_js(policy)?
+    };
+    to_js(&verify_with_bundle(jwt, &bundle, &policy)?)
+}
+
+/// Verify an attestation against one issuer public key (base64url).
+#[wasm_bindgen(js_name = verifyAttestationWithKey)]
+pub fn verify_attestation_with_key(jwt: &str, issuer_key: &str) -> Result<JsValue, JsError> {
+    to_js(&verify_with_key(jwt, issuer_key)?)
+}
+
+/// Check a proxy number's format, returning it without whitespace.
+#[wasm_bindgen(js_name = validateProxyNumber)]
+pub fn validate_proxy_number(number: &str) -> Result<String, JsError> {
+    Ok(ProxyNumber::new(number)?.as_str().to_string())
+}
+
+/// Answer a service's challenge with the attestation `attestationId`,
+/// signing it with the attestation's user private key (base64url).
+///
+/// Returns the challenge response to send back to the service.
+#[wasm_bindgen(js_name = signChallenge)]
+pub fn sign_challenge(challenge: JsValue, attestation_id: &str, private_key: &str) -> Result<JsValue, JsError> {
+    let challenge: Challenge = from_js(challenge)?;
+    to_js(&sign(&challenge, attestation_id, private_key)?)
+}
+
+fn verify_with_bundle(
+    jwt: &str,
+    bundle: &TrustBundle,
+    policy: &VerificationPolicy,
+) -> HeshaResult<AttestationSummary> {
+    let verified = policy.verify_with_bundle(jwt, bundle)?;
+    summarize_verified(jwt, &verified)
+}
+
+fn verify_with_key(jwt: &str, issuer_key: &str) -> HeshaResult<AttestationSummary> {
+    let verified = hesha_core::verify_attestation_with_key(jwt, &PublicKey::from_base64(issuer_key)?)?;
+    summarize_verified(jwt, &verified)
+}
+
+fn sign(challenge: &Challenge, attestation_id: &str, private_key: &str) -> HeshaResult<ChallengeResponse> {
+    hesha_core::sign_challenge(challenge, attestation_id, &PrivateKey::from_base64(private_key)?)
+}
+
+fn summarize_verified(jwt: &str, verified: &VerifiedAttestation) -> HeshaResult<AttestationSummary> {
+    let mut summary = summarize(jwt, &verified.attestation)?;
+    summary.issuer = verified.issuer.clone();
+    Ok(summary)
+}
+
+fn summarize(jwt: &str, attestation: &hesha_types::Attestation) -> HeshaResult<AttestationSummary> {
+    Ok(AttestationSummary {
+        jti: attestation.jti.clone(),
+        issuer: attestation.iss.clone(),
+        trust_domain: attestation.effective_trust_domain().to_string(),
+        key_id: hesha_core::attestation::jwt::decode_key_id_unverified(jwt)?,
+        proxy_number: attestation.proxy_number.as_str().to_string(),
+        user_pubkey: attestation.user_pubkey.to_base64(),
+        issued_at: attestation.iat,
+        expires_at: attestation.exp,
+    })
+}
+
+/// Convert to a plain JS object (not a `Map`), as `JSON.parse` would give.
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<T, JsError> {
+    serde_wasm_bindgen::from_value(value)
+        .map_err(|e| JsError::from(HeshaError::SerializationError(e.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use hesha_core::AttestationBuilder;
+    use hesha_types::{KeyPair, Nonce, PhoneNumber};
+
+    fn attestation_jwt(issuer: &KeyPair, user: &KeyPair) -> String {
+        AttestationBuilder::new(
+            "issuer.example.com".to_string(),
+            &issuer.private,
+            PhoneNumber::new("+2348012345678").unwrap(),
+            ProxyNumber::new("+23400123456789").unwrap(),
+            user.public.clone(),
+        )
+        .trust_domain("example.com".to_string())
+        .key_id("key-1".to_string())
+        .build_jwt()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_with_pinned_keys() {
+        let issuer = hesha_crypto::generate_keypair().unwrap();
+        let user = hesha_crypto::generate_keypair().unwrap();
+        let jwt = attestation_jwt(&issuer, &user);
+        let mut bundle = TrustBundle::new();
+        bundle.add("example.com", issuer.public.clone(), None);
+
+        let summary = verify_with_bundle(&jwt, &bundle, &VerificationPolicy::default()).unwrap();
+        assert_eq!(summary.issuer, "issuer.example.com");
+        assert_eq!(summary.trust_domain, "example.com");
+        assert_eq!(summary.key_id.as_deref(), Some("key-1"));
+        assert_eq!(summary.proxy_number, "+23400123456789");
+        assert_eq!(summary.user_pubkey, user.public.to_base64());
+
+        assert_eq!(verify_with_key(&jwt, &issuer.public.to_base64()).unwrap(), summary);
+        assert!(verify_with_key(&jwt, &user.public.to_base64()).is_err());
+
+        let strict = VerificationPolicy {
+            allowed_issuers: vec!["other.example.com".to_string()],
+            ..VerificationPolicy::default()
+        };
+        assert!(verify_with_bundle(&jwt, &bundle, &strict).is_err());
+        assert!(verify_with_bundle(&jwt, &TrustBundle::new(), &VerificationPolicy::default()).is_err());
+    }
+
+    #[test]
+    fn test_sign_challenge() {
+        let issuer = hesha_crypto::generate_keypair().unwrap();
+        let user = hesha_crypto::generate_keypair().unwrap();
+        let jwt = attestation_jwt(&issuer, &user);
+        let attestation = hesha_core::parse_attestation(&jwt).unwrap();
+        let challenge = Challenge {
+            nonce: Nonce::new("0123456789abcdef"),
+            service_context: "app.example.com".to_string(),
+            timestamp: Utc::now(),
+        };
+
+        let response = sign(&challenge, &attestation.jti, &user.private.to_base64()).unwrap();
+        hesha_core::verify_challenge_response(&response, &attestation, chrono::Duration::minutes(5)).unwrap();
+        assert!(sign(&challenge, &attestation.jti, "not a key").is_err());
+    }
+}
@@ -0,0 +1,428 @@
+//! HTTP endpoints.
+
+use crate::state::{AppState, Role};
+use axum::{
+    extract::{rejection::JsonRejection, Query, State},
+    Json,
+};
+use hesha_core::verification::resolve_issuer_key;
+use hesha_core::verify_log_submission;
+use hesha_node::ApiError;
+use hesha_types::{
+    ConsistencyProof, ErrorCode, HeshaError, InclusionProof, LogEntry, LogInclusion, LogSubmission,
+    PublicKey, SignedTreeHead, SubmissionResponse,
+};
+use serde::Deserialize;
+
+/// Most entries served per request.
+pub const MAX_ENTRIES: u64 = 1000;
+
+/// How far in the future a submission may be dated, in seconds.
+const MAX_CLOCK_SKEW_SECS: i64 = 300;
+
+/// `POST /submissions`: log an issuer's signed attestation hash.
+///
+/// An attestation submitted again keeps its first entry.
+pub async fn submit(
+    State(state): State<AppState>,
+    submission: Result<Json<LogSubmission>, JsonRejection>,
+) -> Result<Json<SubmissionResponse>, ApiError> {
+    let Json(submission) = submission?;
+    if let Role::Mirror { .. } = *state.role {
+        return Err(ApiError::new(
+            ErrorCode::Forbidden,
+            "This log is a mirror; submit to the log it mirrors",
+        ));
+    }
+    let hash = &submission.attestation_hash;
+    if hash.len() != 64 || !hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        return Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            "attestation_hash must be a lowercase hex SHA-256",
+        ));
+    }
+    if submission.submitted_at > chrono::Utc::now() + chrono::Duration::seconds(MAX_CLOCK_SKEW_SECS)
+    {
+        return Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            "Submission is dated in the future",
+        ));
+    }
+
+    let keys = issuer_keys(&state, &submission).await?;
+    if !keys
+        .iter()
+        .any(|key| verify_log_submission(&submission, key).is_ok())
+    {
+        return Err(ApiError::new(
+            ErrorCode::InvalidSignature,
+            format!("Submission is not signed by {}", submission.issuer),
+        ));
+    }
+
+    let issuer = submission.issuer.clone();
+    let mut log = state.lock();
+    if let Some(leaf_index) = log.store.find(hash) {
+        let tree_head = current_head(log.head.as_ref())?;
+        return Ok(Json(SubmissionResponse {
+            leaf_index,
+            tree_head,
+        }));
+    }
+    let hash = hash.clone();
+    let leaf_index = log.store.append(vec![submission]).map_err(|e| {
+        tracing::error!("Failed to append entry: {}", e);
+        ApiError::new(ErrorCode::StorageFailed, "Failed to store the entry")
+    })?;
+    let tree_head = state.sign_head(&log.store).map_err(|e| {
+        tracing::error!("Failed to sign tree head: {}", e);
+        ApiError::new(ErrorCode::SigningFailed, "Failed to sign the tree head")
+    })?;
+    log.head = Some(tree_head.clone());
+    tracing::info!(
+        "Logged attestation {} from {} as entry {}",
+        hash,
+        issuer,
+        leaf_index
+    );
+    Ok(Json(SubmissionResponse {
+        leaf_index,
+        tree_head,
+    }))
+}
+
+/// Keys the submitting issuer may have signed with.
+async fn issuer_keys(
+    state: &AppState,
+    submission: &LogSubmission,
+) -> Result<Vec<PublicKey>, ApiError> {
+    if let Some(issuers) = &state.issuers {
+        let keys: Vec<PublicKey> = issuers.keys_for(&submission.issuer).cloned().collect();
+        if keys.is_empty() {
+            return Err(ApiError::new(
+                ErrorCode::Forbidden,
+                format!("{} may not submit to this log", submission.issuer),
+            ));
+        }
+        return Ok(keys);
+    }
+    match resolve_issuer_key(&submission.issuer, submission.key_id.as_deref()).await {
+        Ok((_, key)) => Ok(vec![key]),
+        Err(HeshaError::InvalidAttestation(detail)) => {
+            Err(ApiError::new(ErrorCode::InvalidKey, detail))
+        }
+        Err(e) => {
+            tracing::warn!("Could not fetch keys of {}: {}", submission.issuer, e);
+            Err(ApiError::new(
+                ErrorCode::IssuerUnavailable,
+                format!("Could not fetch keys of {}: {}", submission.issuer, e),
+            ))
+        }
+    }
+}
+
+/// `GET /tree-head`: the latest signed tree head.
+pub async fn tree_head(State(state): State<AppState>) -> Result<Json<SignedTreeHead>, ApiError> {
+    Ok(Json(current_head(state.lock().head.as_ref())?))
+}
+
+/// Query of `GET /proof/inclusion`.
+#[derive(Debug, Deserialize)]
+pub struct InclusionQuery {
+    /// Hex SHA-256 of the attestation.
+    hash: String,
+    /// Tree size to prove inclusion in; the latest head's by default.
+    tree_size: Option<u64>,
+}
+
+/// `GET /proof/inclusion`: an attestation's entry and the proof that it is
+/// included in a tree.
+pub async fn inclusion(
+    State(state): State<AppState>,
+    Query(query): Query<InclusionQuery>,
+) -> Result<Json<LogInclusion>, ApiError> {
+    let log = state.lock();
+    let head_size = current_head(log.head.as_ref())?.tree_size;
+    let tree_size = query.tree_size.unwrap_or(head_size);
+    let Some(entry) = log
+        .store
+        .find(&query.hash)
+        .and_then(|index| log.store.entry(index))
+    else {
+        return Err(ApiError::new(
+            ErrorCode::NotFound,
+            "Attestation is not logged",
+        ));
+    };
+    if tree_size > head_size {
+        return Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            format!("The tree has {} entries", head_size),
+        ));
+    }
+    let leaf_index = entry.index;
+    let audit_path = log
+        .store
+        .tree()
+        .inclusion_proof(leaf_index, tree_size)
+        .ok_or_else(|| {
+            ApiError::new(
+                ErrorCode::NotFound,
+                format!("Attestation was logged after entry {}", tree_size),
+            )
+        })?;
+    Ok(Json(LogInclusion {
+        entry,
+        proof: InclusionProof {
+            leaf_index,
+            tree_size,
+            audit_path: audit_path.iter().map(hex::encode).collect(),
+        },
+    }))
+}
+
+/// Query of `GET /proof/consistency`.
+#[derive(Debug, Deserialize)]
+pub struct ConsistencyQuery {
+    /// Size of the older tree.
+    first: u64,
+    /// Size of the newer tree; the latest head's by default.
+    second: Option<u64>,
+}
+
+/// `GET /proof/consistency`: proof that a tree extends an older one.
+pub async fn consistency(
+    State(state): State<AppState>,
+    Query(query): Query<ConsistencyQuery>,
+) -> Result<Json<ConsistencyProof>, ApiError> {
+    let log = state.lock();
+    let head_size = current_head(log.head.as_ref())?.tree_size;
+    let second = query.second.unwrap_or(head_size);
+    if query.first > second || second > head_size {
+        return Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            format!("Tree sizes must satisfy first <= second <= {}", head_size),
+        ));
+    }
+    let proof = log
+        .store
+        .tree()
+        .consistency_proof(query.first, second)
+        .unwrap_or_default();
+    Ok(Json(ConsistencyProof {
+        first: query.first,
+        second,
+        proof: proof.iter().map(hex::encode).collect(),
+    }))
+}
+
+/// Query of `GET /entries`.
+#[derive(Debug, Deserialize)]
+pub struct EntriesQuery {
+    /// First entry.
+    start: u64,
+    /// Entry after the last one.
+    end: u64,
+}
+
+/// `GET /entries`: entries `start..end` of the latest tree, at most
+/// [`MAX_ENTRIES`] of them.
+pub async fn entries(
+    State(state): State<AppState>,
+    Query(query): Query<EntriesQuery>,
+) -> Result<Json<Vec<LogEntry>>, ApiError> {
+    let log = state.lock();
+    let head_size = current_head(log.head.as_ref())?.tree_size;
+    if query.start > query.end {
+        return Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            "start must not exceed end",
+        ));
+    }
+    let end = query
+        .end
+        .min(head_size)
+        .min(query.start.saturating_add(MAX_ENTRIES));
+    Ok(Json(log.store.entries(query.start, end)))
+}
+
+/// The latest tree head, which a mirror lacks until its first sync.
+fn current_head(head: Option<&SignedTreeHead>) -> Result<SignedTreeHead, ApiError> {
+    head.cloned().ok_or_else(|| {
+        ApiError::new(
+            ErrorCode::Maintenance,
+            "Mirror has not synced with its log yet",
+        )
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::Config;
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+    use hesha_core::{
+        attestation_hash, check_consistency, check_inclusion, sign_log_submission, verify_tree_head,
+    };
+    use hesha_crypto::generate_keypair;
+    use hesha_types::{KeyPair, Problem};
+    use serde::de::DeserializeOwned;
+    use tower::util::ServiceExt;
+
+    /// A primary log in `dir` accepting submissions from `issuer`.
+    pub(crate) fn primary(dir: &std::path::Path, issuer: &KeyPair) -> AppState {
+        std::fs::create_dir_all(dir).unwrap();
+        let bundle = dir.join("issuers.json");
+        let json = serde_json::json!({
+            "issuers": [{"domain": "issuer.example.com", "public_key": issuer.public.to_base64()}]
+        });
+        std::fs::write(&bundle, json.to_string()).unwrap();
+        let config = Config {
+            data_dir: dir.join("data"),
+            log_id: "log.example.com".to_string(),
+            issuers: Some(bundle),
+            ..Config::default()
+        };
+        AppState::open(config).unwrap()
+    }
+
+    pub(crate) fn submission(issuer: &KeyPair, jwt: &str) -> LogSubmission {
+        let mut submission = LogSubmission {
+            issuer: "issuer.example.com".to_string(),
+            attestation_hash: attestation_hash(jwt),
+            key_id: None,
+            submitted_at: chrono::Utc::now(),
+            signature: None,
+        };
+        sign_log_submission(&mut submission, &issuer.private).unwrap();
+        submission
+    }
+
+    pub(crate) async fn call<T: DeserializeOwned>(
+        state: &AppState,
+        request: Request<Body>,
+    ) -> Result<T, (StatusCode, Problem)> {
+        let response = crate::app(state.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        if status.is_success() {
+            Ok(serde_json::from_slice(&body).unwrap())
+        } else {
+            Err((status, serde_json::from_slice(&body).unwrap()))
+        }
+    }
+
+    pub(crate) async fn submit(
+        state: &AppState,
+        submission: &LogSubmission,
+    ) -> Result<SubmissionResponse, (StatusCode, Problem)> {
+        let request = Request::post("/submissions")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(serde_json::to_vec(submission).unwrap()))
+            .unwrap();
+        call(state, request).await
+    }
+
+    async fn get<T: DeserializeOwned>(
+        state: &AppState,
+        uri: &str,
+    ) -> Result<T, (StatusCode, Problem)> {
+        call(state, Request::get(uri).body(Body::empty()).unwrap()).await
+    }
+
+    fn log_key(state: &AppState) -> PublicKey {
+        match &*state.role {
+            Role::Primary { keypair } => keypair.public.clone(),
+            Role::Mirror { key, .. } => key.clone(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_submit_and_prove() {
+        let dir = tempfile::tempdir().unwrap();
+        let issuer = generate_keypair().unwrap();
+        let state = primary(dir.path(), &issuer);
+        let empty: SignedTreeHead = get(&state, "/tree-head").await.unwrap();
+        assert_eq!(empty.tree_size, 0);
+
+        let first = submit(&state, &submission(&issuer, "jwt-0")).await.unwrap();
+        assert_eq!(first.leaf_index, 0);
+        for i in 1..5 {
+            submit(&state, &submission(&issuer, &format!("jwt-{}", i)))
+                .await
+                .unwrap();
+        }
+        let again = submit(&state, &submission(&issuer, "jwt-2")).await.unwrap();
+        assert_eq!(again.leaf_index, 2);
+        let head = again.tree_head;
+        assert_eq!(head.tree_size, 5);
+        verify_tree_head(&head, &log_key(&state)).unwrap();
+
+        let hash = attestation_hash("jwt-3");
+        let inclusion: LogInclusion = get(&state, &format!("/proof/inclusion?hash={}", hash))
+            .await
+            .unwrap();
+        check_inclusion(&inclusion.entry.submission, &inclusion.proof, &head).unwrap();
+        let (status, _) = get::<LogInclusion>(
+            &state,
+            &format!("/proof/inclusion?hash={}&tree_size=2", hash),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let proof: ConsistencyProof = get(&state, "/proof/consistency?first=1").await.unwrap();
+        check_consistency(&proof, &first.tree_head, &head).unwrap();
+        let (status, _) = get::<ConsistencyProof>(&state, "/proof/consistency?first=1&second=6")
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let entries: Vec<LogEntry> = get(&state, "/entries?start=3&end=10").await.unwrap();
+        assert_eq!(entries.iter().map(|e| e.index).collect::<Vec<_>>(), [3, 4]);
+
+        // The log survives a restart, with a freshly signed head
+        drop(state);
+        let state = primary(dir.path(), &issuer);
+        let reopened: SignedTreeHead = get(&state, "/tree-head").await.unwrap();
+        assert_eq!(
+            (reopened.tree_size, &reopened.root_hash),
+            (5, &head.root_hash)
+        );
+        verify_tree_head(&reopened, &log_key(&state)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_refused_submissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let issuer = generate_keypair().unwrap();
+        let state = primary(dir.path(), &issuer);
+
+        let forged = submission(&generate_keypair().unwrap(), "jwt");
+        let (status, problem) = submit(&state, &forged).await.unwrap_err();
+        assert_eq!(
+            (status, problem.code()),
+            (StatusCode::UNAUTHORIZED, Some(ErrorCode::InvalidSignature))
+        );
+
+        let mut unknown = submission(&issuer, "jwt");
+        unknown.issuer = "other.example.com".to_string();
+        sign_log_submission(&mut unknown, &issuer.private).unwrap();
+        let (status, _) = submit(&state, &unknown).await.unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let mut malformed = submission(&issuer, "jwt");
+        malformed.attestation_hash = "ABC".to_string();
+        sign_log_submission(&mut malformed, &issuer.private).unwrap();
+        let (status, _) = submit(&state, &malformed).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let head: SignedTreeHead = get(&state, "/tree-head").await.unwrap();
+        assert_eq!(head.tree_size, 0);
+    }
+}
@@ -0,0 +1,86 @@
+//! Log configuration.
+//!
+//! Every setting is a flag with an `HESHA_LOG_*` environment variable.
+
+use clap::Parser;
+use hesha_core::TrustBundle;
+use hesha_types::PublicKey;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Hesha transparency log.
+#[derive(Debug, Clone, Parser)]
+#[command(name = "hesha-log", version, about)]
+pub struct Config {
+    /// Address to listen on.
+    #[arg(long, env = "HESHA_LOG_BIND_ADDRESS", default_value = "0.0.0.0:3200")]
+    pub bind_address: SocketAddr,
+
+    /// Directory holding the entries and the log key.
+    #[arg(long, env = "HESHA_LOG_DATA_DIR", default_value = "log-data")]
+    pub data_dir: PathBuf,
+
+    /// Name of the log in its tree heads, usually its domain.
+    #[arg(long, env = "HESHA_LOG_ID", default_value = "localhost")]
+    pub log_id: String,
+
+    /// Trust bundle of the issuers allowed to submit. Without one, any
+    /// issuer may, its keys discovered through its domain.
+    #[arg(long, env = "HESHA_LOG_ISSUERS")]
+    pub issuers: Option<PathBuf>,
+
+    /// URL of a log to mirror. A mirror copies that log's entries and
+    /// serves its tree heads, and accepts no submissions.
+    #[arg(long, env = "HESHA_LOG_MIRROR", requires = "mirror_key")]
+    pub mirror: Option<String>,
+
+    /// Public key (base64url) of the mirrored log.
+    #[arg(long, env = "HESHA_LOG_MIRROR_KEY")]
+    pub mirror_key: Option<String>,
+
+    /// How often a mirror polls the mirrored log, in seconds.
+    #[arg(long, env = "HESHA_LOG_MIRROR_INTERVAL_SECS", default_value_t = 60)]
+    pub mirror_interval_secs: u64,
+
+    /// Largest request body accepted, in bytes.
+    #[arg(long, env = "HESHA_LOG_MAX_BODY_BYTES", default_value_t = 16 * 1024)]
+    pub max_body_bytes: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::parse_from(["hesha-log"])
+    }
+}
+
+impl Config {
+    /// The issuers allowed to submit, if restricted.
+    pub fn issuers(&self) -> anyhow::Result<Option<TrustBundle>> {
+        let Some(path) = &self.issuers else {
+            return Ok(None);
+        };
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+        let bundle = TrustBundle::from_json(&data)
+            .map_err(|e| anyhow::anyhow!("Invalid trust bundle {}: {}", path.display(), e))?;
+        Ok(Some(bundle))
+    }
+
+    /// The mirrored log's URL and key, when mirroring.
+    pub fn mirror(&self) -> anyhow::Result<Option<(String, PublicKey)>> {
+        match (&self.mirror, &self.mirror_key) {
+            (Some(url), Some(key)) => {
+                let key = PublicKey::from_base64(key)
+                    .map_err(|e| anyhow::anyhow!("Invalid mirror key: {}", e))?;
+                Ok(Some((url.trim_end_matches('/').to_string(), key)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Time between two polls of the mirrored log.
+    pub fn mirror_interval(&self) -> Duration {
+        Duration::from_secs(self.mirror_interval_secs)
+    }
+}
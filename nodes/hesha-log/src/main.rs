@@ -0,0 +1,93 @@
+//! Hesha Protocol transparency log.
+//!
+//! Issuers submit a signed hash of each attestation they issue; the log
+//! appends it to a Merkle tree and serves signed tree heads, inclusion
+//! proofs and consistency proofs, so anyone can check that an attestation
+//! was logged and that the log never rewrites its history. Run with
+//! `--mirror`, the node keeps a verified copy of another log instead.
+
+mod api;
+mod config;
+mod mirror;
+mod state;
+mod store;
+
+use crate::config::Config;
+use crate::state::{AppState, Role};
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{get, post},
+    Json, Router,
+};
+use clap::Parser;
+use hesha_node::shutdown_signal;
+use tower_http::trace::TraceLayer;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "hesha_log=debug,tower_http=info".into()),
+        )
+        .init();
+
+    let config = Config::parse();
+    let addr = config.bind_address;
+    let state = AppState::open(config)?;
+
+    match &*state.role {
+        Role::Primary { keypair } => {
+            tracing::info!(
+                "Log {} with key {}",
+                state.config.log_id,
+                keypair.public.to_base64()
+            );
+        }
+        Role::Mirror { url, .. } => {
+            tracing::info!("Mirroring {}", url);
+            mirror::spawn(state.clone());
+        }
+    }
+    tracing::info!(
+        "Log listening on {} with {} entries",
+        addr,
+        state.lock().store.len()
+    );
+    tracing::info!("  POST   /submissions        - Log an attestation hash");
+    tracing::info!("  GET    /tree-head          - Latest signed tree head");
+    tracing::info!("  GET    /proof/inclusion    - Inclusion proof for an attestation");
+    tracing::info!("  GET    /proof/consistency  - Consistency proof between tree sizes");
+    tracing::info!("  GET    /entries            - Entries, for auditors and mirrors");
+    tracing::info!("  GET    /health             - Health check");
+
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    axum::serve(listener, app(state))
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+    Ok(())
+}
+
+/// The node's routes.
+fn app(state: AppState) -> Router {
+    let max_body_bytes = state.config.max_body_bytes;
+    Router::new()
+        .route("/submissions", post(api::submit))
+        .route("/tree-head", get(api::tree_head))
+        .route("/proof/inclusion", get(api::inclusion))
+        .route("/proof/consistency", get(api::consistency))
+        .route("/entries", get(api::entries))
+        .route("/health", get(health))
+        .fallback(hesha_node::not_found)
+        .layer(DefaultBodyLimit::max(max_body_bytes))
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
+
+/// `GET /health`.
+async fn health() -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+    }))
+}
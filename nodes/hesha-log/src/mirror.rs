@@ -0,0 +1,224 @@
+//! Mirroring another log.
+//!
+//! A mirror polls the mirrored log's tree head, checks its signature,
+//! fetches the entries it doesn't have and recomputes the tree. It only
+//! stores them, and serves the head, if the recomputed root matches: since
+//! the copy is built on the entries it already had, that also proves the
+//! new head extends every head it served before. A log that rewrites its
+//! history therefore stops the mirror with an error instead of being
+//! copied.
+
+use crate::api::MAX_ENTRIES;
+use crate::state::{AppState, Role};
+use hesha_core::verify_tree_head;
+use hesha_types::{LogEntry, SignedTreeHead};
+use std::time::Duration;
+
+/// Poll the mirrored log in the background.
+pub fn spawn(state: AppState) {
+    let interval = state.config.mirror_interval();
+    tokio::spawn(async move {
+        let client = client();
+        loop {
+            if let Err(e) = sync(&state, &client).await {
+                tracing::error!("Mirror sync failed: {}", e);
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+/// HTTP client for the mirrored log.
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .unwrap_or_default()
+}
+
+/// Bring the copy up to the mirrored log's latest tree head.
+pub async fn sync(state: &AppState, client: &reqwest::Client) -> anyhow::Result<()> {
+    let Role::Mirror { url, key } = &*state.role else {
+        anyhow::bail!("Only a mirror syncs");
+    };
+    let head: SignedTreeHead = client
+        .get(format!("{}/tree-head", url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    verify_tree_head(&head, key)
+        .map_err(|_| anyhow::anyhow!("Tree head of {} is not signed with its key", url))?;
+
+    let (mut tree, served) = {
+        let log = state.lock();
+        (
+            log.store.tree().clone(),
+            log.head.as_ref().map_or(0, |head| head.tree_size),
+        )
+    };
+    if head.tree_size < tree.len() || head.tree_size < served {
+        anyhow::bail!(
+            "{} shrank to {} entries, from {}",
+            url,
+            head.tree_size,
+            tree.len().max(served)
+        );
+    }
+
+    let mut fetched = Vec::new();
+    while tree.len() < head.tree_size {
+        let start = tree.len();
+        let end = head.tree_size.min(start + MAX_ENTRIES);
+        let page: Vec<LogEntry> = client
+            .get(format!("{}/entries?start={}&end={}", url, start, end))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if page.is_empty() {
+            anyhow::bail!("{} served no entries from {}", url, start);
+        }
+        for entry in page {
+            if entry.index != tree.len() {
+                anyhow::bail!(
+                    "{} served entry {} in place of {}",
+                    url,
+                    entry.index,
+                    tree.len()
+                );
+            }
+            tree.push(hesha_core::transparency::submission_leaf_hash(
+                &entry.submission,
+            ));
+            fetched.push(entry.submission);
+        }
+    }
+    if tree.root_at(head.tree_size).map(hex::encode).as_deref() != Some(head.root_hash.as_str()) {
+        anyhow::bail!("Tree head of {} does not match its entries", url);
+    }
+
+    let mut log = state.lock();
+    if !fetched.is_empty() {
+        log.store.append(fetched)?;
+        tracing::info!("Mirrored {} up to {} entries", url, head.tree_size);
+    }
+    log.head = Some(head);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::tests::{call, primary, submission, submit};
+    use crate::config::Config;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use hesha_crypto::generate_keypair;
+
+    /// Serve `state` on a local port, returning its URL.
+    async fn serve(state: AppState) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, crate::app(state)).await });
+        url
+    }
+
+    fn mirror(dir: &std::path::Path, url: &str, key: &hesha_types::PublicKey) -> AppState {
+        let config = Config {
+            data_dir: dir.to_path_buf(),
+            mirror: Some(url.to_string()),
+            mirror_key: Some(key.to_base64()),
+            ..Config::default()
+        };
+        AppState::open(config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_mirror() {
+        let dir = tempfile::tempdir().unwrap();
+        let issuer = generate_keypair().unwrap();
+        let log = primary(dir.path(), &issuer);
+        let Role::Primary { keypair } = &*log.role else {
+            unreachable!()
+        };
+        let log_key = keypair.public.clone();
+        for i in 0..3 {
+            submit(&log, &submission(&issuer, &format!("jwt-{}", i)))
+                .await
+                .unwrap();
+        }
+        let url = serve(log.clone()).await;
+
+        let copy = mirror(&dir.path().join("mirror"), &url, &log_key);
+        let request = || Request::get("/tree-head").body(Body::empty()).unwrap();
+        let (status, _) = call::<SignedTreeHead>(&copy, request()).await.unwrap_err();
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+        sync(&copy, &client()).await.unwrap();
+        submit(&log, &submission(&issuer, "jwt-3")).await.unwrap();
+        sync(&copy, &client()).await.unwrap();
+        let head: SignedTreeHead = call(&copy, request()).await.unwrap();
+        assert_eq!(head, log.lock().head.clone().unwrap());
+        assert_eq!(
+            copy.lock().store.tree().root(),
+            log.lock().store.tree().root()
+        );
+
+        let (status, _) = submit(&copy, &submission(&issuer, "jwt-4"))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // The copy survives a restart, and only serves heads after a sync
+        drop(copy);
+        let copy = mirror(&dir.path().join("mirror"), &url, &log_key);
+        assert_eq!(copy.lock().store.len(), 4);
+        sync(&copy, &client()).await.unwrap();
+
+        // Heads not signed with the mirrored log's key are refused
+        let impostor = mirror(
+            &dir.path().join("impostor"),
+            &url,
+            &generate_keypair().unwrap().public,
+        );
+        assert!(sync(&impostor, &client()).await.is_err());
+        assert!(impostor.lock().head.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rewritten_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let issuer = generate_keypair().unwrap();
+        let log = primary(&dir.path().join("a"), &issuer);
+        let Role::Primary { keypair } = &*log.role else {
+            unreachable!()
+        };
+        submit(&log, &submission(&issuer, "jwt-0")).await.unwrap();
+        let url = serve(log.clone()).await;
+        let copy = mirror(&dir.path().join("mirror"), &url, &keypair.public);
+        sync(&copy, &client()).await.unwrap();
+
+        // The log's key signing a tree that drops the mirrored entry
+        let forked = primary(&dir.path().join("b"), &issuer);
+        std::fs::copy(
+            dir.path().join("a/data/log.key"),
+            dir.path().join("b/data/log.key"),
+        )
+        .unwrap();
+        let forked = AppState::open((*forked.config).clone()).unwrap();
+        submit(&forked, &submission(&issuer, "jwt-other"))
+            .await
+            .unwrap();
+        submit(&forked, &submission(&issuer, "jwt-1"))
+            .await
+            .unwrap();
+        let forked_url = serve(forked).await;
+
+        let copy = mirror(&dir.path().join("mirror"), &forked_url, &keypair.public);
+        assert!(sync(&copy, &client()).await.is_err());
+        assert_eq!(copy.lock().store.len(), 1);
+    }
+}
@@ -0,0 +1,118 @@
+//! Shared state of the log.
+
+use crate::config::Config;
+use crate::store::Store;
+use hesha_core::{sign_tree_head, TrustBundle};
+use hesha_crypto::{generate_keypair, keypair_from_private};
+use hesha_types::{KeyPair, PrivateKey, PublicKey, SignedTreeHead};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Name of the log key file in the data directory.
+const KEY_FILE: &str = "log.key";
+
+/// What the node is.
+pub enum Role {
+    /// A log accepting submissions and signing its tree heads.
+    Primary {
+        /// Key tree heads are signed with.
+        keypair: KeyPair,
+    },
+    /// A copy of another log.
+    Mirror {
+        /// URL of the mirrored log.
+        url: String,
+        /// Key its tree heads are signed with.
+        key: PublicKey,
+    },
+}
+
+/// The entries and the tree head covering them.
+pub struct Log {
+    /// The entries.
+    pub store: Store,
+    /// Latest tree head; a mirror has none until its first sync.
+    pub head: Option<SignedTreeHead>,
+}
+
+/// State shared by every request.
+#[derive(Clone)]
+pub struct AppState {
+    /// Node configuration.
+    pub config: Arc<Config>,
+    /// Whether the node is a log or a mirror.
+    pub role: Arc<Role>,
+    /// Issuers allowed to submit, if restricted.
+    pub issuers: Option<Arc<TrustBundle>>,
+    /// The log itself.
+    pub log: Arc<Mutex<Log>>,
+}
+
+impl AppState {
+    /// Open the log in the configured data directory, creating the log key
+    /// on first start.
+    pub fn open(config: Config) -> anyhow::Result<Self> {
+        let store = Store::open(&config.data_dir)?;
+        let role = match config.mirror()? {
+            Some((url, key)) => Role::Mirror { url, key },
+            None => Role::Primary {
+                keypair: load_or_create_key(&config.data_dir.join(KEY_FILE))?,
+            },
+        };
+        let state = Self {
+            issuers: config.issuers()?.map(Arc::new),
+            config: Arc::new(config),
+            role: Arc::new(role),
+            log: Arc::new(Mutex::new(Log { store, head: None })),
+        };
+        if let Role::Primary { .. } = *state.role {
+            let mut log = state.lock();
+            log.head = Some(state.sign_head(&log.store)?);
+        }
+        Ok(state)
+    }
+
+    /// Lock the log.
+    pub fn lock(&self) -> MutexGuard<'_, Log> {
+        self.log.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// A tree head for all of `store`, signed with the log key. Only a
+    /// primary log signs heads.
+    pub fn sign_head(&self, store: &Store) -> anyhow::Result<SignedTreeHead> {
+        let Role::Primary { keypair } = &*self.role else {
+            anyhow::bail!("A mirror does not sign tree heads");
+        };
+        let mut head = SignedTreeHead {
+            log_id: self.config.log_id.clone(),
+            tree_size: store.len(),
+            root_hash: hex::encode(store.tree().root()),
+            timestamp: chrono::Utc::now(),
+            signature: None,
+        };
+        sign_tree_head(&mut head, &keypair.private)?;
+        Ok(head)
+    }
+}
+
+/// Read the log key, or generate it if there is none yet.
+fn load_or_create_key(path: &Path) -> anyhow::Result<KeyPair> {
+    if path.exists() {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read key {}: {}", path.display(), e))?;
+        return Ok(keypair_from_private(&PrivateKey::from_base64(
+            data.trim(),
+        )?)?);
+    }
+
+    let keypair = generate_keypair()?;
+    std::fs::write(path, keypair.private.to_base64())
+        .map_err(|e| anyhow::anyhow!("Cannot write key {}: {}", path.display(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    tracing::info!("Generated log key {}", keypair.public.to_base64());
+    Ok(keypair)
+}
@@ -0,0 +1,174 @@
+//! The log's entries, on disk and as a Merkle tree.
+//!
+//! Entries are appended to `entries.jsonl` in the data directory, one JSON
+//! [`LogEntry`] per line, and synced before the call returns. The tree and
+//! the index of attestation hashes are rebuilt from the file at startup. A
+//! line torn by a crash mid-write is dropped then, as its submission was
+//! never acknowledged.
+
+use hesha_core::transparency::submission_leaf_hash;
+use hesha_core::MerkleTree;
+use hesha_types::{LogEntry, LogSubmission};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// Name of the entries file in the data directory.
+const ENTRIES_FILE: &str = "entries.jsonl";
+
+/// The log's entries.
+pub struct Store {
+    file: File,
+    entries: Vec<LogSubmission>,
+    tree: MerkleTree,
+    by_hash: HashMap<String, u64>,
+}
+
+impl Store {
+    /// Open the entries in `dir`, creating the directory if needed.
+    pub fn open(dir: &Path) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| anyhow::anyhow!("Cannot create {}: {}", dir.display(), e))?;
+        let path = dir.join(ENTRIES_FILE);
+        let data = match std::fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(anyhow::anyhow!("Cannot read {}: {}", path.display(), e)),
+        };
+
+        // Everything after the last newline is a torn write
+        let complete = data.rfind('\n').map_or(0, |end| end + 1);
+        if complete < data.len() {
+            tracing::warn!("Dropping a torn entry at the end of {}", path.display());
+            OpenOptions::new()
+                .write(true)
+                .open(&path)?
+                .set_len(complete as u64)?;
+        }
+
+        let mut store = Self {
+            file: OpenOptions::new().create(true).append(true).open(&path)?,
+            entries: Vec::new(),
+            tree: MerkleTree::new(),
+            by_hash: HashMap::new(),
+        };
+        for (line, text) in data[..complete].lines().enumerate() {
+            let entry: LogEntry = serde_json::from_str(text)
+                .map_err(|e| anyhow::anyhow!("{} line {}: {}", path.display(), line + 1, e))?;
+            if entry.index != store.len() {
+                anyhow::bail!(
+                    "{} line {}: expected entry {}",
+                    path.display(),
+                    line + 1,
+                    store.len()
+                );
+            }
+            store.insert(entry.submission);
+        }
+        Ok(store)
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> u64 {
+        self.tree.len()
+    }
+
+    /// The tree of the entries.
+    pub fn tree(&self) -> &MerkleTree {
+        &self.tree
+    }
+
+    /// Index of the entry for the attestation `hash`.
+    pub fn find(&self, hash: &str) -> Option<u64> {
+        self.by_hash.get(hash).copied()
+    }
+
+    /// The entry at `index`.
+    pub fn entry(&self, index: u64) -> Option<LogEntry> {
+        let submission = self.entries.get(usize::try_from(index).ok()?)?;
+        Some(LogEntry {
+            index,
+            submission: submission.clone(),
+        })
+    }
+
+    /// Entries `start..end`, as far as there are.
+    pub fn entries(&self, start: u64, end: u64) -> Vec<LogEntry> {
+        (start..end.min(self.len()))
+            .filter_map(|index| self.entry(index))
+            .collect()
+    }
+
+    /// Append submissions, returning the index of the first.
+    pub fn append(&mut self, submissions: Vec<LogSubmission>) -> anyhow::Result<u64> {
+        let first = self.len();
+        let mut lines = Vec::new();
+        for (offset, submission) in submissions.iter().enumerate() {
+            let entry = LogEntry {
+                index: first + offset as u64,
+                submission: submission.clone(),
+            };
+            serde_json::to_writer(&mut lines, &entry)?;
+            lines.push(b'\n');
+        }
+        self.file.write_all(&lines)?;
+        self.file.sync_data()?;
+        for submission in submissions {
+            self.insert(submission);
+        }
+        Ok(first)
+    }
+
+    fn insert(&mut self, submission: LogSubmission) {
+        let index = self.tree.push(submission_leaf_hash(&submission));
+        self.by_hash
+            .entry(submission.attestation_hash.clone())
+            .or_insert(index);
+        self.entries.push(submission);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn submission(hash: &str) -> LogSubmission {
+        LogSubmission {
+            issuer: "issuer.example.com".to_string(),
+            attestation_hash: hash.to_string(),
+            key_id: None,
+            submitted_at: Utc::now(),
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = Store::open(dir.path()).unwrap();
+        assert_eq!(
+            store
+                .append(vec![submission("a"), submission("b")])
+                .unwrap(),
+            0
+        );
+        assert_eq!(store.append(vec![submission("c")]).unwrap(), 2);
+        let root = store.tree().root();
+        drop(store);
+
+        // A torn write is dropped
+        let path = dir.path().join(ENTRIES_FILE);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"index": 3, "subm"#).unwrap();
+
+        let mut store = Store::open(dir.path()).unwrap();
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.tree().root(), root);
+        assert_eq!(store.find("b"), Some(1));
+        assert_eq!(store.entries(1, 10).len(), 2);
+        assert_eq!(store.append(vec![submission("d")]).unwrap(), 3);
+        assert_eq!(Store::open(dir.path()).unwrap().len(), 4);
+    }
+}
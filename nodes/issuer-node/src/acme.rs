@@ -0,0 +1,658 @@
+//! Certificates from an ACME certificate authority such as Let's Encrypt.
+//!
+//! With `[acme]` set, the node obtains a certificate for its domains on
+//! first start, keeps it in `cache_dir`, and renews it in the background
+//! ahead of expiry, swapping it into the running listener without a
+//! restart. Domain ownership is proven with HTTP-01: the authority fetches
+//! `/.well-known/acme-challenge/<token>` over plain HTTP, which a small
+//! listener on `http_port` answers. Everything else sent to that listener
+//! is redirected to HTTPS.
+//!
+//! Only the parts of RFC 8555 needed for that are implemented: account
+//! registration, orders, HTTP-01 authorizations and finalization.
+
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Redirect, Response},
+    routing::get,
+    Router,
+};
+use crate::metrics::Metrics;
+use crate::tls::certificate_expiry;
+use axum_server::tls_rustls::RustlsConfig;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hesha_types::{AcmeConfig, TlsConfig};
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Route the certificate authority fetches challenge responses from.
+pub const CHALLENGE_PATH: &str = "/.well-known/acme-challenge/:token";
+
+/// How often the certificate's expiry is checked.
+const RENEWAL_CHECK_INTERVAL: Duration = Duration::from_secs(12 * 3600);
+
+/// Delay between polls of a pending authorization or order.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Polls before giving up on an authorization or order.
+const MAX_POLLS: usize = 30;
+
+/// Key authorizations by challenge token, while a challenge is pending.
+pub type Challenges = Arc<RwLock<HashMap<String, String>>>;
+
+/// Obtains, caches and renews the node's certificate.
+pub struct CertificateManager {
+    config: AcmeConfig,
+    domains: Vec<String>,
+    challenges: Challenges,
+    http: reqwest::Client,
+}
+
+impl CertificateManager {
+    /// Set up for `[acme]`, covering `trust_domain` unless domains are listed.
+    pub fn new(config: &AcmeConfig, trust_domain: &str) -> anyhow::Result<Self> {
+        let domains = if config.domains.is_empty() {
+            vec![trust_domain.split(':').next().unwrap_or_default().to_string()]
+        } else {
+            config.domains.clone()
+        };
+        for domain in &domains {
+            anyhow::ensure!(
+                domain.contains('.') && domain != "localhost" && domain.parse::<std::net::IpAddr>().is_err(),
+                "[acme] can't obtain a certificate for {:?}; it needs a public domain name",
+                domain
+            );
+        }
+        std::fs::create_dir_all(&config.cache_dir)
+            .map_err(|e| anyhow::anyhow!("Cannot create ACME cache directory {}: {}", config.cache_dir, e))?;
+
+        Ok(Self {
+            config: config.clone(),
+            domains,
+            challenges: Challenges::default(),
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Where the certificate and its key are kept.
+    pub fn tls_config(&self) -> TlsConfig {
+        TlsConfig {
+            cert_path: self.path("cert.pem").display().to_string(),
+            key_path: self.path("key.pem").display().to_string(),
+            client_ca_path: None,
+        }
+    }
+
+    /// Answer HTTP-01 challenges on `http_port`, redirecting other requests
+    /// to HTTPS on `https_port`.
+    pub async fn listen_for_challenges(&self, https_port: u16) -> anyhow::Result<()> {
+        let addr = SocketAddr::from(([0, 0, 0, 0], self.config.http_port));
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|e| anyhow::anyhow!("Cannot listen for ACME challenges on {}: {}", addr, e))?;
+        let app = challenge_router(self.challenges.clone())
+            .fallback(move |headers: HeaderMap, uri: Uri| async move { redirect_to_https(&headers, &uri, https_port) });
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).with_graceful_shutdown(crate::shutdown_signal()).await {
+                tracing::error!("ACME challenge listener failed: {}", e);
+            }
+        });
+        tracing::info!("Answering ACME challenges on http://{}", addr);
+        Ok(())
+    }
+
+    /// Obtain a certificate unless a cached one is still good.
+    pub async fn ensure_certificate(&self) -> anyhow::Result<()> {
+        if !self.renewal_due() {
+            tracing::info!("Using cached certificate for {}", self.domains.join(", "));
+            return Ok(());
+        }
+        tracing::info!("Requesting certificate for {} from {}", self.domains.join(", "), self.config.directory_url);
+        self.issue().await?;
+        tracing::info!("Certificate for {} issued", self.domains.join(", "));
+        Ok(())
+    }
+
+    /// Renew the certificate ahead of expiry and load it into the listener.
+    pub fn renew_in_background(self, rustls_config: RustlsConfig, metrics: Arc<Metrics>) {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(RENEWAL_CHECK_INTERVAL).await;
+                if !self.renewal_due() {
+                    continue;
+                }
+                let renewed = match self.issue().await {
+                    Ok(()) => crate::tls::server_config(&self.tls_config()),
+                    Err(e) => Err(e),
+                };
+                match renewed {
+                    Ok(server_config) => {
+                        rustls_config.reload_from_config(Arc::new(server_config));
+                        crate::tls::record_expiry(&self.tls_config(), &metrics);
+                        tracing::info!("Certificate for {} renewed", self.domains.join(", "));
+                    }
+                    // The old certificate is still valid; try again at the next check
+                    Err(e) => tracing::error!("Certificate renewal failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Whether there is no cached certificate or it expires soon.
+    fn renewal_due(&self) -> bool {
+        let Ok(pem) = std::fs::read(self.path("cert.pem")) else {
+            return true;
+        };
+        if !self.path("key.pem").is_file() {
+            return true;
+        }
+        match certificate_expiry(&pem) {
+            Ok(expiry) => expiry - chrono::Duration::days(self.config.renew_before_days as i64) < chrono::Utc::now(),
+            Err(e) => {
+                tracing::warn!("Cached certificate is unreadable, requesting a new one: {}", e);
+                true
+            }
+        }
+    }
+
+    /// Run an order to completion and write the certificate to the cache.
+    async fn issue(&self) -> anyhow::Result<()> {
+        let account_key = self.account_key()?;
+        let mut session = Session::open(self.http.clone(), &self.config.directory_url, account_key).await?;
+        session.register(self.config.contact.as_deref()).await?;
+
+        let identifiers: Vec<Value> = self.domains
+            .iter()
+            .map(|domain| json!({ "type": "dns", "value": domain }))
+            .collect();
+        let new_order = session.directory.new_order.clone();
+        let response = session.post(&new_order, Some(json!({ "identifiers": identifiers }))).await?;
+        let order_url = location(&response)?;
+        let order: Order = response.json().await?;
+
+        for authorization_url in &order.authorizations {
+            self.authorize(&mut session, authorization_url).await?;
+        }
+
+        let certificate_key = rcgen::KeyPair::generate()?;
+        let csr = rcgen::CertificateParams::new(self.domains.clone())?.serialize_request(&certificate_key)?;
+        session
+            .post(&order.finalize, Some(json!({ "csr": URL_SAFE_NO_PAD.encode(csr.der()) })))
+            .await?;
+        let certificate_url = session
+            .poll::<Order>(&order_url, |order| order.status == "valid", |order| order.status == "invalid")
+            .await?
+            .certificate
+            .ok_or_else(|| anyhow::anyhow!("Valid ACME order has no certificate URL"))?;
+        let chain = session.post(&certificate_url, None).await?.text().await?;
+        certificate_expiry(chain.as_bytes())?;
+
+        write_private(&self.path("key.pem"), certificate_key.serialize_pem().as_bytes())?;
+        std::fs::write(self.path("cert.pem"), chain)?;
+        Ok(())
+    }
+
+    /// Complete one authorization with its HTTP-01 challenge.
+    async fn authorize(&self, session: &mut Session, url: &str) -> anyhow::Result<()> {
+        let authorization: Authorization = session.post(url, None).await?.json().await?;
+        if authorization.status == "valid" {
+            return Ok(());
+        }
+        let challenge = authorization
+            .challenges
+            .iter()
+            .find(|challenge| challenge.kind == "http-01")
+            .ok_or_else(|| anyhow::anyhow!("No HTTP-01 challenge offered for {}", authorization.identifier.value))?;
+
+        let key_authorization = format!("{}.{}", challenge.token, session.thumbprint());
+        self.challenges.write().unwrap().insert(challenge.token.clone(), key_authorization);
+        let result = async {
+            session.post(&challenge.url, Some(json!({}))).await?;
+            session
+                .poll::<Authorization>(url, |authorization| authorization.status == "valid", |authorization| {
+                    authorization.status != "pending" && authorization.status != "valid"
+                })
+                .await
+        }
+        .await;
+        self.challenges.write().unwrap().remove(&challenge.token);
+
+        result
+            .map(|_| ())
+            .map_err(|e| anyhow::anyhow!("Authorization for {} failed: {}", authorization.identifier.value, e))
+    }
+
+    /// The account key, generated on first use.
+    fn account_key(&self) -> anyhow::Result<EcdsaKeyPair> {
+        let path = self.path("account.pem");
+        let key = match std::fs::read_to_string(&path) {
+            Ok(pem) => rcgen::KeyPair::from_pem(&pem)?,
+            Err(_) => {
+                let key = rcgen::KeyPair::generate()?;
+                write_private(&path, key.serialize_pem().as_bytes())?;
+                key
+            }
+        };
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &key.serialize_der(), &SystemRandom::new())
+            .map_err(|e| anyhow::anyhow!("Invalid ACME account key {}: {}", path.display(), e))
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        FsPath::new(&self.config.cache_dir).join(name)
+    }
+}
+
+/// Routes answering HTTP-01 challenges from `challenges`.
+pub fn challenge_router<S>(challenges: Challenges) -> Router<S> {
+    Router::new()
+        .route(CHALLENGE_PATH, get(challenge_response))
+        .with_state(challenges)
+}
+
+async fn challenge_response(State(challenges): State<Challenges>, Path(token): Path<String>) -> Response {
+    match challenges.read().unwrap().get(&token) {
+        Some(key_authorization) => {
+            ([(header::CONTENT_TYPE, "application/octet-stream")], key_authorization.clone()).into_response()
+        }
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+fn redirect_to_https(headers: &HeaderMap, uri: &Uri, https_port: u16) -> Response {
+    let Some(host) = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .and_then(|host| host.split(':').next())
+    else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let path = uri.path_and_query().map(|path| path.as_str()).unwrap_or("/");
+    let location = match https_port {
+        443 => format!("https://{}{}", host, path),
+        port => format!("https://{}:{}{}", host, port, path),
+    };
+    Redirect::permanent(&location).into_response()
+}
+
+/// Endpoints listed in the ACME directory.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Directory {
+    new_nonce: String,
+    new_account: String,
+    new_order: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Order {
+    status: String,
+    #[serde(default)]
+    authorizations: Vec<String>,
+    finalize: String,
+    certificate: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Authorization {
+    status: String,
+    identifier: Identifier,
+    challenges: Vec<AcmeChallenge>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Identifier {
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AcmeChallenge {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+    token: String,
+}
+
+/// Signed requests to one certificate authority with one account key.
+struct Session {
+    http: reqwest::Client,
+    directory: Directory,
+    key: EcdsaKeyPair,
+    account_url: Option<String>,
+    nonce: Option<String>,
+}
+
+impl Session {
+    async fn open(http: reqwest::Client, directory_url: &str, key: EcdsaKeyPair) -> anyhow::Result<Self> {
+        let directory = http.get(directory_url).send().await?.error_for_status()?.json().await?;
+        Ok(Self {
+            http,
+            directory,
+            key,
+            account_url: None,
+            nonce: None,
+        })
+    }
+
+    /// Find or create the account for the key, agreeing to the terms of service.
+    async fn register(&mut self, contact: Option<&str>) -> anyhow::Result<()> {
+        let mut account = json!({ "termsOfServiceAgreed": true });
+        if let Some(contact) = contact {
+            account["contact"] = json!([format!("mailto:{}", contact)]);
+        }
+        let new_account = self.directory.new_account.clone();
+        let response = self.post(&new_account, Some(account)).await?;
+        self.account_url = Some(location(&response)?);
+        Ok(())
+    }
+
+    /// POST a JWS-signed payload, or a POST-as-GET when `payload` is `None`.
+    ///
+    /// Retries once when the server rejects the nonce, as RFC 8555 expects.
+    async fn post(&mut self, url: &str, payload: Option<Value>) -> anyhow::Result<reqwest::Response> {
+        let mut retried = false;
+        loop {
+            let nonce = match self.nonce.take() {
+                Some(nonce) => nonce,
+                None => self.new_nonce().await?,
+            };
+            let body = self.sign(url, &nonce, payload.as_ref())?;
+            let response = self.http
+                .post(url)
+                .header("Content-Type", "application/jose+json")
+                .body(body.to_string())
+                .send()
+                .await?;
+            self.nonce = replay_nonce(&response);
+            if response.status().is_success() {
+                return Ok(response);
+            }
+
+            let status = response.status();
+            let problem: Value = response.json().await.unwrap_or_default();
+            if problem["type"] == "urn:ietf:params:acme:error:badNonce" && !retried {
+                retried = true;
+                continue;
+            }
+            anyhow::bail!(
+                "ACME request to {} failed with {}: {}",
+                url,
+                status,
+                problem["detail"].as_str().unwrap_or("no detail")
+            );
+        }
+    }
+
+    /// POST-as-GET `url` until `done` or `failed` holds for the resource.
+    async fn poll<T: serde::de::DeserializeOwned>(
+        &mut self,
+        url: &str,
+        done: impl Fn(&T) -> bool,
+        failed: impl Fn(&T) -> bool,
+    ) -> anyhow::Result<T> {
+        for _ in 0..MAX_POLLS {
+            let resource: T = self.post(url, None).await?.json().await?;
+            if done(&resource) {
+                return Ok(resource);
+            }
+            if failed(&resource) {
+                anyhow::bail!("{} became invalid", url);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        anyhow::bail!("{} is still pending after {} checks", url, MAX_POLLS)
+    }
+
+    async fn new_nonce(&self) -> anyhow::Result<String> {
+        let response = self.http.head(&self.directory.new_nonce).send().await?.error_for_status()?;
+        replay_nonce(&response).ok_or_else(|| anyhow::anyhow!("ACME server returned no nonce"))
+    }
+
+    /// Flattened JWS over `payload`, identified by account URL once registered.
+    fn sign(&self, url: &str, nonce: &str, payload: Option<&Value>) -> anyhow::Result<Value> {
+        let mut protected = json!({ "alg": "ES256", "nonce": nonce, "url": url });
+        match &self.account_url {
+            Some(account_url) => protected["kid"] = json!(account_url),
+            None => protected["jwk"] = self.jwk(),
+        }
+        let protected = URL_SAFE_NO_PAD.encode(protected.to_string());
+        let payload = payload
+            .map(|payload| URL_SAFE_NO_PAD.encode(payload.to_string()))
+            .unwrap_or_default();
+        let signature = self.key
+            .sign(&SystemRandom::new(), format!("{}.{}", protected, payload).as_bytes())
+            .map_err(|_| anyhow::anyhow!("Failed to sign ACME request"))?;
+        Ok(json!({
+            "protected": protected,
+            "payload": payload,
+            "signature": URL_SAFE_NO_PAD.encode(signature.as_ref()),
+        }))
+    }
+
+    /// The account public key as a JWK, members in the order RFC 7638 hashes them.
+    fn jwk(&self) -> Value {
+        // Uncompressed point: 0x04 || x || y
+        let point = self.key.public_key().as_ref();
+        json!({
+            "crv": "P-256",
+            "kty": "EC",
+            "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+            "y": URL_SAFE_NO_PAD.encode(&point[33..65]),
+        })
+    }
+
+    /// RFC 7638 thumbprint of the account key, used in key authorizations.
+    fn thumbprint(&self) -> String {
+        let jwk = self.jwk();
+        let canonical = format!(
+            r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#,
+            jwk["x"].as_str().unwrap_or_default(),
+            jwk["y"].as_str().unwrap_or_default()
+        );
+        URL_SAFE_NO_PAD.encode(hesha_crypto::sha256(canonical.as_bytes()))
+    }
+}
+
+fn replay_nonce(response: &reqwest::Response) -> Option<String> {
+    response
+        .headers()
+        .get("Replay-Nonce")
+        .and_then(|nonce| nonce.to_str().ok())
+        .map(str::to_string)
+}
+
+fn location(response: &reqwest::Response) -> anyhow::Result<String> {
+    response
+        .headers()
+        .get("Location")
+        .and_then(|location| location.to_str().ok())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("ACME response to {} has no Location", response.url()))
+}
+
+/// Write a file readable only by the node's user.
+fn write_private(path: &FsPath, contents: &[u8]) -> anyhow::Result<()> {
+    std::fs::write(path, contents)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::util::ServiceExt;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn acme_config(dir: &tempfile::TempDir, directory_url: String) -> AcmeConfig {
+        AcmeConfig {
+            contact: Some("ops@example.com".to_string()),
+            domains: Vec::new(),
+            directory_url,
+            cache_dir: dir.path().display().to_string(),
+            http_port: 80,
+            renew_before_days: 30,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_challenge_route() {
+        let challenges = Challenges::default();
+        challenges.write().unwrap().insert("token-1".to_string(), "token-1.thumbprint".to_string());
+        let app: Router = challenge_router(challenges);
+
+        let get = |path: &str| {
+            app.clone().oneshot(
+                axum::http::Request::builder()
+                    .uri(path)
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+        };
+        let response = get("/.well-known/acme-challenge/token-1").await.unwrap();
+        assert_eq!(response.status(), 200);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"token-1.thumbprint");
+
+        let response = get("/.well-known/acme-challenge/token-2").await.unwrap();
+        assert_eq!(response.status(), 404);
+    }
+
+    #[test]
+    fn test_rejects_local_domains() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = acme_config(&dir, String::new());
+        assert!(CertificateManager::new(&config, "localhost:3000").is_err());
+        assert!(CertificateManager::new(&config, "127.0.0.1").is_err());
+
+        let manager = CertificateManager::new(&config, "issuer.example.com:3000").unwrap();
+        assert_eq!(manager.domains, ["issuer.example.com"]);
+    }
+
+    #[tokio::test]
+    async fn test_issue_certificate() {
+        let server = MockServer::start().await;
+        let url = |path: &str| format!("{}{}", server.uri(), path);
+        let nonce = || ResponseTemplate::new(200).insert_header("Replay-Nonce", "nonce");
+        let created = || ResponseTemplate::new(201).insert_header("Replay-Nonce", "nonce");
+
+        Mock::given(method("GET"))
+            .and(path("/directory"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "newNonce": url("/new-nonce"),
+                "newAccount": url("/new-account"),
+                "newOrder": url("/new-order"),
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/new-nonce"))
+            .respond_with(nonce())
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/new-account"))
+            .respond_with(created().insert_header("Location", url("/account/1").as_str()))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/new-order"))
+            .respond_with(
+                created()
+                    .insert_header("Location", url("/order/1").as_str())
+                    .set_body_json(json!({
+                        "status": "pending",
+                        "authorizations": [url("/authz/1")],
+                        "finalize": url("/order/1/finalize"),
+                    })),
+            )
+            .mount(&server)
+            .await;
+        let authorization = |status: &str| json!({
+            "status": status,
+            "identifier": { "type": "dns", "value": "issuer.example.com" },
+            "challenges": [{ "type": "http-01", "url": url("/challenge/1"), "token": "token-1", "status": status }],
+        });
+        Mock::given(method("POST"))
+            .and(path("/authz/1"))
+            .respond_with(nonce().set_body_json(authorization("pending")))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/authz/1"))
+            .respond_with(nonce().set_body_json(authorization("valid")))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/challenge/1"))
+            .respond_with(nonce().set_body_json(json!({ "status": "processing" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/order/1/finalize"))
+            .respond_with(nonce().set_body_json(json!({
+                "status": "processing",
+                "finalize": url("/order/1/finalize"),
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/order/1"))
+            .respond_with(nonce().set_body_json(json!({
+                "status": "valid",
+                "finalize": url("/order/1/finalize"),
+                "certificate": url("/cert/1"),
+            })))
+            .mount(&server)
+            .await;
+        let key = rcgen::KeyPair::generate().unwrap();
+        let certificate = rcgen::CertificateParams::new(vec!["issuer.example.com".to_string()])
+            .unwrap()
+            .self_signed(&key)
+            .unwrap();
+        Mock::given(method("POST"))
+            .and(path("/cert/1"))
+            .respond_with(nonce().set_body_string(certificate.pem()))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let manager = CertificateManager::new(&acme_config(&dir, url("/directory")), "issuer.example.com").unwrap();
+        assert!(manager.renewal_due());
+        manager.ensure_certificate().await.unwrap();
+
+        assert!(!manager.renewal_due());
+        assert!(manager.challenges.read().unwrap().is_empty());
+        assert_eq!(std::fs::read_to_string(manager.tls_config().cert_path).unwrap(), certificate.pem());
+        assert!(dir.path().join("account.pem").is_file());
+        assert!(std::fs::read_to_string(manager.tls_config().key_path).unwrap().contains("PRIVATE KEY"));
+
+        // The second request is signed with the account URL, not the key
+        let requests = server.received_requests().await.unwrap();
+        let protected = |index: usize| {
+            let body: Value = serde_json::from_slice(&requests[index].body).unwrap();
+            let protected = URL_SAFE_NO_PAD.decode(body["protected"].as_str().unwrap()).unwrap();
+            serde_json::from_slice::<Value>(&protected).unwrap()
+        };
+        assert!(protected(2)["jwk"].is_object());
+        assert_eq!(protected(3)["kid"], url("/account/1"));
+    }
+}
@@ -0,0 +1,232 @@
+//! Operator endpoints, served only to callers holding `[admin_auth]`
+//! credentials (see [`crate::auth::require_admin`]).
+
+use crate::auth::Caller;
+use crate::problem::ApiError;
+use crate::request_validation::{ApiJson, ApiQuery};
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    Extension, Json,
+};
+use chrono::Utc;
+use hesha_storage::{RecordSnapshot, StorageError};
+use hesha_types::{
+    AuditEvent, DailyIssuance, ErrorCode, IssuanceStats, NodeMetrics, RestoreSummary,
+    RevocationEntry, VerificationStats, WebhookEventData,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Days covered by `/admin/stats` unless `days` is given.
+const DEFAULT_STATS_DAYS: u32 = 30;
+
+/// Longest window `/admin/stats` covers.
+const MAX_STATS_DAYS: u32 = 366;
+
+/// Handle metrics request from an operator tool.
+pub async fn metrics(State(state): State<AppState>) -> Json<NodeMetrics> {
+    Json(state.metrics.snapshot())
+}
+
+/// Query of a statistics request.
+#[derive(Debug, Deserialize)]
+pub struct StatsQuery {
+    /// UTC days to cover, today included.
+    #[serde(default)]
+    pub days: Option<u32>,
+}
+
+/// Handle statistics request from an operator tool.
+/// 
+/// Issuance and revocation figures are counted from the attestation store
+/// over the last `days` UTC days; verification figures since node start.
+pub async fn stats(
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<StatsQuery>,
+) -> Result<Json<IssuanceStats>, ApiError> {
+    let days = query.days.unwrap_or(DEFAULT_STATS_DAYS);
+    if !(1..=MAX_STATS_DAYS).contains(&days) {
+        return Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            format!("days must be between 1 and {}", MAX_STATS_DAYS),
+        ));
+    }
+    
+    let computed_at = Utc::now();
+    let first_day = computed_at.date_naive() - chrono::Duration::days(i64::from(days) - 1);
+    let since = first_day.and_time(chrono::NaiveTime::MIN).and_utc();
+    let storage_error = |e: StorageError| {
+        tracing::error!("Failed to compute statistics: {}", e);
+        ApiError::new(ErrorCode::StorageFailed, "Failed to read the records")
+    };
+    let counts = state.storage.attestations.issuance_counts(since).await.map_err(storage_error)?;
+    let revocations = state.storage.revocations.list().await.map_err(storage_error)?
+        .iter()
+        .filter(|entry| entry.revoked_at >= since)
+        .count() as u64;
+    
+    let mut per_day: Vec<DailyIssuance> = first_day
+        .iter_days()
+        .take(days as usize)
+        .map(|date| DailyIssuance { date, issued: 0, renewals: 0 })
+        .collect();
+    let mut per_scope = BTreeMap::new();
+    for count in &counts {
+        if let Some(day) = per_day.iter_mut().find(|day| day.date == count.day) {
+            day.issued += count.issued;
+            day.renewals += count.renewals;
+        }
+        *per_scope.entry(count.scope.clone()).or_default() += count.issued;
+    }
+    let issued = counts.iter().map(|count| count.issued).sum();
+    let renewals = counts.iter().map(|count| count.renewals).sum();
+    
+    let metrics = state.metrics.snapshot();
+    Ok(Json(IssuanceStats {
+        computed_at,
+        since,
+        issued,
+        renewals,
+        renewal_rate: rate(renewals, issued),
+        revocations,
+        per_day,
+        per_scope,
+        verification: VerificationStats {
+            since: metrics.started_at,
+            checks: metrics.verification_checks,
+            failures: metrics.verification_failures,
+            failure_rate: rate(metrics.verification_failures, metrics.verification_checks),
+        },
+    }))
+}
+
+/// `part` as a share of `whole`, 0 when `whole` is.
+fn rate(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+/// Request to revoke an attestation.
+#[derive(Debug, Deserialize)]
+pub struct RevokeRequest {
+    /// JWT ID of the attestation.
+    pub jti: String,
+    /// Optional reason published with the revocation.
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Result of a revocation.
+#[derive(Debug, Serialize)]
+pub struct RevokeResponse {
+    /// False if the attestation was already revoked.
+    pub revoked: bool,
+    /// The published revocation entry.
+    pub entry: RevocationEntry,
+}
+
+/// Handle revocation request from an operator tool.
+/// 
+/// Revoking twice keeps the original entry, so its time and reason don't
+/// change under verifiers that already cached it.
+pub async fn revoke(
+    Extension(caller): Extension<Caller>,
+    State(state): State<AppState>,
+    ApiJson(req): ApiJson<RevokeRequest>,
+) -> Result<Json<RevokeResponse>, ApiError> {
+    if req.jti.trim().is_empty() {
+        return Err(ApiError::new(ErrorCode::InvalidRequest, "jti must not be empty"));
+    }
+    
+    let entry = RevocationEntry {
+        jti: req.jti,
+        revoked_at: Utc::now(),
+        reason: req.reason.filter(|reason| !reason.trim().is_empty()),
+    };
+    let storage_error = |e: StorageError| {
+        tracing::error!("Failed to revoke attestation: {}", e);
+        ApiError::new(ErrorCode::StorageFailed, "Failed to record the revocation")
+    };
+    let revoked = state.storage.revocations.revoke(&entry).await.map_err(storage_error)?;
+    let entry = if revoked {
+        tracing::info!("Revoked attestation {}", entry.jti);
+        state.revocation_list.invalidate();
+        state.audit.record(AuditEvent::AdminAction {
+            action: "revoke".to_string(),
+            peer: caller.id,
+        }).await;
+        state.audit.record(AuditEvent::AttestationRevoked {
+            jti: entry.jti.clone(),
+            reason: entry.reason.clone(),
+        }).await;
+        state.webhooks.emit(WebhookEventData::AttestationRevoked {
+            jti: entry.jti.clone(),
+            reason: entry.reason.clone(),
+        });
+        entry
+    } else {
+        state.storage.revocations.get(&entry.jti).await.map_err(storage_error)?.unwrap_or(entry)
+    };
+    
+    Ok(Json(RevokeResponse { revoked, entry }))
+}
+
+/// Limit on `/admin/restore` bodies, which carry every record of a node.
+pub const MAX_RESTORE_BYTES: usize = 256 * 1024 * 1024;
+
+/// Handle a request from an operator tool to export the node's
+/// attestations, proxy number assignments and revocations for a backup.
+pub async fn backup(
+    Extension(caller): Extension<Caller>,
+    State(state): State<AppState>,
+) -> Result<Json<RecordSnapshot>, ApiError> {
+    let snapshot = state.storage.snapshot().await.map_err(|e| {
+        tracing::error!("Failed to export records: {}", e);
+        ApiError::new(ErrorCode::StorageFailed, "Failed to read the records")
+    })?;
+    tracing::info!("Exported {} attestation(s) for a backup", snapshot.attestations.len());
+    state.audit.record(AuditEvent::AdminAction {
+        action: "backup".to_string(),
+        peer: caller.id,
+    }).await;
+    
+    Ok(Json(snapshot))
+}
+
+/// Handle a request from an operator tool to add the records of a backup.
+/// 
+/// Records the node already holds win, so restoring into a node that has
+/// issued attestations since the backup loses nothing.
+pub async fn restore(
+    Extension(caller): Extension<Caller>,
+    State(state): State<AppState>,
+    ApiJson(snapshot): ApiJson<RecordSnapshot>,
+) -> Result<Json<RestoreSummary>, ApiError> {
+    let summary = state.storage.restore(&snapshot).await.map_err(|e| {
+        tracing::error!("Failed to restore records: {}", e);
+        ApiError::new(ErrorCode::StorageFailed, "Failed to restore the records")
+    })?;
+    if summary.revocations > 0 {
+        state.revocation_list.invalidate();
+    }
+    tracing::info!(
+        "Restored {} attestation(s), {} proxy number(s) and {} revocation(s)",
+        summary.attestations, summary.proxy_numbers, summary.revocations
+    );
+    if summary.proxy_conflicts > 0 {
+        tracing::warn!(
+            "Kept the current owner of {} proxy number(s) assigned differently in the backup",
+            summary.proxy_conflicts
+        );
+    }
+    state.audit.record(AuditEvent::AdminAction {
+        action: "restore".to_string(),
+        peer: caller.id,
+    }).await;
+    
+    Ok(Json(summary))
+}
@@ -1,16 +1,42 @@
 //! Attestation endpoint.
 
+use crate::auth::Caller;
+use crate::captcha::require_captcha;
+use crate::idempotency::{Claim, IdempotencyError, IDEMPOTENCY_KEY_HEADER, MAX_KEY_LENGTH};
+use crate::api::verify::{session_not_found, state_unavailable};
+use crate::problem::ApiError;
+use crate::rate_limit;
+use crate::request_validation::ApiJson;
 use crate::state::AppState;
-use axum::{extract::State, Json};
-use hesha_core::{attestation::AttestationBuilder, generate_proxy_number, ProxyGenerationInput};
-use hesha_crypto::generate_hex_nonce;
-use hesha_types::{PhoneNumber, PublicKey};
+use crate::verification::SessionError;
+use axum::{
+    extract::{ConnectInfo, State},
+    http::HeaderMap,
+    Extension, Json,
+};
+use hesha_core::{
+    attestation::AttestationBuilder, generate_proxy_number, parse_attestation, receipt_hash, sign_receipt,
+    ProxyGenerationInput,
+};
+use hesha_crypto::{generate_hex_nonce, hash_phone_number_spec};
+use hesha_metrics::IssuanceKind;
+use hesha_storage::StoredAttestation;
+use hesha_types::{
+    AuditEvent, ErrorCode, HeshaResult, IssuanceReceipt, IssuedAttestation, PhoneNumber, ProxyNumber, PublicKey,
+    ReceiptOutcome, RecentAttestation, VerificationMethod, WebhookEventData, MAX_VALIDITY_DAYS,
+};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use std::net::SocketAddr;
 use chrono;
 
+/// How many proxy numbers to try before giving up on a request.
+const MAX_PROXY_ATTEMPTS: usize = 5;
+
+/// How far a request `timestamp` may be from the node's clock, in seconds.
+pub const REQUEST_MAX_SKEW_SECS: i64 = 300;
+
 /// Request for attestation.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AttestationRequest {
     /// Protocol version (must be "0.1.0-alpha").
     pub version: String,
@@ -23,10 +49,41 @@ pub struct AttestationRequest {
     /// Optional validity period in days (defaults to issuer config).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub validity_days: Option<i64>,
+    /// JWT ID of an earlier attestation from this issuer being renewed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renews: Option<String>,
+    /// Fresh random value (16-128 characters) identifying this request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// When the request was made (Unix seconds); required with `nonce`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+}
+
+/// Request for attestation of a verified session.
+#[derive(Debug, Deserialize)]
+pub struct SessionAttestationRequest {
+    /// Protocol version (must be "0.1.0-alpha").
+    pub version: String,
+    /// Session verified through `/verify/check`.
+    pub session_id: String,
+    /// User's Ed25519 public key (base64url encoded).
+    pub user_pubkey: String,
+    /// Scope - 1-4 digit calling code (e.g., "1", "44", "234").
+    pub scope: String,
+    /// Optional validity period in days (defaults to issuer config).
+    #[serde(default)]
+    pub validity_days: Option<i64>,
+    /// JWT ID of an earlier attestation from this issuer being renewed.
+    #[serde(default)]
+    pub renews: Option<String>,
+    /// Solved CAPTCHA, when the issuer requires one.
+    #[serde(default)]
+    pub captcha_token: Option<String>,
 }
 
 /// Response containing attestation.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AttestationResponse {
     /// The proxy number assigned.
     pub proxy_number: String,
@@ -34,114 +91,399 @@ pub struct AttestationResponse {
     pub attestation: String,
     /// Expiration timestamp (Unix seconds).
     pub expires_at: i64,
+    /// Issuer's signed receipt for the attestation.
+    pub receipt: IssuanceReceipt,
+}
+
+/// What an attestation request would produce.
+#[derive(Debug, Serialize)]
+pub struct AttestationPreview {
+    /// An example proxy number; the one issued will differ.
+    pub proxy_number: String,
+    /// Shape of the proxy number, with `X` for the generated digits.
+    pub format: String,
+    /// Scope the proxy number would be generated for.
+    pub scope: String,
+    /// Validity the attestation would get, in days.
+    pub validity_days: i64,
+    /// When it would expire if issued now (Unix seconds).
+    pub expires_at: i64,
 }
 
 /// Handle attestation request.
 /// 
 /// This endpoint assumes the issuer has already verified the phone number
 /// through their own mechanism (SMS, carrier API, etc).
+/// 
+/// A request with an `Idempotency-Key` header that repeats an earlier one
+/// gets the earlier response back instead of a new attestation.
+/// 
+/// A request carrying a `nonce` is accepted once: resending a captured
+/// request is refused even if the idempotency key is stripped.
 pub async fn attest(
     State(state): State<AppState>,
-    Json(req): Json<AttestationRequest>,
-) -> Result<Json<AttestationResponse>, (axum::http::StatusCode, Json<serde_json::Value>)> {
+    caller: Option<Extension<Caller>>,
+    headers: HeaderMap,
+    ApiJson(req): ApiJson<AttestationRequest>,
+) -> Result<Json<AttestationResponse>, ApiError> {
+    let caller = caller.map(|Extension(caller)| caller);
+    let result = issue(&state, caller.as_ref(), &headers, req).await;
+    if let Err(e) = &result {
+        state.metrics.record_error(e.code().as_str());
+    }
+    result
+}
+
+/// Issue an attestation, or replay the response to an earlier request.
+async fn issue(
+    state: &AppState,
+    caller: Option<&Caller>,
+    headers: &HeaderMap,
+    req: AttestationRequest,
+) -> Result<Json<AttestationResponse>, ApiError> {
+    check_request_nonce(state, caller, &req).await?;
+    
+    let Some(key) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return create(state, caller, req, IssuanceKind::Direct, None).await.map(Json);
+    };
+    let key = match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => key,
+        _ => {
+            return Err(ApiError::new(
+                ErrorCode::InvalidRequest,
+                format!("Idempotency-Key must be 1-{} visible ASCII characters", MAX_KEY_LENGTH),
+            ));
+        }
+    };
+    
+    // Retries carry a fresh nonce, so it isn't part of the request's identity
+    let request = serde_json::to_vec(&AttestationRequest {
+        nonce: None,
+        timestamp: None,
+        ..req.clone()
+    })
+    .unwrap_or_default();
+    let caller_id = caller.map(|caller| caller.id.as_str()).unwrap_or_default();
+    let reservation = match state.idempotency.claim(caller_id, key, &request).await {
+        Ok(Claim::Replay(response)) => {
+            tracing::info!("Replaying attestation for idempotency key {}", key);
+            return Ok(Json(*response));
+        }
+        Ok(Claim::Reserved(reservation)) => reservation,
+        Err(IdempotencyError::KeyReused) => {
+            return Err(ApiError::new(
+                ErrorCode::IdempotencyKeyReused,
+                "Idempotency key was already used for a different request",
+            ));
+        }
+        Err(IdempotencyError::InProgress) => {
+            return Err(ApiError::new(
+                ErrorCode::IdempotencyKeyInProgress,
+                "A request with this idempotency key is still being handled; retry shortly",
+            ));
+        }
+        Err(IdempotencyError::Unavailable) => {
+            return Err(ApiError::new(ErrorCode::StorageFailed, "Failed to look up idempotency key"));
+        }
+    };
+    
+    // An error drops the reservation, so the request can be retried
+    let response = create(state, caller, req, IssuanceKind::Direct, None).await?;
+    reservation.complete(response.clone()).await;
+    Ok(Json(response))
+}
+
+/// Preview an attestation request.
+/// 
+/// Runs the same validation and proxy generation as `/attest` but signs
+/// and records nothing, so client apps can show users what their proxy
+/// number will look like before committing. Each proxy number is generated
+/// with a fresh random nonce, so the example won't be the number issued.
+pub async fn preview(
+    State(state): State<AppState>,
+    ApiJson(req): ApiJson<AttestationRequest>,
+) -> Result<Json<AttestationPreview>, ApiError> {
+    let result = validate(&state, &req).and_then(|validated| {
+        let proxy_number = generate_proxy(&state, &req)?;
+        // +{scope}00 followed by the generated digits
+        let prefix = req.scope.len() + 3;
+        let format = format!(
+            "{}{}",
+            &proxy_number.as_str()[..prefix],
+            "X".repeat(proxy_number.as_str().len() - prefix)
+        );
+        Ok(AttestationPreview {
+            proxy_number: proxy_number.to_string(),
+            format,
+            scope: req.scope.clone(),
+            validity_days: validated.validity_days,
+            expires_at: (chrono::Utc::now() + chrono::Duration::days(validated.validity_days)).timestamp(),
+        })
+    });
+    if let Err(e) = &result {
+        state.metrics.record_error(e.code().as_str());
+    }
+    result.map(Json)
+}
+
+/// Handle attestation request for a phone verified through `/verify/start`
+/// and `/verify/check`.
+/// 
+/// The session is used up, so each verification yields one attestation.
+pub async fn attest_simple(
+    State(state): State<AppState>,
+    caller: Option<Extension<Caller>>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    ApiJson(req): ApiJson<SessionAttestationRequest>,
+) -> Result<Json<AttestationResponse>, ApiError> {
+    let caller = caller.map(|Extension(caller)| caller);
+    let remote_ip = peer.map(|ConnectInfo(peer)| peer.ip());
+    // Checked first, so a refused request doesn't use up the session
+    let result = match require_captcha(&state, req.captcha_token.as_deref(), remote_ip).await {
+        Ok(()) => issue_for_session(&state, caller.as_ref(), req).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = &result {
+        state.metrics.record_error(e.code().as_str());
+    }
+    result
+}
+
+/// Issue an attestation for the phone number of a verified session.
+/// 
+/// The request names no phone number for the rate limit middleware, so
+/// the per-phone limit is applied here, before the session is used up.
+async fn issue_for_session(
+    state: &AppState,
+    caller: Option<&Caller>,
+    req: SessionAttestationRequest,
+) -> Result<Json<AttestationResponse>, ApiError> {
+    let phone_number = state.verification.verified_phone(&req.session_id).await.map_err(session_error)?;
+    state.rate_limits.check_phone(&phone_number).await.map_err(|limited| {
+        tracing::warn!("Rate limited attestation requests for one phone number");
+        rate_limit::refusal(state, limited, "Too many attestations for this phone number")
+    })?;
+    
+    let (phone_number, method) = state.verification.take_verified(&req.session_id).await.map_err(session_error)?;
+    create(state, caller, AttestationRequest {
+        version: req.version,
+        phone_number: phone_number.to_string(),
+        user_pubkey: req.user_pubkey,
+        scope: req.scope,
+        validity_days: req.validity_days,
+        renews: req.renews,
+        nonce: None,
+        timestamp: None,
+    }, IssuanceKind::Session, Some(method))
+    .await
+    .map(Json)
+}
+
+/// The problem for a session that can't be attested.
+fn session_error(error: SessionError) -> ApiError {
+    match error {
+        SessionError::NotVerified => ApiError::new(
+            ErrorCode::PhoneNotVerified,
+            "Verification code has not been confirmed",
+        ),
+        SessionError::Unavailable => state_unavailable(),
+        _ => session_not_found(),
+    }
+}
+
+/// Refuse a request whose nonce was already used or whose timestamp is
+/// stale, recording the nonce until the timestamp would be refused anyway.
+/// 
+/// Nonces are required unless `require_request_nonce` is turned off.
+async fn check_request_nonce(
+    state: &AppState,
+    caller: Option<&Caller>,
+    req: &AttestationRequest,
+) -> Result<(), ApiError> {
+    let invalid = |description: &str| {
+        ApiError::new(ErrorCode::InvalidRequest, description)
+    };
+    let (nonce, timestamp) = match (&req.nonce, req.timestamp) {
+        (Some(nonce), Some(timestamp)) => (nonce, timestamp),
+        (None, None) if !state.config.require_request_nonce => return Ok(()),
+        (None, None) => return Err(invalid("This issuer requires a request nonce and timestamp")),
+        _ => return Err(invalid("nonce and timestamp must be sent together")),
+    };
+    let valid_nonce = (16..=128).contains(&nonce.len())
+        && nonce.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    if !valid_nonce {
+        return Err(invalid("nonce must be 16-128 letters, digits, '-' or '_'"));
+    }
+    
+    let max_skew = chrono::Duration::seconds(REQUEST_MAX_SKEW_SECS);
+    let sent_at = chrono::DateTime::from_timestamp(timestamp, 0)
+        .filter(|sent_at| (chrono::Utc::now() - *sent_at).abs() <= max_skew);
+    let Some(sent_at) = sent_at else {
+        return Err(ApiError::new(
+            ErrorCode::StaleRequest,
+            format!("Request timestamp must be within {} seconds of the issuer's clock", REQUEST_MAX_SKEW_SECS),
+        ));
+    };
+    
+    let caller_id = caller.map(|caller| caller.id.as_str()).unwrap_or_default();
+    let key = format!("attest-request:{}:{}", caller_id, nonce);
+    match state.storage.nonces.check_and_insert(&key, sent_at + max_skew).await {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            tracing::warn!("Refused replayed attest request");
+            Err(ApiError::new(ErrorCode::ReplayedRequest, "Request nonce was already used"))
+        }
+        Err(e) => {
+            tracing::error!("Failed to record request nonce: {}", e);
+            Err(ApiError::new(ErrorCode::StorageFailed, "Failed to record request nonce"))
+        }
+    }
+}
+
+/// Check a requested scope against the issuer's `[scopes]` policy,
+/// returning the scope's validity in days if it sets one.
+fn check_scope_policy(
+    state: &AppState,
+    scope: &str,
+    phone_number: &PhoneNumber,
+) -> Result<Option<i64>, ApiError> {
+    let Some(policy) = &state.config.scopes else {
+        return Ok(None);
+    };
+    if !policy.allows(scope) {
+        return Err(ApiError::new(
+            ErrorCode::ScopeNotAllowed,
+            format!("This issuer does not issue scope {}", scope),
+        ));
+    }
+    // The scope is a calling code, so the number must start with it
+    if policy.match_phone_country && !phone_number.as_str()[1..].starts_with(scope) {
+        return Err(ApiError::new(
+            ErrorCode::ScopeCountryMismatch,
+            format!("Scope {} does not match the phone number's calling code", scope),
+        ));
+    }
+    Ok(policy.validity_days.get(scope).map(|days| *days as i64))
+}
+
+/// What an attestation request turned out to ask for.
+struct ValidatedRequest {
+    phone_number: PhoneNumber,
+    user_pubkey: PublicKey,
+    validity_days: i64,
+}
+
+/// Check an attestation request against the protocol and the issuer's
+/// policy, without touching storage.
+fn validate(
+    state: &AppState,
+    req: &AttestationRequest,
+) -> Result<ValidatedRequest, ApiError> {
     // Validate protocol version
     if req.version != "0.1.0-alpha" {
-        return Err((
-            axum::http::StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "invalid_version",
-                "error_description": "Only version 0.1.0-alpha is supported"
-            }))
-        ));
+        return Err(ApiError::new(ErrorCode::InvalidVersion, "Only version 0.1.0-alpha is supported"));
     }
     
     // Parse phone number
     let phone_number = PhoneNumber::new(&req.phone_number)
         .map_err(|e| {
-            (
-                axum::http::StatusCode::BAD_REQUEST,
-                Json(json!({
-                    "error": "invalid_phone_number",
-                    "error_description": format!("Invalid phone number: {}", e)
-                }))
-            )
+            ApiError::new(ErrorCode::InvalidPhoneNumber, format!("Invalid phone number: {}", e))
         })?;
     
     // Parse public key
     let user_pubkey = PublicKey::from_base64(&req.user_pubkey)
         .map_err(|e| {
-            (
-                axum::http::StatusCode::BAD_REQUEST,
-                Json(json!({
-                    "error": "invalid_public_key",
-                    "error_description": format!("Invalid public key: {}", e)
-                }))
-            )
+            ApiError::new(ErrorCode::InvalidPublicKey, format!("Invalid public key: {}", e))
         })?;
     
-    // Generate proxy number using new algorithm
-    let nonce = generate_hex_nonce();
-    let generation_input = ProxyGenerationInput {
-        phone_number: req.phone_number.clone(),
-        user_pubkey: req.user_pubkey.clone(),
-        issuer_domain: state.config.domain.clone(),
-        scope: req.scope.clone(),
-        nonce,
-    };
-    
-    let proxy_number = generate_proxy_number(&generation_input)
-        .map_err(|e| {
-            (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "proxy_generation_failed",
-                    "error_description": format!("Failed to generate proxy number: {}", e)
-                }))
-            )
-        })?;
+    // Refuse scopes the issuer's policy doesn't allow for this phone
+    let scope_validity_days = check_scope_policy(state, &req.scope, &phone_number)?;
     
-    // Determine validity days - use request value if provided, otherwise config default
+    // Determine validity days - use request value if provided, otherwise
+    // the scope's or the config default
+    let max_days = scope_validity_days.map_or(MAX_VALIDITY_DAYS, |days| days.min(MAX_VALIDITY_DAYS));
     let validity_days = match req.validity_days {
         Some(days) => {
-            // Enforce reasonable limits (1 day to 2 years)
-            if !(1..=730).contains(&days) {
-                return Err((
-                    axum::http::StatusCode::BAD_REQUEST,
-                    Json(json!({
-                        "error": "invalid_validity_days",
-                        "error_description": "Validity must be between 1 and 730 days"
-                    }))
+            // Enforce reasonable limits (1 day to 2 years, or the scope's validity)
+            if !(1..=max_days).contains(&days) {
+                return Err(ApiError::new(
+                    ErrorCode::InvalidValidityDays,
+                    format!("Validity must be between 1 and {} days", max_days),
                 ));
             }
             days
         }
-        None => state.config.attestation_validity_days
+        None => scope_validity_days.unwrap_or(state.config.attestation_validity_days)
     };
     
-    // Create attestation using builder
+    Ok(ValidatedRequest {
+        phone_number,
+        user_pubkey,
+        validity_days,
+    })
+}
+
+/// Issue a new attestation for a phone number the issuer has verified,
+/// recording `method` when the issuer verified it itself.
+async fn create(
+    state: &AppState,
+    caller: Option<&Caller>,
+    req: AttestationRequest,
+    kind: IssuanceKind,
+    method: Option<VerificationMethod>,
+) -> Result<AttestationResponse, ApiError> {
+    let started = std::time::Instant::now();
+    let kind = if req.renews.is_some() { IssuanceKind::Renewal } else { kind };
+    let ValidatedRequest { phone_number, user_pubkey, validity_days } = validate(state, &req)?;
+    
+    // A renewal must name an attestation this issuer actually issued
+    if let Some(renews) = &req.renews {
+        let previous = state.storage.attestations.get(renews).await
+            .map_err(|e| {
+                tracing::error!("Failed to look up attestation {}: {}", renews, e);
+                ApiError::new(ErrorCode::StorageFailed, "Failed to look up renewed attestation")
+            })?;
+        if previous.is_none() {
+            return Err(ApiError::new(
+                ErrorCode::InvalidRenewal,
+                "Renewed attestation was not issued by this issuer",
+            ));
+        }
+    }
+    
+    // Hold each phone to the issuer's caps on active and repeated attestations
+    let phone_hash = hash_phone_number_spec(&phone_number);
+    if let Err(refusal) = check_issuance_caps(state, &phone_hash, req.renews.as_deref()).await {
+        return Err(deny(state, caller, &req, refusal).await);
+    }
+    
+    // Generate a proxy number no other phone holds
+    let proxy_number = assign_proxy_number(state, &phone_hash, &req).await?;
+    
+    // Create attestation using builder, naming the signing key
+    let signing_key = state.keys.active();
     let mut builder = AttestationBuilder::new(
         state.config.domain.clone(),
-        &state.issuer_key.private,
+        &*signing_key.signer,
         phone_number.clone(),
         proxy_number.clone(),
         user_pubkey.clone(),
     )
-    .validity_days(validity_days);
+    .validity_days(validity_days)
+    .key_id(signing_key.key_id.clone());
     
     // Add trust domain if configured
     if let Some(trust_domain) = &state.config.trust_domain {
         builder = builder.trust_domain(trust_domain.clone());
     }
+    if let Some(method) = method {
+        builder = builder.verification_method(method);
+    }
     
-    let attestation = builder.build_jwt()
+    let attestation = tracing::info_span!("attestation.sign", key_id = %signing_key.key_id)
+        .in_scope(|| builder.build_jwt())
         .map_err(|e| {
-            (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "attestation_failed",
-                    "error_description": format!("Failed to create attestation: {}", e)
-                }))
-            )
+            ApiError::new(ErrorCode::AttestationFailed, format!("Failed to create attestation: {}", e))
         })?;
     
     tracing::info!(
@@ -152,11 +494,222 @@ pub async fn attest(
     );
     
     // Calculate expiration
-    let expires_at = chrono::Utc::now().timestamp() + (validity_days * 24 * 3600);
+    let issued_at = chrono::Utc::now();
+    let expires_at = issued_at.timestamp() + (validity_days * 24 * 3600);
+    
+    let jti = parse_attestation(&attestation).map(|a| a.jti).unwrap_or_default();
+    let receipt = issuance_receipt(state, &req, ReceiptOutcome::Issued, |receipt| {
+        receipt.jti = Some(jti.clone());
+        receipt.proxy_number = Some(proxy_number.to_string());
+    })
+    .map_err(|e| {
+        ApiError::new(ErrorCode::AttestationFailed, format!("Failed to sign receipt: {}", e))
+    })?;
+    
+    // Only the proxy number and JWT ID are kept, never the phone number
+    let record = StoredAttestation {
+        jti,
+        proxy_number: proxy_number.to_string(),
+        scope: req.scope.clone(),
+        issued_at,
+        expires_at: issued_at + chrono::Duration::days(validity_days),
+        renews: req.renews.clone(),
+    };
+    state.storage.attestations.insert(&record).await
+        .map_err(|e| {
+            tracing::error!("Failed to store attestation {}: {}", record.jti, e);
+            ApiError::new(ErrorCode::StorageFailed, "Failed to record attestation")
+        })?;
+    state.audit.record(AuditEvent::AttestationIssued {
+        jti: record.jti.clone(),
+        scope: record.scope.clone(),
+        caller: caller.map(|caller| caller.id.clone()),
+        renews: req.renews.clone(),
+        receipt: Some(receipt_hash(&receipt)),
+    }).await;
+    let issued = IssuedAttestation {
+        jti: record.jti.clone(),
+        proxy_number: record.proxy_number.clone(),
+        scope: record.scope,
+        expires_at: record.expires_at,
+    };
+    state.webhooks.emit(match req.renews {
+        Some(renews) => WebhookEventData::AttestationRenewed { renews, attestation: issued },
+        None => WebhookEventData::AttestationIssued(issued),
+    });
+    state.metrics.record_issued(RecentAttestation {
+        jti: record.jti,
+        proxy_number: record.proxy_number,
+        issued_at: record.issued_at,
+        expires_at: record.expires_at,
+    });
+    hesha_metrics::record_issuance(kind, started.elapsed());
     
-    Ok(Json(AttestationResponse {
+    Ok(AttestationResponse {
         proxy_number: proxy_number.to_string(),
         attestation,
         expires_at,
-    }))
-}
\ No newline at end of file
+        receipt,
+    })
+}
+
+/// A receipt for `req`, signed with the active key. `fill` sets the fields
+/// that depend on the outcome.
+fn issuance_receipt(
+    state: &AppState,
+    req: &AttestationRequest,
+    outcome: ReceiptOutcome,
+    fill: impl FnOnce(&mut IssuanceReceipt),
+) -> HeshaResult<IssuanceReceipt> {
+    let signing_key = state.keys.active();
+    let mut receipt = IssuanceReceipt {
+        issuer: state.config.domain.clone(),
+        outcome,
+        jti: None,
+        proxy_number: None,
+        error: None,
+        user_pubkey: req.user_pubkey.clone(),
+        scope: req.scope.clone(),
+        issued_at: chrono::Utc::now(),
+        key_id: signing_key.key_id.clone(),
+        signature: None,
+    };
+    fill(&mut receipt);
+    sign_receipt(&mut receipt, &*signing_key.signer)?;
+    Ok(receipt)
+}
+
+/// Attach a signed denial receipt to an issuance policy refusal and record
+/// its hash. Server errors are passed through as they are.
+async fn deny(
+    state: &AppState,
+    caller: Option<&Caller>,
+    req: &AttestationRequest,
+    refusal: ApiError,
+) -> ApiError {
+    if refusal.status().is_server_error() {
+        return refusal;
+    }
+    let error = refusal.code().to_string();
+    match issuance_receipt(state, req, ReceiptOutcome::Denied, |receipt| receipt.error = Some(error.clone())) {
+        Ok(receipt) => {
+            state.audit.record(AuditEvent::AttestationDenied {
+                error,
+                scope: req.scope.clone(),
+                caller: caller.map(|caller| caller.id.clone()),
+                receipt: receipt_hash(&receipt),
+            }).await;
+            return refusal.with_extension("receipt", receipt);
+        }
+        Err(e) => tracing::error!("Failed to sign denial receipt: {}", e),
+    }
+    refusal
+}
+
+/// Refuse an attestation for a phone that already holds
+/// `max_active_per_phone` unexpired, unrevoked attestations, or that was
+/// issued one less than `reissue_interval_secs` ago.
+/// 
+/// The attestation being renewed doesn't count towards the active cap.
+async fn check_issuance_caps(
+    state: &AppState,
+    phone_hash: &str,
+    renews: Option<&str>,
+) -> Result<(), ApiError> {
+    let max_active = state.config.max_active_per_phone;
+    let interval = state.config.reissue_interval_secs;
+    if max_active == 0 && interval == 0 {
+        return Ok(());
+    }
+    let storage_failed = |e: hesha_storage::StorageError| {
+        tracing::error!("Failed to look up attestations for phone: {}", e);
+        ApiError::new(ErrorCode::StorageFailed, "Failed to look up earlier attestations")
+    };
+    let issued = state.storage.attestations.find_by_phone_hash(phone_hash).await
+        .map_err(storage_failed)?;
+    let now = chrono::Utc::now();
+    
+    if let Some(latest) = issued.first().filter(|_| interval > 0) {
+        let next = latest.issued_at + chrono::Duration::seconds(interval as i64);
+        if next > now {
+            return Err(ApiError::new(
+                ErrorCode::ReissueTooSoon,
+                format!(
+                    "This phone number was attested recently; retry in {} seconds",
+                    (next - now).num_seconds().max(1)
+                ),
+            ));
+        }
+    }
+    
+    if max_active > 0 {
+        let mut active = 0;
+        for attestation in &issued {
+            if attestation.expires_at <= now || Some(attestation.jti.as_str()) == renews {
+                continue;
+            }
+            if state.storage.revocations.get(&attestation.jti).await.map_err(storage_failed)?.is_none() {
+                active += 1;
+            }
+        }
+        if active >= max_active {
+            return Err(ApiError::new(
+                ErrorCode::ActiveAttestationLimit,
+                format!(
+                    "This phone number already holds {} active attestations; revoke one or let it expire",
+                    active
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Generate a proxy number for a request, with a fresh nonce.
+#[tracing::instrument(name = "proxy.generate", skip_all)]
+fn generate_proxy(
+    state: &AppState,
+    req: &AttestationRequest,
+) -> Result<ProxyNumber, ApiError> {
+    let generation_input = ProxyGenerationInput {
+        phone_number: req.phone_number.clone(),
+        user_pubkey: req.user_pubkey.clone(),
+        issuer_domain: state.config.domain.clone(),
+        scope: req.scope.clone(),
+        nonce: generate_hex_nonce(),
+    };
+    generate_proxy_number(&generation_input)
+        .map_err(|e| {
+            ApiError::new(ErrorCode::ProxyGenerationFailed, format!("Failed to generate proxy number: {}", e))
+        })
+}
+
+/// Generate a proxy number and claim it in the attestation store.
+///
+/// Proxy numbers are derived from a hash, so two phones can land on the
+/// same one. A proxy number already held by another phone is discarded and
+/// regenerated with a new nonce.
+#[tracing::instrument(name = "proxy.assign", skip_all)]
+async fn assign_proxy_number(
+    state: &AppState,
+    phone_hash: &str,
+    req: &AttestationRequest,
+) -> Result<ProxyNumber, ApiError> {
+    for _ in 0..MAX_PROXY_ATTEMPTS {
+        let proxy_number = generate_proxy(state, req)?;
+        let assigned = state.storage.attestations
+            .assign_proxy_number(proxy_number.as_str(), phone_hash)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to assign proxy number: {}", e);
+                ApiError::new(ErrorCode::StorageFailed, "Failed to assign proxy number")
+            })?;
+        if assigned {
+            return Ok(proxy_number);
+        }
+        tracing::warn!("Proxy number {} is already assigned to another phone; regenerating", proxy_number);
+        state.metrics.record_proxy_collision();
+    }
+    
+    Err(ApiError::new(ErrorCode::ProxyGenerationFailed, "Could not find an unassigned proxy number"))
+}
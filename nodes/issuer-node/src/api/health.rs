@@ -0,0 +1,47 @@
+//! Health check endpoint.
+
+use crate::state::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Health check response.
+#[derive(Debug, Serialize)]
+pub struct HealthResponse {
+    /// Overall status ("ok" when the node can issue attestations).
+    pub status: String,
+    /// Node software version.
+    pub version: String,
+    /// Identifier of the active signing key.
+    pub key_id: String,
+    /// Current server time, used by clients to detect clock skew.
+    pub time: DateTime<Utc>,
+    /// ID of the replica that answered, fresh at each start.
+    pub replica_id: String,
+    /// Whether this replica runs the shared background jobs.
+    pub leader: bool,
+    /// Whether attestation requests are refused for maintenance.
+    pub maintenance: bool,
+}
+
+/// Handle health check request.
+/// 
+/// A draining node answers 503 with status "draining", so load balancers
+/// stop sending it traffic before it exits. A node in maintenance stays
+/// healthy: it still serves its public keys and revocations.
+pub async fn health(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    let (code, status) = if state.drain.is_draining() {
+        (StatusCode::SERVICE_UNAVAILABLE, "draining")
+    } else {
+        (StatusCode::OK, "ok")
+    };
+    (code, Json(HealthResponse {
+        status: status.to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        key_id: state.keys.active().key_id,
+        time: Utc::now(),
+        replica_id: state.leader.replica_id().to_string(),
+        leader: state.leader.is_leader(),
+        maintenance: state.maintenance.is_enabled(),
+    }))
+}
@@ -1,4 +1,10 @@
 //! API handlers.
 
+pub mod admin;
 pub mod attest;
-pub mod pubkey;
\ No newline at end of file
+pub mod health;
+pub mod openid;
+pub mod pubkey;
+pub mod revocations;
+pub mod status;
+pub mod verify;
\ No newline at end of file
@@ -0,0 +1,375 @@
+//! OpenID provider endpoints, served when `[oidc_provider]` is set.
+//!
+//! See [`crate::openid`] for the flow. Errors before a redirect URI is
+//! trusted, and errors to the wallet, are problem documents; the token
+//! endpoint answers relying parties with OAuth 2.0 errors (RFC 6749
+//! section 5.2), which OIDC libraries expect.
+
+use crate::openid::{issuer_url, CodeGrant, OpenIdProvider, PendingAuthorization, REQUEST_TTL_SECS};
+use crate::problem::ApiError;
+use crate::request_validation::{ApiJson, ApiQuery};
+use crate::state::AppState;
+use axum::{
+    extract::{rejection::FormRejection, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Form, Json,
+};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{Duration, Utc};
+use hesha_core::attestation::jwt::decode_key_id_unverified;
+use hesha_core::{oidc_service_context, sign_id_token, verify_attestation_with_key, IdTokenClaims};
+use hesha_core::verification::verify_challenge_response;
+use hesha_types::{Attestation, Challenge, ChallengeResponse, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Query of `GET /oidc/authorize`.
+#[derive(Debug, Deserialize)]
+pub struct AuthorizationRequest {
+    /// Must be `code`.
+    pub response_type: String,
+    /// Relying party asking.
+    pub client_id: String,
+    /// Where to send the code; must be registered for the client.
+    pub redirect_uri: String,
+    /// Must include `openid`.
+    #[serde(default)]
+    pub scope: String,
+    /// Opaque value handed back with the code.
+    pub state: Option<String>,
+    /// Value to put in the ID token.
+    pub nonce: Option<String>,
+}
+
+/// Challenge for the wallet to answer.
+#[derive(Debug, Serialize)]
+pub struct AuthorizationChallenge {
+    /// Request to answer at `POST /oidc/authorize`.
+    pub request_id: String,
+    /// Relying party the user is signing in to.
+    pub client_id: String,
+    /// Challenge to sign with the attestation's user key.
+    pub challenge: Challenge,
+    /// Seconds left to answer.
+    pub expires_in: i64,
+}
+
+/// The wallet's answer, posted to `/oidc/authorize`.
+#[derive(Debug, Deserialize)]
+pub struct AuthorizationAnswer {
+    /// Request from `GET /oidc/authorize`.
+    pub request_id: String,
+    /// Attestation from this issuer.
+    pub attestation: String,
+    /// The request's challenge, signed with the attestation's user key.
+    pub challenge_response: ChallengeResponse,
+}
+
+/// Where to send the user back to the relying party.
+#[derive(Debug, Serialize)]
+pub struct AuthorizationRedirect {
+    /// The redirect URI with the code and state.
+    pub redirect_uri: String,
+}
+
+/// Body of `POST /oidc/token`.
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    /// Must be `authorization_code`.
+    pub grant_type: String,
+    /// Code from the redirect.
+    pub code: String,
+    /// Redirect URI the code was sent to.
+    pub redirect_uri: String,
+    /// Client ID, for `client_secret_post`.
+    pub client_id: Option<String>,
+    /// Client secret, for `client_secret_post`.
+    pub client_secret: Option<String>,
+}
+
+/// Tokens for the relying party.
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    /// Opaque token; the node serves no userinfo endpoint, everything is
+    /// in the ID token.
+    pub access_token: String,
+    /// Always `Bearer`.
+    pub token_type: &'static str,
+    /// Seconds until the ID token expires.
+    pub expires_in: i64,
+    /// The signed ID token.
+    pub id_token: String,
+}
+
+/// OpenID provider metadata.
+pub async fn configuration(State(state): State<AppState>) -> Result<Json<serde_json::Value>, ApiError> {
+    provider(&state)?;
+    let issuer = issuer_url(&state.config);
+    Ok(Json(serde_json::json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{}/oidc/authorize", issuer),
+        "token_endpoint": format!("{}/oidc/token", issuer),
+        "jwks_uri": format!("{}/oidc/jwks.json", issuer),
+        "response_types_supported": ["code"],
+        "grant_types_supported": ["authorization_code"],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": ["EdDSA"],
+        "scopes_supported": ["openid"],
+        "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post"],
+        "claims_supported": [
+            "iss", "sub", "aud", "exp", "iat", "auth_time", "nonce",
+            hesha_core::oidc::PROXY_NUMBER_CLAIM, hesha_core::oidc::ATTESTATION_CLAIM,
+        ],
+    })))
+}
+
+/// The issuer keys ID tokens are signed with, as a JWK set.
+pub async fn jwks(State(state): State<AppState>) -> Result<Json<serde_json::Value>, ApiError> {
+    provider(&state)?;
+    let keys: Vec<serde_json::Value> = state
+        .keys
+        .published()
+        .iter()
+        .map(|key| {
+            serde_json::json!({
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "x": key.public_key.to_base64(),
+                "kid": key.key_id,
+                "alg": "EdDSA",
+                "use": "sig",
+            })
+        })
+        .collect();
+    Ok(Json(serde_json::json!({ "keys": keys })))
+}
+
+/// Start an authorization request, returning the challenge for the wallet.
+pub async fn authorize(
+    State(state): State<AppState>,
+    ApiQuery(req): ApiQuery<AuthorizationRequest>,
+) -> Result<Json<AuthorizationChallenge>, ApiError> {
+    let provider = provider(&state)?;
+    if !provider.allows_redirect(&req.client_id, &req.redirect_uri) {
+        return Err(ApiError::new(ErrorCode::InvalidRequest, "Unknown client_id or unregistered redirect_uri"));
+    }
+    if req.response_type != "code" {
+        return Err(ApiError::new(ErrorCode::InvalidRequest, "Only response_type=code is supported"));
+    }
+    if !req.scope.split_whitespace().any(|scope| scope == "openid") {
+        return Err(ApiError::new(ErrorCode::InvalidRequest, "scope must include openid"));
+    }
+
+    let challenge = Challenge {
+        nonce: hesha_crypto::generate_nonce(),
+        service_context: oidc_service_context(&issuer_url(&state.config), &req.client_id),
+        timestamp: Utc::now(),
+    };
+    let pending = PendingAuthorization {
+        client_id: req.client_id,
+        redirect_uri: req.redirect_uri,
+        state: req.state,
+        nonce: req.nonce,
+        challenge,
+    };
+    let request_id = provider.start(&pending).await.map_err(storage_failed)?;
+    Ok(Json(AuthorizationChallenge {
+        request_id,
+        client_id: pending.client_id,
+        challenge: pending.challenge,
+        expires_in: REQUEST_TTL_SECS,
+    }))
+}
+
+/// Check the wallet's answer and issue an authorization code.
+pub async fn answer(
+    State(state): State<AppState>,
+    ApiJson(req): ApiJson<AuthorizationAnswer>,
+) -> Result<Json<AuthorizationRedirect>, ApiError> {
+    let provider = provider(&state)?;
+    let pending = provider
+        .take_request(&req.request_id)
+        .await
+        .map_err(storage_failed)?
+        .ok_or_else(|| ApiError::new(ErrorCode::SessionNotFound, "Authorization request not found or expired"))?;
+
+    let answered = &req.challenge_response.challenge;
+    if answered.nonce != pending.challenge.nonce || answered.service_context != pending.challenge.service_context {
+        return Err(unauthorized("Response does not answer this request's challenge"));
+    }
+    let attestation = verify_own_attestation(&state, &req.attestation).await?;
+    verify_challenge_response(&req.challenge_response, &attestation, Duration::seconds(REQUEST_TTL_SECS))
+        .map_err(|e| unauthorized(format!("Invalid challenge response: {}", e)))?;
+
+    let grant = CodeGrant {
+        client_id: pending.client_id,
+        redirect_uri: pending.redirect_uri,
+        nonce: pending.nonce,
+        attestation: req.attestation,
+        auth_time: Utc::now(),
+    };
+    let code = provider.issue_code(&grant).await.map_err(storage_failed)?;
+    tracing::info!("Issued OIDC authorization code to {}", grant.client_id);
+
+    let separator = if grant.redirect_uri.contains('?') { '&' } else { '?' };
+    let mut redirect_uri = format!("{}{}code={}", grant.redirect_uri, separator, code);
+    if let Some(client_state) = &pending.state {
+        redirect_uri.push_str("&state=");
+        redirect_uri.push_str(&percent_encode(client_state));
+    }
+    Ok(Json(AuthorizationRedirect { redirect_uri }))
+}
+
+/// Redeem an authorization code for an ID token.
+pub async fn token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    form: Result<Form<TokenRequest>, FormRejection>,
+) -> Result<Response, OAuthError> {
+    let provider = state.openid.clone().ok_or_else(|| OAuthError::new(StatusCode::NOT_FOUND, "invalid_request", "This issuer is not an OpenID provider"))?;
+    let Form(req) = form.map_err(|e| OAuthError::invalid_request(e.body_text()))?;
+
+    let (client_id, secret) = client_credentials(&headers, &req)
+        .ok_or_else(|| OAuthError::invalid_client("Client credentials are required"))?;
+    if !provider.authenticate(&client_id, &secret) {
+        return Err(OAuthError::invalid_client("Unknown client or wrong secret"));
+    }
+    if req.grant_type != "authorization_code" {
+        return Err(OAuthError::new(StatusCode::BAD_REQUEST, "unsupported_grant_type", "Only authorization_code is supported"));
+    }
+
+    let invalid_grant = |description: &str| OAuthError::new(StatusCode::BAD_REQUEST, "invalid_grant", description);
+    let grant = provider
+        .redeem_code(&req.code)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to redeem OIDC code: {}", e);
+            OAuthError::new(StatusCode::SERVICE_UNAVAILABLE, "temporarily_unavailable", "Could not look up the code")
+        })?
+        .ok_or_else(|| invalid_grant("Unknown, expired or used code"))?;
+    if grant.client_id != client_id || grant.redirect_uri != req.redirect_uri {
+        return Err(invalid_grant("Code was issued to another client or redirect_uri"));
+    }
+    let attestation = hesha_core::parse_attestation(&grant.attestation)
+        .map_err(|_| invalid_grant("Attestation behind the code is invalid"))?;
+
+    let claims = IdTokenClaims::new(
+        issuer_url(&state.config),
+        client_id,
+        &grant.attestation,
+        &attestation,
+        grant.nonce,
+        grant.auth_time,
+        provider.id_token_ttl(),
+    );
+    let active = state.keys.active();
+    let id_token = sign_id_token(&claims, &*active.signer, &active.key_id).map_err(|e| {
+        tracing::error!("Failed to sign ID token: {}", e);
+        OAuthError::new(StatusCode::INTERNAL_SERVER_ERROR, "server_error", "Could not sign the ID token")
+    })?;
+
+    let response = TokenResponse {
+        access_token: hesha_crypto::generate_hex_nonce(),
+        token_type: "Bearer",
+        expires_in: (claims.exp - Utc::now().timestamp()).max(0),
+        id_token,
+    };
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(response)).into_response())
+}
+
+/// An OAuth 2.0 error response.
+#[derive(Debug)]
+pub struct OAuthError {
+    status: StatusCode,
+    error: &'static str,
+    description: String,
+}
+
+impl OAuthError {
+    fn new(status: StatusCode, error: &'static str, description: impl Into<String>) -> Self {
+        Self { status, error, description: description.into() }
+    }
+
+    fn invalid_request(description: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "invalid_request", description)
+    }
+
+    fn invalid_client(description: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, "invalid_client", description)
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "error": self.error, "error_description": self.description });
+        let mut response = (self.status, [(header::CACHE_CONTROL, "no-store")], Json(body)).into_response();
+        if self.status == StatusCode::UNAUTHORIZED {
+            response.headers_mut().insert(header::WWW_AUTHENTICATE, header::HeaderValue::from_static("Basic"));
+        }
+        response
+    }
+}
+
+fn provider(state: &AppState) -> Result<Arc<OpenIdProvider>, ApiError> {
+    state
+        .openid
+        .clone()
+        .ok_or_else(|| ApiError::new(ErrorCode::NotFound, "This issuer is not an OpenID provider"))
+}
+
+/// An unexpired, unrevoked attestation signed by one of this node's keys.
+async fn verify_own_attestation(state: &AppState, jwt: &str) -> Result<Attestation, ApiError> {
+    let key_id = decode_key_id_unverified(jwt).map_err(|e| unauthorized(format!("Invalid attestation: {}", e)))?;
+    let keys = state.keys.published();
+    let key = match key_id {
+        Some(key_id) => keys.iter().find(|key| key.key_id == key_id),
+        None => keys.first(),
+    }
+    .ok_or_else(|| unauthorized("Attestation is not signed by a key of this issuer"))?;
+    let verified = verify_attestation_with_key(jwt, &key.public_key)
+        .map_err(|e| unauthorized(format!("Invalid attestation: {}", e)))?;
+    if verified.attestation.iss != state.config.domain {
+        return Err(unauthorized("Attestation is from another issuer"));
+    }
+    let revoked = state.storage.revocations.get(&verified.attestation.jti).await.map_err(storage_failed)?;
+    if revoked.is_some() {
+        return Err(unauthorized("Attestation has been revoked"));
+    }
+    Ok(verified.attestation)
+}
+
+/// Client ID and secret from HTTP Basic authentication or the form.
+fn client_credentials(headers: &HeaderMap, req: &TokenRequest) -> Option<(String, String)> {
+    let basic = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|encoded| general_purpose::STANDARD.decode(encoded.trim()).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok());
+    if let Some(basic) = basic {
+        let (client_id, secret) = basic.split_once(':')?;
+        return Some((client_id.to_string(), secret.to_string()));
+    }
+    Some((req.client_id.clone()?, req.client_secret.clone()?))
+}
+
+/// Percent-encode everything but unreserved characters (RFC 3986).
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn unauthorized(detail: impl Into<String>) -> ApiError {
+    ApiError::new(ErrorCode::Unauthorized, detail)
+}
+
+fn storage_failed(e: hesha_storage::StorageError) -> ApiError {
+    tracing::error!("OpenID provider state unavailable: {}", e);
+    ApiError::new(ErrorCode::StorageFailed, "Could not reach the node's shared state")
+}
@@ -1,15 +1,31 @@
 //! Public key discovery endpoint.
 
 use crate::state::AppState;
-use axum::{extract::State, Json};
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use hesha_types::IssuerInfo;
 use hesha_types::attestation::ServiceDiscovery;
-use chrono::Utc;
+use serde::Serialize;
+
+/// How long verifiers may use a key document before revalidating it.
+const CACHE_CONTROL: &str = "public, max-age=300";
+
+/// HTTP date format used by `Last-Modified` and `If-Modified-Since`.
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
 
 /// Handle .well-known public key request.
+/// 
+/// The top-level key is the one new attestations are signed with; `keys`
+/// adds the previous keys whose window hasn't passed, so attestations
+/// signed before a rotation still find their key by `kid`.
 pub async fn pubkey(
     State(state): State<AppState>,
-) -> Json<IssuerInfo> {
+    headers: HeaderMap,
+) -> Response {
     // Build service info if trust domain differs from issuer domain
     let service_info = match (&state.config.trust_domain, &state.config.service_url) {
         (Some(trust_domain), Some(service_url)) if trust_domain != &state.config.domain => {
@@ -22,11 +38,59 @@ pub async fn pubkey(
         _ => None,
     };
     
-    Json(IssuerInfo {
-        public_key: state.issuer_key.public.clone(),
+    let active = state.keys.active();
+    let info = IssuerInfo {
+        public_key: active.keypair.public.clone(),
         algorithm: "Ed25519".to_string(),
-        created_at: Utc::now(), // In production, this would be the key creation time
-        key_id: Some("default".to_string()),
+        created_at: state.keys.active_created_at(),
+        key_id: Some(active.key_id),
         service_info,
-    })
-}
\ No newline at end of file
+        keys: state.keys.published(),
+    };
+    cacheable_json(&headers, &info, state.keys.published_at())
+}
+
+/// Serve a key document with validators, answering `304 Not Modified` when
+/// the client's copy is current.
+/// 
+/// The ETag is a hash of the document, so it only changes when the keys do.
+/// `If-None-Match` takes precedence over `If-Modified-Since`.
+pub(crate) fn cacheable_json<T: Serialize>(
+    headers: &HeaderMap,
+    document: &T,
+    last_modified: DateTime<Utc>,
+) -> Response {
+    let body = match serde_json::to_vec(document) {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!("Failed to serialize key document: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let etag = format!("\"{}\"", hex::encode(&hesha_crypto::sha256(&body)[..16]));
+    // HTTP dates have whole seconds
+    let last_modified = last_modified.format(HTTP_DATE).to_string();
+    
+    let header = |name: header::HeaderName| headers.get(name).and_then(|value| value.to_str().ok());
+    let not_modified = match header(header::IF_NONE_MATCH) {
+        Some(tags) => tags.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"),
+        None => header(header::IF_MODIFIED_SINCE)
+            .and_then(|since| NaiveDateTime::parse_from_str(since, HTTP_DATE).ok())
+            .zip(NaiveDateTime::parse_from_str(&last_modified, HTTP_DATE).ok())
+            .is_some_and(|(since, modified)| modified <= since),
+    };
+    let validators = [
+        (header::ETAG, etag),
+        (header::LAST_MODIFIED, last_modified),
+        (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+    ];
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, validators).into_response();
+    }
+    (
+        validators,
+        [(header::CONTENT_TYPE, "application/json")],
+        body,
+    )
+        .into_response()
+}
@@ -0,0 +1,136 @@
+//! Published revocation list.
+//!
+//! The list is served from a copy held in memory, refreshed by the
+//! scheduler's `revocations` job and dropped whenever this node revokes an
+//! attestation. Revocations recorded by other nodes sharing the database
+//! appear at the next refresh.
+
+use crate::problem::ApiError;
+use crate::request_validation::ApiQuery;
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use hesha_core::sign_revocation_list;
+use hesha_storage::{Storage, StorageResult};
+use hesha_types::{ErrorCode, RevocationEntry, RevocationList};
+use serde::Deserialize;
+use std::sync::{Arc, RwLock};
+
+/// Entries per page unless the client asks for fewer.
+const MAX_PER_PAGE: usize = 1000;
+
+/// Public path of the revocation list.
+const REVOCATIONS_PATH: &str = "/.well-known/hesha/revocations.json";
+
+/// The revocation list as last loaded from storage.
+#[derive(Default)]
+pub struct PublishedRevocations {
+    current: RwLock<Option<Arc<Published>>>,
+}
+
+struct Published {
+    entries: Vec<RevocationEntry>,
+    updated_at: Option<DateTime<Utc>>,
+    etag: String,
+}
+
+impl PublishedRevocations {
+    /// Reload the list from storage, returning whether it changed.
+    pub async fn publish(&self, storage: &Storage) -> StorageResult<bool> {
+        let entries = storage.revocations.list().await?;
+        // Revocations are only ever added, so count and latest time identify the list
+        let updated_at = entries.iter().map(|entry| entry.revoked_at).max();
+        let etag = format!(
+            "\"{}-{}\"",
+            entries.len(),
+            updated_at.map_or(0, |time| time.timestamp_micros())
+        );
+        let published = Arc::new(Published { entries, updated_at, etag });
+        
+        let mut current = self.current.write().unwrap_or_else(|e| e.into_inner());
+        let changed = current.as_ref().map_or(true, |old| old.etag != published.etag);
+        *current = Some(published);
+        Ok(changed)
+    }
+    
+    /// Drop the held list so the next request loads it again.
+    pub fn invalidate(&self) {
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+    
+    async fn current(&self, storage: &Storage) -> StorageResult<Arc<Published>> {
+        loop {
+            if let Some(published) = self.current.read().unwrap_or_else(|e| e.into_inner()).clone() {
+                return Ok(published);
+            }
+            self.publish(storage).await?;
+        }
+    }
+}
+
+/// Pagination parameters.
+#[derive(Debug, Deserialize)]
+pub struct PageQuery {
+    /// Page number, starting at 1.
+    pub page: Option<usize>,
+    /// Entries per page (at most 1000).
+    pub per_page: Option<usize>,
+}
+
+/// Handle revocation list request.
+/// 
+/// Entries are listed oldest first, so pages already fetched stay valid as
+/// revocations are added. Every page carries the same ETag, which changes
+/// whenever an attestation is revoked, and is signed with the issuer key.
+pub async fn revocations(
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<PageQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let published = match state.revocation_list.current(&state.storage).await {
+        Ok(published) => published,
+        Err(e) => {
+            tracing::error!("Failed to load revocations: {}", e);
+            return ApiError::new(ErrorCode::StorageFailed, "Failed to load revocation list").into_response();
+        }
+    };
+    let entries = &published.entries;
+    let etag = published.etag.clone();
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|tags| tags.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"));
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+    
+    let per_page = query.per_page.unwrap_or(MAX_PER_PAGE).clamp(1, MAX_PER_PAGE);
+    let page = query.page.unwrap_or(1).max(1);
+    let start = (page - 1).saturating_mul(per_page).min(entries.len());
+    let end = start.saturating_add(per_page).min(entries.len());
+    
+    let mut list = RevocationList::new(state.config.domain.clone());
+    list.updated_at = published.updated_at.unwrap_or_else(Utc::now);
+    list.entries = entries[start..end].to_vec();
+    if end < entries.len() {
+        list.next = Some(format!("{}?page={}&per_page={}", REVOCATIONS_PATH, page + 1, per_page));
+    }
+    if let Err(e) = sign_revocation_list(&mut list, &*state.keys.active().signer) {
+        tracing::error!("Failed to sign revocation list: {}", e);
+        return ApiError::new(ErrorCode::SigningFailed, "Failed to sign revocation list").into_response();
+    }
+    
+    (
+        [
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, "public, max-age=300".to_string()),
+        ],
+        Json(list),
+    )
+        .into_response()
+}
@@ -0,0 +1,61 @@
+//! Online status of single attestations.
+//!
+//! `GET /status/{jti}` answers whether one attestation is good, revoked or
+//! unknown to this issuer, signed and valid for a few minutes, so verifiers
+//! that can't download the whole revocation list can still check it.
+
+use crate::problem::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use hesha_core::sign_status_response;
+use hesha_types::{ErrorCode, StatusResponse};
+
+/// How long a status response stays valid, in seconds.
+const STATUS_VALIDITY_SECS: i64 = 300;
+
+/// Longest JWT ID looked up.
+const MAX_JTI_LEN: usize = 128;
+
+/// Handle a status request.
+/// 
+/// Revocations are read from storage rather than the published list, so
+/// a revocation on any node sharing the database shows at once.
+pub async fn status(
+    State(state): State<AppState>,
+    Path(jti): Path<String>,
+) -> Response {
+    if jti.is_empty() || jti.len() > MAX_JTI_LEN {
+        return ApiError::new(ErrorCode::InvalidRequest, "Invalid attestation ID").into_response();
+    }
+    let status = match state.storage.attestation_status(&jti).await {
+        Ok(status) => status,
+        Err(e) => {
+            tracing::error!("Failed to look up status of {}: {}", jti, e);
+            return ApiError::new(ErrorCode::StorageFailed, "Failed to look up attestation status").into_response();
+        }
+    };
+    
+    let active = state.keys.active();
+    let mut response = StatusResponse::new(
+        state.config.domain.clone(),
+        jti,
+        status,
+        chrono::Duration::seconds(STATUS_VALIDITY_SECS),
+    );
+    response.key_id = Some(active.key_id.clone());
+    if let Err(e) = sign_status_response(&mut response, &*active.signer) {
+        tracing::error!("Failed to sign status response: {}", e);
+        return ApiError::new(ErrorCode::SigningFailed, "Failed to sign status response").into_response();
+    }
+    
+    (
+        [(header::CACHE_CONTROL, format!("public, max-age={}", STATUS_VALIDITY_SECS))],
+        Json(response),
+    )
+        .into_response()
+}
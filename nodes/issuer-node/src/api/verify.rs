@@ -0,0 +1,222 @@
+//! Phone verification endpoints.
+
+use crate::captcha::require_captcha;
+use crate::problem::ApiError;
+use crate::request_validation::{ApiJson, ApiQuery};
+use crate::state::AppState;
+use crate::verification::{Challenge, PendingCheck, SessionError};
+use axum::{
+    extract::{ConnectInfo, State},
+    http::StatusCode,
+    response::Html,
+    Json,
+};
+use hesha_types::{ErrorCode, PhoneNumber, VerificationMethod, WebhookEventData};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+/// Request to start phone verification.
+#[derive(Debug, Deserialize)]
+pub struct VerificationStartRequest {
+    /// Phone number to send the code to.
+    pub phone_number: String,
+    /// Solved CAPTCHA, when the issuer requires one.
+    #[serde(default)]
+    pub captcha_token: Option<String>,
+}
+
+/// A started verification session.
+#[derive(Debug, Serialize)]
+pub struct VerificationStartResponse {
+    /// Session identifier for `/verify/check` and `/attest/simple`.
+    pub session_id: String,
+    /// Seconds until the session expires.
+    pub expires_in: u64,
+    /// Where the code went (`sms` or `email`), or `carrier` when the phone
+    /// must open `verification_url` instead.
+    pub channel: VerificationMethod,
+    /// For carrier verification, the URL the phone must open instead of
+    /// waiting for a code.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification_url: Option<String>,
+}
+
+/// Request to check a verification code.
+#[derive(Debug, Deserialize)]
+pub struct VerificationCheckRequest {
+    /// Session from `/verify/start`.
+    pub session_id: String,
+    /// Code the user received.
+    pub code: String,
+}
+
+/// Result of checking a verification code.
+#[derive(Debug, Serialize)]
+pub struct VerificationCheckResponse {
+    /// Whether the code was correct.
+    pub verified: bool,
+}
+
+/// Redirect back from a carrier's authorization page.
+#[derive(Debug, Deserialize)]
+pub struct CarrierCallback {
+    /// Session ID passed to the carrier.
+    pub state: String,
+    /// Authorization code, when the carrier recognized the line.
+    pub code: Option<String>,
+    /// OAuth error, when it did not.
+    pub error: Option<String>,
+}
+
+/// Handle request to send a verification code.
+pub async fn start(
+    State(state): State<AppState>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    ApiJson(req): ApiJson<VerificationStartRequest>,
+) -> Result<Json<VerificationStartResponse>, ApiError> {
+    require_captcha(&state, req.captcha_token.as_deref(), peer.map(|ConnectInfo(peer)| peer.ip())).await?;
+    
+    let phone_number = PhoneNumber::new(&req.phone_number)
+        .map_err(|e| {
+            ApiError::new(ErrorCode::InvalidPhoneNumber, format!("Invalid phone number: {}", e))
+        })?;
+    
+    let session_id = state.verification.start(phone_number.clone()).await
+        .map_err(|_| state_unavailable())?;
+    let challenge = match state.verifier.start(&phone_number, &session_id).await {
+        Ok(challenge) => challenge,
+        Err(e) => {
+            tracing::error!("Failed to start phone verification: {}", e);
+            state.verification.cancel(&session_id).await;
+            return Err(verifier_unavailable(&state, "Could not send verification code"));
+        }
+    };
+    state.verification.set_method(&session_id, challenge.method()).await
+        .map_err(|_| state_unavailable())?;
+    
+    Ok(Json(VerificationStartResponse {
+        session_id,
+        expires_in: state.verification.ttl().as_secs(),
+        channel: challenge.method(),
+        verification_url: match challenge {
+            Challenge::Code | Challenge::EmailCode => None,
+            Challenge::Redirect(url) => Some(url),
+        },
+    }))
+}
+
+/// Handle request to check a verification code.
+/// 
+/// A wrong code answers `verified: false` until the attempt limit is hit,
+/// after which the session is closed and the user must start over.
+pub async fn check(
+    State(state): State<AppState>,
+    ApiJson(req): ApiJson<VerificationCheckRequest>,
+) -> Result<Json<VerificationCheckResponse>, ApiError> {
+    let phone_number = match state.verification.begin_check(&req.session_id).await {
+        Ok(PendingCheck::Verified) => return Ok(Json(VerificationCheckResponse { verified: true })),
+        Ok(PendingCheck::Check(phone_number)) => phone_number,
+        Err(SessionError::TooManyAttempts) => {
+            state.metrics.record_verification(false);
+            verification_failed(&state, &req.session_id, "too_many_attempts");
+            return Err(too_many_attempts());
+        }
+        Err(SessionError::Unavailable) => return Err(state_unavailable()),
+        Err(_) => return Err(session_not_found()),
+    };
+    
+    let verified = state.verifier.check_code(&phone_number, &req.code).await
+        .map_err(|e| {
+            tracing::error!("Failed to check verification code: {}", e);
+            verifier_unavailable(&state, "Could not check verification code")
+        })?;
+    state.metrics.record_verification(verified);
+    if verified {
+        state.verification.mark_verified(&req.session_id).await
+            .map_err(|e| match e {
+                SessionError::Unavailable => state_unavailable(),
+                _ => session_not_found(),
+            })?;
+    } else {
+        verification_failed(&state, &req.session_id, "wrong_code");
+    }
+    
+    Ok(Json(VerificationCheckResponse { verified }))
+}
+
+/// Handle the carrier's redirect after silent verification.
+/// 
+/// This is opened in the phone's browser, so it answers with a short page;
+/// the app continues with `/verify/check` or `/attest/simple`.
+pub async fn callback(
+    State(state): State<AppState>,
+    ApiQuery(params): ApiQuery<CarrierCallback>,
+) -> (StatusCode, Html<&'static str>) {
+    const VERIFIED: &str = "<p>Phone number verified. You can return to the app.</p>";
+    const FAILED: &str = "<p>Phone number could not be verified. Return to the app to try again.</p>";
+    
+    let Some(code) = params.code.filter(|_| params.error.is_none()) else {
+        tracing::info!("Carrier declined verification: {}", params.error.unwrap_or_default());
+        state.metrics.record_verification(false);
+        verification_failed(&state, &params.state, "carrier_declined");
+        return (StatusCode::FORBIDDEN, Html(FAILED));
+    };
+    let phone_number = match state.verification.begin_check(&params.state).await {
+        Ok(PendingCheck::Verified) => return (StatusCode::OK, Html(VERIFIED)),
+        Ok(PendingCheck::Check(phone_number)) => phone_number,
+        Err(SessionError::TooManyAttempts) => return (StatusCode::TOO_MANY_REQUESTS, Html(FAILED)),
+        Err(SessionError::Unavailable) => return (StatusCode::INTERNAL_SERVER_ERROR, Html(FAILED)),
+        Err(_) => return (StatusCode::NOT_FOUND, Html(FAILED)),
+    };
+    
+    let verified = state.verifier.check_code(&phone_number, &code).await;
+    let marked = match verified {
+        Ok(true) => state.verification.mark_verified(&params.state).await.is_ok(),
+        _ => false,
+    };
+    match verified {
+        Ok(true) if marked => {
+            state.metrics.record_verification(true);
+            (StatusCode::OK, Html(VERIFIED))
+        }
+        Ok(_) => {
+            state.metrics.record_verification(false);
+            verification_failed(&state, &params.state, "carrier_rejected");
+            (StatusCode::FORBIDDEN, Html(FAILED))
+        }
+        Err(e) => {
+            tracing::error!("Failed to check carrier verification: {}", e);
+            state.metrics.record_error("verification_unavailable");
+            (StatusCode::BAD_GATEWAY, Html(FAILED))
+        }
+    }
+}
+
+/// Notify webhooks of a failed verification attempt.
+fn verification_failed(state: &AppState, session_id: &str, reason: &str) {
+    state.webhooks.emit(WebhookEventData::VerificationFailed {
+        session_id: session_id.to_string(),
+        reason: reason.to_string(),
+    });
+}
+
+/// Error for a session closed after too many wrong codes.
+fn too_many_attempts() -> ApiError {
+    ApiError::new(ErrorCode::TooManyAttempts, "Too many wrong codes; start a new verification")
+}
+
+/// Error for a verification provider that could not be reached.
+fn verifier_unavailable(state: &AppState, description: &str) -> ApiError {
+    state.metrics.record_error("verification_unavailable");
+    ApiError::new(ErrorCode::VerificationUnavailable, description)
+}
+
+/// Error for sessions that could not be read or written.
+pub fn state_unavailable() -> ApiError {
+    ApiError::new(ErrorCode::StorageFailed, "Failed to access verification session")
+}
+
+/// Error for an unknown or expired session.
+pub fn session_not_found() -> ApiError {
+    ApiError::new(ErrorCode::SessionNotFound, "Verification session not found or expired")
+}
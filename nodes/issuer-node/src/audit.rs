@@ -0,0 +1,171 @@
+//! Tamper-evident audit log.
+//!
+//! Issuance, revocation, admin actions, issuer key changes and refused
+//! credentials are appended to the storage backend's audit log as
+//! hash-chained entries signed with the issuer key. Operators export the
+//! log from `/admin/audit` and check it with `hesha audit verify`.
+
+use crate::auth::Caller;
+use crate::keys::Keyring;
+use crate::problem::ApiError;
+use crate::request_validation::ApiQuery;
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use chrono::Utc;
+use hesha_core::seal_audit_entry;
+use hesha_storage::{AuditStore, StorageError};
+use hesha_types::{AuditEntry, AuditEvent, ErrorCode};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Attempts at appending when other writers keep taking the next sequence
+/// number.
+const MAX_APPEND_ATTEMPTS: usize = 5;
+
+/// Largest number of entries exported per request.
+const MAX_EXPORT_ENTRIES: usize = 10_000;
+
+/// Appends sealed entries to the audit store.
+pub struct AuditLog {
+    store: Arc<dyn AuditStore>,
+    keys: Arc<Keyring>,
+    // Keeps this process's appends from racing each other for a sequence number
+    append_lock: tokio::sync::Mutex<()>,
+}
+
+impl AuditLog {
+    /// Log to `store`, signing with the active issuer key.
+    pub fn new(store: Arc<dyn AuditStore>, keys: Arc<Keyring>) -> Self {
+        Self {
+            store,
+            keys,
+            append_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Record an event.
+    ///
+    /// A failure is logged rather than returned, so an unavailable audit
+    /// store doesn't take issuance down with it.
+    pub async fn record(&self, event: AuditEvent) {
+        if let Err(e) = self.append(event).await {
+            tracing::error!("Failed to append to audit log: {}", e);
+        }
+    }
+
+    /// Record a key rotation if the log was last signed with another key.
+    pub async fn record_key_change(&self) -> anyhow::Result<()> {
+        let current = self.keys.active().keypair.public.clone();
+        if let Some(last) = self.store.last().await? {
+            if last.signer != current {
+                self.append(AuditEvent::KeyRotated {
+                    previous: last.signer,
+                    current,
+                })
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn append(&self, event: AuditEvent) -> anyhow::Result<AuditEntry> {
+        let _guard = self.append_lock.lock().await;
+        for _ in 0..MAX_APPEND_ATTEMPTS {
+            let last = self.store.last().await?;
+            let signing_key = self.keys.active().keypair;
+            let entry = seal_audit_entry(last.as_ref(), Utc::now(), event.clone(), &signing_key)?;
+            match self.store.append(&entry).await {
+                Ok(()) => return Ok(entry),
+                // Another replica appended first; chain onto its entry instead
+                Err(StorageError::Conflict(_)) => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        anyhow::bail!("audit log kept changing during {} attempts", MAX_APPEND_ATTEMPTS)
+    }
+}
+
+/// Export range.
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// Export entries after this sequence number.
+    #[serde(default)]
+    pub after: u64,
+    /// Maximum entries to return.
+    pub limit: Option<usize>,
+}
+
+/// Export audit entries as JSON lines, oldest first (operators only).
+pub async fn export(
+    Extension(caller): Extension<Caller>,
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<ExportQuery>,
+) -> Response {
+    let limit = query.limit.unwrap_or(MAX_EXPORT_ENTRIES).min(MAX_EXPORT_ENTRIES);
+    let entries = match state.storage.audit.list(query.after, limit).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::error!("Failed to read audit log: {}", e);
+            return ApiError::new(ErrorCode::StorageFailed, "Failed to read the audit log").into_response();
+        }
+    };
+    state
+        .audit
+        .record(AuditEvent::AdminAction {
+            action: "audit.export".to_string(),
+            peer: caller.id,
+        })
+        .await;
+
+    let mut body = String::new();
+    for entry in &entries {
+        body.push_str(&serde_json::to_string(entry).unwrap_or_default());
+        body.push('\n');
+    }
+    ([(header::CONTENT_TYPE, "application/x-ndjson")], body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hesha_crypto::generate_keypair;
+    use hesha_storage::Storage;
+
+    #[tokio::test]
+    async fn test_record_and_key_change() {
+        let storage = Storage::memory();
+        let keys = Arc::new(Keyring::new("default".to_string(), generate_keypair().unwrap()));
+        let key = keys.active().keypair.public.clone();
+        let log = AuditLog::new(storage.audit.clone(), keys);
+
+        // Nothing to compare against in an empty log
+        log.record_key_change().await.unwrap();
+        log.record(AuditEvent::AttestationRevoked {
+            jti: "jti-1".to_string(),
+            reason: None,
+        })
+        .await;
+
+        let keys = Arc::new(Keyring::new("key-2".to_string(), generate_keypair().unwrap()));
+        let rotated = keys.active().keypair.public.clone();
+        let log = AuditLog::new(storage.audit.clone(), keys);
+        log.record_key_change().await.unwrap();
+        log.record_key_change().await.unwrap();
+
+        let entries = storage.audit.list(0, 10).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[1].event,
+            AuditEvent::KeyRotated {
+                previous: key.clone(),
+                current: rotated.clone(),
+            }
+        );
+        hesha_core::verify_audit_log(&entries, &[key, rotated]).unwrap();
+    }
+}
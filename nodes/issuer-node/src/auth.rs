@@ -0,0 +1,176 @@
+//! Caller authentication for `/attest` and the `/admin` routes.
+//!
+//! `/attest` trusts the phone number it is given, so an issuer exposing it
+//! should accept it only from its own backends. Callers present either a
+//! static API key or a JWT from the operator's OpenID Connect provider as
+//! `Authorization: Bearer ...`. The OTP endpoints stay open, since there
+//! the user proves the number themselves.
+//!
+//! Operators authenticate to `/admin` the same way, against the separate
+//! `[admin_auth]` credentials, so a partner key never grants admin access.
+
+use crate::problem::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use hesha_crypto::{constant_time_compare, sha256};
+use hesha_types::{AuditEvent, AuthConfig, ErrorCode};
+
+/// An authenticated caller, added to the request extensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caller {
+    /// Stable identifier: `key:<hash prefix>` or `oidc:<subject>`.
+    pub id: String,
+}
+
+/// Checks credentials against the configured method.
+pub enum Authenticator {
+    /// No credentials required.
+    None,
+    /// One of these keys must be presented.
+    ApiKeys(Vec<String>),
+    /// A token signed by the OIDC provider must be presented.
+    #[cfg(feature = "oidc")]
+    Oidc(crate::oidc::OidcValidator),
+}
+
+impl Authenticator {
+    /// Set up the method from issuer.toml.
+    pub fn from_config(config: &AuthConfig) -> anyhow::Result<Self> {
+        match config {
+            AuthConfig::None => Ok(Self::None),
+            AuthConfig::ApiKey { keys_env } => {
+                let keys: Vec<String> = std::env::var(keys_env)
+                    .map_err(|_| anyhow::anyhow!("{} must be set for API key authentication", keys_env))?
+                    .split(',')
+                    .map(|key| key.trim().to_string())
+                    .filter(|key| !key.is_empty())
+                    .collect();
+                anyhow::ensure!(!keys.is_empty(), "{} holds no API keys", keys_env);
+                Ok(Self::ApiKeys(keys))
+            }
+            #[cfg(feature = "oidc")]
+            AuthConfig::Oidc { issuer, audience, jwks_url } => Ok(Self::Oidc(
+                crate::oidc::OidcValidator::new(issuer.clone(), audience.clone(), jwks_url.clone()),
+            )),
+            #[cfg(not(feature = "oidc"))]
+            AuthConfig::Oidc { .. } => {
+                anyhow::bail!("OIDC authentication requires issuer-node built with the 'oidc' feature")
+            }
+        }
+    }
+
+    /// Identify the caller, or explain why the credentials were refused.
+    ///
+    /// Returns `None` when no credentials are required.
+    pub async fn authenticate(&self, headers: &HeaderMap) -> Result<Option<Caller>, String> {
+        if matches!(self, Self::None) {
+            return Ok(None);
+        }
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .ok_or_else(|| "Missing bearer token".to_string())?;
+
+        match self {
+            Self::None => Ok(None),
+            Self::ApiKeys(keys) => {
+                // Check every key so timing doesn't reveal which one matched
+                let matched = keys
+                    .iter()
+                    .fold(false, |matched, key| constant_time_compare(key.as_bytes(), token.as_bytes()) | matched);
+                if !matched {
+                    return Err("Unknown API key".to_string());
+                }
+                Ok(Some(Caller {
+                    id: format!("key:{}", &hex::encode(sha256(token.as_bytes()))[..16]),
+                }))
+            }
+            #[cfg(feature = "oidc")]
+            Self::Oidc(validator) => {
+                let subject = validator.validate(token).await.map_err(|e| e.to_string())?;
+                Ok(Some(Caller { id: format!("oidc:{}", subject) }))
+            }
+        }
+    }
+}
+
+/// Middleware rejecting unauthenticated requests with `401 Unauthorized`.
+pub async fn require_auth(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    match state.auth.authenticate(request.headers()).await {
+        Ok(caller) => {
+            if let Some(caller) = caller {
+                request.extensions_mut().insert(caller);
+            }
+            next.run(request).await
+        }
+        Err(reason) => unauthorized(&state, request.uri().path(), reason).await,
+    }
+}
+
+/// Middleware serving the `/admin` routes only to authenticated operators.
+///
+/// Unlike `/attest`, these routes are refused outright while `[admin_auth]`
+/// is unset; the control socket still administers the node then.
+pub async fn require_admin(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    if matches!(*state.admin_auth, Authenticator::None) {
+        return ApiError::new(
+            ErrorCode::Forbidden,
+            "Admin endpoints are disabled; configure [admin_auth] or use the control socket",
+        )
+        .into_response();
+    }
+    match state.admin_auth.authenticate(request.headers()).await {
+        Ok(caller) => {
+            if let Some(caller) = caller {
+                request.extensions_mut().insert(caller);
+            }
+            next.run(request).await
+        }
+        Err(reason) => unauthorized(&state, request.uri().path(), reason).await,
+    }
+}
+
+/// Record a refused request and answer it with `401 Unauthorized`.
+async fn unauthorized(state: &AppState, path: &str, reason: String) -> Response {
+    tracing::info!("Rejected unauthenticated request: {}", reason);
+    state.metrics.record_error("unauthorized");
+    state.audit.record(AuditEvent::AuthFailed {
+        error: "unauthorized".to_string(),
+        reason: reason.clone(),
+        path: path.to_string(),
+    }).await;
+    ApiError::new(ErrorCode::Unauthorized, reason)
+        .with_header(header::WWW_AUTHENTICATE, "Bearer")
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_api_keys() {
+        let auth = Authenticator::ApiKeys(vec!["key-one".to_string(), "key-two".to_string()]);
+
+        let caller = auth.authenticate(&bearer("key-two")).await.unwrap().unwrap();
+        assert!(caller.id.starts_with("key:"));
+        assert_ne!(caller, auth.authenticate(&bearer("key-one")).await.unwrap().unwrap());
+
+        assert!(auth.authenticate(&bearer("key-three")).await.is_err());
+        assert!(auth.authenticate(&HeaderMap::new()).await.is_err());
+        assert_eq!(Authenticator::None.authenticate(&HeaderMap::new()).await, Ok(None));
+    }
+}
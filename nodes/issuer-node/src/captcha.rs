@@ -0,0 +1,166 @@
+//! CAPTCHA checks on the SMS-sending path.
+//!
+//! With `[captcha]` configured, `/verify/start` and `/attest/simple` need a
+//! `captcha_token` from the deployment's Turnstile or hCaptcha widget, which
+//! is checked with the provider before a code is sent or an attestation is
+//! issued. This keeps scripts from using the node to send SMS in bulk.
+
+use crate::problem::ApiError;
+use crate::state::AppState;
+use async_trait::async_trait;
+use hesha_types::{CaptchaConfig, ErrorCode};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// Checks CAPTCHA tokens with the service that issued them.
+#[async_trait]
+pub trait CaptchaVerifier: Send + Sync {
+    /// Whether `token` is a valid, unused solution, optionally for a
+    /// request from `remote_ip`.
+    async fn verify(&self, token: &str, remote_ip: Option<IpAddr>) -> anyhow::Result<bool>;
+}
+
+/// Create the verifier for `[captcha]`.
+pub fn from_config(config: &CaptchaConfig) -> anyhow::Result<Arc<dyn CaptchaVerifier>> {
+    #[cfg(feature = "captcha")]
+    {
+        let secret = std::env::var(&config.secret_env)
+            .map_err(|_| anyhow::anyhow!("{} must be set for CAPTCHA verification", config.secret_env))?;
+        Ok(Arc::new(site_verify::SiteVerify::new(config.provider, secret)))
+    }
+    #[cfg(not(feature = "captcha"))]
+    {
+        let _ = config;
+        anyhow::bail!("[captcha] requires issuer-node built with the 'captcha' feature")
+    }
+}
+
+/// Refuse a request without a valid CAPTCHA token when `[captcha]` is set.
+pub async fn require_captcha(
+    state: &AppState,
+    token: Option<&str>,
+    remote_ip: Option<IpAddr>,
+) -> Result<(), ApiError> {
+    let Some(verifier) = &state.captcha else {
+        return Ok(());
+    };
+    let Some(token) = token.filter(|token| !token.is_empty()) else {
+        return Err(ApiError::new(ErrorCode::CaptchaRequired, "A captcha_token is required"));
+    };
+    match verifier.verify(token, remote_ip).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(ApiError::new(ErrorCode::CaptchaFailed, "CAPTCHA token was not accepted")),
+        Err(e) => {
+            tracing::error!("Failed to verify CAPTCHA token: {}", e);
+            Err(ApiError::new(ErrorCode::CaptchaUnavailable, "Could not verify CAPTCHA token"))
+        }
+    }
+}
+
+#[cfg(feature = "captcha")]
+mod site_verify {
+    use super::CaptchaVerifier;
+    use async_trait::async_trait;
+    use hesha_types::CaptchaProvider;
+    use serde::Deserialize;
+    use std::net::IpAddr;
+    use std::time::Duration;
+
+    /// Cloudflare Turnstile's verification endpoint.
+    const TURNSTILE_VERIFY_URL: &str = "https://challenges.cloudflare.com/turnstile/v0/siteverify";
+
+    /// hCaptcha's verification endpoint.
+    const HCAPTCHA_VERIFY_URL: &str = "https://api.hcaptcha.com/siteverify";
+
+    /// Verifier for providers with a `siteverify` endpoint; Turnstile and
+    /// hCaptcha share the same request and response shape.
+    pub struct SiteVerify {
+        client: reqwest::Client,
+        verify_url: String,
+        secret: String,
+    }
+
+    /// Answer from a `siteverify` endpoint.
+    #[derive(Debug, Deserialize)]
+    struct SiteVerifyResponse {
+        success: bool,
+        #[serde(default, rename = "error-codes")]
+        error_codes: Vec<String>,
+    }
+
+    impl SiteVerify {
+        /// Create a verifier for a provider.
+        pub fn new(provider: CaptchaProvider, secret: String) -> Self {
+            let verify_url = match provider {
+                CaptchaProvider::Turnstile => TURNSTILE_VERIFY_URL,
+                CaptchaProvider::Hcaptcha => HCAPTCHA_VERIFY_URL,
+            };
+            Self::with_verify_url(verify_url, secret)
+        }
+
+        /// Create a verifier against a different endpoint.
+        pub fn with_verify_url(verify_url: &str, secret: String) -> Self {
+            Self {
+                client: reqwest::Client::builder()
+                    .timeout(Duration::from_secs(10))
+                    .build()
+                    .unwrap_or_default(),
+                verify_url: verify_url.to_string(),
+                secret,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl CaptchaVerifier for SiteVerify {
+        async fn verify(&self, token: &str, remote_ip: Option<IpAddr>) -> anyhow::Result<bool> {
+            let remote_ip = remote_ip.map(|ip| ip.to_string());
+            let mut form = vec![("secret", self.secret.as_str()), ("response", token)];
+            if let Some(remote_ip) = &remote_ip {
+                form.push(("remoteip", remote_ip));
+            }
+            let response = self.client.post(&self.verify_url).form(&form).send().await?;
+            if !response.status().is_success() {
+                anyhow::bail!("CAPTCHA provider returned {}", response.status());
+            }
+            let result: SiteVerifyResponse = response.json().await?;
+            if !result.success {
+                tracing::info!("CAPTCHA token rejected: {}", result.error_codes.join(", "));
+            }
+            Ok(result.success)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use wiremock::matchers::{body_string_contains, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        #[tokio::test]
+        async fn test_site_verify() {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(body_string_contains("secret=s3cret"))
+                .and(body_string_contains("response=good"))
+                .and(body_string_contains("remoteip=203.0.113.7"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "success": true
+                })))
+                .mount(&server)
+                .await;
+            Mock::given(method("POST"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "success": false,
+                    "error-codes": ["invalid-input-response"]
+                })))
+                .mount(&server)
+                .await;
+
+            let verifier = SiteVerify::with_verify_url(&server.uri(), "s3cret".to_string());
+            let ip = Some("203.0.113.7".parse().unwrap());
+            assert!(verifier.verify("good", ip).await.unwrap());
+            assert!(!verifier.verify("bad", ip).await.unwrap());
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Silent carrier verification (GSMA Number Verify / CAMARA style).
+//!
+//! Instead of sending a code, `/verify/start` returns the carrier's
+//! authorization URL. The phone opens it over mobile data, the carrier
+//! recognizes the line from its network and redirects to `/verify/callback`
+//! with an authorization code. The node exchanges that code for an access
+//! token and asks the carrier whether the device's number is the one being
+//! verified.
+
+use crate::verification::{Challenge, ContactVerifier};
+use async_trait::async_trait;
+use hesha_types::PhoneNumber;
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+/// OAuth scope for number verification.
+const NUMBER_VERIFY_SCOPE: &str = "number-verification:verify";
+
+/// Carrier endpoints and client registration.
+pub struct CarrierEndpoints {
+    /// OAuth authorization endpoint.
+    pub authorize_url: String,
+    /// OAuth token endpoint.
+    pub token_url: String,
+    /// Number verification API endpoint.
+    pub verify_url: String,
+    /// This node's `/verify/callback` URL.
+    pub redirect_url: String,
+}
+
+/// Verifier backed by a carrier's number verification API.
+pub struct CarrierVerifier {
+    client: reqwest::Client,
+    endpoints: CarrierEndpoints,
+    client_id: String,
+    client_secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifyResponse {
+    device_phone_number_verified: bool,
+}
+
+impl CarrierVerifier {
+    /// Create a verifier for one carrier.
+    pub fn new(endpoints: CarrierEndpoints, client_id: String, client_secret: String) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            endpoints,
+            client_id,
+            client_secret,
+        }
+    }
+}
+
+#[async_trait]
+impl ContactVerifier for CarrierVerifier {
+    async fn start(&self, phone_number: &PhoneNumber, session_id: &str) -> anyhow::Result<Challenge> {
+        let url = Url::parse_with_params(&self.endpoints.authorize_url, &[
+            ("response_type", "code"),
+            ("client_id", &self.client_id),
+            ("redirect_uri", &self.endpoints.redirect_url),
+            ("scope", NUMBER_VERIFY_SCOPE),
+            ("state", session_id),
+            ("login_hint", &format!("tel:{}", phone_number)),
+        ])?;
+        Ok(Challenge::Redirect(url.into()))
+    }
+
+    async fn check_code(&self, phone_number: &PhoneNumber, code: &str) -> anyhow::Result<bool> {
+        if code.is_empty() {
+            return Ok(false);
+        }
+
+        let response = self.client
+            .post(&self.endpoints.token_url)
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &self.endpoints.redirect_url),
+            ])
+            .send()
+            .await?;
+        // An unknown, reused or expired authorization code
+        if response.status() == StatusCode::BAD_REQUEST {
+            return Ok(false);
+        }
+        if !response.status().is_success() {
+            anyhow::bail!("Carrier returned {} exchanging authorization code", response.status());
+        }
+        let token: TokenResponse = response.json().await?;
+
+        let response = self.client
+            .post(&self.endpoints.verify_url)
+            .bearer_auth(token.access_token)
+            .json(&json!({ "phoneNumber": phone_number.to_string() }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("Carrier returned {} verifying number", response.status());
+        }
+        let verified: VerifyResponse = response.json().await?;
+        Ok(verified.device_phone_number_verified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn verifier(base_url: &str) -> CarrierVerifier {
+        CarrierVerifier::new(
+            CarrierEndpoints {
+                authorize_url: format!("{}/authorize", base_url),
+                token_url: format!("{}/token", base_url),
+                verify_url: format!("{}/verify", base_url),
+                redirect_url: "https://issuer.example.com/verify/callback".to_string(),
+            },
+            "hesha".to_string(),
+            "secret".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_redirect_url() {
+        let phone = PhoneNumber::new("+1234567890").unwrap();
+        let Challenge::Redirect(url) = verifier("https://carrier.example").start(&phone, "s-1").await.unwrap() else {
+            panic!("expected redirect");
+        };
+        let url = Url::parse(&url).unwrap();
+        assert_eq!(url.path(), "/authorize");
+        let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["state"], "s-1");
+        assert_eq!(params["login_hint"], "tel:+1234567890");
+        assert_eq!(params["redirect_uri"], "https://issuer.example.com/verify/callback");
+    }
+
+    #[tokio::test]
+    async fn test_token_exchange() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains("code=good"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "at-1",
+                "token_type": "Bearer"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "error": "invalid_grant"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/verify"))
+            .and(header("authorization", "Bearer at-1"))
+            .and(body_json(json!({ "phoneNumber": "+1234567890" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "devicePhoneNumberVerified": true
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let verifier = verifier(&server.uri());
+        let phone = PhoneNumber::new("+1234567890").unwrap();
+        assert!(!verifier.check_code(&phone, "").await.unwrap());
+        assert!(!verifier.check_code(&phone, "bad").await.unwrap());
+        assert!(verifier.check_code(&phone, "good").await.unwrap());
+    }
+}
@@ -0,0 +1,348 @@
+//! Local admin console over a Unix domain socket.
+//!
+//! With `control_socket` set (by default `issuer.sock` in the issuer
+//! directory), the node accepts [`ControlRequest`]s from `hesha admin`: one
+//! JSON line in, one JSON line out per connection. The socket is created
+//! readable and writable by its owner only; unlike the `/admin` routes it is
+//! never reachable over the network.
+
+use crate::config::Config;
+use crate::keys::Keyring;
+use crate::state::AppState;
+use hesha_types::{AuditEvent, ControlRequest, ControlResponse, KeysStatus, NodeStatus};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Peer recorded in the audit log for console commands.
+const CONSOLE_PEER: &str = "control-socket";
+
+/// Whether the node is draining, and the signal to shut down once done.
+#[derive(Default)]
+pub struct Drain {
+    draining: AtomicBool,
+    shutdown: Notify,
+}
+
+impl Drain {
+    /// Whether `/health` should report the node as going away.
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Start draining: report unhealthy now, and shut down after `grace`.
+    pub fn start(self: &Arc<Self>, grace: Duration) {
+        if self.draining.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let drain = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(grace).await;
+            drain.shutdown.notify_one();
+        });
+    }
+
+    /// Resolve once a drain's grace period has passed.
+    pub async fn requested(&self) {
+        self.shutdown.notified().await;
+    }
+}
+
+/// Carry out a console command.
+pub async fn execute(state: &AppState, request: ControlRequest) -> ControlResponse {
+    match request {
+        ControlRequest::Status => ControlResponse::Status(status(state)),
+        ControlRequest::Reload => keys_response(reload_keys(state, "reload").await, "reload_failed"),
+        ControlRequest::RotateKey { overlap_days } => {
+            keys_response(rotate_key(state, overlap_days).await, "rotation_failed")
+        }
+        ControlRequest::ActivateKey { key_id } => {
+            keys_response(activate_key(state, &key_id).await, "activation_failed")
+        }
+        ControlRequest::Drain { grace_secs } => {
+            tracing::info!("Draining on request; shutting down in {}s", grace_secs);
+            state.audit.record(AuditEvent::AdminAction {
+                action: "drain".to_string(),
+                peer: CONSOLE_PEER.to_string(),
+            }).await;
+            state.drain.start(Duration::from_secs(grace_secs));
+            ControlResponse::Draining { grace_secs }
+        }
+        ControlRequest::Maintenance { enabled, reason, retry_after_secs } => {
+            match state.maintenance.set(enabled, reason, retry_after_secs).await {
+                Ok(status) => {
+                    if enabled {
+                        tracing::warn!("Maintenance mode turned on; refusing attestation requests");
+                    } else {
+                        tracing::info!("Maintenance mode turned off");
+                    }
+                    state.audit.record(AuditEvent::AdminAction {
+                        action: format!("maintenance:{}", if enabled { "on" } else { "off" }),
+                        peer: CONSOLE_PEER.to_string(),
+                    }).await;
+                    ControlResponse::Maintenance(status)
+                }
+                Err(e) => {
+                    tracing::error!("Console command failed: {}", e);
+                    ControlResponse::Error {
+                        error: "maintenance_failed".to_string(),
+                        error_description: e.to_string(),
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn status(state: &AppState) -> NodeStatus {
+    let metrics = state.metrics.snapshot();
+    NodeStatus {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        pid: std::process::id(),
+        started_at: metrics.started_at,
+        bind_address: state.config.bind_address.to_string(),
+        key_id: state.keys.active().key_id,
+        published_keys: state.keys.published().len(),
+        attestations_issued: metrics.attestations_issued,
+        draining: state.drain.is_draining(),
+        maintenance: state.maintenance.is_enabled(),
+    }
+}
+
+fn keys_response(result: anyhow::Result<KeysStatus>, error: &str) -> ControlResponse {
+    match result {
+        Ok(keys) => ControlResponse::Keys(keys),
+        Err(e) => {
+            tracing::error!("Console command failed: {}", e);
+            ControlResponse::Error {
+                error: error.to_string(),
+                error_description: e.to_string(),
+            }
+        }
+    }
+}
+
+/// Load the keys named in issuer.toml again and sign with its current key.
+async fn reload_keys(state: &AppState, action: &str) -> anyhow::Result<KeysStatus> {
+    let path = config_path(state)?;
+    let file = Config::from_file(path)?;
+    let config = Config {
+        key_id: file.key_id,
+        key_created_at: file.key_created_at,
+        previous_keys: file.previous_keys,
+        signing_key_id: None,
+        ..state.config.clone()
+    };
+    state.keys.replace(Keyring::load(&config)?);
+
+    let active = state.keys.active();
+    tracing::info!("Reloaded keys; signing with key {}", active.key_id);
+    if let Err(e) = state.audit.record_key_change().await {
+        tracing::error!("Failed to append to audit log: {}", e);
+    }
+    state.audit.record(AuditEvent::AdminAction {
+        action: format!("{}:{}", action, active.key_id),
+        peer: CONSOLE_PEER.to_string(),
+    }).await;
+    Ok(KeysStatus {
+        key_id: active.key_id,
+        published_keys: state.keys.published().len(),
+    })
+}
+
+/// Rotate the key in the issuer directory, then reload it.
+async fn rotate_key(state: &AppState, overlap_days: Option<i64>) -> anyhow::Result<KeysStatus> {
+    let issuer_dir = config_path(state)?
+        .parent()
+        .and_then(Path::parent)
+        .ok_or_else(|| anyhow::anyhow!("issuer.toml is not inside an issuer directory"))?;
+    // Old attestations must keep verifying until they expire
+    let overlap_days = overlap_days.unwrap_or(state.config.attestation_validity_days);
+    anyhow::ensure!(overlap_days >= 0, "overlap_days cannot be negative");
+    hesha_core::rotate_issuer_key(issuer_dir, chrono::Duration::days(overlap_days))?;
+    reload_keys(state, "keys.rotate").await
+}
+
+/// Sign with another held key, without touching issuer.toml.
+async fn activate_key(state: &AppState, key_id: &str) -> anyhow::Result<KeysStatus> {
+    let active = state.keys.activate(key_id)?;
+    tracing::info!("Signing with key {}", active.key_id);
+    if let Err(e) = state.audit.record_key_change().await {
+        tracing::error!("Failed to append to audit log: {}", e);
+    }
+    state.audit.record(AuditEvent::AdminAction {
+        action: format!("keys.activate:{}", active.key_id),
+        peer: CONSOLE_PEER.to_string(),
+    }).await;
+    Ok(KeysStatus {
+        key_id: active.key_id,
+        published_keys: state.keys.published().len(),
+    })
+}
+
+fn config_path(state: &AppState) -> anyhow::Result<&Path> {
+    state.config.config_path.as_deref().ok_or_else(|| {
+        anyhow::anyhow!("The node was not started from an issuer.toml; keys can't be reloaded")
+    })
+}
+
+/// Accept console connections on `path` in the background.
+///
+/// A socket file left behind by a previous run is replaced.
+#[cfg(unix)]
+pub fn listen(state: AppState, path: &Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)
+        .map_err(|e| anyhow::anyhow!("Cannot listen on {}: {}", path.display(), e))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = serve_connection(&state, stream).await {
+                            tracing::warn!("Console connection failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => tracing::error!("Failed to accept console connection: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn serve_connection(state: &AppState, stream: tokio::net::UnixStream) -> anyhow::Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+    let (reader, mut writer) = stream.into_split();
+    // Commands are tiny; don't buffer whatever a misbehaving client sends
+    let mut line = String::new();
+    BufReader::new(reader.take(64 * 1024)).read_line(&mut line).await?;
+    let response = match serde_json::from_str::<ControlRequest>(&line) {
+        Ok(request) => execute(state, request).await,
+        Err(e) => ControlResponse::Error {
+            error: "invalid_request".to_string(),
+            error_description: e.to_string(),
+        },
+    };
+    let mut body = serde_json::to_vec(&response)?;
+    body.push(b'\n');
+    writer.write_all(&body).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hesha_core::IssuerSetupBuilder;
+
+    async fn state_for_issuer(dir: &Path) -> AppState {
+        IssuerSetupBuilder::new()
+            .name("Test Issuer")
+            .trust_domain("issuer.example.com")
+            .contact_email("admin@example.com")
+            .build()
+            .unwrap()
+            .save(dir)
+            .unwrap();
+        let config = Config::from_args(crate::config::NodeArgs {
+            config_dir: Some(dir.to_path_buf()),
+            ..Default::default()
+        })
+        .unwrap();
+        let keys = Keyring::load(&config).unwrap();
+        AppState::with_storage(config, keys, hesha_storage::Storage::memory())
+    }
+
+    #[tokio::test]
+    async fn test_rotate_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state_for_issuer(dir.path()).await;
+        let first = state.keys.active().key_id;
+
+        let ControlResponse::Keys(keys) = execute(&state, ControlRequest::RotateKey { overlap_days: Some(7) }).await else {
+            panic!("rotation failed");
+        };
+        assert_ne!(keys.key_id, first);
+        assert_eq!(keys.published_keys, 2);
+        assert_eq!(state.keys.active().key_id, keys.key_id);
+
+        // Reloading the rotated configuration keeps the same keys
+        assert_eq!(execute(&state, ControlRequest::Reload).await, ControlResponse::Keys(keys.clone()));
+
+        let ControlResponse::Status(status) = execute(&state, ControlRequest::Status).await else {
+            panic!("no status");
+        };
+        assert_eq!(status.key_id, keys.key_id);
+        assert!(!status.draining);
+        assert!(!status.maintenance);
+
+        let ControlResponse::Maintenance(maintenance) = execute(&state, ControlRequest::Maintenance {
+            enabled: true,
+            reason: Some("key ceremony".to_string()),
+            retry_after_secs: None,
+        }).await else {
+            panic!("maintenance not switched");
+        };
+        assert_eq!(maintenance.reason.as_deref(), Some("key ceremony"));
+        let ControlResponse::Status(status) = execute(&state, ControlRequest::Status).await else {
+            panic!("no status");
+        };
+        assert!(status.maintenance);
+
+        // Without an issuer.toml there is nothing to reload
+        let state = AppState::new(Config::default(), hesha_crypto::generate_keypair().unwrap());
+        assert!(matches!(
+            execute(&state, ControlRequest::Reload).await,
+            ControlResponse::Error { error, .. } if error == "reload_failed"
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_socket() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let state = state_for_issuer(dir.path()).await;
+        let path = state.config.control_socket.clone().unwrap();
+        listen(state.clone(), &path).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        let send = |request: &str| {
+            let path = path.clone();
+            let request = request.to_string();
+            tokio::task::spawn_blocking(move || {
+                let mut stream = std::os::unix::net::UnixStream::connect(path).unwrap();
+                stream.write_all(request.as_bytes()).unwrap();
+                let mut line = String::new();
+                BufReader::new(stream).read_line(&mut line).unwrap();
+                serde_json::from_str::<ControlResponse>(&line).unwrap()
+            })
+        };
+
+        assert!(matches!(send("{\"command\":\"status\"}\n").await.unwrap(), ControlResponse::Status(_)));
+        assert!(matches!(
+            send("{\"command\":\"explode\"}\n").await.unwrap(),
+            ControlResponse::Error { error, .. } if error == "invalid_request"
+        ));
+
+        assert_eq!(
+            send("{\"command\":\"drain\",\"grace_secs\":0}\n").await.unwrap(),
+            ControlResponse::Draining { grace_secs: 0 }
+        );
+        assert!(state.drain.is_draining());
+        tokio::time::timeout(Duration::from_secs(5), state.drain.requested()).await.unwrap();
+    }
+}
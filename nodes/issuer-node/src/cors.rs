@@ -0,0 +1,100 @@
+//! Cross-origin access for browser-based verification apps.
+//!
+//! Without a `[cors]` section no CORS headers are sent, so browsers refuse
+//! cross-origin calls to the node.
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use hesha_types::CorsConfig;
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// Build the CORS layer for the configured origins, methods and headers.
+pub fn layer(config: &CorsConfig) -> anyhow::Result<CorsLayer> {
+    anyhow::ensure!(!config.allowed_origins.is_empty(), "[cors] allows no origins");
+
+    let origins = if config.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        let origins = config.allowed_origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin.trim_end_matches('/'))
+                    .map_err(|_| anyhow::anyhow!("Invalid CORS origin '{}'", origin))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        AllowOrigin::list(origins)
+    };
+    let methods = config.allowed_methods
+        .iter()
+        .map(|method| {
+            Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                .map_err(|_| anyhow::anyhow!("Invalid CORS method '{}'", method))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let headers = config.allowed_headers
+        .iter()
+        .map(|header| {
+            HeaderName::from_bytes(header.as_bytes())
+                .map_err(|_| anyhow::anyhow!("Invalid CORS header '{}'", header))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .max_age(Duration::from_secs(config.max_age_secs)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use tower::util::ServiceExt;
+
+    fn config(origins: &[&str]) -> CorsConfig {
+        CorsConfig {
+            allowed_origins: origins.iter().map(|origin| origin.to_string()).collect(),
+            allowed_methods: vec!["GET".to_string(), "post".to_string()],
+            allowed_headers: vec!["content-type".to_string()],
+            max_age_secs: 600,
+        }
+    }
+
+    async fn preflight(layer: CorsLayer, origin: &str) -> axum::http::Response<axum::body::Body> {
+        Router::new()
+            .route("/health", get(|| async { "ok" }))
+            .layer(layer)
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("OPTIONS")
+                    .uri("/health")
+                    .header("origin", origin)
+                    .header("access-control-request-method", "POST")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_allowed_origins() {
+        let layer = layer(&config(&["https://app.example.com/"])).unwrap();
+        let response = preflight(layer.clone(), "https://app.example.com").await;
+        assert_eq!(response.headers()["access-control-allow-origin"], "https://app.example.com");
+        assert_eq!(response.headers()["access-control-max-age"], "600");
+
+        let response = preflight(layer, "https://evil.example.com").await;
+        assert!(response.headers().get("access-control-allow-origin").is_none());
+
+        let response = preflight(super::layer(&config(&["*"])).unwrap(), "https://any.example.com").await;
+        assert_eq!(response.headers()["access-control-allow-origin"], "*");
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(layer(&config(&[])).is_err());
+        assert!(layer(&config(&["https://bad\norigin"])).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+//! Email one-time codes for numbers that can't receive SMS.
+//!
+//! Landlines ported to the issuer and numbers on enterprise SIP trunks have
+//! no SMS path, but their operator can vouch for an address that speaks
+//! for the line. The verifier emails a code to the address registered for
+//! the number and checks it itself; users can't choose where it is sent.
+//!
+//! Codes are kept hashed in the shared [`StateStore`] until they expire,
+//! so any replica can check them.
+
+use crate::verification::{Challenge, ContactVerifier, MAX_UPDATE_ATTEMPTS, SESSION_TTL};
+use async_trait::async_trait;
+use chrono::Utc;
+use hesha_crypto::{constant_time_compare, sha256};
+use hesha_storage::{StateStore, StorageError};
+use hesha_types::PhoneNumber;
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Sends verification emails.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    /// Send a plain-text email to `to`.
+    async fn send(&self, to: &str, subject: &str, body: &str) -> anyhow::Result<()>;
+}
+
+/// Mailer relaying through an SMTP server over STARTTLS.
+pub struct SmtpMailer {
+    transport: lettre::AsyncSmtpTransport<lettre::Tokio1Executor>,
+    from: lettre::message::Mailbox,
+}
+
+impl SmtpMailer {
+    /// Relay through `host:port`, logging in when `credentials` are given.
+    pub fn new(host: &str, port: u16, credentials: Option<(String, String)>, from: &str) -> anyhow::Result<Self> {
+        let mut transport = lettre::AsyncSmtpTransport::<lettre::Tokio1Executor>::starttls_relay(host)?.port(port);
+        if let Some((username, password)) = credentials {
+            transport = transport.credentials(lettre::transport::smtp::authentication::Credentials::new(username, password));
+        }
+        Ok(Self {
+            transport: transport.build(),
+            from: from.parse().map_err(|e| anyhow::anyhow!("Invalid sender address {}: {}", from, e))?,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> anyhow::Result<()> {
+        use lettre::AsyncTransport;
+        let message = lettre::Message::builder()
+            .from(self.from.clone())
+            .to(to.parse()?)
+            .subject(subject)
+            .body(body.to_string())?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+/// Verifier emailing codes to the addresses registered for numbers.
+pub struct EmailVerifier {
+    mailer: Arc<dyn Mailer>,
+    contacts: BTreeMap<String, String>,
+    state: Arc<dyn StateStore>,
+}
+
+impl EmailVerifier {
+    /// Email codes through `mailer` to `contacts`, keyed by E.164 number,
+    /// keeping them in `state`.
+    pub fn new(mailer: Arc<dyn Mailer>, contacts: BTreeMap<String, String>, state: Arc<dyn StateStore>) -> Self {
+        Self { mailer, contacts, state }
+    }
+
+    fn code_key(phone_number: &PhoneNumber) -> String {
+        format!("email-otp:{}", hex::encode(sha256(phone_number.to_string().as_bytes())))
+    }
+
+    /// Keep the hash of the latest code sent to `phone_number`, replacing
+    /// any earlier one.
+    async fn store_code(&self, phone_number: &PhoneNumber, code: &str) -> Result<(), StorageError> {
+        let key = Self::code_key(phone_number);
+        let value = hex::encode(sha256(code.as_bytes()));
+        let expires_at = Utc::now() + chrono::Duration::from_std(SESSION_TTL).unwrap_or_default();
+        for _ in 0..MAX_UPDATE_ATTEMPTS {
+            let current = self.state.get(&key).await?;
+            if self.state.compare_and_swap(&key, current.as_deref(), &value, expires_at).await? {
+                return Ok(());
+            }
+        }
+        Err(StorageError::Conflict(key))
+    }
+}
+
+#[async_trait]
+impl ContactVerifier for EmailVerifier {
+    async fn start(&self, phone_number: &PhoneNumber, _session_id: &str) -> anyhow::Result<Challenge> {
+        let Some(address) = self.contacts.get(&phone_number.to_string()) else {
+            anyhow::bail!("No email contact registered for this number");
+        };
+        let code = format!("{:06}", rand::thread_rng().gen_range(0..1_000_000));
+        self.store_code(phone_number, &code).await?;
+        let body = format!(
+            "Your verification code for {} is {}.\n\nIt expires in {} minutes. If you didn't ask for it, ignore this email.\n",
+            phone_number,
+            code,
+            SESSION_TTL.as_secs() / 60,
+        );
+        self.mailer.send(address, "Your verification code", &body).await?;
+        Ok(Challenge::EmailCode)
+    }
+
+    async fn check_code(&self, phone_number: &PhoneNumber, code: &str) -> anyhow::Result<bool> {
+        let key = Self::code_key(phone_number);
+        let Some(expected) = self.state.get(&key).await? else {
+            return Ok(false);
+        };
+        let entered = hex::encode(sha256(code.trim().as_bytes()));
+        if !constant_time_compare(entered.as_bytes(), expected.as_bytes()) {
+            return Ok(false);
+        }
+        // Single use; a concurrent check may have taken it first
+        Ok(self.state.compare_and_remove(&key, &expected).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Outbox(Mutex<Vec<(String, String)>>);
+
+    #[async_trait]
+    impl Mailer for Outbox {
+        async fn send(&self, to: &str, _subject: &str, body: &str) -> anyhow::Result<()> {
+            self.0.lock().unwrap().push((to.to_string(), body.to_string()));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_and_check() {
+        let outbox = Arc::new(Outbox::default());
+        let phone = PhoneNumber::new("+14155550100").unwrap();
+        let verifier = EmailVerifier::new(
+            outbox.clone(),
+            BTreeMap::from([(phone.to_string(), "noc@acme.example".to_string())]),
+            Arc::new(hesha_storage::MemoryStore::new()),
+        );
+
+        // Unregistered numbers are refused, so failover moves on
+        assert!(verifier.start(&PhoneNumber::new("+14155552671").unwrap(), "s-1").await.is_err());
+
+        assert_eq!(verifier.start(&phone, "s-2").await.unwrap(), Challenge::EmailCode);
+        let (to, body) = outbox.0.lock().unwrap().pop().unwrap();
+        assert_eq!(to, "noc@acme.example");
+        let code = body.split(" is ").nth(1).unwrap()[..6].to_string();
+
+        let wrong = if code == "000000" { "111111" } else { "000000" };
+        assert!(!verifier.check_code(&phone, wrong).await.unwrap());
+        assert!(verifier.check_code(&phone, &code).await.unwrap());
+        // Used up
+        assert!(!verifier.check_code(&phone, &code).await.unwrap());
+    }
+}
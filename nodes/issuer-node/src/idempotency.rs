@@ -0,0 +1,240 @@
+//! Replay of attestation responses for retried requests.
+//!
+//! Clients on flaky connections may resend an attestation request whose
+//! response was lost. When the request carries an `Idempotency-Key` header,
+//! the first response is remembered and returned again for the same key, so
+//! a retry doesn't issue a second attestation.
+//!
+//! Keys are scoped to the authenticated caller, so two API keys can't see
+//! or collide with each other's responses, and bound to a SHA-256 hash of
+//! the request body. A key is reserved while its request is being handled:
+//! a retry racing the original gets a conflict instead of a duplicate.
+//! Records live in the shared [`StateStore`], so a retry landing on another
+//! replica is replayed too.
+
+use crate::api::attest::AttestationResponse;
+use chrono::Utc;
+use hesha_crypto::generate_hex_nonce;
+use hesha_storage::StateStore;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Header carrying the client's idempotency key.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Longest accepted idempotency key.
+pub const MAX_KEY_LENGTH: usize = 255;
+
+/// Default time responses are kept for replay, in seconds.
+pub const DEFAULT_WINDOW_SECS: u64 = 24 * 3600;
+
+/// How long a key stays reserved if the replica handling its request dies.
+const RESERVATION_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// A reserved or remembered key.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    /// Hex SHA-256 of the request that reserved the key.
+    request_hash: String,
+    /// Tells reservations of the same request apart.
+    reservation: String,
+    /// None while the original request is still being handled.
+    response: Option<AttestationResponse>,
+}
+
+/// Why a request can't proceed under its idempotency key.
+#[derive(Debug, PartialEq, Eq)]
+pub enum IdempotencyError {
+    /// The key was already used for a different request.
+    KeyReused,
+    /// The first request with the key hasn't finished yet.
+    InProgress,
+    /// The shared state could not be read or written.
+    Unavailable,
+}
+
+/// Outcome of presenting an idempotency key.
+pub enum Claim {
+    /// The request was already answered; send this again.
+    Replay(Box<AttestationResponse>),
+    /// The key is reserved for this request.
+    Reserved(Reservation),
+}
+
+/// A key reserved for a request being handled.
+///
+/// Dropping it without [`complete`](Self::complete), as happens when
+/// issuance fails, releases the key so the client can retry.
+pub struct Reservation {
+    state: Arc<dyn StateStore>,
+    key: String,
+    /// The stored reservation, until completed.
+    reserved: Option<String>,
+    ttl: Duration,
+}
+
+impl Reservation {
+    /// Remember the response for replay.
+    pub async fn complete(mut self, response: AttestationResponse) {
+        let Some(reserved) = self.reserved.take() else {
+            return;
+        };
+        let Ok(entry) = serde_json::from_str::<Entry>(&reserved) else {
+            return;
+        };
+        let entry = Entry {
+            response: Some(response),
+            ..entry
+        };
+        let value = serde_json::to_string(&entry).unwrap_or_default();
+        let expires_at = Utc::now() + chrono::Duration::from_std(self.ttl).unwrap_or_default();
+        if let Err(e) = self.state.compare_and_swap(&self.key, Some(&reserved), &value, expires_at).await {
+            tracing::error!("Failed to remember response for idempotency key: {}", e);
+        }
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let Some(reserved) = self.reserved.take() else {
+            return;
+        };
+        let state = self.state.clone();
+        let key = std::mem::take(&mut self.key);
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                if let Err(e) = state.compare_and_remove(&key, &reserved).await {
+                    tracing::error!("Failed to release idempotency key: {}", e);
+                }
+            });
+        }
+    }
+}
+
+/// Responses by caller and idempotency key, kept in the shared state.
+pub struct IdempotencyCache {
+    state: Arc<dyn StateStore>,
+    ttl: Duration,
+}
+
+impl IdempotencyCache {
+    /// Create a cache keeping responses for `ttl`.
+    pub fn new(state: Arc<dyn StateStore>, ttl: Duration) -> Self {
+        Self { state, ttl }
+    }
+
+    /// Present `key` for a request from `caller` (empty when anonymous).
+    ///
+    /// `request` is the request body; reusing a key for a different
+    /// request is an error rather than a replay.
+    pub async fn claim(&self, caller: &str, key: &str, request: &[u8]) -> Result<Claim, IdempotencyError> {
+        let request_hash = hex::encode(hesha_crypto::sha256(request));
+        // Callers and keys are client-chosen, so they are hashed into the key
+        let scoped = format!("{}\n{}", caller, key);
+        let state_key = format!("idempotency:{}", hex::encode(hesha_crypto::sha256(scoped.as_bytes())));
+        let unavailable = |e: hesha_storage::StorageError| {
+            tracing::error!("Failed to look up idempotency key: {}", e);
+            IdempotencyError::Unavailable
+        };
+
+        if let Some(stored) = self.state.get(&state_key).await.map_err(unavailable)? {
+            let entry: Entry = serde_json::from_str(&stored).map_err(|_| IdempotencyError::Unavailable)?;
+            if entry.request_hash != request_hash {
+                return Err(IdempotencyError::KeyReused);
+            }
+            return match entry.response {
+                Some(response) => Ok(Claim::Replay(Box::new(response))),
+                None => Err(IdempotencyError::InProgress),
+            };
+        }
+
+        let entry = Entry {
+            request_hash,
+            reservation: generate_hex_nonce(),
+            response: None,
+        };
+        let reserved = serde_json::to_string(&entry).unwrap_or_default();
+        let expires_at = Utc::now() + chrono::Duration::from_std(RESERVATION_TTL).unwrap_or_default();
+        if !self.state.compare_and_swap(&state_key, None, &reserved, expires_at).await.map_err(unavailable)? {
+            // Another request took the key since it was looked up
+            return Err(IdempotencyError::InProgress);
+        }
+        Ok(Claim::Reserved(Reservation {
+            state: self.state.clone(),
+            key: state_key,
+            reserved: Some(reserved),
+            ttl: self.ttl,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hesha_types::{IssuanceReceipt, ReceiptOutcome};
+
+    fn response(proxy_number: &str) -> AttestationResponse {
+        AttestationResponse {
+            proxy_number: proxy_number.to_string(),
+            attestation: "jwt".to_string(),
+            expires_at: 0,
+            receipt: IssuanceReceipt {
+                issuer: "issuer.example.com".to_string(),
+                outcome: ReceiptOutcome::Issued,
+                jti: Some("jti".to_string()),
+                proxy_number: Some(proxy_number.to_string()),
+                error: None,
+                user_pubkey: "key".to_string(),
+                scope: "1".to_string(),
+                issued_at: chrono::Utc::now(),
+                key_id: "default".to_string(),
+                signature: None,
+            },
+        }
+    }
+
+    fn cache(ttl: Duration) -> IdempotencyCache {
+        IdempotencyCache::new(Arc::new(hesha_storage::MemoryStore::new()), ttl)
+    }
+
+    async fn reserve(cache: &IdempotencyCache, caller: &str, request: &[u8]) -> Reservation {
+        match cache.claim(caller, "k", request).await {
+            Ok(Claim::Reserved(reservation)) => reservation,
+            _ => panic!("key was not reserved"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_and_reuse() {
+        let cache = cache(Duration::from_secs(DEFAULT_WINDOW_SECS));
+        let reservation = reserve(&cache, "key:a", b"req").await;
+        assert_eq!(cache.claim("key:a", "k", b"req").await.err(), Some(IdempotencyError::InProgress));
+        reservation.complete(response("+100123")).await;
+
+        match cache.claim("key:a", "k", b"req").await {
+            Ok(Claim::Replay(replayed)) => assert_eq!(replayed.proxy_number, "+100123"),
+            _ => panic!("response was not replayed"),
+        }
+        assert_eq!(cache.claim("key:a", "k", b"other").await.err(), Some(IdempotencyError::KeyReused));
+
+        // Another caller's key is separate
+        reserve(&cache, "key:b", b"other").await;
+    }
+
+    #[tokio::test]
+    async fn test_failed_request_releases_key() {
+        let cache = cache(Duration::from_secs(DEFAULT_WINDOW_SECS));
+        drop(reserve(&cache, "", b"req").await);
+        // The key is released in the background
+        tokio::task::yield_now().await;
+        reserve(&cache, "", b"req").await;
+    }
+
+    #[tokio::test]
+    async fn test_expiry() {
+        let cache = cache(Duration::ZERO);
+        reserve(&cache, "", b"req").await.complete(response("+100123")).await;
+        reserve(&cache, "", b"req").await;
+    }
+}
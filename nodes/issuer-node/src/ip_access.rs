@@ -0,0 +1,247 @@
+//! Network allow and deny lists per group of routes.
+//!
+//! With an `[ip_access]` section, requests to the attest API, the admin
+//! endpoints or the `.well-known` documents are checked against the lists
+//! for their group before anything else is done with them. Deny entries
+//! win over allow entries, and an empty allow list lets every address
+//! through that isn't denied. `/health` is never filtered.
+//!
+//! The address checked is the connection's peer. Behind a reverse proxy
+//! that is the proxy, so restrict the attest API at the proxy instead.
+
+use crate::problem::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use hesha_types::{ErrorCode, IpAccessConfig, IpRules};
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+
+/// Routes sharing one set of lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteGroup {
+    /// `/attest` and `/verify` endpoints.
+    Attest,
+    /// `/admin` endpoints.
+    Admin,
+    /// `/.well-known` documents.
+    WellKnown,
+}
+
+impl RouteGroup {
+    /// The group a request path belongs to, if any.
+    pub fn of(path: &str) -> Option<Self> {
+        let under = |prefix: &str| {
+            path.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        };
+        if under("/attest") || under("/verify") {
+            Some(RouteGroup::Attest)
+        } else if under("/admin") {
+            Some(RouteGroup::Admin)
+        } else if under("/.well-known") {
+            Some(RouteGroup::WellKnown)
+        } else {
+            None
+        }
+    }
+}
+
+/// Parsed allow and deny lists of one group.
+#[derive(Debug, Default)]
+struct Rules {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+impl Rules {
+    fn from_config(rules: &IpRules, group: &str) -> anyhow::Result<Self> {
+        let parse = |entries: &[String]| {
+            entries.iter().map(|entry| parse_network(entry, group)).collect::<anyhow::Result<Vec<_>>>()
+        };
+        Ok(Self {
+            allow: parse(&rules.allow)?,
+            deny: parse(&rules.deny)?,
+        })
+    }
+
+    /// Whether `ip` may make requests. An unknown address only passes when
+    /// nothing is allowed explicitly.
+    fn permits(&self, ip: Option<IpAddr>) -> bool {
+        let Some(ip) = ip.map(canonical) else {
+            return self.allow.is_empty();
+        };
+        if self.deny.iter().any(|net| net.contains(&ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|net| net.contains(&ip))
+    }
+}
+
+/// A CIDR block, or a single address as a block of one.
+fn parse_network(entry: &str, group: &str) -> anyhow::Result<IpNet> {
+    let entry = entry.trim();
+    entry
+        .parse::<IpNet>()
+        .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| anyhow::anyhow!("Invalid network '{}' in [ip_access.{}]", entry, group))
+}
+
+/// IPv4 addresses arriving on a dual-stack socket as IPv6-mapped ones.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+/// Allow and deny lists for every route group.
+#[derive(Debug, Default)]
+pub struct IpAccess {
+    attest: Rules,
+    admin: Rules,
+    well_known: Rules,
+}
+
+impl IpAccess {
+    /// Lists from issuer.toml, refusing entries that aren't networks.
+    pub fn from_config(config: &IpAccessConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            attest: Rules::from_config(&config.attest, "attest")?,
+            admin: Rules::from_config(&config.admin, "admin")?,
+            well_known: Rules::from_config(&config.well_known, "well_known")?,
+        })
+    }
+
+    /// Whether `ip` may call routes in `group`.
+    pub fn permits(&self, group: RouteGroup, ip: Option<IpAddr>) -> bool {
+        match group {
+            RouteGroup::Attest => &self.attest,
+            RouteGroup::Admin => &self.admin,
+            RouteGroup::WellKnown => &self.well_known,
+        }
+        .permits(ip)
+    }
+}
+
+/// Middleware refusing requests from networks their route group doesn't
+/// accept.
+pub async fn restrict_access(
+    State(state): State<AppState>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (Some(access), Some(group)) = (&state.ip_access, RouteGroup::of(request.uri().path())) else {
+        return next.run(request).await;
+    };
+    let ip = peer.map(|ConnectInfo(peer)| peer.ip());
+    if access.permits(group, ip) {
+        return next.run(request).await;
+    }
+    tracing::debug!(
+        "Refused {} {} from {}",
+        request.method(),
+        request.uri().path(),
+        ip.map_or_else(|| "an unknown address".to_string(), |ip| ip.to_string())
+    );
+    state.metrics.record_error("ip_not_allowed");
+    ApiError::new(ErrorCode::IpNotAllowed, "Requests from this network are not accepted").into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use axum::{body::Body, extract::connect_info::MockConnectInfo, http::StatusCode, middleware, routing::get, Router};
+    use hesha_crypto::generate_keypair;
+    use tower::util::ServiceExt;
+
+    fn rules(allow: &[&str], deny: &[&str]) -> IpRules {
+        IpRules {
+            allow: allow.iter().map(|entry| entry.to_string()).collect(),
+            deny: deny.iter().map(|entry| entry.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_route_groups() {
+        assert_eq!(RouteGroup::of("/attest"), Some(RouteGroup::Attest));
+        assert_eq!(RouteGroup::of("/attest/simple"), Some(RouteGroup::Attest));
+        assert_eq!(RouteGroup::of("/verify/start"), Some(RouteGroup::Attest));
+        assert_eq!(RouteGroup::of("/admin/metrics"), Some(RouteGroup::Admin));
+        assert_eq!(RouteGroup::of("/.well-known/hesha/pubkey.json"), Some(RouteGroup::WellKnown));
+        assert_eq!(RouteGroup::of("/health"), None);
+        assert_eq!(RouteGroup::of("/attestations"), None);
+    }
+
+    #[test]
+    fn test_rules() {
+        let access = IpAccess::from_config(&IpAccessConfig {
+            attest: rules(&["10.20.0.0/16", "2001:db8::/32", "192.0.2.7"], &["10.20.99.0/24"]),
+            admin: IpRules::default(),
+            well_known: rules(&[], &["198.51.100.0/24"]),
+        })
+        .unwrap();
+        let ip = |ip: &str| Some(ip.parse().unwrap());
+
+        assert!(access.permits(RouteGroup::Attest, ip("10.20.1.2")));
+        assert!(access.permits(RouteGroup::Attest, ip("192.0.2.7")));
+        assert!(access.permits(RouteGroup::Attest, ip("2001:db8::1")));
+        assert!(access.permits(RouteGroup::Attest, ip("::ffff:10.20.1.2")));
+        assert!(!access.permits(RouteGroup::Attest, ip("10.20.99.1")));
+        assert!(!access.permits(RouteGroup::Attest, ip("192.0.2.8")));
+        assert!(!access.permits(RouteGroup::Attest, None));
+
+        assert!(access.permits(RouteGroup::Admin, ip("203.0.113.1")));
+        assert!(access.permits(RouteGroup::WellKnown, ip("203.0.113.1")));
+        assert!(access.permits(RouteGroup::WellKnown, None));
+        assert!(!access.permits(RouteGroup::WellKnown, ip("198.51.100.20")));
+    }
+
+    #[test]
+    fn test_invalid_networks() {
+        let config = IpAccessConfig {
+            admin: rules(&["10.0.0.0/33"], &[]),
+            ..IpAccessConfig::default()
+        };
+        let error = IpAccess::from_config(&config).unwrap_err().to_string();
+        assert!(error.contains("[ip_access.admin]"));
+        let config = IpAccessConfig {
+            attest: rules(&[], &["intranet"]),
+            ..IpAccessConfig::default()
+        };
+        assert!(IpAccess::from_config(&config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_refuses_before_handlers() {
+        let access = IpAccess::from_config(&IpAccessConfig {
+            attest: rules(&["10.0.0.0/8"], &[]),
+            ..IpAccessConfig::default()
+        })
+        .unwrap();
+        let state = AppState::new(Config::default(), generate_keypair().unwrap()).with_ip_access(access);
+        let app = |peer: SocketAddr| {
+            Router::new()
+                .route("/attest", get(|| async { "ok" }))
+                .route("/health", get(|| async { "ok" }))
+                .layer(middleware::from_fn_with_state(state.clone(), restrict_access))
+                .layer(MockConnectInfo(peer))
+                .with_state(state.clone())
+        };
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let response = app(([10, 1, 2, 3], 4000).into()).oneshot(get("/attest")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let outside = app(([203, 0, 113, 1], 4000).into());
+        let response = outside.clone().oneshot(get("/attest")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(state.metrics.snapshot().errors["ip_not_allowed"], 1);
+        let response = outside.oneshot(get("/health")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
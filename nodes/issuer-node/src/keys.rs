@@ -0,0 +1,279 @@
+//! Issuer signing keys.
+//!
+//! After `hesha setup rotate` the node holds the current key and every
+//! previous key still inside its publication window. One key signs new
+//! attestations, named by its `kid` header; all of them are published at
+//! `/.well-known/hesha/pubkey.json`, so attestations signed before a
+//! rotation keep verifying. Previous keys can sign too when their archived
+//! private key (`keys/private-<key_id>.key`) is still on disk, which lets an
+//! operator switch back at runtime with `hesha admin activate-key`.
+//!
+//! Each private key is expanded into a [`CachedSigner`] once, when it is
+//! loaded, rather than for every signature.
+
+use crate::config::Config;
+use chrono::{DateTime, Utc};
+use hesha_crypto::{generate_keypair, keypair_from_private, CachedSigner};
+use hesha_types::{IssuerKey, KeyPair, PrivateKey, PublicKey};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+/// The key new attestations are signed with.
+#[derive(Clone)]
+pub struct ActiveKey {
+    /// Identifier put in the JWT `kid` header.
+    pub key_id: String,
+    /// The key pair.
+    pub keypair: Arc<KeyPair>,
+    /// The private key, expanded for signing.
+    pub signer: Arc<CachedSigner>,
+}
+
+/// A key held by the node.
+struct HeldKey {
+    key_id: String,
+    public: PublicKey,
+    // None when the archived private key has been moved offline
+    keypair: Option<(Arc<KeyPair>, Arc<CachedSigner>)>,
+    created_at: DateTime<Utc>,
+    // Set for previous keys
+    valid_until: Option<DateTime<Utc>>,
+}
+
+/// The node's current and previous issuer keys.
+pub struct Keyring {
+    keys: RwLock<Vec<HeldKey>>,
+    active: RwLock<ActiveKey>,
+    // Last time the active key changed
+    activated_at: RwLock<DateTime<Utc>>,
+}
+
+impl Keyring {
+    /// Hold a single key.
+    pub fn new(key_id: String, keypair: KeyPair) -> Self {
+        Self::with_current(key_id, keypair, Utc::now())
+    }
+
+    fn with_current(key_id: String, keypair: KeyPair, created_at: DateTime<Utc>) -> Self {
+        let signer = Arc::new(CachedSigner::from(&keypair));
+        let keypair = Arc::new(keypair);
+        Self {
+            keys: RwLock::new(vec![HeldKey {
+                key_id: key_id.clone(),
+                public: keypair.public.clone(),
+                keypair: Some((keypair.clone(), signer.clone())),
+                created_at,
+                valid_until: None,
+            }]),
+            active: RwLock::new(ActiveKey { key_id, keypair, signer }),
+            activated_at: RwLock::new(created_at),
+        }
+    }
+
+    /// Load the keys named in the configuration.
+    ///
+    /// Without a private key file a fresh key is generated. Previous keys
+    /// whose window has passed are skipped; the others are loaded from
+    /// their archived private key next to the current one if it exists, and
+    /// only published otherwise. `signing_key_id` picks the active key.
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        let Some(key_path) = &config.private_key_path else {
+            return Ok(Self::new(config.key_id.clone(), generate_keypair()?));
+        };
+        let key_path = Path::new(key_path);
+        let created_at = match &config.key_created_at {
+            Some(created_at) => parse_time(created_at)?,
+            None => Utc::now(),
+        };
+        let mut keyring = Self::with_current(config.key_id.clone(), read_keypair(key_path)?, created_at);
+
+        let now = Utc::now();
+        for previous in &config.previous_keys {
+            let valid_until = parse_time(&previous.valid_until)?;
+            if valid_until <= now {
+                continue;
+            }
+            let public = PublicKey::from_base64(&previous.public_key_base64url)?;
+            let archived = key_path.with_file_name(format!("private-{}.key", previous.key_id));
+            let keypair = if archived.is_file() {
+                let keypair = read_keypair(&archived)?;
+                anyhow::ensure!(
+                    keypair.public == public,
+                    "{} does not hold the private key of {}",
+                    archived.display(),
+                    previous.key_id
+                );
+                let signer = Arc::new(CachedSigner::from(&keypair));
+                Some((Arc::new(keypair), signer))
+            } else {
+                None
+            };
+            keyring.keys.get_mut().unwrap_or_else(|e| e.into_inner()).push(HeldKey {
+                key_id: previous.key_id.clone(),
+                public,
+                keypair,
+                created_at: parse_time(&previous.created_at)?,
+                valid_until: Some(valid_until),
+            });
+        }
+
+        if let Some(key_id) = &config.signing_key_id {
+            keyring.activate(key_id)?;
+        }
+        Ok(keyring)
+    }
+
+    /// Take over the keys of a freshly loaded keyring, e.g. after the
+    /// configuration was changed by a rotation.
+    pub fn replace(&self, other: Keyring) {
+        let Keyring { keys, active, .. } = other;
+        *self.keys.write().unwrap_or_else(|e| e.into_inner()) = keys.into_inner().unwrap_or_else(|e| e.into_inner());
+        *self.active.write().unwrap_or_else(|e| e.into_inner()) = active.into_inner().unwrap_or_else(|e| e.into_inner());
+        *self.activated_at.write().unwrap_or_else(|e| e.into_inner()) = Utc::now();
+    }
+
+    /// The key new attestations are signed with.
+    pub fn active(&self) -> ActiveKey {
+        self.active.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Sign with another held key from now on.
+    pub fn activate(&self, key_id: &str) -> anyhow::Result<ActiveKey> {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        let key = keys
+            .iter()
+            .find(|key| key.key_id == key_id)
+            .ok_or_else(|| anyhow::anyhow!("No key {} is held by this node", key_id))?;
+        let (keypair, signer) = key.keypair.clone().ok_or_else(|| {
+            anyhow::anyhow!("The private key of {} is not available (keys/private-{}.key)", key_id, key_id)
+        })?;
+        let active = ActiveKey {
+            key_id: key.key_id.clone(),
+            keypair,
+            signer,
+        };
+        *self.active.write().unwrap_or_else(|e| e.into_inner()) = active.clone();
+        *self.activated_at.write().unwrap_or_else(|e| e.into_inner()) = Utc::now();
+        Ok(active)
+    }
+
+    /// When the active key was created.
+    pub fn active_created_at(&self) -> DateTime<Utc> {
+        let active = self.active();
+        self.keys
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|key| key.key_id == active.key_id)
+            .map(|key| key.created_at)
+            .unwrap_or_else(Utc::now)
+    }
+
+    /// When the published keys last changed: a key was created or
+    /// activated, or a previous key's window passed.
+    pub fn published_at(&self) -> DateTime<Utc> {
+        let now = Utc::now();
+        let activated_at = *self.activated_at.read().unwrap_or_else(|e| e.into_inner());
+        self.keys
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .flat_map(|key| [Some(key.created_at), key.valid_until.filter(|until| *until <= now)])
+            .flatten()
+            .fold(activated_at, DateTime::max)
+    }
+
+    /// Keys to publish: the active key first, then every other key whose
+    /// window hasn't passed.
+    pub fn published(&self) -> Vec<IssuerKey> {
+        let active = self.active();
+        let now = Utc::now();
+        let held = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        let mut keys: Vec<&HeldKey> = held
+            .iter()
+            .filter(|key| key.key_id == active.key_id || !matches!(key.valid_until, Some(until) if until <= now))
+            .collect();
+        keys.sort_by_key(|key| key.key_id != active.key_id);
+        keys.into_iter()
+            .map(|key| IssuerKey {
+                key_id: key.key_id.clone(),
+                public_key: key.public.clone(),
+                algorithm: "Ed25519".to_string(),
+                valid_from: key.created_at,
+                valid_until: key.valid_until,
+            })
+            .collect()
+    }
+}
+
+fn read_keypair(path: &Path) -> anyhow::Result<KeyPair> {
+    let key_data = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read key {}: {}", path.display(), e))?;
+    let private_key = PrivateKey::from_base64(key_data.trim())?;
+    Ok(keypair_from_private(&private_key)?)
+}
+
+fn parse_time(value: &str) -> anyhow::Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(value)
+        .map_err(|e| anyhow::anyhow!("Invalid key timestamp {}: {}", value, e))?
+        .with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::SecondsFormat;
+    use hesha_core::{rotate_issuer_key, IssuerSetupBuilder};
+    use hesha_types::IssuerConfig;
+
+    #[test]
+    fn test_load_after_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        IssuerSetupBuilder::new()
+            .name("Test Issuer")
+            .trust_domain("issuer.example.com")
+            .contact_email("admin@example.com")
+            .build()
+            .unwrap()
+            .save(dir.path())
+            .unwrap();
+        let first = IssuerConfig::from_file(dir.path().join("config/issuer.toml")).unwrap();
+        let rotation = rotate_issuer_key(dir.path(), chrono::Duration::days(30)).unwrap();
+
+        let mut config = Config {
+            private_key_path: Some(dir.path().join("keys/private.key").to_string_lossy().into_owned()),
+            key_id: rotation.config.identity.key_id.clone(),
+            key_created_at: Some(rotation.config.identity.created_at.clone()),
+            previous_keys: rotation.config.previous_keys.clone(),
+            ..Config::default()
+        };
+        let keyring = Keyring::load(&config).unwrap();
+        assert_eq!(keyring.active().key_id, rotation.config.identity.key_id);
+        assert_eq!(keyring.active().keypair.public, rotation.keypair.public);
+        let published = keyring.published();
+        assert_eq!(published.len(), 2);
+        assert_eq!(published[1].key_id, first.identity.key_id);
+        assert_eq!(published[1].public_key.to_base64(), first.identity.public_key_base64url);
+
+        // The archived key can be switched back to
+        let previous = keyring.activate(&first.identity.key_id).unwrap();
+        assert_eq!(previous.keypair.public.to_base64(), first.identity.public_key_base64url);
+        assert_eq!(keyring.published()[0].key_id, first.identity.key_id);
+        assert!(keyring.activate("unknown").is_err());
+
+        // Moved offline: still published, no longer usable for signing
+        fs::remove_file(&rotation.archived_private_key).unwrap();
+        let keyring = Keyring::load(&config).unwrap();
+        assert_eq!(keyring.published().len(), 2);
+        assert!(keyring.activate(&first.identity.key_id).is_err());
+
+        // Past its window: no longer held
+        config.previous_keys[0].valid_until = (Utc::now() - chrono::Duration::days(1))
+            .to_rfc3339_opts(SecondsFormat::Secs, true);
+        assert_eq!(Keyring::load(&config).unwrap().published().len(), 1);
+
+        config.signing_key_id = Some(first.identity.key_id.clone());
+        assert!(Keyring::load(&config).is_err());
+    }
+}
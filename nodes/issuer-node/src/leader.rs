@@ -0,0 +1,122 @@
+//! Leader election for background jobs.
+//!
+//! Replicas sharing state compete for a lease in the [`StateStore`]. The
+//! holder renews it every few seconds and runs the jobs that act on shared
+//! records (see [`Job::leader_only`](crate::scheduler::Job::leader_only));
+//! if it stops renewing, another replica takes over once the lease expires.
+//! A node with state of its own always holds the lease.
+
+use chrono::Utc;
+use hesha_crypto::generate_hex_nonce;
+use hesha_storage::{StateStore, StorageResult};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Key of the lease in the shared state.
+const LEASE_KEY: &str = "lease:scheduler";
+
+/// How long a lease lasts without renewal.
+const LEASE_TTL: Duration = Duration::from_secs(30);
+
+/// Time between renewals.
+const RENEW_INTERVAL: Duration = Duration::from_secs(10);
+
+/// This replica's claim on the lease.
+pub struct Leadership {
+    replica_id: String,
+    state: Arc<dyn StateStore>,
+    leader: AtomicBool,
+}
+
+impl Leadership {
+    /// A replica with a random ID, not yet holding the lease.
+    pub fn new(state: Arc<dyn StateStore>) -> Self {
+        Self {
+            replica_id: generate_hex_nonce()[..16].to_string(),
+            state,
+            leader: AtomicBool::new(false),
+        }
+    }
+
+    /// This replica's ID, fresh at each start.
+    pub fn replica_id(&self) -> &str {
+        &self.replica_id
+    }
+
+    /// Whether this replica held the lease at the last renewal.
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::SeqCst)
+    }
+
+    /// Take or extend the lease, returning whether this replica holds it.
+    /// Failing to reach the shared state gives it up.
+    pub async fn renew(&self) -> bool {
+        let held = self.try_renew().await.unwrap_or_else(|e| {
+            tracing::error!("Failed to renew scheduler lease: {}", e);
+            false
+        });
+        let was_leader = self.leader.swap(held, Ordering::SeqCst);
+        if held && !was_leader {
+            tracing::info!("Replica {} now runs the shared background jobs", self.replica_id);
+        } else if !held && was_leader {
+            tracing::warn!("Replica {} no longer runs the shared background jobs", self.replica_id);
+        }
+        held
+    }
+
+    async fn try_renew(&self) -> StorageResult<bool> {
+        let expires_at = Utc::now() + chrono::Duration::from_std(LEASE_TTL).unwrap_or_default();
+        match self.state.get(LEASE_KEY).await? {
+            Some(holder) if holder != self.replica_id => Ok(false),
+            current => {
+                self.state
+                    .compare_and_swap(LEASE_KEY, current.as_deref(), &self.replica_id, expires_at)
+                    .await
+            }
+        }
+    }
+
+    /// Give up the lease so another replica takes over without waiting for
+    /// it to expire.
+    pub async fn resign(&self) {
+        if self.leader.swap(false, Ordering::SeqCst) {
+            if let Err(e) = self.state.compare_and_remove(LEASE_KEY, &self.replica_id).await {
+                tracing::warn!("Failed to release scheduler lease: {}", e);
+            }
+        }
+    }
+}
+
+/// Keep renewing the lease for as long as the node runs.
+pub fn start(leadership: Arc<Leadership>) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(RENEW_INTERVAL).await;
+            leadership.renew().await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_one_leader() {
+        let state: Arc<dyn StateStore> = Arc::new(hesha_storage::MemoryStore::new());
+        let first = Leadership::new(state.clone());
+        let second = Leadership::new(state);
+
+        assert!(first.renew().await);
+        assert!(!second.renew().await);
+        assert!(first.renew().await);
+        assert!(first.is_leader() && !second.is_leader());
+
+        // Resigning hands over at the next renewal
+        first.resign().await;
+        assert!(!first.is_leader());
+        assert!(second.renew().await);
+        assert!(!first.renew().await);
+    }
+}
@@ -0,0 +1,1808 @@
+//! Hesha Protocol issuer node.
+//!
+//! The `issuer-node` binary wires these modules together from its
+//! configuration. [`build_state`] and [`router`] do the same for
+//! in-process servers, such as end-to-end test harnesses.
+
+#[cfg(feature = "acme")]
+pub mod acme;
+pub mod api;
+pub mod audit;
+pub mod auth;
+pub mod captcha;
+#[cfg(feature = "carrier")]
+pub mod carrier;
+pub mod config;
+pub mod control;
+pub mod cors;
+#[cfg(feature = "email")]
+pub mod email;
+pub mod idempotency;
+pub mod ip_access;
+pub mod keys;
+pub mod leader;
+pub mod load_shed;
+pub mod logging;
+pub mod maintenance;
+#[cfg(feature = "messagebird")]
+pub mod messagebird;
+pub mod metrics;
+#[cfg(feature = "oidc")]
+pub mod oidc;
+pub mod openid;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod problem;
+pub mod rate_limit;
+pub mod request_signing;
+pub mod request_validation;
+pub mod scheduler;
+pub mod state;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(feature = "twilio")]
+pub mod twilio;
+pub mod verification;
+#[cfg(feature = "vonage")]
+pub mod vonage;
+pub mod webhooks;
+
+pub use crate::config::Config;
+pub use crate::state::AppState;
+use axum::{
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{get, post},
+    Router,
+};
+
+/// State for a node configured by `config`: keys, storage, phone
+/// verification and the optional checks it enables.
+///
+/// Background jobs, the admin console and TLS are left to the caller.
+pub async fn build_state(config: Config) -> anyhow::Result<AppState> {
+    // Generate or load issuer keys
+    let keys = keys::Keyring::load(&config)?;
+    let active = keys.active();
+    tracing::info!(
+        "Issuer public key: {} (key ID {})",
+        active.keypair.public.to_base64(),
+        active.key_id
+    );
+    let published = keys.published().len();
+    if published > 1 {
+        tracing::info!("Publishing {} previous key(s)", published - 1);
+    }
+    
+    // Open storage backend
+    let mut storage = hesha_storage::Storage::open(&config.storage_url).await?;
+    tracing::info!("Storage backend: {}", config.storage_url.split(':').next().unwrap_or_default());
+    if let Some(state_url) = &config.state_url {
+        storage = storage.with_state_url(state_url).await?;
+        tracing::info!("Shared state backend: {}", state_url.split(':').next().unwrap_or_default());
+    }
+    let shares_state = config.state_url.is_some() || config.storage_url != "memory";
+    if shares_state && config.phone_rate_limit > 0 && config.rate_limit_secret.is_none() {
+        tracing::warn!("HESHA_RATE_LIMIT_SECRET is unset; replicas won't share per-phone rate limits");
+    }
+    
+    // Set up phone verification
+    let verifier = verification::from_config(&config.verification, &storage.state)?;
+    if config.verification.is_mock() {
+        tracing::warn!(
+            "Phone verification uses the mock verifier; code {} is accepted and nothing is sent",
+            verification::MOCK_VERIFICATION_CODE
+        );
+    }
+    
+    // Set up CAPTCHA checks on the SMS-sending path
+    let captcha = config.captcha.as_ref().map(captcha::from_config).transpose()?;
+    
+    // Set up caller authentication
+    let auth = auth::Authenticator::from_config(&config.auth)?;
+    if config.auth.is_none() {
+        tracing::warn!("/attest accepts unauthenticated requests; configure [auth] before exposing it");
+    }
+    
+    // Set up operator authentication for the /admin routes
+    let admin_auth = auth::Authenticator::from_config(&config.admin_auth)?;
+    if config.admin_auth.is_none() {
+        tracing::info!("/admin routes are disabled; configure [admin_auth] to serve them");
+    } else {
+        anyhow::ensure!(
+            config.admin_auth != config.auth,
+            "[admin_auth] must differ from [auth] so partner credentials don't grant admin access"
+        );
+    }
+    
+    // Set up partner request signing
+    let request_signing = config.request_signing.as_ref()
+        .map(request_signing::RequestSigning::from_config)
+        .transpose()?;
+    
+    // Set up network allow and deny lists
+    let ip_access = config.ip_access.as_ref().map(ip_access::IpAccess::from_config).transpose()?;
+    
+    // Set up event delivery
+    let webhooks = webhooks::Webhooks::from_config(&config.webhooks)?;
+    if !config.webhooks.is_empty() {
+        tracing::info!("Delivering events to {} webhook(s)", config.webhooks.len());
+    }
+    
+    // Create app state
+    let mut state = AppState::with_storage(config, keys, storage)
+        .with_verifier(verifier)
+        .with_auth(auth)
+        .with_admin_auth(admin_auth)
+        .with_webhooks(webhooks);
+    if let Some(captcha) = captcha {
+        tracing::info!("/verify/start and /attest/simple require a CAPTCHA token");
+        state = state.with_captcha(captcha);
+    }
+    if let Some(signing) = request_signing {
+        tracing::info!("Attest requests must be signed by one of {} partner(s)", signing.len());
+        state = state.with_request_signing(signing);
+    }
+    if let Some(access) = ip_access {
+        tracing::info!("Requests are checked against the [ip_access] network lists");
+        state = state.with_ip_access(access);
+    }
+    if let Some(oidc) = &state.config.oidc_provider {
+        let provider = openid::OpenIdProvider::from_config(oidc, state.storage.state.clone())?;
+        tracing::info!("Serving {} relying party(ies) as an OpenID provider", provider.len());
+        state = state.with_openid(provider);
+    }
+    
+    // Note a changed issuer key in the audit log
+    state.audit.record_key_change().await?;
+    
+    Ok(state)
+}
+
+/// The node's routes and middleware.
+pub fn router(state: AppState) -> anyhow::Result<Router> {
+    let cors = state.config.cors.as_ref().map(cors::layer).transpose()?;
+    let limit = || middleware::from_fn_with_state(state.clone(), rate_limit::limit_attest);
+    let authenticate = || middleware::from_fn_with_state(state.clone(), auth::require_auth);
+    let signed = || middleware::from_fn_with_state(state.clone(), request_signing::require_signature);
+    let admin = || middleware::from_fn_with_state(state.clone(), auth::require_admin);
+    let app = Router::new()
+        .route(
+            "/attest",
+            post(api::attest::attest)
+                .route_layer(signed())
+                .route_layer(authenticate())
+                .route_layer(limit()),
+        )
+        .route(
+            "/attest/preview",
+            post(api::attest::preview)
+                .route_layer(signed())
+                .route_layer(authenticate())
+                .route_layer(limit()),
+        )
+        .route("/attest/simple", post(api::attest::attest_simple).route_layer(signed()).route_layer(limit()))
+        .route("/verify/start", post(api::verify::start).route_layer(limit()))
+        .route("/verify/check", post(api::verify::check))
+        .route("/verify/callback", get(api::verify::callback))
+        .route("/.well-known/hesha/pubkey.json", get(api::pubkey::pubkey))
+        .route("/.well-known/hesha/revocations.json", get(api::revocations::revocations))
+        .route("/status/:jti", get(api::status::status))
+        .route("/.well-known/openid-configuration", get(api::openid::configuration))
+        .route("/oidc/jwks.json", get(api::openid::jwks))
+        .route("/oidc/authorize", get(api::openid::authorize).post(api::openid::answer))
+        .route("/oidc/token", post(api::openid::token))
+        .route("/health", get(api::health::health))
+        .route("/admin/metrics", get(api::admin::metrics).route_layer(admin()))
+        .route("/admin/stats", get(api::admin::stats).route_layer(admin()))
+        .route("/admin/revoke", post(api::admin::revoke).route_layer(admin()))
+        .route("/admin/audit", get(audit::export).route_layer(admin()))
+        .route("/admin/backup", get(api::admin::backup).route_layer(admin()))
+        .fallback(problem::not_found);
+    // Restores carry every record of a node, far above the usual body limit
+    let restore = Router::new()
+        .route("/admin/restore", post(api::admin::restore).route_layer(admin()))
+        .layer(DefaultBodyLimit::max(api::admin::MAX_RESTORE_BYTES));
+    let mut app = app
+        .layer(middleware::from_fn_with_state(state.clone(), request_validation::validate_request))
+        .layer(DefaultBodyLimit::max(state.config.max_body_bytes))
+        .merge(restore)
+        .layer(middleware::from_fn_with_state(state.clone(), load_shed::limit_concurrency))
+        .layer(middleware::from_fn_with_state(state.clone(), maintenance::refuse_during_maintenance))
+        .layer(middleware::from_fn_with_state(state.clone(), ip_access::restrict_access));
+    if let Some(cors) = cors {
+        app = app.layer(cors);
+    }
+    let app = app.layer(middleware::from_fn(problem::add_request_id));
+    Ok(logging::trace_requests(app).with_state(state))
+    
+}
+
+/// Resolve on a shutdown signal or once a drain requested over the admin
+/// console has run its grace period.
+pub async fn shutdown_or_drain(drain: std::sync::Arc<control::Drain>) {
+    tokio::select! {
+        _ = shutdown_signal() => {}
+        _ = drain.requested() => {
+            tracing::info!("Drain complete; finishing in-flight requests");
+        }
+    }
+}
+
+/// Resolve on Ctrl-C, SIGTERM or (on Windows) a request on the stop pipe,
+/// after which in-flight requests are drained before the server exits.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(windows)]
+    let terminate = stop_requested();
+    #[cfg(not(any(unix, windows)))]
+    let terminate = std::future::pending::<()>();
+    
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    tracing::info!("Shutting down; draining in-flight requests");
+}
+
+/// Resolve once `hesha stop` writes to this node's stop pipe.
+///
+/// Only a write counts: the default pipe DACL gives other users read access
+/// at most, so they can't stop the node.
+#[cfg(windows)]
+async fn stop_requested() {
+    use tokio::io::AsyncReadExt;
+    use tokio::net::windows::named_pipe::ServerOptions;
+    
+    let name = hesha_types::stop_pipe_name(std::process::id());
+    let mut options = ServerOptions::new();
+    options.access_outbound(false).first_pipe_instance(true);
+    loop {
+        let mut server = match options.create(&name) {
+            Ok(server) => server,
+            Err(e) => {
+                tracing::error!("Failed to listen for stop requests on {}: {}", name, e);
+                return std::future::pending::<()>().await;
+            }
+        };
+        let mut request = [0u8; 1];
+        if server.connect().await.is_ok() && matches!(server.read(&mut request).await, Ok(1..)) {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use hesha_crypto::generate_keypair;
+    use hesha_types::PhoneNumber;
+    use tower::util::ServiceExt;
+    
+    /// Operator key the admin route tests authenticate with.
+    const ADMIN_KEY: &str = "admin-key";
+    
+    /// An `/attest` request body with a fresh `nonce` and `timestamp`.
+    fn with_nonce(request: &serde_json::Value) -> serde_json::Value {
+        let mut request = request.clone();
+        request["nonce"] = hesha_crypto::generate_hex_nonce().into();
+        request["timestamp"] = chrono::Utc::now().timestamp().into();
+        request
+    }
+    
+    /// State serving the `/admin` routes to holders of [`ADMIN_KEY`].
+    fn admin_state() -> AppState {
+        AppState::new(Config::default(), generate_keypair().unwrap())
+            .with_admin_auth(auth::Authenticator::ApiKeys(vec![ADMIN_KEY.to_string()]))
+    }
+    
+    /// A request to an admin route, with `token` as the bearer credential.
+    fn admin_request(method: &str, uri: &str, token: Option<&str>, body: axum::body::Body) -> axum::http::Request<axum::body::Body> {
+        let mut request = axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json");
+        if let Some(token) = token {
+            request = request.header("authorization", format!("Bearer {}", token));
+        }
+        request.body(body).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_attestation_issuance() {
+        // Start test server
+        let config = Config::default();
+        let issuer_key = generate_keypair().unwrap();
+        let state = AppState::new(config.clone(), issuer_key);
+        let storage = state.storage.clone();
+        
+        let app = Router::new()
+            .route("/attest", post(api::attest::attest))
+            .route("/.well-known/hesha/pubkey.json", get(api::pubkey::pubkey))
+            .with_state(state);
+        
+        // Create test client
+        let client = tower::ServiceBuilder::new()
+            .service(app);
+        
+        // Test attestation request
+        let user_key = generate_keypair().unwrap();
+        let phone = PhoneNumber::new("+1234567890").unwrap();
+        
+        let request = serde_json::json!({
+            "version": "0.1.0-alpha",
+            "phone_number": phone.to_string(),
+            "user_pubkey": user_key.public.to_base64(),
+            "scope": "234",
+        });
+        
+        let response = client
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/attest")
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(serde_json::to_vec(&with_nonce(&request)).unwrap()))
+                    .unwrap()
+            )
+            .await
+            .unwrap();
+        
+        assert_eq!(response.status(), 200);
+        
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        
+        assert!(result["attestation"].is_string());
+        assert!(result["proxy_number"].is_string());
+        assert!(result["expires_at"].is_number());
+        
+        // Issued attestation is recorded without the phone number
+        let recorded = storage.attestations.recent(10).await.unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].proxy_number, result["proxy_number"].as_str().unwrap());
+        assert_eq!(recorded[0].scope, "234");
+        
+        // The attestation names its signing key
+        let jwt = result["attestation"].as_str().unwrap();
+        let key_id = hesha_core::attestation::jwt::decode_key_id_unverified(jwt).unwrap();
+        assert_eq!(key_id.as_deref(), Some("default"));
+    }
+    
+    #[tokio::test]
+    async fn test_key_activation() {
+        use hesha_core::{rotate_issuer_key, IssuerSetupBuilder};
+        use hesha_types::{ControlRequest, ControlResponse};
+        
+        let dir = tempfile::tempdir().unwrap();
+        IssuerSetupBuilder::new()
+            .name("Test Issuer")
+            .trust_domain("issuer.example.com")
+            .contact_email("admin@example.com")
+            .build()
+            .unwrap()
+            .save(dir.path())
+            .unwrap();
+        rotate_issuer_key(dir.path(), chrono::Duration::days(30)).unwrap();
+        let config = Config::from_args(config::NodeArgs {
+            config_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        })
+        .unwrap();
+        let previous = config.previous_keys[0].clone();
+        let state = AppState::with_storage(
+            config.clone(),
+            keys::Keyring::load(&config).unwrap(),
+            hesha_storage::Storage::memory(),
+        );
+        let router = || Router::new()
+            .route("/.well-known/hesha/pubkey.json", get(api::pubkey::pubkey))
+            .with_state(state.clone());
+        let activate = |key_id: &str| control::execute(&state, ControlRequest::ActivateKey { key_id: key_id.to_string() });
+        let discover = || async {
+            let response = router().oneshot(
+                axum::http::Request::builder()
+                    .uri("/.well-known/hesha/pubkey.json")
+                    .body(axum::body::Body::empty())
+                    .unwrap()
+            ).await.unwrap();
+            let etag = response.headers()["etag"].clone();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (serde_json::from_slice::<hesha_types::IssuerInfo>(&body).unwrap(), etag)
+        };
+        
+        // Both keys are published, the current one on top
+        let (info, etag) = discover().await;
+        assert_eq!(info.key_id.as_deref(), Some(config.key_id.as_str()));
+        assert_eq!(info.keys.len(), 2);
+        assert_eq!(info.key(Some(&previous.key_id)).unwrap().to_base64(), previous.public_key_base64url);
+        
+        assert!(matches!(
+            activate("unknown").await,
+            ControlResponse::Error { error, .. } if error == "activation_failed"
+        ));
+        assert!(matches!(activate(&previous.key_id).await, ControlResponse::Keys(keys) if keys.key_id == previous.key_id));
+        assert_eq!(state.keys.active().key_id, previous.key_id);
+        let (info, new_etag) = discover().await;
+        assert_eq!(info.key_id.as_deref(), Some(previous.key_id.as_str()));
+        assert_eq!(info.keys.len(), 2);
+        assert_ne!(new_etag, etag);
+    }
+    
+    #[tokio::test]
+    async fn test_pubkey_conditional_requests() {
+        let state = AppState::new(Config::default(), generate_keypair().unwrap());
+        let app = Router::new()
+            .route("/.well-known/hesha/pubkey.json", get(api::pubkey::pubkey))
+            .with_state(state);
+        let fetch = |header: Option<(&'static str, String)>| {
+            let mut request = axum::http::Request::builder().uri("/.well-known/hesha/pubkey.json");
+            if let Some((name, value)) = header {
+                request = request.header(name, value);
+            }
+            app.clone().oneshot(request.body(axum::body::Body::empty()).unwrap())
+        };
+        
+        let response = fetch(None).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["cache-control"], "public, max-age=300");
+        assert_eq!(response.headers()["content-type"], "application/json");
+        let etag = response.headers()["etag"].to_str().unwrap().to_string();
+        let last_modified = response.headers()["last-modified"].to_str().unwrap().to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(serde_json::from_slice::<hesha_types::IssuerInfo>(&body).is_ok());
+        
+        // Unchanged keys are not sent again
+        let response = fetch(Some(("if-none-match", etag.clone()))).await.unwrap();
+        assert_eq!(response.status(), 304);
+        assert_eq!(response.headers()["etag"], etag.as_str());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.is_empty());
+        assert_eq!(fetch(Some(("if-none-match", "\"stale\"".to_string()))).await.unwrap().status(), 200);
+        assert_eq!(fetch(Some(("if-modified-since", last_modified))).await.unwrap().status(), 304);
+        assert_eq!(
+            fetch(Some(("if-modified-since", "Mon, 01 Jan 2001 00:00:00 GMT".to_string()))).await.unwrap().status(),
+            200
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_renewal_of_unknown_attestation() {
+        let state = AppState::new(Config::default(), generate_keypair().unwrap());
+        let app = Router::new()
+            .route("/attest", post(api::attest::attest))
+            .with_state(state);
+        
+        let request = serde_json::json!({
+            "version": "0.1.0-alpha",
+            "phone_number": "+1234567890",
+            "user_pubkey": generate_keypair().unwrap().public.to_base64(),
+            "scope": "1",
+            "renews": "not-issued-here",
+        });
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/attest")
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(serde_json::to_vec(&with_nonce(&request)).unwrap()))
+                    .unwrap()
+            )
+            .await
+            .unwrap();
+        
+        assert_eq!(response.status(), 400);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(result["error"], "invalid_renewal");
+    }
+    
+    #[tokio::test]
+    async fn test_idempotent_attestation() {
+        let state = AppState::new(Config::default(), generate_keypair().unwrap());
+        let storage = state.storage.clone();
+        let app = Router::new()
+            .route("/attest", post(api::attest::attest))
+            .with_state(state);
+        let user_pubkey = generate_keypair().unwrap().public.to_base64();
+        let send = |key: &str, scope: &str| {
+            let request = serde_json::json!({
+                "version": "0.1.0-alpha",
+                "phone_number": "+1234567890",
+                "user_pubkey": user_pubkey,
+                "scope": scope,
+            });
+            app.clone().oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/attest")
+                    .header("content-type", "application/json")
+                    .header("idempotency-key", key)
+                    .body(axum::body::Body::from(serde_json::to_vec(&with_nonce(&request)).unwrap()))
+                    .unwrap()
+            )
+        };
+        let body = |response: axum::response::Response| async {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+        
+        let first = send("retry-1", "1").await.unwrap();
+        assert_eq!(first.status(), 200);
+        let first = body(first).await;
+        let retry = send("retry-1", "1").await.unwrap();
+        assert_eq!(retry.status(), 200);
+        assert_eq!(body(retry).await, first);
+        // The retry issued and recorded nothing new
+        assert_eq!(storage.attestations.recent(10).await.unwrap().len(), 1);
+        
+        let reused = send("retry-1", "2").await.unwrap();
+        assert_eq!(reused.status(), 422);
+        assert_eq!(body(reused).await["error"], "idempotency_key_reused");
+        assert_eq!(send(&"k".repeat(256), "1").await.unwrap().status(), 400);
+    }
+    
+    #[tokio::test]
+    async fn test_signed_attest_requests() {
+        use hesha_crypto::{RequestSignature, REQUEST_SIGNATURE_HEADER};
+        
+        let signing = request_signing::RequestSigning::new(
+            [("acme".to_string(), b"secret".to_vec())].into(),
+            300,
+        );
+        let state = AppState::new(Config::default(), generate_keypair().unwrap())
+            .with_request_signing(signing);
+        let app = Router::new()
+            .route(
+                "/attest",
+                post(api::attest::attest).route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    request_signing::require_signature,
+                )),
+            )
+            .with_state(state);
+        
+        let body = serde_json::to_vec(&with_nonce(&serde_json::json!({
+            "version": "0.1.0-alpha",
+            "phone_number": "+1234567890",
+            "user_pubkey": generate_keypair().unwrap().public.to_base64(),
+            "scope": "1",
+        })))
+        .unwrap();
+        let signature = |secret: &[u8], timestamp: i64| {
+            RequestSignature::sign("acme", secret, timestamp, "POST", "/attest", &body).to_header()
+        };
+        let send = |signature: Option<String>, body: Vec<u8>| {
+            let mut request = axum::http::Request::builder()
+                .method("POST")
+                .uri("/attest")
+                .header("content-type", "application/json");
+            if let Some(signature) = signature {
+                request = request.header(REQUEST_SIGNATURE_HEADER, signature);
+            }
+            app.clone().oneshot(request.body(axum::body::Body::from(body)).unwrap())
+        };
+        let now = chrono::Utc::now().timestamp();
+        
+        let valid = signature(b"secret", now);
+        assert_eq!(send(Some(valid.clone()), body.clone()).await.unwrap().status(), 200);
+        // Replayed
+        assert_eq!(send(Some(valid.clone()), body.clone()).await.unwrap().status(), 401);
+        // Tampered body
+        let mut tampered = body.clone();
+        tampered.push(b' ');
+        assert_eq!(send(Some(signature(b"secret", now + 1)), tampered).await.unwrap().status(), 401);
+        // Wrong secret, stale timestamp, unsigned
+        assert_eq!(send(Some(signature(b"guess", now + 2)), body.clone()).await.unwrap().status(), 401);
+        assert_eq!(send(Some(signature(b"secret", now - 3600)), body.clone()).await.unwrap().status(), 401);
+        assert_eq!(send(None, body.clone()).await.unwrap().status(), 401);
+    }
+    
+    #[tokio::test]
+    async fn test_message_signed_attest_requests() {
+        use base64::Engine as _;
+        use hesha_crypto::{content_digest, MessageSignature, SIGNATURE_HEADER, SIGNATURE_INPUT_HEADER};
+        
+        let signing = request_signing::RequestSigning::new(
+            [("acme".to_string(), b"secret".to_vec())].into(),
+            300,
+        );
+        // The same body is accepted under more than one signature
+        let config = Config { require_request_nonce: false, ..Config::default() };
+        let state = AppState::new(config, generate_keypair().unwrap())
+            .with_request_signing(signing);
+        let app = Router::new()
+            .route(
+                "/attest",
+                post(api::attest::attest).route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    request_signing::require_signature,
+                )),
+            )
+            .with_state(state);
+        
+        let body = serde_json::to_vec(&serde_json::json!({
+            "version": "0.1.0-alpha",
+            "phone_number": "+1234567890",
+            "user_pubkey": generate_keypair().unwrap().public.to_base64(),
+            "scope": "1",
+        }))
+        .unwrap();
+        let send = |signature: MessageSignature, digest: String| {
+            let request = axum::http::Request::builder()
+                .method("POST")
+                .uri("/attest")
+                .header("content-type", "application/json")
+                .header("content-digest", digest)
+                .header(SIGNATURE_INPUT_HEADER, signature.signature_input())
+                .header(SIGNATURE_HEADER, signature.to_header());
+            app.clone().oneshot(request.body(axum::body::Body::from(body.clone())).unwrap())
+        };
+        let sign = |secret: &[u8], created: i64| MessageSignature::sign("acme", secret, created, "POST", "/attest", &body);
+        let digest = content_digest(&body);
+        let now = chrono::Utc::now().timestamp();
+        
+        let valid = sign(b"secret", now);
+        assert_eq!(send(valid.clone(), digest.clone()).await.unwrap().status(), 200);
+        // Replayed
+        assert_eq!(send(valid, digest.clone()).await.unwrap().status(), 401);
+        // Digest of another body, signed as sent
+        let other = content_digest(b"{}");
+        let mut forged = MessageSignature::sign("acme", b"secret", now + 1, "POST", "/attest", b"{}");
+        assert_eq!(send(forged.clone(), other).await.unwrap().status(), 401);
+        // Wrong secret, stale, unknown partner
+        assert_eq!(send(sign(b"guess", now + 2), digest.clone()).await.unwrap().status(), 401);
+        assert_eq!(send(sign(b"secret", now - 3600), digest.clone()).await.unwrap().status(), 401);
+        forged = MessageSignature::sign("other", b"secret", now + 3, "POST", "/attest", &body);
+        assert_eq!(send(forged, digest.clone()).await.unwrap().status(), 401);
+        
+        // Standard tooling may order parameters differently and cover more
+        let input = format!(
+            "req=(\"@method\" \"@path\" \"content-type\" \"content-digest\");keyid=\"acme\";created={}",
+            now + 4
+        );
+        let base = format!(
+            "\"@method\": POST\n\"@path\": /attest\n\"content-type\": application/json\n\"content-digest\": {}\n\"@signature-params\": {}",
+            digest,
+            input.trim_start_matches("req=")
+        );
+        let mac = hesha_crypto::hmac_sha256(b"secret", base.as_bytes());
+        let signature = MessageSignature::parse(&input, &format!("req=:{}:", base64::engine::general_purpose::STANDARD.encode(mac))).unwrap();
+        assert_eq!(send(signature, digest.clone()).await.unwrap().status(), 200);
+        
+        // The body must be covered
+        let input = format!("sig1=(\"@method\" \"@path\");keyid=\"acme\";created={}", now + 5);
+        let base = format!("\"@method\": POST\n\"@path\": /attest\n\"@signature-params\": {}", input.trim_start_matches("sig1="));
+        let mac = hesha_crypto::hmac_sha256(b"secret", base.as_bytes());
+        let signature = MessageSignature::parse(&input, &format!("sig1=:{}:", base64::engine::general_purpose::STANDARD.encode(mac))).unwrap();
+        assert_eq!(send(signature, digest).await.unwrap().status(), 401);
+    }
+    
+    #[tokio::test]
+    async fn test_health_check() {
+        let state = AppState::new(Config::default(), generate_keypair().unwrap());
+        
+        let app = Router::new()
+            .route("/health", get(api::health::health))
+            .with_state(state.clone());
+        
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri("/health")
+                    .body(axum::body::Body::empty())
+                    .unwrap()
+            )
+            .await
+            .unwrap();
+        
+        assert_eq!(response.status(), 200);
+        
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        
+        assert_eq!(result["status"], "ok");
+        assert_eq!(result["key_id"], "default");
+        assert!(result["time"].is_string());
+        
+        // Load balancers see a draining node as unhealthy
+        state.drain.start(std::time::Duration::from_secs(3600));
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri("/health")
+                    .body(axum::body::Body::empty())
+                    .unwrap()
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 503);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(result["status"], "draining");
+    }
+    
+    #[tokio::test]
+    async fn test_request_nonce() {
+        let app = |config: Config| {
+            Router::new()
+                .route("/attest", post(api::attest::attest))
+                .with_state(AppState::new(config, generate_keypair().unwrap()))
+        };
+        let user_pubkey = generate_keypair().unwrap().public.to_base64();
+        let send = |app: &Router, nonce: Option<&str>, timestamp: i64| {
+            let mut request = serde_json::json!({
+                "version": "0.1.0-alpha",
+                "phone_number": "+1234567890",
+                "user_pubkey": user_pubkey,
+                "scope": "1",
+            });
+            if let Some(nonce) = nonce {
+                request["nonce"] = nonce.into();
+                request["timestamp"] = timestamp.into();
+            }
+            app.clone().oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/attest")
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(serde_json::to_vec(&request).unwrap()))
+                    .unwrap()
+            )
+        };
+        let error = |response: axum::response::Response| async {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()["error"].clone()
+        };
+        let now = chrono::Utc::now().timestamp();
+        
+        // Requests without a nonce are refused by default
+        let strict = app(Config::default());
+        let missing = send(&strict, None, now).await.unwrap();
+        assert_eq!(missing.status(), 400);
+        assert_eq!(error(missing).await, "invalid_request");
+        
+        let nonce = "0123456789abcdef0123456789abcdef";
+        assert_eq!(send(&strict, Some(nonce), now).await.unwrap().status(), 200);
+        let replayed = send(&strict, Some(nonce), now).await.unwrap();
+        assert_eq!(replayed.status(), 409);
+        assert_eq!(error(replayed).await, "replayed_request");
+        
+        let stale = send(&strict, Some("fedcba9876543210fedcba9876543210"), now - 3600).await.unwrap();
+        assert_eq!(stale.status(), 400);
+        assert_eq!(error(stale).await, "stale_request");
+        assert_eq!(send(&strict, Some("too-short"), now).await.unwrap().status(), 400);
+        
+        // Unless the issuer opts out
+        let lenient = app(Config { require_request_nonce: false, ..Config::default() });
+        assert_eq!(send(&lenient, None, now).await.unwrap().status(), 200);
+    }
+    
+    #[tokio::test]
+    async fn test_scope_policy() {
+        let config = Config {
+            scopes: Some(hesha_types::ScopePolicyConfig {
+                allowed: vec!["1".to_string(), "44".to_string()],
+                match_phone_country: true,
+                validity_days: [("44".to_string(), 90)].into(),
+            }),
+            ..Config::default()
+        };
+        let app = Router::new()
+            .route("/attest", post(api::attest::attest))
+            .with_state(AppState::new(config, generate_keypair().unwrap()));
+        let user_pubkey = generate_keypair().unwrap().public.to_base64();
+        let send = |phone_number: &str, scope: &str, validity_days: Option<i64>| {
+            let request = serde_json::json!({
+                "version": "0.1.0-alpha",
+                "phone_number": phone_number,
+                "user_pubkey": user_pubkey,
+                "scope": scope,
+                "validity_days": validity_days,
+            });
+            app.clone().oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/attest")
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(serde_json::to_vec(&with_nonce(&request)).unwrap()))
+                    .unwrap()
+            )
+        };
+        let body = |response: axum::response::Response| async {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+        
+        assert_eq!(send("+12025550123", "1", None).await.unwrap().status(), 200);
+        
+        let refused = send("+2348031234567", "234", None).await.unwrap();
+        assert_eq!(refused.status(), 403);
+        assert_eq!(body(refused).await["error"], "scope_not_allowed");
+        let mismatch = send("+12025550123", "44", None).await.unwrap();
+        assert_eq!(mismatch.status(), 403);
+        assert_eq!(body(mismatch).await["error"], "scope_country_mismatch");
+        
+        // The scope's validity replaces the default and caps requests
+        let issued = body(send("+447700900123", "44", None).await.unwrap()).await;
+        let days = (issued["expires_at"].as_i64().unwrap() - chrono::Utc::now().timestamp()) / 86400;
+        assert!((89..=90).contains(&days));
+        assert_eq!(send("+447700900123", "44", Some(365)).await.unwrap().status(), 400);
+    }
+    
+    #[tokio::test]
+    async fn test_issuance_caps() {
+        let app = |config: Config| {
+            let state = AppState::new(config, generate_keypair().unwrap());
+            let router = Router::new()
+                .route("/attest", post(api::attest::attest))
+                .with_state(state.clone());
+            (state, router)
+        };
+        let user_pubkey = generate_keypair().unwrap().public.to_base64();
+        let send = |app: &Router, renews: Option<&str>| {
+            let request = serde_json::json!({
+                "version": "0.1.0-alpha",
+                "phone_number": "+1234567890",
+                "user_pubkey": user_pubkey,
+                "scope": "1",
+                "renews": renews,
+            });
+            app.clone().oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/attest")
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(serde_json::to_vec(&with_nonce(&request)).unwrap()))
+                    .unwrap()
+            )
+        };
+        let error = |response: axum::response::Response| async {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()["error"].clone()
+        };
+        
+        let (state, capped) = app(Config {
+            max_active_per_phone: 2,
+            ..Config::default()
+        });
+        for _ in 0..2 {
+            assert_eq!(send(&capped, None).await.unwrap().status(), 200);
+        }
+        let refused = send(&capped, None).await.unwrap();
+        assert_eq!(refused.status(), 403);
+        assert_eq!(error(refused).await, "active_attestation_limit");
+        
+        // A renewal replaces the attestation it renews
+        let issued = state.storage.attestations.recent(2).await.unwrap();
+        assert_eq!(send(&capped, Some(&issued[0].jti)).await.unwrap().status(), 200);
+        
+        // Revoked attestations no longer count
+        for attestation in &issued {
+            state.storage.revocations.revoke(&hesha_types::RevocationEntry {
+                jti: attestation.jti.clone(),
+                revoked_at: chrono::Utc::now(),
+                reason: None,
+            }).await.unwrap();
+        }
+        assert_eq!(send(&capped, None).await.unwrap().status(), 200);
+        
+        let (_, throttled) = app(Config {
+            reissue_interval_secs: 3600,
+            ..Config::default()
+        });
+        assert_eq!(send(&throttled, None).await.unwrap().status(), 200);
+        let refused = send(&throttled, None).await.unwrap();
+        assert_eq!(refused.status(), 429);
+        assert_eq!(error(refused).await, "reissue_too_soon");
+    }
+    
+    #[tokio::test]
+    async fn test_issuance_receipts() {
+        use hesha_types::{AuditEvent, IssuanceReceipt, ReceiptOutcome};
+        let config = Config {
+            max_active_per_phone: 1,
+            ..Config::default()
+        };
+        let state = AppState::new(config, generate_keypair().unwrap());
+        let app = Router::new()
+            .route("/attest", post(api::attest::attest))
+            .with_state(state.clone());
+        let user_pubkey = generate_keypair().unwrap().public.to_base64();
+        let send = || {
+            let request = serde_json::json!({
+                "version": "0.1.0-alpha",
+                "phone_number": "+1234567890",
+                "user_pubkey": user_pubkey,
+                "scope": "1",
+            });
+            app.clone().oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/attest")
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(serde_json::to_vec(&with_nonce(&request)).unwrap()))
+                    .unwrap()
+            )
+        };
+        let receipt = |response: axum::response::Response| async {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            serde_json::from_value::<IssuanceReceipt>(body["receipt"].clone()).unwrap()
+        };
+        let issuer_key = state.keys.active().keypair.public.clone();
+        
+        let issued = receipt(send().await.unwrap()).await;
+        hesha_core::verify_receipt(&issued, &issuer_key).unwrap();
+        assert_eq!(issued.outcome, ReceiptOutcome::Issued);
+        assert_eq!(issued.user_pubkey, user_pubkey);
+        let recorded = state.storage.attestations.recent(1).await.unwrap();
+        assert_eq!(issued.jti.as_deref(), Some(recorded[0].jti.as_str()));
+        assert_eq!(issued.proxy_number.as_deref(), Some(recorded[0].proxy_number.as_str()));
+        let entry = state.storage.audit.last().await.unwrap().unwrap();
+        assert!(matches!(
+            entry.event,
+            AuditEvent::AttestationIssued { receipt: Some(hash), .. } if hash == hesha_core::receipt_hash(&issued)
+        ));
+        
+        // A refusal on the per-phone cap comes with a denial receipt
+        let refused = send().await.unwrap();
+        assert_eq!(refused.status(), 403);
+        let denied = receipt(refused).await;
+        hesha_core::verify_receipt(&denied, &issuer_key).unwrap();
+        assert_eq!(denied.outcome, ReceiptOutcome::Denied);
+        assert_eq!(denied.error.as_deref(), Some("active_attestation_limit"));
+        assert!(denied.jti.is_none());
+        let entry = state.storage.audit.last().await.unwrap().unwrap();
+        assert!(matches!(
+            entry.event,
+            AuditEvent::AttestationDenied { receipt, .. } if receipt == hesha_core::receipt_hash(&denied)
+        ));
+    }
+    
+    #[tokio::test]
+    async fn test_attestation_preview() {
+        let state = AppState::new(Config::default(), generate_keypair().unwrap());
+        let app = Router::new()
+            .route("/attest/preview", post(api::attest::preview))
+            .with_state(state.clone());
+        let user_pubkey = generate_keypair().unwrap().public.to_base64();
+        let send = |validity_days: Option<i64>| {
+            let request = serde_json::json!({
+                "version": "0.1.0-alpha",
+                "phone_number": "+1234567890",
+                "user_pubkey": user_pubkey,
+                "scope": "44",
+                "validity_days": validity_days,
+            });
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(
+                    axum::http::Request::builder()
+                        .method("POST")
+                        .uri("/attest/preview")
+                        .header("content-type", "application/json")
+                        .body(axum::body::Body::from(serde_json::to_vec(&request).unwrap()))
+                        .unwrap()
+                ).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap())
+            }
+        };
+        
+        let (status, preview) = send(Some(30)).await;
+        assert_eq!(status, 200);
+        assert_eq!(preview["format"], "+4400XXXXXXXXXX");
+        assert!(preview["proxy_number"].as_str().unwrap().starts_with("+4400"));
+        assert_eq!(preview["validity_days"], 30);
+        
+        let (status, body) = send(Some(9999)).await;
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "invalid_validity_days");
+        
+        // Nothing was issued or recorded
+        assert!(state.storage.attestations.recent(10).await.unwrap().is_empty());
+        assert_eq!(state.metrics.snapshot().attestations_issued, 0);
+        assert!(state.storage.audit.last().await.unwrap().is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_proxy_number_assignment() {
+        let state = AppState::new(Config::default(), generate_keypair().unwrap());
+        let storage = state.storage.clone();
+        let app = Router::new()
+            .route("/attest", post(api::attest::attest))
+            .with_state(state);
+        let request = serde_json::json!({
+            "version": "0.1.0-alpha",
+            "phone_number": "+1234567890",
+            "user_pubkey": generate_keypair().unwrap().public.to_base64(),
+            "scope": "1",
+        });
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/attest")
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(serde_json::to_vec(&with_nonce(&request)).unwrap()))
+                    .unwrap()
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let proxy_number = body["proxy_number"].as_str().unwrap();
+        
+        // The proxy number now belongs to the phone that was attested
+        let phone_hash = hesha_crypto::hash_phone_number_spec(&hesha_types::PhoneNumber::new("+1234567890").unwrap());
+        assert!(storage.attestations.assign_proxy_number(proxy_number, &phone_hash).await.unwrap());
+        assert!(!storage.attestations.assign_proxy_number(proxy_number, "sha256:other").await.unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_admin_metrics() {
+        use axum::extract::connect_info::MockConnectInfo;
+        
+        let state = admin_state();
+        let router = || Router::new()
+            .route("/attest", post(api::attest::attest))
+            .route(
+                "/admin/metrics",
+                get(api::admin::metrics)
+                    .route_layer(middleware::from_fn_with_state(state.clone(), auth::require_admin)),
+            )
+            .layer(MockConnectInfo("127.0.0.1:40000".parse::<SocketAddr>().unwrap()))
+            .with_state(state.clone());
+        
+        let user_key = generate_keypair().unwrap();
+        for version in ["0.1.0-alpha", "9.9"] {
+            let request = serde_json::json!({
+                "version": version,
+                "phone_number": "+1234567890",
+                "user_pubkey": user_key.public.to_base64(),
+                "scope": "1",
+            });
+            router()
+                .oneshot(
+                    axum::http::Request::builder()
+                        .method("POST")
+                        .uri("/attest")
+                        .header("content-type", "application/json")
+                        .body(axum::body::Body::from(serde_json::to_vec(&with_nonce(&request)).unwrap()))
+                        .unwrap()
+                )
+                .await
+                .unwrap();
+        }
+        
+        let get_metrics = |token| router().oneshot(admin_request("GET", "/admin/metrics", token, axum::body::Body::empty()));
+        
+        let response = get_metrics(Some(ADMIN_KEY)).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let metrics: hesha_types::NodeMetrics = serde_json::from_slice(&body).unwrap();
+        assert_eq!(metrics.attestations_issued, 1);
+        assert_eq!(metrics.errors["invalid_version"], 1);
+        assert!(!metrics.recent_attestations[0].jti.is_empty());
+        assert!(!String::from_utf8_lossy(&body).contains("+1234567890"));
+        
+        let response = get_metrics(Some("partner-key")).await.unwrap();
+        assert_eq!(response.status(), 401);
+    }
+    
+    #[tokio::test]
+    async fn test_rate_limits() {
+        use axum::extract::connect_info::MockConnectInfo;
+        
+        let config = Config {
+            ip_rate_limit: 3,
+            phone_rate_limit: 2,
+            ..Config::default()
+        };
+        let state = AppState::new(config, generate_keypair().unwrap());
+        let router = |peer: SocketAddr| Router::new()
+            .route(
+                "/attest",
+                post(api::attest::attest)
+                    .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit::limit_attest)),
+            )
+            .layer(MockConnectInfo(peer))
+            .with_state(state.clone());
+        
+        let user_key = generate_keypair().unwrap();
+        let attest = |peer: &str, phone: &str| {
+            let request = serde_json::json!({
+                "version": "0.1.0-alpha",
+                "phone_number": phone,
+                "user_pubkey": user_key.public.to_base64(),
+                "scope": "1",
+            });
+            router(peer.parse().unwrap()).oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/attest")
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(serde_json::to_vec(&with_nonce(&request)).unwrap()))
+                    .unwrap()
+            )
+        };
+        
+        // Per phone number, across addresses
+        assert_eq!(attest("198.51.100.1:1", "+1234567890").await.unwrap().status(), 200);
+        assert_eq!(attest("198.51.100.2:1", "+1234567890").await.unwrap().status(), 200);
+        let response = attest("198.51.100.3:1", "+1234567890").await.unwrap();
+        assert_eq!(response.status(), 429);
+        assert_eq!(response.headers()["retry-after"], "1800");
+        
+        // Per address, across phone numbers
+        assert_eq!(attest("198.51.100.4:1", "+1234567801").await.unwrap().status(), 200);
+        assert_eq!(attest("198.51.100.4:1", "+1234567802").await.unwrap().status(), 200);
+        assert_eq!(attest("198.51.100.4:1", "+1234567803").await.unwrap().status(), 200);
+        let response = attest("198.51.100.4:1", "+1234567804").await.unwrap();
+        assert_eq!(response.status(), 429);
+        assert_eq!(response.headers()["retry-after"], "20");
+    }
+    
+    #[tokio::test]
+    async fn test_session_rate_limits() {
+        use axum::extract::connect_info::MockConnectInfo;
+        
+        let config = Config {
+            phone_rate_limit: 2,
+            ..Config::default()
+        };
+        let state = AppState::new(config, generate_keypair().unwrap());
+        let app = Router::new()
+            .route("/verify/start", post(api::verify::start))
+            .route("/verify/check", post(api::verify::check))
+            .route(
+                "/attest/simple",
+                post(api::attest::attest_simple)
+                    .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit::limit_attest)),
+            )
+            .layer(MockConnectInfo("198.51.100.1:1".parse::<SocketAddr>().unwrap()))
+            .with_state(state);
+        let post_json = |uri: &str, body: serde_json::Value| {
+            let request = axum::http::Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+        let attest = |phone: &'static str| {
+            let post_json = &post_json;
+            async move {
+                let response = post_json("/verify/start", serde_json::json!({ "phone_number": phone })).await.unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                let session: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                let session_id = session["session_id"].as_str().unwrap().to_string();
+                let check = serde_json::json!({ "session_id": session_id, "code": verification::MOCK_VERIFICATION_CODE });
+                post_json("/verify/check", check).await.unwrap();
+                let request = serde_json::json!({
+                    "version": "0.1.0-alpha",
+                    "session_id": session_id,
+                    "user_pubkey": generate_keypair().unwrap().public.to_base64(),
+                    "scope": "1",
+                });
+                (post_json("/attest/simple", request.clone()).await.unwrap(), request)
+            }
+        };
+        
+        // Each request names only a session, yet counts against its phone
+        assert_eq!(attest("+1234567890").await.0.status(), 200);
+        assert_eq!(attest("+1234567890").await.0.status(), 200);
+        let (response, request) = attest("+1234567890").await;
+        assert_eq!(response.status(), 429);
+        assert_eq!(response.headers()["retry-after"], "1800");
+        
+        // The refused session isn't used up, and other phones aren't affected
+        assert_eq!(post_json("/attest/simple", request).await.unwrap().status(), 429);
+        assert_eq!(attest("+1234567801").await.0.status(), 200);
+    }
+    
+    #[tokio::test]
+    async fn test_verified_session_attestation() {
+        let state = AppState::new(Config::default(), generate_keypair().unwrap());
+        let app = Router::new()
+            .route("/verify/start", post(api::verify::start))
+            .route("/verify/check", post(api::verify::check))
+            .route("/attest/simple", post(api::attest::attest_simple))
+            .with_state(state);
+        let post_json = |uri: &str, body: serde_json::Value| {
+            let request = axum::http::Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap();
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap())
+            }
+        };
+        
+        let (status, session) = post_json("/verify/start", serde_json::json!({
+            "phone_number": "+1234567890",
+        })).await;
+        assert_eq!(status, 200);
+        assert_eq!(session["channel"], "sms");
+        let session_id = session["session_id"].as_str().unwrap();
+        
+        let attest_request = serde_json::json!({
+            "version": "0.1.0-alpha",
+            "session_id": session_id,
+            "user_pubkey": generate_keypair().unwrap().public.to_base64(),
+            "scope": "1",
+        });
+        let (status, body) = post_json("/attest/simple", attest_request.clone()).await;
+        assert_eq!(status, 403);
+        assert_eq!(body["error"], "phone_not_verified");
+        
+        let check = |code: &str| serde_json::json!({ "session_id": session_id, "code": code });
+        let (_, body) = post_json("/verify/check", check("000000")).await;
+        assert_eq!(body["verified"], false);
+        let (_, body) = post_json("/verify/check", check(verification::MOCK_VERIFICATION_CODE)).await;
+        assert_eq!(body["verified"], true);
+        
+        let (status, body) = post_json("/attest/simple", attest_request.clone()).await;
+        assert_eq!(status, 200);
+        let attestation = hesha_core::parse_attestation(body["attestation"].as_str().unwrap()).unwrap();
+        assert_eq!(attestation.verification_method(), Some(hesha_types::VerificationMethod::Sms));
+        
+        // The session was used up
+        let (status, _) = post_json("/attest/simple", attest_request).await;
+        assert_eq!(status, 404);
+    }
+    
+    #[tokio::test]
+    async fn test_replicas_share_state() {
+        // Two replicas behind a load balancer, over one database
+        let storage = hesha_storage::Storage::memory();
+        let issuer_key = generate_keypair().unwrap();
+        let replica = || {
+            let config = Config::default();
+            let keypair = hesha_crypto::keypair_from_private(&issuer_key.private).unwrap();
+            let keys = keys::Keyring::new(config.key_id.clone(), keypair);
+            let state = AppState::with_storage(config, keys, storage.clone());
+            let app = Router::new()
+                .route("/attest", post(api::attest::attest))
+                .route("/verify/start", post(api::verify::start))
+                .route("/verify/check", post(api::verify::check))
+                .route("/attest/simple", post(api::attest::attest_simple))
+                .with_state(state.clone());
+            (state, app)
+        };
+        let replicas = [replica(), replica()];
+        let post_json = |replica: usize, uri: &str, body: serde_json::Value, key: Option<&str>| {
+            let mut request = axum::http::Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json");
+            if let Some(key) = key {
+                request = request.header("idempotency-key", key);
+            }
+            let request = request.body(axum::body::Body::from(serde_json::to_vec(&body).unwrap())).unwrap();
+            let app = replicas[replica].1.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap())
+            }
+        };
+        
+        // A code sent by one replica is checked and used on the other
+        let (_, session) = post_json(0, "/verify/start", serde_json::json!({ "phone_number": "+1234567890" }), None).await;
+        let session_id = session["session_id"].as_str().unwrap();
+        let check = serde_json::json!({ "session_id": session_id, "code": verification::MOCK_VERIFICATION_CODE });
+        let (_, body) = post_json(1, "/verify/check", check, None).await;
+        assert_eq!(body["verified"], true);
+        let attest_request = serde_json::json!({
+            "version": "0.1.0-alpha",
+            "session_id": session_id,
+            "user_pubkey": generate_keypair().unwrap().public.to_base64(),
+            "scope": "1",
+        });
+        assert_eq!(post_json(0, "/attest/simple", attest_request.clone(), None).await.0, 200);
+        assert_eq!(post_json(1, "/attest/simple", attest_request, None).await.0, 404);
+        
+        // A retry landing on the other replica is replayed
+        let request = serde_json::json!({
+            "version": "0.1.0-alpha",
+            "phone_number": "+447700900123",
+            "user_pubkey": generate_keypair().unwrap().public.to_base64(),
+            "scope": "44",
+        });
+        let (_, first) = post_json(0, "/attest", with_nonce(&request), Some("retry-1")).await;
+        let (status, retried) = post_json(1, "/attest", with_nonce(&request), Some("retry-1")).await;
+        assert_eq!(status, 200);
+        assert_eq!(retried["proxy_number"], first["proxy_number"]);
+        assert_eq!(storage.attestations.recent(10).await.unwrap().len(), 2);
+        
+        // Only one replica runs the shared jobs
+        let leaders = [replicas[0].0.leader.renew().await, replicas[1].0.leader.renew().await];
+        assert_eq!(leaders, [true, false]);
+    }
+    
+    #[tokio::test]
+    async fn test_captcha() {
+        struct FixedCaptcha;
+        
+        #[async_trait::async_trait]
+        impl captcha::CaptchaVerifier for FixedCaptcha {
+            async fn verify(&self, token: &str, _remote_ip: Option<std::net::IpAddr>) -> anyhow::Result<bool> {
+                Ok(token == "solved")
+            }
+        }
+        
+        let state = AppState::new(Config::default(), generate_keypair().unwrap())
+            .with_captcha(std::sync::Arc::new(FixedCaptcha));
+        let app = Router::new()
+            .route("/verify/start", post(api::verify::start))
+            .route("/verify/check", post(api::verify::check))
+            .route("/attest/simple", post(api::attest::attest_simple))
+            .with_state(state);
+        let post_json = |uri: &str, body: serde_json::Value| {
+            let request = axum::http::Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap();
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap())
+            }
+        };
+        
+        let start = |captcha_token: Option<&str>| serde_json::json!({
+            "phone_number": "+1234567890",
+            "captcha_token": captcha_token,
+        });
+        let (status, body) = post_json("/verify/start", start(None)).await;
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "captcha_required");
+        let (status, body) = post_json("/verify/start", start(Some("robot"))).await;
+        assert_eq!(status, 403);
+        assert_eq!(body["error"], "captcha_failed");
+        let (status, session) = post_json("/verify/start", start(Some("solved"))).await;
+        assert_eq!(status, 200);
+        let session_id = session["session_id"].as_str().unwrap();
+        post_json("/verify/check", serde_json::json!({
+            "session_id": session_id,
+            "code": verification::MOCK_VERIFICATION_CODE,
+        })).await;
+        
+        let attest = |captcha_token: Option<&str>| serde_json::json!({
+            "version": "0.1.0-alpha",
+            "session_id": session_id,
+            "user_pubkey": generate_keypair().unwrap().public.to_base64(),
+            "scope": "1",
+            "captcha_token": captcha_token,
+        });
+        let (status, body) = post_json("/attest/simple", attest(None)).await;
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "captcha_required");
+        // The refused request left the session usable
+        let (status, _) = post_json("/attest/simple", attest(Some("solved"))).await;
+        assert_eq!(status, 200);
+    }
+    
+    #[tokio::test]
+    async fn test_verification_callback() {
+        let state = AppState::new(Config::default(), generate_keypair().unwrap());
+        let session_id = state.verification.start(PhoneNumber::new("+1234567890").unwrap()).await.unwrap();
+        let app = Router::new()
+            .route("/verify/callback", get(api::verify::callback))
+            .with_state(state.clone());
+        let callback = |query: String| app.clone().oneshot(
+            axum::http::Request::builder()
+                .uri(format!("/verify/callback?{}", query))
+                .body(axum::body::Body::empty())
+                .unwrap()
+        );
+        
+        let response = callback(format!("state={}&error=access_denied", session_id)).await.unwrap();
+        assert_eq!(response.status(), 403);
+        let response = callback(format!("state=unknown&code={}", verification::MOCK_VERIFICATION_CODE)).await.unwrap();
+        assert_eq!(response.status(), 404);
+        let response = callback(format!("state={}&code={}", session_id, verification::MOCK_VERIFICATION_CODE)).await.unwrap();
+        assert_eq!(response.status(), 200);
+        
+        assert!(state.verification.take_verified(&session_id).await.is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_revocation_list() {
+        let state = admin_state();
+        let router = || Router::new()
+            .route("/.well-known/hesha/revocations.json", get(api::revocations::revocations))
+            .route(
+                "/admin/revoke",
+                post(api::admin::revoke)
+                    .route_layer(middleware::from_fn_with_state(state.clone(), auth::require_admin)),
+            )
+            .with_state(state.clone());
+        let revoke = |token, jti: &str| router().oneshot(admin_request(
+            "POST",
+            "/admin/revoke",
+            token,
+            axum::body::Body::from(serde_json::json!({ "jti": jti, "reason": "test" }).to_string()),
+        ));
+        let list = |uri: &str, etag: Option<&str>| {
+            let mut request = axum::http::Request::builder().uri(uri);
+            if let Some(etag) = etag {
+                request = request.header("if-none-match", etag);
+            }
+            router().oneshot(request.body(axum::body::Body::empty()).unwrap())
+        };
+        
+        assert_eq!(revoke(None, "a").await.unwrap().status(), 401);
+        assert_eq!(revoke(Some("partner-key"), "a").await.unwrap().status(), 401);
+        for jti in ["a", "b", "a"] {
+            assert_eq!(revoke(Some(ADMIN_KEY), jti).await.unwrap().status(), 200);
+        }
+        
+        let response = list("/.well-known/hesha/revocations.json?per_page=1", None).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let etag = response.headers()["etag"].to_str().unwrap().to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let first: hesha_types::RevocationList = serde_json::from_slice(&body).unwrap();
+        assert_eq!(first.entries[0].jti, "a");
+        hesha_core::verify_revocation_list(&first, &state.keys.active().keypair.public).unwrap();
+        
+        let response = list(first.next.as_deref().unwrap(), None).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let second: hesha_types::RevocationList = serde_json::from_slice(&body).unwrap();
+        assert_eq!(second.entries[0].jti, "b");
+        assert!(second.next.is_none());
+        
+        let response = list("/.well-known/hesha/revocations.json", Some(&etag)).await.unwrap();
+        assert_eq!(response.status(), 304);
+    }
+    
+    #[tokio::test]
+    async fn test_openid_provider() {
+        use crate::openid::{OpenIdProvider, RegisteredClient};
+        use hesha_core::{oidc_service_context, sign_challenge, verify_id_token};
+        use std::collections::HashMap;
+        
+        let config = Config::default();
+        let state = AppState::new(config.clone(), generate_keypair().unwrap());
+        let client = RegisteredClient {
+            secret: b"s3cret".to_vec(),
+            redirect_uris: vec!["https://rp.example.com/cb".to_string()],
+        };
+        let provider = OpenIdProvider::new(
+            HashMap::from([("rp".to_string(), client)]),
+            300,
+            state.storage.state.clone(),
+        );
+        let state = state.with_openid(provider);
+        let router = || Router::new()
+            .route("/attest", post(api::attest::attest))
+            .route("/.well-known/openid-configuration", get(api::openid::configuration))
+            .route("/oidc/jwks.json", get(api::openid::jwks))
+            .route("/oidc/authorize", get(api::openid::authorize).post(api::openid::answer))
+            .route("/oidc/token", post(api::openid::token))
+            .with_state(state.clone());
+        let send = |request: axum::http::Request<axum::body::Body>| async move {
+            let response = router().oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap_or_default())
+        };
+        let get_uri = |uri: &str| axum::http::Request::builder().uri(uri).body(axum::body::Body::empty()).unwrap();
+        let post_json = |uri: &str, body: serde_json::Value| axum::http::Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap();
+        let post_form = |body: String| axum::http::Request::builder()
+            .method("POST")
+            .uri("/oidc/token")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(axum::body::Body::from(body))
+            .unwrap();
+        
+        let (status, metadata) = send(get_uri("/.well-known/openid-configuration")).await;
+        assert_eq!(status, 200);
+        let issuer = metadata["issuer"].as_str().unwrap().to_string();
+        assert_eq!(issuer, "https://localhost:3000");
+        let (_, jwks) = send(get_uri("/oidc/jwks.json")).await;
+        assert_eq!(jwks["keys"][0]["kid"], "default");
+        
+        // The wallet holds an attestation from this issuer
+        let user_key = generate_keypair().unwrap();
+        let (_, issued) = send(post_json("/attest", with_nonce(&serde_json::json!({
+            "version": "0.1.0-alpha",
+            "phone_number": "+14155552671",
+            "user_pubkey": user_key.public.to_base64(),
+            "scope": "1",
+        })))).await;
+        let jwt = issued["attestation"].as_str().unwrap().to_string();
+        let jti = hesha_core::parse_attestation(&jwt).unwrap().jti;
+        
+        // Unregistered redirect URIs are refused outright
+        let (status, _) = send(get_uri(
+            "/oidc/authorize?response_type=code&client_id=rp&redirect_uri=https://evil.example.com&scope=openid",
+        )).await;
+        assert_eq!(status, 400);
+        
+        let (status, started) = send(get_uri(
+            "/oidc/authorize?response_type=code&client_id=rp&redirect_uri=https://rp.example.com/cb&scope=openid&state=a%20b&nonce=n1",
+        )).await;
+        assert_eq!(status, 200);
+        let challenge: hesha_types::Challenge = serde_json::from_value(started["challenge"].clone()).unwrap();
+        assert_eq!(challenge.service_context, oidc_service_context(&issuer, "rp"));
+        
+        let response = sign_challenge(&challenge, &jti, &user_key.private).unwrap();
+        let answer = serde_json::json!({
+            "request_id": started["request_id"],
+            "attestation": jwt,
+            "challenge_response": response,
+        });
+        let (status, redirect) = send(post_json("/oidc/authorize", answer.clone())).await;
+        assert_eq!(status, 200);
+        let redirect_uri = redirect["redirect_uri"].as_str().unwrap();
+        assert!(redirect_uri.starts_with("https://rp.example.com/cb?code="));
+        assert!(redirect_uri.ends_with("&state=a%20b"));
+        let code = redirect_uri.split("code=").nth(1).unwrap().split('&').next().unwrap().to_string();
+        
+        // Requests are single use
+        let (status, _) = send(post_json("/oidc/authorize", answer)).await;
+        assert_eq!(status, 404);
+        
+        let form = |secret: &str| format!(
+            "grant_type=authorization_code&code={}&redirect_uri=https%3A%2F%2Frp.example.com%2Fcb&client_id=rp&client_secret={}",
+            code, secret
+        );
+        let (status, error) = send(post_form(form("wrong"))).await;
+        assert_eq!(status, 401);
+        assert_eq!(error["error"], "invalid_client");
+        
+        let (status, tokens) = send(post_form(form("s3cret"))).await;
+        assert_eq!(status, 200);
+        let public_key = state.keys.active().keypair.public.clone();
+        let claims = verify_id_token(tokens["id_token"].as_str().unwrap(), &public_key, &issuer, "rp", Some("n1")).unwrap();
+        assert_eq!(claims.hesha_proxy_number, issued["proxy_number"].as_str().unwrap());
+        assert_eq!(claims.hesha_attestation, jwt);
+        
+        // Codes are single use too
+        let (status, error) = send(post_form(form("s3cret"))).await;
+        assert_eq!(status, 400);
+        assert_eq!(error["error"], "invalid_grant");
+    }
+    
+    #[tokio::test]
+    async fn test_status_responder() {
+        use hesha_types::{AttestationStatus, StatusResponse};
+        
+        let state = admin_state();
+        let now = chrono::Utc::now();
+        for jti in ["good", "revoked"] {
+            state.storage.attestations.insert(&hesha_storage::StoredAttestation {
+                jti: jti.to_string(),
+                proxy_number: "+990012345678".to_string(),
+                scope: "1".to_string(),
+                issued_at: now,
+                expires_at: now + chrono::Duration::days(30),
+                renews: None,
+            }).await.unwrap();
+        }
+        let router = || Router::new()
+            .route("/status/:jti", get(api::status::status))
+            .route(
+                "/admin/revoke",
+                post(api::admin::revoke)
+                    .route_layer(middleware::from_fn_with_state(state.clone(), auth::require_admin)),
+            )
+            .with_state(state.clone());
+        let response = router().oneshot(admin_request(
+            "POST",
+            "/admin/revoke",
+            Some(ADMIN_KEY),
+            axum::body::Body::from(r#"{"jti": "revoked", "reason": "test"}"#),
+        )).await.unwrap();
+        assert_eq!(response.status(), 200);
+        
+        let public_key = state.keys.active().keypair.public.clone();
+        let mut statuses = Vec::new();
+        for jti in ["good", "revoked", "never-issued"] {
+            let response = router().oneshot(
+                axum::http::Request::builder().uri(format!("/status/{}", jti)).body(axum::body::Body::empty()).unwrap()
+            ).await.unwrap();
+            assert_eq!(response.status(), 200);
+            assert_eq!(response.headers()["cache-control"], "public, max-age=300");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let status: StatusResponse = serde_json::from_slice(&body).unwrap();
+            hesha_core::verify_status_response(&status, &public_key).unwrap();
+            assert_eq!(status.jti, jti);
+            statuses.push(status.status);
+        }
+        assert_eq!(statuses[0], AttestationStatus::Good);
+        assert!(matches!(
+            &statuses[1],
+            AttestationStatus::Revoked { reason: Some(reason), .. } if reason == "test"
+        ));
+        assert_eq!(statuses[2], AttestationStatus::Unknown);
+    }
+    
+    #[tokio::test]
+    async fn test_audit_export() {
+        use hesha_types::{AuditEntry, AuditEvent};
+        
+        let state = admin_state();
+        let router = || Router::new()
+            .route("/admin/revoke", post(api::admin::revoke))
+            .route("/admin/audit", get(audit::export))
+            .route_layer(middleware::from_fn_with_state(state.clone(), auth::require_admin))
+            .with_state(state.clone());
+        let export = |token, uri: &str| router().oneshot(admin_request("GET", uri, token, axum::body::Body::empty()));
+        
+        let response = router().oneshot(admin_request(
+            "POST",
+            "/admin/revoke",
+            Some(ADMIN_KEY),
+            axum::body::Body::from(r#"{"jti":"a"}"#),
+        )).await.unwrap();
+        assert_eq!(response.status(), 200);
+        
+        assert_eq!(export(None, "/admin/audit").await.unwrap().status(), 401);
+        let response = export(Some(ADMIN_KEY), "/admin/audit").await.unwrap();
+        assert_eq!(response.status(), 200);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let entries: Vec<AuditEntry> = std::str::from_utf8(&body).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 3);
+        assert!(matches!(&entries[1].event, AuditEvent::AttestationRevoked { jti, .. } if jti == "a"));
+        assert!(matches!(&entries[2].event, AuditEvent::AuthFailed { path, .. } if path == "/admin/audit"));
+        hesha_core::verify_audit_log(&entries, std::slice::from_ref(&state.keys.active().keypair.public)).unwrap();
+        
+        // The export itself is recorded
+        let response = export(Some(ADMIN_KEY), "/admin/audit?after=3").await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let entry: AuditEntry = serde_json::from_slice(body.split(|b| *b == b'\n').next().unwrap()).unwrap();
+        assert!(matches!(entry.event, AuditEvent::AdminAction { action, .. } if action == "audit.export"));
+    }
+    
+    #[tokio::test]
+    async fn test_backup_and_restore() {
+        use hesha_storage::{RecordSnapshot, StoredAttestation};
+        use hesha_types::RestoreSummary;
+        
+        let attestation = StoredAttestation {
+            jti: "a".to_string(),
+            proxy_number: "+100111".to_string(),
+            scope: "1".to_string(),
+            issued_at: chrono::Utc::now(),
+            expires_at: chrono::Utc::now() + chrono::Duration::days(365),
+            renews: None,
+        };
+        let state = admin_state();
+        state.storage.attestations.insert(&attestation).await.unwrap();
+        state.storage.attestations.assign_proxy_number("+100111", "sha256:aa").await.unwrap();
+        let router = |state: AppState| Router::new()
+            .route("/admin/backup", get(api::admin::backup))
+            .route("/admin/restore", post(api::admin::restore))
+            .route_layer(middleware::from_fn_with_state(state.clone(), auth::require_admin))
+            .with_state(state);
+        let backup = |state: AppState, token| router(state).oneshot(
+            admin_request("GET", "/admin/backup", token, axum::body::Body::empty())
+        );
+        
+        // Refused without [admin_auth], and without the operator's key
+        let unconfigured = AppState::new(Config::default(), generate_keypair().unwrap());
+        assert_eq!(backup(unconfigured, Some(ADMIN_KEY)).await.unwrap().status(), 403);
+        assert_eq!(backup(state.clone(), None).await.unwrap().status(), 401);
+        assert_eq!(backup(state.clone(), Some("partner-key")).await.unwrap().status(), 401);
+        
+        let response = backup(state.clone(), Some(ADMIN_KEY)).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let records = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let snapshot: RecordSnapshot = serde_json::from_slice(&records).unwrap();
+        assert_eq!(snapshot.attestations, [attestation]);
+        
+        // A new node takes the records; the original already has them
+        let restore = |state: AppState, token| router(state).oneshot(
+            admin_request("POST", "/admin/restore", token, axum::body::Body::from(records.clone()))
+        );
+        let restored = admin_state();
+        assert_eq!(restore(restored.clone(), None).await.unwrap().status(), 401);
+        let response = restore(restored.clone(), Some(ADMIN_KEY)).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let summary: RestoreSummary = serde_json::from_slice(&body).unwrap();
+        assert_eq!((summary.attestations, summary.proxy_numbers), (1, 1));
+        assert_eq!(restored.storage.attestations.find_by_phone_hash("sha256:aa").await.unwrap().len(), 1);
+        
+        let body = axum::body::to_bytes(restore(state.clone(), Some(ADMIN_KEY)).await.unwrap().into_body(), usize::MAX).await.unwrap();
+        let summary: RestoreSummary = serde_json::from_slice(&body).unwrap();
+        assert_eq!(summary.attestations, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_stats() {
+        use hesha_storage::StoredAttestation;
+        use hesha_types::IssuanceStats;
+        
+        let state = admin_state();
+        let now = chrono::Utc::now();
+        let attestations = [("a", "1", 0, None), ("b", "1", 0, Some("a")), ("c", "44", 2, None), ("d", "1", 40, None)];
+        for (jti, scope, age_days, renews) in attestations {
+            state.storage.attestations.insert(&StoredAttestation {
+                jti: jti.to_string(),
+                proxy_number: format!("+{}00111", scope),
+                scope: scope.to_string(),
+                issued_at: now - chrono::Duration::days(age_days),
+                expires_at: now + chrono::Duration::days(365),
+                renews: renews.map(str::to_string),
+            }).await.unwrap();
+        }
+        state.storage.revocations.revoke(&hesha_types::RevocationEntry {
+            jti: "a".to_string(),
+            revoked_at: now,
+            reason: None,
+        }).await.unwrap();
+        state.metrics.record_verification(true);
+        state.metrics.record_verification(false);
+        
+        let stats = |uri: &str, token| Router::new()
+            .route("/admin/stats", get(api::admin::stats))
+            .route_layer(middleware::from_fn_with_state(state.clone(), auth::require_admin))
+            .with_state(state.clone())
+            .oneshot(admin_request("GET", uri, token, axum::body::Body::empty()));
+        
+        assert_eq!(stats("/admin/stats", None).await.unwrap().status(), 401);
+        assert_eq!(stats("/admin/stats?days=0", Some(ADMIN_KEY)).await.unwrap().status(), 400);
+        let response = stats("/admin/stats?days=7", Some(ADMIN_KEY)).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let stats: IssuanceStats = serde_json::from_slice(&body).unwrap();
+        assert_eq!((stats.issued, stats.renewals, stats.revocations), (3, 1, 1));
+        assert!((stats.renewal_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.per_day.len(), 7);
+        assert_eq!(stats.per_day.last().unwrap().issued, 2);
+        assert_eq!(stats.per_scope["1"], 2);
+        assert_eq!(stats.per_scope["44"], 1);
+        assert_eq!((stats.verification.checks, stats.verification.failures), (2, 1));
+        // Nothing identifies an attestation
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(!body.contains("+100111") && !body.contains("\"a\""));
+    }
+}
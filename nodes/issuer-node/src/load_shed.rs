@@ -0,0 +1,160 @@
+//! Concurrency limit and load shedding.
+//!
+//! At most `max_in_flight` requests are handled at once. A request over the
+//! cap waits up to `queue_timeout_ms` for a slot and is then answered with
+//! `503 Service Unavailable` and `Retry-After`, so an overloaded node fails
+//! fast for some callers instead of slowing down for all of them.
+//! `/health` and the operator-only `/admin/` endpoints are never shed, so
+//! operators can still see what is going on.
+
+use crate::problem::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use hesha_types::ErrorCode;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Default cap on requests handled at once.
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 512;
+
+/// Default wait for a slot before a request is shed.
+pub const DEFAULT_QUEUE_TIMEOUT_MS: u64 = 500;
+
+/// Slots for requests in flight.
+pub struct ConcurrencyLimit {
+    /// None when unlimited.
+    permits: Option<Arc<Semaphore>>,
+    max_in_flight: usize,
+    queue_timeout: Duration,
+}
+
+impl ConcurrencyLimit {
+    /// Allow `max_in_flight` requests at once (0 disables the limit), each
+    /// waiting up to `queue_timeout` for a slot.
+    pub fn new(max_in_flight: usize, queue_timeout: Duration) -> Self {
+        Self {
+            permits: (max_in_flight > 0).then(|| Arc::new(Semaphore::new(max_in_flight))),
+            max_in_flight,
+            queue_timeout,
+        }
+    }
+
+    /// Take a slot, held until the permit is dropped. `Ok(None)` when
+    /// unlimited; `Err` when no slot freed up in time.
+    pub async fn acquire(&self) -> Result<Option<OwnedSemaphorePermit>, Overloaded> {
+        let Some(permits) = &self.permits else {
+            return Ok(None);
+        };
+        if let Ok(permit) = permits.clone().try_acquire_owned() {
+            return Ok(Some(permit));
+        }
+        match tokio::time::timeout(self.queue_timeout, permits.clone().acquire_owned()).await {
+            Ok(Ok(permit)) => Ok(Some(permit)),
+            _ => Err(Overloaded),
+        }
+    }
+
+    /// Requests being handled now.
+    pub fn in_flight(&self) -> usize {
+        self.permits
+            .as_ref()
+            .map_or(0, |permits| self.max_in_flight - permits.available_permits())
+    }
+}
+
+/// No slot freed up within the queue timeout.
+#[derive(Debug)]
+pub struct Overloaded;
+
+/// Middleware holding a [`ConcurrencyLimit`] slot for each request.
+pub async fn limit_concurrency(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    if path == "/health" || path.starts_with("/admin/") {
+        return next.run(request).await;
+    }
+    match state.concurrency.acquire().await {
+        Ok(_permit) => next.run(request).await,
+        Err(Overloaded) => {
+            tracing::warn!(
+                "Shed {} {}: {} requests in flight",
+                request.method(),
+                path,
+                state.concurrency.in_flight()
+            );
+            state.metrics.record_error("overloaded");
+            ApiError::new(ErrorCode::Overloaded, "The issuer is handling too many requests; retry shortly")
+                .with_header(header::RETRY_AFTER, 1)
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use axum::{body::Body, http::StatusCode, middleware, routing::get, Router};
+    use hesha_crypto::generate_keypair;
+    use tower::util::ServiceExt;
+
+    #[tokio::test]
+    async fn test_slots() {
+        let limit = ConcurrencyLimit::new(1, Duration::from_millis(20));
+        let permit = limit.acquire().await.unwrap();
+        assert_eq!(limit.in_flight(), 1);
+        assert!(limit.acquire().await.is_err());
+
+        // A waiting request gets the slot once it frees up
+        let (_, waiting) = tokio::join!(
+            async {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                drop(permit);
+            },
+            limit.acquire()
+        );
+        assert!(waiting.unwrap().is_some());
+        assert_eq!(limit.in_flight(), 0);
+
+        let unlimited = ConcurrencyLimit::new(0, Duration::ZERO);
+        assert!(unlimited.acquire().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sheds_when_full() {
+        let config = Config {
+            max_in_flight: 1,
+            queue_timeout_ms: 0,
+            ..Config::default()
+        };
+        let state = AppState::new(config, generate_keypair().unwrap());
+        let app = Router::new()
+            .route("/.well-known/hesha/pubkey.json", get(|| async { "ok" }))
+            .route("/health", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(state.clone(), limit_concurrency))
+            .with_state(state.clone());
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let response = app.clone().oneshot(get("/.well-known/hesha/pubkey.json")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let _busy = state.concurrency.acquire().await.unwrap();
+        let response = app.clone().oneshot(get("/.well-known/hesha/pubkey.json")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+        assert_eq!(state.metrics.snapshot().errors["overloaded"], 1);
+
+        // Health checks still answer
+        let response = app.oneshot(get("/health")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
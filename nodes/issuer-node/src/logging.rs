@@ -0,0 +1,107 @@
+//! Log output and request correlation.
+//!
+//! Every request gets an ID, taken from an incoming `X-Request-Id` header
+//! (so IDs assigned by a load balancer carry through) or generated. It is
+//! recorded on the request's tracing span, so each log line written while
+//! handling the request carries it, and returned in the `X-Request-Id`
+//! response header for callers to quote when reporting a failure.
+
+use axum::{body::Body, http::Request, Router};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, RequestId, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+/// How log lines are written to stderr.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per line, for log pipelines.
+    Json,
+}
+
+/// Install the global subscriber, filtered by `RUST_LOG`.
+///
+/// Built with the `otel` feature, spans are also exported over OTLP when
+/// the `OTEL_*` environment variables configure an endpoint.
+pub fn init(format: LogFormat) -> anyhow::Result<()> {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| "issuer_node=debug,tower_http=debug,hesha_storage=info".into());
+    let registry = tracing_subscriber::registry().with(filter);
+    #[cfg(feature = "otel")]
+    let registry = registry.with(crate::otel::layer()?);
+    match format {
+        LogFormat::Text => registry.with(tracing_subscriber::fmt::layer()).init(),
+        LogFormat::Json => registry
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .flatten_event(true)
+                    .with_current_span(true)
+                    .with_span_list(false),
+            )
+            .init(),
+    }
+    Ok(())
+}
+
+/// Flush exported spans before the node exits.
+pub async fn shutdown() {
+    #[cfg(feature = "otel")]
+    crate::otel::shutdown().await;
+}
+
+/// Assign request IDs and trace each request in a span carrying its ID.
+pub fn trace_requests<S>(app: Router<S>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    // The last layer added runs first, so the ID is set before the span opens
+    app.layer(PropagateRequestIdLayer::x_request_id())
+        .layer(TraceLayer::new_for_http().make_span_with(request_span))
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+}
+
+fn request_span(request: &Request<Body>) -> tracing::Span {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .unwrap_or_default();
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+    #[cfg(feature = "otel")]
+    crate::otel::set_parent(&span, request.headers());
+    span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use tower::util::ServiceExt;
+
+    #[tokio::test]
+    async fn test_request_ids() {
+        let app = trace_requests(Router::new().route("/health", get(|| async { "ok" })));
+        let request = |id: Option<&str>| {
+            let mut request = Request::builder().uri("/health");
+            if let Some(id) = id {
+                request = request.header("x-request-id", id);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        let response = app.clone().oneshot(request(None)).await.unwrap();
+        let generated = response.headers()["x-request-id"].to_str().unwrap();
+        assert!(uuid::Uuid::parse_str(generated).is_ok());
+
+        let response = app.oneshot(request(Some("lb-1234"))).await.unwrap();
+        assert_eq!(response.headers()["x-request-id"], "lb-1234");
+    }
+}
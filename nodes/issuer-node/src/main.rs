@@ -1,126 +1,153 @@
 //! Hesha Protocol issuer node.
 
-mod api;
-mod config;
-mod config_loader;
-mod state;
-
-use crate::config::Config;
-use crate::state::AppState;
-use axum::{
-    routing::{get, post},
-    Router,
-};
-use hesha_crypto::generate_keypair;
-use std::fs;
-use tower_http::trace::TraceLayer;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+#[cfg(feature = "acme")]
+use issuer_node::acme;
+#[cfg(feature = "tls")]
+use issuer_node::tls;
+use issuer_node::{build_state, config, control, leader, logging, router, scheduler, shutdown_or_drain, Config};
+use std::net::SocketAddr;
+use clap::Parser;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "issuer_node=debug,tower_http=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    // Initialize tracing, then load configuration from file or environment
+    let args = config::NodeArgs::parse();
+    logging::init(args.log_format)?;
+    if args.print_config {
+        let config = Config::resolve(args)?;
+        print!("{}", config.to_toml()?);
+        return config.validate();
+    }
+    let config = Config::load(args)?;
+    
+    // Load keys and storage, and set up verification and the configured checks
+    let state = build_state(config.clone()).await?;
     
-    // Load configuration from file or environment
-    let config = Config::load()?;
+    // Obtain a certificate over ACME before anything is served with it
+    #[cfg(feature = "acme")]
+    let acme = match &config.acme {
+        Some(acme_config) => {
+            let trust_domain = config.trust_domain.as_deref().unwrap_or(&config.domain);
+            let manager = acme::CertificateManager::new(acme_config, trust_domain)?;
+            manager.listen_for_challenges(config.bind_address.port()).await?;
+            manager.ensure_certificate().await?;
+            Some(manager)
+        }
+        None => None,
+    };
+    #[cfg(not(feature = "acme"))]
+    if config.acme.is_some() {
+        anyhow::bail!("[acme] requires issuer-node built with the 'acme' feature");
+    }
     
-    // Generate or load issuer key
-    let issuer_key = if let Some(key_path) = &config.private_key_path {
-        // Load from file
-        let key_data = fs::read_to_string(key_path)?;
-        let private_key = hesha_types::PrivateKey::from_base64(key_data.trim())?;
-        hesha_crypto::keypair_from_private(&private_key)?
-    } else {
-        // Generate new
-        generate_keypair()?
+    // Set up HTTPS
+    #[cfg(feature = "tls")]
+    let tls_config = {
+        let files = config.tls.clone();
+        #[cfg(feature = "acme")]
+        let files = files.or_else(|| acme.as_ref().map(acme::CertificateManager::tls_config));
+        if let Some(files) = &files {
+            tls::record_expiry(files, &state.metrics);
+        }
+        files
+            .as_ref()
+            .map(tls::server_config)
+            .transpose()?
+            .map(|server_config| axum_server::tls_rustls::RustlsConfig::from_config(std::sync::Arc::new(server_config)))
     };
-    tracing::info!(
-        "Issuer public key: {}",
-        issuer_key.public.to_base64()
-    );
+    #[cfg(not(feature = "tls"))]
+    if config.tls.is_some() {
+        anyhow::bail!("[tls] requires issuer-node built with the 'tls' feature");
+    }
+    
+    // Open the local admin console
+    #[cfg(unix)]
+    if let Some(path) = &config.control_socket {
+        control::listen(state.clone(), path)?;
+        tracing::info!("Admin console listening on {}", path.display());
+    }
+    #[cfg(not(unix))]
+    if config.control_socket.is_some() {
+        tracing::warn!("The admin console needs Unix domain sockets; control_socket is ignored");
+    }
+    let drain = state.drain.clone();
+    let leader = state.leader.clone();
+    #[cfg(feature = "acme")]
+    let metrics = state.metrics.clone();
     
-    // Create app state
-    let state = AppState::new(config.clone(), issuer_key);
+    // Take the scheduler lease if no other replica holds it, then start
+    // pruning, retries and statistics
+    if !leader.renew().await {
+        tracing::info!("Replica {} leaves the shared background jobs to another replica", leader.replica_id());
+    }
+    leader::start(leader.clone());
+    if let Err(e) = state.maintenance.refresh().await {
+        tracing::error!("Failed to read maintenance mode: {}", e);
+    }
+    if state.maintenance.is_enabled() {
+        tracing::warn!("Maintenance mode is on; refusing attestation requests until 'hesha admin maintenance off'");
+    }
+    scheduler::start(state.clone());
     
-    // Build router
-    let app = Router::new()
-        .route("/attest", post(api::attest::attest))
-        .route("/.well-known/hesha/pubkey.json", get(api::pubkey::pubkey))
-        .layer(TraceLayer::new_for_http())
-        .with_state(state);
+    let app = router(state)?;
     
     // Start server
     let addr = config.bind_address;
-    tracing::info!("Issuer node listening on {}", addr);
+    let scheme = if config.tls.is_some() || config.acme.is_some() { "https" } else { "http" };
+    tracing::info!("Issuer node listening on {}://{}", scheme, addr);
     tracing::info!("Endpoints:");
-    tracing::info!("  POST   /attest                     - Issue attestation");
+    tracing::info!("  POST   /attest                     - Issue attestation (authenticated if [auth] is set)");
+    tracing::info!("  POST   /attest/preview             - Preview the proxy number for an /attest request");
+    tracing::info!("  POST   /verify/start               - Send phone verification code");
+    tracing::info!("  POST   /verify/check               - Check verification code");
+    tracing::info!("  GET    /verify/callback            - Carrier verification redirect");
+    tracing::info!("  POST   /attest/simple              - Issue attestation for verified session");
     tracing::info!("  GET    /.well-known/hesha/pubkey.json - Public key discovery");
+    tracing::info!("  GET    /health                     - Health check");
+    tracing::info!("  GET    /.well-known/hesha/revocations.json - Signed revocation list");
+    tracing::info!("  GET    /status/{{jti}}               - Signed status of one attestation");
+    if config.oidc_provider.is_some() {
+        tracing::info!("  GET    /.well-known/openid-configuration - OpenID provider metadata");
+        tracing::info!("  GET    /oidc/authorize             - Start sign-in, returning a wallet challenge");
+        tracing::info!("  POST   /oidc/authorize             - Answer the challenge for an authorization code");
+        tracing::info!("  POST   /oidc/token                 - Redeem a code for an ID token");
+    }
+    tracing::info!("  GET    /admin/metrics              - Operator metrics (admin auth)");
+    tracing::info!("  GET    /admin/stats                - Issuance statistics (admin auth)");
+    tracing::info!("  POST   /admin/revoke               - Revoke an attestation (admin auth)");
+    tracing::info!("  GET    /admin/audit                - Export the signed audit log (admin auth)");
+    tracing::info!("  GET    /admin/backup               - Export records for a backup (admin auth)");
+    tracing::info!("  POST   /admin/restore              - Restore records from a backup (admin auth)");
+    
+    #[cfg(feature = "acme")]
+    if let (Some(manager), Some(tls_config)) = (acme, &tls_config) {
+        manager.renew_in_background(tls_config.clone(), metrics);
+    }
+    #[cfg(feature = "tls")]
+    if let Some(tls_config) = tls_config {
+        tls::serve(addr, tls_config, app, drain).await?;
+        remove_control_socket(&config);
+        leader.resign().await;
+        tracing::info!("Issuer node stopped");
+        logging::shutdown().await;
+        return Ok(());
+    }
     
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_or_drain(drain))
+        .await?;
     
+    remove_control_socket(&config);
+    leader.resign().await;
+    tracing::info!("Issuer node stopped");
+    logging::shutdown().await;
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use hesha_crypto::generate_keypair;
-    use hesha_types::PhoneNumber;
-    use tower::util::ServiceExt;
-    
-    #[tokio::test]
-    async fn test_attestation_issuance() {
-        // Start test server
-        let config = Config::default();
-        let issuer_key = generate_keypair().unwrap();
-        let state = AppState::new(config.clone(), issuer_key);
-        
-        let app = Router::new()
-            .route("/attest", post(api::attest::attest))
-            .route("/.well-known/hesha/pubkey.json", get(api::pubkey::pubkey))
-            .with_state(state);
-        
-        // Create test client
-        let client = tower::ServiceBuilder::new()
-            .service(app);
-        
-        // Test attestation request
-        let user_key = generate_keypair().unwrap();
-        let phone = PhoneNumber::new("+1234567890").unwrap();
-        
-        let request = serde_json::json!({
-            "version": "0.1.0-alpha",
-            "phone_number": phone.to_string(),
-            "user_pubkey": user_key.public.to_base64(),
-            "scope": "234",
-        });
-        
-        let response = client
-            .oneshot(
-                axum::http::Request::builder()
-                    .method("POST")
-                    .uri("/attest")
-                    .header("content-type", "application/json")
-                    .body(axum::body::Body::from(serde_json::to_vec(&request).unwrap()))
-                    .unwrap()
-            )
-            .await
-            .unwrap();
-        
-        assert_eq!(response.status(), 200);
-        
-        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
-        let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
-        
-        assert!(result["attestation"].is_string());
-        assert!(result["proxy_number"].is_string());
-        assert!(result["expires_at"].is_number());
+/// Remove the console socket so the CLI doesn't find a dead one.
+fn remove_control_socket(config: &Config) {
+    if let Some(path) = &config.control_socket {
+        let _ = std::fs::remove_file(path);
     }
-}
\ No newline at end of file
+}
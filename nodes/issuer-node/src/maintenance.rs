@@ -0,0 +1,205 @@
+//! Maintenance mode.
+//!
+//! Turned on from the admin console (`hesha admin maintenance on`) before a
+//! database migration or key ceremony. While on, the attest and verify
+//! endpoints answer `503 Service Unavailable` with `Retry-After`, and the
+//! `.well-known` documents, `/health` and the admin endpoints keep working,
+//! so relying parties can still verify attestations.
+//!
+//! The switch is kept in the shared state, so it applies to every replica:
+//! each one re-reads it every few seconds. A switch never turned off lapses
+//! after [`MAX_DURATION`].
+
+use crate::ip_access::RouteGroup;
+use crate::problem::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use hesha_storage::{StateStore, StorageError, StorageResult};
+use hesha_types::{ErrorCode, MaintenanceStatus};
+use std::sync::{Arc, RwLock};
+
+/// Key of the switch in the shared state.
+const MAINTENANCE_KEY: &str = "maintenance";
+
+/// Default wait clients are told to retry after.
+pub const DEFAULT_RETRY_AFTER_SECS: u64 = 300;
+
+/// How long maintenance lasts if nobody turns it off.
+pub const MAX_DURATION: chrono::Duration = chrono::Duration::days(7);
+
+/// Tries at changing the switch while other replicas change it too.
+const MAX_UPDATE_ATTEMPTS: usize = 8;
+
+/// The shared switch and this replica's copy of it.
+pub struct Maintenance {
+    state: Arc<dyn StateStore>,
+    current: RwLock<Option<MaintenanceStatus>>,
+}
+
+impl Maintenance {
+    /// Maintenance off until [`refresh`](Self::refresh) reads the switch.
+    pub fn new(state: Arc<dyn StateStore>) -> Self {
+        Self {
+            state,
+            current: RwLock::new(None),
+        }
+    }
+
+    /// The switch as last read or set by this replica.
+    pub fn status(&self) -> MaintenanceStatus {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .unwrap_or(MaintenanceStatus {
+                enabled: false,
+                reason: None,
+                retry_after_secs: DEFAULT_RETRY_AFTER_SECS,
+                since: None,
+            })
+    }
+
+    /// Whether attestation requests are refused.
+    pub fn is_enabled(&self) -> bool {
+        self.current.read().unwrap_or_else(|e| e.into_inner()).is_some()
+    }
+
+    /// Read the switch from the shared state, returning whether it changed.
+    pub async fn refresh(&self) -> StorageResult<bool> {
+        let stored = self
+            .state
+            .get(MAINTENANCE_KEY)
+            .await?
+            .and_then(|value| serde_json::from_str::<MaintenanceStatus>(&value).ok());
+        let mut current = self.current.write().unwrap_or_else(|e| e.into_inner());
+        let changed = *current != stored;
+        *current = stored;
+        Ok(changed)
+    }
+
+    /// Turn maintenance on, keeping the start time if it already is, or off.
+    pub async fn set(
+        &self,
+        enabled: bool,
+        reason: Option<String>,
+        retry_after_secs: Option<u64>,
+    ) -> StorageResult<MaintenanceStatus> {
+        for _ in 0..MAX_UPDATE_ATTEMPTS {
+            let stored = self.state.get(MAINTENANCE_KEY).await?;
+            let updated = match (&stored, enabled) {
+                (None, false) => true,
+                (Some(stored), false) => self.state.compare_and_remove(MAINTENANCE_KEY, stored).await?,
+                (stored, true) => {
+                    let since = stored
+                        .as_deref()
+                        .and_then(|value| serde_json::from_str::<MaintenanceStatus>(value).ok())
+                        .and_then(|status| status.since)
+                        .unwrap_or_else(Utc::now);
+                    let status = MaintenanceStatus {
+                        enabled: true,
+                        reason: reason.clone(),
+                        retry_after_secs: retry_after_secs.unwrap_or(DEFAULT_RETRY_AFTER_SECS),
+                        since: Some(since),
+                    };
+                    let value = serde_json::to_string(&status).unwrap_or_default();
+                    self.state
+                        .compare_and_swap(MAINTENANCE_KEY, stored.as_deref(), &value, since + MAX_DURATION)
+                        .await?
+                }
+            };
+            if updated {
+                self.refresh().await?;
+                return Ok(self.status());
+            }
+        }
+        Err(StorageError::Conflict(MAINTENANCE_KEY.to_string()))
+    }
+}
+
+/// Middleware refusing attest and verify requests during maintenance.
+pub async fn refuse_during_maintenance(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !state.maintenance.is_enabled() || RouteGroup::of(request.uri().path()) != Some(RouteGroup::Attest) {
+        return next.run(request).await;
+    }
+    let retry_after = state.maintenance.status().retry_after_secs;
+    state.metrics.record_error("maintenance");
+    ApiError::new(ErrorCode::Maintenance, "The issuer is down for maintenance; retry later")
+        .with_header(header::RETRY_AFTER, retry_after)
+        .with_extension("retry_after", retry_after)
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use axum::{body::Body, http::StatusCode, middleware, routing::get, Router};
+    use hesha_crypto::generate_keypair;
+    use hesha_storage::Storage;
+    use tower::util::ServiceExt;
+
+    #[tokio::test]
+    async fn test_switch_is_shared() {
+        let storage = Storage::memory();
+        let one = Maintenance::new(storage.state.clone());
+        let other = Maintenance::new(storage.state.clone());
+
+        let status = one.set(true, Some("migration".to_string()), Some(60)).await.unwrap();
+        assert!(status.enabled && one.is_enabled());
+        assert_eq!(status.retry_after_secs, 60);
+        let since = status.since.unwrap();
+
+        // Another replica sees the switch at its next refresh
+        assert!(!other.is_enabled());
+        assert!(other.refresh().await.unwrap());
+        assert_eq!(other.status(), status);
+        assert!(!other.refresh().await.unwrap());
+
+        // Changing the reason keeps the start time
+        let status = other.set(true, None, None).await.unwrap();
+        assert_eq!((status.since, status.retry_after_secs), (Some(since), DEFAULT_RETRY_AFTER_SECS));
+
+        assert!(!other.set(false, None, None).await.unwrap().enabled);
+        assert!(one.refresh().await.unwrap());
+        assert!(!one.is_enabled());
+        assert!(!one.set(false, None, None).await.unwrap().enabled);
+    }
+
+    #[tokio::test]
+    async fn test_refuses_attest_routes() {
+        let state = AppState::new(Config::default(), generate_keypair().unwrap());
+        let app = Router::new()
+            .route("/attest", get(|| async { "ok" }))
+            .route("/.well-known/hesha/pubkey.json", get(|| async { "ok" }))
+            .route("/health", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(state.clone(), refuse_during_maintenance))
+            .with_state(state.clone());
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let response = app.clone().oneshot(get("/attest")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        state.maintenance.set(true, None, Some(120)).await.unwrap();
+        let response = app.clone().oneshot(get("/attest")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "120");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!((body["error"].as_str(), body["retry_after"].as_u64()), (Some("maintenance"), Some(120)));
+
+        for uri in ["/.well-known/hesha/pubkey.json", "/health"] {
+            let response = app.clone().oneshot(get(uri)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! MessageBird Verify provider for phone verification.
+//!
+//! MessageBird generates, sends and checks the codes. Checks are made
+//! against the MessageBird verify ID, so the verifier remembers the latest
+//! one for each phone number until it is verified or expires.
+//!
+//! Many countries only deliver SMS from registered local sender IDs, so
+//! the sender can be chosen by calling-code prefix.
+
+use crate::verification::{Challenge, ContactVerifier, SESSION_TTL};
+use async_trait::async_trait;
+use hesha_types::PhoneNumber;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// MessageBird Verify API URL.
+const MESSAGEBIRD_VERIFY_URL: &str = "https://rest.messagebird.com/verify";
+
+/// Verifier backed by MessageBird Verify.
+pub struct MessageBirdVerifier {
+    client: reqwest::Client,
+    base_url: String,
+    access_key: String,
+    originator: String,
+    regional_originators: BTreeMap<String, String>,
+    /// Pending MessageBird verify IDs, by phone number.
+    requests: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+/// Verify object returned by MessageBird.
+#[derive(Debug, Deserialize)]
+struct Verify {
+    id: String,
+    status: String,
+}
+
+impl MessageBirdVerifier {
+    /// Create a verifier sending from `originator`, or from the sender ID
+    /// for the longest matching calling-code prefix in
+    /// `regional_originators`.
+    pub fn new(
+        access_key: String,
+        originator: String,
+        regional_originators: BTreeMap<String, String>,
+    ) -> Self {
+        Self::with_base_url(MESSAGEBIRD_VERIFY_URL, access_key, originator, regional_originators)
+    }
+
+    /// Create a verifier against a different API endpoint.
+    pub fn with_base_url(
+        base_url: &str,
+        access_key: String,
+        originator: String,
+        regional_originators: BTreeMap<String, String>,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            access_key,
+            originator,
+            regional_originators,
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sender ID for a number given as E.164 digits.
+    fn originator_for(&self, digits: &str) -> &str {
+        self.regional_originators
+            .iter()
+            .filter(|(prefix, _)| digits.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(&self.originator, |(_, originator)| originator)
+    }
+
+    fn authorization(&self) -> String {
+        format!("AccessKey {}", self.access_key)
+    }
+}
+
+#[async_trait]
+impl ContactVerifier for MessageBirdVerifier {
+    async fn start(&self, phone_number: &PhoneNumber, _session_id: &str) -> anyhow::Result<Challenge> {
+        let recipient = phone_number.to_string().trim_start_matches('+').to_string();
+        let response = self.client
+            .post(&self.base_url)
+            .header(reqwest::header::AUTHORIZATION, self.authorization())
+            .json(&json!({
+                "recipient": recipient,
+                "originator": self.originator_for(&recipient),
+                "type": "sms",
+                "timeout": SESSION_TTL.as_secs(),
+            }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("MessageBird returned {} starting verification", response.status());
+        }
+        let verify: Verify = response.json().await?;
+
+        let mut requests = self.requests.lock().unwrap_or_else(|e| e.into_inner());
+        requests.retain(|_, (_, sent_at)| sent_at.elapsed() < SESSION_TTL);
+        requests.insert(phone_number.to_string(), (verify.id, Instant::now()));
+        Ok(Challenge::Code)
+    }
+
+    async fn check_code(&self, phone_number: &PhoneNumber, code: &str) -> anyhow::Result<bool> {
+        let verify_id = self.requests.lock().unwrap_or_else(|e| e.into_inner())
+            .get(&phone_number.to_string())
+            .map(|(verify_id, _)| verify_id.clone());
+        let Some(verify_id) = verify_id else {
+            return Ok(false);
+        };
+
+        let response = self.client
+            .get(format!("{}/{}", self.base_url, verify_id))
+            .header(reqwest::header::AUTHORIZATION, self.authorization())
+            .query(&[("token", code)])
+            .send()
+            .await?;
+        match response.status().as_u16() {
+            200 => {
+                let verify: Verify = response.json().await?;
+                let verified = verify.status == "verified";
+                if verified {
+                    self.requests.lock().unwrap_or_else(|e| e.into_inner())
+                        .remove(&phone_number.to_string());
+                }
+                Ok(verified)
+            }
+            // Wrong code, or a verify object that has expired
+            404 | 422 => Ok(false),
+            status => anyhow::bail!("MessageBird returned {} checking verification", status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn verifier(base_url: &str) -> MessageBirdVerifier {
+        MessageBirdVerifier::with_base_url(
+            base_url,
+            "live_key".to_string(),
+            "Acme".to_string(),
+            BTreeMap::from([
+                ("2".to_string(), "AcmeAfrica".to_string()),
+                ("234".to_string(), "AcmeNG".to_string()),
+            ]),
+        )
+    }
+
+    #[test]
+    fn test_regional_originator() {
+        let verifier = verifier("http://localhost");
+        assert_eq!(verifier.originator_for("2348012345678"), "AcmeNG");
+        assert_eq!(verifier.originator_for("27821234567"), "AcmeAfrica");
+        assert_eq!(verifier.originator_for("447700900123"), "Acme");
+    }
+
+    #[tokio::test]
+    async fn test_send_and_check() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/"))
+            .and(header("authorization", "AccessKey live_key"))
+            .and(body_partial_json(json!({ "recipient": "2348012345678", "originator": "AcmeNG" })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "v-1",
+                "status": "sent"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v-1"))
+            .and(query_param("token", "424242"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "v-1",
+                "status": "verified"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v-1"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+                "errors": [{ "code": 10, "description": "The token is invalid." }]
+            })))
+            .mount(&server)
+            .await;
+
+        let verifier = verifier(&server.uri());
+        let phone = PhoneNumber::new("+2348012345678").unwrap();
+        verifier.start(&phone, "session").await.unwrap();
+        assert!(!verifier.check_code(&phone, "000000").await.unwrap());
+        assert!(verifier.check_code(&phone, "424242").await.unwrap());
+    }
+}
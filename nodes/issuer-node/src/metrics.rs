@@ -0,0 +1,142 @@
+//! In-memory counters for the operator dashboard.
+//!
+//! The attest handler records each issued attestation and each failed
+//! request here, the verify handlers each checked code, and the scheduler
+//! each background job run, and the TLS setup each loaded certificate;
+//! `/admin/metrics` serves a snapshot. Nothing is persisted,
+//! so counts restart with the node.
+
+use chrono::{DateTime, Utc};
+use hesha_types::{ExpiryStats, JobStats, NodeMetrics, RecentAttestation};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How many recent attestations are kept.
+const RECENT_CAPACITY: usize = 50;
+
+/// Counters shared by the request handlers.
+pub struct Metrics {
+    started_at: DateTime<Utc>,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    attestations_issued: u64,
+    proxy_collisions: u64,
+    errors: BTreeMap<String, u64>,
+    verification_checks: u64,
+    verification_failures: u64,
+    recent: VecDeque<RecentAttestation>,
+    attestation_expiry: Option<ExpiryStats>,
+    certificate_expires_at: Option<DateTime<Utc>>,
+    jobs: BTreeMap<String, JobStats>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            started_at: Utc::now(),
+            inner: Mutex::default(),
+        }
+    }
+}
+
+impl Metrics {
+    /// Record an issued attestation.
+    pub fn record_issued(&self, attestation: RecentAttestation) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.attestations_issued += 1;
+        if inner.recent.len() == RECENT_CAPACITY {
+            inner.recent.pop_back();
+        }
+        inner.recent.push_front(attestation);
+    }
+
+    /// Record a generated proxy number that was already assigned.
+    pub fn record_proxy_collision(&self) {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).proxy_collisions += 1;
+    }
+
+    /// Record a failed request by its error code.
+    pub fn record_error(&self, code: &str) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        *inner.errors.entry(code.to_string()).or_default() += 1;
+    }
+
+    /// Record a checked verification code or carrier result.
+    pub fn record_verification(&self, verified: bool) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.verification_checks += 1;
+        inner.verification_failures += u64::from(!verified);
+    }
+
+    /// Record the latest attestation expiry counts.
+    pub fn record_expiry(&self, stats: ExpiryStats) {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).attestation_expiry = Some(stats);
+    }
+
+    /// Record the expiry of the certificate now served over HTTPS.
+    pub fn record_certificate_expiry(&self, expires_at: DateTime<Utc>) {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).certificate_expires_at = Some(expires_at);
+    }
+
+    /// Record a finished run of a background job.
+    pub fn record_job(&self, name: &str, duration: Duration, error: Option<String>) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let job = inner.jobs.entry(name.to_string()).or_default();
+        job.runs += 1;
+        job.failures += u64::from(error.is_some());
+        job.last_run_at = Some(Utc::now());
+        job.last_duration_ms = duration.as_millis().try_into().unwrap_or(u64::MAX);
+        job.last_error = error;
+    }
+
+    /// Current values.
+    pub fn snapshot(&self) -> NodeMetrics {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        NodeMetrics {
+            started_at: self.started_at,
+            attestations_issued: inner.attestations_issued,
+            proxy_collisions: inner.proxy_collisions,
+            errors: inner.errors.clone(),
+            verification_checks: inner.verification_checks,
+            verification_failures: inner.verification_failures,
+            recent_attestations: inner.recent.iter().cloned().collect(),
+            attestation_expiry: inner.attestation_expiry.clone(),
+            certificate_expires_at: inner.certificate_expires_at,
+            jobs: inner.jobs.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attestation(jti: &str) -> RecentAttestation {
+        RecentAttestation {
+            jti: jti.to_string(),
+            proxy_number: "+99012345678".to_string(),
+            issued_at: Utc::now(),
+            expires_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_recent_attestations_are_bounded() {
+        let metrics = Metrics::default();
+        for i in 0..RECENT_CAPACITY + 5 {
+            metrics.record_issued(attestation(&i.to_string()));
+        }
+        metrics.record_error("invalid_version");
+        metrics.record_error("invalid_version");
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.attestations_issued, (RECENT_CAPACITY + 5) as u64);
+        assert_eq!(snapshot.recent_attestations.len(), RECENT_CAPACITY);
+        assert_eq!(snapshot.recent_attestations[0].jti, (RECENT_CAPACITY + 4).to_string());
+        assert_eq!(snapshot.errors["invalid_version"], 2);
+    }
+}
@@ -0,0 +1,146 @@
+//! Bearer token validation against an OpenID Connect provider.
+//!
+//! The provider's signing keys are fetched from its JWKS URL and cached.
+//! A token signed with a key not in the cache triggers a refetch, so key
+//! rotation at the provider is picked up without a restart.
+
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// How long fetched keys are used before refetching.
+const JWKS_TTL: Duration = Duration::from_secs(3600);
+
+/// Minimum time between fetches prompted by unknown key IDs.
+const MIN_REFETCH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Validates tokens issued by one provider for one audience.
+pub struct OidcValidator {
+    client: reqwest::Client,
+    issuer: String,
+    audience: String,
+    jwks_url: String,
+    keys: RwLock<Option<(JwkSet, Instant)>>,
+}
+
+/// Claims read from a validated token.
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: String,
+}
+
+impl OidcValidator {
+    /// Create a validator; keys are fetched on first use.
+    pub fn new(issuer: String, audience: String, jwks_url: String) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            issuer,
+            audience,
+            jwks_url,
+            keys: RwLock::new(None),
+        }
+    }
+
+    /// Check a token's signature, issuer, audience and expiry, returning
+    /// its subject.
+    pub async fn validate(&self, token: &str) -> anyhow::Result<String> {
+        let header = decode_header(token)?;
+        // Shared-secret algorithms would let anyone holding the public JWKS forge tokens
+        if matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+            anyhow::bail!("Unsupported token algorithm {:?}", header.alg);
+        }
+        let kid = header.kid.ok_or_else(|| anyhow::anyhow!("Token has no key ID"))?;
+        let key = self.decoding_key(&kid).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        Ok(decode::<Claims>(token, &key, &validation)?.claims.sub)
+    }
+
+    /// Look up a signing key, refetching the JWKS if it is stale or lacks the key.
+    async fn decoding_key(&self, kid: &str) -> anyhow::Result<DecodingKey> {
+        let refetch = {
+            let keys = self.keys.read().await;
+            match &*keys {
+                Some((set, fetched_at)) if fetched_at.elapsed() < JWKS_TTL => match set.find(kid) {
+                    Some(jwk) => return Ok(DecodingKey::from_jwk(jwk)?),
+                    None => fetched_at.elapsed() >= MIN_REFETCH_INTERVAL,
+                },
+                _ => true,
+            }
+        };
+        if !refetch {
+            anyhow::bail!("Unknown signing key {}", kid);
+        }
+
+        let set: JwkSet = self.client
+            .get(&self.jwks_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let key = set.find(kid).map(DecodingKey::from_jwk).transpose()?;
+        *self.keys.write().await = Some((set, Instant::now()));
+        key.ok_or_else(|| anyhow::anyhow!("Unknown signing key {}", kid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use hesha_crypto::{generate_keypair, sign_message};
+    use hesha_types::KeyPair;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn token(key: &KeyPair, kid: &str, claims: serde_json::Value) -> String {
+        let header = URL_SAFE_NO_PAD.encode(json!({ "alg": "EdDSA", "typ": "JWT", "kid": kid }).to_string());
+        let claims = URL_SAFE_NO_PAD.encode(claims.to_string());
+        let message = format!("{}.{}", header, claims);
+        let signature = sign_message(&key.private, message.as_bytes()).unwrap();
+        format!("{}.{}", message, URL_SAFE_NO_PAD.encode(signature.as_bytes()))
+    }
+
+    #[tokio::test]
+    async fn test_validate() {
+        let key = generate_keypair().unwrap();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/jwks.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "keys": [{ "kty": "OKP", "crv": "Ed25519", "kid": "k1", "x": key.public.to_base64() }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let validator = OidcValidator::new(
+            "https://login.example.com/".to_string(),
+            "hesha-issuer".to_string(),
+            format!("{}/jwks.json", server.uri()),
+        );
+        let exp = chrono::Utc::now().timestamp() + 300;
+        let claims = |aud: &str| json!({
+            "iss": "https://login.example.com/",
+            "aud": aud,
+            "sub": "billing-service",
+            "exp": exp,
+        });
+
+        let subject = validator.validate(&token(&key, "k1", claims("hesha-issuer"))).await.unwrap();
+        assert_eq!(subject, "billing-service");
+        assert!(validator.validate(&token(&key, "k1", claims("other"))).await.is_err());
+        // Unknown key IDs don't refetch more than once a minute
+        assert!(validator.validate(&token(&key, "k2", claims("hesha-issuer"))).await.is_err());
+        let forged = token(&generate_keypair().unwrap(), "k1", claims("hesha-issuer"));
+        assert!(validator.validate(&forged).await.is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! The node as an OpenID provider for proxy numbers.
+//!
+//! With `[oidc_provider]` configured, relying parties that only speak
+//! OpenID Connect can sign users in with their proxy number, using the
+//! authorization code flow:
+//!
+//! 1. The relying party sends the user to `GET /oidc/authorize` with its
+//!    client ID, a registered redirect URI, `state` and `nonce`. The node
+//!    answers with a challenge for the user's wallet.
+//! 2. The wallet posts an attestation from this issuer and its answer to
+//!    `POST /oidc/authorize`, and is given the redirect URI with an
+//!    authorization code.
+//! 3. The relying party redeems the code at `/oidc/token` with its client
+//!    secret for an ID token carrying the proxy number and attestation
+//!    (see [`hesha_core::oidc`]).
+//!
+//! Pending requests and codes are kept in the shared state, so each step
+//! may reach a different replica. Both are single use.
+
+use crate::config::Config;
+use hesha_crypto::{constant_time_compare, generate_hex_nonce};
+use hesha_storage::{StateStore, StorageError};
+use hesha_types::{Challenge, OidcProviderConfig};
+use chrono::{DateTime, Duration, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// How long the wallet has to answer an authorization request.
+pub const REQUEST_TTL_SECS: i64 = hesha_core::verification::CHALLENGE_MAX_AGE_SECS;
+
+/// How long the relying party has to redeem an authorization code.
+pub const CODE_TTL_SECS: i64 = 60;
+
+/// State key prefix of pending authorization requests.
+const REQUEST_PREFIX: &str = "oidc-request:";
+
+/// State key prefix of authorization codes.
+const CODE_PREFIX: &str = "oidc-code:";
+
+/// A relying party, with its secret.
+pub struct RegisteredClient {
+    /// Secret the relying party authenticates to `/oidc/token` with.
+    pub secret: Vec<u8>,
+    /// Where authorization codes may be sent.
+    pub redirect_uris: Vec<String>,
+}
+
+/// An authorization request waiting for the wallet's answer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingAuthorization {
+    /// Relying party asking.
+    pub client_id: String,
+    /// Where to send the code.
+    pub redirect_uri: String,
+    /// Opaque value to hand back to the relying party.
+    pub state: Option<String>,
+    /// Nonce to put in the ID token.
+    pub nonce: Option<String>,
+    /// Challenge the wallet must answer.
+    pub challenge: Challenge,
+}
+
+/// What an authorization code stands for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeGrant {
+    /// Relying party the code was issued to.
+    pub client_id: String,
+    /// Redirect URI it was sent to, which the token request must repeat.
+    pub redirect_uri: String,
+    /// Nonce to put in the ID token.
+    pub nonce: Option<String>,
+    /// Attestation the wallet answered with.
+    pub attestation: String,
+    /// When the wallet answered.
+    pub auth_time: DateTime<Utc>,
+}
+
+/// Registered relying parties and the requests and codes in flight.
+pub struct OpenIdProvider {
+    clients: HashMap<String, RegisteredClient>,
+    id_token_ttl: Duration,
+    state: Arc<dyn StateStore>,
+}
+
+impl OpenIdProvider {
+    /// Relying parties from issuer.toml, with secrets read from the
+    /// environment.
+    pub fn from_config(config: &OidcProviderConfig, state: Arc<dyn StateStore>) -> anyhow::Result<Self> {
+        anyhow::ensure!(!config.clients.is_empty(), "[oidc_provider] lists no clients");
+        let clients = config
+            .clients
+            .iter()
+            .map(|client| {
+                let secret = std::env::var(&client.secret_env).map_err(|_| {
+                    anyhow::anyhow!("{} must be set for OIDC client {}", client.secret_env, client.client_id)
+                })?;
+                let registered = RegisteredClient {
+                    secret: secret.into_bytes(),
+                    redirect_uris: client.redirect_uris.clone(),
+                };
+                Ok((client.client_id.clone(), registered))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self::new(clients, config.id_token_ttl_secs, state))
+    }
+
+    /// Relying parties by client ID.
+    pub fn new(clients: HashMap<String, RegisteredClient>, id_token_ttl_secs: u64, state: Arc<dyn StateStore>) -> Self {
+        Self {
+            clients,
+            id_token_ttl: Duration::seconds(id_token_ttl_secs as i64),
+            state,
+        }
+    }
+
+    /// Number of relying parties.
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Whether there are no relying parties.
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// How long ID tokens are valid.
+    pub fn id_token_ttl(&self) -> Duration {
+        self.id_token_ttl
+    }
+
+    /// Whether `client_id` is registered with `redirect_uri`.
+    pub fn allows_redirect(&self, client_id: &str, redirect_uri: &str) -> bool {
+        self.clients
+            .get(client_id)
+            .is_some_and(|client| client.redirect_uris.iter().any(|uri| uri == redirect_uri))
+    }
+
+    /// Whether `secret` is the secret of `client_id`.
+    pub fn authenticate(&self, client_id: &str, secret: &str) -> bool {
+        self.clients
+            .get(client_id)
+            .is_some_and(|client| constant_time_compare(&client.secret, secret.as_bytes()))
+    }
+
+    /// Record an authorization request, returning its ID.
+    pub async fn start(&self, pending: &PendingAuthorization) -> Result<String, StorageError> {
+        let expires_at = pending.challenge.timestamp + Duration::seconds(REQUEST_TTL_SECS);
+        self.put(REQUEST_PREFIX, pending, expires_at).await
+    }
+
+    /// Use up an authorization request.
+    pub async fn take_request(&self, request_id: &str) -> Result<Option<PendingAuthorization>, StorageError> {
+        self.take(&format!("{}{}", REQUEST_PREFIX, request_id)).await
+    }
+
+    /// Issue an authorization code for `grant`.
+    pub async fn issue_code(&self, grant: &CodeGrant) -> Result<String, StorageError> {
+        self.put(CODE_PREFIX, grant, Utc::now() + Duration::seconds(CODE_TTL_SECS)).await
+    }
+
+    /// Use up an authorization code.
+    pub async fn redeem_code(&self, code: &str) -> Result<Option<CodeGrant>, StorageError> {
+        self.take(&format!("{}{}", CODE_PREFIX, code)).await
+    }
+
+    async fn put<T: Serialize>(
+        &self,
+        prefix: &str,
+        value: &T,
+        expires_at: DateTime<Utc>,
+    ) -> Result<String, StorageError> {
+        let id = generate_hex_nonce();
+        let value = serde_json::to_string(value).map_err(|e| StorageError::Corrupt(e.to_string()))?;
+        if !self.state.compare_and_swap(&format!("{}{}", prefix, id), None, &value, expires_at).await? {
+            // Only a repeated random ID could already be there
+            return Err(StorageError::Conflict(id));
+        }
+        Ok(id)
+    }
+
+    async fn take<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, StorageError> {
+        let Some(current) = self.state.get(key).await? else {
+            return Ok(None);
+        };
+        // Whoever removes the value owns it; a concurrent taker gets nothing
+        if !self.state.compare_and_remove(key, &current).await? {
+            return Ok(None);
+        }
+        serde_json::from_str(&current).map(Some).map_err(|e| StorageError::Corrupt(e.to_string()))
+    }
+}
+
+/// The provider's issuer identifier: the service URL, or the domain over
+/// HTTPS.
+pub fn issuer_url(config: &Config) -> String {
+    match &config.service_url {
+        Some(service_url) => service_url.trim_end_matches('/').to_string(),
+        None => format!("https://{}", config.domain),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider() -> OpenIdProvider {
+        let client = RegisteredClient {
+            secret: b"s3cret".to_vec(),
+            redirect_uris: vec!["https://rp.example.com/cb".to_string()],
+        };
+        OpenIdProvider::new(
+            HashMap::from([("rp".to_string(), client)]),
+            300,
+            Arc::new(hesha_storage::MemoryStore::new()),
+        )
+    }
+
+    #[test]
+    fn test_clients() {
+        let provider = provider();
+        assert!(provider.allows_redirect("rp", "https://rp.example.com/cb"));
+        assert!(!provider.allows_redirect("rp", "https://rp.example.com/cb/../evil"));
+        assert!(!provider.allows_redirect("other", "https://rp.example.com/cb"));
+        assert!(provider.authenticate("rp", "s3cret"));
+        assert!(!provider.authenticate("rp", "wrong"));
+        assert!(!provider.authenticate("other", "s3cret"));
+    }
+
+    #[tokio::test]
+    async fn test_codes_are_single_use() {
+        let provider = provider();
+        let grant = CodeGrant {
+            client_id: "rp".to_string(),
+            redirect_uri: "https://rp.example.com/cb".to_string(),
+            nonce: None,
+            attestation: "jwt".to_string(),
+            auth_time: Utc::now(),
+        };
+        let code = provider.issue_code(&grant).await.unwrap();
+        assert_eq!(provider.redeem_code(&code).await.unwrap().unwrap().attestation, "jwt");
+        assert!(provider.redeem_code(&code).await.unwrap().is_none());
+        assert!(provider.redeem_code("unknown").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,134 @@
+//! Trace export over OTLP.
+//!
+//! Spans (requests, proxy generation, signing, storage calls) are sent to
+//! an OpenTelemetry collector, Jaeger or Tempo when an OTLP endpoint is
+//! configured through the standard environment variables:
+//!
+//! - `OTEL_EXPORTER_OTLP_ENDPOINT` or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`
+//! - `OTEL_EXPORTER_OTLP_PROTOCOL` (or `..._TRACES_PROTOCOL`): `http/protobuf`
+//!   (the default) or `grpc`
+//! - `OTEL_EXPORTER_OTLP_HEADERS`, `OTEL_EXPORTER_OTLP_TIMEOUT`
+//! - `OTEL_SERVICE_NAME` and `OTEL_RESOURCE_ATTRIBUTES`
+//! - `OTEL_TRACES_SAMPLER` and `OTEL_TRACES_SAMPLER_ARG`
+//! - `OTEL_TRACES_EXPORTER=none` or `OTEL_SDK_DISABLED=true` to turn export off
+//!
+//! A `traceparent` header on an incoming request makes its span a child of
+//! the caller's trace.
+
+use axum::http::HeaderMap;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::resource::{EnvResourceDetector, TelemetryResourceDetector};
+use opentelemetry_sdk::{runtime, trace, Resource};
+use std::env;
+use std::time::Duration;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Service name reported when `OTEL_SERVICE_NAME` is not set.
+const SERVICE_NAME: &str = "hesha-issuer-node";
+
+/// The layer exporting spans, if the environment configures an exporter.
+pub fn layer<S>() -> anyhow::Result<Option<OpenTelemetryLayer<S, trace::Tracer>>>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    if !enabled() {
+        return Ok(None);
+    }
+    let protocol = env::var("OTEL_EXPORTER_OTLP_TRACES_PROTOCOL")
+        .or_else(|_| env::var("OTEL_EXPORTER_OTLP_PROTOCOL"))
+        .unwrap_or_else(|_| "http/protobuf".to_string());
+    let exporter: opentelemetry_otlp::SpanExporterBuilder = match protocol.as_str() {
+        "grpc" => opentelemetry_otlp::new_exporter().tonic().into(),
+        "http/protobuf" => opentelemetry_otlp::new_exporter().http().into(),
+        other => anyhow::bail!(
+            "Unsupported OTLP protocol '{}'; use 'http/protobuf' or 'grpc'",
+            other
+        ),
+    };
+
+    // The exporters read the endpoint, headers and timeout, and the SDK the
+    // sampler, from the environment
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter)
+        .with_trace_config(trace::config().with_resource(resource()))
+        .install_batch(runtime::Tokio)?;
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+}
+
+/// Whether the environment asks for OTLP trace export.
+fn enabled() -> bool {
+    let set = |name: &str| env::var(name).is_ok_and(|value| !value.is_empty());
+    if env::var("OTEL_SDK_DISABLED").is_ok_and(|value| value.eq_ignore_ascii_case("true")) {
+        return false;
+    }
+    match env::var("OTEL_TRACES_EXPORTER") {
+        Ok(exporter) if !exporter.is_empty() => exporter == "otlp",
+        _ => set("OTEL_EXPORTER_OTLP_ENDPOINT") || set("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT"),
+    }
+}
+
+/// The node's name and version, overridden by `OTEL_RESOURCE_ATTRIBUTES`
+/// and `OTEL_SERVICE_NAME`.
+fn resource() -> Resource {
+    let detected = Resource::from_detectors(
+        Duration::from_secs(0),
+        vec![Box::new(TelemetryResourceDetector), Box::new(EnvResourceDetector::new())],
+    );
+    let mut resource = Resource::new([
+        KeyValue::new("service.name", SERVICE_NAME),
+        KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+    ])
+    .merge(&detected);
+    if let Ok(name) = env::var("OTEL_SERVICE_NAME") {
+        if !name.is_empty() {
+            resource = resource.merge(&Resource::new([KeyValue::new("service.name", name)]));
+        }
+    }
+    resource
+}
+
+/// Make `span` a child of the trace named in a `traceparent` header.
+pub fn set_parent(span: &tracing::Span, headers: &HeaderMap) {
+    let context = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    });
+    span.set_parent(context);
+}
+
+/// Send spans still waiting in the batch before the node exits.
+pub async fn shutdown() {
+    let _ = tokio::task::spawn_blocking(opentelemetry::global::shutdown_tracer_provider).await;
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_names_the_node() {
+        let resource = resource();
+        let name = resource.get(opentelemetry::Key::new("service.name")).unwrap();
+        // Unless the test environment names the service itself
+        if env::var("OTEL_SERVICE_NAME").is_err() {
+            assert_eq!(name.as_str(), SERVICE_NAME);
+        }
+        assert!(resource.get(opentelemetry::Key::new("service.version")).is_some());
+    }
+}
@@ -0,0 +1,145 @@
+//! Error responses as RFC 7807 problem documents.
+//!
+//! Every refusal the node sends is an [`ApiError`]: a [`Problem`] with the
+//! catalogue's type, title and status for its [`ErrorCode`], served as
+//! `application/problem+json`. [`add_request_id`] fills in the request ID
+//! on the way out, so handlers don't need to know it.
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use hesha_types::{ErrorCode, Problem, PROBLEM_CONTENT_TYPE};
+use serde::Serialize;
+use tower_http::request_id::RequestId;
+
+/// A refused request.
+#[derive(Debug)]
+pub struct ApiError {
+    code: ErrorCode,
+    problem: Box<Problem>,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl ApiError {
+    /// A refusal with `code`, explained by `detail`.
+    pub fn new(code: ErrorCode, detail: impl Into<String>) -> Self {
+        Self {
+            code,
+            problem: Box::new(Problem::new(code, detail)),
+            headers: Vec::new(),
+        }
+    }
+
+    /// The refusal's code.
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    /// The status the refusal is sent with.
+    pub fn status(&self) -> StatusCode {
+        StatusCode::from_u16(self.problem.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    /// Send a header with the refusal, such as `Retry-After`.
+    pub fn with_header(mut self, name: HeaderName, value: impl ToString) -> Self {
+        if let Ok(value) = HeaderValue::from_str(&value.to_string()) {
+            self.headers.push((name, value));
+        }
+        self
+    }
+
+    /// Add a member particular to this refusal.
+    pub fn with_extension(mut self, name: &str, value: impl Serialize) -> Self {
+        self.problem = Box::new(self.problem.with_extension(name, value));
+        self
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut response = render(&self.problem);
+        *response.status_mut() = self.status();
+        response.headers_mut().extend(self.headers);
+        // Kept so add_request_id can render it again with the request ID
+        response.extensions_mut().insert(*self.problem);
+        response
+    }
+}
+
+/// A problem document as a response body.
+fn render(problem: &Problem) -> Response {
+    (
+        [(header::CONTENT_TYPE, PROBLEM_CONTENT_TYPE)],
+        serde_json::to_vec(problem).unwrap_or_default(),
+    )
+        .into_response()
+}
+
+/// Middleware adding the request's ID to problem documents.
+pub async fn add_request_id(request: Request, next: Next) -> Response {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .map(str::to_string);
+    let mut response = next.run(request).await;
+    let (Some(request_id), Some(problem)) = (request_id, response.extensions_mut().get_mut::<Problem>()) else {
+        return response;
+    };
+    problem.request_id = Some(request_id);
+    let body = serde_json::to_vec(problem).unwrap_or_default();
+    response.headers_mut().remove(header::CONTENT_LENGTH);
+    *response.body_mut() = Body::from(body);
+    response
+}
+
+/// Fallback for requests no route matches.
+pub async fn not_found() -> ApiError {
+    ApiError::new(ErrorCode::NotFound, "No such endpoint")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::trace_requests;
+    use axum::{middleware, routing::get, Router};
+    use tower::util::ServiceExt;
+
+    #[tokio::test]
+    async fn test_problem_responses() {
+        let app = trace_requests(
+            Router::new()
+                .route(
+                    "/busy",
+                    get(|| async {
+                        ApiError::new(ErrorCode::Overloaded, "Busy").with_header(header::RETRY_AFTER, 1)
+                    }),
+                )
+                .fallback(not_found)
+                .layer(middleware::from_fn(add_request_id)),
+        );
+        let request = |uri: &str| {
+            Request::builder().uri(uri).header("x-request-id", "lb-1234").body(Body::empty()).unwrap()
+        };
+
+        let response = app.clone().oneshot(request("/busy")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], PROBLEM_CONTENT_TYPE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let problem: Problem = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem.code(), Some(ErrorCode::Overloaded));
+        assert_eq!((problem.status, problem.detail.as_deref()), (503, Some("Busy")));
+        assert_eq!(problem.request_id.as_deref(), Some("lb-1234"));
+
+        let response = app.oneshot(request("/nowhere")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let problem: Problem = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem.error, "not_found");
+    }
+}
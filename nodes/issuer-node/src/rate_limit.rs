@@ -0,0 +1,320 @@
+//! Rate limiting for attestation requests.
+//!
+//! Each source IP and each phone number gets a token bucket that refills
+//! at the configured rate. Requests over the limit are answered with
+//! `429 Too Many Requests` and a `Retry-After` header, which slows down
+//! both proxy-number enumeration and bulk issuance for one phone.
+//!
+//! `/attest/simple` names a verification session rather than a phone
+//! number, so its handler takes the phone token once the session is
+//! resolved (see [`AttestRateLimits::check_phone`]).
+//!
+//! Phone numbers are keyed by an HMAC under the node's rate limit secret,
+//! so neither the limiter nor anyone reading the shared state can recover
+//! them by hashing every number. Buckets live in the shared
+//! [`StateStore`], so replicas behind a load balancer enforce one limit
+//! between them as long as they share the secret; if the store can't be
+//! reached, requests are refused with `503 Service Unavailable` rather than
+//! let through unlimited.
+
+use crate::problem::ApiError;
+use crate::request_validation::payload_too_large;
+use crate::state::AppState;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use hesha_storage::{StateStore, StorageResult};
+use hesha_types::{ErrorCode, PhoneNumber};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Times a token is retried when another request took one first.
+const MAX_UPDATE_ATTEMPTS: usize = 8;
+
+/// Why [`KeyedLimiter::check`] refused a request.
+#[derive(Debug, PartialEq)]
+pub enum Limited {
+    /// Over the limit; a token is available after this long.
+    RetryAfter(Duration),
+    /// The shared state couldn't be read or updated.
+    Unavailable,
+}
+
+/// A token bucket.
+#[derive(Serialize, Deserialize)]
+struct Bucket {
+    tokens: f64,
+    updated_at: DateTime<Utc>,
+}
+
+/// Token buckets by key, allowing `limit` requests per `period`.
+pub struct KeyedLimiter {
+    state: Arc<dyn StateStore>,
+    /// Namespace of the limiter's keys in the shared state.
+    name: &'static str,
+    capacity: f64,
+    /// Tokens added per second.
+    refill_rate: f64,
+}
+
+impl KeyedLimiter {
+    /// Allow bursts of `limit` requests, refilled evenly over `period`.
+    pub fn new(state: Arc<dyn StateStore>, name: &'static str, limit: u32, period: Duration) -> Self {
+        Self {
+            state,
+            name,
+            capacity: limit as f64,
+            refill_rate: limit as f64 / period.as_secs_f64(),
+        }
+    }
+
+    /// Take a token for `key`, or say why the request must wait.
+    pub async fn check(&self, key: &str) -> Result<(), Limited> {
+        match self.check_at(key, Utc::now()).await {
+            Ok(result) => result.map_err(Limited::RetryAfter),
+            Err(e) => {
+                tracing::error!("Rate limiter unavailable, refusing request: {}", e);
+                Err(Limited::Unavailable)
+            }
+        }
+    }
+
+    async fn check_at(&self, key: &str, now: DateTime<Utc>) -> StorageResult<Result<(), Duration>> {
+        let key = format!("{}:{}", self.name, key);
+        for _ in 0..MAX_UPDATE_ATTEMPTS {
+            let current = self.state.get(&key).await?;
+            let mut bucket = current
+                .as_deref()
+                .and_then(|stored| serde_json::from_str::<Bucket>(stored).ok())
+                .unwrap_or(Bucket {
+                    tokens: self.capacity,
+                    updated_at: now,
+                });
+            let elapsed = (now - bucket.updated_at).to_std().unwrap_or_default().as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * self.refill_rate).min(self.capacity);
+            bucket.updated_at = now;
+
+            if bucket.tokens < 1.0 {
+                return Ok(Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_rate)));
+            }
+            bucket.tokens -= 1.0;
+
+            // A bucket that would have refilled carries no state
+            let full_after = Duration::from_secs_f64((self.capacity - bucket.tokens) / self.refill_rate);
+            let expires_at = now + chrono::Duration::from_std(full_after).unwrap_or_default();
+            let value = serde_json::to_string(&bucket).unwrap_or_default();
+            if self.state.compare_and_swap(&key, current.as_deref(), &value, expires_at).await? {
+                return Ok(Ok(()));
+            }
+        }
+        // So contended that the bucket is surely empty
+        Ok(Err(Duration::from_secs(1)))
+    }
+}
+
+/// The limits applied to `/attest`.
+pub struct AttestRateLimits {
+    per_ip: Option<KeyedLimiter>,
+    per_phone: Option<KeyedLimiter>,
+    /// HMAC key for phone number bucket keys.
+    phone_key: Vec<u8>,
+}
+
+impl AttestRateLimits {
+    /// Requests allowed per source IP per minute and per phone number per
+    /// hour; 0 disables a limit.
+    ///
+    /// Phone buckets are keyed under `secret`, or a random key for this
+    /// process when none is given.
+    pub fn new(
+        state: Arc<dyn StateStore>,
+        secret: Option<&str>,
+        per_ip_per_minute: u32,
+        per_phone_per_hour: u32,
+    ) -> Self {
+        let phone_key = match secret {
+            Some(secret) => secret.as_bytes().to_vec(),
+            None => {
+                let mut key = vec![0u8; 32];
+                rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut key);
+                key
+            }
+        };
+        Self {
+            per_ip: (per_ip_per_minute > 0).then(|| {
+                KeyedLimiter::new(state.clone(), "rate:ip", per_ip_per_minute, Duration::from_secs(60))
+            }),
+            per_phone: (per_phone_per_hour > 0).then(|| {
+                KeyedLimiter::new(state, "rate:phone", per_phone_per_hour, Duration::from_secs(3600))
+            }),
+            phone_key,
+        }
+    }
+
+    /// Take a token from `phone_number`'s bucket.
+    ///
+    /// For handlers that only learn the phone number from the request,
+    /// such as a verification session, after the middleware has run.
+    pub async fn check_phone(&self, phone_number: &PhoneNumber) -> Result<(), Limited> {
+        match &self.per_phone {
+            Some(limiter) => limiter.check(&self.phone_key(phone_number)).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Bucket key for a phone number.
+    fn phone_key(&self, phone_number: &PhoneNumber) -> String {
+        hex::encode(hesha_crypto::hmac_sha256(&self.phone_key, phone_number.to_string().as_bytes()))
+    }
+}
+
+/// Middleware enforcing [`AttestRateLimits`] in front of the attest handler.
+///
+/// The source IP is the connecting peer; forwarding headers are not trusted.
+/// Bodies without a valid phone number pass through for the handler to reject.
+pub async fn limit_attest(
+    State(state): State<AppState>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limits = &state.rate_limits;
+
+    if let (Some(limiter), Some(ConnectInfo(peer))) = (&limits.per_ip, peer) {
+        if let Err(limited) = limiter.check(&peer.ip().to_string()).await {
+            tracing::warn!("Rate limited attestation requests from {}", peer.ip());
+            return refuse(&state, limited, "Too many requests from this address");
+        }
+    }
+
+    let Some(limiter) = &limits.per_phone else {
+        return next.run(request).await;
+    };
+
+    let (parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, state.config.max_body_bytes).await {
+        Ok(bytes) => bytes,
+        Err(_) => return payload_too_large(&state),
+    };
+
+    let phone_number = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|body| PhoneNumber::new(body["phone_number"].as_str()?).ok());
+    if let Some(phone_number) = phone_number {
+        if let Err(limited) = limiter.check(&limits.phone_key(&phone_number)).await {
+            tracing::warn!("Rate limited attestation requests for one phone number");
+            return refuse(&state, limited, "Too many attestations for this phone number");
+        }
+    }
+
+    next.run(Request::from_parts(parts, Body::from(bytes))).await
+}
+
+fn refuse(state: &AppState, limited: Limited, description: &str) -> Response {
+    refusal(state, limited, description).into_response()
+}
+
+/// A 429 problem telling the client when to retry, or a 503 when the
+/// limit couldn't be checked.
+pub fn refusal(state: &AppState, limited: Limited, description: &str) -> ApiError {
+    let retry_after = match limited {
+        Limited::RetryAfter(retry_after) => retry_after,
+        Limited::Unavailable => {
+            state.metrics.record_error("rate_limiter_unavailable");
+            return ApiError::new(ErrorCode::Overloaded, "Rate limits can't be checked right now; retry shortly")
+                .with_header(header::RETRY_AFTER, 1);
+        }
+    };
+    state.metrics.record_error("rate_limited");
+
+    // Round up so a client retrying on time is not refused again
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    ApiError::new(ErrorCode::RateLimited, description)
+        .with_header(header::RETRY_AFTER, seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hesha_storage::StorageError;
+
+    #[tokio::test]
+    async fn test_bucket_refills() {
+        let state: Arc<dyn StateStore> = Arc::new(hesha_storage::MemoryStore::new());
+        let limiter = KeyedLimiter::new(state.clone(), "test", 2, Duration::from_secs(60));
+        let start = Utc::now();
+        let check = |key: &'static str, at: DateTime<Utc>| {
+            let limiter = &limiter;
+            async move { limiter.check_at(key, at).await.unwrap() }
+        };
+
+        assert!(check("a", start).await.is_ok());
+        assert!(check("a", start).await.is_ok());
+        let retry_after = check("a", start).await.unwrap_err();
+        assert_eq!(retry_after.as_secs_f64().round(), 30.0);
+
+        // Other keys have their own bucket
+        assert!(check("b", start).await.is_ok());
+
+        assert!(check("a", start + chrono::Duration::seconds(15)).await.is_err());
+        assert!(check("a", start + chrono::Duration::seconds(30)).await.is_ok());
+
+        // Another replica shares the bucket
+        let replica = KeyedLimiter::new(state, "test", 2, Duration::from_secs(60));
+        assert!(replica.check_at("a", start + chrono::Duration::seconds(30)).await.unwrap().is_err());
+    }
+
+    /// A state store that is always down.
+    struct Unreachable;
+
+    #[async_trait::async_trait]
+    impl StateStore for Unreachable {
+        async fn get(&self, _key: &str) -> StorageResult<Option<String>> {
+            Err(StorageError::Database("connection refused".to_string()))
+        }
+
+        async fn compare_and_swap(
+            &self,
+            _key: &str,
+            _current: Option<&str>,
+            _value: &str,
+            _expires_at: DateTime<Utc>,
+        ) -> StorageResult<bool> {
+            Err(StorageError::Database("connection refused".to_string()))
+        }
+
+        async fn compare_and_remove(&self, _key: &str, _current: &str) -> StorageResult<bool> {
+            Err(StorageError::Database("connection refused".to_string()))
+        }
+
+        async fn purge_expired(&self, _now: DateTime<Utc>) -> StorageResult<u64> {
+            Err(StorageError::Database("connection refused".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fails_closed() {
+        let limiter = KeyedLimiter::new(Arc::new(Unreachable), "test", 2, Duration::from_secs(60));
+        assert_eq!(limiter.check("a").await, Err(Limited::Unavailable));
+    }
+
+    #[test]
+    fn test_phone_keys_depend_on_secret() {
+        let state: Arc<dyn StateStore> = Arc::new(hesha_storage::MemoryStore::new());
+        let phone = PhoneNumber::new("+14155552671").unwrap();
+        let key = |secret| AttestRateLimits::new(state.clone(), secret, 1, 1).phone_key(&phone);
+
+        // Replicas sharing a secret share buckets
+        assert_eq!(key(Some("shared")), key(Some("shared")));
+        assert_ne!(key(Some("shared")), key(Some("other")));
+        assert_ne!(key(None), key(None));
+        assert_ne!(key(Some("shared")), hex::encode(hesha_crypto::sha256(phone.to_string().as_bytes())));
+    }
+}
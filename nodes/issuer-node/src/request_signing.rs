@@ -0,0 +1,212 @@
+//! HMAC request signature checks on the attest endpoints.
+//!
+//! When `[request_signing]` is configured, attest requests must be signed
+//! by one of the listed partners, either with a [`REQUEST_SIGNATURE_HEADER`]
+//! or with HTTP Message Signatures (RFC 9421) using `hmac-sha256`, the
+//! partner ID as `keyid` and covering `@method`, `@path` and a matching
+//! `content-digest`. The timestamp must be within `max_skew_secs` of the
+//! node's clock, and each signature is recorded in the nonce store until
+//! it goes stale, so a captured request can't be replayed.
+
+use crate::auth::Caller;
+use crate::problem::ApiError;
+use crate::request_validation::payload_too_large;
+use crate::state::AppState;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, TimeZone, Utc};
+use axum::http::{request::Parts, HeaderMap};
+use hesha_crypto::{
+    verify_content_digest, MessageSignature, RequestSignature, CONTENT_DIGEST_HEADER, REQUEST_SIGNATURE_HEADER,
+    SIGNATURE_HEADER, SIGNATURE_INPUT_HEADER,
+};
+use hesha_types::{AuditEvent, ErrorCode, RequestSigningConfig};
+use std::collections::HashMap;
+
+/// Partner secrets and the allowed clock skew.
+pub struct RequestSigning {
+    secrets: HashMap<String, Vec<u8>>,
+    max_skew: Duration,
+}
+
+impl RequestSigning {
+    /// Partners from issuer.toml, with secrets read from the environment.
+    pub fn from_config(config: &RequestSigningConfig) -> anyhow::Result<Self> {
+        anyhow::ensure!(!config.partners.is_empty(), "[request_signing] lists no partners");
+        let secrets = config
+            .partners
+            .iter()
+            .map(|partner| {
+                let secret = std::env::var(&partner.secret_env).map_err(|_| {
+                    anyhow::anyhow!("{} must be set for signing partner {}", partner.secret_env, partner.id)
+                })?;
+                Ok((partner.id.clone(), secret.into_bytes()))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self::new(secrets, config.max_skew_secs))
+    }
+
+    /// Partners by ID with their secrets.
+    pub fn new(secrets: HashMap<String, Vec<u8>>, max_skew_secs: u64) -> Self {
+        Self {
+            secrets,
+            max_skew: Duration::seconds(max_skew_secs as i64),
+        }
+    }
+
+    /// Number of partners.
+    pub fn len(&self) -> usize {
+        self.secrets.len()
+    }
+
+    /// Whether there are no partners.
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty()
+    }
+}
+
+/// Middleware rejecting attest requests without a valid, fresh signature.
+///
+/// Passes everything through when request signing isn't configured. The
+/// signing partner becomes the [`Caller`] unless an earlier layer already
+/// authenticated one.
+pub async fn require_signature(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(signing) = &state.request_signing else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path().to_string();
+    let refuse = |description: &'static str| invalid_signature(&state, &path, description);
+
+    let Some(signature) = Presented::from_headers(request.headers()) else {
+        return refuse("Missing or malformed request signature").await;
+    };
+    let Some(secret) = signing.secrets.get(signature.partner()) else {
+        return refuse("Unknown signing partner").await;
+    };
+    let Some(signed_at) = Utc.timestamp_opt(signature.timestamp(), 0).single() else {
+        return refuse("Invalid signature timestamp").await;
+    };
+    if (Utc::now() - signed_at).abs() > signing.max_skew {
+        return refuse("Request signature timestamp is too old or in the future").await;
+    }
+    if let Presented::Rfc9421(signature) = &signature {
+        if signature.expires.is_some_and(|expires| expires <= Utc::now().timestamp()) {
+            return refuse("Request signature has expired").await;
+        }
+        if !MESSAGE_SIGNATURE_COMPONENTS.iter().all(|component| signature.covers(component)) {
+            return refuse("Request signature must cover @method, @path and content-digest").await;
+        }
+    }
+
+    let (mut parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, state.config.max_body_bytes).await {
+        Ok(bytes) => bytes,
+        Err(_) => return payload_too_large(&state),
+    };
+    if !signature.verify(secret, &parts, &bytes) {
+        return refuse("Request signature does not match").await;
+    }
+
+    // A signature is usable once, until its timestamp would be refused anyway
+    let nonce = format!("request-signature:{}", signature.replay_key());
+    match state.storage.nonces.check_and_insert(&nonce, signed_at + signing.max_skew).await {
+        Ok(true) => {}
+        Ok(false) => return refuse("Request signature was already used").await,
+        Err(e) => {
+            tracing::error!("Failed to record request signature: {}", e);
+            state.metrics.record_error("storage_failed");
+            return ApiError::new(ErrorCode::StorageFailed, "Failed to record request signature").into_response();
+        }
+    }
+
+    if parts.extensions.get::<Caller>().is_none() {
+        parts.extensions.insert(Caller {
+            id: format!("partner:{}", signature.partner()),
+        });
+    }
+    next.run(Request::from_parts(parts, Body::from(bytes))).await
+}
+
+/// Components an RFC 9421 signature must cover.
+const MESSAGE_SIGNATURE_COMPONENTS: [&str; 3] = ["@method", "@path", "content-digest"];
+
+/// A signature in either of the accepted forms.
+enum Presented {
+    Hmac(RequestSignature),
+    Rfc9421(MessageSignature),
+}
+
+impl Presented {
+    /// The signature on a request, preferring the HMAC header when both
+    /// are sent.
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        if let Some(value) = header(REQUEST_SIGNATURE_HEADER) {
+            return RequestSignature::parse(value).map(Self::Hmac);
+        }
+        MessageSignature::parse(header(SIGNATURE_INPUT_HEADER)?, header(SIGNATURE_HEADER)?).map(Self::Rfc9421)
+    }
+
+    fn partner(&self) -> &str {
+        match self {
+            Self::Hmac(signature) => &signature.partner,
+            Self::Rfc9421(signature) => &signature.key_id,
+        }
+    }
+
+    fn timestamp(&self) -> i64 {
+        match self {
+            Self::Hmac(signature) => signature.timestamp,
+            Self::Rfc9421(signature) => signature.created,
+        }
+    }
+
+    /// Check the signature, and for RFC 9421 that the covered
+    /// `Content-Digest` matches the body.
+    fn verify(&self, secret: &[u8], parts: &Parts, body: &[u8]) -> bool {
+        match self {
+            Self::Hmac(signature) => signature.verify(secret, parts.method.as_str(), parts.uri.path(), body),
+            Self::Rfc9421(signature) => {
+                let header = |name: &str| {
+                    let values = parts.headers.get_all(name).iter().map(|value| value.to_str().ok());
+                    let values = values.collect::<Option<Vec<_>>>()?;
+                    (!values.is_empty()).then(|| values.join(", "))
+                };
+                let digest_matches = header(CONTENT_DIGEST_HEADER).is_some_and(|digest| verify_content_digest(&digest, body));
+                digest_matches
+                    && signature.verify(secret, |component| match component {
+                        "@method" => Some(parts.method.to_string()),
+                        "@path" => Some(parts.uri.path().to_string()),
+                        "@query" => Some(format!("?{}", parts.uri.query().unwrap_or(""))),
+                        "@authority" => header("host"),
+                        name if !name.starts_with('@') => header(name),
+                        _ => None,
+                    })
+            }
+        }
+    }
+
+    /// What identifies this signature in the nonce store.
+    fn replay_key(&self) -> String {
+        match self {
+            Self::Hmac(signature) => signature.mac.clone(),
+            Self::Rfc9421(signature) => hex::encode(&signature.signature),
+        }
+    }
+}
+
+/// A 401 response for a refused signature.
+async fn invalid_signature(state: &AppState, path: &str, description: &str) -> Response {
+    tracing::info!("Rejected attest request: {}", description);
+    state.metrics.record_error("invalid_signature");
+    state.audit.record(AuditEvent::AuthFailed {
+        error: "invalid_signature".to_string(),
+        reason: description.to_string(),
+        path: path.to_string(),
+    }).await;
+    ApiError::new(ErrorCode::InvalidSignature, description).into_response()
+}
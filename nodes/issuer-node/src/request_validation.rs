@@ -0,0 +1,192 @@
+//! Request size limits and input validation.
+//!
+//! Bodies larger than `max_body_bytes` are refused before anything buffers
+//! them, request bodies must be JSON, and malformed input gets the same
+//! problem document as every other refusal (see [`crate::problem`]).
+//! Handlers take their input through [`ApiJson`] and [`ApiQuery`] instead
+//! of axum's extractors, whose rejections are plain text.
+
+use crate::problem::ApiError;
+use crate::state::AppState;
+use axum::{
+    async_trait,
+    extract::{
+        rejection::JsonRejection,
+        FromRequest, FromRequestParts, Query, Request, State,
+    },
+    http::{header, request::Parts, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use hesha_types::ErrorCode;
+use serde::de::DeserializeOwned;
+
+/// Default limit on request bodies.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Refuse oversized and non-JSON request bodies.
+///
+/// `Content-Length` is checked here; chunked bodies are cut off at the same
+/// limit when they are read.
+pub async fn validate_request(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !matches!(*request.method(), Method::POST | Method::PUT | Method::PATCH) {
+        return next.run(request).await;
+    }
+    let headers = request.headers();
+    let content_length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if content_length.is_some_and(|length| length > state.config.max_body_bytes as u64) {
+        return payload_too_large(&state);
+    }
+    let has_body = content_length.map_or(headers.contains_key(header::TRANSFER_ENCODING), |length| length > 0);
+    if has_body && !is_json(headers.get(header::CONTENT_TYPE)) {
+        return refuse(
+            &state,
+            ErrorCode::UnsupportedMediaType,
+            "Request body must be JSON (Content-Type: application/json)",
+        );
+    }
+    next.run(request).await
+}
+
+fn is_json(content_type: Option<&header::HeaderValue>) -> bool {
+    let Some(mime) = content_type.and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    let mime = mime.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    mime == "application/json" || (mime.starts_with("application/") && mime.ends_with("+json"))
+}
+
+/// The response to a body over `max_body_bytes`, for middleware that reads
+/// bodies itself.
+pub fn payload_too_large(state: &AppState) -> Response {
+    refuse(
+        state,
+        ErrorCode::PayloadTooLarge,
+        &format!("Request body is larger than {} bytes", state.config.max_body_bytes),
+    )
+}
+
+/// A refusal, counted in the error metrics.
+fn refuse(state: &AppState, code: ErrorCode, detail: &str) -> Response {
+    state.metrics.record_error(code.as_str());
+    ApiError::new(code, detail).into_response()
+}
+
+/// JSON request body, refused with a problem document.
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T: DeserializeOwned> FromRequest<AppState> for ApiJson<T> {
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &AppState) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(request, state).await {
+            Ok(Json(value)) => Ok(Self(value)),
+            Err(JsonRejection::MissingJsonContentType(_)) => Err(refuse(
+                state,
+                ErrorCode::UnsupportedMediaType,
+                "Request body must be JSON (Content-Type: application/json)",
+            )),
+            Err(rejection) if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => Err(payload_too_large(state)),
+            Err(rejection) => Err(refuse(state, ErrorCode::InvalidRequest, &rejection.body_text())),
+        }
+    }
+}
+
+/// Query string parameters, refused with a problem document.
+pub struct ApiQuery<T>(pub T);
+
+#[async_trait]
+impl<T: DeserializeOwned> FromRequestParts<AppState> for ApiQuery<T> {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        match Query::<T>::from_request_parts(parts, state).await {
+            Ok(Query(value)) => Ok(Self(value)),
+            Err(rejection) => Err(refuse(state, ErrorCode::InvalidRequest, &rejection.body_text())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use axum::{body::Body, extract::DefaultBodyLimit, middleware, routing::post, Router};
+    use hesha_crypto::generate_keypair;
+    use serde::Deserialize;
+    use serde_json::json;
+    use tower::util::ServiceExt;
+
+    #[derive(Deserialize)]
+    struct Echo {
+        value: u32,
+    }
+
+    async fn echo(ApiJson(echo): ApiJson<Echo>) -> String {
+        echo.value.to_string()
+    }
+
+    async fn send(content_type: Option<&str>, body: Vec<u8>, chunked: bool) -> (StatusCode, serde_json::Value) {
+        let config = Config {
+            max_body_bytes: 64,
+            ..Config::default()
+        };
+        let state = AppState::new(config, generate_keypair().unwrap());
+        let app = Router::new()
+            .route("/echo", post(echo))
+            .layer(middleware::from_fn_with_state(state.clone(), validate_request))
+            .layer(DefaultBodyLimit::max(64))
+            .with_state(state);
+
+        let mut request = Request::builder().method("POST").uri("/echo");
+        if let Some(content_type) = content_type {
+            request = request.header("content-type", content_type);
+        }
+        // Without a length the body is only cut off when read
+        let request = if chunked {
+            request.header("transfer-encoding", "chunked")
+        } else {
+            request.header("content-length", body.len())
+        };
+        let request = request.body(Body::from(body)).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(json!(String::from_utf8_lossy(&body))))
+    }
+
+    #[tokio::test]
+    async fn test_request_validation() {
+        let json = Some("application/json");
+        assert_eq!(send(json, br#"{"value":7}"#.to_vec(), false).await, (StatusCode::OK, json!(7)));
+        assert_eq!(send(Some("application/json; charset=utf-8"), br#"{"value":7}"#.to_vec(), false).await.0, StatusCode::OK);
+
+        let (status, body) = send(Some("text/plain"), br#"{"value":7}"#.to_vec(), false).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(body["error"], "unsupported_media_type");
+        assert_eq!(send(None, br#"{"value":7}"#.to_vec(), false).await.0, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let large = format!(r#"{{"value":7,"padding":"{}"}}"#, "x".repeat(100)).into_bytes();
+        let (status, body) = send(json, large.clone(), false).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["error"], "payload_too_large");
+        assert_eq!(send(json, large, true).await.0, StatusCode::PAYLOAD_TOO_LARGE);
+
+        for malformed in [&br#"{"value":"#[..], br#"{"value":"seven"}"#, br#"[]"#] {
+            let (status, body) = send(json, malformed.to_vec(), false).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], "invalid_request");
+            assert!(body["detail"].is_string());
+            assert_eq!(body["type"], ErrorCode::InvalidRequest.type_uri());
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Periodic background jobs.
+//!
+//! Each [`Job`] runs in its own task: once shortly after startup, then
+//! every interval. Waits are stretched or shortened by up to a tenth at
+//! random, so nodes sharing a database don't all hit it at the same
+//! moment. Runs and failures are counted per job and served with the
+//! other metrics at `/admin/metrics`; a failed run is logged and retried at
+//! the next interval. Jobs acting on state replicas share only run on the
+//! replica holding the scheduler lease (see [`crate::leader`]).
+
+use crate::state::AppState;
+use chrono::Utc;
+use hesha_types::ExpiryStats;
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+/// Share of an interval a wait may be moved by.
+const JITTER: f64 = 0.1;
+
+/// How long before a published key's window ends to start warning.
+const KEY_EXPIRY_WARNING: chrono::Duration = chrono::Duration::days(7);
+
+/// A periodic job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Job {
+    /// Forget expired nonces and shared state (verification sessions,
+    /// idempotency keys, rate-limit buckets).
+    Prune,
+    /// Reload the published revocation list from storage.
+    Revocations,
+    /// Retry failed webhook deliveries that are due.
+    WebhookRetries,
+    /// Count stored attestations by expiry.
+    ExpiryStats,
+    /// Warn about published keys whose window is about to end.
+    KeyExpiry,
+    /// Pick up maintenance mode switched by another replica.
+    Maintenance,
+}
+
+impl Job {
+    /// Every job.
+    pub const ALL: [Job; 6] = [
+        Job::Prune,
+        Job::Revocations,
+        Job::WebhookRetries,
+        Job::ExpiryStats,
+        Job::KeyExpiry,
+        Job::Maintenance,
+    ];
+
+    /// Name used in logs and metrics.
+    pub fn name(self) -> &'static str {
+        match self {
+            Job::Prune => "prune",
+            Job::Revocations => "revocations",
+            Job::WebhookRetries => "webhook_retries",
+            Job::ExpiryStats => "expiry_stats",
+            Job::KeyExpiry => "key_expiry",
+            Job::Maintenance => "maintenance",
+        }
+    }
+
+    /// Whether only the replica holding the scheduler lease runs the job.
+    /// The others keep per-replica caches and metrics up to date.
+    pub fn leader_only(self) -> bool {
+        matches!(self, Job::Prune | Job::KeyExpiry)
+    }
+
+    /// Time between runs, before jitter.
+    pub fn interval(self) -> Duration {
+        match self {
+            Job::Prune => Duration::from_secs(60),
+            Job::Revocations => Duration::from_secs(60),
+            Job::WebhookRetries => Duration::from_secs(1),
+            Job::ExpiryStats => Duration::from_secs(600),
+            Job::KeyExpiry => Duration::from_secs(3600),
+            Job::Maintenance => Duration::from_secs(5),
+        }
+    }
+
+    /// Run the job once.
+    pub async fn run(self, state: &AppState) -> anyhow::Result<()> {
+        match self {
+            Job::Prune => {
+                let nonces = state.storage.nonces.purge_expired(Utc::now()).await?;
+                let shared = state.storage.state.purge_expired(Utc::now()).await?;
+                if nonces + shared > 0 {
+                    tracing::debug!("Pruned {} nonce(s) and {} shared state value(s)", nonces, shared);
+                }
+            }
+            Job::Revocations => {
+                if state.revocation_list.publish(&state.storage).await? {
+                    tracing::debug!("Published an updated revocation list");
+                }
+            }
+            Job::WebhookRetries => {
+                let started = state.webhooks.drain_retries();
+                if started > 0 {
+                    tracing::debug!(
+                        "Retrying {} webhook deliveries, {} still waiting",
+                        started,
+                        state.webhooks.pending_retries()
+                    );
+                }
+            }
+            Job::ExpiryStats => {
+                let now = Utc::now();
+                let attestations = &state.storage.attestations;
+                state.metrics.record_expiry(ExpiryStats {
+                    computed_at: now,
+                    valid: attestations.count_expiring(now, None).await?,
+                    expiring_within_7_days: attestations
+                        .count_expiring(now, Some(now + chrono::Duration::days(7)))
+                        .await?,
+                    expiring_within_30_days: attestations
+                        .count_expiring(now, Some(now + chrono::Duration::days(30)))
+                        .await?,
+                });
+            }
+            Job::KeyExpiry => {
+                let now = Utc::now();
+                let active = state.keys.active();
+                for key in state.keys.published() {
+                    let Some(until) = key.valid_until.filter(|until| *until - now < KEY_EXPIRY_WARNING) else {
+                        continue;
+                    };
+                    if key.key_id == active.key_id {
+                        tracing::warn!(
+                            "Signing key {} stops being published at {}; attestations it signs will stop verifying. Rotate with 'hesha admin rotate-key'",
+                            key.key_id, until.to_rfc3339()
+                        );
+                    } else if until > now {
+                        tracing::warn!(
+                            "Previous key {} stops being published at {}; attestations it signed will stop verifying",
+                            key.key_id, until.to_rfc3339()
+                        );
+                    }
+                }
+            }
+            Job::Maintenance => {
+                if state.maintenance.refresh().await? {
+                    if state.maintenance.is_enabled() {
+                        tracing::warn!("Maintenance mode is on; refusing attestation requests");
+                    } else {
+                        tracing::info!("Maintenance mode is off; accepting attestation requests");
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run every job in the background for as long as the node runs.
+pub fn start(state: AppState) {
+    for job in Job::ALL {
+        let state = state.clone();
+        tokio::spawn(async move {
+            // Spread first runs over the first tenth of an interval
+            let first = job.interval().mul_f64(rand::thread_rng().gen_range(0.0..JITTER));
+            tokio::time::sleep(first).await;
+            loop {
+                run_once(job, &state).await;
+                tokio::time::sleep(jittered(job.interval())).await;
+            }
+        });
+    }
+}
+
+/// Run a job, logging and counting the outcome. Leader-only jobs are
+/// skipped on other replicas.
+pub async fn run_once(job: Job, state: &AppState) {
+    if job.leader_only() && !state.leader.is_leader() {
+        return;
+    }
+    let started = Instant::now();
+    let result = job.run(state).await;
+    if let Err(e) = &result {
+        tracing::error!("Job {} failed: {}", job.name(), e);
+    }
+    state.metrics.record_job(job.name(), started.elapsed(), result.err().map(|e| e.to_string()));
+}
+
+/// `interval` moved by up to [`JITTER`] of itself either way.
+fn jittered(interval: Duration) -> Duration {
+    interval.mul_f64(1.0 + rand::thread_rng().gen_range(-JITTER..=JITTER))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use hesha_crypto::generate_keypair;
+    use hesha_storage::StoredAttestation;
+
+    #[test]
+    fn test_jitter() {
+        for _ in 0..100 {
+            let wait = jittered(Duration::from_secs(100));
+            assert!(wait >= Duration::from_secs(90) && wait <= Duration::from_secs(110));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jobs() {
+        let state = AppState::new(Config::default(), generate_keypair().unwrap());
+        let now = Utc::now();
+        for (jti, days) in [("a", 3), ("b", 20), ("c", 90), ("d", -1)] {
+            state.storage.attestations.insert(&StoredAttestation {
+                jti: jti.to_string(),
+                proxy_number: "+100111".to_string(),
+                scope: "1".to_string(),
+                issued_at: now - chrono::Duration::days(100),
+                expires_at: now + chrono::Duration::days(days),
+                renews: None,
+            }).await.unwrap();
+        }
+        state.storage.nonces.check_and_insert("n", now - chrono::Duration::seconds(1)).await.unwrap();
+
+        // Leader-only jobs wait for the lease
+        run_once(Job::Prune, &state).await;
+        assert!(state.metrics.snapshot().jobs.is_empty());
+        assert!(state.leader.renew().await);
+        for job in Job::ALL {
+            run_once(job, &state).await;
+        }
+        let metrics = state.metrics.snapshot();
+        let expiry = metrics.attestation_expiry.unwrap();
+        assert_eq!((expiry.valid, expiry.expiring_within_7_days, expiry.expiring_within_30_days), (3, 1, 2));
+        assert_eq!(metrics.jobs.len(), Job::ALL.len());
+        assert!(metrics.jobs.values().all(|job| job.runs == 1 && job.failures == 0));
+        // The expired nonce was purged
+        assert_eq!(state.storage.nonces.purge_expired(Utc::now()).await.unwrap(), 0);
+    }
+}
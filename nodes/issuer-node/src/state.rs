@@ -1,24 +1,176 @@
 //! Application state management.
 
+use crate::api::revocations::PublishedRevocations;
+use crate::audit::AuditLog;
+use crate::auth::Authenticator;
+use crate::captcha::CaptchaVerifier;
 use crate::config::Config;
+use crate::control::Drain;
+use crate::idempotency::IdempotencyCache;
+use crate::ip_access::IpAccess;
+use crate::keys::Keyring;
+use crate::leader::Leadership;
+use crate::load_shed::ConcurrencyLimit;
+use crate::maintenance::Maintenance;
+use crate::metrics::Metrics;
+use crate::openid::OpenIdProvider;
+use crate::rate_limit::AttestRateLimits;
+use crate::request_signing::RequestSigning;
+use crate::verification::{MockVerifier, ContactVerifier, VerificationSessions, SESSION_TTL};
+use crate::webhooks::Webhooks;
+use hesha_storage::Storage;
+#[cfg(test)]
 use hesha_types::KeyPair;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Application state.
 #[derive(Clone)]
 pub struct AppState {
     /// Configuration.
     pub config: Config,
-    /// Issuer's key pair.
-    pub issuer_key: Arc<KeyPair>,
+    /// Issuer's signing keys.
+    pub keys: Arc<Keyring>,
+    /// Checks credentials of `/attest` callers.
+    pub auth: Arc<Authenticator>,
+    /// Checks credentials of operators calling the `/admin` routes.
+    pub admin_auth: Arc<Authenticator>,
+    /// Partner secrets, when attest requests must be signed.
+    pub request_signing: Option<Arc<RequestSigning>>,
+    /// Networks allowed per route group, when `[ip_access]` is set.
+    pub ip_access: Option<Arc<IpAccess>>,
+    /// Responses to replay for retried attestation requests.
+    pub idempotency: Arc<IdempotencyCache>,
+    /// Counters served to operators at `/admin/metrics`.
+    pub metrics: Arc<Metrics>,
+    /// Per-IP and per-phone limits on attestation requests.
+    pub rate_limits: Arc<AttestRateLimits>,
+    /// Slots for requests in flight.
+    pub concurrency: Arc<ConcurrencyLimit>,
+    /// Delivers phone verification codes.
+    pub verifier: Arc<dyn ContactVerifier>,
+    /// Open phone verification sessions.
+    pub verification: Arc<VerificationSessions>,
+    /// Checks CAPTCHA tokens, when `[captcha]` is set.
+    pub captcha: Option<Arc<dyn CaptchaVerifier>>,
+    /// Persistent records, in the backend chosen by `storage_url`.
+    pub storage: Storage,
+    /// Revocations served at `/.well-known/hesha/revocations.json`.
+    pub revocation_list: Arc<PublishedRevocations>,
+    /// Operator endpoints notified of events.
+    pub webhooks: Arc<Webhooks>,
+    /// Signed record of security-relevant events, kept in `storage`.
+    pub audit: Arc<AuditLog>,
+    /// Set when the admin console asks the node to drain.
+    pub drain: Arc<Drain>,
+    /// Whether attestation requests are refused for maintenance.
+    pub maintenance: Arc<Maintenance>,
+    /// This replica's claim on running the shared background jobs.
+    pub leader: Arc<Leadership>,
+    /// Relying parties signing users in over OpenID Connect, when
+    /// `[oidc_provider]` is set.
+    pub openid: Option<Arc<OpenIdProvider>>,
 }
 
 impl AppState {
-    /// Create new app state.
+    /// Create new app state, keeping records in memory.
+    #[cfg(test)]
     pub fn new(config: Config, issuer_key: KeyPair) -> Self {
+        let keys = Keyring::new(config.key_id.clone(), issuer_key);
+        Self::with_storage(config, keys, Storage::memory())
+    }
+    
+    /// Create new app state over an opened storage backend.
+    pub fn with_storage(config: Config, keys: Keyring, storage: Storage) -> Self {
+        let rate_limits = AttestRateLimits::new(
+            storage.state.clone(),
+            config.rate_limit_secret.as_deref(),
+            config.ip_rate_limit,
+            config.phone_rate_limit,
+        );
+        let idempotency = IdempotencyCache::new(
+            storage.state.clone(),
+            Duration::from_secs(config.idempotency_window_secs),
+        );
+        let verification = VerificationSessions::new(storage.state.clone(), SESSION_TTL);
+        let maintenance = Maintenance::new(storage.state.clone());
+        let leader = Leadership::new(storage.state.clone());
+        let concurrency = ConcurrencyLimit::new(
+            config.max_in_flight,
+            Duration::from_millis(config.queue_timeout_ms),
+        );
+        let keys = Arc::new(keys);
+        let audit = AuditLog::new(storage.audit.clone(), keys.clone());
         Self {
             config,
-            issuer_key: Arc::new(issuer_key),
+            keys,
+            auth: Arc::new(Authenticator::None),
+            admin_auth: Arc::new(Authenticator::None),
+            request_signing: None,
+            ip_access: None,
+            idempotency: Arc::new(idempotency),
+            metrics: Arc::new(Metrics::default()),
+            rate_limits: Arc::new(rate_limits),
+            concurrency: Arc::new(concurrency),
+            verifier: Arc::new(MockVerifier),
+            verification: Arc::new(verification),
+            captcha: None,
+            storage,
+            revocation_list: Arc::new(PublishedRevocations::default()),
+            webhooks: Arc::new(Webhooks::default()),
+            audit: Arc::new(audit),
+            drain: Arc::new(Drain::default()),
+            maintenance: Arc::new(maintenance),
+            leader: Arc::new(leader),
+            openid: None,
         }
     }
+    
+    /// Use a different phone verification provider.
+    pub fn with_verifier(mut self, verifier: Arc<dyn ContactVerifier>) -> Self {
+        self.verifier = verifier;
+        self
+    }
+    
+    /// Require a CAPTCHA token before sending verification codes.
+    pub fn with_captcha(mut self, captcha: Arc<dyn CaptchaVerifier>) -> Self {
+        self.captcha = Some(captcha);
+        self
+    }
+    
+    /// Require `/attest` callers to authenticate.
+    pub fn with_auth(mut self, auth: Authenticator) -> Self {
+        self.auth = Arc::new(auth);
+        self
+    }
+    
+    /// Serve the `/admin` routes to operators presenting these credentials.
+    pub fn with_admin_auth(mut self, auth: Authenticator) -> Self {
+        self.admin_auth = Arc::new(auth);
+        self
+    }
+    
+    /// Require attest requests to be signed by a partner.
+    pub fn with_request_signing(mut self, signing: RequestSigning) -> Self {
+        self.request_signing = Some(Arc::new(signing));
+        self
+    }
+    
+    /// Refuse requests from networks a route group doesn't accept.
+    pub fn with_ip_access(mut self, access: IpAccess) -> Self {
+        self.ip_access = Some(Arc::new(access));
+        self
+    }
+    
+    /// Act as an OpenID provider for the registered relying parties.
+    pub fn with_openid(mut self, provider: OpenIdProvider) -> Self {
+        self.openid = Some(Arc::new(provider));
+        self
+    }
+    
+    /// Deliver events to webhook endpoints.
+    pub fn with_webhooks(mut self, webhooks: Webhooks) -> Self {
+        self.webhooks = Arc::new(webhooks);
+        self
+    }
 }
\ No newline at end of file
@@ -0,0 +1,131 @@
+//! HTTPS served by the node itself.
+//!
+//! The protocol requires `/.well-known/hesha/pubkey.json` over HTTPS. Small
+//! deployments can terminate TLS in the node with rustls instead of
+//! running a reverse proxy in front of it. With `client_ca_path` set,
+//! clients must also present a certificate from that CA (mutual TLS).
+
+use crate::control::Drain;
+use crate::metrics::Metrics;
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use chrono::{DateTime, Utc};
+use hesha_types::TlsConfig;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use std::fs::File;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Build the rustls server configuration from PEM files.
+pub fn server_config(config: &TlsConfig) -> anyhow::Result<ServerConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let certs = load_certs(Path::new(&config.cert_path))?;
+    let key = load_key(Path::new(&config.key_path))?;
+
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+    let builder = match &config.client_ca_path {
+        Some(ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(Path::new(ca_path))? {
+                roots.add(cert)?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider).build()?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut server_config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| anyhow::anyhow!("Invalid TLS certificate or key: {}", e))?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(server_config)
+}
+
+/// Serve the app over HTTPS until a shutdown signal, then drain in-flight
+/// requests.
+///
+/// The certificate can be swapped while serving through `rustls_config`.
+pub async fn serve(
+    addr: SocketAddr,
+    rustls_config: RustlsConfig,
+    app: Router,
+    drain: Arc<Drain>,
+) -> anyhow::Result<()> {
+    let handle = axum_server::Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            crate::shutdown_or_drain(drain).await;
+            handle.graceful_shutdown(None);
+        }
+    });
+
+    axum_server::bind_rustls(addr, rustls_config)
+        .handle(handle)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await?;
+    Ok(())
+}
+
+/// When the first certificate in a PEM chain expires.
+pub fn certificate_expiry(pem: &[u8]) -> anyhow::Result<DateTime<Utc>> {
+    let (_, pem) = x509_parser::pem::parse_x509_pem(pem)?;
+    let certificate = pem.parse_x509()?;
+    DateTime::from_timestamp(certificate.validity().not_after.timestamp(), 0)
+        .ok_or_else(|| anyhow::anyhow!("Certificate expiry out of range"))
+}
+
+/// Record when the served certificate expires, for `/admin/metrics`.
+pub fn record_expiry(config: &TlsConfig, metrics: &Metrics) {
+    let expiry = std::fs::read(&config.cert_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|pem| certificate_expiry(&pem));
+    match expiry {
+        Ok(expires_at) => metrics.record_certificate_expiry(expires_at),
+        Err(e) => tracing::warn!("Cannot read the expiry of {}: {}", config.cert_path, e),
+    }
+}
+
+/// Certificates in a PEM file.
+fn load_certs(path: &Path) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    let file = File::open(path)
+        .map_err(|e| anyhow::anyhow!("Cannot open {}: {}", path.display(), e))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file)).collect::<Result<Vec<_>, _>>()?;
+    anyhow::ensure!(!certs.is_empty(), "No certificates found in {}", path.display());
+    Ok(certs)
+}
+
+/// The first private key in a PEM file.
+fn load_key(path: &Path) -> anyhow::Result<PrivateKeyDer<'static>> {
+    let file = File::open(path)
+        .map_err(|e| anyhow::anyhow!("Cannot open {}: {}", path.display(), e))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))?
+        .ok_or_else(|| anyhow::anyhow!("No private key found in {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = TlsConfig {
+            cert_path: dir.path().join("cert.pem").display().to_string(),
+            key_path: dir.path().join("key.pem").display().to_string(),
+            client_ca_path: None,
+        };
+        let error = server_config(&config).unwrap_err().to_string();
+        assert!(error.contains("cert.pem"));
+
+        std::fs::write(&config.cert_path, "not a certificate").unwrap();
+        let error = server_config(&config).unwrap_err().to_string();
+        assert!(error.contains("No certificates found"));
+    }
+}
@@ -0,0 +1,139 @@
+//! Twilio Verify provider for phone verification.
+//!
+//! Twilio generates, sends and checks the codes; the node only relays the
+//! phone number and the code the user typed.
+
+use crate::verification::{Challenge, ContactVerifier};
+use async_trait::async_trait;
+use hesha_types::PhoneNumber;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Twilio Verify API base URL.
+const TWILIO_VERIFY_URL: &str = "https://verify.twilio.com/v2";
+
+/// Verifier backed by a Twilio Verify service.
+pub struct TwilioVerifier {
+    client: reqwest::Client,
+    base_url: String,
+    account_sid: String,
+    service_sid: String,
+    auth_token: String,
+}
+
+/// Verification resource returned by Twilio.
+#[derive(Debug, Deserialize)]
+struct Verification {
+    status: String,
+}
+
+impl TwilioVerifier {
+    /// Create a verifier for a Verify service.
+    pub fn new(account_sid: String, service_sid: String, auth_token: String) -> Self {
+        Self::with_base_url(TWILIO_VERIFY_URL, account_sid, service_sid, auth_token)
+    }
+
+    /// Create a verifier against a different API endpoint.
+    pub fn with_base_url(
+        base_url: &str,
+        account_sid: String,
+        service_sid: String,
+        auth_token: String,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            account_sid,
+            service_sid,
+            auth_token,
+        }
+    }
+
+    async fn post(&self, resource: &str, form: &[(&str, &str)]) -> anyhow::Result<reqwest::Response> {
+        let url = format!("{}/Services/{}/{}", self.base_url, self.service_sid, resource);
+        Ok(self.client
+            .post(url)
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .form(form)
+            .send()
+            .await?)
+    }
+}
+
+#[async_trait]
+impl ContactVerifier for TwilioVerifier {
+    async fn start(&self, phone_number: &PhoneNumber, _session_id: &str) -> anyhow::Result<Challenge> {
+        let to = phone_number.to_string();
+        let response = self.post("Verifications", &[("To", &to), ("Channel", "sms")]).await?;
+        if !response.status().is_success() {
+            anyhow::bail!("Twilio returned {} starting verification", response.status());
+        }
+        Ok(Challenge::Code)
+    }
+
+    async fn check_code(&self, phone_number: &PhoneNumber, code: &str) -> anyhow::Result<bool> {
+        let to = phone_number.to_string();
+        let response = self.post("VerificationCheck", &[("To", &to), ("Code", code)]).await?;
+
+        // Twilio answers 404 once the verification has expired or been used up
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        if !response.status().is_success() {
+            anyhow::bail!("Twilio returned {} checking verification", response.status());
+        }
+        let verification: Verification = response.json().await?;
+        Ok(verification.status == "approved")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_string_contains, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_send_and_check() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/Services/VA456/Verifications"))
+            .and(header_exists("authorization"))
+            .and(body_string_contains("Channel=sms"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({
+                "status": "pending"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/Services/VA456/VerificationCheck"))
+            .and(body_string_contains("Code=424242"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "status": "approved"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/Services/VA456/VerificationCheck"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "status": "pending"
+            })))
+            .mount(&server)
+            .await;
+
+        let verifier = TwilioVerifier::with_base_url(
+            &server.uri(),
+            "AC123".to_string(),
+            "VA456".to_string(),
+            "token".to_string(),
+        );
+        let phone = PhoneNumber::new("+1234567890").unwrap();
+        verifier.start(&phone, "session").await.unwrap();
+        assert!(verifier.check_code(&phone, "424242").await.unwrap());
+        assert!(!verifier.check_code(&phone, "000000").await.unwrap());
+    }
+}
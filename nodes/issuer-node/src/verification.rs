@@ -0,0 +1,575 @@
+//! Phone verification for issuers that check numbers themselves.
+//!
+//! `/verify/start` creates a session and has the [`ContactVerifier`] deliver a
+//! one-time code, by SMS or to a contact registered for the number;
+//! `/verify/check` compares the code the user typed. A
+//! verified session can then be exchanged once for an attestation at
+//! `/attest/simple`. Sessions expire, and allow only a few wrong guesses.
+//!
+//! Sessions live in the shared [`StateStore`], so the replica that sent a
+//! code need not be the one that checks it. They are the only shared state
+//! holding a phone number, and only until they expire.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hesha_crypto::{constant_time_compare, generate_hex_nonce};
+use hesha_storage::{StateStore, StorageError};
+use hesha_types::{PhoneNumber, VerificationConfig, VerificationMethod};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Code accepted by the [`MockVerifier`].
+pub const MOCK_VERIFICATION_CODE: &str = "123456";
+
+/// How long a code can be checked, and a verified session used.
+pub const SESSION_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Code checks allowed per session.
+pub const MAX_ATTEMPTS: u32 = 5;
+
+/// Times an update is retried when another request changed the session
+/// first.
+pub(crate) const MAX_UPDATE_ATTEMPTS: usize = 8;
+
+/// How the user proves possession of the phone after `/verify/start`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Challenge {
+    /// A code was sent to the phone, to be entered at `/verify/check`.
+    Code,
+    /// A code was emailed to the contact registered for the number, to be
+    /// entered at `/verify/check`.
+    #[cfg_attr(not(feature = "email"), allow(dead_code))]
+    EmailCode,
+    /// The phone must open this URL over its mobile data connection; the
+    /// carrier then redirects to `/verify/callback` with a token.
+    #[cfg_attr(not(feature = "carrier"), allow(dead_code))]
+    Redirect(String),
+}
+
+impl Challenge {
+    /// How the number is being verified, as recorded in the attestation.
+    pub fn method(&self) -> VerificationMethod {
+        match self {
+            Self::Code => VerificationMethod::Sms,
+            Self::EmailCode => VerificationMethod::Email,
+            Self::Redirect(_) => VerificationMethod::Carrier,
+        }
+    }
+}
+
+/// Confirms control of a phone number through a contact channel: codes
+/// texted to the phone or emailed to an address registered for it, or the
+/// carrier's own check.
+/// 
+/// Providers such as Twilio Verify choose and check codes themselves, so
+/// the node never sees the expected code. Carrier verifiers confirm the
+/// number without a code, checking a redirect token instead.
+#[async_trait]
+pub trait ContactVerifier: Send + Sync {
+    /// Start verifying `phone_number` for a session, e.g. by sending an SMS.
+    /// Fail when the number can't be reached this way, so failover can
+    /// try the next provider.
+    async fn start(&self, phone_number: &PhoneNumber, session_id: &str) -> anyhow::Result<Challenge>;
+    
+    /// Check the code the user entered, or the carrier's redirect token,
+    /// for `phone_number`.
+    async fn check_code(&self, phone_number: &PhoneNumber, code: &str) -> anyhow::Result<bool>;
+}
+
+/// Development verifier: sends nothing and accepts
+/// [`MOCK_VERIFICATION_CODE`].
+pub struct MockVerifier;
+
+#[async_trait]
+impl ContactVerifier for MockVerifier {
+    async fn start(&self, _phone_number: &PhoneNumber, _session_id: &str) -> anyhow::Result<Challenge> {
+        tracing::debug!("Mock verifier: use code {}", MOCK_VERIFICATION_CODE);
+        Ok(Challenge::Code)
+    }
+    
+    async fn check_code(&self, _phone_number: &PhoneNumber, code: &str) -> anyhow::Result<bool> {
+        Ok(constant_time_compare(code.as_bytes(), MOCK_VERIFICATION_CODE.as_bytes()))
+    }
+}
+
+/// Create the verifier selected in the issuer configuration. Failover
+/// verifiers remember which provider sent each code in `state`.
+pub fn from_config(
+    config: &VerificationConfig,
+    state: &Arc<dyn StateStore>,
+) -> anyhow::Result<Arc<dyn ContactVerifier>> {
+    match config {
+        VerificationConfig::Mock => Ok(Arc::new(MockVerifier)),
+        #[cfg(feature = "twilio")]
+        VerificationConfig::Twilio { account_sid, service_sid, auth_token_env } => {
+            Ok(Arc::new(crate::twilio::TwilioVerifier::new(
+                account_sid.clone(),
+                service_sid.clone(),
+                secret_from_env(auth_token_env, "Twilio")?,
+            )))
+        }
+        #[cfg(not(feature = "twilio"))]
+        VerificationConfig::Twilio { .. } => {
+            anyhow::bail!("Twilio verification requires issuer-node built with the 'twilio' feature")
+        }
+        #[cfg(feature = "vonage")]
+        VerificationConfig::Vonage { api_key, brand, api_secret_env } => {
+            Ok(Arc::new(crate::vonage::VonageVerifier::new(
+                api_key.clone(),
+                secret_from_env(api_secret_env, "Vonage")?,
+                brand.clone(),
+            )))
+        }
+        #[cfg(not(feature = "vonage"))]
+        VerificationConfig::Vonage { .. } => {
+            anyhow::bail!("Vonage verification requires issuer-node built with the 'vonage' feature")
+        }
+        #[cfg(feature = "messagebird")]
+        VerificationConfig::MessageBird { originator, regional_originators, access_key_env } => {
+            Ok(Arc::new(crate::messagebird::MessageBirdVerifier::new(
+                secret_from_env(access_key_env, "MessageBird")?,
+                originator.clone(),
+                regional_originators.clone(),
+            )))
+        }
+        #[cfg(not(feature = "messagebird"))]
+        VerificationConfig::MessageBird { .. } => {
+            anyhow::bail!("MessageBird verification requires issuer-node built with the 'messagebird' feature")
+        }
+        #[cfg(feature = "carrier")]
+        VerificationConfig::Carrier {
+            authorize_url,
+            token_url,
+            verify_url,
+            client_id,
+            redirect_url,
+            client_secret_env,
+        } => {
+            let endpoints = crate::carrier::CarrierEndpoints {
+                authorize_url: authorize_url.clone(),
+                token_url: token_url.clone(),
+                verify_url: verify_url.clone(),
+                redirect_url: redirect_url.clone(),
+            };
+            Ok(Arc::new(crate::carrier::CarrierVerifier::new(
+                endpoints,
+                client_id.clone(),
+                secret_from_env(client_secret_env, "carrier")?,
+            )))
+        }
+        #[cfg(not(feature = "carrier"))]
+        VerificationConfig::Carrier { .. } => {
+            anyhow::bail!("Carrier verification requires issuer-node built with the 'carrier' feature")
+        }
+        #[cfg(feature = "email")]
+        VerificationConfig::Email {
+            smtp_host,
+            smtp_port,
+            smtp_username,
+            smtp_password_env,
+            from,
+            contacts,
+        } => {
+            let credentials = match smtp_username {
+                Some(username) => Some((username.clone(), secret_from_env(smtp_password_env, "email")?)),
+                None => None,
+            };
+            let contacts = contacts
+                .iter()
+                .map(|(number, address)| {
+                    let number = PhoneNumber::new(number)
+                        .map_err(|e| anyhow::anyhow!("Invalid number {} in email contacts: {}", number, e))?;
+                    Ok((number.to_string(), address.clone()))
+                })
+                .collect::<anyhow::Result<_>>()?;
+            Ok(Arc::new(crate::email::EmailVerifier::new(
+                Arc::new(crate::email::SmtpMailer::new(smtp_host, *smtp_port, credentials, from)?),
+                contacts,
+                state.clone(),
+            )))
+        }
+        #[cfg(not(feature = "email"))]
+        VerificationConfig::Email { .. } => {
+            anyhow::bail!("Email verification requires issuer-node built with the 'email' feature")
+        }
+        VerificationConfig::Failover { providers } => {
+            anyhow::ensure!(!providers.is_empty(), "Failover verification needs at least one provider");
+            let providers = providers
+                .iter()
+                .map(|provider| from_config(provider, state))
+                .collect::<anyhow::Result<_>>()?;
+            Ok(Arc::new(FailoverVerifier::new(providers, state.clone())))
+        }
+    }
+}
+
+/// Read a provider credential kept out of the config file.
+#[cfg(any(feature = "twilio", feature = "vonage", feature = "messagebird", feature = "carrier", feature = "email"))]
+fn secret_from_env(name: &str, provider: &str) -> anyhow::Result<String> {
+    std::env::var(name)
+        .map_err(|_| anyhow::anyhow!("{} must be set for {} verification", name, provider))
+}
+
+/// Verifier that falls back to the next provider when sending fails.
+/// 
+/// A code is checked with the provider that sent it, which is remembered
+/// in the shared state under the phone number's hash.
+pub struct FailoverVerifier {
+    providers: Vec<Arc<dyn ContactVerifier>>,
+    state: Arc<dyn StateStore>,
+}
+
+impl FailoverVerifier {
+    /// Try `providers` in order.
+    pub fn new(providers: Vec<Arc<dyn ContactVerifier>>, state: Arc<dyn StateStore>) -> Self {
+        Self { providers, state }
+    }
+    
+    fn sender_key(phone_number: &PhoneNumber) -> String {
+        format!("failover:{}", hex::encode(hesha_crypto::sha256(phone_number.to_string().as_bytes())))
+    }
+    
+    /// Remember that provider `index` sent the latest code to `phone_number`.
+    async fn record_sender(&self, phone_number: &PhoneNumber, index: usize) -> Result<(), StorageError> {
+        let key = Self::sender_key(phone_number);
+        let expires_at = Utc::now() + chrono::Duration::from_std(SESSION_TTL).unwrap_or_default();
+        for _ in 0..MAX_UPDATE_ATTEMPTS {
+            let current = self.state.get(&key).await?;
+            if self.state.compare_and_swap(&key, current.as_deref(), &index.to_string(), expires_at).await? {
+                return Ok(());
+            }
+        }
+        Err(StorageError::Conflict(key))
+    }
+}
+
+#[async_trait]
+impl ContactVerifier for FailoverVerifier {
+    async fn start(&self, phone_number: &PhoneNumber, session_id: &str) -> anyhow::Result<Challenge> {
+        let mut last_error = anyhow::anyhow!("No verification providers configured");
+        for (index, provider) in self.providers.iter().enumerate() {
+            match provider.start(phone_number, session_id).await {
+                Ok(challenge) => {
+                    self.record_sender(phone_number, index).await?;
+                    return Ok(challenge);
+                }
+                Err(e) => {
+                    tracing::warn!("Verification provider {} failed, trying next: {}", index + 1, e);
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+    
+    async fn check_code(&self, phone_number: &PhoneNumber, code: &str) -> anyhow::Result<bool> {
+        let key = Self::sender_key(phone_number);
+        let Some(sender) = self.state.get(&key).await? else {
+            return Ok(false);
+        };
+        let Some(provider) = sender.parse::<usize>().ok().and_then(|index| self.providers.get(index)) else {
+            return Ok(false);
+        };
+        let verified = provider.check_code(phone_number, code).await?;
+        if verified {
+            self.state.compare_and_remove(&key, &sender).await?;
+        }
+        Ok(verified)
+    }
+}
+
+/// Why a session could not be checked or used.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SessionError {
+    /// Unknown or expired session.
+    NotFound,
+    /// Too many codes checked; the session is closed.
+    TooManyAttempts,
+    /// The code has not been confirmed yet.
+    NotVerified,
+    /// The shared state could not be read or written.
+    Unavailable,
+}
+
+impl From<StorageError> for SessionError {
+    fn from(e: StorageError) -> Self {
+        tracing::error!("Failed to access verification session: {}", e);
+        SessionError::Unavailable
+    }
+}
+
+/// State of a session about to have a code checked.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PendingCheck {
+    /// An earlier code was already correct.
+    Verified,
+    /// The code should be checked for this phone number.
+    Check(PhoneNumber),
+}
+
+#[derive(Serialize, Deserialize)]
+struct Session {
+    phone_number: String,
+    expires_at: DateTime<Utc>,
+    attempts: u32,
+    verified: bool,
+    #[serde(default = "default_method")]
+    method: VerificationMethod,
+}
+
+/// Method of sessions from before it was recorded, when only SMS existed.
+fn default_method() -> VerificationMethod {
+    VerificationMethod::Sms
+}
+
+/// What to do with a session after looking at it.
+enum Update {
+    Keep,
+    Save,
+    Remove,
+}
+
+/// Open verification sessions, kept in the shared state.
+pub struct VerificationSessions {
+    state: Arc<dyn StateStore>,
+    ttl: Duration,
+}
+
+impl VerificationSessions {
+    /// Create a store whose sessions last `ttl`.
+    pub fn new(state: Arc<dyn StateStore>, ttl: Duration) -> Self {
+        Self { state, ttl }
+    }
+
+    /// How long sessions last.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Open a session for `phone_number`, returning its ID.
+    pub async fn start(&self, phone_number: PhoneNumber) -> Result<String, SessionError> {
+        let session_id = generate_hex_nonce();
+        let session = Session {
+            phone_number: phone_number.to_string(),
+            expires_at: Utc::now() + chrono::Duration::from_std(self.ttl).unwrap_or_default(),
+            attempts: 0,
+            verified: false,
+            method: default_method(),
+        };
+        let value = serde_json::to_string(&session).unwrap_or_default();
+        if !self.state.compare_and_swap(&session_key(&session_id), None, &value, session.expires_at).await? {
+            // Only a repeated random ID could already be there
+            return Err(SessionError::Unavailable);
+        }
+        Ok(session_id)
+    }
+
+    /// Close a session whose verification could not be started.
+    pub async fn cancel(&self, session_id: &str) {
+        let _ = self.update(session_id, |_| (Update::Remove, Ok(()))).await;
+    }
+
+    /// Record how the verifier is confirming the session's number.
+    pub async fn set_method(&self, session_id: &str, method: VerificationMethod) -> Result<(), SessionError> {
+        self.update(session_id, |session| {
+            session.method = method;
+            (Update::Save, Ok(()))
+        })
+        .await
+    }
+
+    /// Count a code check against the session's attempts.
+    /// 
+    /// Attempts are taken before the code is checked, so concurrent guesses
+    /// cannot exceed the limit.
+    pub async fn begin_check(&self, session_id: &str) -> Result<PendingCheck, SessionError> {
+        self.update(session_id, |session| {
+            if session.verified {
+                return (Update::Keep, Ok(PendingCheck::Verified));
+            }
+            if session.attempts >= MAX_ATTEMPTS {
+                return (Update::Remove, Err(SessionError::TooManyAttempts));
+            }
+            session.attempts += 1;
+            match PhoneNumber::new(&session.phone_number) {
+                Ok(phone_number) => (Update::Save, Ok(PendingCheck::Check(phone_number))),
+                Err(_) => (Update::Remove, Err(SessionError::NotFound)),
+            }
+        })
+        .await
+    }
+
+    /// Record that the session's code was correct.
+    pub async fn mark_verified(&self, session_id: &str) -> Result<(), SessionError> {
+        self.update(session_id, |session| {
+            session.verified = true;
+            (Update::Save, Ok(()))
+        })
+        .await
+    }
+
+    /// Phone number of a verified session, leaving the session open.
+    pub async fn verified_phone(&self, session_id: &str) -> Result<PhoneNumber, SessionError> {
+        self.update(session_id, |session| {
+            if !session.verified {
+                return (Update::Keep, Err(SessionError::NotVerified));
+            }
+            let phone_number = PhoneNumber::new(&session.phone_number).map_err(|_| SessionError::NotFound);
+            (Update::Keep, phone_number)
+        })
+        .await
+    }
+
+    /// Use up a verified session, returning its phone number and how it
+    /// was verified.
+    pub async fn take_verified(&self, session_id: &str) -> Result<(PhoneNumber, VerificationMethod), SessionError> {
+        self.update(session_id, |session| {
+            if !session.verified {
+                return (Update::Keep, Err(SessionError::NotVerified));
+            }
+            let phone_number = PhoneNumber::new(&session.phone_number).map_err(|_| SessionError::NotFound);
+            (Update::Remove, phone_number.map(|phone_number| (phone_number, session.method)))
+        })
+        .await
+    }
+
+    /// Apply `change` to a live session, starting over if another request
+    /// changed it in the meantime.
+    async fn update<T>(
+        &self,
+        session_id: &str,
+        mut change: impl FnMut(&mut Session) -> (Update, Result<T, SessionError>),
+    ) -> Result<T, SessionError> {
+        let key = session_key(session_id);
+        for _ in 0..MAX_UPDATE_ATTEMPTS {
+            let Some(current) = self.state.get(&key).await? else {
+                return Err(SessionError::NotFound);
+            };
+            let mut session: Session = serde_json::from_str(&current)
+                .map_err(|e| SessionError::from(StorageError::Corrupt(e.to_string())))?;
+            let (update, result) = change(&mut session);
+            let applied = match update {
+                Update::Keep => true,
+                Update::Save => {
+                    let value = serde_json::to_string(&session).unwrap_or_default();
+                    self.state.compare_and_swap(&key, Some(&current), &value, session.expires_at).await?
+                }
+                Update::Remove => self.state.compare_and_remove(&key, &current).await?,
+            };
+            if applied {
+                return result;
+            }
+        }
+        Err(SessionError::Unavailable)
+    }
+}
+
+fn session_key(session_id: &str) -> String {
+    format!("verification:{}", session_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phone() -> PhoneNumber {
+        PhoneNumber::new("+1234567890").unwrap()
+    }
+
+    fn state() -> Arc<dyn StateStore> {
+        Arc::new(hesha_storage::MemoryStore::new())
+    }
+
+    #[tokio::test]
+    async fn test_verify_and_take() {
+        let sessions = VerificationSessions::new(state(), SESSION_TTL);
+        let id = sessions.start(phone()).await.unwrap();
+
+        assert_eq!(sessions.take_verified(&id).await, Err(SessionError::NotVerified));
+        assert_eq!(sessions.begin_check(&id).await, Ok(PendingCheck::Check(phone())));
+        sessions.mark_verified(&id).await.unwrap();
+        assert_eq!(sessions.begin_check(&id).await, Ok(PendingCheck::Verified));
+        assert_eq!(sessions.take_verified(&id).await, Ok((phone(), VerificationMethod::Sms)));
+
+        // Sessions are single use
+        assert_eq!(sessions.take_verified(&id).await, Err(SessionError::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_attempt_limit() {
+        let sessions = VerificationSessions::new(state(), SESSION_TTL);
+        let id = sessions.start(phone()).await.unwrap();
+
+        for _ in 0..MAX_ATTEMPTS {
+            assert!(sessions.begin_check(&id).await.is_ok());
+        }
+        assert_eq!(sessions.begin_check(&id).await, Err(SessionError::TooManyAttempts));
+        assert_eq!(sessions.begin_check(&id).await, Err(SessionError::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_expiry() {
+        let sessions = VerificationSessions::new(state(), Duration::ZERO);
+        let id = sessions.start(phone()).await.unwrap();
+        assert_eq!(sessions.begin_check(&id).await, Err(SessionError::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_sessions_shared_between_replicas() {
+        let state = state();
+        let first = Arc::new(VerificationSessions::new(state.clone(), SESSION_TTL));
+        let second = Arc::new(VerificationSessions::new(state, SESSION_TTL));
+        let id = first.start(phone()).await.unwrap();
+
+        // Concurrent guesses on both replicas share the attempt limit
+        let mut checks = tokio::task::JoinSet::new();
+        for i in 0..MAX_ATTEMPTS + 3 {
+            let sessions = if i % 2 == 0 { first.clone() } else { second.clone() };
+            let id = id.clone();
+            checks.spawn(async move { sessions.begin_check(&id).await });
+        }
+        let mut accepted = 0;
+        while let Some(result) = checks.join_next().await {
+            accepted += usize::from(result.unwrap().is_ok());
+        }
+        assert_eq!(accepted, MAX_ATTEMPTS as usize);
+
+        let id = first.start(phone()).await.unwrap();
+        first.set_method(&id, VerificationMethod::Email).await.unwrap();
+        second.begin_check(&id).await.unwrap();
+        second.mark_verified(&id).await.unwrap();
+        assert_eq!(first.take_verified(&id).await, Ok((phone(), VerificationMethod::Email)));
+        assert_eq!(second.take_verified(&id).await, Err(SessionError::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_failover() {
+        struct Down;
+        #[async_trait]
+        impl ContactVerifier for Down {
+            async fn start(&self, _: &PhoneNumber, _session_id: &str) -> anyhow::Result<Challenge> {
+                anyhow::bail!("provider down")
+            }
+            async fn check_code(&self, _: &PhoneNumber, _: &str) -> anyhow::Result<bool> {
+                anyhow::bail!("provider down")
+            }
+        }
+        
+        let verifier = FailoverVerifier::new(vec![Arc::new(Down), Arc::new(MockVerifier)], state());
+        assert!(!verifier.check_code(&phone(), MOCK_VERIFICATION_CODE).await.unwrap());
+        verifier.start(&phone(), "session").await.unwrap();
+        assert!(verifier.check_code(&phone(), MOCK_VERIFICATION_CODE).await.unwrap());
+        
+        let verifier = FailoverVerifier::new(vec![Arc::new(Down)], state());
+        assert!(verifier.start(&phone(), "session").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_verifier() {
+        let verifier = from_config(&VerificationConfig::Mock, &state()).unwrap();
+        verifier.start(&phone(), "session").await.unwrap();
+        assert!(verifier.check_code(&phone(), MOCK_VERIFICATION_CODE).await.unwrap());
+        assert!(!verifier.check_code(&phone(), "000000").await.unwrap());
+    }
+}
@@ -0,0 +1,159 @@
+//! Vonage (formerly Nexmo) Verify provider for phone verification.
+//!
+//! Vonage generates, sends and checks the codes. Checks are made against
+//! the Vonage request ID, so the verifier remembers the latest request for
+//! each phone number until it is approved or expires.
+
+use crate::verification::{Challenge, ContactVerifier, SESSION_TTL};
+use async_trait::async_trait;
+use hesha_types::PhoneNumber;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Vonage Verify v2 API URL.
+const VONAGE_VERIFY_URL: &str = "https://api.nexmo.com/v2/verify";
+
+/// Verifier backed by Vonage Verify.
+pub struct VonageVerifier {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    api_secret: String,
+    brand: String,
+    /// Pending Vonage request IDs, by phone number.
+    requests: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+/// Response to starting a verification.
+#[derive(Debug, Deserialize)]
+struct StartResponse {
+    request_id: String,
+}
+
+impl VonageVerifier {
+    /// Create a verifier whose messages are signed with `brand`.
+    pub fn new(api_key: String, api_secret: String, brand: String) -> Self {
+        Self::with_base_url(VONAGE_VERIFY_URL, api_key, api_secret, brand)
+    }
+
+    /// Create a verifier against a different API endpoint.
+    pub fn with_base_url(base_url: &str, api_key: String, api_secret: String, brand: String) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            api_secret,
+            brand,
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn post(&self, url: String, body: serde_json::Value) -> anyhow::Result<reqwest::Response> {
+        Ok(self.client
+            .post(url)
+            .basic_auth(&self.api_key, Some(&self.api_secret))
+            .json(&body)
+            .send()
+            .await?)
+    }
+}
+
+#[async_trait]
+impl ContactVerifier for VonageVerifier {
+    async fn start(&self, phone_number: &PhoneNumber, _session_id: &str) -> anyhow::Result<Challenge> {
+        // Vonage expects E.164 digits without the leading '+'
+        let to = phone_number.to_string().trim_start_matches('+').to_string();
+        let response = self.post(self.base_url.clone(), json!({
+            "brand": self.brand,
+            "workflow": [{ "channel": "sms", "to": to }],
+        }))
+        .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("Vonage returned {} starting verification", response.status());
+        }
+        let started: StartResponse = response.json().await?;
+
+        let mut requests = self.requests.lock().unwrap_or_else(|e| e.into_inner());
+        requests.retain(|_, (_, sent_at)| sent_at.elapsed() < SESSION_TTL);
+        requests.insert(phone_number.to_string(), (started.request_id, Instant::now()));
+        Ok(Challenge::Code)
+    }
+
+    async fn check_code(&self, phone_number: &PhoneNumber, code: &str) -> anyhow::Result<bool> {
+        let request_id = self.requests.lock().unwrap_or_else(|e| e.into_inner())
+            .get(&phone_number.to_string())
+            .map(|(request_id, _)| request_id.clone());
+        let Some(request_id) = request_id else {
+            return Ok(false);
+        };
+
+        let url = format!("{}/{}", self.base_url, request_id);
+        let response = self.post(url, json!({ "code": code })).await?;
+        match response.status().as_u16() {
+            200 => {
+                self.requests.lock().unwrap_or_else(|e| e.into_inner())
+                    .remove(&phone_number.to_string());
+                Ok(true)
+            }
+            // Wrong code, or a request that has expired or been used up
+            400 | 404 | 410 => Ok(false),
+            status => anyhow::bail!("Vonage returned {} checking verification", status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_send_and_check() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/"))
+            .and(header_exists("authorization"))
+            .and(body_partial_json(json!({ "brand": "Acme", "workflow": [{ "to": "1234567890" }] })))
+            .respond_with(ResponseTemplate::new(202).set_body_json(json!({
+                "request_id": "req-1"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/req-1"))
+            .and(body_partial_json(json!({ "code": "424242" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "request_id": "req-1",
+                "status": "completed"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/req-1"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "title": "Invalid Code"
+            })))
+            .mount(&server)
+            .await;
+
+        let verifier = VonageVerifier::with_base_url(
+            &server.uri(),
+            "key".to_string(),
+            "secret".to_string(),
+            "Acme".to_string(),
+        );
+        let phone = PhoneNumber::new("+1234567890").unwrap();
+        assert!(!verifier.check_code(&phone, "424242").await.unwrap());
+        verifier.start(&phone, "session").await.unwrap();
+        assert!(!verifier.check_code(&phone, "000000").await.unwrap());
+        assert!(verifier.check_code(&phone, "424242").await.unwrap());
+    }
+}
@@ -0,0 +1,290 @@
+//! Webhook notifications for issuance, revocation and verification events.
+//!
+//! Each event is POSTed as JSON to every configured endpoint that wants its
+//! kind, signed with the endpoint's secret in [`WEBHOOK_SIGNATURE_HEADER`].
+//! Deliveries run in the background, so a slow or failing endpoint never
+//! holds up the request that raised the event. Failed deliveries wait in a
+//! retry queue with backoff, which the scheduler's `webhook_retries` job
+//! drains.
+
+use chrono::Utc;
+use hesha_crypto::hmac_sha256;
+use hesha_types::{WebhookConfig, WebhookEvent, WebhookEventData};
+#[cfg(feature = "webhooks")]
+use hesha_types::WEBHOOK_SIGNATURE_HEADER;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Waits between delivery attempts; an event is dropped after the last.
+const RETRY_DELAYS: [Duration; 5] = [
+    Duration::from_secs(1),
+    Duration::from_secs(10),
+    Duration::from_secs(60),
+    Duration::from_secs(300),
+    Duration::from_secs(1800),
+];
+
+/// Configured webhook endpoints.
+pub struct Webhooks {
+    endpoints: Vec<Arc<Endpoint>>,
+    #[cfg_attr(not(feature = "webhooks"), allow(dead_code))]
+    retry_delays: Arc<[Duration]>,
+    retries: Arc<Mutex<Vec<Retry>>>,
+    #[cfg(feature = "webhooks")]
+    client: reqwest::Client,
+}
+
+/// A failed delivery waiting for its next attempt.
+#[cfg_attr(not(feature = "webhooks"), allow(dead_code))]
+struct Retry {
+    endpoint: Arc<Endpoint>,
+    event_id: String,
+    body: Arc<[u8]>,
+    /// Attempts made so far.
+    attempts: usize,
+    due: Instant,
+}
+
+/// An endpoint and its signing secret.
+#[cfg_attr(not(feature = "webhooks"), allow(dead_code))]
+struct Endpoint {
+    config: WebhookConfig,
+    secret: String,
+}
+
+impl Default for Webhooks {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl Webhooks {
+    /// Endpoints from issuer.toml, with secrets read from the environment.
+    pub fn from_config(configs: &[WebhookConfig]) -> anyhow::Result<Self> {
+        #[cfg(not(feature = "webhooks"))]
+        if !configs.is_empty() {
+            anyhow::bail!("Webhooks require issuer-node built with the 'webhooks' feature");
+        }
+
+        let endpoints = configs
+            .iter()
+            .map(|config| {
+                let secret = std::env::var(&config.secret_env).map_err(|_| {
+                    anyhow::anyhow!("{} must be set for webhook {}", config.secret_env, config.url)
+                })?;
+                Ok((config.clone(), secret))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self::new(endpoints))
+    }
+
+    /// Endpoints with their secrets.
+    pub fn new(endpoints: Vec<(WebhookConfig, String)>) -> Self {
+        Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|(config, secret)| Arc::new(Endpoint { config, secret }))
+                .collect(),
+            retry_delays: RETRY_DELAYS.into(),
+            retries: Arc::default(),
+            #[cfg(feature = "webhooks")]
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Wait differently between delivery attempts.
+    #[cfg(all(test, feature = "webhooks"))]
+    pub fn with_retry_delays(mut self, retry_delays: Vec<Duration>) -> Self {
+        self.retry_delays = retry_delays.into();
+        self
+    }
+
+    /// Queue an event for delivery to the endpoints that want it.
+    pub fn emit(&self, data: WebhookEventData) {
+        let kind = data.kind();
+        let endpoints: Vec<_> = self.endpoints
+            .iter()
+            .filter(|endpoint| endpoint.config.wants(kind))
+            .cloned()
+            .collect();
+        if endpoints.is_empty() {
+            return;
+        }
+
+        let event = WebhookEvent {
+            id: format!("evt_{}", uuid::Uuid::new_v4().simple()),
+            created_at: Utc::now(),
+            data,
+        };
+        let body: Arc<[u8]> = match serde_json::to_vec(&event) {
+            Ok(body) => body.into(),
+            Err(e) => {
+                tracing::error!("Failed to encode webhook event: {}", e);
+                return;
+            }
+        };
+
+        #[cfg(feature = "webhooks")]
+        for endpoint in endpoints {
+            self.spawn_attempt(Retry {
+                endpoint,
+                event_id: event.id.clone(),
+                body: body.clone(),
+                attempts: 0,
+                due: Instant::now(),
+            });
+        }
+        #[cfg(not(feature = "webhooks"))]
+        let _ = (endpoints, body);
+    }
+
+    /// Start the next attempt of every retry that is due, returning how
+    /// many were started.
+    pub fn drain_retries(&self) -> usize {
+        let now = Instant::now();
+        let due: Vec<_> = {
+            let mut retries = self.retries.lock().unwrap_or_else(|e| e.into_inner());
+            let (due, waiting) = retries.drain(..).partition(|retry| retry.due <= now);
+            *retries = waiting;
+            due
+        };
+        let count = due.len();
+        #[cfg(feature = "webhooks")]
+        for retry in due {
+            self.spawn_attempt(retry);
+        }
+        count
+    }
+
+    /// Deliveries waiting to be retried.
+    pub fn pending_retries(&self) -> usize {
+        self.retries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    #[cfg(feature = "webhooks")]
+    fn spawn_attempt(&self, retry: Retry) {
+        tokio::spawn(deliver(
+            self.client.clone(),
+            retry,
+            self.retry_delays.clone(),
+            self.retries.clone(),
+        ));
+    }
+}
+
+/// Signature header value for a delivery body sent at `timestamp`.
+#[cfg_attr(not(feature = "webhooks"), allow(dead_code))]
+pub fn signature_header(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut message = format!("{}.", timestamp).into_bytes();
+    message.extend_from_slice(body);
+    format!("t={},v1={}", timestamp, hex::encode(hmac_sha256(secret.as_bytes(), &message)))
+}
+
+/// POST an event once, queueing a retry if the endpoint doesn't accept it
+/// and attempts remain.
+#[cfg(feature = "webhooks")]
+async fn deliver(
+    client: reqwest::Client,
+    mut retry: Retry,
+    retry_delays: Arc<[Duration]>,
+    retries: Arc<Mutex<Vec<Retry>>>,
+) {
+    let url = &retry.endpoint.config.url;
+    let signature = signature_header(&retry.endpoint.secret, Utc::now().timestamp(), &retry.body);
+    let result = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(WEBHOOK_SIGNATURE_HEADER, signature)
+        .body(retry.body.to_vec())
+        .send()
+        .await;
+    match result {
+        Ok(response) if response.status().is_success() => return,
+        Ok(response) => {
+            tracing::warn!("Webhook {} answered {} to event {}", url, response.status(), retry.event_id);
+        }
+        Err(e) => tracing::warn!("Failed to deliver event {} to webhook {}: {}", retry.event_id, url, e),
+    }
+
+    let Some(delay) = retry_delays.get(retry.attempts) else {
+        tracing::error!("Giving up on delivering event {} to webhook {}", retry.event_id, url);
+        return;
+    };
+    retry.attempts += 1;
+    retry.due = Instant::now() + *delay;
+    retries.lock().unwrap_or_else(|e| e.into_inner()).push(retry);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_header() {
+        let header = signature_header("secret", 1700000000, b"{}");
+        let expected = hmac_sha256(b"secret", b"1700000000.{}");
+        assert_eq!(header, format!("t=1700000000,v1={}", hex::encode(expected)));
+        assert_ne!(header, signature_header("secret", 1700000001, b"{}"));
+    }
+
+    #[cfg(feature = "webhooks")]
+    #[tokio::test]
+    async fn test_delivery_retries() {
+        use hesha_types::{WebhookEventKind, WEBHOOK_SIGNATURE_HEADER};
+        use wiremock::matchers::{header_exists, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(header_exists(WEBHOOK_SIGNATURE_HEADER))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let config = |events| WebhookConfig {
+            url: server.uri(),
+            events,
+            secret_env: String::new(),
+        };
+        let webhooks = Webhooks::new(vec![
+            (config(vec![WebhookEventKind::AttestationRevoked]), "secret".to_string()),
+            (config(vec![WebhookEventKind::VerificationFailed]), "secret".to_string()),
+        ])
+        .with_retry_delays(vec![Duration::from_millis(10)]);
+        webhooks.emit(WebhookEventData::AttestationRevoked {
+            jti: "revoked-jti".to_string(),
+            reason: None,
+        });
+
+        // The failed first attempt waits in the queue until drained
+        let mut received = Vec::new();
+        for _ in 0..100 {
+            webhooks.drain_retries();
+            received = server.received_requests().await.unwrap();
+            if received.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(webhooks.pending_retries(), 0);
+        assert_eq!(received.len(), 2);
+        let event: WebhookEvent = serde_json::from_slice(&received[1].body).unwrap();
+        assert_eq!(event.data.kind(), WebhookEventKind::AttestationRevoked);
+
+        let (_, signature) = received[1].headers.iter()
+            .find(|(name, _)| name.as_str().eq_ignore_ascii_case(WEBHOOK_SIGNATURE_HEADER))
+            .unwrap();
+        // wiremock splits header values at commas
+        let signature = signature.iter().map(|value| value.as_str()).collect::<Vec<_>>().join(",");
+        let timestamp = signature[2..signature.find(',').unwrap()].parse().unwrap();
+        assert_eq!(signature, signature_header("secret", timestamp, &received[1].body));
+    }
+}
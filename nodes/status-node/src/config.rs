@@ -0,0 +1,54 @@
+//! Status node configuration.
+//!
+//! Every setting is a flag with an `HESHA_STATUS_*` environment variable.
+
+use clap::Parser;
+use hesha_crypto::keypair_from_private;
+use hesha_types::{KeyPair, PrivateKey};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// Hesha attestation status responder.
+#[derive(Debug, Clone, Parser)]
+#[command(name = "status-node", version, about)]
+pub struct Config {
+    /// Address to listen on.
+    #[arg(long, env = "HESHA_STATUS_BIND_ADDRESS", default_value = "0.0.0.0:3300")]
+    pub bind_address: SocketAddr,
+
+    /// Storage of the issuer node whose attestations are answered for:
+    /// `sqlite:PATH` or `postgres://...`.
+    #[arg(long, env = "HESHA_STATUS_STORAGE_URL")]
+    pub storage_url: String,
+
+    /// Issuer domain named in the responses.
+    #[arg(long, env = "HESHA_STATUS_DOMAIN", default_value = "localhost")]
+    pub domain: String,
+
+    /// Issuer private key file responses are signed with.
+    #[arg(long, env = "HESHA_STATUS_PRIVATE_KEY")]
+    pub private_key: PathBuf,
+
+    /// Identifier of that key in the issuer's key document.
+    #[arg(long, env = "HESHA_STATUS_KEY_ID", default_value = "default")]
+    pub key_id: String,
+
+    /// How long a response stays valid, in seconds.
+    #[arg(long, env = "HESHA_STATUS_VALIDITY_SECS", default_value_t = 300)]
+    pub validity_secs: i64,
+}
+
+impl Config {
+    /// The signing key pair.
+    pub fn keypair(&self) -> anyhow::Result<KeyPair> {
+        let path = &self.private_key;
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read key {}: {}", path.display(), e))?;
+        Ok(keypair_from_private(&PrivateKey::from_base64(data.trim())?)?)
+    }
+
+    /// Validity of a response.
+    pub fn validity(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.validity_secs)
+    }
+}
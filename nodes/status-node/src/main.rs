@@ -0,0 +1,63 @@
+//! Hesha Protocol attestation status responder.
+//!
+//! A lightweight service answering `GET /status/{jti}` with a signed,
+//! short-lived good, revoked or unknown, read from an issuer node's
+//! storage. Verifiers that can't download the full revocation list check
+//! single attestations here; the issuer node serves the same route itself,
+//! and this node takes the load off it.
+
+mod config;
+mod status;
+
+use crate::config::Config;
+use crate::status::AppState;
+use axum::{routing::get, Json, Router};
+use clap::Parser;
+use hesha_node::shutdown_signal;
+use tower_http::trace::TraceLayer;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "status_node=debug,tower_http=info".into()),
+        )
+        .init();
+
+    let config = Config::parse();
+    let addr = config.bind_address;
+    let state = AppState::open(config).await?;
+
+    tracing::info!(
+        "Status responder for {} listening on {}",
+        state.config.domain,
+        addr
+    );
+    tracing::info!("  GET    /status/{{jti}}  - Signed status of one attestation");
+    tracing::info!("  GET    /health        - Health check");
+
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    axum::serve(listener, app(state))
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+    Ok(())
+}
+
+/// The node's routes.
+fn app(state: AppState) -> Router {
+    Router::new()
+        .route("/status/:jti", get(status::status))
+        .route("/health", get(health))
+        .fallback(hesha_node::not_found)
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
+
+/// `GET /health`.
+async fn health() -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+    }))
+}
@@ -0,0 +1,187 @@
+//! `GET /status/{jti}`.
+//!
+//! Statuses are read from the issuer node's storage on every request, so a
+//! revocation shows as soon as the issuer records it. Each response is
+//! signed with the issuer key and valid for the configured time.
+
+use crate::config::Config;
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use hesha_core::sign_status_response;
+use hesha_node::ApiError;
+use hesha_storage::Storage;
+use hesha_types::{ErrorCode, KeyPair, StatusResponse};
+use std::sync::Arc;
+
+/// Longest JWT ID looked up.
+const MAX_JTI_LEN: usize = 128;
+
+/// State shared by every request.
+#[derive(Clone)]
+pub struct AppState {
+    /// Node configuration.
+    pub config: Arc<Config>,
+    /// The issuer node's storage.
+    pub storage: Storage,
+    /// Key responses are signed with.
+    pub keypair: Arc<KeyPair>,
+}
+
+impl AppState {
+    /// Open the configured storage and key.
+    pub async fn open(config: Config) -> anyhow::Result<Self> {
+        let storage = Storage::open(&config.storage_url)
+            .await
+            .map_err(|e| anyhow::anyhow!("Cannot open storage: {}", e))?;
+        Ok(Self {
+            keypair: Arc::new(config.keypair()?),
+            config: Arc::new(config),
+            storage,
+        })
+    }
+}
+
+/// Handle a status request.
+pub async fn status(State(state): State<AppState>, Path(jti): Path<String>) -> Response {
+    if jti.is_empty() || jti.len() > MAX_JTI_LEN {
+        return ApiError::new(ErrorCode::InvalidRequest, "Invalid attestation ID").into_response();
+    }
+    let status = match state.storage.attestation_status(&jti).await {
+        Ok(status) => status,
+        Err(e) => {
+            tracing::error!("Failed to look up status of {}: {}", jti, e);
+            return ApiError::new(
+                ErrorCode::StorageFailed,
+                "Failed to look up attestation status",
+            )
+            .into_response();
+        }
+    };
+
+    let mut response = StatusResponse::new(
+        state.config.domain.clone(),
+        jti,
+        status,
+        state.config.validity(),
+    );
+    response.key_id = Some(state.config.key_id.clone());
+    if let Err(e) = sign_status_response(&mut response, &state.keypair.private) {
+        tracing::error!("Failed to sign status response: {}", e);
+        return ApiError::new(ErrorCode::SigningFailed, "Failed to sign status response")
+            .into_response();
+    }
+
+    (
+        [(
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", state.config.validity_secs),
+        )],
+        Json(response),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, http::StatusCode};
+    use chrono::Utc;
+    use clap::Parser;
+    use hesha_core::verify_status_response;
+    use hesha_crypto::generate_keypair;
+    use hesha_storage::StoredAttestation;
+    use hesha_types::{AttestationStatus, PublicKey, RevocationEntry};
+    use tower::util::ServiceExt;
+
+    #[tokio::test]
+    async fn test_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let keypair = generate_keypair().unwrap();
+        let key_path = dir.path().join("private.key");
+        std::fs::write(&key_path, keypair.private.to_base64()).unwrap();
+        let storage_url = format!("sqlite:{}", dir.path().join("issuer.db").display());
+
+        // Records written by the issuer node
+        let issuer = Storage::open(&storage_url).await.unwrap();
+        for jti in ["good", "revoked"] {
+            issuer
+                .attestations
+                .insert(&StoredAttestation {
+                    jti: jti.to_string(),
+                    proxy_number: "+990012345678".to_string(),
+                    scope: "1".to_string(),
+                    issued_at: Utc::now(),
+                    expires_at: Utc::now() + chrono::Duration::days(30),
+                    renews: None,
+                })
+                .await
+                .unwrap();
+        }
+
+        let config = Config::parse_from([
+            "status-node",
+            "--storage-url",
+            &storage_url,
+            "--domain",
+            "issuer.example.com",
+            "--private-key",
+            key_path.to_str().unwrap(),
+            "--key-id",
+            "k1",
+        ]);
+        let state = AppState::open(config).await.unwrap();
+        let status = |jti: &str| check(state.clone(), keypair.public.clone(), jti.to_string());
+        assert_eq!(status("good").await, AttestationStatus::Good);
+        assert_eq!(status("other").await, AttestationStatus::Unknown);
+
+        // A revocation recorded by the issuer shows at once
+        assert_eq!(status("revoked").await, AttestationStatus::Good);
+        issuer
+            .revocations
+            .revoke(&RevocationEntry {
+                jti: "revoked".to_string(),
+                revoked_at: Utc::now(),
+                reason: None,
+            })
+            .await
+            .unwrap();
+        assert!(matches!(
+            status("revoked").await,
+            AttestationStatus::Revoked { .. }
+        ));
+
+        let response = get(state, &"x".repeat(MAX_JTI_LEN + 1)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    async fn get(state: AppState, jti: &str) -> Response {
+        crate::app(state)
+            .oneshot(
+                Request::get(format!("/status/{}", jti))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    /// The verified status of `jti`.
+    async fn check(state: AppState, key: PublicKey, jti: String) -> AttestationStatus {
+        let response = get(state, &jti).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let status: StatusResponse = serde_json::from_slice(&body).unwrap();
+        verify_status_response(&status, &key).unwrap();
+        assert_eq!(
+            (status.issuer.as_str(), status.jti.as_str(), status.key_id.as_deref()),
+            ("issuer.example.com", jti.as_str(), Some("k1"))
+        );
+        status.status
+    }
+}
@@ -0,0 +1,77 @@
+//! Verifier node configuration.
+//!
+//! Every setting is a flag with an `HESHA_VERIFIER_*` environment variable;
+//! acceptance rules beyond a valid signature come from a
+//! [`VerificationPolicy`] file.
+
+use clap::Parser;
+use hesha_core::VerificationPolicy;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Hesha verifier node.
+#[derive(Debug, Clone, Parser)]
+#[command(name = "verifier-node", version, about)]
+pub struct Config {
+    /// Address to listen on.
+    #[arg(long, env = "HESHA_VERIFIER_BIND_ADDRESS", default_value = "0.0.0.0:3100")]
+    pub bind_address: SocketAddr,
+
+    /// Verification policy file (allowed issuers and audiences, validity,
+    /// clock skew). Without one, the protocol's baseline applies.
+    #[arg(long, env = "HESHA_VERIFIER_POLICY")]
+    pub policy: Option<PathBuf>,
+
+    /// How long issuer keys are cached, in seconds.
+    #[arg(long, env = "HESHA_VERIFIER_KEY_TTL_SECS", default_value_t = 3600)]
+    pub key_ttl_secs: u64,
+
+    /// How long revocation lists are cached, in seconds.
+    #[arg(long, env = "HESHA_VERIFIER_REVOCATION_TTL_SECS", default_value_t = 60)]
+    pub revocation_ttl_secs: u64,
+
+    /// How old a challenge may be when its response is checked, in seconds.
+    #[arg(
+        long,
+        env = "HESHA_VERIFIER_CHALLENGE_MAX_AGE_SECS",
+        default_value_t = hesha_core::verification::CHALLENGE_MAX_AGE_SECS
+    )]
+    pub challenge_max_age_secs: i64,
+
+    /// Largest request body accepted, in bytes.
+    #[arg(long, env = "HESHA_VERIFIER_MAX_BODY_BYTES", default_value_t = 64 * 1024)]
+    pub max_body_bytes: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::parse_from(["verifier-node"])
+    }
+}
+
+impl Config {
+    /// The policy file's rules, or the baseline without one.
+    pub fn policy(&self) -> anyhow::Result<VerificationPolicy> {
+        match &self.policy {
+            Some(path) => VerificationPolicy::from_file(path)
+                .map_err(|e| anyhow::anyhow!("Failed to load policy {}: {}", path.display(), e)),
+            None => Ok(VerificationPolicy::default()),
+        }
+    }
+
+    /// Lifetime of cached issuer keys.
+    pub fn key_ttl(&self) -> Duration {
+        Duration::from_secs(self.key_ttl_secs)
+    }
+
+    /// Lifetime of cached revocation lists.
+    pub fn revocation_ttl(&self) -> Duration {
+        Duration::from_secs(self.revocation_ttl_secs)
+    }
+
+    /// Oldest challenge whose response is accepted.
+    pub fn challenge_max_age(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.challenge_max_age_secs)
+    }
+}
@@ -0,0 +1,369 @@
+//! Issuer keys and revocation lists, cached per trust domain.
+//!
+//! An issuer's discovery document is fetched through its trust domain and
+//! kept for the key TTL. Its revocation list is kept for the much shorter
+//! revocation TTL, so a revoked attestation stops verifying within that
+//! time, and each page of it must be signed with one of the issuer's
+//! published keys.
+//!
+//! Cached keys follow the issuer's rotations and withdrawals early: an
+//! attestation naming a key the cached document doesn't list, a signature
+//! that fails with a cached key, or a revocation list signed with a key
+//! not cached yet all fetch the document again. Such refreshes happen at
+//! most once per [`MIN_REFRESH`] per issuer, so garbage attestations can't
+//! make the node hammer an issuer.
+
+use hesha_core::verification::resolve_issuer_info;
+use hesha_core::verify_revocation_list;
+use hesha_types::{
+    HeshaError, HeshaResult, IssuerInfo, PublicKey, RevocationList, RevocationSummary,
+};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Shortest time between two fetches of an issuer's keys.
+pub const MIN_REFRESH: Duration = Duration::from_secs(10);
+
+/// How long past its TTL a revocation list is still used while its issuer
+/// can't be reached. Past that, verification fails rather than trusting an
+/// old list.
+pub const STALE_REVOCATIONS_GRACE: Duration = Duration::from_secs(3600);
+
+/// Where issuers publish their revocation list.
+const REVOCATIONS_PATH: &str = "/.well-known/hesha/revocations.json";
+
+/// Most revocation list pages followed.
+const MAX_REVOCATION_PAGES: usize = 1000;
+
+/// What is cached about one issuer.
+#[derive(Clone)]
+struct Entry {
+    service_domain: String,
+    info: IssuerInfo,
+    fetched: Instant,
+    revocations: Option<(RevocationList, Instant)>,
+}
+
+impl Entry {
+    /// Every key the issuer publishes.
+    fn keys(&self) -> impl Iterator<Item = &PublicKey> {
+        std::iter::once(&self.info.public_key).chain(self.info.keys.iter().map(|key| &key.public_key))
+    }
+}
+
+/// Cached issuer keys and revocation lists.
+pub struct Issuers {
+    client: reqwest::Client,
+    key_ttl: Duration,
+    revocation_ttl: Duration,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl Issuers {
+    /// An empty cache.
+    pub fn new(key_ttl: Duration, revocation_ttl: Duration) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            key_ttl,
+            revocation_ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The key named `key_id` (the issuer's current key without one).
+    ///
+    /// Fails with [`HeshaError::InvalidAttestation`] when the issuer
+    /// doesn't publish the key, and with other errors when it can't be
+    /// reached.
+    pub async fn key(&self, trust_domain: &str, key_id: Option<&str>) -> HeshaResult<PublicKey> {
+        if let Some(entry) = self.entry(trust_domain) {
+            let age = entry.fetched.elapsed();
+            match entry.info.key(key_id) {
+                Some(key) if age < self.key_ttl => return Ok(key.clone()),
+                None if age < MIN_REFRESH => return Err(unknown_key(trust_domain, key_id)),
+                _ => {}
+            }
+        }
+        let entry = self.refresh(trust_domain).await?;
+        entry.info.key(key_id).cloned().ok_or_else(|| unknown_key(trust_domain, key_id))
+    }
+
+    /// Fetch the key again after a signature failed with `failed`, returning
+    /// it if the issuer now publishes a different one under the same name.
+    pub async fn recheck_key(
+        &self,
+        trust_domain: &str,
+        key_id: Option<&str>,
+        failed: &PublicKey,
+    ) -> HeshaResult<Option<PublicKey>> {
+        if self.entry(trust_domain).is_some_and(|entry| entry.fetched.elapsed() < MIN_REFRESH) {
+            return Ok(None);
+        }
+        let entry = self.refresh(trust_domain).await?;
+        Ok(entry.info.key(key_id).filter(|key| *key != failed).cloned())
+    }
+
+    /// Whether the issuer revoked the attestation `jti`.
+    pub async fn revocation(&self, trust_domain: &str, jti: &str) -> HeshaResult<RevocationSummary> {
+        let entry = match self.entry(trust_domain) {
+            Some(entry) => entry,
+            None => self.refresh(trust_domain).await?,
+        };
+        if let Some((list, fetched)) = &entry.revocations {
+            if fetched.elapsed() < self.revocation_ttl {
+                return Ok(summary(list, jti));
+            }
+        }
+
+        let fetched = match self.fetch_revocations(&entry).await {
+            // Signed with a key published since the document was cached
+            Err(HeshaError::InvalidSignature) if entry.fetched.elapsed() >= MIN_REFRESH => {
+                let entry = self.refresh(trust_domain).await?;
+                self.fetch_revocations(&entry).await
+            }
+            fetched => fetched,
+        };
+        match fetched {
+            Ok(list) => {
+                let revocation = summary(&list, jti);
+                if let Some(entry) = self.lock().get_mut(trust_domain) {
+                    entry.revocations = Some((list, Instant::now()));
+                }
+                Ok(revocation)
+            }
+            Err(e) => match entry.revocations {
+                Some((list, fetched)) if fetched.elapsed() < self.revocation_ttl + STALE_REVOCATIONS_GRACE => {
+                    tracing::warn!("Using a stale revocation list for {}: {}", trust_domain, e);
+                    Ok(summary(&list, jti))
+                }
+                _ => Err(e),
+            },
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn entry(&self, trust_domain: &str) -> Option<Entry> {
+        self.lock().get(trust_domain).cloned()
+    }
+
+    /// Fetch the issuer's discovery document, keeping its revocation list.
+    async fn refresh(&self, trust_domain: &str) -> HeshaResult<Entry> {
+        let (service_domain, info) = resolve_issuer_info(trust_domain).await?;
+        tracing::debug!("Fetched keys of {} from {}", trust_domain, service_domain);
+        let mut entries = self.lock();
+        let revocations = entries.remove(trust_domain).and_then(|entry| entry.revocations);
+        let entry = Entry {
+            service_domain,
+            info,
+            fetched: Instant::now(),
+            revocations,
+        };
+        entries.insert(trust_domain.to_string(), entry.clone());
+        Ok(entry)
+    }
+
+    /// Fetch every page of the issuer's revocation list, checking each
+    /// page's signature.
+    async fn fetch_revocations(&self, entry: &Entry) -> HeshaResult<RevocationList> {
+        let mut path = REVOCATIONS_PATH.to_string();
+        let mut merged: Option<RevocationList> = None;
+        for _ in 0..MAX_REVOCATION_PAGES {
+            let response = self
+                .client
+                .get(issuer_url(&entry.service_domain, &path))
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| HeshaError::NetworkError(format!("Revocation list fetch failed: {}", e)))?;
+            let page: RevocationList = response
+                .json()
+                .await
+                .map_err(|e| HeshaError::NetworkError(format!("Invalid revocation list: {}", e)))?;
+            if !entry.keys().any(|key| verify_revocation_list(&page, key).is_ok()) {
+                return Err(HeshaError::InvalidSignature);
+            }
+
+            let next = page.next.clone();
+            match &mut merged {
+                Some(list) => list.entries.extend(page.entries),
+                None => merged = Some(page),
+            }
+            match next {
+                // Pages are only followed on the issuer itself
+                Some(next) if next.starts_with('/') => path = next,
+                Some(next) => {
+                    return Err(HeshaError::NetworkError(format!("Revocation list links off the issuer: {}", next)))
+                }
+                None => {
+                    let mut list = merged.unwrap_or_else(|| RevocationList::new(&entry.service_domain));
+                    list.next = None;
+                    list.signature = None;
+                    return Ok(list);
+                }
+            }
+        }
+        Err(HeshaError::NetworkError(format!(
+            "Revocation list has more than {} pages",
+            MAX_REVOCATION_PAGES
+        )))
+    }
+}
+
+fn unknown_key(trust_domain: &str, key_id: Option<&str>) -> HeshaError {
+    HeshaError::InvalidAttestation(format!(
+        "Issuer {} does not publish key {}",
+        trust_domain,
+        key_id.unwrap_or_default()
+    ))
+}
+
+fn summary(list: &RevocationList, jti: &str) -> RevocationSummary {
+    let entry = list.find(jti);
+    RevocationSummary {
+        revoked: entry.is_some(),
+        revoked_at: entry.map(|entry| entry.revoked_at),
+        reason: entry.and_then(|entry| entry.reason.clone()),
+        list_updated_at: list.updated_at,
+    }
+}
+
+/// URL of `path` on an issuer, over plain HTTP only for local issuers as
+/// in key discovery.
+fn issuer_url(domain: &str, path: &str) -> String {
+    let scheme = if domain.starts_with("localhost") || domain.starts_with("127.0.0.1") {
+        "http"
+    } else {
+        "https"
+    };
+    format!("{}://{}{}", scheme, domain, path)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use chrono::Utc;
+    use hesha_core::sign_revocation_list;
+    use hesha_types::{KeyPair, RevocationEntry};
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Discovery document publishing `keypair` as `key_id`.
+    pub(crate) fn issuer_info(keypair: &KeyPair, key_id: &str) -> IssuerInfo {
+        IssuerInfo {
+            public_key: keypair.public.clone(),
+            algorithm: "Ed25519".to_string(),
+            created_at: Utc::now(),
+            key_id: Some(key_id.to_string()),
+            service_info: None,
+            keys: Vec::new(),
+        }
+    }
+
+    /// A revocation list page signed with `keypair`.
+    pub(crate) fn revocations(keypair: &KeyPair, revoked: &[&str], next: Option<&str>) -> RevocationList {
+        let mut list = RevocationList::new("issuer");
+        list.entries = revoked
+            .iter()
+            .map(|jti| RevocationEntry {
+                jti: jti.to_string(),
+                revoked_at: Utc::now(),
+                reason: Some("lost phone".to_string()),
+            })
+            .collect();
+        list.next = next.map(str::to_string);
+        sign_revocation_list(&mut list, &keypair.private).unwrap();
+        list
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_cached() {
+        let server = MockServer::start().await;
+        let keypair = hesha_crypto::generate_keypair().unwrap();
+        Mock::given(method("GET"))
+            .and(path("/.well-known/hesha/pubkey.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(issuer_info(&keypair, "k1")))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let domain = server.address().to_string();
+        let issuers = Issuers::new(Duration::from_secs(60), Duration::from_secs(60));
+
+        assert_eq!(issuers.key(&domain, Some("k1")).await.unwrap(), keypair.public);
+        assert_eq!(issuers.key(&domain, None).await.unwrap(), keypair.public);
+        // Unknown keys are refused without asking the issuer again right away
+        assert!(matches!(
+            issuers.key(&domain, Some("k2")).await,
+            Err(HeshaError::InvalidAttestation(_))
+        ));
+        assert_eq!(issuers.recheck_key(&domain, Some("k1"), &keypair.public).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_revocations() {
+        let server = MockServer::start().await;
+        let keypair = hesha_crypto::generate_keypair().unwrap();
+        Mock::given(method("GET"))
+            .and(path("/.well-known/hesha/pubkey.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(issuer_info(&keypair, "k1")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(REVOCATIONS_PATH))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(revocations(&keypair, &["b"], None)))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(REVOCATIONS_PATH))
+            .respond_with(ResponseTemplate::new(200).set_body_json(revocations(
+                &keypair,
+                &["a"],
+                Some("/.well-known/hesha/revocations.json?page=2"),
+            )))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let domain = server.address().to_string();
+        let issuers = Issuers::new(Duration::from_secs(60), Duration::from_secs(60));
+
+        // Both pages are read once, then served from the cache
+        for jti in ["a", "b"] {
+            let revocation = issuers.revocation(&domain, jti).await.unwrap();
+            assert!(revocation.revoked);
+            assert_eq!(revocation.reason.as_deref(), Some("lost phone"));
+        }
+        assert!(!issuers.revocation(&domain, "c").await.unwrap().revoked);
+    }
+
+    #[tokio::test]
+    async fn test_forged_revocations() {
+        let server = MockServer::start().await;
+        let keypair = hesha_crypto::generate_keypair().unwrap();
+        let forger = hesha_crypto::generate_keypair().unwrap();
+        Mock::given(method("GET"))
+            .and(path("/.well-known/hesha/pubkey.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(issuer_info(&keypair, "k1")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(REVOCATIONS_PATH))
+            .respond_with(ResponseTemplate::new(200).set_body_json(revocations(&forger, &[], None)))
+            .mount(&server)
+            .await;
+        let domain = server.address().to_string();
+        let issuers = Issuers::new(Duration::from_secs(60), Duration::from_secs(60));
+
+        assert!(matches!(
+            issuers.revocation(&domain, "a").await,
+            Err(HeshaError::InvalidSignature)
+        ));
+    }
+}
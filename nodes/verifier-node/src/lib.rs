@@ -0,0 +1,42 @@
+//! Hesha Protocol verifier node.
+//!
+//! Verifies attestations and challenge responses for relying parties that
+//! would rather call a service than embed `hesha-core` in every backend.
+//! The `verifier-node` binary serves [`app`]; tests can serve it
+//! in-process.
+
+pub mod config;
+pub mod issuers;
+pub mod nonces;
+pub mod state;
+pub mod verify;
+
+pub use crate::config::Config;
+pub use crate::state::AppState;
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{get, post},
+    Json, Router,
+};
+use tower_http::trace::TraceLayer;
+
+/// The node's routes.
+pub fn app(state: AppState) -> Router {
+    let max_body_bytes = state.config.max_body_bytes;
+    Router::new()
+        .route("/verify", post(verify::verify))
+        .route("/verify/challenge-response", post(verify::verify_challenge))
+        .route("/health", get(health))
+        .fallback(hesha_node::not_found)
+        .layer(DefaultBodyLimit::max(max_body_bytes))
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
+
+/// `GET /health`.
+async fn health() -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+    }))
+}
@@ -0,0 +1,31 @@
+//! Hesha Protocol verifier node.
+
+use clap::Parser;
+use hesha_node::shutdown_signal;
+use verifier_node::{app, AppState, Config};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "verifier_node=debug,tower_http=info".into()),
+        )
+        .init();
+
+    let config = Config::parse();
+    let policy = config.policy()?;
+    let addr = config.bind_address;
+    let state = AppState::new(config, policy);
+
+    tracing::info!("Verifier node listening on {}", addr);
+    tracing::info!("  POST   /verify                    - Verify an attestation");
+    tracing::info!("  POST   /verify/challenge-response - Verify a challenge response");
+    tracing::info!("  GET    /health                    - Health check");
+
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    axum::serve(listener, app(state))
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+    Ok(())
+}
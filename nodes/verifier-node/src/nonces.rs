@@ -0,0 +1,49 @@
+//! Challenge nonces already answered.
+//!
+//! A nonce is remembered until its challenge can no longer be answered
+//! anyway, so the set stays as small as the challenges in flight. Nonces
+//! are remembered per node: relying parties running several verifier
+//! nodes must send each challenge's response to the same one, or track
+//! nonces themselves.
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Nonces answered, with when their challenge expires.
+#[derive(Debug, Default)]
+pub struct UsedNonces {
+    used: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl UsedNonces {
+    /// Record `nonce` as answered, returning false if it already was.
+    pub fn insert(&self, nonce: &str, expires_at: DateTime<Utc>) -> bool {
+        let mut used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Utc::now();
+        used.retain(|_, expiry| *expiry > now);
+        if used.contains_key(nonce) {
+            return false;
+        }
+        used.insert(nonce.to_string(), expires_at);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonces_are_single_use() {
+        let nonces = UsedNonces::default();
+        let later = Utc::now() + chrono::Duration::minutes(5);
+        assert!(nonces.insert("a", later));
+        assert!(!nonces.insert("a", later));
+        assert!(nonces.insert("b", later));
+
+        // Forgotten once their challenge has expired
+        assert!(nonces.insert("c", Utc::now() - chrono::Duration::seconds(1)));
+        assert!(nonces.insert("c", later));
+    }
+}
@@ -0,0 +1,32 @@
+//! Shared state of the verifier node.
+
+use crate::config::Config;
+use crate::issuers::Issuers;
+use crate::nonces::UsedNonces;
+use hesha_core::VerificationPolicy;
+use std::sync::Arc;
+
+/// State shared by every request.
+#[derive(Clone)]
+pub struct AppState {
+    /// Node configuration.
+    pub config: Arc<Config>,
+    /// Rules attestations must satisfy beyond a valid signature.
+    pub policy: Arc<VerificationPolicy>,
+    /// Cached issuer keys and revocation lists.
+    pub issuers: Arc<Issuers>,
+    /// Challenge nonces already answered.
+    pub nonces: Arc<UsedNonces>,
+}
+
+impl AppState {
+    /// State for `config`, verifying under `policy`.
+    pub fn new(config: Config, policy: VerificationPolicy) -> Self {
+        Self {
+            issuers: Arc::new(Issuers::new(config.key_ttl(), config.revocation_ttl())),
+            config: Arc::new(config),
+            policy: Arc::new(policy),
+            nonces: Arc::new(UsedNonces::default()),
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! Verification endpoints.
+//!
+//! Both endpoints answer `200 OK` with a [`VerificationReport`] whether or
+//! not the attestation verifies; `valid` says which, and the last check
+//! says why not. Only requests that can't be checked at all (unreadable
+//! bodies, an issuer that can't be reached) are refused with a problem
+//! document.
+
+use crate::state::AppState;
+use axum::{extract::rejection::JsonRejection, extract::State, Json};
+use chrono::Utc;
+use hesha_core::attestation::jwt::decode_key_id_unverified;
+use hesha_core::{parse_attestation, verify_challenge_response};
+use hesha_node::ApiError;
+use hesha_types::{
+    Attestation, AttestationSummary, ChallengeVerifyRequest, ErrorCode, HeshaError, VerificationCheck,
+    VerificationReport, VerifyRequest,
+};
+
+/// A report being filled in, check by check.
+struct Report(VerificationReport);
+
+impl Report {
+    fn new() -> Self {
+        Self(VerificationReport {
+            valid: false,
+            checked_at: Utc::now(),
+            attestation: None,
+            revocation: None,
+            checks: Vec::new(),
+        })
+    }
+
+    fn passed(&mut self, name: &str) {
+        self.0.checks.push(VerificationCheck {
+            name: name.to_string(),
+            passed: true,
+            detail: None,
+        });
+    }
+
+    fn failed(&mut self, name: &str, detail: impl ToString) {
+        self.0.checks.push(VerificationCheck {
+            name: name.to_string(),
+            passed: false,
+            detail: Some(detail.to_string()),
+        });
+    }
+
+    /// The report, valid if no check failed.
+    fn finish(mut self) -> VerificationReport {
+        self.0.valid = self.0.failure().is_none();
+        self.0
+    }
+}
+
+/// `POST /verify`: check an attestation's signature, the policy and the
+/// issuer's revocation list.
+pub async fn verify(
+    State(state): State<AppState>,
+    request: Result<Json<VerifyRequest>, JsonRejection>,
+) -> Result<Json<VerificationReport>, ApiError> {
+    let Json(request) = request?;
+    let mut report = Report::new();
+    check_attestation(&state, &request.attestation, &mut report).await?;
+    Ok(Json(report.finish()))
+}
+
+/// `POST /verify/challenge-response`: check an attestation as `/verify`
+/// does, then the wallet's signed answer to a challenge made with it.
+pub async fn verify_challenge(
+    State(state): State<AppState>,
+    request: Result<Json<ChallengeVerifyRequest>, JsonRejection>,
+) -> Result<Json<VerificationReport>, ApiError> {
+    let Json(request) = request?;
+    let mut report = Report::new();
+    let Some(attestation) = check_attestation(&state, &request.attestation, &mut report).await? else {
+        return Ok(Json(report.finish()));
+    };
+
+    let response = &request.response;
+    let max_age = state.config.challenge_max_age();
+    let context_matches = request
+        .service_context
+        .as_ref()
+        .map_or(true, |context| *context == response.challenge.service_context);
+    if !context_matches {
+        report.failed("challenge", "Challenge was issued for another service");
+    } else if let Err(e) = verify_challenge_response(response, &attestation, max_age) {
+        let detail = match e {
+            HeshaError::InvalidNonce => "Challenge expired or dated in the future".to_string(),
+            HeshaError::InvalidSignature => "Response is not signed with the attestation's user key".to_string(),
+            e => e.to_string(),
+        };
+        report.failed("challenge", detail);
+    } else if !state
+        .nonces
+        .insert(response.challenge.nonce.as_str(), response.challenge.timestamp + max_age)
+    {
+        report.failed("challenge", "Challenge was already answered");
+    } else {
+        report.passed("challenge");
+    }
+    Ok(Json(report.finish()))
+}
+
+/// Run the attestation's checks into `report`, returning the attestation if
+/// they all passed.
+async fn check_attestation(
+    state: &AppState,
+    jwt: &str,
+    report: &mut Report,
+) -> Result<Option<Attestation>, ApiError> {
+    let (attestation, key_id) = match parse_attestation(jwt).and_then(|a| Ok((a, decode_key_id_unverified(jwt)?))) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            report.failed("format", e);
+            return Ok(None);
+        }
+    };
+    report.passed("format");
+    let trust_domain = attestation.effective_trust_domain().to_string();
+    report.0.attestation = Some(AttestationSummary {
+        jti: attestation.jti.clone(),
+        issuer: attestation.iss.clone(),
+        trust_domain: trust_domain.clone(),
+        key_id: key_id.clone(),
+        proxy_number: attestation.proxy_number.as_str().to_string(),
+        user_pubkey: attestation.user_pubkey.to_base64(),
+        issued_at: attestation.iat,
+        expires_at: attestation.exp,
+    });
+
+    if let Err(e) = state.policy.validate(&attestation) {
+        report.failed("policy", e);
+        return Ok(None);
+    }
+    report.passed("policy");
+
+    let key = match state.issuers.key(&trust_domain, key_id.as_deref()).await {
+        Ok(key) => key,
+        Err(HeshaError::InvalidAttestation(detail)) => {
+            report.failed("issuer_key", detail);
+            return Ok(None);
+        }
+        Err(e) => return Err(unreachable_issuer(&trust_domain, e)),
+    };
+    report.passed("issuer_key");
+
+    let mut verified = state.policy.verify_with_key(jwt, &key);
+    if verified.is_err() {
+        // The issuer may have replaced the key since it was cached
+        let rechecked = state.issuers.recheck_key(&trust_domain, key_id.as_deref(), &key).await;
+        if let Ok(Some(key)) = rechecked {
+            verified = state.policy.verify_with_key(jwt, &key);
+        }
+    }
+    let attestation = match verified {
+        Ok(verified) => verified.attestation,
+        Err(e) => {
+            report.failed("signature", e);
+            return Ok(None);
+        }
+    };
+    report.passed("signature");
+
+    let revocation = state
+        .issuers
+        .revocation(&trust_domain, &attestation.jti)
+        .await
+        .map_err(|e| unreachable_issuer(&trust_domain, e))?;
+    let revoked = revocation.revoked;
+    report.0.revocation = Some(revocation);
+    if revoked {
+        report.failed("revocation", "Attestation was revoked by its issuer");
+        return Ok(None);
+    }
+    report.passed("revocation");
+    Ok(Some(attestation))
+}
+
+fn unreachable_issuer(trust_domain: &str, e: HeshaError) -> ApiError {
+    tracing::warn!("Could not check with issuer {}: {}", trust_domain, e);
+    ApiError::new(
+        ErrorCode::IssuerUnavailable,
+        format!("Could not fetch keys or revocations of {}: {}", trust_domain, e),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::issuers::tests::{issuer_info, revocations};
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+    use chrono::Duration;
+    use hesha_core::{create_attestation, sign_challenge, VerificationPolicy};
+    use hesha_types::{Challenge, KeyPair, Nonce, PhoneNumber, Problem, ProxyNumber};
+    use tower::util::ServiceExt;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Serve `keypair` and a revocation list holding `revoked` from `server`.
+    async fn publish(server: &MockServer, keypair: &KeyPair, revoked: &[&str]) {
+        Mock::given(method("GET"))
+            .and(path("/.well-known/hesha/pubkey.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(issuer_info(keypair, "k1")))
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/hesha/revocations.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(revocations(keypair, revoked, None)))
+            .mount(server)
+            .await;
+    }
+
+    fn attestation(issuer: &str, issuer_key: &KeyPair, user_key: &KeyPair) -> String {
+        create_attestation(
+            issuer,
+            &issuer_key.private,
+            &PhoneNumber::new("+1234567890").unwrap(),
+            &ProxyNumber::new("+23400123456789").unwrap(),
+            &user_key.public,
+        )
+        .unwrap()
+    }
+
+    async fn post(state: &AppState, uri: &str, body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+        let request = Request::post(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = crate::app(state.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    /// Name of the check the report failed at.
+    fn failure(report: &serde_json::Value) -> Option<String> {
+        let report: VerificationReport = serde_json::from_value(report.clone()).unwrap();
+        assert_eq!(report.valid, report.failure().is_none());
+        report.failure().map(|check| check.name.clone())
+    }
+
+    #[tokio::test]
+    async fn test_verify() {
+        let issuer_key = hesha_crypto::generate_keypair().unwrap();
+        let user_key = hesha_crypto::generate_keypair().unwrap();
+        let server = MockServer::start().await;
+        publish(&server, &issuer_key, &[]).await;
+        let domain = server.address().to_string();
+        let state = AppState::new(Config::default(), VerificationPolicy::default());
+
+        let jwt = attestation(&domain, &issuer_key, &user_key);
+        let (status, report) = post(&state, "/verify", serde_json::json!({ "attestation": jwt })).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(report["valid"], true);
+        assert_eq!(failure(&report), None);
+        assert_eq!(report["attestation"]["proxy_number"], "+23400123456789");
+        assert_eq!(report["attestation"]["user_pubkey"], user_key.public.to_base64());
+        assert_eq!(report["revocation"]["revoked"], false);
+
+        // Signed by someone else
+        let forger = hesha_crypto::generate_keypair().unwrap();
+        let jwt = attestation(&domain, &forger, &user_key);
+        let (_, report) = post(&state, "/verify", serde_json::json!({ "attestation": jwt })).await;
+        assert_eq!(failure(&report).as_deref(), Some("signature"));
+
+        // Not a JWT
+        let (_, report) = post(&state, "/verify", serde_json::json!({ "attestation": "nonsense" })).await;
+        assert_eq!(failure(&report).as_deref(), Some("format"));
+        assert!(report.get("attestation").is_none());
+
+        // Outside the policy
+        let policy = VerificationPolicy {
+            allowed_issuers: vec!["issuer.example.com".to_string()],
+            ..VerificationPolicy::default()
+        };
+        let strict = AppState::new(Config::default(), policy);
+        let jwt = attestation(&domain, &issuer_key, &user_key);
+        let (_, report) = post(&strict, "/verify", serde_json::json!({ "attestation": jwt })).await;
+        assert_eq!(failure(&report).as_deref(), Some("policy"));
+
+        // Malformed body
+        let (status, problem) = post(&state, "/verify", serde_json::json!({ "jwt": "x" })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let problem: Problem = serde_json::from_value(problem).unwrap();
+        assert_eq!(problem.code(), Some(ErrorCode::InvalidRequest));
+    }
+
+    #[tokio::test]
+    async fn test_revoked_and_unreachable_issuers() {
+        let issuer_key = hesha_crypto::generate_keypair().unwrap();
+        let user_key = hesha_crypto::generate_keypair().unwrap();
+        let state = AppState::new(Config::default(), VerificationPolicy::default());
+
+        // Nothing listening on this port
+        let jwt = attestation("127.0.0.1:1", &issuer_key, &user_key);
+        let (status, problem) = post(&state, "/verify", serde_json::json!({ "attestation": jwt })).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(problem["error"], "issuer_unavailable");
+
+        let server = MockServer::start().await;
+        let jwt = attestation(&server.address().to_string(), &issuer_key, &user_key);
+        let jti = parse_attestation(&jwt).unwrap().jti;
+        publish(&server, &issuer_key, &[&jti]).await;
+        let (status, report) = post(&state, "/verify", serde_json::json!({ "attestation": jwt })).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(failure(&report).as_deref(), Some("revocation"));
+        assert_eq!(report["revocation"]["revoked"], true);
+        assert_eq!(report["revocation"]["reason"], "lost phone");
+    }
+
+    #[tokio::test]
+    async fn test_challenge_response() {
+        let issuer_key = hesha_crypto::generate_keypair().unwrap();
+        let user_key = hesha_crypto::generate_keypair().unwrap();
+        let server = MockServer::start().await;
+        publish(&server, &issuer_key, &[]).await;
+        let jwt = attestation(&server.address().to_string(), &issuer_key, &user_key);
+        let jti = parse_attestation(&jwt).unwrap().jti;
+        let state = AppState::new(Config::default(), VerificationPolicy::default());
+        let challenge = |nonce: &str, age: i64| Challenge {
+            nonce: Nonce::new(nonce),
+            service_context: "app.example.com".to_string(),
+            timestamp: Utc::now() - Duration::seconds(age),
+        };
+        let body = |challenge: &Challenge, key: &KeyPair| {
+            serde_json::json!({
+                "attestation": jwt,
+                "response": sign_challenge(challenge, &jti, &key.private).unwrap(),
+                "service_context": "app.example.com",
+            })
+        };
+        let last_check = |report: &serde_json::Value| report["checks"].as_array().unwrap().last().unwrap().clone();
+
+        let answered = challenge("5f0c8a1e9b2d4c6f", 5);
+        let (status, report) = post(&state, "/verify/challenge-response", body(&answered, &user_key)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(failure(&report), None);
+        assert_eq!(last_check(&report)["name"], "challenge");
+
+        // Each challenge is answered once
+        let (_, report) = post(&state, "/verify/challenge-response", body(&answered, &user_key)).await;
+        assert_eq!(last_check(&report)["detail"], "Challenge was already answered");
+
+        // Too old, or not signed by the attestation's holder
+        let (_, report) = post(&state, "/verify/challenge-response", body(&challenge("a1", 600), &user_key)).await;
+        assert_eq!(failure(&report).as_deref(), Some("challenge"));
+        let other = hesha_crypto::generate_keypair().unwrap();
+        let (_, report) = post(&state, "/verify/challenge-response", body(&challenge("a2", 5), &other)).await;
+        assert_eq!(failure(&report).as_deref(), Some("challenge"));
+
+        // Issued for another service
+        let mut elsewhere = body(&challenge("a3", 5), &user_key);
+        elsewhere["service_context"] = "other.example.com".into();
+        let (_, report) = post(&state, "/verify/challenge-response", elsewhere).await;
+        assert_eq!(failure(&report).as_deref(), Some("challenge"));
+    }
+}